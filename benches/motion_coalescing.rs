@@ -0,0 +1,55 @@
+//! Compares event processing time with and without motion coalescing for
+//! one second of synthetic motion at 8 kHz, requires a running X server
+use std::mem;
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use x11::xlib;
+
+const MOTION_EVENTS: usize = 8000;
+const ROUNDS: u32 = 20;
+
+/// Queue synthetic MotionNotify events directly in Xlib's event queue
+fn queue_motion(event_loop: &EventLoop, window: &X11Window) {
+    unsafe {
+        let mut xmotion: xlib::XMotionEvent = mem::zeroed();
+        xmotion.type_ = xlib::MotionNotify;
+        xmotion.display = event_loop.display();
+        xmotion.window = window.window;
+        // XPutBackEvent prepends, so queue in reverse
+        for i in (0..MOTION_EVENTS).rev() {
+            xmotion.x = (i % 800) as i32;
+            xmotion.y = (i / 800) as i32;
+            let mut xevent = xlib::XEvent::from(xmotion);
+            xlib::XPutBackEvent(event_loop.display(), &mut xevent);
+        }
+    }
+}
+
+fn bench(coalescing: bool) -> (Duration, usize) {
+    let mut event_loop = EventLoop::new();
+    let window = X11Window::new(&event_loop, "bench", (800, 600));
+    event_loop.set_motion_coalescing(coalescing);
+
+    let mut events = Vec::new();
+    let mut elapsed = Duration::ZERO;
+    let mut delivered = 0;
+    for _ in 0..ROUNDS {
+        queue_motion(&event_loop, &window);
+        let start = Instant::now();
+        event_loop.poll_events(&mut events);
+        delivered += events.len();
+        elapsed += start.elapsed();
+    }
+    (elapsed / ROUNDS, delivered / ROUNDS as usize)
+}
+
+fn main() {
+    for &coalescing in &[false, true] {
+        let (elapsed, delivered) = bench(coalescing);
+        println!(
+            "coalescing {:5}: {:>8.3?} per {} motion events, {} delivered",
+            coalescing, elapsed, MOTION_EVENTS, delivered
+        );
+    }
+}
@@ -0,0 +1,65 @@
+//! Measures events per second through the translation path with a mix of
+//! key, button and motion events, requires a running X server
+use std::mem;
+use std::time::Instant;
+
+use daswin::prelude::*;
+use x11::xlib;
+
+const BATCH: usize = 10_000;
+const ROUNDS: u32 = 50;
+
+/// Queue a batch of synthetic input events directly in Xlib's event queue
+fn queue_input(event_loop: &EventLoop, window: &X11Window) {
+    unsafe {
+        for i in 0..BATCH {
+            let mut xevent: xlib::XEvent = mem::zeroed();
+            match i % 4 {
+                0 => {
+                    xevent.key.type_ = xlib::KeyPress;
+                    xevent.key.window = window.window;
+                    xevent.key.keycode = 38;
+                }
+                1 => {
+                    xevent.key.type_ = xlib::KeyRelease;
+                    xevent.key.window = window.window;
+                    xevent.key.keycode = 38;
+                }
+                2 => {
+                    xevent.button.type_ = xlib::ButtonPress;
+                    xevent.button.window = window.window;
+                    xevent.button.button = 1;
+                }
+                _ => {
+                    xevent.motion.type_ = xlib::MotionNotify;
+                    xevent.motion.window = window.window;
+                    xevent.motion.x = i as i32;
+                }
+            }
+            xevent.any.display = event_loop.display();
+            xlib::XPutBackEvent(event_loop.display(), &mut xevent);
+        }
+    }
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let window = X11Window::new(&event_loop, "bench", (800, 600));
+
+    // The same buffer is reused every round
+    let mut events = Vec::with_capacity(BATCH);
+    let mut translated = 0;
+    let mut elapsed = 0.0;
+    for _ in 0..ROUNDS {
+        queue_input(&event_loop, &window);
+        let start = Instant::now();
+        event_loop.poll_events(&mut events);
+        elapsed += start.elapsed().as_secs_f64();
+        translated += events.len();
+    }
+    println!(
+        "{:.0} events/s through translation ({} events)",
+        translated as f64 / elapsed,
+        translated
+    );
+}
@@ -0,0 +1,35 @@
+//! A window locked to 16:9 while resizing, anchored at its bottom right
+//! corner, logging the sizes the window manager settles on
+use daswin::prelude::*;
+use daswin::Gravity;
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "16:9 - resize me", (640, 360));
+    window.set_min_inner_size(Some((160, 90).into()));
+    window.set_aspect_ratio(Some((16, 9)));
+    window.set_gravity(Gravity::SouthEast);
+    window.show();
+
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::Resized { size, .. } => {
+                    println!(
+                        "{}x{} ratio {:.3} (16:9 is {:.3})",
+                        size.width,
+                        size.height,
+                        size.width as f64 / size.height as f64,
+                        16.0 / 9.0
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,115 @@
+//! Drives a window from async code without an executor crate: datagrams
+//! received on a UDP socket are sent to the loop over a bounded channel
+//! while a burst of them is sent, so the receiver waits whenever the loop
+//! falls behind. The title shows what arrived, the terminal how long the
+//! receiver was held back
+use std::future::Future;
+use std::net::UdpSocket;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use daswin::AsyncChannelProxy;
+
+// Little room, so the burst below fills the channel
+const CAPACITY: usize = 4;
+
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+// The smallest executor there is: poll, and park the thread until woken
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Arc::new(Unpark(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+fn receive(socket: UdpSocket, proxy: AsyncChannelProxy<String>) {
+    let mut buf = [0; 512];
+    while let Ok(len) = socket.recv(&mut buf) {
+        let datagram = String::from_utf8_lossy(&buf[..len]).into_owned();
+        let start = Instant::now();
+        // Waits while the channel is full, the socket buffers meanwhile
+        if block_on(proxy.send(datagram)).is_err() {
+            return;
+        }
+        let waited = start.elapsed();
+        if waited > Duration::from_millis(1) {
+            println!("receiver held back for {:?}", waited);
+        }
+    }
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "async events", (480, 240));
+    window.show();
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+    let (proxy, network) = event_loop.create_async_channel::<String>(CAPACITY);
+    thread::spawn(move || receive(socket, proxy));
+    thread::spawn(move || {
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for burst in 0.. {
+            for packet in 0..16 {
+                let message = format!("burst {} packet {}", burst, packet);
+                let _ = sender.send_to(message.as_bytes(), addr);
+            }
+            thread::sleep(Duration::from_secs(2));
+        }
+    });
+
+    let mut stream = event_loop.event_stream().unwrap();
+    block_on(async {
+        // A future dropped before completing, e.g. by losing a race in a
+        // select, takes no event with it
+        let mut cx = Context::from_waker(noop_waker());
+        if let Poll::Ready(Some(event)) = Pin::new(&mut stream.next_event()).poll(&mut cx) {
+            println!("first event {:?}", event);
+        }
+
+        let mut received = 0;
+        while let Some(event) = stream.next_event().await {
+            match event {
+                Event::Channel(channel) if channel == network => {
+                    let datagrams = stream
+                        .event_loop()
+                        .drain_channel::<String>(channel)
+                        .unwrap();
+                    received += datagrams.len();
+                    if let Some(last) = datagrams.last() {
+                        window.set_title(&format!("{} datagrams, last: {}", received, last));
+                    }
+                    // A slow consumer, to show the receiver waiting
+                    thread::sleep(Duration::from_millis(50));
+                }
+                Event::CloseRequested { .. } => stream.event_loop().exit(0),
+                _ => {}
+            }
+        }
+    });
+}
+
+// Nothing to wake for the single poll above, the stream polls again
+fn noop_waker() -> &'static std::task::Waker {
+    struct Noop;
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+    Box::leak(Box::new(Arc::new(Noop).into()))
+}
@@ -0,0 +1,25 @@
+//! Connects with the backend named on the command line, or by
+//! DASWIN_BACKEND, and reports which one was used or why none worked.
+//! Try `DISPLAY= cargo run --example backend`
+use daswin::prelude::*;
+use daswin::Backend;
+
+fn main() {
+    let backend = match std::env::args().nth(1) {
+        Some(name) => match Backend::from_name(&name) {
+            Some(backend) => backend,
+            None => {
+                eprintln!("usage: backend [auto|x11|wayland]");
+                return;
+            }
+        },
+        None => Backend::Auto,
+    };
+    match EventLoopBuilder::new().with_backend(backend).build() {
+        Ok(event_loop) => println!("connected with {}", event_loop.backend()),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+}
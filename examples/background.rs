@@ -0,0 +1,50 @@
+//! Starts without a window, as an application waiting in the tray
+//! would. A window opens two seconds in, and two seconds after it is
+//! closed another one opens. Closing the second one exits
+use std::thread;
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+
+const WINDOWS: u32 = 2;
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    event_loop.catch_exit_signals();
+    event_loop.set_exit_on_last_window_closed(false);
+
+    let mut window: Option<X11Window> = None;
+    let mut opened = 0;
+    let mut open_at = Some(Instant::now() + Duration::from_secs(2));
+    println!("running without a window");
+
+    let mut events = Vec::new();
+    loop {
+        if let PumpStatus::Exit { .. } = event_loop.pump_events(&mut events) {
+            return;
+        }
+        for event in &events {
+            if let Event::CloseRequested { .. } = event {
+                // Dropping the window destroys it
+                if let Some(closed) = window.take() {
+                    println!("{:?} closed, running without a window", closed.id());
+                }
+                if opened == WINDOWS {
+                    return;
+                }
+                open_at = Some(Instant::now() + Duration::from_secs(2));
+            }
+        }
+
+        if open_at.is_some_and(|at| Instant::now() >= at) {
+            open_at = None;
+            opened += 1;
+            let title = format!("window {} of {}", opened, WINDOWS);
+            let mut new_window = X11Window::new(&event_loop, &title, (400, 300));
+            new_window.show();
+            window = Some(new_window);
+            println!("opened {}", title);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
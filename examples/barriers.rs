@@ -0,0 +1,50 @@
+//! Confines the pointer to the window with XFixes pointer barriers,
+//! falling back to a pointer grab on servers without them. Press c to
+//! toggle confinement
+use std::time::Duration;
+
+use daswin::prelude::*;
+use x11::{keysym, xlib};
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "barriers - press c", (640, 480));
+    window.show();
+
+    let mut confined = false;
+    let mut grabbed = false;
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::KeyboardInput {
+                    keysym,
+                    pressed: true,
+                    ..
+                } if keysym == keysym::XK_c as xlib::KeySym => {
+                    confined = !confined;
+                    if grabbed {
+                        let _ = window.set_pointer_grab(false);
+                        grabbed = false;
+                        continue;
+                    }
+                    match window.confine_cursor_with_barriers(confined) {
+                        Ok(()) => println!("barriers {}", if confined { "on" } else { "off" }),
+                        Err(Error::Unsupported(what)) => {
+                            println!("{} missing, grabbing the pointer instead", what);
+                            match window.set_pointer_grab(true) {
+                                Ok(()) => grabbed = true,
+                                Err(err) => println!("pointer grab failed: {}", err),
+                            }
+                        }
+                        Err(err) => println!("confinement failed: {}", err),
+                    }
+                }
+                _ => {}
+            }
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
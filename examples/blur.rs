@@ -0,0 +1,89 @@
+//! The triangle blurred by a compute shader, B toggles the blur. The
+//! frame is rendered into the harness's compute stage while blurring and
+//! straight to the surface otherwise
+mod common;
+
+use std::borrow::Cow;
+
+use common::{run_example, Step};
+use daswin::prelude::*;
+use daswin::render::helpers::ComputeStage;
+use x11::{keysym, xlib};
+
+const WORKGROUP_SIZE: (u32, u32) = (8, 8);
+
+// A box blur, invocations past the edges of odd sizes do nothing
+const BLUR: &str = r#"
+const RADIUS: i32 = 6;
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = vec2<i32>(textureDimensions(scene));
+    let center = vec2<i32>(id.xy);
+    if center.x >= size.x || center.y >= size.y {
+        return;
+    }
+    var sum = vec4<f32>(0.0);
+    for (var y = -RADIUS; y <= RADIUS; y = y + 1) {
+        for (var x = -RADIUS; x <= RADIUS; x = x + 1) {
+            let at = clamp(center + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+            sum = sum + textureLoad(scene, at, 0);
+        }
+    }
+    let taps = f32((2 * RADIUS + 1) * (2 * RADIUS + 1));
+    textureStore(output, center, sum / taps);
+}
+"#;
+
+fn blur_pipeline(device: &wgpu::Device, stage: &ComputeStage) -> wgpu::ComputePipeline {
+    let source = format!("{}{}", stage.declarations(), BLUR);
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("blur"),
+        source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[stage.bind_group_layout()],
+        push_constant_ranges: &[],
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("blur"),
+        layout: Some(&layout),
+        module: &shader,
+        entry_point: "main",
+    })
+}
+
+fn main() {
+    let mut blur = false;
+    // Built for the stage's format when blurring the first time
+    let mut pipeline = None;
+    run_example("blur", move |ctx, step| match step {
+        Step::Event(Event::KeyboardInput {
+            keysym,
+            pressed: true,
+            repeat: false,
+            ..
+        }) if keysym == keysym::XK_b as xlib::KeySym => {
+            blur = !blur;
+            // Dropping the stage frees its textures
+            ctx.set_compute_stage(blur.then_some(wgpu::TextureFormat::Rgba8Unorm));
+            println!("blur {}", if blur { "on" } else { "off" });
+        }
+        Step::Event(_) => {}
+        Step::Draw(_) => {
+            let device = ctx.device();
+            let frame = ctx.frame().unwrap();
+            let triangle = frame.pipeline.clone();
+            {
+                let mut pass = frame.pass(wgpu::Color::GREEN);
+                pass.set_pipeline(&triangle);
+                pass.draw(0..3, 0..1);
+            }
+            if let Some(stage) = frame.compute_stage() {
+                let blur = pipeline.get_or_insert_with(|| blur_pipeline(device, stage));
+                frame.compute_pass(blur, None, WORKGROUP_SIZE);
+            }
+        }
+    });
+}
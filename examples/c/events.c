@@ -0,0 +1,71 @@
+/* Opens a window through the C interface and prints its events until it
+ * is closed, checking the event layout against the Rust side.
+ *
+ *   cargo build --features capi
+ *   cc examples/c/events.c -Iinclude -Ltarget/debug -ldaswin -o events
+ *   LD_LIBRARY_PATH=target/debug ./events
+ */
+#include <assert.h>
+#include <stddef.h>
+#include <stdio.h>
+
+#include "daswin.h"
+
+/* The layout rustc picks for the repr(C) types on x86_64 and aarch64 */
+static_assert(sizeof(DaswinEventTag) == 4, "tag size");
+static_assert(offsetof(DaswinEvent, data) == 8, "data offset");
+static_assert(sizeof(DaswinEvent) == 72, "event size");
+static_assert(offsetof(DaswinKeyboardInput, keysym) == 16, "keysym offset");
+static_assert(offsetof(DaswinTablet, pressure) == 32, "pressure offset");
+
+int main(void) {
+    DaswinWindow *window = daswin_window_new("daswin from C", 640, 480);
+    if (!window) {
+        fprintf(stderr, "failed to create window\n");
+        return 1;
+    }
+    assert(daswin_window_show(window) == DASWIN_OK);
+    assert(daswin_window_show(NULL) == DASWIN_NULL_ARGUMENT);
+
+    void *display;
+    unsigned long xid;
+    assert(daswin_window_raw_xlib(window, &display, &xid) == DASWIN_OK);
+    printf("display %p window 0x%lx\n", display, xid);
+
+    bool running = true;
+    while (running) {
+        DaswinEvent event;
+        while (daswin_poll_event(window, &event)) {
+            switch (event.tag) {
+            case DASWIN_CLOSE_REQUESTED:
+                assert((event.data.close_requested.window & 0xffffffff) == xid);
+                running = false;
+                break;
+            case DASWIN_RESIZED:
+                printf("resized to %ux%u\n", event.data.resized.width, event.data.resized.height);
+                break;
+            case DASWIN_KEYBOARD_INPUT:
+                printf("key %u %s%s\n", event.data.keyboard_input.keycode,
+                       event.data.keyboard_input.pressed ? "pressed" : "released",
+                       event.data.keyboard_input.synthetic ? " on focus change" : "");
+                break;
+            case DASWIN_TEXT: {
+                char text[64];
+                if (daswin_event_text(window, &event, text, sizeof text) >= 0)
+                    printf("typed \"%s\"\n", text);
+                break;
+            }
+            case DASWIN_MOUSE_INPUT:
+                printf("button %u at %.0f,%.0f\n", event.data.mouse_input.button,
+                       event.data.mouse_input.x, event.data.mouse_input.y);
+                break;
+            default:
+                break;
+            }
+        }
+        assert(daswin_wait_events(window, 1000) == DASWIN_OK);
+    }
+
+    daswin_window_destroy(window);
+    return 0;
+}
@@ -0,0 +1,280 @@
+//! Requests frames which can be copied from and premultiplied alpha,
+//! reporting what the surface supports when it cannot. C copies the
+//! next frame into a buffer and prints the pixel at the center, from the
+//! frame itself or the intermediate texture used in its place. K draws
+//! a 3840x2160 frame offscreen and reads it back the same way.
+//!
+//! Readbacks go through GpuContext::map_async_callback, so the loop keeps
+//! handling input while the GPU copies. With `--check` a 4K readback is
+//! started right away and the example exits with 1 when a poll took
+//! longer than 20ms meanwhile or the readback never arrived, e.g.
+//! `cargo run --example capture -- --check`
+use std::borrow::Cow;
+use std::env;
+use std::num::NonZeroU32;
+use std::process;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use daswin::render::SurfaceOptions;
+use daswin::ReadbackId;
+use x11::{keysym, xlib};
+
+// Longest poll allowed by --check while the 4K readback is pending
+const MAX_POLL: Duration = Duration::from_millis(20);
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "capture", (640, 480));
+    window.on_first_present(|window| window.show());
+    let (gpu, mut window) = GpuContext::with_window(window);
+
+    let mut options = SurfaceOptions {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        alpha_mode: Some(wgpu::CompositeAlphaMode::PreMultiplied),
+        ..Default::default()
+    };
+    if let Err(err) = window.set_options(&gpu, options) {
+        println!("{}, keeping the default alpha mode", err);
+        options.alpha_mode = None;
+        window.set_options(&gpu, options).unwrap();
+    }
+    println!(
+        "rendering to {}",
+        if window.surface().uses_intermediate() {
+            "an intermediate texture"
+        } else {
+            "the frames"
+        }
+    );
+
+    let shader = gpu
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../src/shader.wgsl"))),
+        });
+    let mut pipeline = None;
+
+    let check = env::args().any(|arg| arg == "--check");
+    let mut capture = false;
+    let mut capture_4k = check;
+    // The 4K readback pending, when it started and the longest poll since
+    let mut pending_4k: Option<(ReadbackId, Instant, Duration)> = None;
+    let mut events = Vec::new();
+    'running: loop {
+        let poll_start = Instant::now();
+        event_loop.poll_events(&mut events);
+        let poll_time = poll_start.elapsed();
+        for event in &events {
+            window.handle_event(&gpu, event);
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::KeyboardInput {
+                    keysym, pressed, ..
+                } if pressed && keysym == keysym::XK_c as xlib::KeySym => capture = true,
+                Event::KeyboardInput {
+                    keysym, pressed, ..
+                } if pressed && keysym == keysym::XK_k as xlib::KeySym => {
+                    capture_4k = pending_4k.is_none()
+                }
+                Event::GpuReadbackReady(id) => match pending_4k {
+                    Some((pending, started, longest)) if pending == id => {
+                        println!(
+                            "4K readback took {:?}, the longest poll meanwhile {:?}",
+                            started.elapsed(),
+                            longest
+                        );
+                        pending_4k = None;
+                        if check {
+                            if longest > MAX_POLL {
+                                println!("FAILED  polls stayed short during the readback");
+                                process::exit(1);
+                            }
+                            println!("ok      polls stayed short during the readback");
+                            break 'running;
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        if let Some((_, started, longest)) = &mut pending_4k {
+            // The poll delivering it ran the callback
+            if !events
+                .iter()
+                .any(|event| matches!(event, Event::GpuReadbackReady(_)))
+            {
+                *longest = (*longest).max(poll_time);
+            }
+            if check && started.elapsed() > Duration::from_secs(10) {
+                println!("FAILED  the readback arrived");
+                process::exit(1);
+            }
+        }
+
+        let surface = window.surface_mut();
+        let frame = match surface.acquire(&gpu) {
+            Some(frame) => frame,
+            None => continue,
+        };
+        let format = surface.format();
+        // The surface format can change when it is reconfigured
+        if pipeline.as_ref().map(|(_, f)| *f) != Some(format) {
+            pipeline = Some((create_pipeline(&gpu.device, &shader, format), format));
+        }
+        let (pipeline, _) = pipeline.as_ref().unwrap();
+
+        let texture = surface.texture(&frame);
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        draw(&mut encoder, &view, pipeline);
+
+        let (width, height) = (surface.config.width, surface.config.height);
+        let readback = capture.then(|| copy_to_buffer(&gpu, &mut encoder, texture, width, height));
+        capture = false;
+
+        let offscreen = capture_4k.then(|| {
+            let texture = gpu.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("4K capture"),
+                size: wgpu::Extent3d {
+                    width: 3840,
+                    height: 2160,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            draw(&mut encoder, &view, pipeline);
+            copy_to_buffer(&gpu, &mut encoder, &texture, 3840, 2160)
+        });
+        capture_4k = false;
+
+        gpu.queue.submit(Some(encoder.finish()));
+        surface.resolve(&gpu, &frame);
+        window.present(frame);
+
+        let id = Some(window.window().id());
+        if let Some((buffer, stride)) = readback {
+            let center = (height / 2 * stride + width / 2 * 4) as usize;
+            gpu.map_async_callback(buffer, .., id, move |data| match data {
+                Ok(data) => println!(
+                    "center pixel {:?} of {}x{} in {:?}",
+                    &data[center..center + 4],
+                    width,
+                    height,
+                    format
+                ),
+                Err(err) => println!("failed to read the frame back: {}", err),
+            });
+        }
+        if let Some((buffer, _)) = offscreen {
+            let bytes = buffer.size();
+            let readback = gpu.map_async_callback(buffer, .., id, move |data| {
+                if let Err(err) = data {
+                    println!("failed to read the 4K frame back: {}", err);
+                }
+            });
+            println!("reading {} MiB back", bytes >> 20);
+            pending_4k = Some((readback, Instant::now(), Duration::ZERO));
+        }
+    }
+
+    window.destroy(&gpu);
+}
+
+fn draw(
+    encoder: &mut wgpu::CommandEncoder,
+    view: &wgpu::TextureView,
+    pipeline: &wgpu::RenderPipeline,
+) {
+    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                // Half transparent, premultiplied
+                load: wgpu::LoadOp::Clear(wgpu::Color {
+                    r: 0.0,
+                    g: 0.25,
+                    b: 0.0,
+                    a: 0.5,
+                }),
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: None,
+    });
+    rpass.set_pipeline(pipeline);
+    rpass.draw(0..3, 0..1);
+}
+
+/// Copy all of `texture` into a new buffer, returned with the bytes per
+/// row, which buffer copies align to 256
+fn copy_to_buffer(
+    gpu: &GpuContext,
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    width: u32,
+    height: u32,
+) -> (Arc<wgpu::Buffer>, u32) {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let stride = (width * 4).div_ceil(align) * align;
+    let buffer = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: stride as u64 * height as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(stride),
+                rows_per_image: None,
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    (Arc::new(buffer), stride)
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
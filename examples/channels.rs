@@ -0,0 +1,100 @@
+//! Three simulated subsystems, each sending its own message type to the
+//! event loop over a channel of its own. The title shows the latest
+//! state, the terminal every message
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use daswin::prelude::*;
+use daswin::ChannelProxy;
+
+enum Network {
+    Connected { peer: String },
+    Received { bytes: usize },
+}
+
+// Peak level of the last audio buffer, from 0 to 1
+struct AudioLevel(f32);
+
+struct FileChanged(PathBuf);
+
+fn network(proxy: ChannelProxy<Network>) {
+    let peer = String::from("203.0.113.7:4000");
+    if proxy.send(Network::Connected { peer }).is_err() {
+        return;
+    }
+    for bytes in (1..).map(|packet| packet * 512 % 1400) {
+        thread::sleep(Duration::from_millis(700));
+        if proxy.send(Network::Received { bytes }).is_err() {
+            return;
+        }
+    }
+}
+
+fn audio(proxy: ChannelProxy<AudioLevel>) {
+    for buffer in 0.. {
+        thread::sleep(Duration::from_millis(100));
+        let level = ((buffer as f32 * 0.3).sin() * 0.5 + 0.5).abs();
+        // Fails once the loop is gone, ending the thread
+        if proxy.send(AudioLevel(level)).is_err() {
+            return;
+        }
+    }
+}
+
+fn file_watcher(proxy: ChannelProxy<FileChanged>) {
+    for save in 0.. {
+        thread::sleep(Duration::from_secs(2));
+        let path = PathBuf::from(format!("assets/level{}.ron", save % 3));
+        if proxy.send(FileChanged(path)).is_err() {
+            return;
+        }
+    }
+}
+
+fn main() {
+    let event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "channels", (480, 240));
+    window.show();
+
+    let (net_proxy, net) = event_loop.create_channel::<Network>();
+    let (audio_proxy, audio_channel) = event_loop.create_channel::<AudioLevel>();
+    let (file_proxy, files) = event_loop.create_channel::<FileChanged>();
+    thread::spawn(move || network(net_proxy));
+    thread::spawn(move || audio(audio_proxy));
+    thread::spawn(move || file_watcher(file_proxy));
+
+    let mut received = 0;
+    let mut level = 0.0;
+    let code = event_loop.run(move |event_loop, event| match event {
+        Event::Channel(channel) if channel == net => {
+            for message in event_loop.drain_channel::<Network>(channel).unwrap() {
+                match message {
+                    Network::Connected { peer } => println!("network: connected to {}", peer),
+                    Network::Received { bytes } => received += bytes,
+                }
+            }
+        }
+        Event::Channel(channel) if channel == audio_channel => {
+            // Only the latest level matters
+            if let Some(AudioLevel(latest)) = event_loop.drain_channel(channel).unwrap().pop() {
+                level = latest;
+            }
+        }
+        Event::Channel(channel) if channel == files => {
+            for FileChanged(path) in event_loop.drain_channel(channel).unwrap() {
+                println!("reloading {}", path.display());
+            }
+            // Asking for the wrong type is an error, not a panic
+            let wrong = event_loop.drain_channel::<AudioLevel>(channel);
+            assert!(wrong.is_err());
+        }
+        Event::AboutToWait => {
+            let meter = "#".repeat((level * 10.0) as usize);
+            window.set_title(&format!("{} bytes received, audio {:<10}", received, meter));
+        }
+        Event::CloseRequested { .. } => event_loop.exit(0),
+        _ => {}
+    });
+    std::process::exit(code);
+}
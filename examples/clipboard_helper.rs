@@ -0,0 +1,91 @@
+//! Checks a HelperWindow keeps serving the clipboard after the last
+//! visible window closed: one helper owns CLIPBOARD, the window is
+//! dropped, and a second helper reads the clipboard back through the
+//! server. Exits with 1 when the text differs, e.g. `xvfb-run cargo run
+//! --example clipboard_helper`
+use std::os::raw::c_long;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use daswin::HelperWindow;
+use x11::xlib;
+
+const TEXT: &str = "copied by daswin, served without a window";
+
+fn check(ok: bool, what: &str) {
+    if ok {
+        println!("ok      {}", what);
+    } else {
+        println!("FAILED  {}", what);
+        process::exit(1);
+    }
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "clipboard helper", (320, 200));
+    window.show();
+
+    let owner = HelperWindow::new(&event_loop);
+    let clipboard = owner.atom("CLIPBOARD");
+    let utf8 = owner.atom("UTF8_STRING");
+    let targets = owner.atom("TARGETS");
+    check(owner.own_selection(clipboard), "the helper owns CLIPBOARD");
+
+    let mut events = Vec::new();
+    drop(window);
+    event_loop.poll_events(&mut events);
+
+    let reader = HelperWindow::new(&event_loop);
+    reader.convert_selection(clipboard, utf8);
+    let deadline = Instant::now() + Duration::from_secs(2);
+    let mut read = None;
+    while read.is_none() && Instant::now() < deadline {
+        event_loop.poll_events(&mut events);
+        for xevent in owner.take_events() {
+            match xevent.get_type() {
+                xlib::SelectionRequest => {
+                    let request = unsafe { xevent.selection_request };
+                    let data = if request.target == utf8 {
+                        Some((utf8, 8, TEXT.as_bytes().to_vec()))
+                    } else if request.target == targets {
+                        // ATOM lists are format 32, an array of longs
+                        let atoms = [targets, utf8]
+                            .iter()
+                            .flat_map(|&atom| (atom as c_long).to_ne_bytes())
+                            .collect();
+                        Some((xlib::XA_ATOM, 32, atoms))
+                    } else {
+                        None
+                    };
+                    let data = data
+                        .as_ref()
+                        .map(|(type_, format, bytes)| (*type_, *format, bytes.as_slice()));
+                    owner.send_selection(&request, data);
+                }
+                xlib::SelectionClear => check(false, "no other client took CLIPBOARD"),
+                _ => {}
+            }
+        }
+        for xevent in reader.take_events() {
+            if xevent.get_type() == xlib::SelectionNotify {
+                let notify = unsafe { xevent.selection };
+                read = Some(reader.read_selection(&notify));
+            }
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let read = read.flatten();
+    println!(
+        "read {:?}",
+        read.as_ref()
+            .map(|(_, bytes)| String::from_utf8_lossy(bytes))
+    );
+    check(
+        read.is_some_and(|(type_, bytes)| type_ == utf8 && bytes == TEXT.as_bytes()),
+        "the clipboard is read back after the window closed",
+    );
+}
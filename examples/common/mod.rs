@@ -0,0 +1,621 @@
+//! Harness shared by the examples: opens a window with a GpuContext,
+//! builds the default triangle pipeline, keeps frame statistics and
+//! handles close requests, so an example only holds its own code.
+//!
+//! Examples use it with `mod common;` and hand run_example a callback,
+//! which is called with every event and once per window per frame
+// Each example uses a different part of the harness
+#![allow(dead_code)]
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use daswin::render::helpers::{ComputeStage, DamageTarget};
+use daswin::{EventTime, Rect, ServerClock};
+
+// Latency samples kept for percentiles
+const LATENCY_SAMPLES: usize = 1000;
+
+/// What the callback is called for
+#[derive(Debug, Clone, Copy)]
+pub enum Step {
+    /// An event from the loop, after the harness updated the surfaces
+    Event(Event),
+    /// Record the frame of the window, see ExampleCtx::frame
+    Draw(WindowId),
+}
+
+/// Frame timing of the harness, updated once per loop iteration.
+///
+/// Input latency is measured once an example marks input, see
+/// mark_input
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    /// Frames drawn since the start
+    pub frames: u64,
+    /// Duration of the last frame
+    pub frame_time: Duration,
+    /// Frames per second over the last full second
+    pub fps: u32,
+    /// Time since the start
+    pub elapsed: Duration,
+    start: Instant,
+    last: Instant,
+    second: Instant,
+    frames_this_second: u32,
+
+    latency_started: bool,
+    clock: Option<ServerClock>,
+    // The newest mark not drawn yet
+    input_mark: Option<EventTime>,
+    // Marks of the frames presented to the first window which did not
+    // reach the screen yet, oldest first
+    in_flight: VecDeque<Option<EventTime>>,
+    latencies: VecDeque<Duration>,
+}
+
+impl FrameStats {
+    fn new() -> FrameStats {
+        let now = Instant::now();
+        FrameStats {
+            frames: 0,
+            frame_time: Duration::ZERO,
+            fps: 0,
+            elapsed: Duration::ZERO,
+            start: now,
+            last: now,
+            second: now,
+            frames_this_second: 0,
+            latency_started: false,
+            clock: None,
+            input_mark: None,
+            in_flight: VecDeque::new(),
+            latencies: VecDeque::new(),
+        }
+    }
+
+    /// Measure the latency from the input with the server time to the
+    /// first frame of the first window showing it. Mark the input when
+    /// its event is handled, usually with EventLoop::last_input_time.
+    ///
+    /// Frames are matched to Present feedback in order, which assumes
+    /// a present mode never replacing queued frames, like the default
+    /// Fifo
+    pub fn mark_input(&mut self, time: EventTime) {
+        self.input_mark = Some(time);
+    }
+
+    /// Input to screen latencies of the last frames, oldest first
+    pub fn latency_samples(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.latencies.iter().copied()
+    }
+
+    /// The latency `percentile` percent of the samples are below, None
+    /// without samples
+    pub fn latency_percentile(&self, percentile: f64) -> Option<Duration> {
+        let mut samples: Vec<Duration> = self.latency_samples().collect();
+        samples.sort_unstable();
+        let last = samples.len().checked_sub(1)?;
+        let index = (last as f64 * percentile / 100.0).round() as usize;
+        Some(samples[index.min(last)])
+    }
+
+    /// How far off the samples may be, None before the first mark
+    pub fn latency_uncertainty(&self) -> Option<Duration> {
+        self.clock.map(|clock| clock.uncertainty())
+    }
+
+    /// Set up measuring for the first window once input is marked
+    fn start_latency(&mut self, event_loop: &EventLoop, window: &mut X11Window) {
+        if self.latency_started || self.input_mark.is_none() {
+            return;
+        }
+        self.latency_started = true;
+        match window.enable_present_feedback() {
+            Ok(()) => self.clock = Some(event_loop.calibrate_clock()),
+            Err(err) => println!("no latency measurements: {}", err),
+        }
+    }
+
+    /// Count a frame presented to the first window
+    fn presented(&mut self) {
+        if self.clock.is_none() {
+            return;
+        }
+        // Drivers presenting without Present never report frames
+        if self.in_flight.len() == LATENCY_SAMPLES {
+            self.in_flight.pop_front();
+        }
+        self.in_flight.push_back(self.input_mark.take());
+    }
+
+    /// A frame of the first window reached the screen
+    fn reached_screen(&mut self, ust: Duration) {
+        let (clock, mark) = match (self.clock, self.in_flight.pop_front()) {
+            (Some(clock), Some(Some(mark))) => (clock, mark),
+            _ => return,
+        };
+        let latency = clock
+            .ust_instant(ust)
+            .saturating_duration_since(clock.instant(mark));
+        if self.latencies.len() == LATENCY_SAMPLES {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency);
+    }
+
+    /// Count a frame, returns true once a second when fps was updated
+    fn tick(&mut self) -> bool {
+        let now = Instant::now();
+        self.frames += 1;
+        self.frame_time = now - self.last;
+        self.elapsed = now - self.start;
+        self.last = now;
+        self.frames_this_second += 1;
+        if now - self.second < Duration::from_secs(1) {
+            return false;
+        }
+        self.fps = self.frames_this_second;
+        self.frames_this_second = 0;
+        self.second = now;
+        true
+    }
+}
+
+/// The frame being drawn, submitted and presented by the harness after
+/// the Draw step
+pub struct Frame {
+    pub view: wgpu::TextureView,
+    pub encoder: wgpu::CommandEncoder,
+    pub size: PhysicalSize<u32>,
+    pub format: wgpu::TextureFormat,
+    /// The default pipeline for the frame's format, see
+    /// ExampleCtx::pipeline
+    pub pipeline: Rc<wgpu::RenderPipeline>,
+    // The stage `view` belongs to, resolved onto the surface after the
+    // Draw step
+    compute: Option<Rc<ComputeStage>>,
+    // The target `view` belongs to when tracking damage
+    damage: Option<Rc<DamageTarget>>,
+}
+
+impl Frame {
+    /// Begin a render pass clearing the frame, or only its damaged
+    /// regions when tracking damage, see Frame::set_damage
+    pub fn pass(&mut self, clear: wgpu::Color) -> wgpu::RenderPass<'_> {
+        if let Some(target) = &self.damage {
+            return target.pass(&mut self.encoder, clear);
+        }
+        self.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        })
+    }
+
+    /// Redraw only `rects` of the frame, the rest keeps the previous one.
+    /// Does nothing unless damage tracking is on, see
+    /// ExampleCtx::set_damage_tracking
+    pub fn set_damage(&mut self, rects: &[Rect]) {
+        if let Some(target) = &self.damage {
+            target.set_damage(rects);
+        }
+    }
+
+    /// Whether presents are partial, see DamageTarget::supports_damage
+    pub fn supports_damage(&self) -> bool {
+        self.damage
+            .as_ref()
+            .is_some_and(|target| target.supports_damage())
+    }
+
+    /// The compute stage the frame is rendered into, see
+    /// ExampleCtx::set_compute_stage
+    pub fn compute_stage(&self) -> Option<&ComputeStage> {
+        self.compute.as_deref()
+    }
+
+    /// Run `pipeline` over the rendered frame, see ComputeStage::dispatch.
+    /// Returns false without a compute stage
+    pub fn compute_pass(
+        &mut self,
+        pipeline: &wgpu::ComputePipeline,
+        bind_group: Option<&wgpu::BindGroup>,
+        workgroup_size: (u32, u32),
+    ) -> bool {
+        match &self.compute {
+            Some(stage) => {
+                stage.dispatch(&mut self.encoder, pipeline, bind_group, workgroup_size);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Everything an example callback can reach
+pub struct ExampleCtx<'a> {
+    event_loop: &'a EventLoop,
+    gpu: &'a GpuContext,
+    windows: &'a mut Vec<WindowSurface>,
+    pipelines: &'a mut Vec<(wgpu::TextureFormat, Rc<wgpu::RenderPipeline>)>,
+    shader: &'a wgpu::ShaderModule,
+    stats: &'a mut FrameStats,
+    compute: &'a mut Option<Rc<ComputeStage>>,
+    damage: &'a mut Option<Rc<DamageTarget>>,
+    frame: Option<Frame>,
+    exit: bool,
+}
+
+impl<'a> ExampleCtx<'a> {
+    pub fn event_loop(&self) -> &'a EventLoop {
+        self.event_loop
+    }
+
+    pub fn gpu(&self) -> &'a GpuContext {
+        self.gpu
+    }
+
+    pub fn device(&self) -> &'a wgpu::Device {
+        &self.gpu.device
+    }
+
+    pub fn queue(&self) -> &'a wgpu::Queue {
+        &self.gpu.queue
+    }
+
+    pub fn stats(&self) -> &FrameStats {
+        self.stats
+    }
+
+    pub fn stats_mut(&mut self) -> &mut FrameStats {
+        self.stats
+    }
+
+    /// The frame of the window being drawn, None outside Draw steps
+    pub fn frame(&mut self) -> Option<&mut Frame> {
+        self.frame.as_mut()
+    }
+
+    /// The window opened by run_example
+    pub fn window(&mut self) -> &mut X11Window {
+        self.windows[0].window_mut()
+    }
+
+    /// The window opened by run_example along with its surface
+    pub fn surface(&mut self) -> &mut WindowSurface {
+        &mut self.windows[0]
+    }
+
+    /// A window opened by run_example or open_window
+    pub fn window_by_id(&mut self, id: WindowId) -> Option<&mut X11Window> {
+        self.windows
+            .iter_mut()
+            .map(WindowSurface::window_mut)
+            .find(|window| window.id() == id)
+    }
+
+    /// Ids of all open windows, the first is the one of run_example
+    pub fn windows(&self) -> Vec<WindowId> {
+        self.windows.iter().map(|w| w.window().id()).collect()
+    }
+
+    /// Open another window drawn by the callback, closed by the harness
+    /// when the window manager asks
+    pub fn open_window(&mut self, title: &str, width: u32, height: u32) -> WindowId {
+        let mut window = X11Window::new(self.event_loop, title, (width, height));
+        window.on_first_present(|window| window.show());
+        let id = window.id();
+        self.windows.push(WindowSurface::new(self.gpu, window));
+        id
+    }
+
+    /// Close a window other than the first, closing the first exits
+    pub fn close_window(&mut self, id: WindowId) {
+        match self.windows.iter().position(|w| w.window().id() == id) {
+            Some(0) => self.exit = true,
+            Some(i) => {
+                self.windows.remove(i).destroy(self.gpu);
+            }
+            None => {}
+        }
+    }
+
+    /// The default pipeline drawing a triangle with `draw(0..3, 0..1)`,
+    /// for frames of the given format
+    pub fn pipeline(&mut self, format: wgpu::TextureFormat) -> Rc<wgpu::RenderPipeline> {
+        if let Some((_, pipeline)) = self.pipelines.iter().find(|(f, _)| *f == format) {
+            return pipeline.clone();
+        }
+        let pipeline = Rc::new(create_pipeline(&self.gpu.device, self.shader, format));
+        self.pipelines.push((format, pipeline.clone()));
+        pipeline
+    }
+
+    /// Render the frames of the first window into a compute stage of
+    /// `format` from the next frame on, see Frame::compute_pass. None
+    /// renders to the surface again and drops the stage's textures
+    pub fn set_compute_stage(&mut self, format: Option<wgpu::TextureFormat>) {
+        let format = match format {
+            Some(format) => format,
+            None => {
+                *self.compute = None;
+                return;
+            }
+        };
+        if self.compute_stage().map(ComputeStage::format) == Some(format) {
+            return;
+        }
+        let surface = self.windows[0].surface();
+        let size = PhysicalSize::new(surface.config.width, surface.config.height);
+        let stage = ComputeStage::new(&self.gpu.device, format, surface.format(), size);
+        *self.compute = Some(Rc::new(stage));
+    }
+
+    /// Keep the frames of the first window between frames so they only
+    /// redraw their damage, see Frame::set_damage. Ignored while there is
+    /// a compute stage
+    pub fn set_damage_tracking(&mut self, enabled: bool) {
+        if !enabled {
+            *self.damage = None;
+        } else if self.damage.is_none() {
+            let surface = self.windows[0].surface();
+            let size = PhysicalSize::new(surface.config.width, surface.config.height);
+            let format = surface.format();
+            let target = DamageTarget::new(&self.gpu.device, format, format, size);
+            *self.damage = Some(Rc::new(target));
+        }
+    }
+
+    /// The compute stage of the first window, e.g. for the layout of
+    /// its bind group
+    pub fn compute_stage(&self) -> Option<&ComputeStage> {
+        self.compute.as_deref()
+    }
+
+    /// Leave run_example after this step
+    pub fn exit(&mut self) {
+        self.exit = true;
+    }
+}
+
+// Hand the events to the callback, true when it wants to exit
+fn dispatch<F>(ctx: &mut ExampleCtx, f: &mut F, events: &[Event]) -> bool
+where
+    F: FnMut(&mut ExampleCtx, Step),
+{
+    for event in events {
+        for window in ctx.windows.iter_mut() {
+            window.handle_event(ctx.gpu, event);
+        }
+        f(ctx, Step::Event(*event));
+        match *event {
+            Event::CloseRequested { window } => ctx.close_window(window),
+            Event::FramePresented { window, ust, .. } if window == ctx.windows()[0] => {
+                ctx.stats.reached_screen(ust)
+            }
+            _ => {}
+        }
+        if ctx.exit {
+            return true;
+        }
+    }
+    false
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("default pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+/// Open a window and call `f` with every event and for every frame until
+/// the window is closed or the callback calls ExampleCtx::exit. The
+/// window title shows the frame rate
+pub fn run_example<F>(title: &str, f: F)
+where
+    F: FnMut(&mut ExampleCtx, Step),
+{
+    run_example_with(title, |_| {}, f)
+}
+
+/// Like run_example, calling `setup` with the event loop before the
+/// window is opened
+pub fn run_example_with<S, F>(title: &str, setup: S, mut f: F)
+where
+    S: FnOnce(&mut EventLoop),
+    F: FnMut(&mut ExampleCtx, Step),
+{
+    let mut event_loop = EventLoop::new();
+    setup(&mut event_loop);
+    let mut window = X11Window::new(&event_loop, title, (800, 600));
+    // Map once there is a frame, so the window never shows up blank
+    window.on_first_present(|window| window.show());
+    let (gpu, window) = GpuContext::with_window(window);
+    let shader = gpu
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../../src/shader.wgsl"))),
+        });
+
+    let mut windows = vec![window];
+    let mut pipelines = Vec::new();
+    let mut stats = FrameStats::new();
+    let mut compute = None;
+    let mut damage = None;
+    let mut events = Vec::new();
+    let mut late = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        let mut ctx = ExampleCtx {
+            event_loop: &event_loop,
+            gpu: &gpu,
+            windows: &mut windows,
+            pipelines: &mut pipelines,
+            shader: &shader,
+            stats: &mut stats,
+            compute: &mut compute,
+            damage: &mut damage,
+            frame: None,
+            exit: false,
+        };
+        if dispatch(&mut ctx, &mut f, &events) {
+            break;
+        }
+
+        // Wait for the next image of the first window, then read the
+        // input which arrived meanwhile
+        let mut acquired = None;
+        if event_loop.late_latch() {
+            acquired = windows[0].surface_mut().acquire(&gpu);
+            event_loop.poll_late_input(&mut late);
+        }
+        let mut ctx = ExampleCtx {
+            event_loop: &event_loop,
+            gpu: &gpu,
+            windows: &mut windows,
+            pipelines: &mut pipelines,
+            shader: &shader,
+            stats: &mut stats,
+            compute: &mut compute,
+            damage: &mut damage,
+            frame: None,
+            exit: false,
+        };
+        if dispatch(&mut ctx, &mut f, &late) {
+            break;
+        }
+
+        // Before drawing, so the first marked frame is reported
+        ctx.stats
+            .start_latency(&event_loop, ctx.windows[0].window_mut());
+        for id in ctx.windows() {
+            let surface = match ctx.windows.iter_mut().find(|w| w.window().id() == id) {
+                Some(window) => window.surface_mut(),
+                None => continue,
+            };
+            let texture = match acquired.take().or_else(|| surface.acquire(&gpu)) {
+                Some(texture) => texture,
+                None => continue,
+            };
+            let size = PhysicalSize::new(surface.config.width, surface.config.height);
+            let surface_format = surface.format();
+            let target = texture
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default());
+            let encoder = gpu
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            let first = id == ctx.windows()[0];
+            // The frame of the previous iteration let go of the stage
+            if let Some(stage) = ctx.compute.as_mut().and_then(Rc::get_mut) {
+                stage.resize(&gpu.device, size);
+                stage.rebuild(&gpu.device, surface_format);
+            }
+            if let Some(target) = ctx.damage.as_mut().and_then(Rc::get_mut) {
+                target.resize(&gpu.device, size);
+                target.rebuild(&gpu.device, surface_format);
+            }
+            let stage = if first { ctx.compute.clone() } else { None };
+            let damage = if first && stage.is_none() {
+                ctx.damage.clone()
+            } else {
+                None
+            };
+            let (view, format) = match (&stage, &damage) {
+                (Some(stage), _) => (
+                    stage
+                        .scene()
+                        .create_view(&wgpu::TextureViewDescriptor::default()),
+                    stage.format(),
+                ),
+                (None, Some(damage)) => (
+                    damage
+                        .texture()
+                        .create_view(&wgpu::TextureViewDescriptor::default()),
+                    damage.format(),
+                ),
+                (None, None) => (target, surface_format),
+            };
+            ctx.frame = Some(Frame {
+                view,
+                encoder,
+                size,
+                format,
+                pipeline: ctx.pipeline(format),
+                compute: stage,
+                damage,
+            });
+            f(&mut ctx, Step::Draw(id));
+
+            let mut frame = ctx.frame.take().unwrap();
+            if let Some(stage) = frame.compute.take() {
+                let target = texture
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                stage.resolve(&mut frame.encoder, &target);
+            }
+            if let Some(damage) = frame.damage.take() {
+                let target = texture
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                damage.resolve(&mut frame.encoder, &target);
+            }
+            gpu.queue.submit(Some(frame.encoder.finish()));
+            // The callback may have closed the window while drawing
+            if let Some(window) = ctx.windows.iter_mut().find(|w| w.window().id() == id) {
+                window.present(texture);
+            }
+            if id == ctx.windows()[0] {
+                ctx.stats.presented();
+            }
+            if ctx.exit {
+                break 'running;
+            }
+        }
+
+        if stats.tick() {
+            let title = format!("{} - {} fps", title, stats.fps);
+            windows[0].window_mut().set_title(&title);
+        }
+    }
+
+    for window in windows {
+        window.destroy(&gpu);
+    }
+}
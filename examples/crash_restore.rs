@@ -0,0 +1,66 @@
+//! Starts itself again as a child which grabs the keyboard and pointer
+//! under a SessionGuard and then aborts. Afterwards a fresh window must
+//! be able to take both grabs, exits with 1 otherwise, e.g. to run under
+//! Xvfb in CI: `xvfb-run cargo run --example crash_restore`
+use std::env;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{self, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+
+// Windows can only be grabbed to once mapped, try for a while
+fn grab(event_loop: &mut EventLoop, window: &mut X11Window) -> bool {
+    let start = Instant::now();
+    let mut events = Vec::new();
+    while start.elapsed() < Duration::from_secs(3) {
+        event_loop.poll_events(&mut events);
+        if window.set_keyboard_grab(true).is_ok() && window.set_pointer_grab(true).is_ok() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+fn child() {
+    let mut event_loop = EventLoop::new();
+    let _guard = event_loop.session_guard();
+    let mut window = X11Window::new(&event_loop, "crashing with grabs", (320, 200));
+    window.show();
+    if !grab(&mut event_loop, &mut window) {
+        eprintln!("child: could not grab");
+        process::exit(2);
+    }
+    println!("child: grabbed, aborting");
+    process::abort();
+}
+
+fn main() {
+    if env::args().nth(1).as_deref() == Some("child") {
+        return child();
+    }
+
+    let status = Command::new(env::current_exe().unwrap())
+        .arg("child")
+        .status()
+        .expect("failed to start the child");
+    match status.signal() {
+        Some(signal) => println!("child died of signal {}", signal),
+        None => {
+            println!("FAILED  the child did not abort: {}", status);
+            process::exit(1);
+        }
+    }
+
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "grabbing after the crash", (320, 200));
+    window.show();
+    if grab(&mut event_loop, &mut window) {
+        println!("ok      grabbed after the crash");
+    } else {
+        println!("FAILED  the grabs of the crashed child are still held");
+        process::exit(1);
+    }
+}
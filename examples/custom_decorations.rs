@@ -0,0 +1,121 @@
+//! Two undecorated windows with title bars of their own. Drag a window
+//! by its title bar: it snaps to the edges of the usable area of each
+//! monitor and to the other window, its title bar flashing when it does.
+//! Hold Shift to move freely, click the red square to close
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use daswin::render::software::SoftwareSurface;
+use daswin::render::RenderSurface;
+use daswin::Snapping;
+
+const TITLE_BAR_HEIGHT: f64 = 28.0;
+const CLOSE_SIZE: f64 = 28.0;
+
+struct Decorated {
+    surface: RenderSurface<SoftwareSurface>,
+    snapped_at: Option<Instant>,
+}
+
+impl Decorated {
+    fn new(event_loop: &EventLoop, title: &str, position: (i32, i32)) -> Decorated {
+        let mut window = WindowBuilder::new()
+            .with_title(title)
+            .with_inner_size((360, 240))
+            .with_position(Position::At(position.into()))
+            .with_decorations(false)
+            .build(event_loop);
+        window.show();
+        let backend =
+            SoftwareSurface::new(&window, window.inner_size()).expect("unsupported window visual");
+        Decorated {
+            surface: RenderSurface::new(window, backend),
+            snapped_at: None,
+        }
+    }
+
+    fn draw(&mut self) {
+        let flash = self
+            .snapped_at
+            .is_some_and(|at| at.elapsed() < Duration::from_millis(200));
+        let mut frame = match self.surface.acquire() {
+            Some(frame) => frame,
+            None => return,
+        };
+        let width = frame.size().width as usize;
+        for (i, pixel) in frame.rgba_mut().chunks_exact_mut(4).enumerate() {
+            let (x, y) = ((i % width) as f64, (i / width) as f64);
+            let color = if y >= TITLE_BAR_HEIGHT {
+                [235, 235, 235]
+            } else if x >= width as f64 - CLOSE_SIZE {
+                [200, 60, 60]
+            } else if flash {
+                [80, 160, 255]
+            } else {
+                [50, 50, 60]
+            };
+            pixel.copy_from_slice(&[color[0], color[1], color[2], 255]);
+        }
+        self.surface.present(frame);
+    }
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    event_loop.set_snapping(Some(Snapping::default()));
+    let mut windows = vec![
+        Decorated::new(&event_loop, "custom decorations", (100, 100)),
+        Decorated::new(&event_loop, "drag me next to the other", (520, 100)),
+    ];
+
+    let mut events = Vec::new();
+    while !windows.is_empty() {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            for decorated in &mut windows {
+                decorated.surface.handle_event(event);
+            }
+            let find = |windows: &[Decorated], id: WindowId| {
+                windows
+                    .iter()
+                    .position(|decorated| decorated.surface.window().id() == id)
+            };
+            match *event {
+                Event::MouseInput {
+                    window,
+                    button: 1,
+                    pressed: true,
+                    position,
+                    ..
+                } if position.y < TITLE_BAR_HEIGHT => {
+                    let index = match find(&windows, window) {
+                        Some(index) => index,
+                        None => continue,
+                    };
+                    let width = windows[index].surface.size().width as f64;
+                    if position.x >= width - CLOSE_SIZE {
+                        windows.remove(index);
+                    } else {
+                        windows[index].surface.window_mut().begin_manual_drag();
+                    }
+                }
+                Event::Snapped { window, edge } => {
+                    println!("snapped with the {:?} edge", edge);
+                    if let Some(index) = find(&windows, window) {
+                        windows[index].snapped_at = Some(Instant::now());
+                    }
+                }
+                Event::CloseRequested { window } => {
+                    if let Some(index) = find(&windows, window) {
+                        windows.remove(index);
+                    }
+                }
+                _ => {}
+            }
+        }
+        for decorated in &mut windows {
+            decorated.draw();
+        }
+        std::thread::sleep(Duration::from_millis(16));
+    }
+}
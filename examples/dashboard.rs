@@ -0,0 +1,76 @@
+//! A mostly static dashboard: the triangle is drawn once and only the
+//! clock square in the top left corner is redrawn, once a second. D
+//! toggles damage tracking, the terminal shows whether presents are
+//! partial or only the drawing is saved
+mod common;
+
+use std::time::Instant;
+
+use common::{run_example, Step};
+use daswin::prelude::*;
+use daswin::Rect;
+use x11::{keysym, xlib};
+
+const CLOCK: Rect = Rect {
+    x: 16,
+    y: 16,
+    width: 64,
+    height: 64,
+};
+
+// Cycles through a few colors, one per second
+fn clock_color(second: u64) -> wgpu::Color {
+    let hue = (second % 6) as f64 / 6.0;
+    wgpu::Color {
+        r: (hue * std::f64::consts::TAU).cos() * 0.5 + 0.5,
+        g: ((hue + 1.0 / 3.0) * std::f64::consts::TAU).cos() * 0.5 + 0.5,
+        b: ((hue + 2.0 / 3.0) * std::f64::consts::TAU).cos() * 0.5 + 0.5,
+        a: 1.0,
+    }
+}
+
+fn main() {
+    let start = Instant::now();
+    let mut tracking = true;
+    let mut last_second = None;
+    let mut report = true;
+    run_example("dashboard", move |ctx, step| match step {
+        Step::Event(Event::KeyboardInput {
+            keysym,
+            pressed: true,
+            repeat: false,
+            ..
+        }) if keysym == keysym::XK_d as xlib::KeySym => {
+            tracking = !tracking;
+            report = true;
+        }
+        Step::Event(_) => {}
+        Step::Draw(_) => {
+            // Takes effect from the next frame on, which is a full one
+            ctx.set_damage_tracking(tracking);
+            let frame = ctx.frame().unwrap();
+            if report {
+                report = false;
+                let mode = match (tracking, frame.supports_damage()) {
+                    (false, _) => "full redraws and presents",
+                    (true, true) => "partial redraws and presents",
+                    (true, false) => "partial redraws, full presents",
+                };
+                println!("{}", mode);
+            }
+            let second = start.elapsed().as_secs();
+            if last_second != Some(second) {
+                last_second = Some(second);
+                frame.set_damage(&[CLOCK]);
+            } else {
+                frame.set_damage(&[]);
+            }
+            // Frames with the whole target damaged redraw the triangle
+            // on the clock's color
+            let triangle = frame.pipeline.clone();
+            let mut pass = frame.pass(clock_color(second));
+            pass.set_pipeline(&triangle);
+            pass.draw(0..3, 0..1);
+        }
+    });
+}
@@ -0,0 +1,65 @@
+//! Draws the frame statistics with DebugText over the triangle. Space
+//! toggles a stress test of 10000 glyphs, whose cost shows in the frame
+//! time next to the one without it
+mod common;
+
+use common::{run_example, Step};
+use daswin::prelude::*;
+use daswin::render::text::{DebugText, GLYPH_SIZE};
+use x11::{keysym, xlib};
+
+const STRESS_GLYPHS: usize = 10_000;
+const STRESS_COLUMNS: usize = 100;
+
+fn main() {
+    let mut text: Option<DebugText> = None;
+    let mut stress = false;
+    run_example("debug text", move |ctx, step| match step {
+        Step::Event(Event::KeyboardInput {
+            keysym, pressed, ..
+        }) if pressed && keysym == keysym::XK_space as xlib::KeySym => stress = !stress,
+        Step::Event(_) => {}
+        Step::Draw(_) => {
+            let (device, queue) = (ctx.device(), ctx.queue());
+            let (fps, frame_time) = (ctx.stats().fps, ctx.stats().frame_time);
+            let frame = ctx.frame().unwrap();
+            let text = text.get_or_insert_with(|| DebugText::new(device, queue, frame.format));
+            text.rebuild(device, frame.format);
+
+            if stress {
+                // The rows repeat over the same lines, so none is culled
+                // on smaller windows
+                let line: String = (0..STRESS_COLUMNS)
+                    .map(|i| (b'!' + (i % 94) as u8) as char)
+                    .collect();
+                for row in 0..STRESS_GLYPHS / STRESS_COLUMNS {
+                    let y = (3 + row as u32 % 32 * 2) * GLYPH_SIZE.1 / 2;
+                    text.queue_text(8.0, y as f32, wgpu::Color::WHITE, &line);
+                }
+            }
+            let stats_line = format!(
+                "{} fps\n{:.2} ms frame time\n{} glyphs stress test, space toggles",
+                fps,
+                frame_time.as_secs_f64() * 1000.0,
+                if stress { STRESS_GLYPHS } else { 0 },
+            );
+            text.queue_text(8.0, 8.0, wgpu::Color::GREEN, &stats_line);
+            let (width, height) = frame.size.into();
+            text.queue_text(
+                8.0,
+                height as f32 - 24.0,
+                wgpu::Color::RED,
+                "No glyph for \u{e4} or \u{263a}, drawn as boxes",
+            );
+            // Half off the right edge, clipped by the rasterizer
+            text.queue_text(width as f32 - 40.0, 8.0, wgpu::Color::BLUE, "clipped");
+            text.prepare(device, queue, frame.size);
+
+            let pipeline = frame.pipeline.clone();
+            let mut pass = frame.pass(wgpu::Color::BLACK);
+            pass.set_pipeline(&pipeline);
+            pass.draw(0..3, 0..1);
+            text.draw(&mut pass);
+        }
+    });
+}
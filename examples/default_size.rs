@@ -0,0 +1,70 @@
+//! Checks Size::Default: first the sizes default_inner_size picks for a
+//! few monitor geometries, then a window built with it on the primary
+//! monitor. Exits with 1 when a size is wrong, e.g. `xvfb-run -s
+//! '-screen 0 1366x768x24' cargo run --example default_size`
+use std::process;
+
+use daswin::prelude::*;
+use daswin::{default_inner_size, Rect, DEFAULT_MIN_INNER_SIZE};
+
+fn check(ok: bool, what: &str) {
+    if ok {
+        println!("ok      {}", what);
+    } else {
+        println!("FAILED  {}", what);
+        process::exit(1);
+    }
+}
+
+fn area(width: u32, height: u32) -> Rect {
+    Rect {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    }
+}
+
+fn main() {
+    // Work area, scale factor and the expected size
+    let geometries = [
+        ("1080p", area(1920, 1050), 1.0, (1152, 630)),
+        ("4K at scale 2", area(3840, 2100), 2.0, (2304, 1260)),
+        ("1366x768 laptop", area(1366, 740), 1.0, (820, 480)),
+        ("800x600 at scale 2", area(800, 600), 2.0, (800, 600)),
+        ("portrait 1080p", area(1080, 1920), 1.0, (648, 1152)),
+    ];
+    for (name, work_area, scale_factor, expected) in geometries {
+        let min = DEFAULT_MIN_INNER_SIZE.to_physical(scale_factor);
+        let size = default_inner_size(work_area, min);
+        println!("{}: {:?}", name, size);
+        check(size == expected.into(), name);
+    }
+    check(
+        default_inner_size(area(1920, 1050), PhysicalSize::new(1600, 1200))
+            == PhysicalSize::new(1600, 1050),
+        "a minimum larger than the work area gives the work area",
+    );
+
+    let event_loop = EventLoop::new();
+    let monitor = event_loop.primary_monitor().expect("no monitor");
+    let window = WindowBuilder::new()
+        .with_title("default size")
+        .build(&event_loop);
+    let min = DEFAULT_MIN_INNER_SIZE.to_physical(monitor.scale_factor());
+    let expected = default_inner_size(monitor.work_area(), min);
+    println!("window: {:?}", window.inner_size());
+    check(
+        window.inner_size() == expected,
+        "the window has its default size before the first frame",
+    );
+
+    let window = WindowBuilder::new()
+        .with_inner_size((200, 100))
+        .with_min_inner_size((320, 240))
+        .build(&event_loop);
+    check(
+        window.inner_size() == PhysicalSize::new(320, 240),
+        "the initial size is raised to the minimum",
+    );
+}
@@ -0,0 +1,80 @@
+//! Lists the input devices with what they report, then logs devices
+//! plugged in or out until interrupted
+use std::thread;
+use std::time::Duration;
+
+use daswin::prelude::*;
+use daswin::{DeviceInfo, DeviceType};
+
+fn print_device(device: &DeviceInfo) {
+    let kind = match device.device_type() {
+        DeviceType::MasterPointer => "master pointer",
+        DeviceType::MasterKeyboard => "master keyboard",
+        DeviceType::SlavePointer => "pointer",
+        DeviceType::SlaveKeyboard => "keyboard",
+        DeviceType::FloatingSlave => "floating",
+        _ => "other",
+    };
+    let mut capabilities = Vec::new();
+    if device.buttons() > 0 {
+        capabilities.push(format!("{} buttons", device.buttons()));
+    }
+    if device.relative_axes() > 0 {
+        capabilities.push(format!("{} relative axes", device.relative_axes()));
+    }
+    if device.scroll_axes() > 0 {
+        capabilities.push(format!("{} scroll axes", device.scroll_axes()));
+    }
+    if device.has_pressure() {
+        capabilities.push("pressure".to_string());
+    }
+    if let Some(touches) = device.max_touches() {
+        capabilities.push(format!("touch, {} contacts", touches));
+    }
+    if !device.is_enabled() {
+        capabilities.push("disabled".to_string());
+    }
+    println!(
+        "{:>4}  {:<16} {:<40} {}",
+        device.id().into_raw(),
+        kind,
+        device.name(),
+        capabilities.join(", ")
+    );
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    event_loop.catch_exit_signals();
+    let devices = event_loop.devices();
+    if devices.is_empty() {
+        println!("XInput2 is not available");
+        return;
+    }
+    println!("{:>4}  {:<16} {:<40} capabilities", "id", "type", "name");
+    for device in &devices {
+        print_device(device);
+    }
+
+    let mut events = Vec::new();
+    loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::DeviceAdded { device } => {
+                    print!("added   ");
+                    match event_loop.device(device) {
+                        Some(info) => print_device(&info),
+                        None => println!("{}", device.into_raw()),
+                    }
+                }
+                Event::DeviceRemoved { device } => {
+                    println!("removed {}", device.into_raw())
+                }
+                Event::ExitRequested => return,
+                _ => {}
+            }
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
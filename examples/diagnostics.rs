@@ -0,0 +1,60 @@
+//! Prints what the loop did every 5 seconds: events by kind, wakeups and
+//! the split between polling, handling and idling. A thread ticking over
+//! a channel wakes the loop for it. `kill -USR1 <pid>` prints the totals
+//! since the start as JSON with the next wakeup
+use std::thread;
+use std::time::Duration;
+
+use daswin::prelude::*;
+
+const INTERVAL: Duration = Duration::from_secs(5);
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    event_loop.print_metrics_on_signal();
+    let mut window = X11Window::new(&event_loop, "diagnostics", (480, 320));
+    window.show();
+    println!("pid {}, move the pointer or type", std::process::id());
+
+    let (tick_proxy, ticks) = event_loop.create_channel::<()>();
+    thread::spawn(move || loop {
+        thread::sleep(INTERVAL);
+        if tick_proxy.send(()).is_err() {
+            return;
+        }
+    });
+
+    let mut last = event_loop.metrics();
+    let code = event_loop.run(move |event_loop, event| match event {
+        Event::Channel(channel) if channel == ticks => {
+            event_loop.drain_channel::<()>(channel).unwrap();
+            let metrics = event_loop.metrics();
+            let interval = metrics.since(&last);
+            let seconds = interval.uptime.as_secs_f64();
+            println!(
+                "{:.0} polls/s, {} X events, at most {} per poll",
+                interval.polls as f64 / seconds,
+                interval.x_events,
+                interval.max_x_events_per_poll,
+            );
+            let kinds: Vec<String> = interval
+                .events
+                .iter()
+                .filter(|&&(name, count)| count > 0 && name != "AboutToWait")
+                .map(|(name, count)| format!("{} {}", name, count))
+                .collect();
+            println!("  events: {}", kinds.join(", "));
+            println!("  wakeups: {:?}", interval.wakeups);
+            println!(
+                "  polling {:.1}%, handler {:.1}%, idle {:.1}%",
+                interval.polling.as_secs_f64() / seconds * 100.0,
+                interval.handler.as_secs_f64() / seconds * 100.0,
+                interval.idle.as_secs_f64() / seconds * 100.0,
+            );
+            last = metrics;
+        }
+        Event::CloseRequested { .. } => event_loop.exit(0),
+        _ => {}
+    });
+    std::process::exit(code);
+}
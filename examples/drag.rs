@@ -0,0 +1,45 @@
+//! Prints drags with any button, ending each one on its release even when
+//! it happens outside the window or the window loses focus mid-drag
+use std::collections::HashMap;
+use std::time::Duration;
+
+use daswin::prelude::*;
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "drag", (640, 480));
+    window.show();
+
+    let mut drags = HashMap::new();
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::MouseInput {
+                    button,
+                    pressed: true,
+                    position,
+                    ..
+                } => {
+                    drags.insert(button, position);
+                }
+                Event::MouseInput {
+                    button,
+                    pressed: false,
+                    position,
+                    ..
+                } => match drags.remove(&button) {
+                    Some(start) => println!(
+                        "button {} dragged from {:.0},{:.0} to {:.0},{:.0}",
+                        button, start.x, start.y, position.x, position.y
+                    ),
+                    None => println!("button {} released without a press", button),
+                },
+                _ => {}
+            }
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
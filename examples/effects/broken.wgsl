@@ -0,0 +1,5 @@
+// Fails to compile on purpose, to show how the gallery reports errors
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.uv, undefined_value, 1.0);
+}
@@ -0,0 +1,9 @@
+// Classic plasma of overlapping sine waves
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let p = in.uv * 8.0;
+    let t = globals.time;
+    let v = sin(p.x + t) + sin(p.y + t * 1.3) + sin(p.x + p.y + t * 0.7) + sin(length(p - 4.0) * 1.5 - t);
+    let col = 0.5 + 0.5 * cos(v * 1.5 + vec3<f32>(0.0, 2.1, 4.2));
+    return vec4<f32>(col, 1.0);
+}
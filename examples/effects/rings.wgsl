@@ -0,0 +1,8 @@
+// Rings spreading from the center of the window
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let aspect = globals.resolution.x / max(globals.resolution.y, 1.0);
+    let p = (in.uv - 0.5) * vec2<f32>(aspect, 1.0);
+    let ring = 0.5 + 0.5 * sin(length(p) * 40.0 - globals.time * 4.0);
+    return vec4<f32>(vec3<f32>(ring * 0.2, ring * 0.6, ring), 1.0);
+}
@@ -0,0 +1,9 @@
+// A light following the cursor over a checkerboard
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = in.uv * globals.resolution;
+    let cell = floor(pixel / 32.0);
+    let checker = 0.25 + 0.5 * abs(cell.x + cell.y - 2.0 * floor((cell.x + cell.y) / 2.0));
+    let light = exp(-distance(pixel, globals.cursor) / 120.0);
+    return vec4<f32>(vec3<f32>(checker * (0.15 + light)), 1.0);
+}
@@ -0,0 +1,81 @@
+//! Embeds a window into a second window playing the part of a GtkSocket,
+//! doing the embedder's half of the XEMBED handshake by hand. Press f in
+//! the socket to pass focus into the embedded window, u to take it back
+use std::mem;
+use std::os::raw::c_long;
+
+use daswin::prelude::*;
+use x11::{keysym, xlib};
+
+const XEMBED_EMBEDDED_NOTIFY: c_long = 0;
+const XEMBED_FOCUS_IN: c_long = 4;
+const XEMBED_FOCUS_OUT: c_long = 5;
+
+/// Send an XEMBED message from the socket to the embedded window
+fn send_xembed(display: *mut xlib::Display, client: &X11Window, data: [c_long; 5]) {
+    unsafe {
+        let mut xclient: xlib::XClientMessageEvent = mem::zeroed();
+        xclient.type_ = xlib::ClientMessage;
+        xclient.window = client.window;
+        xclient.message_type = xlib::XInternAtom(display, b"_XEMBED\0".as_ptr() as *const _, 0);
+        xclient.format = 32;
+        for (i, &value) in data.iter().enumerate() {
+            xclient.data.set_long(i, value);
+        }
+        let mut event = xlib::XEvent::from(xclient);
+        xlib::XSendEvent(display, client.window, 0, xlib::NoEventMask, &mut event);
+        xlib::XFlush(display);
+    }
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let display = event_loop.display();
+
+    let mut socket = X11Window::new(&event_loop, "socket - press f or u", (480, 320));
+    socket.show();
+
+    let mut client = WindowBuilder::new()
+        .with_inner_size((320, 200))
+        .with_position(Position::CenteredOnParent)
+        .with_embed_parent(socket.window)
+        .build(&event_loop);
+    client.show();
+
+    send_xembed(
+        display,
+        &client,
+        [0, XEMBED_EMBEDDED_NOTIFY, 0, socket.window as c_long, 0],
+    );
+
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for &event in &events {
+            match event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::Focused { window, focused } if window == client.id() => {
+                    println!("embedded window focused: {}", focused);
+                }
+                Event::Resized { window, .. } if window == client.id() => {
+                    println!("embedded window mapped");
+                }
+                Event::KeyboardInput {
+                    window,
+                    keysym,
+                    pressed: true,
+                    ..
+                } if window == socket.id() => {
+                    if keysym == keysym::XK_f as xlib::KeySym {
+                        send_xembed(display, &client, [0, XEMBED_FOCUS_IN, 0, 0, 0]);
+                    } else if keysym == keysym::XK_u as xlib::KeySym {
+                        send_xembed(display, &client, [0, XEMBED_FOCUS_OUT, 0, 0, 0]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,62 @@
+//! Reports the CPU time spent per second with pointer motion subscribed
+//! or not. Wiggle the mouse over the window and press m to toggle
+//! POINTER_MOTION
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use daswin::EventFilter;
+use x11::{keysym, xlib};
+
+/// User and system CPU time of the process
+fn cpu_time() -> Duration {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) };
+    let time = |t: libc::timeval| Duration::new(t.tv_sec as u64, t.tv_usec as u32 * 1000);
+    time(usage.ru_utime) + time(usage.ru_stime)
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "event filter - press m", (640, 480));
+    window.show();
+
+    let mut events = Vec::new();
+    let mut delivered = 0;
+    let (mut second, mut cpu) = (Instant::now(), cpu_time());
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        delivered += events.len();
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::KeyboardInput {
+                    keysym,
+                    pressed: true,
+                    ..
+                } if keysym == keysym::XK_m as xlib::KeySym => {
+                    let filter = event_loop.event_filter() ^ EventFilter::POINTER_MOTION;
+                    event_loop.set_event_filter(filter);
+                }
+                _ => {}
+            }
+        }
+
+        if second.elapsed() >= Duration::from_secs(1) {
+            let used = cpu_time();
+            let motion = event_loop
+                .event_filter()
+                .contains(EventFilter::POINTER_MOTION);
+            println!(
+                "motion {}: {:?} cpu, {} events",
+                if motion { "subscribed" } else { "unsubscribed" },
+                used - cpu,
+                delivered
+            );
+            second = Instant::now();
+            cpu = used;
+            delivered = 0;
+        }
+
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,124 @@
+//! Checks the report run logs when its handler panics: key presses of a,
+//! b and c are sent from another connection, the handler panics on c,
+//! and the logged table must list the three presses in order. Exits with
+//! 1 otherwise, e.g. `xvfb-run cargo run --example event_history`
+use std::mem;
+use std::panic;
+use std::process;
+use std::ptr;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use daswin::prelude::*;
+use daswin::PanicPolicy;
+use x11::{keysym, xlib};
+
+/// Keeps the errors logged, where the report goes
+struct Capture(Mutex<String>);
+
+impl log::Log for Capture {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Error
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            let mut captured = self.0.lock().unwrap();
+            captured.push_str(&record.args().to_string());
+            captured.push('\n');
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static CAPTURE: Capture = Capture(Mutex::new(String::new()));
+
+fn check(ok: bool, what: &str) {
+    if ok {
+        println!("ok      {}", what);
+    } else {
+        println!("FAILED  {}", what);
+        process::exit(1);
+    }
+}
+
+unsafe fn send_press(display: *mut xlib::Display, window: xlib::Window, key: u32) {
+    let mut xevent: xlib::XEvent = mem::zeroed();
+    xevent.key = xlib::XKeyEvent {
+        type_: xlib::KeyPress,
+        serial: 0,
+        send_event: xlib::True,
+        display,
+        window,
+        root: xlib::XDefaultRootWindow(display),
+        subwindow: 0,
+        time: xlib::CurrentTime,
+        x: 0,
+        y: 0,
+        x_root: 0,
+        y_root: 0,
+        state: 0,
+        keycode: xlib::XKeysymToKeycode(display, key as xlib::KeySym) as u32,
+        same_screen: xlib::True,
+    };
+    xlib::XSendEvent(display, window, xlib::True, xlib::KeyPressMask, &mut xevent);
+}
+
+fn main() {
+    log::set_logger(&CAPTURE).unwrap();
+    log::set_max_level(log::LevelFilter::Error);
+    // The report is what is checked, not the default panic message
+    panic::set_hook(Box::new(|_| {}));
+
+    let mut event_loop = EventLoop::new();
+    event_loop.set_panic_policy(PanicPolicy::ConvertToExit(3));
+    let mut window = X11Window::new(&event_loop, "event history", (320, 200));
+    window.show();
+    let mut events = Vec::new();
+    for _ in 0..20 {
+        event_loop.poll_events(&mut events);
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let other = unsafe { xlib::XOpenDisplay(ptr::null()) };
+    check(!other.is_null(), "a second connection opens");
+    unsafe {
+        for key in [keysym::XK_a, keysym::XK_b, keysym::XK_c] {
+            send_press(other, window.window, key);
+        }
+        xlib::XSync(other, xlib::False);
+    }
+
+    // The loop sleeps while nothing arrives, the timeout wakes it
+    let (timeout_proxy, timeout) = event_loop.create_channel::<()>();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_secs(2));
+        let _ = timeout_proxy.send(());
+    });
+    let code = event_loop.run(move |event_loop, event| match event {
+        Event::KeyboardInput {
+            keysym,
+            pressed: true,
+            ..
+        } if keysym == keysym::XK_c as xlib::KeySym => panic!("c pressed"),
+        Event::Channel(channel) if channel == timeout => event_loop.exit(1),
+        _ => {}
+    });
+    drop(window);
+    check(code == 3, "the panic converted to exit code 3");
+
+    let report = CAPTURE.0.lock().unwrap().clone();
+    println!("{}", report);
+    let table = report.split("recent events:").nth(1).unwrap_or("");
+    let presses: Vec<usize> = [keysym::XK_a, keysym::XK_b, keysym::XK_c]
+        .iter()
+        .filter_map(|&key| table.find(&format!("keysym: {}, pressed: true", key)))
+        .collect();
+    check(presses.len() == 3, "the report lists the three presses");
+    check(
+        presses.windows(2).all(|pair| pair[0] < pair[1]),
+        "the presses are in the order they were sent",
+    );
+}
@@ -0,0 +1,37 @@
+//! Counts key presses and saves the count when asked to exit, run it,
+//! press some keys and `kill` it or press Ctrl+C in the terminal
+use std::fs;
+
+use daswin::prelude::*;
+
+const STATE_FILE: &str = "daswin-exit-save.txt";
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    event_loop.catch_exit_signals();
+    let mut window = X11Window::new(&event_loop, "press keys, then kill me", (480, 240));
+    window.show();
+
+    let mut presses: u32 = fs::read_to_string(STATE_FILE)
+        .ok()
+        .and_then(|state| state.trim().parse().ok())
+        .unwrap_or(0);
+    println!("{} key presses so far", presses);
+
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } | Event::ExitRequested => break 'running,
+                Event::KeyboardInput { pressed: true, .. } => presses += 1,
+                _ => {}
+            }
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    fs::write(STATE_FILE, presses.to_string()).expect("failed to write state file");
+    println!("saved {} key presses to {}", presses, STATE_FILE);
+}
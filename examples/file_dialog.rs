@@ -0,0 +1,147 @@
+//! Press O to pick binary PPM images with the portal's file chooser, the
+//! first one readable becomes the window icon. S saves the icon as a PPM
+//! through a save dialog. Without xdg-desktop-portal it says so and keeps
+//! the window as it is
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use daswin::dialogs::{self, FileDialog, FileDialogOptions, FileFilter};
+use daswin::prelude::*;
+use x11::{keysym, xlib};
+
+/// An image of the netpbm P6 format, 8 bits per channel
+fn read_ppm(path: &Path) -> Option<Icon> {
+    let data = fs::read(path).ok()?;
+    // Magic, width, height and maxval separated by whitespace, then one
+    // whitespace byte before the pixels. Comments are not supported
+    let mut fields = Vec::new();
+    let mut start = None;
+    let mut pixels = None;
+    for (i, &byte) in data.iter().enumerate() {
+        match (byte.is_ascii_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(from)) => {
+                fields.push(std::str::from_utf8(&data[from..i]).ok()?);
+                start = None;
+                if fields.len() == 4 {
+                    pixels = Some(i + 1);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let (width, height): (u32, u32) = match fields[..] {
+        ["P6", width, height, "255"] => (width.parse().ok()?, height.parse().ok()?),
+        _ => return None,
+    };
+    let rgb = data.get(pixels?..)?;
+    let rgba = rgb
+        .chunks_exact(3)
+        .take(width as usize * height as usize)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+        .collect();
+    Icon::from_rgba(rgba, width, height)
+}
+
+fn write_ppm(path: &Path, icon: &Icon) -> std::io::Result<()> {
+    let mut data = format!("P6\n{} {}\n255\n", icon.width(), icon.height()).into_bytes();
+    for pixel in icon.rgba().chunks_exact(4) {
+        data.extend_from_slice(&pixel[..3]);
+    }
+    fs::write(path, data)
+}
+
+fn ppm_filter() -> FileFilter {
+    FileFilter::new("PPM images", &["*.ppm", "image/x-portable-pixmap"])
+}
+
+enum Pending {
+    Open(FileDialog),
+    Save(FileDialog),
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(
+        &event_loop,
+        "file dialog - O to open, S to save",
+        (480, 240),
+    );
+    window.show();
+
+    let mut icon: Option<Icon> = None;
+    let mut pending = None;
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for &event in &events {
+            match event {
+                Event::KeyboardInput {
+                    keysym,
+                    pressed: true,
+                    repeat: false,
+                    ..
+                } if pending.is_none() => {
+                    let dialog = if keysym == keysym::XK_o as xlib::KeySym {
+                        let options = FileDialogOptions {
+                            title: "Choose a window icon".into(),
+                            filters: vec![ppm_filter()],
+                            multiple: true,
+                            ..FileDialogOptions::default()
+                        };
+                        dialogs::open_file(&window, &options).map(Pending::Open)
+                    } else if keysym == keysym::XK_s as xlib::KeySym {
+                        let options = FileDialogOptions {
+                            title: "Save the window icon".into(),
+                            filters: vec![ppm_filter()],
+                            current_name: Some("icon.ppm".into()),
+                            ..FileDialogOptions::default()
+                        };
+                        dialogs::save_file(&window, &options).map(Pending::Save)
+                    } else {
+                        continue;
+                    };
+                    match dialog {
+                        Ok(dialog) => pending = Some(dialog),
+                        Err(error) => println!("{}, use a dialog of your own", error),
+                    }
+                }
+                Event::CloseRequested { .. } => break 'running,
+                _ => {}
+            }
+        }
+
+        let response = match &mut pending {
+            Some(Pending::Open(dialog)) | Some(Pending::Save(dialog)) => dialog.take_response(),
+            None => None,
+        };
+        if let Some(paths) = response {
+            match (pending.take(), paths) {
+                (_, None) => println!("cancelled"),
+                (Some(Pending::Open(_)), Some(paths)) => {
+                    match paths.iter().find_map(|path| Some((path, read_ppm(path)?))) {
+                        Some((path, read)) => {
+                            println!("icon from {}", path.display());
+                            window.set_window_icon(Some(&read));
+                            icon = Some(read);
+                        }
+                        None => println!("none of {:?} is a binary PPM", paths),
+                    }
+                }
+                (Some(Pending::Save(_)), Some(paths)) => match (paths.first(), &icon) {
+                    (Some(path), Some(icon)) => match write_ppm(path, icon) {
+                        Ok(()) => println!("saved to {}", path.display()),
+                        Err(error) => println!("failed to save to {}: {}", path.display(), error),
+                    },
+                    (_, None) => println!("no icon to save yet, open one first"),
+                    (None, _) => println!("no file chosen"),
+                },
+                (None, Some(_)) => {}
+            }
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,126 @@
+//! Hosts the window of another client in the right part of a window,
+//! laid out again as the window resizes. Without arguments an xterm is
+//! started and its terminal taken, or pass the id of any window, e.g.
+//! from xwininfo: `cargo run --example foreign_embed -- 0x1a00003`.
+//! Press f to type into the child, click the left part to take focus
+//! back, r to give the child back to the desktop
+use std::env;
+use std::fs;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use daswin::render::software::SoftwareSurface;
+use daswin::render::RenderSurface;
+use daswin::Rect;
+use x11::{keysym, xlib};
+
+const PANEL_WIDTH: u32 = 200;
+const MARGIN: u32 = 16;
+
+// Everything right of the panel, inside a margin
+fn child_rect(size: PhysicalSize<u32>) -> Rect {
+    Rect {
+        x: (PANEL_WIDTH + MARGIN) as i32,
+        y: MARGIN as i32,
+        width: size.width.saturating_sub(PANEL_WIDTH + 2 * MARGIN),
+        height: size.height.saturating_sub(2 * MARGIN),
+    }
+}
+
+fn parse_window(arg: &str) -> Option<xlib::Window> {
+    match arg.strip_prefix("0x") {
+        Some(hex) => xlib::Window::from_str_radix(hex, 16).ok(),
+        None => arg.parse().ok(),
+    }
+}
+
+// xterm tells the programs it runs its window in WINDOWID
+fn spawn_xterm() -> Option<xlib::Window> {
+    let path = env::temp_dir().join(format!("daswin-foreign-{}", std::process::id()));
+    let script = format!("echo $WINDOWID > {}; exec ${{SHELL:-sh}}", path.display());
+    Command::new("xterm")
+        .args(["-e", "sh", "-c", &script])
+        .spawn()
+        .ok()?;
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(5) {
+        if let Some(window) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|id| id.trim().parse().ok())
+        {
+            let _ = fs::remove_file(&path);
+            return Some(window);
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    None
+}
+
+fn main() {
+    let child = match env::args().nth(1) {
+        Some(arg) => parse_window(&arg).expect("expected a window id"),
+        None => spawn_xterm().expect("could not start xterm"),
+    };
+
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "foreign embed", (800, 500));
+    window.show();
+    let backend =
+        SoftwareSurface::new(&window, window.inner_size()).expect("unsupported window visual");
+    let mut surface = RenderSurface::new(window, backend);
+    let rect = child_rect(surface.size());
+    if let Err(err) = surface.window_mut().embed_foreign(child, rect) {
+        eprintln!("cannot embed {:#x}: {}", child, err);
+        return;
+    }
+
+    let mut events = Vec::new();
+    loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            surface.handle_event(event);
+            match *event {
+                Event::CloseRequested { .. } => return,
+                Event::Resized { size, .. } => {
+                    surface
+                        .window_mut()
+                        .set_foreign_rect(child, child_rect(size));
+                }
+                Event::KeyboardInput {
+                    keysym,
+                    pressed: true,
+                    ..
+                } => {
+                    if keysym == keysym::XK_f as xlib::KeySym {
+                        surface.window_mut().focus_foreign(Some(child));
+                    } else if keysym == keysym::XK_r as xlib::KeySym {
+                        surface.window_mut().remove_foreign(child);
+                    }
+                }
+                Event::MouseInput { pressed: true, .. } => {
+                    surface.window_mut().focus_foreign(None);
+                }
+                Event::EmbeddedChildDestroyed { child, .. } => {
+                    println!("{:#x} was destroyed", child);
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(mut frame) = surface.acquire() {
+            let width = frame.size().width as usize;
+            for (i, pixel) in frame.rgba_mut().chunks_exact_mut(4).enumerate() {
+                let color = if i % width < PANEL_WIDTH as usize {
+                    [60, 60, 72]
+                } else {
+                    [30, 30, 36]
+                };
+                pixel.copy_from_slice(&[color[0], color[1], color[2], 255]);
+            }
+            surface.present(frame);
+        }
+        thread::sleep(Duration::from_millis(16));
+    }
+}
@@ -0,0 +1,160 @@
+//! Compares input latency with 1, 2 or 3 frames in flight, press the
+//! number keys to switch, the average latency is shown in the title.
+//! The refresh rate of the window's monitor is logged whenever the
+//! monitor configuration changes. f toggles fullscreen and v vsync, the
+//! frame time deviation shows the effect of variable refresh
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use daswin::render::helpers::FullscreenShader;
+use daswin::render::pacing::FramePacer;
+use x11::keysym;
+
+// Enough work per pixel for frames to queue up on most GPUs
+const FRAGMENT: &str = r#"
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var v = in.uv;
+    for (var i = 0; i < 64; i = i + 1) {
+        v = fract(v * 1.7 + vec2<f32>(sin(globals.time + v.y), cos(v.x)));
+    }
+    return vec4<f32>(v, 0.5, 1.0);
+}
+"#;
+
+fn main() {
+    let mut size = PhysicalSize::new(800, 600);
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "frame pacing", size);
+    window.show();
+
+    let (gpu, mut window) = GpuContext::with_window(window);
+    let mut shader = FullscreenShader::new(&gpu.device, window.surface().format(), FRAGMENT);
+
+    let mut max_in_flight = 1;
+    let mut pacer = FramePacer::new(max_in_flight);
+
+    let start = Instant::now();
+    let mut second = Instant::now();
+    let (mut frames, mut total_latency) = (0, Duration::ZERO);
+    // Sums of frame times and their squares in seconds, for the deviation
+    let mut last_frame = Instant::now();
+    let (mut frame_time_sum, mut frame_time_squares) = (0.0, 0.0);
+    let mut events = Vec::new();
+    'running: loop {
+        pacer.begin_frame(&gpu.device).unwrap();
+
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            window.handle_event(&gpu, event);
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::Resized { size: new_size, .. } => size = new_size,
+                Event::MonitorConfigurationChanged => match window.window().current_monitor() {
+                    Some(monitor) => println!(
+                        "now on {} at {:?} mHz",
+                        monitor.name(),
+                        monitor.refresh_rate_millihertz()
+                    ),
+                    None => println!("window is off every monitor"),
+                },
+                Event::KeyboardInput {
+                    keysym,
+                    pressed: true,
+                    ..
+                } if (keysym::XK_1..=keysym::XK_3).contains(&(keysym as u32)) => {
+                    max_in_flight = keysym as u32 - keysym::XK_1 + 1;
+                    pacer.set_max_in_flight(max_in_flight);
+                }
+                Event::KeyboardInput {
+                    keysym,
+                    pressed: true,
+                    ..
+                } if keysym as u32 == keysym::XK_f => {
+                    let fullscreen = match window.window().fullscreen() {
+                        Some(_) => None,
+                        None => Some(Fullscreen::Borderless(None)),
+                    };
+                    window.window_mut().set_fullscreen(fullscreen);
+                }
+                Event::KeyboardInput {
+                    keysym,
+                    pressed: true,
+                    ..
+                } if keysym as u32 == keysym::XK_v => {
+                    let mode = match window.surface().present_mode() {
+                        wgpu::PresentMode::AutoVsync => wgpu::PresentMode::AutoNoVsync,
+                        _ => wgpu::PresentMode::AutoVsync,
+                    };
+                    window.surface_mut().set_present_mode(&gpu, mode);
+                }
+                _ => {}
+            }
+        }
+
+        let frame = match window.acquire(&gpu) {
+            Some(frame) => frame,
+            None => continue,
+        };
+        shader.rebuild(&gpu.device, window.surface().format());
+        shader.set_globals(&gpu.queue, size, start.elapsed().as_secs_f32());
+
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            shader.draw(&mut rpass);
+        }
+
+        let submission = gpu.queue.submit(Some(encoder.finish()));
+        pacer.end_frame(&gpu.queue, submission);
+        window.present(frame);
+
+        frames += 1;
+        total_latency += pacer.latency();
+        let frame_time = last_frame.elapsed().as_secs_f64();
+        last_frame = Instant::now();
+        frame_time_sum += frame_time;
+        frame_time_squares += frame_time * frame_time;
+        if second.elapsed() >= Duration::from_secs(1) {
+            let mean = frame_time_sum / frames as f64;
+            let deviation = (frame_time_squares / frames as f64 - mean * mean)
+                .max(0.0)
+                .sqrt();
+            let title = format!(
+                "frame pacing - {} in flight, {} fps, {:.2?} latency, {:.2} ms deviation, {:?}{}",
+                max_in_flight,
+                frames,
+                total_latency / frames,
+                deviation * 1000.0,
+                window.surface().present_mode(),
+                if window.window().is_compositor_bypassed() {
+                    ", unredirected"
+                } else {
+                    ""
+                }
+            );
+            window.window_mut().set_title(&title);
+            frames = 0;
+            total_latency = Duration::ZERO;
+            frame_time_sum = 0.0;
+            frame_time_squares = 0.0;
+            second = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Lists monitors and their video modes, then toggles fullscreen:
+//! b for borderless, e for exclusive at the lowest listed mode of the
+//! primary monitor, w to return to windowed
+use daswin::prelude::*;
+use x11::keysym;
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+
+    for monitor in event_loop.available_monitors() {
+        println!(
+            "{} at {:?}, {:?} @ {:?} mHz{}",
+            monitor.name(),
+            monitor.position(),
+            monitor.size(),
+            monitor.refresh_rate_millihertz(),
+            if monitor.is_primary() {
+                " (primary)"
+            } else {
+                ""
+            }
+        );
+        for mode in monitor.video_modes() {
+            println!(
+                "    {:?} @ {} mHz",
+                mode.size(),
+                mode.refresh_rate_millihertz()
+            );
+        }
+    }
+
+    let monitor = event_loop.primary_monitor();
+    let mut window = X11Window::new(&event_loop, "fullscreen", (640, 480));
+    window.set_exclusive_fullscreen_grab(true);
+    window.show();
+
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::KeyboardInput {
+                    keysym,
+                    pressed: true,
+                    ..
+                } => match keysym as u32 {
+                    keysym::XK_b => {
+                        window.set_fullscreen(Some(Fullscreen::Borderless(monitor.clone())))
+                    }
+                    keysym::XK_e => {
+                        let mode = monitor.as_ref().and_then(|m| {
+                            m.video_modes()
+                                .min_by_key(|mode| <(u32, u32)>::from(mode.size()))
+                        });
+                        window.set_fullscreen(mode.map(Fullscreen::Exclusive));
+                    }
+                    keysym::XK_w => window.set_fullscreen(None),
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
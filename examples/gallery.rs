@@ -0,0 +1,80 @@
+//! Cycles through the effects in examples/effects, or the directory
+//! given as argument, with the left and right arrow keys. Effects get
+//! the time, resolution and cursor position, are recompiled when their
+//! file is saved, and compile errors are shown over the frame while
+//! broken effects are skipped
+mod common;
+
+use std::path::PathBuf;
+
+use common::{run_example, Step};
+use daswin::prelude::*;
+use daswin::render::gallery::Gallery;
+use daswin::render::text::{DebugText, GLYPH_SIZE};
+use x11::keysym;
+
+fn main() {
+    let dir = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples/effects"));
+    let mut gallery: Option<Gallery> = None;
+    let mut text: Option<DebugText> = None;
+    let mut cursor = PhysicalPosition::default();
+    run_example("gallery", move |ctx, step| match step {
+        Step::Event(Event::KeyboardInput {
+            keysym, pressed, ..
+        }) if pressed => {
+            if let Some(gallery) = gallery.as_mut() {
+                match keysym as u32 {
+                    keysym::XK_Right => gallery.next(ctx.device()),
+                    keysym::XK_Left => gallery.previous(ctx.device()),
+                    _ => {}
+                }
+            }
+        }
+        Step::Event(Event::CursorMoved { position, .. }) => cursor = position,
+        Step::Event(_) => {}
+        Step::Draw(_) => {
+            let (device, queue) = (ctx.device(), ctx.queue());
+            let elapsed = ctx.stats().elapsed.as_secs_f32();
+            let frame = ctx.frame().unwrap();
+            let gallery = gallery.get_or_insert_with(|| {
+                Gallery::scan(&dir, frame.format)
+                    .unwrap_or_else(|err| panic!("cannot read {}: {}", dir.display(), err))
+            });
+            gallery.set_format(frame.format);
+            let text = text.get_or_insert_with(|| DebugText::new(device, queue, frame.format));
+            text.rebuild(device, frame.format);
+
+            let title = match gallery.name() {
+                Some(name) => format!(
+                    "{} ({}/{}), left and right switch",
+                    name,
+                    gallery.index() + 1,
+                    gallery.len()
+                ),
+                None => format!("no effects in {}", dir.display()),
+            };
+            text.queue_text(8.0, 8.0, wgpu::Color::WHITE, &title);
+            let mut y = 8.0 + 2.0 * GLYPH_SIZE.1 as f32;
+            for (name, error) in gallery.errors() {
+                let message = format!("{}: {}", name, error);
+                text.queue_text(8.0, y, wgpu::Color::RED, &message);
+                y += (message.lines().count() + 1) as f32 * GLYPH_SIZE.1 as f32;
+            }
+            text.prepare(device, queue, frame.size);
+
+            let shader = gallery.current(device);
+            if let Some(shader) = shader {
+                shader.set_globals(queue, frame.size, elapsed);
+                shader.set_cursor(queue, cursor);
+            }
+            let mut rpass = frame.pass(wgpu::Color::BLACK);
+            if let Some(shader) = shader {
+                shader.draw(&mut rpass);
+            }
+            text.draw(&mut rpass);
+        }
+    });
+}
@@ -0,0 +1,59 @@
+//! Shows and hides a window with Ctrl+Print while any application has
+//! focus, or with the chord given as argument:
+//! `cargo run --example global_hotkey -- Super+Shift+S`
+use std::env;
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use daswin::prelude::*;
+use daswin::Error;
+
+fn main() {
+    let keys = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "Ctrl+Print".to_owned());
+    let mut event_loop = EventLoop::new();
+    let hotkey = match event_loop.register_global_hotkey(&keys) {
+        Ok(hotkey) => hotkey,
+        Err(Error::AlreadyGrabbed) => {
+            eprintln!("{} is taken by the window manager or another client", keys);
+            process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("cannot use {}: {}", keys, err);
+            process::exit(1);
+        }
+    };
+    println!(
+        "press {} in any application to show or hide the window",
+        keys
+    );
+
+    let mut window = X11Window::new(&event_loop, "global hotkey", (320, 200));
+    window.show();
+    let mut shown = true;
+    let mut events = Vec::new();
+    loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::GlobalHotkey(id) if id == hotkey => {
+                    shown = !shown;
+                    if shown {
+                        window.show();
+                    } else {
+                        window.hide();
+                    }
+                    println!("{}", if shown { "shown" } else { "hidden" });
+                }
+                Event::CloseRequested { .. } => {
+                    event_loop.unregister_global_hotkey(hotkey);
+                    return;
+                }
+                _ => {}
+            }
+        }
+        thread::sleep(Duration::from_millis(16));
+    }
+}
@@ -0,0 +1,37 @@
+//! Prints the adapter a window would render with, or every adapter tried
+//! and why it failed. Pass `--backend vulkan` or `--backend gl` to allow
+//! only that backend, and set DASWIN_DEBUG=1 for each step
+use std::env;
+use std::process;
+
+use daswin::prelude::*;
+use daswin::render::{GpuContext, GpuOptions};
+
+fn main() {
+    let mut options = GpuOptions::default();
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let [flag, backend] = args.as_slice() {
+        options.require_backend = match (flag.as_str(), backend.as_str()) {
+            ("--backend", "vulkan") => Some(wgpu::Backend::Vulkan),
+            ("--backend", "gl") => Some(wgpu::Backend::Gl),
+            _ => None,
+        };
+    }
+    if !args.is_empty() && options.require_backend.is_none() {
+        eprintln!("usage: gpu_adapters [--backend vulkan|gl]");
+        process::exit(2);
+    }
+
+    let event_loop = EventLoop::new();
+    let window = X11Window::new(&event_loop, "gpu adapters", (320, 200));
+    match GpuContext::with_options(window, &options) {
+        Ok((gpu, _surface)) => {
+            let info = gpu.adapter.get_info();
+            println!("{:?} {} ({:?})", info.backend, info.name, info.device_type);
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Prefers a floating point surface format, so the window renders HDR on
+//! outputs which support it and SDR elsewhere. Moving the window to
+//! another monitor renegotiates the format, which is printed along with
+//! the monitor; the harness builds the pipeline for the new format
+mod common;
+
+use common::{run_example, Step};
+use daswin::prelude::*;
+use daswin::render::SurfaceOptions;
+
+const FORMATS: &[wgpu::TextureFormat] = &[
+    wgpu::TextureFormat::Rgba16Float,
+    wgpu::TextureFormat::Bgra8UnormSrgb,
+];
+
+fn main() {
+    let mut configured = false;
+    run_example("hdr", move |ctx, step| match step {
+        Step::Event(Event::AboutToWait) if !configured => {
+            configured = true;
+            let gpu = ctx.gpu();
+            let options = SurfaceOptions {
+                formats: FORMATS,
+                ..Default::default()
+            };
+            ctx.surface().set_options(gpu, options).unwrap();
+            println!("rendering to {:?}", ctx.surface().surface().format());
+        }
+        Step::Event(Event::MonitorChanged { monitor, .. }) => {
+            let name = ctx.event_loop().monitor_from_raw(monitor).map_or_else(
+                || "an unknown monitor".to_string(),
+                |m| m.name().to_string(),
+            );
+            println!(
+                "moved to {}, rendering to {:?}",
+                name,
+                ctx.surface().surface().format()
+            );
+        }
+        Step::Draw(_) => {
+            let frame = ctx.frame().unwrap();
+            let pipeline = frame.pipeline.clone();
+            // Brighter than SDR white where the format holds it
+            let mut rpass = frame.pass(wgpu::Color {
+                r: 2.0,
+                g: 2.0,
+                b: 2.0,
+                a: 1.0,
+            });
+            rpass.set_pipeline(&pipeline);
+            rpass.draw(0..3, 0..1);
+        }
+        _ => {}
+    });
+}
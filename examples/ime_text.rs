@@ -0,0 +1,74 @@
+//! A single line text field shown in the title bar. Composed text from
+//! the input method is inserted at the caret, whose rectangle is passed to
+//! set_ime_cursor_area so the candidate window follows it. Left and Right
+//! move the caret, BackSpace deletes, Tab toggles IME input
+use daswin::prelude::*;
+use x11::keysym;
+
+// Pretend monospace cell size of the text field
+const CELL: (i32, u32) = (8, 16);
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "|", (640, 120));
+    window.show();
+    // Applied once the input context exists
+    window.set_ime_cursor_area((16, 52).into(), (1, CELL.1).into());
+    let mut allowed = true;
+    window.set_ime_allowed(allowed);
+
+    let mut text: Vec<char> = Vec::new();
+    let mut caret = 0;
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        let mut changed = false;
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::Text { index, .. } => {
+                    if let Some(typed) = event_loop.text(index) {
+                        for c in typed.chars() {
+                            text.insert(caret, c);
+                            caret += 1;
+                        }
+                        changed = true;
+                    }
+                }
+                Event::KeyboardInput {
+                    keysym,
+                    pressed: true,
+                    ..
+                } => {
+                    match keysym as u32 {
+                        keysym::XK_Left => caret = caret.saturating_sub(1),
+                        keysym::XK_Right => caret = (caret + 1).min(text.len()),
+                        keysym::XK_BackSpace if caret > 0 => {
+                            caret -= 1;
+                            text.remove(caret);
+                        }
+                        keysym::XK_Tab => {
+                            allowed = !allowed;
+                            window.set_ime_allowed(allowed);
+                            println!("IME input {}", if allowed { "on" } else { "off" });
+                        }
+                        _ => continue,
+                    }
+                    changed = true;
+                }
+                _ => {}
+            }
+        }
+
+        if changed {
+            let mut title: String = text[..caret].iter().collect();
+            title.push('|');
+            title.extend(&text[caret..]);
+            window.set_title(&title);
+            let x = 16 + caret as i32 * CELL.0;
+            window.set_ime_cursor_area((x, 52).into(), (1, CELL.1).into());
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,33 @@
+//! Prints every event but the AboutToWait ending each poll with the time
+//! since the start, naming keys by their label in the current layout
+mod common;
+
+use common::{run_example, Step};
+use daswin::prelude::*;
+
+fn main() {
+    run_example("input logger", |ctx, step| match step {
+        Step::Event(event) => {
+            let elapsed = ctx.stats().elapsed.as_secs_f64();
+            match event {
+                Event::KeyboardInput {
+                    keycode, pressed, ..
+                } => {
+                    let label = ctx.event_loop().key_label(keycode);
+                    let action = if pressed { "pressed" } else { "released" };
+                    println!("{:10.3} key {} {}", elapsed, label, action);
+                }
+                Event::Text { index, .. } => {
+                    let text = ctx.event_loop().text(index).unwrap_or_default();
+                    println!("{:10.3} text {:?}", elapsed, text);
+                }
+                Event::AboutToWait => {}
+                event => println!("{:10.3} {:?}", elapsed, event),
+            }
+        }
+        Step::Draw(_) => {
+            let frame = ctx.frame().unwrap();
+            frame.pass(wgpu::Color::BLACK);
+        }
+    });
+}
@@ -0,0 +1,73 @@
+//! Prints typed text along with the time between key repeats. Hold a
+//! key, change the server's rate with e.g. `xset r rate 500 10` and hold
+//! it again: the cadence stays at 250 ms and 30 Hz. Press F1 to switch
+//! between daswin's repeats and the server's
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use daswin::KeyRepeat;
+use x11::{keysym, xlib};
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    event_loop.set_key_repeat(Some(KeyRepeat::default()));
+    let mut window = X11Window::new(&event_loop, "key repeat - press F1", (640, 480));
+    window.set_ime_allowed(true);
+    window.show();
+
+    let mut events = Vec::new();
+    // Time of the last press or repeat and the gaps between repeats
+    let mut last_press: Option<Instant> = None;
+    let mut gaps: Vec<Duration> = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::KeyboardInput {
+                    keysym,
+                    pressed: true,
+                    ..
+                } if keysym == keysym::XK_F1 as xlib::KeySym => {
+                    let repeat = match event_loop.key_repeat() {
+                        Some(_) => None,
+                        None => Some(KeyRepeat::default()),
+                    };
+                    event_loop.set_key_repeat(repeat);
+                    let source = if repeat.is_some() { "daswin" } else { "server" };
+                    println!("\nrepeating with the {} rate", source);
+                }
+                Event::KeyboardInput {
+                    pressed: true,
+                    repeat,
+                    ..
+                } => {
+                    let now = Instant::now();
+                    match last_press {
+                        Some(last) if repeat => gaps.push(now - last),
+                        _ => gaps.clear(),
+                    }
+                    last_press = Some(now);
+                }
+                Event::KeyboardInput { pressed: false, .. } if !gaps.is_empty() => {
+                    let first = gaps.remove(0);
+                    let mean = gaps.iter().sum::<Duration>() / gaps.len().max(1) as u32;
+                    println!(
+                        "\n{} repeats, first after {:?}, then every {:?}",
+                        gaps.len() + 1,
+                        first,
+                        mean
+                    );
+                    gaps.clear();
+                }
+                Event::Text { index, .. } => {
+                    print!("{}", event_loop.text(index).unwrap_or(""));
+                    let _ = io::stdout().flush();
+                }
+                _ => {}
+            }
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
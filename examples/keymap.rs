@@ -0,0 +1,78 @@
+//! Checks keysym and keycode lookups follow the active layout: Y and Z
+//! swap places between the us and de layouts. Switches layouts with
+//! setxkbmap and exits with 1 when a lookup is wrong, e.g. `xvfb-run
+//! cargo run --example keymap`
+use std::process::{self, Command};
+use std::thread;
+use std::time::Duration;
+
+use daswin::prelude::*;
+use x11::{keysym, xlib};
+
+fn check(ok: bool, what: &str) {
+    if ok {
+        println!("ok      {}", what);
+    } else {
+        println!("FAILED  {}", what);
+        process::exit(1);
+    }
+}
+
+// Switch layouts and poll until the mapping change arrived
+fn set_layout(event_loop: &mut EventLoop, layout: &str) {
+    let status = Command::new("setxkbmap")
+        .arg(layout)
+        .status()
+        .expect("setxkbmap is needed");
+    check(status.success(), &format!("setxkbmap {}", layout));
+    let mut events = Vec::new();
+    for _ in 0..10 {
+        event_loop.poll_events(&mut events);
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let y = keysym::XK_y as xlib::KeySym;
+    let z = keysym::XK_z as xlib::KeySym;
+
+    set_layout(&mut event_loop, "us");
+    let us_y = event_loop.keysym_to_keycode(y);
+    let us_z = event_loop.keysym_to_keycode(z);
+    println!("us: y on {:?}, z on {:?}", us_y, us_z);
+    check(
+        us_y.is_some() && us_z.is_some(),
+        "the us layout has y and z",
+    );
+    check(
+        event_loop.keycode_to_keysym(us_y.unwrap()) == Some(y),
+        "the keycode of y maps back to y",
+    );
+    check(
+        event_loop.key_label(us_z.unwrap()) == "Z",
+        "the key of z is labelled Z",
+    );
+
+    set_layout(&mut event_loop, "de");
+    check(
+        event_loop.keysym_to_keycode(y) == us_z && event_loop.keysym_to_keycode(z) == us_y,
+        "y and z swapped places in the de layout",
+    );
+    check(
+        event_loop.keycode_to_keysym(us_y.unwrap()) == Some(z),
+        "the key of y in us types z in de",
+    );
+
+    check(
+        event_loop
+            .keysym_to_keycode(keysym::XK_kana_A as xlib::KeySym)
+            .is_none(),
+        "a keysym without a key gives None",
+    );
+    check(
+        event_loop.keycode_to_keysym(300).is_none(),
+        "a keycode past the range gives None",
+    );
+    set_layout(&mut event_loop, "us");
+}
@@ -0,0 +1,55 @@
+//! Covers the primary monitor and holds the keyboard and pointer grabs,
+//! so Alt+Tab and other window manager shortcuts do nothing. Ctrl+Alt+
+//! Escape releases both grabs, q quits once they are released
+use daswin::prelude::*;
+use x11::{keysym, xlib};
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let monitor = event_loop.primary_monitor();
+    let mut window = X11Window::new(&event_loop, "kiosk", (640, 480));
+    window.show();
+    window.set_fullscreen(Some(Fullscreen::Borderless(monitor)));
+
+    let (mut grabbed, mut released) = (false, false);
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } if !grabbed => break 'running,
+                // Grabbing fails until the window is mapped
+                Event::Focused { focused: true, .. } if !grabbed && !released => {
+                    let grabs = window
+                        .set_keyboard_grab(true)
+                        .and_then(|()| window.set_pointer_grab(true));
+                    match grabs {
+                        Ok(()) => {
+                            grabbed = true;
+                            println!("grabbed, press Ctrl+Alt+Escape to release");
+                        }
+                        Err(err) => println!("grab failed: {}", err),
+                    }
+                }
+                Event::KeyboardInput {
+                    keysym,
+                    pressed: true,
+                    ..
+                } => {
+                    // The escape chord releases the grabs before the key
+                    // press is delivered
+                    if grabbed && !window.has_keyboard_grab() {
+                        grabbed = false;
+                        released = true;
+                        println!("released, q quits");
+                    } else if keysym == keysym::XK_q as xlib::KeySym && !grabbed {
+                        break 'running;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,142 @@
+//! Checks the ordering of late input: a key press and release sent
+//! around a close request after the batch was read come before the
+//! redraw in poll_late_input, the close request waits for the next poll.
+//! Exits with 1 when the order is wrong, e.g. `xvfb-run cargo run
+//! --example late_latch`
+use std::mem;
+use std::process;
+use std::ptr;
+use std::thread;
+use std::time::Duration;
+
+use daswin::prelude::*;
+use x11::{keysym, xlib};
+
+fn check(ok: bool, what: &str) {
+    if ok {
+        println!("ok      {}", what);
+    } else {
+        println!("FAILED  {}", what);
+        process::exit(1);
+    }
+}
+
+unsafe fn send_key(display: *mut xlib::Display, window: xlib::Window, pressed: bool) {
+    let root = xlib::XDefaultRootWindow(display);
+    let mut xevent: xlib::XEvent = mem::zeroed();
+    xevent.key = xlib::XKeyEvent {
+        type_: if pressed {
+            xlib::KeyPress
+        } else {
+            xlib::KeyRelease
+        },
+        serial: 0,
+        send_event: xlib::True,
+        display,
+        window,
+        root,
+        subwindow: 0,
+        time: xlib::CurrentTime,
+        x: 0,
+        y: 0,
+        x_root: 0,
+        y_root: 0,
+        state: 0,
+        keycode: xlib::XKeysymToKeycode(display, keysym::XK_a as xlib::KeySym) as u32,
+        same_screen: xlib::True,
+    };
+    let mask = if pressed {
+        xlib::KeyPressMask
+    } else {
+        xlib::KeyReleaseMask
+    };
+    xlib::XSendEvent(display, window, xlib::True, mask, &mut xevent);
+}
+
+unsafe fn send_close(display: *mut xlib::Display, window: xlib::Window) {
+    let intern = |name: &[u8]| xlib::XInternAtom(display, name.as_ptr() as *const _, 0);
+    let mut xevent: xlib::XEvent = mem::zeroed();
+    xevent.client_message.type_ = xlib::ClientMessage;
+    xevent.client_message.window = window;
+    xevent.client_message.message_type = intern(b"WM_PROTOCOLS\0");
+    xevent.client_message.format = 32;
+    xevent
+        .client_message
+        .data
+        .set_long(0, intern(b"WM_DELETE_WINDOW\0") as _);
+    xlib::XSendEvent(display, window, xlib::False, 0, &mut xevent);
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "late latch", (320, 200));
+    window.show();
+    let id = window.id();
+    let mut events = Vec::new();
+    for _ in 0..20 {
+        event_loop.poll_events(&mut events);
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let mut late = Vec::new();
+    event_loop.poll_late_input(&mut late);
+    check(late.is_empty(), "nothing is latched without a redraw");
+    window.request_redraw();
+    event_loop.poll_late_input(&mut late);
+    check(
+        late == [Event::RedrawRequested { window: id }],
+        "the redraw comes alone without input",
+    );
+
+    // Another client plays the input arriving while the batch is handled
+    let other = unsafe { xlib::XOpenDisplay(ptr::null()) };
+    check(!other.is_null(), "a second connection opens");
+    unsafe {
+        send_key(other, window.window, true);
+        send_close(other, window.window);
+        send_key(other, window.window, false);
+        xlib::XSync(other, xlib::False);
+    }
+    window.request_redraw();
+    event_loop.poll_late_input(&mut late);
+    println!("late batch: {:?}", late);
+    check(
+        late.first() == Some(&Event::LateInput),
+        "LateInput starts the batch",
+    );
+    let keys: Vec<bool> = late
+        .iter()
+        .filter_map(|event| match *event {
+            Event::KeyboardInput { pressed, .. } => Some(pressed),
+            _ => None,
+        })
+        .collect();
+    check(keys == [true, false], "the key press precedes its release");
+    check(
+        !late
+            .iter()
+            .any(|event| matches!(event, Event::CloseRequested { .. })),
+        "the close request is not latched",
+    );
+    check(
+        late.last() == Some(&Event::RedrawRequested { window: id }),
+        "the redraw ends the batch",
+    );
+    check(
+        !late.contains(&Event::AboutToWait),
+        "the batch has no AboutToWait",
+    );
+
+    event_loop.poll_events(&mut events);
+    check(
+        events.contains(&Event::CloseRequested { window: id }),
+        "the close request comes with the next poll",
+    );
+    check(
+        !events
+            .iter()
+            .any(|event| matches!(event, Event::KeyboardInput { .. })),
+        "the latched keys are not delivered again",
+    );
+    unsafe { xlib::XCloseDisplay(other) };
+}
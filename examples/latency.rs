@@ -0,0 +1,65 @@
+//! Measures the latency from clicks to the first frame showing them.
+//! Every click flips the background, the percentiles of the latency are
+//! printed once a second along with how far off they may be. Needs a
+//! driver presenting through the Present extension, like Mesa's.
+//!
+//! With `-- --late-latch` clicks arriving while the loop waits for the
+//! next image are drawn in that frame rather than the next, compare the
+//! p50 of both runs
+mod common;
+
+use std::env;
+use std::time::Duration;
+
+use common::{run_example_with, Step};
+use daswin::prelude::*;
+
+fn main() {
+    let late_latch = env::args().any(|arg| arg == "--late-latch");
+    let mut light = false;
+    let mut last_report = Duration::ZERO;
+    let title = if late_latch {
+        "latency, late latch"
+    } else {
+        "latency"
+    };
+    let setup = move |event_loop: &mut EventLoop| event_loop.set_late_latch(late_latch);
+    run_example_with(title, setup, move |ctx, step| match step {
+        Step::Event(Event::MouseInput { pressed: true, .. }) => {
+            light = !light;
+            if let Some(time) = ctx.event_loop().last_input_time() {
+                ctx.stats_mut().mark_input(time);
+            }
+        }
+        Step::Event(_) => {}
+        Step::Draw(_) => {
+            // Late input is only read for frames about to be drawn
+            ctx.window().request_redraw();
+            let stats = ctx.stats();
+            if stats.elapsed - last_report >= Duration::from_secs(1) {
+                last_report = stats.elapsed;
+                if let (Some(p50), Some(p99), Some(uncertainty)) = (
+                    stats.latency_percentile(50.0),
+                    stats.latency_percentile(99.0),
+                    stats.latency_uncertainty(),
+                ) {
+                    println!(
+                        "{} clicks: p50 {:.1} ms, p99 {:.1} ms, ±{:.1} ms",
+                        stats.latency_samples().count(),
+                        p50.as_secs_f64() * 1000.0,
+                        p99.as_secs_f64() * 1000.0,
+                        uncertainty.as_secs_f64() * 1000.0
+                    );
+                }
+            }
+
+            let shade = if light { 0.9 } else { 0.1 };
+            ctx.frame().unwrap().pass(wgpu::Color {
+                r: shade,
+                g: shade,
+                b: shade,
+                a: 1.0,
+            });
+        }
+    });
+}
@@ -0,0 +1,158 @@
+//! A parent window with a modal settings dialog. S in the parent opens
+//! the dialog centered over it, Escape in the dialog closes it. While it
+//! is open clicks on the parent flash the dialog, and M in the dialog
+//! minimizes the parent, which takes the dialog along.
+//!
+//! With `--check` the dialog opens right away, then focusing the parent
+//! must pass focus on to the dialog and minimizing the parent must hide
+//! the dialog. Exits with 1 otherwise, e.g. `cargo run --example
+//! modal_dialog -- --check`. Both need a window manager
+use std::env;
+use std::mem;
+use std::process;
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use x11::{keysym, xlib};
+
+const TITLE: &str = "settings - Escape closes, m minimizes the parent";
+
+fn check(ok: bool, what: &str) {
+    if ok {
+        println!("ok      {}", what);
+    } else {
+        println!("FAILED  {}", what);
+        process::exit(1);
+    }
+}
+
+fn open_dialog(event_loop: &EventLoop, parent: &X11Window) -> X11Window {
+    let mut dialog = WindowBuilder::new()
+        .with_title(TITLE)
+        .with_inner_size((360, 240))
+        .with_owner(parent)
+        .build(event_loop);
+    dialog.set_modal(true);
+    dialog.show();
+    dialog
+}
+
+fn minimize(window: &X11Window) {
+    unsafe {
+        xlib::XIconifyWindow(window.display, window.window, window.screen);
+        xlib::XFlush(window.display);
+    }
+}
+
+fn is_mapped(window: &X11Window) -> bool {
+    unsafe {
+        let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+        xlib::XGetWindowAttributes(window.display, window.window, &mut attributes);
+        attributes.map_state != xlib::IsUnmapped
+    }
+}
+
+/// Poll for up to two seconds until `done` holds for an event, or for
+/// the windows when `done` is given none
+fn poll_until(
+    event_loop: &mut EventLoop,
+    events: &mut Vec<Event>,
+    mut done: impl FnMut(Option<&Event>) -> bool,
+) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(2);
+    while Instant::now() < deadline {
+        event_loop.poll_events(events);
+        if events.iter().any(|event| done(Some(event))) || done(None) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
+fn gains_focus(event: Option<&Event>, id: WindowId) -> bool {
+    matches!(event, Some(&Event::Focused { window, focused: true }) if window == id)
+}
+
+fn run_check(mut event_loop: EventLoop, mut parent: X11Window) {
+    let mut events = Vec::new();
+    let parent_id = parent.id();
+    poll_until(&mut event_loop, &mut events, |event| {
+        gains_focus(event, parent_id)
+    });
+    let dialog = open_dialog(&event_loop, &parent);
+    let dialog_id = dialog.id();
+    check(dialog.is_modal(), "the dialog is modal");
+    let focused = |event: Option<&Event>| gains_focus(event, dialog_id);
+    check(
+        poll_until(&mut event_loop, &mut events, focused),
+        "the dialog takes focus when shown",
+    );
+
+    parent.focus();
+    check(
+        poll_until(&mut event_loop, &mut events, focused),
+        "focusing the parent passes focus on to the dialog",
+    );
+
+    minimize(&parent);
+    check(
+        poll_until(&mut event_loop, &mut events, |_| !is_mapped(&dialog)),
+        "minimizing the parent hides the dialog",
+    );
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    event_loop.set_modal_input_blocking(true);
+    let mut parent = X11Window::new(&event_loop, "parent - press s for the settings", (640, 480));
+    parent.show();
+    if env::args().any(|arg| arg == "--check") {
+        return run_check(event_loop, parent);
+    }
+
+    let mut dialog: Option<X11Window> = None;
+    // When the flashed title of the dialog is restored
+    let mut flashing: Option<Instant> = None;
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for &event in &events {
+            let in_dialog = |window| dialog.as_ref().map(X11Window::id) == Some(window);
+            match event {
+                Event::CloseRequested { window } if in_dialog(window) => dialog = None,
+                Event::CloseRequested { .. } => break 'running,
+                Event::KeyboardInput {
+                    window,
+                    keysym,
+                    pressed: true,
+                    ..
+                } => match keysym as u32 {
+                    // Blocked while the dialog is open
+                    keysym::XK_s if dialog.is_none() => {
+                        dialog = Some(open_dialog(&event_loop, &parent))
+                    }
+                    keysym::XK_Escape if in_dialog(window) => dialog = None,
+                    keysym::XK_m if in_dialog(window) => minimize(&parent),
+                    _ => {}
+                },
+                Event::ModalBlocked { .. } => {
+                    if let Some(dialog) = &mut dialog {
+                        println!("the parent was clicked while the settings are open");
+                        dialog.set_title(&format!(">> {} <<", TITLE));
+                        dialog.focus();
+                        flashing = Some(Instant::now() + Duration::from_millis(400));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if matches!(flashing, Some(until) if Instant::now() >= until) {
+            if let Some(dialog) = &mut dialog {
+                dialog.set_title(TITLE);
+            }
+            flashing = None;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
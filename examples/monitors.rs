@@ -0,0 +1,25 @@
+//! Lists the connected monitors with their output and EDID names,
+//! physical size, DPI and scale factor
+use daswin::prelude::*;
+
+fn main() {
+    let event_loop = EventLoop::new();
+    for monitor in event_loop.available_monitors() {
+        let (width, height) = monitor.size().into();
+        let (x, y) = monitor.position().into();
+        println!(
+            "{} \"{}\"{}",
+            monitor.name(),
+            monitor.friendly_name(),
+            if monitor.is_primary() { " primary" } else { "" }
+        );
+        println!("  {}x{} at {},{}", width, height, x, y);
+        match (monitor.physical_size_mm(), monitor.dpi()) {
+            (Some((width_mm, height_mm)), Some(dpi)) => {
+                println!("  {}x{} mm, {:.0} dpi", width_mm, height_mm, dpi)
+            }
+            _ => println!("  physical size unknown"),
+        }
+        println!("  scale factor {}", monitor.scale_factor());
+    }
+}
@@ -0,0 +1,98 @@
+//! Draws a square under every master pointer, filled while a button is
+//! held on it, and logs which pointer each event came from. Add a second
+//! seat to try it, e.g. under Xvfb or on the desktop:
+//! `xinput create-master second`, then `xinput reattach <mouse> "second pointer"`
+//! with the id of a mouse from `xinput list`. `xinput remove-master
+//! "second pointer"` undoes it
+use std::thread;
+use std::time::Duration;
+
+use daswin::prelude::*;
+use daswin::render::software::SoftwareSurface;
+use daswin::render::RenderSurface;
+
+const COLORS: [[u8; 3]; 4] = [
+    [230, 80, 80],
+    [80, 160, 255],
+    [90, 200, 110],
+    [240, 190, 60],
+];
+const SQUARE: i32 = 12;
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    event_loop.catch_exit_signals();
+    let mut window = X11Window::new(&event_loop, "multi seat", (640, 480));
+    window.show();
+    let backend =
+        SoftwareSurface::new(&window, window.inner_size()).expect("unsupported window visual");
+    let mut surface = RenderSurface::new(window, backend);
+    println!(
+        "core pointer {:?}, master pointers {:?}",
+        event_loop.core_pointer(),
+        event_loop.master_pointers()
+    );
+
+    let mut events = Vec::new();
+    loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            surface.handle_event(event);
+            match *event {
+                Event::MouseInput {
+                    button,
+                    pressed,
+                    position,
+                    device,
+                    ..
+                } => println!(
+                    "{:?}: button {} {} at {:.0}, {:.0}",
+                    device,
+                    button,
+                    if pressed { "pressed" } else { "released" },
+                    position.x,
+                    position.y
+                ),
+                Event::DeviceAdded { .. } | Event::DeviceRemoved { .. } => {
+                    println!("master pointers now {:?}", event_loop.master_pointers());
+                }
+                Event::CloseRequested { .. } | Event::ExitRequested => return,
+                _ => {}
+            }
+        }
+
+        let input = event_loop.input_snapshot();
+        let pointers = event_loop.master_pointers();
+        if let Some(mut frame) = surface.acquire() {
+            let size = frame.size();
+            let rgba = frame.rgba_mut();
+            for pixel in rgba.chunks_exact_mut(4) {
+                pixel.copy_from_slice(&[40, 40, 48, 255]);
+            }
+            for (i, &device) in pointers.iter().enumerate() {
+                let position = match input.cursor_of(device) {
+                    Some((_, position)) => position,
+                    None => continue,
+                };
+                let held = input.is_button_down_on(device, 1);
+                let color = COLORS[i % COLORS.len()];
+                for dy in -SQUARE..=SQUARE {
+                    for dx in -SQUARE..=SQUARE {
+                        let edge = dx.abs() == SQUARE || dy.abs() == SQUARE;
+                        if !held && !edge {
+                            continue;
+                        }
+                        let (x, y) = (position.x as i32 + dx, position.y as i32 + dy);
+                        if x < 0 || y < 0 || x >= size.width as i32 || y >= size.height as i32 {
+                            continue;
+                        }
+                        let start = (y as usize * size.width as usize + x as usize) * 4;
+                        rgba[start..start + 3].copy_from_slice(&color);
+                    }
+                }
+            }
+            surface.present(frame);
+        }
+        thread::sleep(Duration::from_millis(16));
+    }
+}
@@ -0,0 +1,45 @@
+//! Opens a window per press of n, each cleared with its own color. Closing
+//! the first window exits, closing another only closes it
+mod common;
+
+use std::collections::HashMap;
+
+use common::{run_example, Step};
+use daswin::prelude::*;
+use x11::{keysym, xlib};
+
+fn main() {
+    let mut colors = HashMap::new();
+    run_example("multi window - press n", move |ctx, step| match step {
+        Step::Event(Event::KeyboardInput {
+            keysym,
+            pressed: true,
+            ..
+        }) if keysym == keysym::XK_n as xlib::KeySym => {
+            let count = ctx.windows().len();
+            let id = ctx.open_window(&format!("window {}", count + 1), 320, 240);
+            let hue = count as f64 * 0.3;
+            colors.insert(
+                id,
+                wgpu::Color {
+                    r: 0.5 + 0.5 * hue.sin(),
+                    g: 0.5 + 0.5 * (hue + 2.0).sin(),
+                    b: 0.5 + 0.5 * (hue + 4.0).sin(),
+                    a: 1.0,
+                },
+            );
+        }
+        Step::Event(Event::Destroyed { window }) => {
+            colors.remove(&window);
+        }
+        Step::Event(_) => {}
+        Step::Draw(window) => {
+            let color = colors.get(&window).copied().unwrap_or(wgpu::Color::BLACK);
+            let frame = ctx.frame().unwrap();
+            let pipeline = frame.pipeline.clone();
+            let mut pass = frame.pass(color);
+            pass.set_pipeline(&pipeline);
+            pass.draw(0..3, 0..1);
+        }
+    });
+}
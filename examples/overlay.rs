@@ -0,0 +1,114 @@
+//! A click-through HUD shaped like a plus sign, showing the frame rate in
+//! its title and flashing red when a frame misses its 100 ms deadline.
+//! Clicks anywhere on it reach the window below, nothing is printed while
+//! clicking on it
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use daswin::Rect;
+
+fn main() {
+    let (width, height) = (240, 240);
+    let mut event_loop = EventLoop::new();
+    event_loop.set_frame_deadline(Some(Duration::from_millis(100)));
+    let mut window = X11Window::new(&event_loop, "overlay", (width, height));
+
+    let third = width / 3;
+    let plus = [
+        Rect {
+            x: third as i32,
+            y: 0,
+            width: third,
+            height,
+        },
+        Rect {
+            x: 0,
+            y: third as i32,
+            width,
+            height: third,
+        },
+    ];
+    window
+        .set_shape_region(Some(&plus))
+        .expect("shaped windows are not supported");
+    window
+        .set_input_region(Some(&[]))
+        .expect("input shapes are not supported");
+    window.show();
+
+    let (gpu, mut window) = GpuContext::with_window(window);
+
+    let start = Instant::now();
+    let mut second = Instant::now();
+    let mut frames = 0;
+    let mut missed_at: Option<Instant> = None;
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            window.handle_event(&gpu, event);
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::MouseInput { .. } => println!("overlay intercepted a click"),
+                Event::FrameDeadlineMissed {
+                    elapsed,
+                    budget,
+                    translation,
+                } => {
+                    println!(
+                        "frame took {:?} of {:?}, {:?} translating events",
+                        elapsed, budget, translation
+                    );
+                    missed_at = Some(Instant::now());
+                }
+                _ => {}
+            }
+        }
+
+        let frame = match window.acquire(&gpu) {
+            Some(frame) => frame,
+            None => continue,
+        };
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let t = start.elapsed().as_secs_f64();
+        let flash = missed_at.is_some_and(|at| at.elapsed() < Duration::from_millis(250));
+        let color = if flash {
+            wgpu::Color::RED
+        } else {
+            wgpu::Color {
+                r: 0.2,
+                g: t.sin() * 0.4 + 0.6,
+                b: 0.2,
+                a: 1.0,
+            }
+        };
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(color),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        gpu.queue.submit(Some(encoder.finish()));
+        window.present(frame);
+
+        frames += 1;
+        if second.elapsed() >= Duration::from_secs(1) {
+            window
+                .window_mut()
+                .set_title(&format!("overlay - {} fps", frames));
+            frames = 0;
+            second = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Renders until a key is pressed, then panics in the event handler.
+//! The window is gone before the process exits, check with
+//! `xdotool search --name "press a key to panic"` once it did.
+//!
+//! Pass `abort` or `exit` to try the other panic policies, only abort
+//! leaves the window to the server
+use std::env;
+
+use daswin::prelude::*;
+use daswin::PanicPolicy;
+
+fn main() {
+    let policy = match env::args().nth(1).as_deref() {
+        Some("abort") => PanicPolicy::Abort,
+        Some("exit") => PanicPolicy::ConvertToExit(3),
+        _ => PanicPolicy::CleanupThenResume,
+    };
+    let mut event_loop = EventLoop::new();
+    event_loop.set_panic_policy(policy);
+    let window = X11Window::new(&event_loop, "press a key to panic", (480, 240));
+    let (gpu, mut surface) = GpuContext::with_window(window);
+    surface.window_mut().show();
+    surface.window_mut().request_redraw();
+    println!("{:?}, press a key in the window", policy);
+
+    let code = event_loop.run(move |_, event| {
+        surface.handle_event(&gpu, &event);
+        match event {
+            Event::KeyboardInput { pressed: true, .. } => panic!("key pressed"),
+            Event::Resized { .. } => surface.window_mut().request_redraw(),
+            // Requested again after every frame, presenting paces it
+            Event::RedrawRequested { .. } => {
+                let frame = match surface.surface_mut().acquire(&gpu) {
+                    Some(frame) => frame,
+                    None => return,
+                };
+                let view = frame
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                let mut encoder = gpu
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLUE),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                gpu.queue.submit(Some(encoder.finish()));
+                surface.present(frame);
+                surface.window_mut().request_redraw();
+            }
+            _ => {}
+        }
+    });
+    println!("run returned {}", code);
+    std::process::exit(code);
+}
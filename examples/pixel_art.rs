@@ -0,0 +1,128 @@
+//! A 320x180 canvas with a one pixel checkerboard and a border, scaled by
+//! whole pixels at every window size. Press p to cycle through the other
+//! aspect policies. Clicking prints the canvas pixel under the cursor, or
+//! that the click landed in the black bars
+use std::num::NonZeroU32;
+
+use daswin::prelude::*;
+use daswin::render::pixel::PixelPerfectPresenter;
+use daswin::render::AspectPolicy;
+use x11::{keysym, xlib};
+
+const CANVAS: (u32, u32) = (320, 180);
+
+const POLICIES: [AspectPolicy; 5] = [
+    AspectPolicy::IntegerScale,
+    AspectPolicy::Contain,
+    AspectPolicy::Cover,
+    AspectPolicy::FixedHeight,
+    AspectPolicy::Stretch,
+];
+
+fn canvas_pixels() -> Vec<u8> {
+    let (width, height) = CANVAS;
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let border = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+            data.extend_from_slice(if border {
+                &[220, 40, 40, 255]
+            } else if (x + y) % 2 == 0 {
+                &[255, 255, 255, 255]
+            } else {
+                &[20, 20, 20, 255]
+            });
+        }
+    }
+    data
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "pixel art - resize me, press p", (960, 540));
+    window.show();
+
+    let (gpu, surface) = GpuContext::with_window(window);
+    let mut surface = surface.into_render_surface(&gpu);
+    let mut policy = 0;
+    surface.set_canvas(CANVAS.into(), POLICIES[policy]);
+
+    let mut presenter = PixelPerfectPresenter::new(
+        &gpu.device,
+        CANVAS.into(),
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+        surface.backend().surface.format(),
+    );
+    gpu.queue.write_texture(
+        presenter.canvas().as_image_copy(),
+        &canvas_pixels(),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(CANVAS.0 * 4),
+            rows_per_image: None,
+        },
+        wgpu::Extent3d {
+            width: CANVAS.0,
+            height: CANVAS.1,
+            depth_or_array_layers: 1,
+        },
+    );
+
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            surface.handle_event(event);
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::CanvasViewportChanged { viewport, .. } => {
+                    let placement = *surface.canvas().unwrap();
+                    presenter.set_placement(placement);
+                    let (scale, _) = placement.scale();
+                    println!(
+                        "{:?}: {}x{} at {},{}, scale {:.2}",
+                        placement.policy(),
+                        viewport.width,
+                        viewport.height,
+                        viewport.x,
+                        viewport.y,
+                        scale
+                    );
+                }
+                Event::KeyboardInput {
+                    keysym,
+                    pressed: true,
+                    ..
+                } if keysym == keysym::XK_p as xlib::KeySym => {
+                    policy = (policy + 1) % POLICIES.len();
+                    surface.set_aspect_policy(POLICIES[policy]);
+                }
+                Event::MouseInput {
+                    pressed: true,
+                    position,
+                    ..
+                } => match presenter.window_to_canvas_coords(position) {
+                    Some((x, y)) => println!("canvas pixel {},{}", x as u32, y as u32),
+                    None => println!("letterbox"),
+                },
+                _ => {}
+            }
+        }
+
+        let frame = match surface.acquire() {
+            Some(frame) => frame,
+            None => continue,
+        };
+        presenter.rebuild(&gpu.device, surface.backend().surface.format());
+
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        presenter.present(&mut encoder, &view);
+        gpu.queue.submit(Some(encoder.finish()));
+        surface.present(frame);
+    }
+}
@@ -0,0 +1,49 @@
+//! Opens a window centered on the primary monitor and a dialog centered
+//! on it. Pressing m in the main window centers it on the next monitor
+use daswin::prelude::*;
+use x11::{keysym, xlib};
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = WindowBuilder::new()
+        .with_title("placement - press m")
+        .with_inner_size((640, 480))
+        .with_position(Position::Centered)
+        .build(&event_loop);
+    window.show();
+
+    let mut dialog = WindowBuilder::new()
+        .with_title("dialog")
+        .with_inner_size((240, 120))
+        .with_transient_for(&window)
+        .with_position(Position::CenteredOnParent)
+        .build(&event_loop);
+    dialog.show();
+
+    let mut next = 0;
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::CloseRequested { window: id } if id == window.id() => break 'running,
+                Event::KeyboardInput {
+                    window: id,
+                    keysym,
+                    pressed: true,
+                    ..
+                } if id == window.id() && keysym == keysym::XK_m as xlib::KeySym => {
+                    let monitors = event_loop.available_monitors();
+                    next = (next + 1) % monitors.len().max(1);
+                    if let Some(monitor) = monitors.get(next) {
+                        println!("centering on {}", monitor.name());
+                        window.center_on(monitor);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,150 @@
+//! Opens a popup on keypress or right click which takes keyboard focus
+//! while open, press Escape in the popup to close it and return focus to
+//! the parent. The top left corner of the popup contents opens under the
+//! pointer, which is checked once it is shown, also on monitor layouts
+//! whose primary monitor is not at the origin
+use daswin::prelude::*;
+use daswin::{MonitorHandle, Rect};
+use x11::{keysym, xlib};
+
+// The monitor containing a root position
+fn monitor_at(event_loop: &EventLoop, x: i32, y: i32) -> Option<MonitorHandle> {
+    event_loop.available_monitors().into_iter().find(|monitor| {
+        let (position, size) = (monitor.position(), monitor.size());
+        let rect = Rect {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        };
+        rect.contains(x, y)
+    })
+}
+
+fn open_popup(
+    event_loop: &EventLoop,
+    parent: &X11Window,
+    cursor: PhysicalPosition<f64>,
+) -> X11Window {
+    let root = parent.window_to_root(cursor.x, cursor.y);
+    let at = PhysicalPosition::new(root.x.round() as i32, root.y.round() as i32);
+    println!(
+        "cursor at {:.0},{:.0} in parent, {},{} on the root",
+        cursor.x, cursor.y, at.x, at.y
+    );
+    if let Some(monitor) = monitor_at(event_loop, at.x, at.y) {
+        let local = parent.window_to_monitor(cursor.x, cursor.y, &monitor);
+        println!(
+            "  {:.0},{:.0} on monitor {}",
+            local.x,
+            local.y,
+            monitor.name()
+        );
+    }
+    let mut window = WindowBuilder::new()
+        .with_title("popup - press Escape to close")
+        .with_inner_size((320, 120))
+        .with_position(Position::At(at))
+        .build(event_loop);
+    window.show();
+    window
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+
+    let mut parent = X11Window::new(
+        &event_loop,
+        "parent - press p or right click for a popup",
+        (640, 480),
+    );
+    parent.show();
+
+    let mut tooltip = WindowBuilder::new()
+        .with_title("tooltip - never takes focus")
+        .with_inner_size((240, 40))
+        .with_focusable(false)
+        .build(&event_loop);
+    tooltip.show();
+
+    // The popup along with where its contents should be on the root
+    let mut popup: Option<(X11Window, PhysicalPosition<f64>)> = None;
+    let mut cursor = None;
+    let mut events = Vec::new();
+
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for &event in &events {
+            match event {
+                Event::CloseRequested { window } if window == parent.id() => break 'running,
+                Event::CloseRequested { .. } => {
+                    popup = None;
+                    parent.focus();
+                }
+                Event::CursorMoved {
+                    window, position, ..
+                } if window == parent.id() => cursor = Some(position),
+                Event::MouseInput {
+                    window,
+                    button: 3,
+                    pressed: true,
+                    position,
+                    root_position,
+                    ..
+                } if window == parent.id() => {
+                    let window = open_popup(&event_loop, &parent, position);
+                    popup = Some((window, root_position));
+                }
+                Event::KeyboardInput {
+                    window,
+                    keysym,
+                    pressed: true,
+                    ..
+                } => {
+                    assert_ne!(window, tooltip.id(), "tooltip received keyboard input");
+                    let is_popup = popup.as_ref().map(|(p, _)| p.id()) == Some(window);
+                    println!(
+                        "{}: {:?}",
+                        if is_popup { "popup" } else { "parent" },
+                        char::from_u32(keysym as u32)
+                    );
+
+                    if is_popup && keysym == keysym::XK_Escape as xlib::KeySym {
+                        popup = None;
+                        parent.focus();
+                    } else if !is_popup && keysym == keysym::XK_p as xlib::KeySym {
+                        // Before the first motion the server is asked
+                        let cursor = cursor.or_else(|| parent.cursor_position());
+                        if let Some(cursor) = cursor {
+                            let target = parent.window_to_root(cursor.x, cursor.y);
+                            let window = open_popup(&event_loop, &parent, cursor);
+                            popup = Some((window, target));
+                        }
+                    }
+                }
+                // The popup can only take focus once it is viewable, by
+                // then the window manager placed it
+                Event::Resized { window, .. } => {
+                    if let Some((popup, target)) = popup.as_mut().filter(|(p, _)| p.id() == window)
+                    {
+                        popup.focus();
+                        popup.refresh_geometry();
+                        let corner = popup.window_to_root(0.0, 0.0);
+                        let (dx, dy) = (corner.x - target.x.round(), corner.y - target.y.round());
+                        if (dx, dy) == (0.0, 0.0) {
+                            println!(
+                                "popup landed under the pointer at {:.0},{:.0}",
+                                corner.x, corner.y
+                            );
+                        } else {
+                            println!("popup landed {:.0},{:.0} off the pointer", dx, dy);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
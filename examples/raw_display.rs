@@ -0,0 +1,90 @@
+//! Opens the display with Xlib, hands it to daswin to create a window
+//! and adopt one of its own, then checks the display is still usable
+//! once daswin is gone. Exits with 1 otherwise, e.g. to run under Xvfb
+//! in CI: `xvfb-run cargo run --example raw_display`
+use std::mem;
+use std::process;
+use std::ptr;
+use std::thread;
+use std::time::Duration;
+
+use daswin::prelude::*;
+use x11::xlib;
+
+fn check(ok: bool, what: &str) {
+    if ok {
+        println!("ok      {}", what);
+    } else {
+        println!("FAILED  {}", what);
+        process::exit(1);
+    }
+}
+
+fn main() {
+    unsafe {
+        let display = xlib::XOpenDisplay(ptr::null());
+        if display.is_null() {
+            eprintln!("cannot open the display");
+            process::exit(2);
+        }
+        let screen = xlib::XDefaultScreen(display);
+        let root = xlib::XRootWindow(display, screen);
+        let atom = xlib::XInternAtom(display, b"DASWIN_RAW_DISPLAY\0".as_ptr() as *const _, 0);
+        let own = xlib::XCreateSimpleWindow(display, root, 0, 0, 200, 100, 0, 0, 0);
+        let own_mask = xlib::ExposureMask | xlib::KeyPressMask;
+        xlib::XSelectInput(display, own, own_mask);
+        xlib::XMapWindow(display, own);
+        xlib::XSync(display, xlib::False);
+
+        {
+            let mut event_loop = EventLoop::from_raw_display(display, false);
+            let mut window = X11Window::new(&event_loop, "raw display", (320, 200));
+            window.show();
+            let adopted = X11Window::from_raw(&event_loop, own);
+            check(
+                adopted.display == display,
+                "daswin uses the display handed in",
+            );
+
+            let mut events = Vec::new();
+            let mut created = 0;
+            for _ in 0..20 {
+                event_loop.poll_events(&mut events);
+                created += events
+                    .iter()
+                    .filter(|event| matches!(event, Event::WindowCreated { .. }))
+                    .count();
+                thread::sleep(Duration::from_millis(10));
+            }
+            check(created == 2, "both windows were reported");
+        }
+
+        // Only the display's own windows are left, with their selection
+        let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+        let exists = xlib::XGetWindowAttributes(display, own, &mut attributes) != 0;
+        check(exists, "the adopted window was not destroyed");
+        check(
+            attributes.your_event_mask == own_mask,
+            "the adopted window has its own events selected again",
+        );
+        check(
+            xlib::XInternAtom(display, b"DASWIN_RAW_DISPLAY\0".as_ptr() as *const _, 1) == atom,
+            "the display still answers",
+        );
+        xlib::XClearArea(display, own, 0, 0, 0, 0, xlib::True);
+        xlib::XSync(display, xlib::False);
+        let mut xevent: xlib::XEvent = mem::zeroed();
+        let mut exposed = false;
+        while xlib::XPending(display) != 0 {
+            xlib::XNextEvent(display, &mut xevent);
+            exposed |= xevent.get_type() == xlib::Expose && xevent.expose.window == own;
+        }
+        check(
+            exposed,
+            "events of the released window reach the application",
+        );
+
+        xlib::XDestroyWindow(display, own);
+        xlib::XCloseDisplay(display);
+    }
+}
@@ -0,0 +1,46 @@
+//! Rebinds a "jump" action to any key. Press Return, then the new key,
+//! the binding shows the key's label in the current layout and matches
+//! later presses by keycode
+use std::time::Duration;
+
+use daswin::prelude::*;
+use x11::{keysym, xlib};
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "rebind - press Return", (640, 480));
+    window.show();
+
+    // The keycode of the space bar on evdev keyboards
+    let mut jump = 65;
+    println!("jump is bound to {}", event_loop.key_label(jump));
+    let mut rebinding = false;
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::KeyboardInput {
+                    keycode,
+                    keysym,
+                    pressed: true,
+                    ..
+                } => {
+                    if rebinding {
+                        jump = keycode;
+                        rebinding = false;
+                        println!("jump is bound to {}", event_loop.key_label(jump));
+                    } else if keysym == keysym::XK_Return as xlib::KeySym {
+                        rebinding = true;
+                        println!("press the new jump key");
+                    } else if keycode == jump {
+                        println!("jump!");
+                    }
+                }
+                _ => {}
+            }
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,109 @@
+//! `record_replay record <file>` opens a window and records its events
+//! until it is closed, `record_replay replay <file>` plays them back
+//! without a window and prints how many events of each kind it saw and
+//! the text typed
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+use daswin::prelude::*;
+use daswin::record::{EventPlayer, EventRecorder, Playback};
+
+fn kind(event: &Event) -> &'static str {
+    match event {
+        Event::CloseRequested { .. } => "CloseRequested",
+        Event::Resized { .. } => "Resized",
+        Event::Focused { .. } => "Focused",
+        Event::StateChanged { .. } => "StateChanged",
+        Event::KeyboardInput { .. } => "KeyboardInput",
+        Event::MouseInput { .. } => "MouseInput",
+        Event::MouseWheel { .. } => "MouseWheel",
+        Event::CursorMoved { .. } => "CursorMoved",
+        Event::DeviceEvent { .. } => "DeviceEvent",
+        Event::Tablet { .. } => "Tablet",
+        Event::ExitRequested => "ExitRequested",
+        Event::EventsLost(_) => "EventsLost",
+        Event::MonitorConfigurationChanged => "MonitorConfigurationChanged",
+        Event::WorkAreaChanged => "WorkAreaChanged",
+        Event::MonitorChanged { .. } => "MonitorChanged",
+        Event::FrameDeadlineMissed { .. } => "FrameDeadlineMissed",
+        Event::GpuError { .. } => "GpuError",
+        Event::Suspending => "Suspending",
+        Event::Resumed => "Resumed",
+        Event::WindowCreated { .. } => "WindowCreated",
+        Event::Destroyed { .. } => "Destroyed",
+        Event::Text { .. } => "Text",
+        Event::RedrawRequested { .. } => "RedrawRequested",
+        Event::LateInput => "LateInput",
+        Event::AboutToWait => "AboutToWait",
+        Event::ConnectionLost => "ConnectionLost",
+        Event::DeviceAdded { .. } => "DeviceAdded",
+        Event::DeviceRemoved { .. } => "DeviceRemoved",
+        Event::TrayIconClicked { .. } => "TrayIconClicked",
+        Event::TrayMenuItem { .. } => "TrayMenuItem",
+        Event::FramePresented { .. } => "FramePresented",
+        Event::CanvasViewportChanged { .. } => "CanvasViewportChanged",
+        Event::Channel(_) => "Channel",
+        Event::Snapped { .. } => "Snapped",
+        Event::EmbeddedChildDestroyed { .. } => "EmbeddedChildDestroyed",
+        Event::GlobalHotkey(_) => "GlobalHotkey",
+        Event::ScriptError { .. } => "ScriptError",
+        Event::GpuReadbackReady(_) => "GpuReadbackReady",
+        Event::ModalBlocked { .. } => "ModalBlocked",
+        _ => "other",
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let (mode, path) = match &args[..] {
+        [_, mode, path] => (mode.as_str(), path.as_str()),
+        _ => {
+            eprintln!("usage: record_replay record|replay <file>");
+            return Ok(());
+        }
+    };
+
+    let mut counts = BTreeMap::new();
+    let mut events = Vec::new();
+    let mut typed = String::new();
+    if mode == "record" {
+        let mut event_loop = EventLoop::new();
+        let mut window = X11Window::new(&event_loop, "recording", (640, 480));
+        window.show();
+        let mut recorder = EventRecorder::new(BufWriter::new(File::create(path)?), &[&window])?;
+
+        'running: loop {
+            event_loop.poll_events(&mut events);
+            recorder.record(&events, &event_loop)?;
+            for event in &events {
+                *counts.entry(kind(event)).or_insert(0) += 1;
+                if let Event::CloseRequested { .. } = event {
+                    break 'running;
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        recorder.finish()?;
+    } else {
+        let file = BufReader::new(File::open(path)?);
+        let mut player = EventPlayer::new(file, Playback::AsFastAsPossible)?;
+        while !player.is_finished() {
+            player.poll_events(&mut events)?;
+            for event in &events {
+                *counts.entry(kind(event)).or_insert(0) += 1;
+                if let Event::Text { index, .. } = *event {
+                    typed.push_str(player.text(index).unwrap_or_default());
+                }
+            }
+        }
+    }
+
+    for (kind, count) in counts {
+        println!("{:>16} {}", kind, count);
+    }
+    if !typed.is_empty() {
+        println!("typed {:?}", typed);
+    }
+    Ok(())
+}
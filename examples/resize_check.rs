@@ -0,0 +1,182 @@
+//! Checks frames and their attachments agree in size under a resize
+//! storm: the window is resized 100 times a second for 10 seconds, with
+//! xdotool when it is installed and from a second connection otherwise,
+//! while every frame uses a depth buffer of SurfaceState::current_size.
+//! Exits with 1 on any wgpu error, e.g. `xvfb-run cargo run --example
+//! resize_check`
+use std::process::{self, Command, Stdio};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use x11::xlib;
+
+const STORM: Duration = Duration::from_secs(10);
+const INTERVAL: Duration = Duration::from_millis(10);
+
+fn check(ok: bool, what: &str) {
+    if ok {
+        println!("ok      {}", what);
+    } else {
+        println!("FAILED  {}", what);
+        process::exit(1);
+    }
+}
+
+// Sizes cycling between 200x150 and 599x449
+fn size_at(step: u32) -> (u32, u32) {
+    (200 + step % 400, 150 + step * 7 % 300)
+}
+
+fn resize_storm(window: xlib::Window, stop: Arc<AtomicBool>) {
+    let xdotool = Command::new("xdotool")
+        .arg("version")
+        .stdout(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    println!(
+        "resizing with {}",
+        if xdotool { "xdotool" } else { "XResizeWindow" }
+    );
+    let display = if xdotool {
+        ptr::null_mut()
+    } else {
+        unsafe { xlib::XOpenDisplay(ptr::null()) }
+    };
+    let mut step = 0;
+    while !stop.load(Ordering::Relaxed) {
+        let (width, height) = size_at(step);
+        if xdotool {
+            let _ = Command::new("xdotool")
+                .args(["windowsize", &window.to_string()])
+                .args([width.to_string(), height.to_string()])
+                .status();
+        } else if !display.is_null() {
+            unsafe {
+                xlib::XResizeWindow(display, window, width, height);
+                xlib::XFlush(display);
+            }
+        }
+        step += 1;
+        thread::sleep(INTERVAL);
+    }
+    if !display.is_null() {
+        unsafe { xlib::XCloseDisplay(display) };
+    }
+}
+
+fn create_depth(gpu: &GpuContext, size: PhysicalSize<u32>) -> wgpu::TextureView {
+    gpu.device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let window = X11Window::new(&event_loop, "resize check", (400, 300));
+    let (gpu, mut surface) = GpuContext::with_window(window);
+    surface.window_mut().show();
+    let xid = surface.window().window;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let storm = {
+        let stop = stop.clone();
+        thread::spawn(move || resize_storm(xid, stop))
+    };
+
+    let mut depth_size = surface.surface().current_size();
+    let mut depth = create_depth(&gpu, depth_size);
+    let (mut frames, mut resizes, mut errors) = (0u32, 0u32, Vec::new());
+    let started = Instant::now();
+    let mut events = Vec::new();
+    while started.elapsed() < STORM {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            surface.handle_event(&gpu, event);
+            match *event {
+                Event::Resized { .. } => resizes += 1,
+                Event::GpuError { index } => {
+                    errors.push(event_loop.gpu_error(index).unwrap_or("").to_owned())
+                }
+                _ => {}
+            }
+        }
+
+        let frame = match surface.acquire(&gpu) {
+            Some(frame) => frame,
+            None => continue,
+        };
+        // The frame has the size last applied, whatever the window has now
+        let size = surface.surface().current_size();
+        if size != depth_size {
+            depth_size = size;
+            depth = create_depth(&gpu, size);
+        }
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &depth,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: false,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        gpu.queue.submit(Some(encoder.finish()));
+        surface.present(frame);
+        frames += 1;
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    let _ = storm.join();
+    gpu.device.poll(wgpu::Maintain::Wait);
+    event_loop.poll_events(&mut events);
+    for event in &events {
+        if let Event::GpuError { index } = *event {
+            errors.push(event_loop.gpu_error(index).unwrap_or("").to_owned());
+        }
+    }
+
+    println!(
+        "{} frames, {} resizes, surface now {:?}, window {:?}",
+        frames,
+        resizes,
+        surface.surface().current_size(),
+        surface.window().inner_size_physical()
+    );
+    for error in &errors {
+        println!("{}", error);
+    }
+    check(resizes > 0, "the window was resized");
+    check(errors.is_empty(), "no wgpu error was raised");
+}
@@ -0,0 +1,86 @@
+//! Resizes the window every frame while drawing, to catch surfaces
+//! configured with a stale size. Counts Resized events and surface
+//! configures against the requested sizes, the totals are printed when
+//! the window closes.
+//!
+//! `--no-throttle` delivers every size, see
+//! EventLoop::set_resize_throttle. `--passive` leaves resizing to
+//! someone else, e.g. a scripted window manager resize:
+//!
+//!     for i in $(seq 300); do
+//!         xdotool search --name "resize stress" windowsize $((400 + i)) $((300 + i))
+//!     done
+mod common;
+
+use std::env;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use common::{run_example_with, Step};
+use daswin::prelude::*;
+
+fn main() {
+    let throttle = !env::args().any(|arg| arg == "--no-throttle");
+    let passive = env::args().any(|arg| arg == "--passive");
+    let mut requested = 0u32;
+    let mut resized = 0u32;
+    let configured = Arc::new(AtomicU32::new(0));
+    let mut counting = false;
+    let setup = move |event_loop: &mut EventLoop| {
+        if !throttle {
+            event_loop.set_resize_throttle(None);
+        }
+    };
+    run_example_with("resize stress", setup, move |ctx, step| match step {
+        Step::Event(Event::Resized { size, .. }) => {
+            resized += 1;
+            if resized.is_multiple_of(100) {
+                println!(
+                    "{} resizes requested, {} delivered, {} configures, now {}x{}",
+                    requested,
+                    resized,
+                    configured.load(Ordering::Relaxed),
+                    size.width,
+                    size.height
+                );
+            }
+        }
+        Step::Event(Event::CloseRequested { .. }) => {
+            println!(
+                "resize throttle {}: {} resizes requested, {} delivered, {} configures",
+                if throttle { "on" } else { "off" },
+                requested,
+                resized,
+                configured.load(Ordering::Relaxed)
+            );
+        }
+        Step::Event(_) if !counting => {
+            counting = true;
+            let configured = configured.clone();
+            ctx.surface().surface_mut().on_reconfigure(move |_, _| {
+                configured.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+        Step::Event(_) => {}
+        Step::Draw(_) => {
+            if !passive {
+                let t = ctx.stats().elapsed.as_secs_f32();
+                let width = 500.0 + 300.0 * (t * 3.0).sin();
+                let height = 400.0 + 200.0 * (t * 2.3).cos();
+                ctx.window().set_inner_size((width as u32, height as u32));
+                requested += 1;
+            }
+
+            let frame = ctx.frame().unwrap();
+            let pipeline = frame.pipeline.clone();
+            let mut pass = frame.pass(wgpu::Color {
+                r: 0.1,
+                g: 0.1,
+                b: 0.3,
+                a: 1.0,
+            });
+            pass.set_pipeline(&pipeline);
+            pass.draw(0..3, 0..1);
+        }
+    });
+}
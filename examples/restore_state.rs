@@ -0,0 +1,63 @@
+//! Reopens the window as it was left: move, resize or maximize it, press
+//! F for fullscreen, close it and run the example again. The state is
+//! kept as TOML in the temporary directory, delete it to start over
+use std::fs;
+use std::path::PathBuf;
+
+use daswin::prelude::*;
+use daswin::SavedWindowState;
+use x11::{keysym, xlib};
+
+fn state_path() -> PathBuf {
+    std::env::temp_dir().join("daswin-restore-state.toml")
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let path = state_path();
+    let saved = fs::read_to_string(&path)
+        .ok()
+        .and_then(|toml| SavedWindowState::from_toml(&toml));
+
+    let mut builder = WindowBuilder::new().with_title("restore state - F for fullscreen");
+    match saved {
+        Some(saved) => {
+            println!("restoring from {}:\n{}", path.display(), saved.to_toml());
+            builder = builder.with_restored_state(saved);
+        }
+        None => println!("nothing saved in {} yet", path.display()),
+    }
+    let mut window = builder.build(&event_loop);
+    window.show();
+
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for &event in &events {
+            match event {
+                Event::KeyboardInput {
+                    keysym,
+                    pressed: true,
+                    repeat: false,
+                    ..
+                } if keysym == keysym::XK_f as xlib::KeySym => {
+                    let fullscreen = match window.fullscreen() {
+                        Some(_) => None,
+                        None => Some(Fullscreen::Borderless(None)),
+                    };
+                    window.set_fullscreen(fullscreen);
+                }
+                Event::StateChanged { change, .. } => println!("{:?}", change),
+                Event::CloseRequested { .. } => break 'running,
+                _ => {}
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let state = window.save_state();
+    match fs::write(&path, state.to_toml()) {
+        Ok(()) => println!("saved to {}:\n{}", path.display(), state.to_toml()),
+        Err(error) => eprintln!("failed to save to {}: {}", path.display(), error),
+    }
+}
@@ -0,0 +1,108 @@
+//! Checks a ScriptHost: a script file sets the title, a uniform and the
+//! fullscreen state, rewriting the file reloads it, a script that never
+//! ends fails on its budget and errors arrive as events. Exits with 1
+//! otherwise, e.g. `xvfb-run cargo run --features scripting --example
+//! scripting`
+use std::env;
+use std::fs;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use daswin::ScriptHost;
+
+fn check(ok: bool, what: &str) {
+    if ok {
+        println!("ok      {}", what);
+    } else {
+        println!("FAILED  {}", what);
+        process::exit(1);
+    }
+}
+
+/// Poll for up to a second, returning the messages of the script errors
+fn poll_until(
+    event_loop: &mut EventLoop,
+    events: &mut Vec<Event>,
+    mut done: impl FnMut(&[String]) -> bool,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while Instant::now() < deadline && !done(&errors) {
+        event_loop.poll_events(events);
+        for event in events.iter() {
+            if let Event::ScriptError { index } = *event {
+                errors.push(event_loop.script_error(index).unwrap_or("").to_owned());
+            }
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    errors
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "scripting", (320, 200));
+    window.show();
+    let mut host = ScriptHost::new(&event_loop, &window);
+    let mut events = Vec::new();
+
+    let path = env::temp_dir().join(format!("daswin-{}.script", process::id()));
+    fs::write(
+        &path,
+        "set_title(\"frame \" + frame);\n\
+         set_uniform(\"scale\", 2 * 0.25);\n\
+         if frame == 0 { toggle_fullscreen(); }\n",
+    )
+    .unwrap();
+    host.load_script(&path).unwrap();
+    let errors = poll_until(&mut event_loop, &mut events, |_| {
+        window.fullscreen().is_some()
+    });
+    check(errors.is_empty(), "the script runs without errors");
+    check(
+        host.uniform("scale") == Some(0.5),
+        "set_uniform is read back",
+    );
+    check(
+        window.title().starts_with("frame "),
+        "set_title changes the title",
+    );
+    check(
+        window.fullscreen().is_some(),
+        "toggle_fullscreen enters fullscreen",
+    );
+
+    // Past the mtime granularity of older file systems
+    thread::sleep(Duration::from_millis(1100));
+    fs::write(&path, "set_clear_color(1, 0, 0);\nlet x = ;\n").unwrap();
+    let errors = poll_until(&mut event_loop, &mut events, |errors| !errors.is_empty());
+    check(
+        errors.first().is_some_and(|error| error.contains("line 2")),
+        "a broken script is reported as an event with its line",
+    );
+    check(
+        host.clear_color().is_none(),
+        "the broken script does not run",
+    );
+
+    host.set_instruction_budget(10_000);
+    let started = Instant::now();
+    let result = host.eval("let i = 0; while true { i += 1; }");
+    check(
+        matches!(&result, Err(error) if error.message.contains("budget")),
+        "a script looping forever fails on its budget",
+    );
+    check(
+        started.elapsed() < Duration::from_secs(1),
+        "and returns quickly",
+    );
+    check(
+        host.eval("set_clear_color(0, 0, 1)").is_ok()
+            && host.clear_color() == Some([0.0, 0.0, 1.0, 1.0]),
+        "eval changes the clear color",
+    );
+
+    let _ = fs::remove_file(&path);
+}
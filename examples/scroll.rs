@@ -0,0 +1,39 @@
+//! Pans the triangle with the wheel or two finger touchpad scrolling,
+//! printing whether deltas come in lines or pixels
+mod common;
+
+use common::{run_example, Step};
+use daswin::prelude::*;
+use daswin::ScrollDelta;
+
+fn main() {
+    let mut offset = (0.0f64, 0.0f64);
+    run_example("scroll", move |ctx, step| match step {
+        // Scrolling up and left moves the content down and right, like
+        // a page
+        Step::Event(Event::MouseWheel { delta, .. }) => {
+            let (x, y) = delta.to_pixels();
+            offset.0 += x;
+            offset.1 += y;
+            match delta {
+                ScrollDelta::Lines(x, y) => println!("{:+} {:+} lines", x, y),
+                ScrollDelta::Pixels(x, y) => println!("{:+.2} {:+.2} pixels", x, y),
+            }
+        }
+        Step::Event(_) => {}
+        Step::Draw(_) => {
+            let frame = ctx.frame().unwrap();
+            let (width, height) = (frame.size.width as f32, frame.size.height as f32);
+            let pipeline = frame.pipeline.clone();
+            let mut pass = frame.pass(wgpu::Color::BLACK);
+            // Viewports must lie inside the frame, the half size triangle
+            // wraps around at the edges
+            let (w, h) = (width / 2.0, height / 2.0);
+            let x = (w / 2.0 + offset.0 as f32).rem_euclid(width - w);
+            let y = (h / 2.0 + offset.1 as f32).rem_euclid(height - h);
+            pass.set_viewport(x, y, w, h, 0.0, 1.0);
+            pass.set_pipeline(&pipeline);
+            pass.draw(0..3, 0..1);
+        }
+    });
+}
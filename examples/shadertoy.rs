@@ -0,0 +1,98 @@
+//! Animates a fragment shader over the whole window with FullscreenShader,
+//! feeding it the elapsed time and window resolution. Given a WGSL file
+//! defining fs_main, pressing r loads it, a broken shader is reported and
+//! the previous one keeps running
+use std::time::Instant;
+
+use daswin::prelude::*;
+use daswin::render::helpers::FullscreenShader;
+use x11::{keysym, xlib};
+
+const FRAGMENT: &str = r#"
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let aspect = globals.resolution.x / max(globals.resolution.y, 1.0);
+    let p = (in.uv - 0.5) * vec2<f32>(aspect, 1.0);
+    let d = length(p);
+    let t = globals.time;
+    let col = 0.5 + 0.5 * cos(t + vec3<f32>(d * 12.0, d * 12.0 + 2.0, d * 12.0 + 4.0) - atan2(p.y, p.x));
+    return vec4<f32>(col, 1.0);
+}
+"#;
+
+fn main() {
+    let mut size = PhysicalSize::new(800, 600);
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "shadertoy", size);
+    window.show();
+
+    let (gpu, mut surface) = GpuContext::with_window(window);
+    let mut shader = FullscreenShader::new(&gpu.device, surface.surface().format(), FRAGMENT);
+
+    let path = std::env::args().nth(1);
+    let start = Instant::now();
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            surface.handle_event(&gpu, event);
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::Resized { size: new_size, .. } => size = new_size,
+                Event::KeyboardInput {
+                    keysym,
+                    pressed: true,
+                    ..
+                } if keysym == keysym::XK_r as xlib::KeySym => {
+                    if let Some(path) = &path {
+                        let source = std::fs::read_to_string(path).unwrap_or_default();
+                        match FullscreenShader::try_new(
+                            &gpu.device,
+                            surface.surface().format(),
+                            &source,
+                        ) {
+                            Ok(reloaded) => shader = reloaded,
+                            Err(error) => eprintln!("keeping the previous shader: {}", error),
+                        }
+                    }
+                }
+                Event::GpuError { index } => {
+                    eprintln!("gpu error: {}", event_loop.gpu_error(index).unwrap());
+                }
+                _ => {}
+            }
+        }
+
+        let frame = match surface.acquire(&gpu) {
+            Some(frame) => frame,
+            None => continue,
+        };
+        shader.rebuild(&gpu.device, surface.surface().format());
+        shader.set_globals(&gpu.queue, size, start.elapsed().as_secs_f32());
+
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            shader.draw(&mut rpass);
+        }
+
+        gpu.queue.submit(Some(encoder.finish()));
+        surface.present(frame);
+    }
+}
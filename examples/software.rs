@@ -0,0 +1,64 @@
+//! Draws an animated gradient on the CPU and presents it with
+//! SoftwareSurface, which works without any GPU adapter. With `--gpu` the
+//! GPU is tried first and the gradient only drawn when no adapter is
+//! found, showing the fallback applications would take.
+//!
+//! RenderSurface drives the software surface through RenderBackend, as
+//! it would drive wgpu or a renderer of the application's own
+use std::time::Instant;
+
+use daswin::prelude::*;
+use daswin::render::software::SoftwareSurface;
+use daswin::render::RenderSurface;
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "software", (640, 480));
+    window.show();
+
+    #[cfg(feature = "wgpu")]
+    if std::env::args().any(|arg| arg == "--gpu") {
+        window = match GpuContext::try_with_window(window) {
+            Ok((gpu, _)) => {
+                println!("a GPU adapter is available: {:?}", gpu.adapter.get_info());
+                return;
+            }
+            Err(window) => {
+                println!("no GPU adapter, falling back to software presentation");
+                window
+            }
+        };
+    }
+
+    let backend =
+        SoftwareSurface::new(&window, window.inner_size()).expect("unsupported window visual");
+    let mut surface = RenderSurface::new(window, backend);
+
+    let start = Instant::now();
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            surface.handle_event(event);
+            if let Event::CloseRequested { .. } = event {
+                break 'running;
+            }
+        }
+
+        if let Some(mut frame) = surface.acquire() {
+            let (width, height) = frame.size().into();
+            let t = start.elapsed().as_secs_f32();
+            for (i, pixel) in frame.rgba_mut().chunks_exact_mut(4).enumerate() {
+                let x = (i as u32 % width) as f32 / width as f32;
+                let y = (i as u32 / width) as f32 / height as f32;
+                pixel[0] = (x * 255.0) as u8;
+                pixel[1] = (y * 255.0) as u8;
+                pixel[2] = ((t.sin() * 0.5 + 0.5) * 255.0) as u8;
+                pixel[3] = 255;
+            }
+            surface.present(frame);
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(16));
+    }
+}
@@ -0,0 +1,110 @@
+//! Draws the cursor in software as on servers without cursors, forced
+//! with DASWIN_SOFTWARE_CURSOR. A red dot marks every reported cursor
+//! position, the hotspot of each shape sits on it. Click to go through
+//! the shapes, the cursor disappears while the window is unfocused
+use std::env;
+
+use daswin::prelude::*;
+use daswin::render::helpers::{CursorPass, FullscreenShader};
+
+const FRAGMENT: &str = r#"
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = in.uv * globals.resolution;
+    if distance(floor(pixel), globals.cursor) < 1.5 {
+        return vec4<f32>(1.0, 0.0, 0.0, 1.0);
+    }
+    let checker = (u32(pixel.x / 32.0) + u32(pixel.y / 32.0)) % 2u;
+    return vec4<f32>(vec3<f32>(0.3 + 0.1 * f32(checker)), 1.0);
+}
+"#;
+
+const ICONS: [CursorIcon; 8] = [
+    CursorIcon::Arrow,
+    CursorIcon::Text,
+    CursorIcon::Hand,
+    CursorIcon::Crosshair,
+    CursorIcon::Move,
+    CursorIcon::Wait,
+    CursorIcon::ResizeHorizontal,
+    CursorIcon::ResizeVertical,
+];
+
+fn main() {
+    env::set_var("DASWIN_SOFTWARE_CURSOR", "1");
+    let mut size = PhysicalSize::new(640, 480);
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "software cursor", size);
+    window.set_cursor_icon(ICONS[0]);
+    window.show();
+    println!("software cursor: {}", window.uses_software_cursor());
+
+    let (gpu, mut window) = GpuContext::with_window(window);
+    let format = window.surface().format();
+    let mut shader = FullscreenShader::new(&gpu.device, format, FRAGMENT);
+    let mut cursor = CursorPass::new(&gpu.device, format);
+
+    let mut icon = 0;
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            window.handle_event(&gpu, event);
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::Resized { size: new_size, .. } => size = new_size,
+                Event::CursorMoved { position, .. } => {
+                    shader.set_cursor(&gpu.queue, position);
+                }
+                Event::MouseInput { pressed: true, .. } => {
+                    icon = (icon + 1) % ICONS.len();
+                    let window = window.window_mut();
+                    window.set_cursor_icon(ICONS[icon]);
+                    window.set_title(&format!("software cursor: {:?}", ICONS[icon]));
+                }
+                _ => {}
+            }
+        }
+
+        let frame = match window.acquire(&gpu) {
+            Some(frame) => frame,
+            None => continue,
+        };
+        shader.rebuild(&gpu.device, window.surface().format());
+        cursor.rebuild(&gpu.device, window.surface().format());
+        shader.set_globals(&gpu.queue, size, 0.0);
+        cursor.prepare(
+            &gpu.device,
+            &gpu.queue,
+            size,
+            window.window().software_cursor(),
+        );
+
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            shader.draw(&mut rpass);
+            // Last, so the cursor is above everything
+            cursor.draw(&mut rpass);
+        }
+
+        gpu.queue.submit(Some(encoder.finish()));
+        window.present(frame);
+    }
+}
@@ -0,0 +1,49 @@
+//! Keeps a palette above its document and a dialog above the palette,
+//! click any of the three windows and the relative order is restored
+use daswin::prelude::*;
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut document = X11Window::new(&event_loop, "document", (640, 480));
+    let mut palette = X11Window::new(&event_loop, "palette - above document", (200, 400));
+    let mut dialog = X11Window::new(&event_loop, "dialog - above palette", (320, 160));
+    document.show();
+    palette.show();
+    dialog.show();
+
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                // Clicking raises the window under most window managers
+                Event::MouseInput {
+                    window,
+                    pressed: false,
+                    ..
+                } => {
+                    if window == document.id() {
+                        println!("document clicked");
+                    } else if window == palette.id() {
+                        println!("palette clicked");
+                    } else {
+                        println!("dialog clicked");
+                    }
+                    palette.restack_above(&document);
+                    dialog.restack_above(&palette);
+                }
+                // Stacking changes must not show up as resizes
+                Event::Resized { window, size } => println!(
+                    "0x{:x} resized to {}x{}",
+                    window.xid(),
+                    size.width,
+                    size.height
+                ),
+                _ => {}
+            }
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,41 @@
+//! Logs pen pressure and tilt while drawing on a tablet over the window,
+//! plug a tablet in while it runs to see it picked up
+use daswin::prelude::*;
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "tablet - draw here", (640, 480));
+    window.show();
+
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::Tablet {
+                    device,
+                    position,
+                    pressure,
+                    tilt,
+                    tool,
+                    contact,
+                    ..
+                } => println!(
+                    "{:?} {:?} at ({:.1}, {:.1}) pressure {:.3} tilt ({:.2}, {:.2}) {}",
+                    device,
+                    tool,
+                    position.x,
+                    position.y,
+                    pressure,
+                    tilt.0,
+                    tilt.1,
+                    if contact { "touching" } else { "hovering" }
+                ),
+                _ => {}
+            }
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+}
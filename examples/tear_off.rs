@@ -0,0 +1,125 @@
+//! Drags a tab between two windows: the orange rectangle follows the
+//! pointer into whichever of the windows is on top under it, found with
+//! EventLoop::window_at. Over other clients or the desktop it stays in
+//! the window it was last in
+mod common;
+
+use common::{run_example, Step};
+use daswin::prelude::*;
+use daswin::render::helpers::FullscreenShader;
+use daswin::Rect;
+
+const TAB_SIZE: (u32, u32) = (120, 40);
+
+const TAB_FRAGMENT: &str = r#"
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.5, 0.1, 1.0);
+}
+"#;
+
+struct Tab {
+    window: WindowId,
+    x: i32,
+    y: i32,
+}
+
+impl Tab {
+    fn rect(&self) -> Rect {
+        Rect {
+            x: self.x,
+            y: self.y,
+            width: TAB_SIZE.0,
+            height: TAB_SIZE.1,
+        }
+    }
+}
+
+fn main() {
+    let mut tab: Option<Tab> = None;
+    // Offset of the pointer from the tab's top left while dragging
+    let mut drag: Option<(i32, i32)> = None;
+    let mut shader: Option<FullscreenShader> = None;
+    run_example("tear off", move |ctx, step| {
+        let tab = tab.get_or_insert_with(|| {
+            ctx.open_window("tear off - second window", 480, 360);
+            Tab {
+                window: ctx.window().id(),
+                x: 40,
+                y: 40,
+            }
+        });
+        match step {
+            Step::Event(Event::MouseInput {
+                window,
+                button: 1,
+                pressed,
+                position,
+                ..
+            }) => {
+                let (x, y) = position.cast::<i32>().into();
+                drag = if pressed && window == tab.window && tab.rect().contains(x, y) {
+                    Some((x - tab.x, y - tab.y))
+                } else {
+                    None
+                };
+            }
+            Step::Event(Event::CursorMoved { .. }) if drag.is_some() => {
+                let (offset_x, offset_y) = drag.unwrap();
+                let position = ctx.event_loop().pointer_state().position;
+                let target = match ctx.event_loop().window_at(position) {
+                    Some(target) => target,
+                    None => return,
+                };
+                if target != tab.window {
+                    println!("tab moved to {:?}", target);
+                }
+                let window = ctx.window_by_id(target).unwrap();
+                if let Some(position) = window.cursor_position() {
+                    *tab = Tab {
+                        window: target,
+                        x: position.x as i32 - offset_x,
+                        y: position.y as i32 - offset_y,
+                    };
+                }
+            }
+            Step::Event(Event::Destroyed { window }) if window == tab.window => {
+                tab.window = ctx.window().id();
+                drag = None;
+            }
+            Step::Event(_) => {}
+            Step::Draw(window) => {
+                let device = ctx.device();
+                let frame = ctx.frame().unwrap();
+                let shader = shader.get_or_insert_with(|| {
+                    FullscreenShader::new(device, frame.format, TAB_FRAGMENT)
+                });
+                shader.rebuild(device, frame.format);
+
+                let (width, height) = (frame.size.width as i32, frame.size.height as i32);
+                let mut pass = frame.pass(wgpu::Color {
+                    r: 0.1,
+                    g: 0.1,
+                    b: 0.15,
+                    a: 1.0,
+                });
+                if window != tab.window {
+                    return;
+                }
+                // Scissor rects must lie inside the frame
+                let (left, top) = (tab.x.clamp(0, width), tab.y.clamp(0, height));
+                let right = (tab.x + TAB_SIZE.0 as i32).clamp(0, width);
+                let bottom = (tab.y + TAB_SIZE.1 as i32).clamp(0, height);
+                if right > left && bottom > top {
+                    pass.set_scissor_rect(
+                        left as u32,
+                        top as u32,
+                        (right - left) as u32,
+                        (bottom - top) as u32,
+                    );
+                    shader.draw(&mut pass);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,124 @@
+//! Builds windows with every resource attached and drops them in the
+//! orders applications get wrong: the loop before its windows, windows
+//! before their proxies and surfaces, surfaces after their window. Exits
+//! with 1 if any X error was recorded, e.g. to run under Xvfb in CI:
+//! `xvfb-run cargo run --example teardown`
+use std::mem;
+use std::process;
+
+use daswin::prelude::*;
+use daswin::render::software::SoftwareSurface;
+use daswin::render::RenderSurface;
+use daswin::ChannelProxy;
+
+// A window with a cursor, an input context and pointer barriers
+fn loaded_window(event_loop: &EventLoop, title: &str) -> X11Window {
+    let mut window = X11Window::new(event_loop, title, (320, 200));
+    window.set_cursor_icon(CursorIcon::Text);
+    window.set_ime_allowed(true);
+    // Barriers need XFixes 5.0, the scenarios run without them too
+    let _ = window.confine_cursor_with_barriers(true);
+    window.show();
+    window
+}
+
+fn loop_first() {
+    let event_loop = EventLoop::new();
+    let window = loaded_window(&event_loop, "loop first");
+    let proxy = window.proxy();
+    let (channel, _) = event_loop.create_channel::<u32>();
+    let surface = SoftwareSurface::new(&window, window.inner_size()).unwrap();
+    drop(event_loop);
+    assert!(channel.send(1).is_err());
+    proxy.set_title("the loop is gone");
+    drop(window);
+    drop(surface);
+    drop(proxy);
+}
+
+fn window_before_proxy() {
+    let event_loop = EventLoop::new();
+    let window = loaded_window(&event_loop, "window before proxy");
+    let proxy = window.proxy();
+    drop(window);
+    assert!(!proxy.is_alive());
+    proxy.set_title("the window is gone");
+    proxy.request_redraw();
+    drop(event_loop);
+}
+
+fn surface_after_window() {
+    let event_loop = EventLoop::new();
+    let window = loaded_window(&event_loop, "surface after window");
+    let surface = SoftwareSurface::new(&window, window.inner_size()).unwrap();
+    drop(window);
+    drop(event_loop);
+    drop(surface);
+}
+
+fn forgotten_render_surface() {
+    let event_loop = EventLoop::new();
+    let window = loaded_window(&event_loop, "forgotten surface");
+    let backend = SoftwareSurface::new(&window, window.inner_size()).unwrap();
+    // Leaking leaves the window to the server, never a dangling display
+    mem::forget(RenderSurface::new(window, backend));
+    let window = loaded_window(&event_loop, "dropped surface");
+    let backend = SoftwareSurface::new(&window, window.inner_size()).unwrap();
+    let surface = RenderSurface::new(window, backend);
+    drop(event_loop);
+    drop(surface);
+}
+
+fn channel_outliving_loop() -> ChannelProxy<String> {
+    let event_loop = EventLoop::new();
+    let (proxy, _) = event_loop.create_channel::<String>();
+    let _window = loaded_window(&event_loop, "channel outliving loop");
+    proxy
+}
+
+#[cfg(feature = "wgpu")]
+fn gpu_after_loop() {
+    let event_loop = EventLoop::new();
+    let window = loaded_window(&event_loop, "gpu after loop");
+    let (gpu, surface) = GpuContext::with_window(window);
+    drop(event_loop);
+    drop(gpu);
+    drop(surface);
+}
+
+#[cfg(feature = "wgpu")]
+const GPU_SCENARIOS: &[(&str, fn())] = &[("gpu after loop", gpu_after_loop)];
+#[cfg(not(feature = "wgpu"))]
+const GPU_SCENARIOS: &[(&str, fn())] = &[];
+
+fn main() {
+    let scenarios: Vec<(&str, fn())> = vec![
+        ("loop first", loop_first),
+        ("window before proxy", window_before_proxy),
+        ("surface after window", surface_after_window),
+        ("forgotten render surface", forgotten_render_surface),
+        ("channel outliving loop", || {
+            let proxy = channel_outliving_loop();
+            assert!(proxy.send(String::from("late")).is_err());
+        }),
+    ];
+
+    let mut failed = false;
+    for &(name, scenario) in scenarios.iter().chain(GPU_SCENARIOS) {
+        scenario();
+        // A fresh connection reads the errors of every connection so far
+        let errors = EventLoop::new().take_x_errors();
+        if errors.is_empty() {
+            println!("ok      {}", name);
+        } else {
+            failed = true;
+            println!("FAILED  {}", name);
+            for error in errors {
+                println!("        {}", error);
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
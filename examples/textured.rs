@@ -0,0 +1,110 @@
+//! Samples a generated mipmapped texture in a FullscreenShader, zooming
+//! out over time so the mip levels blend in
+use std::time::Instant;
+
+use daswin::prelude::*;
+use daswin::render::helpers::FullscreenShader;
+use daswin::render::texture::{Texture, TextureBuilder};
+
+const FRAGMENT: &str = r#"
+@group(1) @binding(0)
+var image: texture_2d<f32>;
+@group(1) @binding(1)
+var image_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let zoom = 1.0 + 15.0 * (0.5 - 0.5 * cos(globals.time * 0.5));
+    return textureSample(image, image_sampler, in.uv * zoom);
+}
+"#;
+
+/// A checkerboard with a non power of two size
+fn checkerboard(width: u32, height: u32) -> Vec<u8> {
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let on = (x / 10 + y / 10) % 2 == 0;
+            data.extend_from_slice(if on {
+                &[240, 200, 40, 255]
+            } else {
+                &[30, 30, 90, 255]
+            });
+        }
+    }
+    data
+}
+
+fn main() {
+    let mut size = PhysicalSize::new(800, 600);
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "textured", size);
+    window.show();
+
+    let (gpu, mut surface) = GpuContext::with_window(window);
+
+    let pixels = checkerboard(300, 200);
+    let texture = TextureBuilder::from_rgba8(&gpu.device, &gpu.queue, &pixels, 300, 200)
+        .with_mipmaps(true)
+        .build();
+    let layout = Texture::bind_group_layout(&gpu.device);
+    let bind_group = texture.bind_group(&gpu.device, &layout);
+    println!(
+        "uploaded {:?} with {} mip levels",
+        texture.size, texture.mip_level_count
+    );
+
+    let mut shader = FullscreenShader::with_bind_group_layouts(
+        &gpu.device,
+        surface.surface().format(),
+        FRAGMENT,
+        &[&layout],
+    );
+
+    let start = Instant::now();
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            surface.handle_event(&gpu, event);
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::Resized { size: new_size, .. } => size = new_size,
+                _ => {}
+            }
+        }
+
+        let frame = match surface.acquire(&gpu) {
+            Some(frame) => frame,
+            None => continue,
+        };
+        shader.rebuild(&gpu.device, surface.surface().format());
+        shader.set_globals(&gpu.queue, size, start.elapsed().as_secs_f32());
+
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_bind_group(1, &bind_group, &[]);
+            shader.draw(&mut rpass);
+        }
+
+        gpu.queue.submit(Some(encoder.finish()));
+        surface.present(frame);
+    }
+}
@@ -0,0 +1,90 @@
+//! Keeps animating while event delivery stalls, e.g. while another client
+//! holds a pointer grab: `xdotool mousedown 1` over the title bar on WMs
+//! that block during moves, or any long-held grab
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use pollster::block_on;
+
+fn main() {
+    let mut event_loop = EventLoop::new_threaded(256);
+    let mut window = X11Window::new(&event_loop, "threaded", (640, 480));
+    window.show();
+
+    let instance = wgpu::Instance::new(wgpu::Backends::all());
+    let surface = unsafe { instance.create_surface(&window) };
+    let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::default(),
+        compatible_surface: Some(&surface),
+        force_fallback_adapter: false,
+    }))
+    .expect("Failed to find an appropriate adapter");
+    let (device, queue) = block_on(adapter.request_device(&Default::default(), None))
+        .expect("Failed to create device");
+
+    let mut config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: surface.get_supported_formats(&adapter)[0],
+        width: 640,
+        height: 480,
+        present_mode: wgpu::PresentMode::AutoVsync,
+        alpha_mode: surface.get_supported_alpha_modes(&adapter)[0],
+    };
+    surface.configure(&device, &config);
+
+    let start = Instant::now();
+    let mut second = Instant::now();
+    let mut frames = 0;
+    let mut events = Vec::new();
+
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::Resized { size, .. } => {
+                    config.width = size.width;
+                    config.height = size.height;
+                    surface.configure(&device, &config);
+                }
+                _ => {}
+            }
+        }
+
+        // Animate the clear color so stalls are visible
+        let t = start.elapsed().as_secs_f64();
+        let frame = surface
+            .get_current_texture()
+            .expect("Failed to acquire next swap chain texture");
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: t.sin() * 0.5 + 0.5,
+                        g: (t * 0.7).cos() * 0.5 + 0.5,
+                        b: 0.5,
+                        a: 1.0,
+                    }),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        queue.submit(Some(encoder.finish()));
+        frame.present();
+
+        frames += 1;
+        if second.elapsed() >= Duration::from_secs(1) {
+            window.set_title(&format!("threaded - {} fps", frames));
+            frames = 0;
+            second = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Lives in the system tray: clicking the icon shows or hides the
+//! window, closing the window only hides it. Quit from the icon's menu.
+//! Without a tray the window stays on screen and closing it quits
+use std::thread;
+use std::time::Duration;
+
+use daswin::prelude::*;
+use daswin::{MenuItem, StatusIcon};
+
+const TOGGLE: u32 = 1;
+const QUIT: u32 = 2;
+
+/// A filled circle, `rgb` on transparent
+fn circle_icon(rgb: [u8; 3]) -> Icon {
+    let size = 32;
+    let mut rgba = Vec::with_capacity(size * size * 4);
+    for y in 0..size {
+        for x in 0..size {
+            let dx = x as f32 + 0.5 - size as f32 / 2.0;
+            let dy = y as f32 + 0.5 - size as f32 / 2.0;
+            let alpha = if dx * dx + dy * dy < 14.0 * 14.0 {
+                255
+            } else {
+                0
+            };
+            rgba.extend_from_slice(&[rgb[0], rgb[1], rgb[2], alpha]);
+        }
+    }
+    Icon::from_rgba(rgba, size as u32, size as u32).unwrap()
+}
+
+fn menu(shown: bool) -> Vec<MenuItem> {
+    let toggle = if shown {
+        "_Hide window"
+    } else {
+        "_Show window"
+    };
+    vec![
+        MenuItem::new(TOGGLE, toggle),
+        MenuItem::separator(),
+        MenuItem::new(QUIT, "_Quit"),
+    ]
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    event_loop.catch_exit_signals();
+    let shown_icon = circle_icon([70, 160, 230]);
+    let hidden_icon = circle_icon([140, 140, 140]);
+
+    let mut window = X11Window::new(&event_loop, "tray", (400, 300));
+    window.set_window_icon(Some(&shown_icon));
+    window.show();
+    let mut shown = true;
+
+    let mut tray = match StatusIcon::new(&event_loop, "daswin tray", &shown_icon) {
+        Ok(mut tray) => {
+            tray.set_tooltip("Click to show or hide the window");
+            tray.set_menu(menu(true));
+            Some(tray)
+        }
+        Err(err) => {
+            println!("{}, keeping the window", err);
+            None
+        }
+    };
+
+    let mut events = Vec::new();
+    loop {
+        event_loop.poll_events(&mut events);
+        let mut toggle = false;
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } if tray.is_some() => toggle = shown,
+                Event::CloseRequested { .. } | Event::ExitRequested => return,
+                Event::TrayIconClicked {
+                    secondary: false, ..
+                } => toggle = true,
+                Event::TrayMenuItem { id: TOGGLE } => toggle = true,
+                Event::TrayMenuItem { id: QUIT } => return,
+                _ => {}
+            }
+        }
+
+        if toggle {
+            shown = !shown;
+            if shown {
+                window.show();
+            } else {
+                window.hide();
+            }
+            if let Some(tray) = &mut tray {
+                tray.set_icon(if shown { &shown_icon } else { &hidden_icon });
+                tray.set_menu(menu(shown));
+            }
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,22 @@
+//! The triangle of the daswin binary on the example harness, printing the
+//! size of the window whenever it changes
+mod common;
+
+use common::{run_example, Step};
+use daswin::prelude::*;
+
+fn main() {
+    run_example("triangle", |ctx, step| match step {
+        Step::Event(Event::Resized { size, .. }) => {
+            println!("resized to {}x{}", size.width, size.height)
+        }
+        Step::Event(_) => {}
+        Step::Draw(_) => {
+            let frame = ctx.frame().unwrap();
+            let pipeline = frame.pipeline.clone();
+            let mut pass = frame.pass(wgpu::Color::GREEN);
+            pass.set_pipeline(&pipeline);
+            pass.draw(0..3, 0..1);
+        }
+    });
+}
@@ -0,0 +1,43 @@
+//! Sets titles with CJK text, emoji and a 100 KB one larger than a single
+//! request, reading each back the way the window manager does. Check
+//! the result with `xprop _NET_WM_NAME WM_NAME` while the window is open
+use std::time::Duration;
+
+use daswin::prelude::*;
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "", (480, 120));
+    window.show();
+
+    let long: String = "🦀 日本語 ".repeat(100 * 1024 / "🦀 日本語 ".len());
+    let titles = [
+        "daswin — Größe",
+        "日本語のタイトル",
+        "🦀🎉 emoji",
+        "cut at\0the nul",
+        &long,
+    ];
+    for title in titles.iter() {
+        window.set_title(title);
+        let expected = title.split('\0').next().unwrap();
+        let read = window.title();
+        println!(
+            "{} bytes: {}",
+            title.len(),
+            if read == expected { "ok" } else { "MISMATCH" }
+        );
+    }
+    window.set_title("日本語 🦀 - close to exit");
+
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            if let Event::CloseRequested { .. } = event {
+                break 'running;
+            }
+        }
+        std::thread::sleep(Duration::from_millis(16));
+    }
+}
@@ -0,0 +1,344 @@
+//! Clears the window with Vulkan through ash, using nothing of daswin but
+//! the window, its events and the raw handles. Build with
+//! `cargo run --example vulkan --no-default-features --features ash`
+use std::error::Error;
+
+use ash::extensions::khr::{Surface, Swapchain, XlibSurface};
+use ash::vk;
+use daswin::prelude::*;
+use raw_window_handle::{
+    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
+};
+
+/// The swapchain and the objects sized after it
+struct Frames {
+    swapchain: vk::SwapchainKHR,
+    images: Vec<vk::Image>,
+}
+
+struct Renderer {
+    _entry: ash::Entry,
+    instance: ash::Instance,
+    surface_loader: Surface,
+    surface: vk::SurfaceKHR,
+    physical_device: vk::PhysicalDevice,
+    device: ash::Device,
+    queue: vk::Queue,
+    swapchain_loader: Swapchain,
+    frames: Option<Frames>,
+    command_pool: vk::CommandPool,
+    command_buffer: vk::CommandBuffer,
+    acquired: vk::Semaphore,
+    rendered: vk::Semaphore,
+    in_flight: vk::Fence,
+}
+
+impl Renderer {
+    unsafe fn new(window: &X11Window) -> Result<Renderer, Box<dyn Error>> {
+        let display = match window.raw_display_handle() {
+            RawDisplayHandle::Xlib(handle) => handle.display,
+            _ => return Err("not an Xlib display".into()),
+        };
+        let xlib_window = match window.raw_window_handle() {
+            RawWindowHandle::Xlib(handle) => handle.window,
+            _ => return Err("not an Xlib window".into()),
+        };
+
+        let entry = ash::Entry::load()?;
+        let extensions = [Surface::name().as_ptr(), XlibSurface::name().as_ptr()];
+        let instance = entry.create_instance(
+            &vk::InstanceCreateInfo::builder().enabled_extension_names(&extensions),
+            None,
+        )?;
+
+        let surface = XlibSurface::new(&entry, &instance).create_xlib_surface(
+            &vk::XlibSurfaceCreateInfoKHR::builder()
+                .dpy(display as *mut vk::Display)
+                .window(xlib_window),
+            None,
+        )?;
+        let surface_loader = Surface::new(&entry, &instance);
+
+        // A queue family which draws and presents to the window
+        let mut found = None;
+        for physical_device in instance.enumerate_physical_devices()? {
+            let families = instance.get_physical_device_queue_family_properties(physical_device);
+            for (index, family) in families.iter().enumerate() {
+                let index = index as u32;
+                if family.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+                    && surface_loader.get_physical_device_surface_support(
+                        physical_device,
+                        index,
+                        surface,
+                    )?
+                {
+                    found = Some((physical_device, index));
+                    break;
+                }
+            }
+            if found.is_some() {
+                break;
+            }
+        }
+        let (physical_device, family) = found.ok_or("no device presents to the window")?;
+
+        let priorities = [1.0];
+        let queues = [vk::DeviceQueueCreateInfo::builder()
+            .queue_family_index(family)
+            .queue_priorities(&priorities)
+            .build()];
+        let device_extensions = [Swapchain::name().as_ptr()];
+        let device = instance.create_device(
+            physical_device,
+            &vk::DeviceCreateInfo::builder()
+                .queue_create_infos(&queues)
+                .enabled_extension_names(&device_extensions),
+            None,
+        )?;
+        let queue = device.get_device_queue(family, 0);
+        let swapchain_loader = Swapchain::new(&instance, &device);
+
+        let command_pool = device.create_command_pool(
+            &vk::CommandPoolCreateInfo::builder()
+                .queue_family_index(family)
+                .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER),
+            None,
+        )?;
+        let command_buffer = device.allocate_command_buffers(
+            &vk::CommandBufferAllocateInfo::builder()
+                .command_pool(command_pool)
+                .command_buffer_count(1),
+        )?[0];
+        let acquired = device.create_semaphore(&vk::SemaphoreCreateInfo::default(), None)?;
+        let rendered = device.create_semaphore(&vk::SemaphoreCreateInfo::default(), None)?;
+        let in_flight = device.create_fence(
+            &vk::FenceCreateInfo::builder().flags(vk::FenceCreateFlags::SIGNALED),
+            None,
+        )?;
+
+        Ok(Renderer {
+            _entry: entry,
+            instance,
+            surface_loader,
+            surface,
+            physical_device,
+            device,
+            queue,
+            swapchain_loader,
+            frames: None,
+            command_pool,
+            command_buffer,
+            acquired,
+            rendered,
+            in_flight,
+        })
+    }
+
+    /// Create the swapchain for the current window size, replacing the
+    /// previous one
+    unsafe fn configure(&mut self, (width, height): (u32, u32)) -> Result<(), Box<dyn Error>> {
+        self.device.device_wait_idle()?;
+        let capabilities = self
+            .surface_loader
+            .get_physical_device_surface_capabilities(self.physical_device, self.surface)?;
+        if !capabilities
+            .supported_usage_flags
+            .contains(vk::ImageUsageFlags::TRANSFER_DST)
+        {
+            return Err("the surface cannot be cleared with transfers".into());
+        }
+        let format = self
+            .surface_loader
+            .get_physical_device_surface_formats(self.physical_device, self.surface)?[0];
+        // u32::MAX means the surface takes the swapchain's size
+        let extent = match capabilities.current_extent.width {
+            u32::MAX => vk::Extent2D { width, height },
+            _ => capabilities.current_extent,
+        };
+
+        let old = self.frames.take();
+        let mut image_count = capabilities.min_image_count + 1;
+        if capabilities.max_image_count > 0 {
+            image_count = image_count.min(capabilities.max_image_count);
+        }
+        let swapchain = self.swapchain_loader.create_swapchain(
+            &vk::SwapchainCreateInfoKHR::builder()
+                .surface(self.surface)
+                .min_image_count(image_count)
+                .image_format(format.format)
+                .image_color_space(format.color_space)
+                .image_extent(extent)
+                .image_array_layers(1)
+                .image_usage(vk::ImageUsageFlags::TRANSFER_DST)
+                .pre_transform(capabilities.current_transform)
+                .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
+                .present_mode(vk::PresentModeKHR::FIFO)
+                .clipped(true)
+                .old_swapchain(
+                    old.as_ref()
+                        .map_or(vk::SwapchainKHR::null(), |f| f.swapchain),
+                ),
+            None,
+        )?;
+        if let Some(old) = old {
+            self.swapchain_loader.destroy_swapchain(old.swapchain, None);
+        }
+        let images = self.swapchain_loader.get_swapchain_images(swapchain)?;
+        self.frames = Some(Frames { swapchain, images });
+        Ok(())
+    }
+
+    /// Clear the next swapchain image and present it, returns false when
+    /// the swapchain must be configured again
+    unsafe fn draw(&mut self, color: [f32; 4]) -> Result<bool, Box<dyn Error>> {
+        let frames = match &self.frames {
+            Some(frames) => frames,
+            None => return Ok(false),
+        };
+        self.device
+            .wait_for_fences(&[self.in_flight], true, u64::MAX)?;
+        let index = match self.swapchain_loader.acquire_next_image(
+            frames.swapchain,
+            u64::MAX,
+            self.acquired,
+            vk::Fence::null(),
+        ) {
+            Ok((index, _)) => index,
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => return Ok(false),
+            Err(err) => return Err(err.into()),
+        };
+        self.device.reset_fences(&[self.in_flight])?;
+
+        let image = frames.images[index as usize];
+        let range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .level_count(1)
+            .layer_count(1)
+            .build();
+        let barrier = |old, new, src_access, dst_access| {
+            vk::ImageMemoryBarrier::builder()
+                .old_layout(old)
+                .new_layout(new)
+                .src_access_mask(src_access)
+                .dst_access_mask(dst_access)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(range)
+                .build()
+        };
+
+        let cmd = self.command_buffer;
+        self.device
+            .reset_command_buffer(cmd, vk::CommandBufferResetFlags::empty())?;
+        self.device
+            .begin_command_buffer(cmd, &vk::CommandBufferBeginInfo::default())?;
+        self.device.cmd_pipeline_barrier(
+            cmd,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier(
+                vk::ImageLayout::UNDEFINED,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::AccessFlags::empty(),
+                vk::AccessFlags::TRANSFER_WRITE,
+            )],
+        );
+        self.device.cmd_clear_color_image(
+            cmd,
+            image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &vk::ClearColorValue { float32: color },
+            &[range],
+        );
+        self.device.cmd_pipeline_barrier(
+            cmd,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier(
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                vk::ImageLayout::PRESENT_SRC_KHR,
+                vk::AccessFlags::TRANSFER_WRITE,
+                vk::AccessFlags::empty(),
+            )],
+        );
+        self.device.end_command_buffer(cmd)?;
+
+        let wait_stages = [vk::PipelineStageFlags::TRANSFER];
+        let submit = vk::SubmitInfo::builder()
+            .wait_semaphores(std::slice::from_ref(&self.acquired))
+            .wait_dst_stage_mask(&wait_stages)
+            .command_buffers(std::slice::from_ref(&cmd))
+            .signal_semaphores(std::slice::from_ref(&self.rendered))
+            .build();
+        self.device
+            .queue_submit(self.queue, &[submit], self.in_flight)?;
+
+        let swapchains = [frames.swapchain];
+        let indices = [index];
+        let present = vk::PresentInfoKHR::builder()
+            .wait_semaphores(std::slice::from_ref(&self.rendered))
+            .swapchains(&swapchains)
+            .image_indices(&indices);
+        match self.swapchain_loader.queue_present(self.queue, &present) {
+            Ok(suboptimal) => Ok(!suboptimal),
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self.device.device_wait_idle();
+            self.device.destroy_fence(self.in_flight, None);
+            self.device.destroy_semaphore(self.rendered, None);
+            self.device.destroy_semaphore(self.acquired, None);
+            self.device.destroy_command_pool(self.command_pool, None);
+            if let Some(frames) = self.frames.take() {
+                self.swapchain_loader
+                    .destroy_swapchain(frames.swapchain, None);
+            }
+            self.device.destroy_device(None);
+            self.surface_loader.destroy_surface(self.surface, None);
+            self.instance.destroy_instance(None);
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "vulkan", (640, 480));
+    window.show();
+
+    let mut renderer = unsafe { Renderer::new(&window)? };
+    let start = std::time::Instant::now();
+    let mut configured = false;
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::Resized { .. } => configured = false,
+                _ => {}
+            }
+        }
+
+        if !configured {
+            unsafe { renderer.configure(window.inner_size().into())? };
+        }
+        let t = start.elapsed().as_secs_f32();
+        let color = [t.sin() * 0.5 + 0.5, 0.2, t.cos() * 0.5 + 0.5, 1.0];
+        // FIFO presentation paces the loop
+        configured = unsafe { renderer.draw(color)? };
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+//! Moves the triangle with the keys in the WASD position on any layout,
+//! matched by keycode, or the arrow keys, matched by keysym. Dragging with
+//! the left button moves it along with the cursor. No event is matched,
+//! all input comes from snapshots
+mod common;
+
+use common::{run_example, Step};
+use daswin::prelude::*;
+use daswin::InputSnapshot;
+use x11::{keysym, xlib};
+
+// Pixels per second
+const SPEED: f64 = 400.0;
+
+// Keycodes of the W, A, S and D positions on evdev keyboards
+const KEY_W: u32 = 25;
+const KEY_A: u32 = 38;
+const KEY_S: u32 = 39;
+const KEY_D: u32 = 40;
+
+fn axis(input: &InputSnapshot, negative: (u32, u32), positive: (u32, u32)) -> f64 {
+    let down = |(keycode, keysym): (u32, u32)| {
+        input.is_key_down(keycode) || input.is_keysym_down(keysym as xlib::KeySym)
+    };
+    down(positive) as i32 as f64 - down(negative) as i32 as f64
+}
+
+fn main() {
+    let mut offset = PhysicalPosition::new(0.0f64, 0.0f64);
+    run_example("wasd", move |ctx, step| {
+        if let Step::Draw(_) = step {
+            let input = ctx.event_loop().input_snapshot();
+            let dt = ctx.stats().frame_time.as_secs_f64();
+            offset.x +=
+                SPEED * dt * axis(&input, (KEY_A, keysym::XK_Left), (KEY_D, keysym::XK_Right));
+            offset.y += SPEED * dt * axis(&input, (KEY_W, keysym::XK_Up), (KEY_S, keysym::XK_Down));
+            if input.is_button_down(1) {
+                offset.x += input.cursor_delta.0;
+                offset.y += input.cursor_delta.1;
+            }
+
+            let frame = ctx.frame().unwrap();
+            let (width, height) = (frame.size.width as f32, frame.size.height as f32);
+            let pipeline = frame.pipeline.clone();
+            let mut pass = frame.pass(wgpu::Color::BLACK);
+            // Viewports must lie inside the frame, the half size triangle
+            // wraps around at the edges
+            let (w, h) = (width / 2.0, height / 2.0);
+            let x = (w / 2.0 + offset.x as f32).rem_euclid(width - w);
+            let y = (h / 2.0 + offset.y as f32).rem_euclid(height - h);
+            pass.set_viewport(x, y, w, h, 0.0, 1.0);
+            pass.set_pipeline(&pipeline);
+            pass.draw(0..3, 0..1);
+        }
+    });
+}
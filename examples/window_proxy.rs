@@ -0,0 +1,66 @@
+//! Eight worker threads report their progress in the window title
+//! through WindowProxy while the loop runs, one of them also changes the
+//! cursor and requests redraws. Closing the window stops the workers,
+//! which notice through is_alive
+use std::thread;
+use std::time::Duration;
+
+use daswin::prelude::*;
+
+const WORKERS: usize = 8;
+const STEPS: usize = 500;
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "window proxy", (640, 240));
+    window.show();
+
+    let workers: Vec<_> = (0..WORKERS)
+        .map(|worker| {
+            let proxy = window.proxy();
+            thread::spawn(move || {
+                let icons = [CursorIcon::Wait, CursorIcon::Hand, CursorIcon::Crosshair];
+                for step in 0..STEPS {
+                    if !proxy.is_alive() {
+                        return;
+                    }
+                    let percent = (step + 1) * 100 / STEPS;
+                    proxy.set_title(&format!("worker {} at {}%", worker, percent));
+                    if worker == 0 {
+                        proxy.set_cursor_icon(icons[step / 50 % icons.len()]);
+                        proxy.request_redraw();
+                    }
+                    thread::sleep(Duration::from_millis(2));
+                }
+                if worker == 0 {
+                    proxy.set_cursor_icon(CursorIcon::Default);
+                }
+            })
+        })
+        .collect();
+
+    let mut redraws = 0;
+    let mut done = false;
+    let mut events = Vec::new();
+    'running: loop {
+        event_loop.poll_events(&mut events);
+        for event in &events {
+            match *event {
+                Event::CloseRequested { .. } => break 'running,
+                Event::RedrawRequested { .. } => redraws += 1,
+                _ => {}
+            }
+        }
+        if !done && workers.iter().all(|worker| worker.is_finished()) {
+            done = true;
+            window.set_title("window proxy - all workers done");
+        }
+        thread::sleep(Duration::from_millis(16));
+    }
+
+    drop(window);
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    println!("{} redraws requested by the workers arrived", redraws);
+}
@@ -0,0 +1,71 @@
+//! Checks the quirks table against window managers faked with
+//! WmInfo::new, then prints the window manager running and what it
+//! supports. Exits with 1 when a check fails, e.g. `cargo run --example
+//! wm_quirks`
+use std::process;
+
+use daswin::prelude::*;
+use daswin::wm::{Feature, WmInfo};
+
+const FEATURES: [Feature; 10] = [
+    Feature::Fullscreen,
+    Feature::Maximize,
+    Feature::DemandsAttention,
+    Feature::MoveResize,
+    Feature::RestackWindow,
+    Feature::ActiveWindow,
+    Feature::WorkArea,
+    Feature::FrameExtents,
+    Feature::Ping,
+    Feature::SyncRequest,
+];
+
+fn check(ok: bool, what: &str) {
+    if ok {
+        println!("ok      {}", what);
+    } else {
+        println!("FAILED  {}", what);
+        process::exit(1);
+    }
+}
+
+fn main() {
+    let mutter = WmInfo::new("Mutter", &FEATURES);
+    check(
+        FEATURES.iter().all(|&feature| mutter.supports(feature)),
+        "a window manager without quirks supports what it lists",
+    );
+    let i3 = WmInfo::new("i3", &FEATURES);
+    check(
+        !i3.supports(Feature::MoveResize),
+        "i3 drags are made by the event loop",
+    );
+    check(
+        i3.supports(Feature::Fullscreen),
+        "i3 keeps the features it has no quirk for",
+    );
+    check(
+        !WmInfo::new("I3", &FEATURES).supports(Feature::MoveResize),
+        "names are compared without case",
+    );
+    let dwm = WmInfo::new("dwm", &[Feature::Fullscreen, Feature::ActiveWindow]);
+    check(
+        !dwm.supports(Feature::MoveResize) && !dwm.supports(Feature::Maximize),
+        "features not listed are not supported",
+    );
+
+    let event_loop = EventLoop::new();
+    match event_loop.window_manager() {
+        Some(wm) => {
+            println!("running {:?}, listing {} hints", wm.name, wm.supports.len());
+            for feature in FEATURES {
+                println!(
+                    "  {:<16} {}",
+                    format!("{:?}", feature),
+                    wm.supports(feature)
+                );
+            }
+        }
+        None => println!("no window manager following EWMH is running"),
+    }
+}
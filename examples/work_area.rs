@@ -0,0 +1,131 @@
+//! Maps a fake panel reserving the top 40 pixels with _NET_WM_STRUT and
+//! checks the work area shrinks accordingly, then opens a window sized to
+//! 80% of it. Needs a window manager computing _NET_WORKAREA, exits with
+//! 1 when the area is wrong, e.g. `xvfb-run sh -c 'openbox & sleep 1;
+//! cargo run --example work_area'`
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+use daswin::{Rect, Size};
+use x11::xlib;
+
+const PANEL_HEIGHT: u32 = 40;
+
+fn check(ok: bool, what: &str) {
+    if ok {
+        println!("ok      {}", what);
+    } else {
+        println!("FAILED  {}", what);
+        process::exit(1);
+    }
+}
+
+fn primary_work_area(event_loop: &EventLoop) -> Rect {
+    event_loop
+        .primary_monitor()
+        .expect("no monitor")
+        .work_area()
+}
+
+// Polls until the work area changed or a few seconds passed
+fn wait_for_change(event_loop: &mut EventLoop) -> bool {
+    let start = Instant::now();
+    let mut events = Vec::new();
+    while start.elapsed() < Duration::from_secs(3) {
+        event_loop.poll_events(&mut events);
+        if events.contains(&Event::WorkAreaChanged) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+unsafe fn intern(display: *mut xlib::Display, name: &[u8]) -> xlib::Atom {
+    xlib::XInternAtom(display, name.as_ptr() as *const _, xlib::False)
+}
+
+// A dock along the top of the screen, which the WM keeps windows clear of
+unsafe fn make_panel(display: *mut xlib::Display, window: xlib::Window) {
+    let window_type = intern(display, b"_NET_WM_WINDOW_TYPE\0");
+    let dock = intern(display, b"_NET_WM_WINDOW_TYPE_DOCK\0");
+    xlib::XChangeProperty(
+        display,
+        window,
+        window_type,
+        xlib::XA_ATOM,
+        32,
+        xlib::PropModeReplace,
+        &dock as *const xlib::Atom as *const u8,
+        1,
+    );
+    // Left, right, top and bottom
+    let strut: [std::os::raw::c_long; 4] = [0, 0, PANEL_HEIGHT as _, 0];
+    xlib::XChangeProperty(
+        display,
+        window,
+        intern(display, b"_NET_WM_STRUT\0"),
+        xlib::XA_CARDINAL,
+        32,
+        xlib::PropModeReplace,
+        strut.as_ptr() as *const u8,
+        strut.len() as _,
+    );
+}
+
+fn main() {
+    let mut event_loop = EventLoop::new();
+    let before = primary_work_area(&event_loop);
+    println!("work area before: {:?}", before);
+
+    let monitor = event_loop.primary_monitor().expect("no monitor");
+    let mut panel = WindowBuilder::new()
+        .with_title("fake panel")
+        .with_inner_size((monitor.size().width, PANEL_HEIGHT))
+        .with_position(Position::At(monitor.position()))
+        .with_decorations(false)
+        .build(&event_loop);
+    unsafe { make_panel(panel.display, panel.window) };
+    panel.show();
+
+    check(
+        wait_for_change(&mut event_loop),
+        "WorkAreaChanged delivered",
+    );
+    let after = primary_work_area(&event_loop);
+    println!("work area with the panel: {:?}", after);
+    check(
+        after.y >= monitor.position().y + PANEL_HEIGHT as i32,
+        "the work area starts below the panel",
+    );
+    check(
+        after.height + PANEL_HEIGHT <= monitor.size().height,
+        "the work area lost the panel's height",
+    );
+
+    let window = WindowBuilder::new()
+        .with_title("80% of the work area")
+        .with_inner_size(Size::WorkArea(0.8))
+        .with_position(Position::CenteredOnMonitor(
+            event_loop.primary_monitor().unwrap(),
+        ))
+        .build(&event_loop);
+    let size = window.inner_size();
+    check(
+        size.width == (after.width as f64 * 0.8).round() as u32
+            && size.height == (after.height as f64 * 0.8).round() as u32,
+        "the window is 80% of the work area",
+    );
+
+    drop(panel);
+    check(
+        wait_for_change(&mut event_loop),
+        "WorkAreaChanged delivered again",
+    );
+    check(
+        primary_work_area(&event_loop) == before,
+        "the work area is restored without the panel",
+    );
+}
@@ -0,0 +1,317 @@
+/* C interface of daswin, built with `cargo build --features capi` into
+ * libdaswin.so. Kept in sync with src/ffi.rs by hand. */
+
+#ifndef DASWIN_H
+#define DASWIN_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define DASWIN_OK 0
+#define DASWIN_NULL_ARGUMENT -1
+#define DASWIN_PANIC -2
+#define DASWIN_NO_STRING -3
+
+typedef struct DaswinWindow DaswinWindow;
+
+typedef enum DaswinEventTag {
+    DASWIN_CLOSE_REQUESTED = 0,
+    DASWIN_RESIZED = 1,
+    DASWIN_FOCUSED = 2,
+    DASWIN_KEYBOARD_INPUT = 3,
+    DASWIN_MOUSE_INPUT = 4,
+    DASWIN_CURSOR_MOVED = 5,
+    DASWIN_DEVICE_EVENT = 6,
+    DASWIN_TABLET = 7,
+    DASWIN_EXIT_REQUESTED = 8,
+    DASWIN_EVENTS_LOST = 9,
+    DASWIN_FRAME_DEADLINE_MISSED = 10,
+    DASWIN_MONITOR_CONFIGURATION_CHANGED = 11,
+    DASWIN_GPU_ERROR = 12,
+    DASWIN_SUSPENDING = 13,
+    DASWIN_RESUMED = 14,
+    DASWIN_WINDOW_CREATED = 15,
+    DASWIN_DESTROYED = 16,
+    DASWIN_TEXT = 17,
+    DASWIN_STATE_CHANGED = 18,
+    DASWIN_MOUSE_WHEEL = 19,
+    DASWIN_REDRAW_REQUESTED = 20,
+    DASWIN_ABOUT_TO_WAIT = 21,
+    DASWIN_MONITOR_CHANGED = 22,
+    DASWIN_CONNECTION_LOST = 23,
+    DASWIN_DEVICE_ADDED = 24,
+    DASWIN_DEVICE_REMOVED = 25,
+    DASWIN_TRAY_ICON_CLICKED = 26,
+    DASWIN_TRAY_MENU_ITEM = 27,
+    DASWIN_FRAME_PRESENTED = 28,
+    DASWIN_CANVAS_VIEWPORT_CHANGED = 29,
+    DASWIN_CHANNEL = 30,
+    DASWIN_SNAPPED = 31,
+    DASWIN_EMBEDDED_CHILD_DESTROYED = 32,
+    DASWIN_GLOBAL_HOTKEY = 33,
+    DASWIN_WORK_AREA_CHANGED = 34,
+    DASWIN_LATE_INPUT = 35,
+    DASWIN_SCRIPT_ERROR = 36,
+    DASWIN_GPU_READBACK_READY = 37,
+    DASWIN_MODAL_BLOCKED = 38,
+} DaswinEventTag;
+
+/* Window ids include a generation, the X window id is the low 32 bits */
+typedef struct DaswinCloseRequested {
+    uint64_t window;
+} DaswinCloseRequested;
+
+typedef struct DaswinWindowCreated {
+    uint64_t window;
+} DaswinWindowCreated;
+
+typedef struct DaswinDestroyed {
+    uint64_t window;
+} DaswinDestroyed;
+
+typedef struct DaswinRedrawRequested {
+    uint64_t window;
+} DaswinRedrawRequested;
+
+/* The string is read with daswin_event_text */
+typedef struct DaswinText {
+    uint64_t window;
+    uint32_t index;
+} DaswinText;
+
+typedef enum DaswinStateChange {
+    DASWIN_MAXIMIZED = 0,
+    DASWIN_UNMAXIMIZED = 1,
+    DASWIN_FULLSCREEN_ENTERED = 2,
+    DASWIN_FULLSCREEN_EXITED = 3,
+    DASWIN_ATTENTION_CLEARED = 4,
+} DaswinStateChange;
+
+/* change is a DaswinStateChange */
+typedef struct DaswinStateChanged {
+    uint64_t window;
+    uint32_t change;
+} DaswinStateChanged;
+
+/* monitor is the RandR output of the monitor */
+typedef struct DaswinMonitorChanged {
+    uint64_t window;
+    uint64_t monitor;
+} DaswinMonitorChanged;
+
+typedef struct DaswinResized {
+    uint64_t window;
+    uint32_t width;
+    uint32_t height;
+} DaswinResized;
+
+typedef struct DaswinFocused {
+    uint64_t window;
+    bool focused;
+} DaswinFocused;
+
+typedef struct DaswinKeyboardInput {
+    uint64_t window;
+    uint32_t keycode;
+    unsigned long keysym;
+    bool pressed;
+    /* Made up by daswin on focus changes and for repeats */
+    bool synthetic;
+    bool repeat;
+} DaswinKeyboardInput;
+
+typedef struct DaswinMouseInput {
+    uint64_t window;
+    uint32_t button;
+    bool pressed;
+    bool synthetic;
+    double x;
+    double y;
+    /* The same position relative to the root window */
+    double root_x;
+    double root_y;
+    uint32_t device;
+} DaswinMouseInput;
+
+/* Positive deltas scroll up and left, in pixels when pixels is set and
+ * in lines otherwise */
+typedef struct DaswinMouseWheel {
+    uint64_t window;
+    bool pixels;
+    double delta_x;
+    double delta_y;
+} DaswinMouseWheel;
+
+typedef struct DaswinCursorMoved {
+    uint64_t window;
+    double x;
+    double y;
+    double root_x;
+    double root_y;
+    double delta_x;
+    double delta_y;
+    uint32_t device;
+} DaswinCursorMoved;
+
+/* kind is 0 for keys, 1 for buttons and 2 for motion, code is the
+ * keycode or button number */
+typedef struct DaswinDeviceEvent {
+    uint32_t device;
+    uint32_t kind;
+    uint32_t code;
+    bool pressed;
+    double delta_x;
+    double delta_y;
+} DaswinDeviceEvent;
+
+typedef struct DaswinTablet {
+    uint64_t window;
+    uint32_t device;
+    double x;
+    double y;
+    float pressure;
+    float tilt_x;
+    float tilt_y;
+    bool eraser;
+    bool contact;
+} DaswinTablet;
+
+typedef struct DaswinTrayIconClicked {
+    bool secondary;
+    int32_t x;
+    int32_t y;
+} DaswinTrayIconClicked;
+
+/* ust_ns is on the monotonic clock */
+typedef struct DaswinFramePresented {
+    uint64_t window;
+    uint64_t msc;
+    uint64_t ust_ns;
+} DaswinFramePresented;
+
+typedef enum DaswinEdge {
+    DASWIN_EDGE_LEFT = 0,
+    DASWIN_EDGE_TOP = 1,
+    DASWIN_EDGE_RIGHT = 2,
+    DASWIN_EDGE_BOTTOM = 3,
+} DaswinEdge;
+
+/* edge is a DaswinEdge */
+typedef struct DaswinSnapped {
+    uint64_t window;
+    uint32_t edge;
+} DaswinSnapped;
+
+/* child is the X id of the foreign window */
+typedef struct DaswinEmbeddedChildDestroyed {
+    uint64_t window;
+    uint64_t child;
+} DaswinEmbeddedChildDestroyed;
+
+/* modal is the window id of the modal window */
+typedef struct DaswinModalBlocked {
+    uint64_t window;
+    uint64_t modal;
+} DaswinModalBlocked;
+
+typedef struct DaswinCanvasViewportChanged {
+    uint64_t window;
+    int32_t x;
+    int32_t y;
+    uint32_t width;
+    uint32_t height;
+} DaswinCanvasViewportChanged;
+
+/* Durations in nanoseconds */
+typedef struct DaswinFrameDeadlineMissed {
+    uint64_t elapsed_ns;
+    uint64_t budget_ns;
+    uint64_t translation_ns;
+} DaswinFrameDeadlineMissed;
+
+typedef union DaswinEventData {
+    DaswinCloseRequested close_requested;
+    DaswinResized resized;
+    DaswinFocused focused;
+    DaswinKeyboardInput keyboard_input;
+    DaswinMouseInput mouse_input;
+    DaswinCursorMoved cursor_moved;
+    DaswinDeviceEvent device_event;
+    DaswinTablet tablet;
+    uint32_t events_lost;
+    DaswinFrameDeadlineMissed frame_deadline_missed;
+    uint32_t gpu_error;
+    uint32_t device;
+    DaswinWindowCreated window_created;
+    DaswinDestroyed destroyed;
+    DaswinText text;
+    DaswinStateChanged state_changed;
+    DaswinMouseWheel mouse_wheel;
+    DaswinRedrawRequested redraw_requested;
+    DaswinMonitorChanged monitor_changed;
+    DaswinTrayIconClicked tray_icon_clicked;
+    uint32_t tray_menu_item;
+    DaswinFramePresented frame_presented;
+    DaswinCanvasViewportChanged canvas_viewport_changed;
+    uint32_t channel;
+    DaswinSnapped snapped;
+    DaswinEmbeddedChildDestroyed embedded_child_destroyed;
+    uint32_t global_hotkey;
+    uint32_t script_error;
+    uint32_t gpu_readback_ready;
+    DaswinModalBlocked modal_blocked;
+} DaswinEventData;
+
+/* data holds the member named after the tag, DASWIN_EXIT_REQUESTED,
+ * DASWIN_MONITOR_CONFIGURATION_CHANGED, DASWIN_WORK_AREA_CHANGED,
+ * DASWIN_SUSPENDING, DASWIN_RESUMED, DASWIN_LATE_INPUT,
+ * DASWIN_ABOUT_TO_WAIT and DASWIN_CONNECTION_LOST carry no data.
+ * DASWIN_ABOUT_TO_WAIT is never returned by daswin_poll_event, the end
+ * of a batch is its false */
+typedef struct DaswinEvent {
+    DaswinEventTag tag;
+    DaswinEventData data;
+} DaswinEvent;
+
+/* Open a connection and create a window with a UTF-8 title, returns NULL
+ * on failure */
+DaswinWindow *daswin_window_new(const char *title, uint32_t width, uint32_t height);
+
+/* Map the window */
+int daswin_window_show(DaswinWindow *window);
+
+/* Take the next event without blocking, returns false when none is
+ * pending. Polling again after false looks for new events */
+bool daswin_poll_event(DaswinWindow *window, DaswinEvent *out);
+
+/* Block until events arrive or timeout_ms passed, negative to wait
+ * without a timeout. Returns right away while a batch is only partly
+ * taken */
+int daswin_wait_events(DaswinWindow *window, int64_t timeout_ms);
+
+/* Copy the string of a DASWIN_TEXT, DASWIN_GPU_ERROR or
+ * DASWIN_SCRIPT_ERROR event into buf as UTF-8 with a terminating nul, cut
+ * to fit len bytes. Returns the length of the whole string without the
+ * nul, so a return of len or more means it was cut, like snprintf, and
+ * DASWIN_NO_STRING for other events. The event must be of the current
+ * batch: once daswin_poll_event returned false the next call polls again
+ * and the strings of the batch before are gone */
+ptrdiff_t daswin_event_text(const DaswinWindow *window, const DaswinEvent *event, char *buf, size_t len);
+
+/* Write the Xlib Display pointer and window id, for creating a surface
+ * in another renderer */
+int daswin_window_raw_xlib(const DaswinWindow *window, void **display_out, unsigned long *window_out);
+
+/* Destroy the window and close its connection, NULL is ignored */
+void daswin_window_destroy(DaswinWindow *window);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* DASWIN_H */
@@ -0,0 +1,757 @@
+"""Python bindings for daswin over the C interface in include/daswin.h.
+
+Build the library with `cargo build --features capi` and point
+DASWIN_LIBRARY at libdaswin.so if it is not next to this file or on the
+loader path. ctypes releases the GIL during every call into the library.
+
+The bindings use ctypes in place of a PyO3 extension module, as PyO3 is
+not available to the offline build. They need no Python headers or
+compiler, only the library. test_daswin.py holds their tests, run with
+`python3 -m unittest` in this directory or with pytest.
+
+    with Window("hello", 640, 480) as window:
+        window.show()
+        while not any(isinstance(e, CloseRequested) for e in window.wait_event()):
+            ...
+"""
+
+import ctypes
+import math
+import os
+from dataclasses import dataclass
+
+DASWIN_OK = 0
+DASWIN_NO_STRING = -3
+
+_CLOSE_REQUESTED = 0
+_RESIZED = 1
+_FOCUSED = 2
+_KEYBOARD_INPUT = 3
+_MOUSE_INPUT = 4
+_CURSOR_MOVED = 5
+_DEVICE_EVENT = 6
+_TABLET = 7
+_EXIT_REQUESTED = 8
+_EVENTS_LOST = 9
+_FRAME_DEADLINE_MISSED = 10
+_MONITOR_CONFIGURATION_CHANGED = 11
+_GPU_ERROR = 12
+_SUSPENDING = 13
+_RESUMED = 14
+_WINDOW_CREATED = 15
+_DESTROYED = 16
+_TEXT = 17
+_STATE_CHANGED = 18
+_MOUSE_WHEEL = 19
+_REDRAW_REQUESTED = 20
+_ABOUT_TO_WAIT = 21
+_MONITOR_CHANGED = 22
+_CONNECTION_LOST = 23
+_DEVICE_ADDED = 24
+_DEVICE_REMOVED = 25
+_TRAY_ICON_CLICKED = 26
+_TRAY_MENU_ITEM = 27
+_FRAME_PRESENTED = 28
+_CANVAS_VIEWPORT_CHANGED = 29
+_CHANNEL = 30
+_SNAPPED = 31
+_EMBEDDED_CHILD_DESTROYED = 32
+_GLOBAL_HOTKEY = 33
+_WORK_AREA_CHANGED = 34
+_LATE_INPUT = 35
+_SCRIPT_ERROR = 36
+_GPU_READBACK_READY = 37
+_MODAL_BLOCKED = 38
+
+_STATE_CHANGES = (
+    "maximized",
+    "unmaximized",
+    "fullscreen_entered",
+    "fullscreen_exited",
+    "attention_cleared",
+)
+
+_EDGES = ("left", "top", "right", "bottom")
+
+
+class _CloseRequested(ctypes.Structure):
+    _fields_ = [("window", ctypes.c_uint64)]
+
+
+class _Text(ctypes.Structure):
+    _fields_ = [("window", ctypes.c_uint64), ("index", ctypes.c_uint32)]
+
+
+class _MouseWheel(ctypes.Structure):
+    _fields_ = [
+        ("window", ctypes.c_uint64),
+        ("pixels", ctypes.c_bool),
+        ("delta_x", ctypes.c_double),
+        ("delta_y", ctypes.c_double),
+    ]
+
+
+class _StateChanged(ctypes.Structure):
+    _fields_ = [("window", ctypes.c_uint64), ("change", ctypes.c_uint32)]
+
+
+class _MonitorChanged(ctypes.Structure):
+    _fields_ = [("window", ctypes.c_uint64), ("monitor", ctypes.c_uint64)]
+
+
+class _Resized(ctypes.Structure):
+    _fields_ = [
+        ("window", ctypes.c_uint64),
+        ("width", ctypes.c_uint32),
+        ("height", ctypes.c_uint32),
+    ]
+
+
+class _Focused(ctypes.Structure):
+    _fields_ = [("window", ctypes.c_uint64), ("focused", ctypes.c_bool)]
+
+
+class _KeyboardInput(ctypes.Structure):
+    _fields_ = [
+        ("window", ctypes.c_uint64),
+        ("keycode", ctypes.c_uint32),
+        ("keysym", ctypes.c_ulong),
+        ("pressed", ctypes.c_bool),
+        ("synthetic", ctypes.c_bool),
+        ("repeat", ctypes.c_bool),
+    ]
+
+
+class _MouseInput(ctypes.Structure):
+    _fields_ = [
+        ("window", ctypes.c_uint64),
+        ("button", ctypes.c_uint32),
+        ("pressed", ctypes.c_bool),
+        ("synthetic", ctypes.c_bool),
+        ("x", ctypes.c_double),
+        ("y", ctypes.c_double),
+        ("root_x", ctypes.c_double),
+        ("root_y", ctypes.c_double),
+        ("device", ctypes.c_uint32),
+    ]
+
+
+class _CursorMoved(ctypes.Structure):
+    _fields_ = [
+        ("window", ctypes.c_uint64),
+        ("x", ctypes.c_double),
+        ("y", ctypes.c_double),
+        ("root_x", ctypes.c_double),
+        ("root_y", ctypes.c_double),
+        ("delta_x", ctypes.c_double),
+        ("delta_y", ctypes.c_double),
+        ("device", ctypes.c_uint32),
+    ]
+
+
+class _DeviceEvent(ctypes.Structure):
+    _fields_ = [
+        ("device", ctypes.c_uint32),
+        ("kind", ctypes.c_uint32),
+        ("code", ctypes.c_uint32),
+        ("pressed", ctypes.c_bool),
+        ("delta_x", ctypes.c_double),
+        ("delta_y", ctypes.c_double),
+    ]
+
+
+class _Tablet(ctypes.Structure):
+    _fields_ = [
+        ("window", ctypes.c_uint64),
+        ("device", ctypes.c_uint32),
+        ("x", ctypes.c_double),
+        ("y", ctypes.c_double),
+        ("pressure", ctypes.c_float),
+        ("tilt_x", ctypes.c_float),
+        ("tilt_y", ctypes.c_float),
+        ("eraser", ctypes.c_bool),
+        ("contact", ctypes.c_bool),
+    ]
+
+
+class _TrayIconClicked(ctypes.Structure):
+    _fields_ = [
+        ("secondary", ctypes.c_bool),
+        ("x", ctypes.c_int32),
+        ("y", ctypes.c_int32),
+    ]
+
+
+class _FramePresented(ctypes.Structure):
+    _fields_ = [
+        ("window", ctypes.c_uint64),
+        ("msc", ctypes.c_uint64),
+        ("ust_ns", ctypes.c_uint64),
+    ]
+
+
+class _Snapped(ctypes.Structure):
+    _fields_ = [
+        ("window", ctypes.c_uint64),
+        ("edge", ctypes.c_uint32),
+    ]
+
+
+class _EmbeddedChildDestroyed(ctypes.Structure):
+    _fields_ = [
+        ("window", ctypes.c_uint64),
+        ("child", ctypes.c_uint64),
+    ]
+
+
+class _ModalBlocked(ctypes.Structure):
+    _fields_ = [
+        ("window", ctypes.c_uint64),
+        ("modal", ctypes.c_uint64),
+    ]
+
+
+class _CanvasViewportChanged(ctypes.Structure):
+    _fields_ = [
+        ("window", ctypes.c_uint64),
+        ("x", ctypes.c_int32),
+        ("y", ctypes.c_int32),
+        ("width", ctypes.c_uint32),
+        ("height", ctypes.c_uint32),
+    ]
+
+
+class _FrameDeadlineMissed(ctypes.Structure):
+    _fields_ = [
+        ("elapsed_ns", ctypes.c_uint64),
+        ("budget_ns", ctypes.c_uint64),
+        ("translation_ns", ctypes.c_uint64),
+    ]
+
+
+class _EventData(ctypes.Union):
+    _fields_ = [
+        ("close_requested", _CloseRequested),
+        ("resized", _Resized),
+        ("focused", _Focused),
+        ("keyboard_input", _KeyboardInput),
+        ("mouse_input", _MouseInput),
+        ("cursor_moved", _CursorMoved),
+        ("device_event", _DeviceEvent),
+        ("tablet", _Tablet),
+        ("events_lost", ctypes.c_uint32),
+        ("frame_deadline_missed", _FrameDeadlineMissed),
+        ("gpu_error", ctypes.c_uint32),
+        ("device", ctypes.c_uint32),
+        ("window_created", _CloseRequested),
+        ("destroyed", _CloseRequested),
+        ("text", _Text),
+        ("state_changed", _StateChanged),
+        ("mouse_wheel", _MouseWheel),
+        ("redraw_requested", _CloseRequested),
+        ("monitor_changed", _MonitorChanged),
+        ("tray_icon_clicked", _TrayIconClicked),
+        ("tray_menu_item", ctypes.c_uint32),
+        ("frame_presented", _FramePresented),
+        ("canvas_viewport_changed", _CanvasViewportChanged),
+        ("channel", ctypes.c_uint32),
+        ("snapped", _Snapped),
+        ("embedded_child_destroyed", _EmbeddedChildDestroyed),
+        ("global_hotkey", ctypes.c_uint32),
+        ("script_error", ctypes.c_uint32),
+        ("gpu_readback_ready", ctypes.c_uint32),
+        ("modal_blocked", _ModalBlocked),
+    ]
+
+
+class _Event(ctypes.Structure):
+    _fields_ = [("tag", ctypes.c_int), ("data", _EventData)]
+
+
+def _load():
+    path = os.environ.get("DASWIN_LIBRARY")
+    if path is None:
+        local = os.path.join(os.path.dirname(os.path.abspath(__file__)), "libdaswin.so")
+        path = local if os.path.exists(local) else "libdaswin.so"
+    lib = ctypes.CDLL(path)
+
+    lib.daswin_window_new.restype = ctypes.c_void_p
+    lib.daswin_window_new.argtypes = [ctypes.c_char_p, ctypes.c_uint32, ctypes.c_uint32]
+    lib.daswin_window_show.restype = ctypes.c_int
+    lib.daswin_window_show.argtypes = [ctypes.c_void_p]
+    lib.daswin_poll_event.restype = ctypes.c_bool
+    lib.daswin_poll_event.argtypes = [ctypes.c_void_p, ctypes.POINTER(_Event)]
+    lib.daswin_wait_events.restype = ctypes.c_int
+    lib.daswin_wait_events.argtypes = [ctypes.c_void_p, ctypes.c_int64]
+    lib.daswin_event_text.restype = ctypes.c_ssize_t
+    lib.daswin_event_text.argtypes = [
+        ctypes.c_void_p,
+        ctypes.POINTER(_Event),
+        ctypes.c_char_p,
+        ctypes.c_size_t,
+    ]
+    lib.daswin_window_raw_xlib.restype = ctypes.c_int
+    lib.daswin_window_raw_xlib.argtypes = [
+        ctypes.c_void_p,
+        ctypes.POINTER(ctypes.c_void_p),
+        ctypes.POINTER(ctypes.c_ulong),
+    ]
+    lib.daswin_window_destroy.restype = None
+    lib.daswin_window_destroy.argtypes = [ctypes.c_void_p]
+    return lib
+
+
+_lib = None
+
+
+def _library():
+    global _lib
+    if _lib is None:
+        _lib = _load()
+    return _lib
+
+
+class Event:
+    """Base class of all events"""
+
+
+@dataclass
+class CloseRequested(Event):
+    window: int
+
+
+@dataclass
+class Resized(Event):
+    window: int
+    width: int
+    height: int
+
+
+@dataclass
+class Focused(Event):
+    window: int
+    focused: bool
+
+
+@dataclass
+class KeyboardInput(Event):
+    window: int
+    keycode: int
+    keysym: int
+    pressed: bool
+    synthetic: bool
+    repeat: bool
+
+
+@dataclass
+class MouseInput(Event):
+    window: int
+    button: int
+    pressed: bool
+    x: float
+    y: float
+    root_x: float
+    root_y: float
+    synthetic: bool
+    device: int
+
+
+@dataclass
+class CursorMoved(Event):
+    window: int
+    x: float
+    y: float
+    root_x: float
+    root_y: float
+    delta: tuple
+    device: int
+
+
+@dataclass
+class DeviceEvent(Event):
+    """kind is "key", "button" or "motion", code the keycode or button"""
+
+    device: int
+    kind: str
+    code: int
+    pressed: bool
+    delta: tuple
+
+
+@dataclass
+class Tablet(Event):
+    window: int
+    device: int
+    x: float
+    y: float
+    pressure: float
+    tilt: tuple
+    eraser: bool
+    contact: bool
+
+
+@dataclass
+class ExitRequested(Event):
+    pass
+
+
+@dataclass
+class EventsLost(Event):
+    count: int
+
+
+@dataclass
+class MonitorConfigurationChanged(Event):
+    pass
+
+
+@dataclass
+class WorkAreaChanged(Event):
+    pass
+
+
+@dataclass
+class FrameDeadlineMissed(Event):
+    """Durations in seconds"""
+
+    elapsed: float
+    budget: float
+    translation: float
+
+
+@dataclass
+class GpuError(Event):
+    index: int
+    message: str = ""
+
+
+@dataclass
+class Text(Event):
+    window: int
+    index: int
+    text: str = ""
+
+
+@dataclass
+class RedrawRequested(Event):
+    window: int
+
+
+@dataclass
+class LateInput(Event):
+    pass
+
+
+@dataclass
+class AboutToWait(Event):
+    pass
+
+
+@dataclass
+class ConnectionLost(Event):
+    pass
+
+
+@dataclass
+class DeviceAdded(Event):
+    device: int
+
+
+@dataclass
+class DeviceRemoved(Event):
+    device: int
+
+
+@dataclass
+class TrayIconClicked(Event):
+    secondary: bool
+    position: tuple
+
+
+@dataclass
+class TrayMenuItem(Event):
+    id: int
+
+
+@dataclass
+class FramePresented(Event):
+    window: int
+    msc: int
+    ust_ns: int
+
+
+@dataclass
+class CanvasViewportChanged(Event):
+    window: int
+    x: int
+    y: int
+    width: int
+    height: int
+
+
+@dataclass
+class Snapped(Event):
+    window: int
+    edge: str
+
+
+@dataclass
+class EmbeddedChildDestroyed(Event):
+    window: int
+    child: int
+
+
+@dataclass
+class Channel(Event):
+    channel: int
+
+
+@dataclass
+class GlobalHotkey(Event):
+    hotkey: int
+
+
+@dataclass
+class ScriptError(Event):
+    index: int
+    message: str = ""
+
+
+@dataclass
+class GpuReadbackReady(Event):
+    readback: int
+
+
+@dataclass
+class ModalBlocked(Event):
+    window: int
+    modal: int
+
+
+@dataclass
+class MouseWheel(Event):
+    window: int
+    pixels: bool
+    delta: tuple
+
+
+@dataclass
+class MonitorChanged(Event):
+    window: int
+    monitor: int
+
+
+@dataclass
+class StateChanged(Event):
+    window: int
+    change: str
+
+
+@dataclass
+class WindowCreated(Event):
+    window: int
+
+
+@dataclass
+class Destroyed(Event):
+    window: int
+
+
+@dataclass
+class Suspending(Event):
+    pass
+
+
+@dataclass
+class Resumed(Event):
+    pass
+
+
+def _convert(event):
+    data = event.data
+    if event.tag == _CLOSE_REQUESTED:
+        return CloseRequested(data.close_requested.window)
+    if event.tag == _RESIZED:
+        d = data.resized
+        return Resized(d.window, d.width, d.height)
+    if event.tag == _FOCUSED:
+        return Focused(data.focused.window, data.focused.focused)
+    if event.tag == _KEYBOARD_INPUT:
+        d = data.keyboard_input
+        return KeyboardInput(
+            d.window, d.keycode, d.keysym, d.pressed, d.synthetic, d.repeat
+        )
+    if event.tag == _MOUSE_INPUT:
+        d = data.mouse_input
+        return MouseInput(
+            d.window,
+            d.button,
+            d.pressed,
+            d.x,
+            d.y,
+            d.root_x,
+            d.root_y,
+            d.synthetic,
+            d.device,
+        )
+    if event.tag == _CURSOR_MOVED:
+        d = data.cursor_moved
+        return CursorMoved(
+            d.window, d.x, d.y, d.root_x, d.root_y, (d.delta_x, d.delta_y), d.device
+        )
+    if event.tag == _DEVICE_EVENT:
+        d = data.device_event
+        kind = ("key", "button", "motion")[d.kind]
+        return DeviceEvent(d.device, kind, d.code, d.pressed, (d.delta_x, d.delta_y))
+    if event.tag == _TABLET:
+        d = data.tablet
+        return Tablet(
+            d.window, d.device, d.x, d.y, d.pressure, (d.tilt_x, d.tilt_y), d.eraser, d.contact
+        )
+    if event.tag == _EXIT_REQUESTED:
+        return ExitRequested()
+    if event.tag == _EVENTS_LOST:
+        return EventsLost(data.events_lost)
+    if event.tag == _MONITOR_CONFIGURATION_CHANGED:
+        return MonitorConfigurationChanged()
+    if event.tag == _WORK_AREA_CHANGED:
+        return WorkAreaChanged()
+    if event.tag == _FRAME_DEADLINE_MISSED:
+        d = data.frame_deadline_missed
+        return FrameDeadlineMissed(
+            d.elapsed_ns / 1e9, d.budget_ns / 1e9, d.translation_ns / 1e9
+        )
+    if event.tag == _GPU_ERROR:
+        return GpuError(data.gpu_error)
+    if event.tag == _TEXT:
+        return Text(data.text.window, data.text.index)
+    if event.tag == _REDRAW_REQUESTED:
+        return RedrawRequested(data.redraw_requested.window)
+    if event.tag == _LATE_INPUT:
+        return LateInput()
+    if event.tag == _ABOUT_TO_WAIT:
+        return AboutToWait()
+    if event.tag == _CONNECTION_LOST:
+        return ConnectionLost()
+    if event.tag == _DEVICE_ADDED:
+        return DeviceAdded(data.device)
+    if event.tag == _DEVICE_REMOVED:
+        return DeviceRemoved(data.device)
+    if event.tag == _TRAY_ICON_CLICKED:
+        d = data.tray_icon_clicked
+        return TrayIconClicked(d.secondary, (d.x, d.y))
+    if event.tag == _TRAY_MENU_ITEM:
+        return TrayMenuItem(data.tray_menu_item)
+    if event.tag == _FRAME_PRESENTED:
+        d = data.frame_presented
+        return FramePresented(d.window, d.msc, d.ust_ns)
+    if event.tag == _CANVAS_VIEWPORT_CHANGED:
+        d = data.canvas_viewport_changed
+        return CanvasViewportChanged(d.window, d.x, d.y, d.width, d.height)
+    if event.tag == _SNAPPED:
+        d = data.snapped
+        return Snapped(d.window, _EDGES[d.edge])
+    if event.tag == _EMBEDDED_CHILD_DESTROYED:
+        d = data.embedded_child_destroyed
+        return EmbeddedChildDestroyed(d.window, d.child)
+    if event.tag == _CHANNEL:
+        return Channel(data.channel)
+    if event.tag == _GLOBAL_HOTKEY:
+        return GlobalHotkey(data.global_hotkey)
+    if event.tag == _SCRIPT_ERROR:
+        return ScriptError(data.script_error)
+    if event.tag == _GPU_READBACK_READY:
+        return GpuReadbackReady(data.gpu_readback_ready)
+    if event.tag == _MODAL_BLOCKED:
+        d = data.modal_blocked
+        return ModalBlocked(d.window, d.modal)
+    if event.tag == _MOUSE_WHEEL:
+        d = data.mouse_wheel
+        return MouseWheel(d.window, d.pixels, (d.delta_x, d.delta_y))
+    if event.tag == _MONITOR_CHANGED:
+        d = data.monitor_changed
+        return MonitorChanged(d.window, d.monitor)
+    if event.tag == _STATE_CHANGED:
+        d = data.state_changed
+        return StateChanged(d.window, _STATE_CHANGES[d.change])
+    if event.tag == _WINDOW_CREATED:
+        return WindowCreated(data.window_created.window)
+    if event.tag == _DESTROYED:
+        return Destroyed(data.destroyed.window)
+    if event.tag == _SUSPENDING:
+        return Suspending()
+    if event.tag == _RESUMED:
+        return Resumed()
+    raise ValueError("unknown event tag {}".format(event.tag))
+
+
+class Window:
+    """A window with its own connection, usable as a context manager"""
+
+    def __init__(self, title, width, height):
+        self._handle = None
+        self._lib = _library()
+        self._handle = self._lib.daswin_window_new(title.encode(), width, height)
+        if not self._handle:
+            raise RuntimeError("failed to create window")
+
+    def show(self):
+        if self._lib.daswin_window_show(self._handle) != DASWIN_OK:
+            raise RuntimeError("failed to show window")
+
+    def poll_events(self):
+        """All pending events, without blocking. Text, GpuError and
+        ScriptError events come with their string"""
+        events = []
+        event = _Event()
+        while self._lib.daswin_poll_event(self._handle, ctypes.byref(event)):
+            converted = _convert(event)
+            if isinstance(converted, Text):
+                converted.text = self._event_text(event)
+            elif isinstance(converted, (GpuError, ScriptError)):
+                converted.message = self._event_text(event)
+            events.append(converted)
+        return events
+
+    def wait_event(self, timeout=None):
+        """Block until events arrive or `timeout` seconds passed, then
+        return them like poll_events. The GIL is released while blocking,
+        so other Python threads keep running"""
+        timeout_ms = -1 if timeout is None else max(0, math.ceil(timeout * 1000))
+        if self._lib.daswin_wait_events(self._handle, timeout_ms) != DASWIN_OK:
+            raise RuntimeError("failed to wait for events")
+        return self.poll_events()
+
+    def _event_text(self, event):
+        length = self._lib.daswin_event_text(self._handle, ctypes.byref(event), None, 0)
+        if length < 0:
+            return ""
+        buf = ctypes.create_string_buffer(length + 1)
+        self._lib.daswin_event_text(self._handle, ctypes.byref(event), buf, len(buf))
+        return buf.value.decode()
+
+    def raw_handle(self):
+        """The Xlib Display pointer and window id as integers"""
+        display = ctypes.c_void_p()
+        window = ctypes.c_ulong()
+        status = self._lib.daswin_window_raw_xlib(
+            self._handle, ctypes.byref(display), ctypes.byref(window)
+        )
+        if status != DASWIN_OK:
+            raise RuntimeError("failed to get raw handle")
+        return display.value, window.value
+
+    def close(self):
+        if self._handle:
+            self._lib.daswin_window_destroy(self._handle)
+            self._handle = None
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *exc):
+        self.close()
+        return False
+
+    def __del__(self):
+        self.close()
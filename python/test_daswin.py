@@ -0,0 +1,346 @@
+"""Tests of the Python bindings, run with `python3 -m unittest` in this
+directory or with pytest where it is installed.
+
+The layout tests compile a small C program against include/daswin.h and
+are skipped without a C compiler. The window test needs libdaswin.so built
+with `cargo build --features capi` and is skipped without it.
+"""
+
+import ctypes
+import os
+import re
+import shutil
+import subprocess
+import sys
+import tempfile
+import unittest
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+ROOT = os.path.dirname(HERE)
+HEADER = os.path.join(ROOT, "include", "daswin.h")
+sys.path.insert(0, HERE)
+
+import daswin  # noqa: E402
+
+
+def header_tags():
+    with open(HEADER) as header:
+        source = header.read()
+    body = re.search(r"enum DaswinEventTag \{(.*?)\}", source, re.S).group(1)
+    return {name: int(value) for name, value in re.findall(r"DASWIN_(\w+) = (\d+)", body)}
+
+
+def raw_event(tag, member=None, **fields):
+    raw = daswin._Event()
+    raw.tag = tag
+    for name, value in fields.items():
+        if isinstance(getattr(raw.data, member), int):
+            setattr(raw.data, member, value)
+        else:
+            setattr(getattr(raw.data, member), name, value)
+    return raw
+
+
+def event(tag, member=None, **fields):
+    return daswin._convert(raw_event(tag, member, **fields))
+
+
+class FakeLibrary:
+    """Stands in for libdaswin.so. Hands out `batches` of raw events, each
+    ended by false, and `strings[index]` for events carrying a string"""
+
+    def __init__(self, batches, strings=()):
+        self.batches = list(batches)
+        self.strings = list(strings)
+        self.pending = None
+        self.polls = 0
+        self.waits = []
+
+    def daswin_window_new(self, title, width, height):
+        return 1
+
+    def daswin_window_destroy(self, handle):
+        pass
+
+    def daswin_poll_event(self, handle, out):
+        if self.pending is None:
+            self.polls += 1
+            self.pending = list(self.batches.pop(0)) if self.batches else []
+        if not self.pending:
+            self.pending = None
+            return False
+        raw = self.pending.pop(0)
+        out._obj.tag = raw.tag
+        out._obj.data = raw.data
+        return True
+
+    def daswin_wait_events(self, handle, timeout_ms):
+        self.waits.append(timeout_ms)
+        return daswin.DASWIN_OK
+
+    def daswin_event_text(self, handle, event, buf, length):
+        event = event._obj
+        index = {
+            daswin._TEXT: event.data.text.index,
+            daswin._GPU_ERROR: event.data.gpu_error,
+            daswin._SCRIPT_ERROR: event.data.script_error,
+        }.get(event.tag)
+        if index is None or index >= len(self.strings):
+            return daswin.DASWIN_NO_STRING
+        encoded = self.strings[index].encode()
+        if length:
+            cut = encoded[: length - 1] + b"\0"
+            ctypes.memmove(buf, cut, len(cut))
+        return len(encoded)
+
+
+class TagTest(unittest.TestCase):
+    def test_tags_match_header(self):
+        tags = header_tags()
+        self.assertEqual(len(tags), 39)
+        for name, value in tags.items():
+            self.assertEqual(getattr(daswin, "_" + name), value, name)
+
+    def test_every_tag_converts(self):
+        for name, value in header_tags().items():
+            self.assertIsInstance(event(value), daswin.Event, name)
+
+    def test_unknown_tag(self):
+        with self.assertRaises(ValueError):
+            event(len(header_tags()))
+
+
+class ConvertTest(unittest.TestCase):
+    def test_keyboard_input(self):
+        converted = event(
+            daswin._KEYBOARD_INPUT,
+            "keyboard_input",
+            window=7,
+            keycode=38,
+            keysym=0x61,
+            pressed=True,
+            repeat=True,
+        )
+        self.assertEqual(converted, daswin.KeyboardInput(7, 38, 0x61, True, False, True))
+
+    def test_mouse(self):
+        converted = event(
+            daswin._MOUSE_INPUT,
+            "mouse_input",
+            window=1,
+            button=3,
+            pressed=True,
+            x=1.5,
+            y=2.5,
+            root_x=11.5,
+            root_y=12.5,
+            device=2,
+        )
+        self.assertEqual(
+            converted, daswin.MouseInput(1, 3, True, 1.5, 2.5, 11.5, 12.5, False, 2)
+        )
+        converted = event(
+            daswin._MOUSE_WHEEL, "mouse_wheel", window=1, pixels=True, delta_x=0.5, delta_y=-3.0
+        )
+        self.assertEqual(converted, daswin.MouseWheel(1, True, (0.5, -3.0)))
+
+    def test_names_of_enums(self):
+        converted = event(daswin._STATE_CHANGED, "state_changed", window=1, change=2)
+        self.assertEqual(converted, daswin.StateChanged(1, "fullscreen_entered"))
+        converted = event(daswin._SNAPPED, "snapped", window=1, edge=3)
+        self.assertEqual(converted, daswin.Snapped(1, "bottom"))
+        converted = event(daswin._DEVICE_EVENT, "device_event", device=4, kind=2)
+        self.assertEqual(converted.kind, "motion")
+
+    def test_durations_in_seconds(self):
+        converted = event(
+            daswin._FRAME_DEADLINE_MISSED,
+            "frame_deadline_missed",
+            elapsed_ns=20_000_000,
+            budget_ns=16_000_000,
+            translation_ns=500_000,
+        )
+        self.assertEqual(converted, daswin.FrameDeadlineMissed(0.02, 0.016, 0.0005))
+
+    def test_ids(self):
+        self.assertEqual(
+            event(daswin._MODAL_BLOCKED, "modal_blocked", window=1, modal=2),
+            daswin.ModalBlocked(1, 2),
+        )
+        self.assertEqual(
+            event(daswin._GPU_READBACK_READY).readback,
+            0,
+        )
+        self.assertEqual(event(daswin._CLOSE_REQUESTED, "close_requested", window=9).window, 9)
+
+    def test_events_without_data(self):
+        self.assertEqual(event(daswin._ABOUT_TO_WAIT), daswin.AboutToWait())
+        self.assertEqual(event(daswin._CONNECTION_LOST), daswin.ConnectionLost())
+
+
+@unittest.skipIf(shutil.which("cc") is None, "no C compiler")
+class LayoutTest(unittest.TestCase):
+    """Sizes and offsets of the ctypes structures against the header"""
+
+    @classmethod
+    def setUpClass(cls):
+        lines = [
+            "#include <stddef.h>",
+            "#include <stdio.h>",
+            '#include "daswin.h"',
+            "int main(void) {",
+            '    printf("event %zu %zu\\n", sizeof(DaswinEvent), offsetof(DaswinEvent, data));',
+        ]
+        for member, kind in daswin._EventData._fields_:
+            lines.append(
+                '    printf("{0} %zu\\n", sizeof(((DaswinEventData *)0)->{0}));'.format(member)
+            )
+            for field, _ in getattr(kind, "_fields_", []):
+                lines.append(
+                    '    printf("{0}.{1} %zu\\n", offsetof(DaswinEventData, {0}.{1}));'.format(
+                        member, field
+                    )
+                )
+        lines += ["    return 0;", "}"]
+        with tempfile.TemporaryDirectory() as tmp:
+            source = os.path.join(tmp, "layout.c")
+            binary = os.path.join(tmp, "layout")
+            with open(source, "w") as f:
+                f.write("\n".join(lines))
+            subprocess.run(
+                ["cc", "-std=c11", "-I", os.path.dirname(HEADER), source, "-o", binary],
+                check=True,
+            )
+            output = subprocess.run([binary], check=True, capture_output=True, text=True)
+        cls.layout = {}
+        for line in output.stdout.splitlines():
+            name, *values = line.split()
+            cls.layout[name] = [int(value) for value in values]
+
+    def test_event(self):
+        self.assertEqual(
+            self.layout["event"],
+            [ctypes.sizeof(daswin._Event), daswin._Event.data.offset],
+        )
+
+    def test_members(self):
+        for member, kind in daswin._EventData._fields_:
+            self.assertEqual(self.layout[member], [ctypes.sizeof(kind)], member)
+            for field, _ in getattr(kind, "_fields_", []):
+                name = "{}.{}".format(member, field)
+                self.assertEqual(self.layout[name], [getattr(kind, field).offset], name)
+
+
+def built_library():
+    for profile in ("debug", "release"):
+        path = os.path.join(ROOT, "target", profile, "libdaswin.so")
+        if os.path.exists(path):
+            return path
+    return None
+
+
+class FakeWindowTest(unittest.TestCase):
+    def setUp(self):
+        daswin._lib = None
+
+    def tearDown(self):
+        daswin._lib = None
+
+    def window(self, lib):
+        daswin._lib = lib
+        return daswin.Window("test", 64, 64)
+
+    def test_poll_events_returns_one_batch(self):
+        close = raw_event(daswin._CLOSE_REQUESTED, "close_requested", window=3)
+        resized = raw_event(daswin._RESIZED, "resized", window=3, width=8, height=6)
+        # A busy connection, every poll finds something
+        lib = FakeLibrary([[close, resized]] + [[resized]] * 100)
+        with self.window(lib) as window:
+            self.assertEqual(
+                window.poll_events(),
+                [daswin.CloseRequested(3), daswin.Resized(3, 8, 6)],
+            )
+            self.assertEqual(window.poll_events(), [daswin.Resized(3, 8, 6)])
+        self.assertEqual(lib.polls, 2)
+
+    def test_poll_events_without_events(self):
+        with self.window(FakeLibrary([])) as window:
+            self.assertEqual(window.poll_events(), [])
+            self.assertEqual(window.poll_events(), [])
+
+    def test_wait_event(self):
+        close = raw_event(daswin._CLOSE_REQUESTED, "close_requested", window=3)
+        lib = FakeLibrary([[close], [], []])
+        with self.window(lib) as window:
+            self.assertEqual(window.wait_event(), [daswin.CloseRequested(3)])
+            self.assertEqual(window.wait_event(0.0015), [])
+            self.assertEqual(window.wait_event(-1), [])
+        self.assertEqual(lib.waits, [-1, 2, 0])
+
+    def test_strings(self):
+        lib = FakeLibrary(
+            [
+                [
+                    raw_event(daswin._TEXT, "text", window=1, index=0),
+                    raw_event(daswin._GPU_ERROR, "gpu_error", index=1),
+                    raw_event(daswin._SCRIPT_ERROR, "script_error", index=2),
+                    raw_event(daswin._TEXT, "text", window=1, index=7),
+                ]
+            ],
+            ["h\u00e9llo", "device lost", "line 3: unknown function"],
+        )
+        with self.window(lib) as window:
+            events = window.poll_events()
+        self.assertEqual(
+            events,
+            [
+                daswin.Text(1, 0, "h\u00e9llo"),
+                daswin.GpuError(1, "device lost"),
+                daswin.ScriptError(2, "line 3: unknown function"),
+                daswin.Text(1, 7, ""),
+            ],
+        )
+
+
+class LibraryTest(unittest.TestCase):
+    def setUp(self):
+        self.saved = dict(os.environ)
+        daswin._lib = None
+
+    def tearDown(self):
+        os.environ.clear()
+        os.environ.update(self.saved)
+        daswin._lib = None
+
+    def test_missing_library(self):
+        os.environ["DASWIN_LIBRARY"] = os.path.join(HERE, "missing", "libdaswin.so")
+        with self.assertRaises(OSError):
+            daswin.Window("test", 64, 64)
+
+    @unittest.skipIf(built_library() is None, "libdaswin.so is not built")
+    def test_calls_release_the_gil(self):
+        os.environ["DASWIN_LIBRARY"] = built_library()
+        try:
+            lib = daswin._library()
+        except AttributeError:
+            self.skipTest("libdaswin.so is built without the capi feature")
+        # PyDLL would hold the GIL through daswin_wait_events
+        self.assertIs(type(lib), ctypes.CDLL)
+        self.assertEqual(lib.daswin_wait_events.argtypes, [ctypes.c_void_p, ctypes.c_int64])
+
+    @unittest.skipIf(built_library() is None, "libdaswin.so is not built")
+    def test_window_without_display(self):
+        os.environ["DASWIN_LIBRARY"] = built_library()
+        os.environ.pop("DISPLAY", None)
+        os.environ.pop("DASWIN_BACKEND", None)
+        try:
+            lib = daswin._library()
+            lib.daswin_window_new
+        except (OSError, AttributeError):
+            self.skipTest("libdaswin.so is built without the capi feature")
+        with self.assertRaises(RuntimeError):
+            daswin.Window("test", 64, 64)
+
+
+if __name__ == "__main__":
+    unittest.main()
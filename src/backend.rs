@@ -0,0 +1,222 @@
+//! Choosing the windowing system an EventLoop connects to
+
+use std::env;
+use std::error;
+use std::fmt;
+
+/// Windowing system of an EventLoop
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// The first backend which connects, tried in the order of this enum
+    #[default]
+    Auto,
+    X11,
+    Wayland,
+}
+
+impl Backend {
+    /// Parse a backend name as DASWIN_BACKEND takes it, case insensitive
+    pub fn from_name(name: &str) -> Option<Backend> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" => Some(Backend::Auto),
+            "x11" => Some(Backend::X11),
+            "wayland" => Some(Backend::Wayland),
+            _ => None,
+        }
+    }
+
+    /// The backend named by DASWIN_BACKEND, None when it is unset or
+    /// names no backend
+    pub(crate) fn from_env() -> Option<Backend> {
+        let name = env::var("DASWIN_BACKEND").ok()?;
+        let backend = Backend::from_name(&name);
+        if backend.is_none() {
+            log::warn!(
+                "ignoring DASWIN_BACKEND={:?}, expected x11, wayland or auto",
+                name
+            );
+        }
+        backend
+    }
+
+    /// The backends to try in order
+    pub(crate) fn candidates(self) -> &'static [Backend] {
+        match self {
+            Backend::Auto => &[Backend::X11, Backend::Wayland],
+            Backend::X11 => &[Backend::X11],
+            Backend::Wayland => &[Backend::Wayland],
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Backend::Auto => "auto",
+            Backend::X11 => "x11",
+            Backend::Wayland => "wayland",
+        })
+    }
+}
+
+/// Why a backend could not be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BackendFailure {
+    /// DISPLAY is unset or empty
+    MissingDisplay,
+    /// WAYLAND_DISPLAY is unset or empty
+    MissingWaylandDisplay,
+    /// Support for the backend is not built into this version of daswin
+    NotCompiled,
+    /// The display server refused the connection or is not running
+    ConnectionFailed(String),
+}
+
+impl fmt::Display for BackendFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BackendFailure::MissingDisplay => write!(f, "DISPLAY is not set"),
+            BackendFailure::MissingWaylandDisplay => write!(f, "WAYLAND_DISPLAY is not set"),
+            BackendFailure::NotCompiled => write!(f, "support is not compiled in"),
+            BackendFailure::ConnectionFailed(display) => {
+                write!(f, "cannot connect to display {:?}", display)
+            }
+        }
+    }
+}
+
+/// No backend could be used, lists every backend tried along with why
+/// it failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendError {
+    pub tried: Vec<(Backend, BackendFailure)>,
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no windowing backend available")?;
+        for (i, (backend, failure)) in self.tried.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { ", " };
+            write!(f, "{}{} ({})", separator, backend, failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for BackendError {}
+
+/// Try the candidates of `backend` in order, `var` reading the
+/// environment and `connect_x11` connecting to the display it names
+pub(crate) fn connect<T>(
+    backend: Backend,
+    var: impl Fn(&str) -> Option<String>,
+    mut connect_x11: impl FnMut() -> Option<T>,
+) -> Result<T, BackendError> {
+    let set = |name| var(name).is_some_and(|value| !value.is_empty());
+    let mut tried = Vec::new();
+    for &candidate in backend.candidates() {
+        let failure = match candidate {
+            Backend::X11 if !set("DISPLAY") => BackendFailure::MissingDisplay,
+            Backend::X11 => match connect_x11() {
+                Some(connected) => return Ok(connected),
+                None => BackendFailure::ConnectionFailed(var("DISPLAY").unwrap_or_default()),
+            },
+            Backend::Wayland if !set("WAYLAND_DISPLAY") => BackendFailure::MissingWaylandDisplay,
+            Backend::Wayland => BackendFailure::NotCompiled,
+            Backend::Auto => continue,
+        };
+        log::info!("{} backend unavailable: {}", candidate, failure);
+        tried.push((candidate, failure));
+    }
+    Err(BackendError { tried })
+}
+
+/// The variable is set to a non-empty value
+pub(crate) fn env_set(name: &str) -> bool {
+    env::var_os(name).is_some_and(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Connects to the X server with `vars` as the environment, `reachable`
+    // telling whether one is running
+    fn connect_with(
+        backend: Backend,
+        vars: &[(&str, &str)],
+        reachable: bool,
+    ) -> Result<Backend, BackendError> {
+        let var = |name: &str| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        };
+        connect(backend, var, || reachable.then_some(Backend::X11))
+    }
+
+    fn message(backend: Backend, vars: &[(&str, &str)]) -> String {
+        connect_with(backend, vars, false).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn failure_messages() {
+        assert_eq!(
+            message(Backend::X11, &[]),
+            "no windowing backend available: x11 (DISPLAY is not set)"
+        );
+        assert_eq!(
+            message(Backend::X11, &[("DISPLAY", "")]),
+            "no windowing backend available: x11 (DISPLAY is not set)"
+        );
+        assert_eq!(
+            message(Backend::Wayland, &[("DISPLAY", ":0")]),
+            "no windowing backend available: wayland (WAYLAND_DISPLAY is not set)"
+        );
+        assert_eq!(
+            message(Backend::Auto, &[]),
+            "no windowing backend available: x11 (DISPLAY is not set), \
+             wayland (WAYLAND_DISPLAY is not set)"
+        );
+        assert_eq!(
+            message(
+                Backend::Auto,
+                &[("DISPLAY", ":0"), ("WAYLAND_DISPLAY", "wayland-0")]
+            ),
+            "no windowing backend available: x11 (cannot connect to display \":0\"), \
+             wayland (support is not compiled in)"
+        );
+    }
+
+    #[test]
+    fn failures_list_every_backend_tried() {
+        let err = connect_with(Backend::Auto, &[("WAYLAND_DISPLAY", "wayland-0")], false);
+        assert_eq!(
+            err.unwrap_err().tried,
+            [
+                (Backend::X11, BackendFailure::MissingDisplay),
+                (Backend::Wayland, BackendFailure::NotCompiled),
+            ]
+        );
+    }
+
+    #[test]
+    fn first_working_backend_is_taken() {
+        assert_eq!(
+            connect_with(Backend::Auto, &[("DISPLAY", ":1")], true),
+            Ok(Backend::X11)
+        );
+        assert!(connect_with(Backend::Wayland, &[("DISPLAY", ":1")], true).is_err());
+    }
+
+    #[test]
+    fn names() {
+        for backend in [Backend::Auto, Backend::X11, Backend::Wayland] {
+            assert_eq!(Backend::from_name(&backend.to_string()), Some(backend));
+        }
+        assert_eq!(Backend::from_name("X11"), Some(Backend::X11));
+        assert_eq!(Backend::from_name("Wayland"), Some(Backend::Wayland));
+        assert_eq!(Backend::from_name("xcb"), None);
+    }
+}
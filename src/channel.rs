@@ -0,0 +1,344 @@
+//! Typed message channels into the event loop, one per subsystem, see
+//! EventLoop::create_channel
+
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::error;
+use std::fmt;
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::marker::PhantomData;
+use std::mem;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Weak};
+#[cfg(feature = "async")]
+use std::task::{Context, Poll, Waker};
+
+use crate::event::{ChannelId, Event};
+use crate::wake::WakePipe;
+
+/// The queues of every channel of a loop, shared with its proxies
+pub(crate) struct Channels {
+    inner: Mutex<Queues>,
+}
+
+#[derive(Default)]
+struct Queues {
+    // Counted up for every channel created
+    next: u32,
+    // A VecDeque<M> for each channel, keyed by id
+    queues: HashMap<ChannelId, Box<dyn Any + Send>>,
+    // Channels sent to since the last poll, in the order of their first
+    // message
+    pending: Vec<ChannelId>,
+    // Room left in bounded channels, see EventLoop::create_async_channel
+    #[cfg(feature = "async")]
+    bounds: HashMap<ChannelId, Bound>,
+    // Woken by every send, see EventLoop::event_stream
+    #[cfg(feature = "async")]
+    listener: Option<Waker>,
+    // Written by every send, see EventLoop::run
+    wake: Option<Arc<WakePipe>>,
+}
+
+#[cfg(feature = "async")]
+struct Bound {
+    capacity: usize,
+    queued: usize,
+    // Senders waiting for room
+    blocked: Vec<Waker>,
+}
+
+impl Queues {
+    fn push<M: 'static + Send>(&mut self, channel: ChannelId, message: M) {
+        let queue = self
+            .queues
+            .get_mut(&channel)
+            .and_then(|queue| queue.downcast_mut::<VecDeque<M>>())
+            .expect("channel created with another message type");
+        queue.push_back(message);
+        if !self.pending.contains(&channel) {
+            self.pending.push(channel);
+        }
+        if let Some(wake) = &self.wake {
+            wake.wake();
+        }
+        #[cfg(feature = "async")]
+        {
+            if let Some(bound) = self.bounds.get_mut(&channel) {
+                bound.queued += 1;
+            }
+            if let Some(listener) = &self.listener {
+                listener.wake_by_ref();
+            }
+        }
+    }
+}
+
+impl Channels {
+    pub fn new(wake: Arc<WakePipe>) -> Channels {
+        let queues = Queues {
+            wake: Some(wake),
+            ..Queues::default()
+        };
+        Channels {
+            inner: Mutex::new(queues),
+        }
+    }
+
+    /// Write sends to `wake` from now on, once the loop reconnected
+    pub fn set_wake(&self, wake: Arc<WakePipe>) {
+        self.inner.lock().unwrap().wake = Some(wake);
+    }
+
+    pub fn create<M: 'static + Send>(self: &Arc<Self>) -> (ChannelProxy<M>, ChannelId) {
+        let mut inner = self.inner.lock().unwrap();
+        let id = ChannelId(inner.next);
+        inner.next += 1;
+        inner.queues.insert(id, Box::new(VecDeque::<M>::new()));
+        let proxy = ChannelProxy {
+            channel: id,
+            channels: Arc::downgrade(self),
+            _message: PhantomData,
+        };
+        (proxy, id)
+    }
+
+    /// A channel holding at most `capacity` messages not drained yet
+    #[cfg(feature = "async")]
+    pub fn create_bounded<M: 'static + Send>(
+        self: &Arc<Self>,
+        capacity: usize,
+    ) -> (AsyncChannelProxy<M>, ChannelId) {
+        let (proxy, id) = self.create();
+        let bound = Bound {
+            capacity: capacity.max(1),
+            queued: 0,
+            blocked: Vec::new(),
+        };
+        self.inner.lock().unwrap().bounds.insert(id, bound);
+        (AsyncChannelProxy { proxy }, id)
+    }
+
+    /// Take the messages sent over the channel, oldest first
+    pub fn drain<M: 'static + Send>(&self, id: ChannelId) -> Result<Vec<M>, ChannelError> {
+        let mut inner = self.inner.lock().unwrap();
+        let queue = inner
+            .queues
+            .get_mut(&id)
+            .ok_or(ChannelError::UnknownChannel)?
+            .downcast_mut::<VecDeque<M>>()
+            .ok_or(ChannelError::WrongType)?;
+        let messages = mem::take(queue).into();
+        #[cfg(feature = "async")]
+        if let Some(bound) = inner.bounds.get_mut(&id) {
+            bound.queued = 0;
+            for sender in bound.blocked.drain(..) {
+                sender.wake();
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Wake `listener` whenever a message is sent, None stops
+    #[cfg(feature = "async")]
+    pub fn set_listener(&self, listener: Option<Waker>) {
+        self.inner.lock().unwrap().listener = listener;
+    }
+
+    /// Report the channels sent to since the last call
+    pub fn poll(&self, events: &mut Vec<Event>) {
+        let mut inner = self.inner.lock().unwrap();
+        events.extend(inner.pending.drain(..).map(Event::Channel));
+    }
+}
+
+/// Sends messages of type M to the event loop from any thread, see
+/// EventLoop::create_channel.
+///
+/// Messages arrive in the order they were sent, each poll delivering
+/// Event::Channel once for every channel sent to since the last one
+pub struct ChannelProxy<M> {
+    channel: ChannelId,
+    channels: Weak<Channels>,
+    _message: PhantomData<fn(M)>,
+}
+
+impl<M: 'static + Send> ChannelProxy<M> {
+    /// The channel the proxy sends to
+    pub fn id(&self) -> ChannelId {
+        self.channel
+    }
+
+    /// Queue the message for the loop, handing it back once the loop
+    /// was dropped
+    pub fn send(&self, message: M) -> Result<(), EventLoopClosed<M>> {
+        let channels = match self.channels.upgrade() {
+            Some(channels) => channels,
+            None => return Err(EventLoopClosed(message)),
+        };
+        channels.inner.lock().unwrap().push(self.channel, message);
+        Ok(())
+    }
+}
+
+impl<M> Clone for ChannelProxy<M> {
+    fn clone(&self) -> Self {
+        ChannelProxy {
+            channel: self.channel,
+            channels: self.channels.clone(),
+            _message: PhantomData,
+        }
+    }
+}
+
+impl<M> fmt::Debug for ChannelProxy<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChannelProxy")
+            .field("channel", &self.channel)
+            .field("closed", &(self.channels.strong_count() == 0))
+            .finish()
+    }
+}
+
+#[cfg(feature = "async")]
+impl Drop for Channels {
+    /// Let blocked senders find out the loop is gone
+    fn drop(&mut self) {
+        let inner = self.inner.get_mut().unwrap();
+        for bound in inner.bounds.values_mut() {
+            for sender in bound.blocked.drain(..) {
+                sender.wake();
+            }
+        }
+    }
+}
+
+/// Sends messages of type M to the event loop from async code, waiting
+/// while the channel is full, see EventLoop::create_async_channel
+#[cfg(feature = "async")]
+pub struct AsyncChannelProxy<M> {
+    proxy: ChannelProxy<M>,
+}
+
+#[cfg(feature = "async")]
+impl<M: 'static + Send> AsyncChannelProxy<M> {
+    /// The channel the proxy sends to
+    pub fn id(&self) -> ChannelId {
+        self.proxy.channel
+    }
+
+    /// Queue the message once the channel has room, handing it back once
+    /// the loop was dropped. Dropping the future before it completes
+    /// drops the message unsent, messages of completed sends are kept
+    pub fn send(&self, message: M) -> SendMessage<'_, M> {
+        SendMessage {
+            proxy: &self.proxy,
+            message: Some(message),
+        }
+    }
+
+    /// Queue the message now, also when the channel is full
+    pub fn send_now(&self, message: M) -> Result<(), EventLoopClosed<M>> {
+        self.proxy.send(message)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<M> Clone for AsyncChannelProxy<M> {
+    fn clone(&self) -> Self {
+        AsyncChannelProxy {
+            proxy: self.proxy.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<M> fmt::Debug for AsyncChannelProxy<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AsyncChannelProxy")
+            .field("channel", &self.proxy.channel)
+            .field("closed", &(self.proxy.channels.strong_count() == 0))
+            .finish()
+    }
+}
+
+/// The future of AsyncChannelProxy::send
+#[cfg(feature = "async")]
+pub struct SendMessage<'a, M> {
+    proxy: &'a ChannelProxy<M>,
+    message: Option<M>,
+}
+
+#[cfg(feature = "async")]
+impl<M> Unpin for SendMessage<'_, M> {}
+
+#[cfg(feature = "async")]
+impl<M: 'static + Send> Future for SendMessage<'_, M> {
+    type Output = Result<(), EventLoopClosed<M>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let message = self
+            .message
+            .take()
+            .expect("SendMessage polled after completion");
+        let channels = match self.proxy.channels.upgrade() {
+            Some(channels) => channels,
+            None => return Poll::Ready(Err(EventLoopClosed(message))),
+        };
+        let mut inner = channels.inner.lock().unwrap();
+        let channel = self.proxy.channel;
+        let bound = inner
+            .bounds
+            .get_mut(&channel)
+            .expect("AsyncChannelProxy of an unbounded channel");
+        if bound.queued >= bound.capacity {
+            if !bound
+                .blocked
+                .iter()
+                .any(|sender| sender.will_wake(cx.waker()))
+            {
+                bound.blocked.push(cx.waker().clone());
+            }
+            drop(inner);
+            self.message = Some(message);
+            return Poll::Pending;
+        }
+        inner.push(channel, message);
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The event loop a message was sent to is gone, the message is handed
+/// back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventLoopClosed<M>(pub M);
+
+impl<M> fmt::Display for EventLoopClosed<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the event loop was closed")
+    }
+}
+
+impl<M: fmt::Debug> error::Error for EventLoopClosed<M> {}
+
+/// Why EventLoop::drain_channel failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelError {
+    /// The loop created no channel with the id
+    UnknownChannel,
+    /// The channel carries messages of another type
+    WrongType,
+}
+
+impl fmt::Display for ChannelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChannelError::UnknownChannel => write!(f, "no channel with this id"),
+            ChannelError::WrongType => write!(f, "the channel carries another message type"),
+        }
+    }
+}
+
+impl error::Error for ChannelError {}
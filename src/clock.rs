@@ -0,0 +1,149 @@
+//! Relating X server timestamps and Present times to Instant, for
+//! measuring latency from input to the screen
+
+use std::mem;
+use std::ptr;
+use std::time::{Duration, Instant};
+
+use x11::xlib;
+
+use crate::xconn::XConnection;
+
+// Round trips timed by a calibration, the fastest one is used
+const ROUND_TRIPS: u32 = 8;
+
+/// The X server time of an input event, in milliseconds since an
+/// arbitrary point and wrapping around every 49.7 days
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventTime(u32);
+
+impl EventTime {
+    pub fn from_millis(millis: u32) -> EventTime {
+        EventTime(millis)
+    }
+
+    pub fn as_millis(self) -> u32 {
+        self.0
+    }
+
+    /// Time from `earlier` to this one, zero when `earlier` is later
+    pub fn duration_since(self, earlier: EventTime) -> Duration {
+        match self.0.wrapping_sub(earlier.0) {
+            millis if millis <= i32::MAX as u32 => Duration::from_millis(millis as u64),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// Maps server timestamps and Present UST onto Instant, see
+/// EventLoop::calibrate_clock.
+///
+/// Server time is sampled by timing round trips which the server
+/// stamps, with the server assumed to stamp halfway through. The UST of
+/// Present is the monotonic clock of the machine running the server,
+/// which is only comparable to Instant for local servers. The clocks
+/// drift apart slowly, calibrate again every few minutes for long
+/// measurements
+#[derive(Debug, Clone, Copy)]
+pub struct ServerClock {
+    instant: Instant,
+    server: EventTime,
+    monotonic: Duration,
+    uncertainty: Duration,
+}
+
+impl ServerClock {
+    /// When an event with the timestamp happened
+    pub fn instant(&self, time: EventTime) -> Instant {
+        let millis = time.0.wrapping_sub(self.server.0) as i32;
+        shift(self.instant, millis as i128 * 1_000_000)
+    }
+
+    /// When a Present UST was, e.g. of Event::FramePresented
+    pub fn ust_instant(&self, ust: Duration) -> Instant {
+        shift(
+            self.instant,
+            ust.as_nanos() as i128 - self.monotonic.as_nanos() as i128,
+        )
+    }
+
+    /// How far off instant may be: half the fastest round trip plus the
+    /// millisecond resolution of server time
+    pub fn uncertainty(&self) -> Duration {
+        self.uncertainty
+    }
+}
+
+fn shift(instant: Instant, nanos: i128) -> Instant {
+    let delta = Duration::from_nanos(nanos.unsigned_abs() as u64);
+    if nanos >= 0 {
+        instant + delta
+    } else {
+        instant.checked_sub(delta).unwrap_or(instant)
+    }
+}
+
+/// The monotonic clock, which Present UST is measured with
+fn monotonic_now() -> Duration {
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut time);
+    }
+    Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
+
+/// Time property changes on a scratch window, whose PropertyNotify
+/// carries the server time
+pub(crate) unsafe fn calibrate(conn: &XConnection) -> ServerClock {
+    let display = conn.display;
+    let mut attributes: xlib::XSetWindowAttributes = mem::zeroed();
+    attributes.event_mask = xlib::PropertyChangeMask;
+    let window = xlib::XCreateWindow(
+        display,
+        conn.root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        0,
+        xlib::InputOnly as u32,
+        ptr::null_mut(),
+        xlib::CWEventMask,
+        &mut attributes,
+    );
+
+    let mut fastest: Option<(Duration, Instant, xlib::Time)> = None;
+    for _ in 0..ROUND_TRIPS {
+        let sent = Instant::now();
+        xlib::XChangeProperty(
+            display,
+            window,
+            conn.atoms.daswin_timestamp,
+            xlib::XA_STRING,
+            8,
+            xlib::PropModeAppend,
+            ptr::null(),
+            0,
+        );
+        let mut xevent: xlib::XEvent = mem::zeroed();
+        xlib::XWindowEvent(display, window, xlib::PropertyChangeMask, &mut xevent);
+        let round_trip = sent.elapsed();
+        if fastest.is_none_or(|(fastest, ..)| round_trip < fastest) {
+            fastest = Some((round_trip, sent + round_trip / 2, xevent.property.time));
+        }
+    }
+    xlib::XDestroyWindow(display, window);
+
+    let (round_trip, instant, time) = fastest.unwrap();
+    let (now, monotonic) = (Instant::now(), monotonic_now());
+    ServerClock {
+        instant,
+        server: EventTime(time as u32),
+        monotonic: monotonic.saturating_sub(now - instant),
+        uncertainty: round_trip / 2 + Duration::from_millis(1),
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+use std::mem;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+
+use x11::xlib;
+
+use crate::xconn::{Request, RequestQueue, XConnection};
+
+/// An XEvent handed over from the connection thread
+pub(crate) struct SendEvent(pub xlib::XEvent);
+
+// The display pointer inside is only dereferenced with XInitThreads active.
+unsafe impl Send for SendEvent {}
+
+/// A thread owning the X connection, which waits for events and runs
+/// requests marshalled from the event loop thread so rendering is never
+/// blocked on the server
+pub(crate) struct ConnectionThread {
+    pub events: mpsc::Receiver<SendEvent>,
+    pub capacity: usize,
+    running: Arc<AtomicBool>,
+    wake: RawFd,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ConnectionThread {
+    /// Open a connection owned by a new thread, at most `capacity` events
+    /// are buffered before the thread stops reading from the server. None
+    /// when the server cannot be reached
+    pub fn spawn(capacity: usize) -> Option<(Arc<XConnection>, ConnectionThread)> {
+        let mut fds = [0; 2];
+        unsafe {
+            if libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) != 0 {
+                panic!("failed to create connection thread wakeup pipe");
+            }
+        }
+        let (wake_read, wake) = (fds[0], fds[1]);
+
+        let (sender, requests) = mpsc::channel();
+        let conn = match XConnection::open(Some(RequestQueue { sender, wake })) {
+            Some(conn) => Arc::new(conn),
+            None => {
+                // The queue closed the write end
+                unsafe {
+                    libc::close(wake_read);
+                }
+                return None;
+            }
+        };
+
+        let (event_sender, events) = mpsc::sync_channel(capacity);
+        let running = Arc::new(AtomicBool::new(true));
+        let handle = {
+            let conn = conn.clone();
+            let running = running.clone();
+            thread::Builder::new()
+                .name("daswin-x11".into())
+                .spawn(move || run(conn, requests, event_sender, wake_read, running))
+                .expect("failed to spawn connection thread")
+        };
+
+        let thread = ConnectionThread {
+            events,
+            capacity,
+            running,
+            wake,
+            handle: Some(handle),
+        };
+        Some((conn, thread))
+    }
+}
+
+impl Drop for ConnectionThread {
+    /// Stops the thread, requests made afterwards run on the caller's thread
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Release);
+        unsafe {
+            libc::write(self.wake, [1u8].as_ptr() as *const _, 1);
+        }
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run(
+    conn: Arc<XConnection>,
+    requests: mpsc::Receiver<Request>,
+    events: mpsc::SyncSender<SendEvent>,
+    wake: RawFd,
+    running: Arc<AtomicBool>,
+) {
+    XConnection::enter_connection_thread();
+    let display = conn.display;
+    let mut backlog = VecDeque::new();
+    let mut fds = [
+        libc::pollfd {
+            fd: unsafe { xlib::XConnectionNumber(display) },
+            events: libc::POLLIN,
+            revents: 0,
+        },
+        libc::pollfd {
+            fd: wake,
+            events: libc::POLLIN,
+            revents: 0,
+        },
+    ];
+
+    // The socket stays readable once the server is gone
+    while running.load(Ordering::Acquire) && !conn.is_lost() {
+        while let Ok(request) = requests.try_recv() {
+            request(&conn);
+        }
+
+        // Stop reading from the server while the consumer is behind, the
+        // backlog drains as the event loop catches up.
+        unsafe {
+            while backlog.is_empty() && xlib::XPending(display) != 0 {
+                let mut xevent: xlib::XEvent = mem::zeroed();
+                xlib::XNextEvent(display, &mut xevent);
+                // Cookie data is freed by the next XNextEvent unless claimed,
+                // the event loop releases it after translation
+                if xevent.get_type() == xlib::GenericEvent {
+                    xlib::XGetEventData(display, &mut xevent.generic_event_cookie);
+                }
+                backlog.push_back(SendEvent(xevent));
+            }
+        }
+        let mut sent = false;
+        while let Some(event) = backlog.pop_front() {
+            match events.try_send(event) {
+                Ok(()) => sent = true,
+                Err(mpsc::TrySendError::Full(event)) => {
+                    backlog.push_front(event);
+                    break;
+                }
+                Err(mpsc::TrySendError::Disconnected(_)) => return,
+            }
+        }
+        if sent {
+            conn.wake.wake();
+        }
+
+        let timeout = if backlog.is_empty() { -1 } else { 1 };
+        unsafe {
+            if xlib::XPending(display) == 0 || !backlog.is_empty() {
+                libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout);
+            }
+            let mut buf = [0u8; 64];
+            while libc::read(wake, buf.as_mut_ptr() as *mut _, buf.len()) > 0 {}
+        }
+    }
+
+    // The loop notices the lost connection on its next poll
+    conn.wake.wake();
+    // Nobody may be left waiting on a reply.
+    while let Ok(request) = requests.try_recv() {
+        request(&conn);
+    }
+    unsafe {
+        libc::close(wake);
+    }
+}
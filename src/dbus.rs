@@ -0,0 +1,1111 @@
+//! A minimal D-Bus client speaking the wire protocol over the bus socket,
+//! enough to call methods, receive signals and export simple objects
+//! without linking libdbus
+
+use std::collections::VecDeque;
+use std::env;
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::ptr;
+use std::time::{Duration, Instant};
+
+/// How long a method call waits for its reply
+const CALL_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Messages larger than this are a protocol error
+const MAX_MESSAGE_LEN: usize = 1 << 27;
+
+/// A value of the D-Bus type system
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Byte(u8),
+    Bool(bool),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Str(String),
+    Path(String),
+    Signature(String),
+    /// Index into the unix fds passed along with the message
+    Fd(u32),
+    /// Elements of the given signature
+    Array(String, Vec<Value>),
+    Struct(Vec<Value>),
+    DictEntry(Box<Value>, Box<Value>),
+    Variant(Box<Value>),
+}
+
+impl Value {
+    pub fn signature(&self) -> String {
+        match self {
+            Value::Byte(_) => "y".into(),
+            Value::Bool(_) => "b".into(),
+            Value::I16(_) => "n".into(),
+            Value::U16(_) => "q".into(),
+            Value::I32(_) => "i".into(),
+            Value::U32(_) => "u".into(),
+            Value::I64(_) => "x".into(),
+            Value::U64(_) => "t".into(),
+            Value::F64(_) => "d".into(),
+            Value::Str(_) => "s".into(),
+            Value::Path(_) => "o".into(),
+            Value::Signature(_) => "g".into(),
+            Value::Fd(_) => "h".into(),
+            Value::Array(element, _) => format!("a{}", element),
+            Value::Struct(fields) => {
+                let fields: String = fields.iter().map(Value::signature).collect();
+                format!("({})", fields)
+            }
+            Value::DictEntry(key, value) => {
+                format!("{{{}{}}}", key.signature(), value.signature())
+            }
+            Value::Variant(_) => "v".into(),
+        }
+    }
+}
+
+/// Alignment of the first type in a signature
+fn alignment(signature: &[u8]) -> usize {
+    match signature.first() {
+        Some(b'y') | Some(b'g') | Some(b'v') => 1,
+        Some(b'n') | Some(b'q') => 2,
+        Some(b'x') | Some(b't') | Some(b'd') | Some(b'(') | Some(b'{') => 8,
+        _ => 4,
+    }
+}
+
+/// Length of the first complete type in a signature
+fn type_len(signature: &[u8]) -> io::Result<usize> {
+    match signature.first() {
+        Some(b'a') => Ok(1 + type_len(&signature[1..])?),
+        Some(b'(') | Some(b'{') => {
+            let mut depth = 0;
+            for (i, &c) in signature.iter().enumerate() {
+                match c {
+                    b'(' | b'{' => depth += 1,
+                    b')' | b'}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Ok(i + 1);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Err(invalid("unbalanced signature"))
+        }
+        Some(_) => Ok(1),
+        None => Err(invalid("empty signature")),
+    }
+}
+
+/// Split a signature into its complete types
+fn split_types(mut signature: &str) -> io::Result<Vec<&str>> {
+    let mut types = Vec::new();
+    while !signature.is_empty() {
+        let len = type_len(signature.as_bytes())?;
+        types.push(&signature[..len]);
+        signature = &signature[len..];
+    }
+    Ok(types)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Marshals values in little endian, offsets are relative to the start
+/// of the message or body, which are both 8 byte aligned
+#[derive(Default)]
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn align(&mut self, alignment: usize) {
+        while !self.buf.len().is_multiple_of(alignment) {
+            self.buf.push(0);
+        }
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write(&mut self, value: &Value) {
+        match value {
+            Value::Byte(value) => self.buf.push(*value),
+            Value::Bool(value) => self.u32(*value as u32),
+            Value::I16(value) => {
+                self.align(2);
+                self.buf.extend_from_slice(&value.to_le_bytes());
+            }
+            Value::U16(value) => {
+                self.align(2);
+                self.buf.extend_from_slice(&value.to_le_bytes());
+            }
+            Value::I32(value) => {
+                self.align(4);
+                self.buf.extend_from_slice(&value.to_le_bytes());
+            }
+            Value::U32(value) | Value::Fd(value) => self.u32(*value),
+            Value::I64(value) => {
+                self.align(8);
+                self.buf.extend_from_slice(&value.to_le_bytes());
+            }
+            Value::U64(value) => {
+                self.align(8);
+                self.buf.extend_from_slice(&value.to_le_bytes());
+            }
+            Value::F64(value) => {
+                self.align(8);
+                self.buf.extend_from_slice(&value.to_le_bytes());
+            }
+            Value::Str(value) | Value::Path(value) => {
+                // The bus disconnects senders of strings with nul bytes
+                let value = value.split('\0').next().unwrap();
+                self.u32(value.len() as u32);
+                self.buf.extend_from_slice(value.as_bytes());
+                self.buf.push(0);
+            }
+            Value::Signature(value) => {
+                self.buf.push(value.len() as u8);
+                self.buf.extend_from_slice(value.as_bytes());
+                self.buf.push(0);
+            }
+            Value::Array(element, items) => {
+                self.u32(0);
+                let len_at = self.buf.len() - 4;
+                // The length excludes the padding to the first element
+                self.align(alignment(element.as_bytes()));
+                let start = self.buf.len();
+                for item in items {
+                    self.write(item);
+                }
+                let len = (self.buf.len() - start) as u32;
+                self.buf[len_at..len_at + 4].copy_from_slice(&len.to_le_bytes());
+            }
+            Value::Struct(fields) => {
+                self.align(8);
+                for field in fields {
+                    self.write(field);
+                }
+            }
+            Value::DictEntry(key, value) => {
+                self.align(8);
+                self.write(key);
+                self.write(value);
+            }
+            Value::Variant(value) => {
+                self.write(&Value::Signature(value.signature()));
+                self.write(value);
+            }
+        }
+    }
+}
+
+/// Unmarshals values in the byte order of the message
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+macro_rules! read_number {
+    ($reader:expr, $ty:ty) => {{
+        const LEN: usize = mem::size_of::<$ty>();
+        $reader.align(LEN)?;
+        let mut bytes = [0; LEN];
+        bytes.copy_from_slice($reader.take(LEN)?);
+        if $reader.big_endian {
+            <$ty>::from_be_bytes(bytes)
+        } else {
+            <$ty>::from_le_bytes(bytes)
+        }
+    }};
+}
+
+impl<'a> Reader<'a> {
+    fn align(&mut self, alignment: usize) -> io::Result<()> {
+        self.pos = self.pos.div_ceil(alignment) * alignment;
+        if self.pos > self.buf.len() {
+            return Err(invalid("message truncated"));
+        }
+        Ok(())
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| invalid("message truncated"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn string(&mut self, len: usize) -> io::Result<String> {
+        let bytes = self.take(len)?.to_vec();
+        self.take(1)?;
+        String::from_utf8(bytes).map_err(|_| invalid("string is not UTF-8"))
+    }
+
+    /// Read one value of a single complete type
+    fn read(&mut self, signature: &str) -> io::Result<Value> {
+        Ok(match signature.as_bytes()[0] {
+            b'y' => Value::Byte(self.take(1)?[0]),
+            b'b' => Value::Bool(read_number!(self, u32) != 0),
+            b'n' => Value::I16(read_number!(self, i16)),
+            b'q' => Value::U16(read_number!(self, u16)),
+            b'i' => Value::I32(read_number!(self, i32)),
+            b'u' => Value::U32(read_number!(self, u32)),
+            b'x' => Value::I64(read_number!(self, i64)),
+            b't' => Value::U64(read_number!(self, u64)),
+            b'd' => Value::F64(read_number!(self, f64)),
+            b'h' => Value::Fd(read_number!(self, u32)),
+            b's' => {
+                let len = read_number!(self, u32) as usize;
+                Value::Str(self.string(len)?)
+            }
+            b'o' => {
+                let len = read_number!(self, u32) as usize;
+                Value::Path(self.string(len)?)
+            }
+            b'g' => {
+                let len = self.take(1)?[0] as usize;
+                Value::Signature(self.string(len)?)
+            }
+            b'v' => {
+                let len = self.take(1)?[0] as usize;
+                let signature = self.string(len)?;
+                if type_len(signature.as_bytes())? != signature.len() {
+                    return Err(invalid("variant holds more than one type"));
+                }
+                Value::Variant(Box::new(self.read(&signature)?))
+            }
+            b'a' => {
+                let element = &signature[1..];
+                let len = read_number!(self, u32) as usize;
+                self.align(alignment(element.as_bytes()))?;
+                let end = self.pos + len;
+                let mut items = Vec::new();
+                while self.pos < end {
+                    items.push(self.read(element)?);
+                }
+                if self.pos != end {
+                    return Err(invalid("array length splits an element"));
+                }
+                Value::Array(element.into(), items)
+            }
+            b'(' => {
+                self.align(8)?;
+                let types = split_types(&signature[1..signature.len() - 1])?;
+                // An empty struct takes no bytes, an array of them no end
+                if types.is_empty() {
+                    return Err(invalid("empty struct"));
+                }
+                let fields = types
+                    .into_iter()
+                    .map(|field| self.read(field))
+                    .collect::<io::Result<_>>()?;
+                Value::Struct(fields)
+            }
+            b'{' => {
+                self.align(8)?;
+                let types = split_types(&signature[1..signature.len() - 1])?;
+                if types.len() != 2 {
+                    return Err(invalid("dict entry must hold a key and a value"));
+                }
+                let key = self.read(types[0])?;
+                let value = self.read(types[1])?;
+                Value::DictEntry(Box::new(key), Box::new(value))
+            }
+            _ => return Err(invalid("unknown type in signature")),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MessageType {
+    MethodCall = 1,
+    MethodReturn = 2,
+    Error = 3,
+    Signal = 4,
+}
+
+/// A message along with the header fields we use
+#[derive(Debug)]
+pub(crate) struct Message {
+    pub kind: MessageType,
+    // Serial the message was received with, replies refer to it
+    pub serial: u32,
+    pub reply_serial: Option<u32>,
+    pub path: Option<String>,
+    pub interface: Option<String>,
+    pub member: Option<String>,
+    pub error_name: Option<String>,
+    pub destination: Option<String>,
+    pub sender: Option<String>,
+    pub body: Vec<Value>,
+    // Received unix fds, indexed by Value::Fd
+    pub fds: Vec<OwnedFd>,
+}
+
+impl Message {
+    pub fn method_call(
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        body: Vec<Value>,
+    ) -> Message {
+        Message {
+            kind: MessageType::MethodCall,
+            serial: 0,
+            reply_serial: None,
+            path: Some(path.into()),
+            interface: Some(interface.into()),
+            member: Some(member.into()),
+            error_name: None,
+            destination: Some(destination.into()),
+            sender: None,
+            body,
+            fds: Vec::new(),
+        }
+    }
+
+    /// A signal broadcast from an object we export
+    pub fn signal(path: &str, interface: &str, member: &str, body: Vec<Value>) -> Message {
+        Message {
+            kind: MessageType::Signal,
+            destination: None,
+            ..Message::method_call("", path, interface, member, body)
+        }
+    }
+
+    /// The successful reply to a method call we received
+    pub fn method_return(call: &Message, body: Vec<Value>) -> Message {
+        Message {
+            kind: MessageType::MethodReturn,
+            serial: 0,
+            reply_serial: Some(call.serial),
+            path: None,
+            interface: None,
+            member: None,
+            error_name: None,
+            destination: call.sender.clone(),
+            sender: None,
+            body,
+            fds: Vec::new(),
+        }
+    }
+
+    /// The error reply to a method call we received
+    pub fn error(call: &Message, name: &str, detail: &str) -> Message {
+        Message {
+            kind: MessageType::Error,
+            error_name: Some(name.into()),
+            ..Message::method_return(call, vec![Value::Str(detail.into())])
+        }
+    }
+
+    /// Whether this is a call of the given method
+    pub fn is_method_call(&self, interface: &str, member: &str) -> bool {
+        self.kind == MessageType::MethodCall
+            && self.interface.as_deref() == Some(interface)
+            && self.member.as_deref() == Some(member)
+    }
+
+    /// Whether this is the given signal
+    pub fn is_signal(&self, interface: &str, member: &str) -> bool {
+        self.kind == MessageType::Signal
+            && self.interface.as_deref() == Some(interface)
+            && self.member.as_deref() == Some(member)
+    }
+
+    fn encode(&self, serial: u32) -> Vec<u8> {
+        let mut body = Writer::default();
+        for value in &self.body {
+            body.write(value);
+        }
+        let signature: String = self.body.iter().map(Value::signature).collect();
+
+        let mut fields = Vec::new();
+        let strings = [
+            (1, self.path.clone().map(Value::Path)),
+            (2, self.interface.clone().map(Value::Str)),
+            (3, self.member.clone().map(Value::Str)),
+            (4, self.error_name.clone().map(Value::Str)),
+            (5, self.reply_serial.map(Value::U32)),
+            (6, self.destination.clone().map(Value::Str)),
+            (7, self.sender.clone().map(Value::Str)),
+        ];
+        for (code, value) in strings {
+            if let Some(value) = value {
+                fields.push((code, value));
+            }
+        }
+        if !signature.is_empty() {
+            fields.push((8, Value::Signature(signature)));
+        }
+        if !self.fds.is_empty() {
+            fields.push((9, Value::U32(self.fds.len() as u32)));
+        }
+        let fields = fields
+            .into_iter()
+            .map(|(code, value)| {
+                Value::Struct(vec![Value::Byte(code), Value::Variant(Box::new(value))])
+            })
+            .collect();
+
+        let mut message = Writer::default();
+        message
+            .buf
+            .extend_from_slice(&[b'l', self.kind as u8, 0, 1]);
+        message.u32(body.buf.len() as u32);
+        message.u32(serial);
+        message.write(&Value::Array("(yv)".into(), fields));
+        message.align(8);
+        message.buf.extend_from_slice(&body.buf);
+        message.buf
+    }
+
+    /// Decode a complete message, returning it with the number of unix
+    /// fds it carries
+    fn decode(buf: &[u8]) -> io::Result<(Message, usize)> {
+        let mut reader = Reader {
+            buf,
+            pos: 0,
+            big_endian: buf.first() == Some(&b'B'),
+        };
+        let header = reader.take(4)?;
+        let kind = match header[1] {
+            1 => MessageType::MethodCall,
+            2 => MessageType::MethodReturn,
+            3 => MessageType::Error,
+            4 => MessageType::Signal,
+            _ => return Err(invalid("unknown message type")),
+        };
+        let _body_len = read_number!(reader, u32);
+        let serial = read_number!(reader, u32);
+
+        let mut message = Message {
+            kind,
+            serial,
+            reply_serial: None,
+            path: None,
+            interface: None,
+            member: None,
+            error_name: None,
+            destination: None,
+            sender: None,
+            body: Vec::new(),
+            fds: Vec::new(),
+        };
+        let mut signature = String::new();
+        let mut fd_count = 0;
+        if let Value::Array(_, fields) = reader.read("a(yv)")? {
+            for field in fields {
+                let (code, value) = match field {
+                    Value::Struct(mut field) if field.len() == 2 => {
+                        match (field.remove(0), field.remove(0)) {
+                            (Value::Byte(code), Value::Variant(value)) => (code, *value),
+                            _ => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+                match (code, value) {
+                    (1, Value::Path(path)) => message.path = Some(path),
+                    (2, Value::Str(interface)) => message.interface = Some(interface),
+                    (3, Value::Str(member)) => message.member = Some(member),
+                    (4, Value::Str(name)) => message.error_name = Some(name),
+                    (5, Value::U32(serial)) => message.reply_serial = Some(serial),
+                    (6, Value::Str(destination)) => message.destination = Some(destination),
+                    (7, Value::Str(sender)) => message.sender = Some(sender),
+                    (8, Value::Signature(body)) => signature = body,
+                    (9, Value::U32(count)) => fd_count = count as usize,
+                    _ => {}
+                }
+            }
+        }
+        reader.align(8)?;
+        for ty in split_types(&signature)? {
+            message.body.push(reader.read(ty)?);
+        }
+        Ok((message, fd_count))
+    }
+}
+
+/// Total length of the message at the start of `buf`, None until the
+/// fixed part of the header has arrived
+fn message_len(buf: &[u8]) -> io::Result<Option<usize>> {
+    if buf.len() < 16 {
+        return Ok(None);
+    }
+    let number = |at: usize| {
+        let bytes = [buf[at], buf[at + 1], buf[at + 2], buf[at + 3]];
+        if buf[0] == b'B' {
+            u32::from_be_bytes(bytes) as usize
+        } else {
+            u32::from_le_bytes(bytes) as usize
+        }
+    };
+    let fields = (16 + number(12)).div_ceil(8) * 8;
+    let len = fields + number(4);
+    if len > MAX_MESSAGE_LEN {
+        return Err(invalid("message too large"));
+    }
+    Ok(Some(len))
+}
+
+/// A connection to a message bus
+pub(crate) struct Connection {
+    fd: OwnedFd,
+    serial: u32,
+    // Bytes and fds received but not yet part of a complete message
+    buf: Vec<u8>,
+    fds: VecDeque<OwnedFd>,
+    // Complete messages not yet taken
+    queue: VecDeque<Message>,
+}
+
+impl Connection {
+    /// Connect to the system bus
+    pub fn system() -> io::Result<Connection> {
+        let address = env::var("DBUS_SYSTEM_BUS_ADDRESS")
+            .unwrap_or_else(|_| "unix:path=/var/run/dbus/system_bus_socket".into());
+        Connection::open(&address)
+    }
+
+    /// Connect to the session bus of the user's login session
+    pub fn session() -> io::Result<Connection> {
+        let address = env::var("DBUS_SESSION_BUS_ADDRESS")
+            .ok()
+            .or_else(|| {
+                let runtime = env::var("XDG_RUNTIME_DIR").ok()?;
+                Some(format!("unix:path={}/bus", runtime))
+            })
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no session bus address"))?;
+        Connection::open(&address)
+    }
+
+    /// Own a well-known name, so other clients find our objects under it
+    pub fn request_name(&mut self, name: &str) -> io::Result<()> {
+        // DBUS_NAME_FLAG_DO_NOT_QUEUE, replies 1 when we are the owner
+        let reply = self.call(Message::method_call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "RequestName",
+            vec![Value::Str(name.into()), Value::U32(4)],
+        ))?;
+        match reply.body.first() {
+            Some(Value::U32(1)) => Ok(()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} is owned by another client", name),
+            )),
+        }
+    }
+
+    /// Connect to the first reachable unix socket in a bus address
+    fn open(address: &str) -> io::Result<Connection> {
+        let mut error = io::Error::new(io::ErrorKind::NotFound, "no usable bus address");
+        for address in address.split(';') {
+            match connect(address) {
+                Ok(fd) => {
+                    let mut connection = Connection {
+                        fd,
+                        serial: 0,
+                        buf: Vec::new(),
+                        fds: VecDeque::new(),
+                        queue: VecDeque::new(),
+                    };
+                    connection.authenticate()?;
+                    connection.call(Message::method_call(
+                        "org.freedesktop.DBus",
+                        "/org/freedesktop/DBus",
+                        "org.freedesktop.DBus",
+                        "Hello",
+                        Vec::new(),
+                    ))?;
+                    return Ok(connection);
+                }
+                Err(err) => error = err,
+            }
+        }
+        Err(error)
+    }
+
+    /// Authenticate as our uid with credentials passed by the socket,
+    /// asking for unix fd passing
+    fn authenticate(&mut self) -> io::Result<()> {
+        let uid = unsafe { libc::getuid() }.to_string();
+        let hex: String = uid.bytes().map(|b| format!("{:02x}", b)).collect();
+        self.write_all(format!("\0AUTH EXTERNAL {}\r\n", hex).as_bytes())?;
+        if !self.read_line()?.starts_with("OK ") {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "bus rejected authentication",
+            ));
+        }
+        self.write_all(b"NEGOTIATE_UNIX_FD\r\n")?;
+        // Without fd passing the bus answers with an ERROR, which only
+        // matters to calls returning fds
+        self.read_line()?;
+        self.write_all(b"BEGIN\r\n")?;
+
+        unsafe {
+            let flags = libc::fcntl(self.fd.as_raw_fd(), libc::F_GETFL);
+            libc::fcntl(self.fd.as_raw_fd(), libc::F_SETFL, flags | libc::O_NONBLOCK);
+        }
+        Ok(())
+    }
+
+    /// Read a line of the authentication exchange, byte by byte so
+    /// nothing after it is consumed
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = Vec::new();
+        while !line.ends_with(b"\r\n") {
+            let mut byte = 0u8;
+            let read =
+                unsafe { libc::read(self.fd.as_raw_fd(), &mut byte as *mut u8 as *mut _, 1) };
+            match read {
+                1 => line.push(byte),
+                0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                _ => return Err(io::Error::last_os_error()),
+            }
+        }
+        line.truncate(line.len() - 2);
+        String::from_utf8(line).map_err(|_| invalid("authentication reply is not UTF-8"))
+    }
+
+    fn write_all(&mut self, mut bytes: &[u8]) -> io::Result<()> {
+        while !bytes.is_empty() {
+            let written = unsafe {
+                libc::send(
+                    self.fd.as_raw_fd(),
+                    bytes.as_ptr() as *const _,
+                    bytes.len(),
+                    libc::MSG_NOSIGNAL,
+                )
+            };
+            if written < 0 {
+                let err = io::Error::last_os_error();
+                match err.kind() {
+                    io::ErrorKind::Interrupted => continue,
+                    io::ErrorKind::WouldBlock => wait(self.fd.as_raw_fd(), libc::POLLOUT, None),
+                    _ => return Err(err),
+                }
+            } else {
+                bytes = &bytes[written as usize..];
+            }
+        }
+        Ok(())
+    }
+
+    /// Send a message, returning its serial
+    pub fn send(&mut self, message: &Message) -> io::Result<u32> {
+        self.serial += 1;
+        let serial = self.serial;
+        let bytes = message.encode(serial);
+        self.write_all(&bytes)?;
+        Ok(serial)
+    }
+
+    /// Call a method and wait for its reply, error replies are returned
+    /// as errors. Messages arriving meanwhile are kept for poll_message
+    pub fn call(&mut self, message: Message) -> io::Result<Message> {
+        let serial = self.send(&message)?;
+        let deadline = Instant::now() + CALL_TIMEOUT;
+        loop {
+            let reply = self.queue.iter().position(|reply| {
+                matches!(reply.kind, MessageType::MethodReturn | MessageType::Error)
+                    && reply.reply_serial == Some(serial)
+            });
+            if let Some(reply) = reply.and_then(|i| self.queue.remove(i)) {
+                if reply.kind == MessageType::Error {
+                    let detail = match reply.body.first() {
+                        Some(Value::Str(detail)) => detail.as_str(),
+                        _ => "",
+                    };
+                    return Err(io::Error::other(format!(
+                        "{}: {}",
+                        reply.error_name.as_deref().unwrap_or("error"),
+                        detail
+                    )));
+                }
+                return Ok(reply);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "no reply from the bus",
+                ));
+            }
+            wait(self.fd.as_raw_fd(), libc::POLLIN, Some(deadline - now));
+            self.receive()?;
+        }
+    }
+
+    /// Subscribe to the messages matching a match rule
+    pub fn add_match(&mut self, rule: &str) -> io::Result<()> {
+        self.call(Message::method_call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "AddMatch",
+            vec![Value::Str(rule.into())],
+        ))?;
+        Ok(())
+    }
+
+    /// The socket, readable once a message arrives
+    pub fn fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+
+    /// Take the next received message without blocking
+    pub fn poll_message(&mut self) -> io::Result<Option<Message>> {
+        if self.queue.is_empty() {
+            self.receive()?;
+        }
+        Ok(self.queue.pop_front())
+    }
+
+    /// Read everything the socket holds, queueing complete messages
+    fn receive(&mut self) -> io::Result<()> {
+        loop {
+            let mut data = [0u8; 4096];
+            // Room for the fds of a few messages
+            let mut control = [0u64; 32];
+            let mut iov = libc::iovec {
+                iov_base: data.as_mut_ptr() as *mut _,
+                iov_len: data.len(),
+            };
+            let mut header: libc::msghdr = unsafe { mem::zeroed() };
+            header.msg_iov = &mut iov;
+            header.msg_iovlen = 1;
+            header.msg_control = control.as_mut_ptr() as *mut _;
+            header.msg_controllen = mem::size_of_val(&control) as _;
+
+            let read =
+                unsafe { libc::recvmsg(self.fd.as_raw_fd(), &mut header, libc::MSG_CMSG_CLOEXEC) };
+            if read < 0 {
+                let err = io::Error::last_os_error();
+                match err.kind() {
+                    io::ErrorKind::Interrupted => continue,
+                    io::ErrorKind::WouldBlock => break,
+                    _ => return Err(err),
+                }
+            }
+            if read == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "bus closed the connection",
+                ));
+            }
+
+            unsafe {
+                let mut cmsg = libc::CMSG_FIRSTHDR(&header);
+                while !cmsg.is_null() {
+                    if (*cmsg).cmsg_level == libc::SOL_SOCKET
+                        && (*cmsg).cmsg_type == libc::SCM_RIGHTS
+                    {
+                        let data = libc::CMSG_DATA(cmsg) as *const RawFd;
+                        let len = (*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize;
+                        for i in 0..len / mem::size_of::<RawFd>() {
+                            let fd = ptr::read_unaligned(data.add(i));
+                            self.fds.push_back(OwnedFd::from_raw_fd(fd));
+                        }
+                    }
+                    cmsg = libc::CMSG_NXTHDR(&header, cmsg);
+                }
+            }
+            self.buf.extend_from_slice(&data[..read as usize]);
+        }
+
+        while let Some(len) = message_len(&self.buf)? {
+            if self.buf.len() < len {
+                break;
+            }
+            let (mut message, fd_count) = Message::decode(&self.buf[..len])?;
+            let fd_count = fd_count.min(self.fds.len());
+            message.fds = self.fds.drain(..fd_count).collect();
+            self.buf.drain(..len);
+            self.queue.push_back(message);
+        }
+        Ok(())
+    }
+}
+
+/// Open a stream socket for a unix bus address such as
+/// `unix:path=/run/dbus/system_bus_socket`
+fn connect(address: &str) -> io::Result<OwnedFd> {
+    let params = address
+        .strip_prefix("unix:")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "not a unix bus address"))?;
+    let mut path = None;
+    for param in params.split(',') {
+        if let Some(value) = param.strip_prefix("path=") {
+            path = Some((false, unescape(value)));
+        } else if let Some(value) = param.strip_prefix("abstract=") {
+            path = Some((true, unescape(value)));
+        }
+    }
+    let (is_abstract, path) =
+        path.ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "no socket path"))?;
+
+    unsafe {
+        let mut addr: libc::sockaddr_un = mem::zeroed();
+        addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        // Abstract names start with a nul byte, paths end with one
+        let offset = is_abstract as usize;
+        if offset + path.len() >= addr.sun_path.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "socket path too long",
+            ));
+        }
+        for (i, &byte) in path.iter().enumerate() {
+            addr.sun_path[offset + i] = byte as libc::c_char;
+        }
+        let sun_path_offset = mem::size_of::<libc::sa_family_t>();
+        let len = sun_path_offset + offset + path.len() + (!is_abstract) as usize;
+
+        let fd = libc::socket(libc::AF_UNIX, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = OwnedFd::from_raw_fd(fd);
+        let addr_ptr = &addr as *const libc::sockaddr_un as *const libc::sockaddr;
+        if libc::connect(fd.as_raw_fd(), addr_ptr, len as libc::socklen_t) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(fd)
+    }
+}
+
+/// Undo the %xx escaping of address values
+fn unescape(value: &str) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = value
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Wait for the socket to become readable or writable
+fn wait(fd: RawFd, events: libc::c_short, timeout: Option<Duration>) {
+    let mut pollfd = libc::pollfd {
+        fd,
+        events,
+        revents: 0,
+    };
+    let timeout = timeout.map_or(-1, |timeout| {
+        timeout.as_millis().min(i32::MAX as u128) as i32
+    });
+    unsafe {
+        libc::poll(&mut pollfd, 1, timeout);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(values: &[Value]) -> Vec<Value> {
+        let mut writer = Writer::default();
+        for value in values {
+            writer.write(value);
+        }
+        let mut reader = Reader {
+            buf: &writer.buf,
+            pos: 0,
+            big_endian: false,
+        };
+        let values = values
+            .iter()
+            .map(|value| reader.read(&value.signature()).unwrap())
+            .collect();
+        assert_eq!(reader.pos, writer.buf.len());
+        values
+    }
+
+    fn read(buf: &[u8], signature: &str) -> io::Result<Value> {
+        Reader {
+            buf,
+            pos: 0,
+            big_endian: false,
+        }
+        .read(signature)
+    }
+
+    #[test]
+    fn values_round_trip() {
+        let values = vec![
+            Value::Byte(7),
+            Value::Bool(true),
+            Value::I16(-2),
+            Value::U16(3),
+            Value::Byte(1),
+            Value::I32(-70000),
+            Value::U32(70000),
+            Value::I64(-1 << 40),
+            Value::U64(1 << 50),
+            Value::F64(1.5),
+            Value::Str("sleep".into()),
+            Value::Path("/org/freedesktop/login1".into()),
+            Value::Signature("a{sv}".into()),
+            Value::Fd(0),
+            Value::Array("t".into(), vec![Value::U64(1), Value::U64(2)]),
+            Value::Array("s".into(), vec![]),
+            Value::Struct(vec![Value::Byte(1), Value::Str("x".into())]),
+            Value::Array(
+                "{sv}".into(),
+                vec![Value::DictEntry(
+                    Box::new(Value::Str("Id".into())),
+                    Box::new(Value::Variant(Box::new(Value::U32(4)))),
+                )],
+            ),
+        ];
+        assert_eq!(round_trip(&values), values);
+    }
+
+    #[test]
+    fn array_length_excludes_padding() {
+        let mut writer = Writer::default();
+        writer.write(&Value::Array("t".into(), vec![Value::U64(9)]));
+        // Length, padding to 8, then the element
+        assert_eq!(writer.buf.len(), 16);
+        assert_eq!(writer.buf[..4], 8u32.to_le_bytes());
+    }
+
+    #[test]
+    fn strings_stop_at_nul() {
+        assert_eq!(
+            round_trip(&[Value::Str("a\0b".into())]),
+            [Value::Str("a".into())]
+        );
+    }
+
+    #[test]
+    fn big_endian_numbers() {
+        let reader = &mut Reader {
+            buf: &[0, 0, 1, 2, 0, 3],
+            pos: 0,
+            big_endian: true,
+        };
+        assert_eq!(reader.read("u").unwrap(), Value::U32(258));
+        assert_eq!(reader.read("q").unwrap(), Value::U16(3));
+    }
+
+    #[test]
+    fn message_round_trips() {
+        let mut call = Message::method_call(
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1",
+            "org.freedesktop.login1.Manager",
+            "Inhibit",
+            vec![Value::Str("sleep".into()), Value::Str("delay".into())],
+        );
+        call.sender = Some(":1.5".into());
+        let buf = call.encode(12);
+        assert_eq!(message_len(&buf).unwrap(), Some(buf.len()));
+        let (decoded, fd_count) = Message::decode(&buf).unwrap();
+        assert_eq!(fd_count, 0);
+        assert_eq!(decoded.kind, MessageType::MethodCall);
+        assert_eq!(decoded.serial, 12);
+        assert!(decoded.is_method_call("org.freedesktop.login1.Manager", "Inhibit"));
+        assert_eq!(decoded.path.as_deref(), Some("/org/freedesktop/login1"));
+        assert_eq!(
+            decoded.destination.as_deref(),
+            Some("org.freedesktop.login1")
+        );
+        assert_eq!(decoded.body, call.body);
+
+        let reply = Message::error(&decoded, "org.example.Failed", "no");
+        let (reply, _) = Message::decode(&reply.encode(13)).unwrap();
+        assert_eq!(reply.kind, MessageType::Error);
+        assert_eq!(reply.reply_serial, Some(12));
+        assert_eq!(reply.destination.as_deref(), Some(":1.5"));
+        assert_eq!(reply.error_name.as_deref(), Some("org.example.Failed"));
+        assert_eq!(reply.body, [Value::Str("no".into())]);
+    }
+
+    #[test]
+    fn prepare_for_sleep_signal() {
+        for sleeping in [true, false] {
+            let signal = Message::signal(
+                "/org/freedesktop/login1",
+                "org.freedesktop.login1.Manager",
+                "PrepareForSleep",
+                vec![Value::Bool(sleeping)],
+            );
+            let (decoded, _) = Message::decode(&signal.encode(1)).unwrap();
+            assert!(decoded.is_signal("org.freedesktop.login1.Manager", "PrepareForSleep"));
+            assert!(!decoded.is_method_call("org.freedesktop.login1.Manager", "PrepareForSleep"));
+            assert_eq!(decoded.destination, None);
+            assert_eq!(decoded.body, [Value::Bool(sleeping)]);
+        }
+    }
+
+    #[test]
+    fn truncated_messages_are_errors() {
+        let signal = Message::signal("/a", "b.c", "D", vec![Value::Str("long".into())]);
+        let buf = signal.encode(1);
+        assert_eq!(message_len(&buf[..15]).unwrap(), None);
+        for len in 0..buf.len() {
+            assert!(Message::decode(&buf[..len]).is_err(), "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn message_len_limits_size() {
+        let mut buf = Message::signal("/a", "b.c", "D", vec![]).encode(1);
+        buf[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(message_len(&buf).is_err());
+    }
+
+    #[test]
+    fn malformed_values_are_errors() {
+        // Padding to a u32 runs past the end
+        assert!(read(&[1, 0, 0, 0, 5, 0], "(yu)").is_err());
+        // An array of u32 whose length ends inside its second element
+        let mut buf = 6u32.to_le_bytes().to_vec();
+        buf.extend_from_slice(&[1, 0, 0, 0, 2, 0, 0, 0]);
+        assert!(read(&buf, "au").is_err());
+        // A string missing its nul
+        assert!(read(&[2, 0, 0, 0, b'h', b'i'], "s").is_err());
+        assert!(read(&[2, 0, 0, 0, 0xff, 0xfe, 0], "s").is_err());
+        // Variants must hold a single type
+        assert!(read(&[2, b'u', b'u', 0, 0, 0, 0, 0], "v").is_err());
+        assert!(read(&[0, 0], "v").is_err());
+        assert!(read(&[1, 0, 0, 0, 0, 0, 0, 0], "a()").is_err());
+        assert!(read(&[0; 8], "{u}").is_err());
+        assert!(split_types("(uu").is_err());
+    }
+}
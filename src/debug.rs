@@ -0,0 +1,8 @@
+use std::env;
+use std::sync::OnceLock;
+
+/// Whether DASWIN_DEBUG is set, enabling diagnostic output on stderr
+pub(crate) fn enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| env::var_os("DASWIN_DEBUG").is_some())
+}
@@ -0,0 +1,25 @@
+// The triangle of shader.wgsl, scaled, turned and colored by the
+// parameters the demo's script sets
+struct Params {
+    color: vec4<f32>,
+    scale: f32,
+    angle: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let x = f32(i32(in_vertex_index) - 1);
+    let y = f32(i32(in_vertex_index & 1u) * 2 - 1);
+    let c = cos(params.angle);
+    let s = sin(params.angle);
+    let turned = vec2<f32>(x * c - y * s, x * s + y * c) * params.scale;
+    return vec4<f32>(turned, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return params.color;
+}
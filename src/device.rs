@@ -0,0 +1,167 @@
+//! Input devices as XInput2 lists them, see EventLoop::devices
+
+use std::ffi::CStr;
+use std::slice;
+
+use x11::xinput2;
+
+use crate::event::DeviceId;
+use crate::ext;
+use crate::xconn::XConnection;
+
+/// The role of a device in the XInput2 hierarchy. Master devices are
+/// the pointers and keyboards on screen, the physical devices are slaves
+/// attached to them. Core events are reported for the masters, every
+/// other event carries the id of the slave which caused it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DeviceType {
+    MasterPointer,
+    MasterKeyboard,
+    SlavePointer,
+    SlaveKeyboard,
+    /// A slave detached from every master, its input only reaches
+    /// clients selecting it directly
+    FloatingSlave,
+}
+
+/// An input device and what it reports
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceInfo {
+    id: DeviceId,
+    name: String,
+    device_type: DeviceType,
+    attachment: Option<DeviceId>,
+    enabled: bool,
+    buttons: u32,
+    pressure: bool,
+    max_touches: Option<u32>,
+    scroll_axes: u32,
+    relative_axes: u32,
+}
+
+impl DeviceInfo {
+    /// The id input events of the device carry
+    pub fn id(&self) -> DeviceId {
+        self.id
+    }
+
+    /// Name the driver gives the device, e.g. "Logitech USB Receiver"
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn device_type(&self) -> DeviceType {
+        self.device_type
+    }
+
+    /// The master of a slave, or the keyboard paired with a master
+    /// pointer and the other way around. None for floating slaves
+    pub fn attachment(&self) -> Option<DeviceId> {
+        self.attachment
+    }
+
+    /// Disabled devices report no input
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn buttons(&self) -> u32 {
+        self.buttons
+    }
+
+    /// Whether the device reports pressure, like pen tablets
+    pub fn has_pressure(&self) -> bool {
+        self.pressure
+    }
+
+    /// Whether the device is a touchscreen or touchpad
+    pub fn has_touch(&self) -> bool {
+        self.max_touches.is_some()
+    }
+
+    /// Contacts tracked at once, None without touch and Some(0) when the
+    /// device does not tell
+    pub fn max_touches(&self) -> Option<u32> {
+        self.max_touches
+    }
+
+    /// Valuators scrolling smoothly, e.g. two on a touchpad
+    pub fn scroll_axes(&self) -> u32 {
+        self.scroll_axes
+    }
+
+    /// Valuators reporting motion relative to the last event, e.g. the
+    /// two of a mouse. Absolute devices like tablets have none
+    pub fn relative_axes(&self) -> u32 {
+        self.relative_axes
+    }
+}
+
+/// Every device of the server sorted by id, empty without XInput2
+pub(crate) fn query_devices(conn: &XConnection) -> Vec<DeviceInfo> {
+    let mut devices = Vec::new();
+    let xinput2 = match ext::xinput2() {
+        Some(xinput2) => xinput2,
+        None => return devices,
+    };
+
+    unsafe {
+        let mut count = 0;
+        let info = (xinput2.XIQueryDevice)(conn.display, xinput2::XIAllDevices, &mut count);
+        if info.is_null() {
+            return devices;
+        }
+
+        for device in slice::from_raw_parts(info, count as usize) {
+            let device_type = match device._use {
+                xinput2::XIMasterPointer => DeviceType::MasterPointer,
+                xinput2::XIMasterKeyboard => DeviceType::MasterKeyboard,
+                xinput2::XISlavePointer => DeviceType::SlavePointer,
+                xinput2::XISlaveKeyboard => DeviceType::SlaveKeyboard,
+                xinput2::XIFloatingSlave => DeviceType::FloatingSlave,
+                _ => continue,
+            };
+            let mut info = DeviceInfo {
+                id: DeviceId(device.deviceid as u32),
+                name: CStr::from_ptr(device.name).to_string_lossy().into_owned(),
+                device_type,
+                attachment: (device_type != DeviceType::FloatingSlave)
+                    .then_some(DeviceId(device.attachment as u32)),
+                enabled: device.enabled != 0,
+                buttons: 0,
+                pressure: false,
+                max_touches: None,
+                scroll_axes: 0,
+                relative_axes: 0,
+            };
+            for &class in slice::from_raw_parts(device.classes, device.num_classes as usize) {
+                match (*class)._type {
+                    xinput2::XIButtonClass => {
+                        let class = &*(class as *const xinput2::XIButtonClassInfo);
+                        info.buttons = class.num_buttons as u32;
+                    }
+                    xinput2::XIValuatorClass => {
+                        let class = &*(class as *const xinput2::XIValuatorClassInfo);
+                        if class.label == conn.atoms.abs_pressure {
+                            info.pressure = true;
+                        }
+                        if class.mode == xinput2::XIModeRelative {
+                            info.relative_axes += 1;
+                        }
+                    }
+                    xinput2::XIScrollClass => info.scroll_axes += 1,
+                    xinput2::XITouchClass => {
+                        let class = &*(class as *const xinput2::XITouchClassInfo);
+                        info.max_touches = Some(class.num_touches as u32);
+                    }
+                    _ => {}
+                }
+            }
+            devices.push(info);
+        }
+        (xinput2.XIFreeDeviceInfo)(info);
+    }
+    devices.sort_by_key(|device| device.id);
+    devices
+}
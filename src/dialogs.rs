@@ -0,0 +1,360 @@
+//! Native file choosers through the FileChooser interface of
+//! xdg-desktop-portal, see open_file and save_file.
+//!
+//! The portal answers with a Response signal on a request object once
+//! the user closed the dialog. Each dialog keeps a session bus
+//! connection of its own, read by the polls of the event loop of its
+//! parent window, which then wakes the task awaiting the FileDialog
+
+use std::ffi::OsString;
+use std::future::Future;
+use std::io;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use crate::dbus::{Connection, Message, Value};
+use crate::error::Error;
+use crate::window::X11Window;
+use crate::xconn::XConnection;
+
+const PORTAL: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const FILE_CHOOSER: &str = "org.freedesktop.portal.FileChooser";
+const REQUEST: &str = "org.freedesktop.portal.Request";
+
+/// How often an event stream wakes to read the bus while a dialog is
+/// open, its watcher only waits on the X connection
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Files offered by a dialog under one name, e.g. "Images"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFilter {
+    pub name: String,
+    /// Globs such as "*.png", or MIME types such as "image/png", told
+    /// apart by the slash
+    pub patterns: Vec<String>,
+}
+
+impl FileFilter {
+    pub fn new(name: &str, patterns: &[&str]) -> FileFilter {
+        FileFilter {
+            name: name.to_owned(),
+            patterns: patterns.iter().map(|&pattern| pattern.to_owned()).collect(),
+        }
+    }
+
+    // (sa(us)), 0 marks a glob and 1 a MIME type
+    fn to_value(&self) -> Value {
+        let patterns = self
+            .patterns
+            .iter()
+            .map(|pattern| {
+                let kind = pattern.contains('/') as u32;
+                Value::Struct(vec![Value::U32(kind), Value::Str(pattern.clone())])
+            })
+            .collect();
+        Value::Struct(vec![
+            Value::Str(self.name.clone()),
+            Value::Array("(us)".into(), patterns),
+        ])
+    }
+}
+
+/// What a file dialog shows and lets the user choose
+#[derive(Debug, Clone, Default)]
+pub struct FileDialogOptions {
+    /// Title of the dialog, empty leaves it to the portal
+    pub title: String,
+    /// Label of the button choosing, e.g. "Import"
+    pub accept_label: Option<String>,
+    /// Filters the user picks from, the first is selected. Empty shows
+    /// every file
+    pub filters: Vec<FileFilter>,
+    /// Let open_file choose several files
+    pub multiple: bool,
+    /// Let open_file choose directories instead of files
+    pub directory: bool,
+    /// The file name save_file suggests
+    pub current_name: Option<String>,
+    /// The directory the dialog starts in. Portals before version 4
+    /// only honor it for save_file
+    pub current_folder: Option<PathBuf>,
+}
+
+/// A file dialog shown by the portal, resolving to the chosen paths or
+/// to None when the user cancelled it or the portal failed.
+///
+/// Only answered while the event loop of the parent window is polled.
+/// Dropping it before then closes the dialog
+pub struct FileDialog {
+    request: Arc<Mutex<Request>>,
+}
+
+impl FileDialog {
+    /// The response once the portal answered, for loops polling without
+    /// an executor. Taken by the first call returning Some
+    pub fn take_response(&mut self) -> Option<Option<Vec<PathBuf>>> {
+        self.request.lock().unwrap().response.take()
+    }
+}
+
+impl Future for FileDialog {
+    type Output = Option<Vec<PathBuf>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let mut request = self.request.lock().unwrap();
+        match request.response.take() {
+            Some(response) => Poll::Ready(response),
+            None => {
+                request.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for FileDialog {
+    fn drop(&mut self) {
+        let mut request = self.request.lock().unwrap();
+        if !request.answered {
+            let close = Message::method_call(PORTAL, &request.handle, REQUEST, "Close", Vec::new());
+            if let Err(err) = request.bus.send(&close) {
+                log::debug!("failed to close the file dialog: {}", err);
+            }
+        }
+    }
+}
+
+/// A dialog waiting for the Response signal of its request object
+pub(crate) struct Request {
+    bus: Connection,
+    handle: String,
+    answered: bool,
+    response: Option<Option<Vec<PathBuf>>>,
+    waker: Option<Waker>,
+}
+
+impl Request {
+    /// Read the bus, true once the response arrived
+    fn poll(&mut self) -> bool {
+        while !self.answered {
+            match self.bus.poll_message() {
+                Ok(Some(message)) => {
+                    if message.is_signal(REQUEST, "Response")
+                        && message.path.as_deref() == Some(self.handle.as_str())
+                    {
+                        self.answer(response_paths(&message.body));
+                    }
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    log::warn!("lost the session bus while a file dialog was open: {}", err);
+                    self.answer(None);
+                }
+            }
+        }
+        self.answered
+    }
+
+    fn answer(&mut self, response: Option<Vec<PathBuf>>) {
+        self.answered = true;
+        self.response = Some(response);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Show a dialog choosing files to open over `parent`.
+///
+/// Fails with Error::Unsupported("xdg-desktop-portal") without a session
+/// bus or a portal offering FileChooser, applications should then fall
+/// back to a dialog of their own
+pub fn open_file(parent: &X11Window, options: &FileDialogOptions) -> Result<FileDialog, Error> {
+    let mut dict = common_options(options);
+    dict.push(entry("multiple", Value::Bool(options.multiple)));
+    dict.push(entry("directory", Value::Bool(options.directory)));
+    show(parent, "OpenFile", options, dict)
+}
+
+/// Show a dialog choosing where to save a file over `parent`, resolving
+/// to a single path. Fails like open_file
+pub fn save_file(parent: &X11Window, options: &FileDialogOptions) -> Result<FileDialog, Error> {
+    let mut dict = common_options(options);
+    if let Some(name) = &options.current_name {
+        dict.push(entry("current_name", Value::Str(name.clone())));
+    }
+    show(parent, "SaveFile", options, dict)
+}
+
+fn common_options(options: &FileDialogOptions) -> Vec<Value> {
+    let mut dict = vec![entry("modal", Value::Bool(true))];
+    if let Some(label) = &options.accept_label {
+        dict.push(entry("accept_label", Value::Str(label.clone())));
+    }
+    if let Some(first) = options.filters.first() {
+        let filters = options.filters.iter().map(FileFilter::to_value).collect();
+        dict.push(entry("filters", Value::Array("(sa(us))".into(), filters)));
+        dict.push(entry("current_filter", first.to_value()));
+    }
+    if let Some(folder) = &options.current_folder {
+        dict.push(entry("current_folder", path_bytes(folder)));
+    }
+    dict
+}
+
+fn show(
+    parent: &X11Window,
+    method: &str,
+    options: &FileDialogOptions,
+    dict: Vec<Value>,
+) -> Result<FileDialog, Error> {
+    let request = call(parent, method, options, dict).map_err(|err| {
+        log::info!("no file dialog from the portal: {}", err);
+        Error::Unsupported("xdg-desktop-portal")
+    })?;
+    let request = Arc::new(Mutex::new(request));
+    let mut dialogs = parent.conn.dialogs.lock().unwrap();
+    dialogs.retain(|request| request.strong_count() > 0);
+    dialogs.push(Arc::downgrade(&request));
+    Ok(FileDialog { request })
+}
+
+fn call(
+    parent: &X11Window,
+    method: &str,
+    options: &FileDialogOptions,
+    dict: Vec<Value>,
+) -> io::Result<Request> {
+    let mut bus = Connection::session()?;
+    // Subscribed before the call, so a response sent right away is kept
+    bus.add_match(&format!(
+        "type='signal',interface='{}',member='Response'",
+        REQUEST
+    ))?;
+    let reply = bus.call(Message::method_call(
+        PORTAL,
+        PORTAL_PATH,
+        FILE_CHOOSER,
+        method,
+        vec![
+            Value::Str(format!("x11:{:x}", parent.window)),
+            Value::Str(options.title.clone()),
+            Value::Array("{sv}".into(), dict),
+        ],
+    ))?;
+    let handle = match reply.body.first() {
+        Some(Value::Path(handle)) => handle.clone(),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the portal returned no request handle",
+            ))
+        }
+    };
+    Ok(Request {
+        bus,
+        handle,
+        answered: false,
+        response: None,
+        waker: None,
+    })
+}
+
+/// Read the responses to the dialogs of a connection, called by every
+/// poll of its event loop
+pub(crate) fn poll(conn: &XConnection) {
+    conn.dialogs
+        .lock()
+        .unwrap()
+        .retain(|request| match request.upgrade() {
+            Some(request) => !request.lock().unwrap().poll(),
+            None => false,
+        });
+}
+
+/// Whether a dialog of the connection waits for its response
+pub(crate) fn pending(conn: &XConnection) -> bool {
+    conn.dialogs
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|request| request.strong_count() > 0)
+}
+
+// Response carries 0 on success, 1 when cancelled and 2 otherwise, and
+// the chosen files as URIs
+fn response_paths(body: &[Value]) -> Option<Vec<PathBuf>> {
+    let results = match body {
+        [Value::U32(0), Value::Array(_, results)] => results,
+        _ => return None,
+    };
+    let uris = results.iter().find_map(|entry| match entry {
+        Value::DictEntry(key, value) if **key == Value::Str("uris".into()) => match &**value {
+            Value::Variant(uris) => match &**uris {
+                Value::Array(_, uris) => Some(uris),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    })?;
+    Some(
+        uris.iter()
+            .filter_map(|uri| match uri {
+                Value::Str(uri) => file_path(uri),
+                _ => None,
+            })
+            .collect(),
+    )
+}
+
+/// The path of a file:// URI, None for other schemes
+fn file_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    // An empty host or localhost, the path starts after it
+    let path = &path[path.find('/')?..];
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = path
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    Some(OsString::from_vec(decoded).into())
+}
+
+// Paths go to the portal as nul terminated byte arrays
+fn path_bytes(path: &Path) -> Value {
+    let bytes = path
+        .as_os_str()
+        .as_bytes()
+        .iter()
+        .chain(&[0])
+        .map(|&byte| Value::Byte(byte))
+        .collect();
+    Value::Array("y".into(), bytes)
+}
+
+fn entry(key: &str, value: Value) -> Value {
+    Value::DictEntry(
+        Box::new(Value::Str(key.into())),
+        Box::new(Value::Variant(Box::new(value))),
+    )
+}
@@ -0,0 +1,283 @@
+//! Positions and sizes tagged with the space they are in.
+//!
+//! Physical values are pixels of the X server, the space of every event
+//! and query. Logical values are physical ones divided by the scale
+//! factor, see MonitorHandle::scale_factor, so a UI laid out in them
+//! keeps its size across monitors.
+//!
+//! Conversions to integers round half to even: at a scale factor of 1.5,
+//! 3 and 5 logical pixels are 4.5 and 7.5 physical ones, which round to
+//! 4 and 8, so sums of converted values do not drift in one direction.
+//! Values out of range saturate, NaN becomes 0
+
+/// A number positions and sizes can hold
+pub trait Pixel: Copy + Into<f64> {
+    /// Convert from f64, rounding half to even for integers
+    fn from_f64(value: f64) -> Self;
+
+    /// Convert to another pixel type, rounding as from_f64
+    fn cast<P: Pixel>(self) -> P {
+        P::from_f64(self.into())
+    }
+}
+
+macro_rules! integer_pixel {
+    ($($ty:ty)*) => {
+        $(impl Pixel for $ty {
+            fn from_f64(value: f64) -> $ty {
+                value.round_ties_even() as $ty
+            }
+        })*
+    };
+}
+
+integer_pixel!(u8 u16 u32 i8 i16 i32);
+
+impl Pixel for f32 {
+    fn from_f64(value: f64) -> f32 {
+        value as f32
+    }
+}
+
+impl Pixel for f64 {
+    fn from_f64(value: f64) -> f64 {
+        value
+    }
+}
+
+fn check_scale_factor(scale_factor: f64) {
+    debug_assert!(
+        scale_factor.is_finite() && scale_factor > 0.0,
+        "invalid scale factor {}",
+        scale_factor
+    );
+}
+
+macro_rules! pixel_pair {
+    (
+        $(#[$attr:meta])*
+        $name:ident { $a:ident, $b:ident }, $convert:ident -> $other:ident, $op:tt
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+        pub struct $name<T> {
+            pub $a: T,
+            pub $b: T,
+        }
+
+        impl<T> $name<T> {
+            pub const fn new($a: T, $b: T) -> $name<T> {
+                $name { $a, $b }
+            }
+        }
+
+        impl<T: Pixel> $name<T> {
+            /// Convert with the scale factor, see the module for rounding
+            pub fn $convert<U: Pixel>(self, scale_factor: f64) -> $other<U> {
+                check_scale_factor(scale_factor);
+                $other::new(
+                    U::from_f64(self.$a.into() $op scale_factor),
+                    U::from_f64(self.$b.into() $op scale_factor),
+                )
+            }
+
+            /// Convert the components to another type in the same space
+            pub fn cast<U: Pixel>(self) -> $name<U> {
+                $name::new(self.$a.cast(), self.$b.cast())
+            }
+        }
+
+        impl<T> From<(T, T)> for $name<T> {
+            fn from(($a, $b): (T, T)) -> $name<T> {
+                $name::new($a, $b)
+            }
+        }
+
+        impl<T> From<$name<T>> for (T, T) {
+            fn from(value: $name<T>) -> (T, T) {
+                (value.$a, value.$b)
+            }
+        }
+    };
+}
+
+pixel_pair!(
+    /// A position in pixels of the X server
+    PhysicalPosition { x, y }, to_logical -> LogicalPosition, /
+);
+pixel_pair!(
+    /// A position in physical pixels divided by the scale factor
+    LogicalPosition { x, y }, to_physical -> PhysicalPosition, *
+);
+pixel_pair!(
+    /// A size in pixels of the X server
+    PhysicalSize { width, height }, to_logical -> LogicalSize, /
+);
+pixel_pair!(
+    /// A size in physical pixels divided by the scale factor
+    LogicalSize { width, height }, to_physical -> PhysicalSize, *
+);
+
+/// The share of the work area Size::Default windows take
+pub const DEFAULT_SIZE_FRACTION: f64 = 0.6;
+
+/// The smallest Size::Default window unless the builder sets a minimum
+pub const DEFAULT_MIN_INNER_SIZE: LogicalSize<f64> = LogicalSize::new(640.0, 480.0);
+
+/// A size in either space, for methods taking both. Tuples of u32 are
+/// physical
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Size {
+    Physical(PhysicalSize<u32>),
+    Logical(LogicalSize<f64>),
+    /// A fraction of the work area of the window's monitor in both
+    /// directions, e.g. 0.8 for 80% of the space panels leave, see
+    /// MonitorHandle::work_area
+    WorkArea(f64),
+    /// A size fitting the monitor: DEFAULT_SIZE_FRACTION of the work
+    /// area, at least the minimum size and at most the work area, see
+    /// default_inner_size. The minimum is DEFAULT_MIN_INNER_SIZE unless
+    /// set with WindowBuilder::with_min_inner_size
+    Default,
+}
+
+impl Size {
+    /// WorkArea sizes need their monitor, see
+    /// MonitorHandle::resolve_size, and are empty here. Default sizes are
+    /// their minimum
+    pub fn to_physical(self, scale_factor: f64) -> PhysicalSize<u32> {
+        match self {
+            Size::Physical(size) => size,
+            Size::Logical(size) => size.to_physical(scale_factor),
+            Size::WorkArea(_) => PhysicalSize::new(0, 0),
+            Size::Default => DEFAULT_MIN_INNER_SIZE.to_physical(scale_factor),
+        }
+    }
+
+    /// WorkArea sizes are empty and Default sizes their minimum, as with
+    /// to_physical
+    pub fn to_logical(self, scale_factor: f64) -> LogicalSize<f64> {
+        match self {
+            Size::Physical(size) => size.to_logical(scale_factor),
+            Size::Logical(size) => size,
+            Size::WorkArea(_) => LogicalSize::new(0.0, 0.0),
+            Size::Default => DEFAULT_MIN_INNER_SIZE,
+        }
+    }
+}
+
+impl<P: Pixel> From<PhysicalSize<P>> for Size {
+    fn from(size: PhysicalSize<P>) -> Size {
+        Size::Physical(size.cast())
+    }
+}
+
+impl<P: Pixel> From<LogicalSize<P>> for Size {
+    fn from(size: LogicalSize<P>) -> Size {
+        Size::Logical(size.cast())
+    }
+}
+
+impl From<(u32, u32)> for Size {
+    fn from(size: (u32, u32)) -> Size {
+        Size::Physical(size.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_physical_rounds_ties_to_even() {
+        // Each product is exact, so only the rounding decides
+        let cases: &[(f64, f64, u32)] = &[
+            (1.25, 2.0, 2),
+            (1.25, 6.0, 8),
+            (1.25, 10.0, 12),
+            (1.5, 1.0, 2),
+            (1.5, 3.0, 4),
+            (1.5, 5.0, 8),
+            (1.75, 2.0, 4),
+            (1.75, 6.0, 10),
+            (1.75, 10.0, 18),
+        ];
+        for &(scale, logical, physical) in cases {
+            let size = LogicalSize::new(logical, logical).to_physical::<u32>(scale);
+            assert_eq!(
+                size,
+                PhysicalSize::new(physical, physical),
+                "{} at {}",
+                logical,
+                scale
+            );
+        }
+        let position = LogicalPosition::new(-3.0, -5.0).to_physical::<i32>(1.5);
+        assert_eq!(position, PhysicalPosition::new(-4, -8));
+    }
+
+    #[test]
+    fn to_physical_rounds_to_nearest() {
+        let size = LogicalSize::new(101.0, 33.0).to_physical::<u32>(1.25);
+        assert_eq!(size, PhysicalSize::new(126, 41));
+        let size = LogicalSize::new(101.0, 33.0).to_physical::<u32>(1.75);
+        assert_eq!(size, PhysicalSize::new(177, 58));
+    }
+
+    #[test]
+    fn to_logical() {
+        for &scale in &[1.25, 1.5, 1.75] {
+            let size = PhysicalSize::new(700u32, 2100).to_logical::<f64>(scale);
+            let back = size.to_physical::<u32>(scale);
+            assert_eq!(back, PhysicalSize::new(700, 2100), "at {}", scale);
+        }
+        assert_eq!(
+            PhysicalSize::new(5u32, 7).to_logical::<f64>(1.25),
+            LogicalSize::new(4.0, 5.6)
+        );
+        // 7 / 1.5 and 11 / 1.75 round up, 4 / 1.5 down
+        assert_eq!(
+            PhysicalPosition::new(7i32, 4).to_logical::<i32>(1.5),
+            LogicalPosition::new(5, 3)
+        );
+        assert_eq!(
+            PhysicalPosition::new(11i32, -11).to_logical::<i32>(1.75),
+            LogicalPosition::new(6, -6)
+        );
+    }
+
+    #[test]
+    fn cast_rounds_ties_to_even() {
+        let position = PhysicalPosition::new(2.5, -2.5).cast::<i32>();
+        assert_eq!(position, PhysicalPosition::new(2, -2));
+        let position = PhysicalPosition::new(3.5, -3.5).cast::<i32>();
+        assert_eq!(position, PhysicalPosition::new(4, -4));
+        let size = LogicalSize::new(0.5f32, 1.5).cast::<u16>();
+        assert_eq!(size, LogicalSize::new(0, 2));
+    }
+
+    #[test]
+    fn nan_becomes_zero() {
+        let size = PhysicalSize::new(f64::NAN, 3.0).cast::<u32>();
+        assert_eq!(size, PhysicalSize::new(0, 3));
+        let position = LogicalPosition::new(f64::NAN, 1.0).to_physical::<i32>(1.5);
+        assert_eq!(position, PhysicalPosition::new(0, 2));
+    }
+
+    #[test]
+    fn overflow_saturates() {
+        let size = PhysicalSize::new(1e12, -5.0).cast::<u32>();
+        assert_eq!(size, PhysicalSize::new(u32::MAX, 0));
+        let position = PhysicalPosition::new(40000.0, -40000.0).cast::<i16>();
+        assert_eq!(position, PhysicalPosition::new(i16::MAX, i16::MIN));
+        assert_eq!(
+            LogicalSize::new(300.0, 255.5).cast::<u8>(),
+            LogicalSize::new(255, 255)
+        );
+        let size = LogicalSize::new(3e9, 1.0).to_physical::<u32>(1.75);
+        assert_eq!(size, PhysicalSize::new(u32::MAX, 2));
+        let size = LogicalSize::new(f64::INFINITY, 1.0).to_physical::<i32>(1.25);
+        assert_eq!(size, PhysicalSize::new(i32::MAX, 1));
+    }
+}
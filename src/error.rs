@@ -0,0 +1,140 @@
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+use std::sync::Mutex;
+
+/// Errors returned by operations the X server may not support
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The named X extension, its client library or desktop service is
+    /// unavailable
+    Unsupported(&'static str),
+    /// Another client holds a grab of the device
+    AlreadyGrabbed,
+    /// The window must be mapped and visible to grab input to it
+    NotViewable,
+    /// The X server could not be reached
+    ConnectionFailed,
+    /// The window given by its X id does not exist
+    NoSuchWindow,
+    /// The key chord is not modifiers and a key of the keyboard joined
+    /// by +, e.g. "Ctrl+Print"
+    InvalidHotkey,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Unsupported(feature) => write!(f, "{} is not available", feature),
+            Error::AlreadyGrabbed => write!(f, "the device is grabbed by another client"),
+            Error::NotViewable => write!(f, "the window is not viewable"),
+            Error::ConnectionFailed => write!(f, "cannot connect to the X server"),
+            Error::NoSuchWindow => write!(f, "the window does not exist"),
+            Error::InvalidHotkey => write!(f, "the hotkey is not a key chord of the keyboard"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// An error the X server returned for a request, see
+/// EventLoop::take_x_errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XError {
+    /// The error code, e.g. 3 for BadWindow
+    pub error_code: u8,
+    /// Major and minor opcode of the failed request
+    pub request_code: u8,
+    pub minor_code: u8,
+    /// The resource the request named, e.g. a window id
+    pub resource: u64,
+    /// Xlib's description of the error code
+    pub description: String,
+}
+
+impl fmt::Display for XError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} in request {}.{} on resource {:#x}",
+            self.description, self.request_code, self.minor_code, self.resource
+        )
+    }
+}
+
+impl error::Error for XError {}
+
+// Errors of every connection not taken yet, the oldest are dropped once
+// nobody takes them
+const RECORDED_LIMIT: usize = 256;
+static RECORDED: Mutex<VecDeque<XError>> = Mutex::new(VecDeque::new());
+
+pub(crate) fn record_x_error(error: XError) {
+    let mut recorded = RECORDED.lock().unwrap();
+    if recorded.len() == RECORDED_LIMIT {
+        recorded.pop_front();
+    }
+    recorded.push_back(error);
+}
+
+pub(crate) fn take_x_errors() -> Vec<XError> {
+    RECORDED.lock().unwrap().drain(..).collect()
+}
+
+/// Take the recorded errors an expected failure caused, leaving the rest
+pub(crate) fn take_x_errors_where(mut f: impl FnMut(&XError) -> bool) -> Vec<XError> {
+    let mut taken = Vec::new();
+    RECORDED.lock().unwrap().retain(|error| {
+        let matches = f(error);
+        if matches {
+            taken.push(error.clone());
+        }
+        !matches
+    });
+    taken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages() {
+        let messages = [
+            (
+                Error::Unsupported("XInput 2.2"),
+                "XInput 2.2 is not available",
+            ),
+            (
+                Error::AlreadyGrabbed,
+                "the device is grabbed by another client",
+            ),
+            (Error::NotViewable, "the window is not viewable"),
+            (Error::ConnectionFailed, "cannot connect to the X server"),
+            (Error::NoSuchWindow, "the window does not exist"),
+            (
+                Error::InvalidHotkey,
+                "the hotkey is not a key chord of the keyboard",
+            ),
+        ];
+        for (error, message) in messages {
+            assert_eq!(error.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn x_error_message() {
+        let error = XError {
+            error_code: 3,
+            request_code: 18,
+            minor_code: 0,
+            resource: 0x40_0001,
+            description: "BadWindow (invalid Window parameter)".into(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "BadWindow (invalid Window parameter) in request 18.0 on resource 0x400001"
+        );
+    }
+}
@@ -0,0 +1,432 @@
+use std::time::Duration;
+
+use x11::xlib;
+
+use crate::dpi::{PhysicalPosition, PhysicalSize};
+use crate::window::Rect;
+
+/// An event translated from the X server.
+///
+/// Events are plain Copy values so translating them never allocates,
+/// variants carrying variable-length data should borrow it from a buffer
+/// owned by the EventLoop rather than boxing it per event.
+///
+/// New variants are added in minor releases, matches need a wildcard arm
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Event {
+    /// A window was created by this loop, delivered by the next poll
+    WindowCreated { window: WindowId },
+    /// The X window was destroyed, no further events carry its id
+    Destroyed { window: WindowId },
+    /// The window manager asked for the window to be closed
+    CloseRequested { window: WindowId },
+    /// The window contents changed size
+    Resized {
+        window: WindowId,
+        size: PhysicalSize<u32>,
+    },
+    /// The window gained or lost keyboard focus
+    Focused { window: WindowId, focused: bool },
+    /// The window manager changed _NET_WM_STATE, e.g. when the user
+    /// maximized the window from its title bar. Delivered once per
+    /// transition, also for changes the application requested
+    StateChanged {
+        window: WindowId,
+        change: StateChange,
+    },
+    /// A key was pressed or released.
+    ///
+    /// Keys held when the window loses focus, also to a keyboard grab of
+    /// another client, are released then. Keys still held when focus
+    /// returns are pressed again, so presses and releases stay paired.
+    /// Held keys repeat as further presses without releases in between
+    KeyboardInput {
+        window: WindowId,
+        keycode: u32,
+        keysym: xlib::KeySym,
+        pressed: bool,
+        /// Made up by daswin rather than reported by the server, on focus
+        /// changes and for repeats of EventLoop::set_key_repeat
+        synthetic: bool,
+        /// A press repeating a held key
+        repeat: bool,
+    },
+    /// Text typed or composed by the input method while IME input is
+    /// allowed, see X11Window::set_ime_allowed. The text is
+    /// EventLoop::text(index) until the next poll, EventPlayer::text on
+    /// replay
+    Text { window: WindowId, index: u32 },
+    /// A mouse button was pressed or released at the given position.
+    ///
+    /// Every press is followed by a release of the same button, which is
+    /// reported early when another client grabs the pointer or focus
+    /// moves elsewhere during a drag. Buttons still held when focus
+    /// returns are pressed again
+    MouseInput {
+        window: WindowId,
+        button: u32,
+        pressed: bool,
+        position: PhysicalPosition<f64>,
+        /// The same position relative to the root window, where windows
+        /// are placed with Position::At
+        root_position: PhysicalPosition<f64>,
+        /// Made up by daswin on focus and grab changes rather than
+        /// reported by the server
+        synthetic: bool,
+        /// The master pointer, see CursorMoved
+        device: DeviceId,
+    },
+    /// The wheel turned or the touchpad scrolled over the window
+    MouseWheel {
+        window: WindowId,
+        delta: ScrollDelta,
+    },
+    /// The pointer moved within the window, delta is the movement in
+    /// physical pixels since the previous CursorMoved of the same pointer
+    /// for the same window
+    CursorMoved {
+        window: WindowId,
+        position: PhysicalPosition<f64>,
+        /// See MouseInput
+        root_position: PhysicalPosition<f64>,
+        delta: (f64, f64),
+        /// The master pointer which moved. Always the core pointer,
+        /// EventLoop::core_pointer, unless the server has several master
+        /// pointers as with multi-seat setups
+        device: DeviceId,
+    },
+    /// Raw input from a physical device, delivered regardless of which
+    /// window has focus depending on EventLoop::listen_device_events
+    DeviceEvent {
+        device: DeviceId,
+        event: DeviceEvent,
+    },
+    /// A pen moved over or touched a tablet while over the window.
+    ///
+    /// Pressure runs from 0 to 1 and tilt from -1 to 1 across the range
+    /// of the device, contact tells a pen touching the tablet with no
+    /// pressure apart from a hovering one
+    Tablet {
+        window: WindowId,
+        device: DeviceId,
+        position: PhysicalPosition<f64>,
+        pressure: f32,
+        tilt: (f32, f32),
+        tool: TabletTool,
+        contact: bool,
+    },
+    /// The process received SIGTERM or SIGINT, see
+    /// EventLoop::catch_exit_signals
+    ExitRequested,
+    /// Events were dropped by the queue policy since the last poll
+    EventsLost(u32),
+    /// Monitors were connected, disconnected or changed mode, see
+    /// EventLoop::available_monitors and X11Window::current_monitor for
+    /// the new configuration
+    MonitorConfigurationChanged,
+    /// Panels or docks appeared, went away or moved, or the desktop
+    /// switched to one with another work area, see
+    /// MonitorHandle::work_area. Delivered once per poll
+    WorkAreaChanged,
+    /// The center of the window moved onto another monitor, or onto its
+    /// first. The monitor is EventLoop::monitor_from_raw(monitor), whose
+    /// surface formats may differ from the previous one's
+    MonitorChanged { window: WindowId, monitor: u64 },
+    /// The previous frame, from one poll to the next, took longer than
+    /// the budget set with EventLoop::set_frame_deadline.
+    ///
+    /// `translation` is the part spent translating events in poll_events,
+    /// the rest was spent by the application, including presenting
+    FrameDeadlineMissed {
+        elapsed: Duration,
+        budget: Duration,
+        translation: Duration,
+    },
+    /// A wgpu device created by render::GpuContext raised an error no
+    /// error scope caught, e.g. a validation error while recording a
+    /// frame. The message is EventLoop::gpu_error(index) until the next
+    /// poll
+    GpuError { index: u32 },
+    /// The system is about to suspend, finish in-flight GPU work now.
+    /// Suspend waits for the next poll, up to the logind delay limit.
+    ///
+    /// Only delivered with the dbus feature, see
+    /// EventLoop::listen_sleep_events
+    Suspending,
+    /// The system woke up from suspend, surfaces and textures may have
+    /// been lost by the driver
+    Resumed,
+    /// The input following it up to the first redraw was read right
+    /// before the redraws, see EventLoop::poll_late_input
+    LateInput,
+    /// The window should be drawn, see X11Window::request_redraw
+    RedrawRequested { window: WindowId },
+    /// The last event of every poll, also when no other event arrived.
+    /// Advance simulations and request redraws here, once per iteration
+    /// of the application's loop rather than once per event. run blocks
+    /// after it unless a redraw was requested, loops of their own call
+    /// EventLoop::wait_events there
+    AboutToWait,
+    /// The X server went away or stopped answering, see
+    /// EventLoop::set_connection_timeout. Delivered once, no further
+    /// events arrive until EventLoop::reconnect succeeds
+    ConnectionLost,
+    /// An input device was plugged in or created, EventLoop::device
+    /// tells what it is
+    DeviceAdded { device: DeviceId },
+    /// An input device was unplugged or removed
+    DeviceRemoved { device: DeviceId },
+    /// A StatusIcon was clicked, `secondary` for middle clicks. The
+    /// position is in root window coordinates when the tray tells it.
+    ///
+    /// Only delivered with the dbus feature
+    TrayIconClicked {
+        secondary: bool,
+        position: PhysicalPosition<i32>,
+    },
+    /// An item of a StatusIcon's menu was clicked, with the id given to
+    /// MenuItem::new
+    TrayMenuItem { id: u32 },
+    /// A frame of the window reached the screen, only delivered after
+    /// X11Window::enable_present_feedback. `ust` tells when on the
+    /// monotonic clock, see ServerClock::ust_instant, and `msc` counts
+    /// the vertical blanks of the monitor
+    FramePresented {
+        window: WindowId,
+        msc: u64,
+        ust: Duration,
+    },
+    /// Where the canvas of a RenderSurface lands in the window changed,
+    /// see RenderSurface::set_canvas. Delivered by the poll after the
+    /// surface was configured
+    CanvasViewportChanged { window: WindowId, viewport: Rect },
+    /// The window dragged with X11Window::begin_manual_drag snapped with
+    /// `edge` to the edge of a work area or another window, see
+    /// EventLoop::set_snapping
+    Snapped { window: WindowId, edge: Edge },
+    /// A foreign window hosted with X11Window::embed_foreign was
+    /// destroyed by its client and is no longer embedded
+    EmbeddedChildDestroyed {
+        window: WindowId,
+        child: xlib::Window,
+    },
+    /// Messages were sent over the channel since the last poll, take
+    /// them with EventLoop::drain_channel. Delivered once per poll and
+    /// channel however many were sent
+    Channel(ChannelId),
+    /// The chord of a hotkey registered with
+    /// EventLoop::register_global_hotkey was pressed, whichever window
+    /// had focus
+    GlobalHotkey(HotkeyId),
+    /// A script of a ScriptHost failed to load or run. The message is
+    /// EventLoop::script_error(index) until the next poll.
+    ///
+    /// Only delivered with the scripting feature
+    ScriptError { index: u32 },
+    /// The buffer mapped with GpuContext::map_async_callback was read and
+    /// its closure ran, or failed. Not delivered for readbacks cancelled
+    /// before.
+    ///
+    /// Only delivered with the wgpu feature
+    GpuReadbackReady(ReadbackId),
+    /// The owner `window` of the modal window `modal` was clicked while
+    /// its input is blocked, e.g. to flash the dialog. Delivered for the
+    /// first click since the modal window last had focus, see
+    /// EventLoop::set_modal_input_blocking
+    ModalBlocked { window: WindowId, modal: WindowId },
+}
+
+/// Identifies a window for the life of the EventLoop that created it.
+///
+/// X window ids can be reused once a window is destroyed, so
+/// the id pairs the X id with a generation counted up by the loop for
+/// every window it creates. A stale WindowId never equals the id of a
+/// newer window which happens to get the same X id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WindowId(u64);
+
+// Window ids are unsigned longs, 32 bit on some targets
+#[allow(clippy::unnecessary_cast)]
+impl WindowId {
+    pub(crate) fn new(window: xlib::Window, generation: u32) -> WindowId {
+        WindowId((generation as u64) << 32 | (window as u64 & 0xffff_ffff))
+    }
+
+    /// The X id of the window, for use with other X libraries
+    pub fn xid(self) -> xlib::Window {
+        (self.0 & 0xffff_ffff) as xlib::Window
+    }
+
+    /// The id including its generation, e.g. to store it elsewhere
+    pub fn into_raw(self) -> u64 {
+        self.0
+    }
+
+    /// Restore an id returned by into_raw
+    pub fn from_raw(raw: u64) -> WindowId {
+        WindowId(raw)
+    }
+}
+
+/// Identifies an XInput2 input device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DeviceId(pub(crate) u32);
+
+impl DeviceId {
+    /// The XInput2 device id, for use with other X libraries
+    pub fn into_raw(self) -> u32 {
+        self.0
+    }
+
+    /// Wrap an XInput2 device id
+    pub fn from_raw(raw: u32) -> DeviceId {
+        DeviceId(raw)
+    }
+}
+
+/// Identifies a channel created with EventLoop::create_channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChannelId(pub(crate) u32);
+
+impl ChannelId {
+    /// The id as a number, e.g. to store it elsewhere
+    pub fn into_raw(self) -> u32 {
+        self.0
+    }
+
+    /// Restore an id returned by into_raw
+    pub fn from_raw(raw: u32) -> ChannelId {
+        ChannelId(raw)
+    }
+}
+
+/// Identifies a hotkey registered with EventLoop::register_global_hotkey
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct HotkeyId(pub(crate) u32);
+
+impl HotkeyId {
+    /// The id as a number, e.g. to store it elsewhere
+    pub fn into_raw(self) -> u32 {
+        self.0
+    }
+
+    /// Restore an id returned by into_raw
+    pub fn from_raw(raw: u32) -> HotkeyId {
+        HotkeyId(raw)
+    }
+}
+
+/// Identifies a readback started with GpuContext::map_async_callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ReadbackId(pub(crate) u32);
+
+impl ReadbackId {
+    /// The id as a number, e.g. to store it elsewhere
+    pub fn into_raw(self) -> u32 {
+        self.0
+    }
+
+    /// Restore an id returned by into_raw
+    pub fn from_raw(raw: u32) -> ReadbackId {
+        ReadbackId(raw)
+    }
+}
+
+/// Raw device input, carrying physical keycodes only and never text
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum DeviceEvent {
+    /// A key was pressed or released
+    Key { keycode: u32, pressed: bool },
+    /// A mouse button was pressed or released
+    Button { button: u32, pressed: bool },
+    /// Unaccelerated pointer motion
+    Motion { delta: (f64, f64) },
+}
+
+/// Distance scrolled, positive values scroll up and left like wheel
+/// buttons 4 and 6 do.
+///
+/// Devices reporting smooth scrolling through XInput2 scroll in pixels
+/// once they scrolled by part of a line, PIXELS_PER_LINE for each wheel
+/// click worth of scrolling. Wheels scroll in lines
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrollDelta {
+    Lines(f32, f32),
+    Pixels(f64, f64),
+}
+
+impl ScrollDelta {
+    /// Pixels scrolled per line
+    pub const PIXELS_PER_LINE: f64 = crate::scroll::PIXELS_PER_LINE;
+
+    /// The distance in pixels, converting lines
+    pub fn to_pixels(self) -> (f64, f64) {
+        match self {
+            ScrollDelta::Lines(x, y) => (
+                x as f64 * Self::PIXELS_PER_LINE,
+                y as f64 * Self::PIXELS_PER_LINE,
+            ),
+            ScrollDelta::Pixels(x, y) => (x, y),
+        }
+    }
+}
+
+/// A window manager state transition, see Event::StateChanged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StateChange {
+    Maximized,
+    Unmaximized,
+    FullscreenEntered,
+    FullscreenExited,
+    /// The urgency hint was cleared, usually by focusing the window
+    AttentionCleared,
+}
+
+/// An edge of a window, see Event::Snapped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Left,
+    Top,
+    Right,
+    Bottom,
+}
+
+/// The end of the pen used on a tablet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabletTool {
+    Pen,
+    Eraser,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn generations_tell_reused_ids_apart() {
+        let xid = 0x0480_0003;
+        let old = WindowId::new(xid, 1);
+        let new = WindowId::new(xid, 2);
+        assert_ne!(old, new);
+        assert_eq!(old.xid(), new.xid());
+        let ids: HashSet<WindowId> = [old, new].iter().copied().collect();
+        assert_eq!(ids.len(), 2);
+    }
+
+    #[test]
+    fn raw_round_trip() {
+        for &(xid, generation) in &[(0x0480_0003, 0), (1, 7), (0xffff_ffff, u32::MAX)] {
+            let id = WindowId::new(xid, generation);
+            assert_eq!(id.xid(), xid);
+            let raw = id.into_raw();
+            assert_eq!(raw >> 32, generation as u64);
+            assert_eq!(WindowId::from_raw(raw), id);
+            assert_eq!(WindowId::from_raw(raw).xid(), xid);
+        }
+    }
+}
@@ -0,0 +1,3950 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw;
+use std::os::unix::io::RawFd;
+use std::panic;
+use std::process;
+use std::rc::{Rc, Weak};
+use std::slice;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bitflags::bitflags;
+use x11::xrandr::{RRNotify, RROutput, RRScreenChangeNotify};
+use x11::{xinput2, xlib};
+
+use crate::backend::{self, Backend, BackendError};
+#[cfg(feature = "async")]
+use crate::channel::AsyncChannelProxy;
+use crate::channel::{ChannelError, ChannelProxy, Channels};
+use crate::clock::{self, EventTime, ServerClock};
+use crate::conn_thread::ConnectionThread;
+use crate::device::{self, DeviceInfo, DeviceType};
+#[cfg(feature = "dbus")]
+use crate::dialogs;
+use crate::dpi::{PhysicalPosition, PhysicalSize};
+use crate::error::{self, Error, XError};
+use crate::event::{
+    ChannelId, DeviceEvent, DeviceId, Event, HotkeyId, ScrollDelta, StateChange, WindowId,
+};
+use crate::ext;
+use crate::foreign;
+use crate::history::{History, TimestampedEvent};
+use crate::hotkey::Hotkeys;
+use crate::input::{InputHandle, InputSnapshot};
+use crate::keyboard;
+use crate::metrics::{EventLoopMetrics, Metrics, MetricsHandle, Wakeup};
+use crate::monitor::{self, MonitorHandle};
+use crate::present::Completion;
+use crate::proxy::WindowCommand;
+#[cfg(feature = "wgpu")]
+use crate::render::readback;
+use crate::resize::{self, ResizeStorm};
+#[cfg(feature = "scripting")]
+use crate::script::{self, Script};
+use crate::scroll::{self, ScrollDevice};
+use crate::seat;
+use crate::session::{self, SessionGuard};
+use crate::signal;
+#[cfg(feature = "dbus")]
+use crate::sleep::SleepMonitor;
+use crate::snap::{self, Snapping, Targets};
+#[cfg(feature = "async")]
+use crate::stream::EventStream;
+use crate::tablet::{self, Tablet};
+#[cfg(feature = "dbus")]
+use crate::tray::Tray;
+use crate::wake;
+use crate::watchdog::Watchdog;
+use crate::window::{self, Fullscreen, LastKnown, Rect, WindowState, X11Window};
+use crate::wm::{self, WmInfo};
+use crate::xconn::XConnection;
+use crate::xembed;
+
+/// What to do when more events arrive than the queue holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// Discard the oldest queued event to make room
+    DropOldest,
+    /// Discard the incoming event
+    DropNewest,
+    /// Leave further events with the X server until the next poll
+    Block,
+}
+
+/// Timing of the key repeats synthesized by EventLoop::set_key_repeat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyRepeat {
+    /// From the press to the first repeat
+    pub delay: Duration,
+    /// Between repeats
+    pub interval: Duration,
+}
+
+impl Default for KeyRepeat {
+    /// 250 ms, then 30 repeats a second
+    fn default() -> KeyRepeat {
+        KeyRepeat {
+            delay: Duration::from_millis(250),
+            interval: Duration::from_secs(1) / 30,
+        }
+    }
+}
+
+/// What EventLoop::run does when the handler panics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PanicPolicy {
+    /// Undo grabs and video modes, then abort the process right away,
+    /// leaving windows to the server
+    Abort,
+    /// Undo grabs and video modes, destroy every window, wait for the GPU
+    /// and continue unwinding with the panic
+    #[default]
+    CleanupThenResume,
+    /// Undo grabs and video modes, destroy every window, wait for the GPU
+    /// and return `code` from run
+    ConvertToExit(i32),
+}
+
+// The key repeated for set_key_repeat, along with the text its press
+// typed
+struct Repeating {
+    window: xlib::Window,
+    keycode: u32,
+    keysym: xlib::KeySym,
+    text: Option<String>,
+    next: Instant,
+}
+
+/// Whether the application should keep running after pump_events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PumpStatus {
+    Continue,
+    /// A window was asked to close, the process to exit, or exit was
+    /// called with `code`
+    Exit {
+        code: i32,
+    },
+}
+
+/// When raw device input is delivered as Event::DeviceEvent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceEvents {
+    /// Always, even while none of the loop's windows has focus
+    Always,
+    /// Only while one of the loop's windows has focus
+    WhenFocused,
+    /// Never
+    Never,
+}
+
+bitflags! {
+    /// Categories of events the loop delivers, see
+    /// EventLoop::set_event_filter. Lifecycle, close and loop events are
+    /// always delivered
+    pub struct EventFilter: u32 {
+        /// KeyboardInput and Text
+        const KEYBOARD = 1 << 0;
+        /// CursorMoved
+        const POINTER_MOTION = 1 << 1;
+        /// MouseInput
+        const POINTER_BUTTON = 1 << 2;
+        /// Tablet
+        const TOUCH = 1 << 3;
+        /// Focused
+        const FOCUS = 1 << 4;
+        /// Resized
+        const WINDOW_STATE = 1 << 5;
+    }
+}
+
+impl EventFilter {
+    /// The category of an event, empty for events which are always
+    /// delivered
+    fn of(event: &Event) -> EventFilter {
+        match event {
+            Event::KeyboardInput { .. } | Event::Text { .. } => EventFilter::KEYBOARD,
+            Event::CursorMoved { .. } => EventFilter::POINTER_MOTION,
+            Event::MouseInput { .. } | Event::MouseWheel { .. } => EventFilter::POINTER_BUTTON,
+            Event::Tablet { .. } => EventFilter::TOUCH,
+            Event::Focused { .. } => EventFilter::FOCUS,
+            Event::Resized { .. } | Event::StateChanged { .. } | Event::MonitorChanged { .. } => {
+                EventFilter::WINDOW_STATE
+            }
+            _ => EventFilter::empty(),
+        }
+    }
+
+    /// The core event mask selected on windows. Focus, structure and
+    /// property changes stay selected, grabs, fullscreen, sizes and the
+    /// cached window manager state depend on them, as do foreign windows
+    /// embedded with X11Window::embed_foreign on those of children
+    pub(crate) fn event_mask(self) -> raw::c_long {
+        let mut mask = xlib::FocusChangeMask
+            | xlib::StructureNotifyMask
+            | xlib::SubstructureNotifyMask
+            | xlib::PropertyChangeMask;
+        if self.contains(EventFilter::KEYBOARD) {
+            mask |= xlib::KeyPressMask | xlib::KeyReleaseMask;
+        }
+        if self.contains(EventFilter::POINTER_MOTION) {
+            // Leaving hides software cursors
+            mask |= xlib::PointerMotionMask | xlib::LeaveWindowMask;
+        }
+        if self.contains(EventFilter::POINTER_BUTTON) {
+            // Leaving for another client's grab ends drags
+            mask |= xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::LeaveWindowMask;
+        }
+        mask
+    }
+}
+
+impl Default for EventFilter {
+    fn default() -> Self {
+        EventFilter::all()
+    }
+}
+
+/// The pointer at the time of a query
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PointerState {
+    /// Position relative to the root window
+    pub position: PhysicalPosition<i32>,
+    /// Held modifiers and buttons, e.g. xlib::ShiftMask or xlib::Button1Mask
+    pub mask: u32,
+}
+
+// Id and state of a window, the state is gone once the window was dropped
+type WindowEntry = (WindowId, Weak<RefCell<WindowState>>);
+
+/// Owns the connection to the X server and translates its events
+pub struct EventLoop {
+    pub(crate) conn: Arc<XConnection>,
+    thread: Option<ConnectionThread>,
+    windows: RefCell<HashMap<xlib::Window, WindowEntry>>,
+    // Events of each HelperWindow, kept until it takes them
+    helpers: RefCell<HashMap<xlib::Window, Weak<RefCell<Vec<xlib::XEvent>>>>>,
+    // Counted up for every window created, see WindowId
+    generation: Cell<u32>,
+    // Windows created since the last poll, reported as WindowCreated
+    created: RefCell<Vec<WindowId>>,
+
+    motion_coalescing: bool,
+    // Input of owners of modal windows is dropped, see
+    // set_modal_input_blocking
+    modal_input_blocking: bool,
+    blocked_presses: BlockedPresses,
+    // Input read once more right before redraws, see set_late_latch
+    late_latch: bool,
+    // Snapping of windows moved with X11Window::begin_manual_drag
+    snapping: Option<Snapping>,
+    // Last reported position of each master pointer, used to compute
+    // motion deltas
+    cursors: HashMap<DeviceId, (xlib::Window, f64, f64)>,
+    // Last reported size of each window, used to filter out moves
+    sizes: HashMap<xlib::Window, (u32, u32)>,
+    // Windows resized faster than the throttle, see set_resize_throttle
+    resize_throttle: Option<Duration>,
+    resizes: HashMap<xlib::Window, ResizeStorm>,
+
+    queue_limit: Option<(QueuePolicy, usize)>,
+    events_lost: u32,
+    // Whether the queue limit left events for the next poll
+    backlogged: bool,
+
+    // Repeats synthesized in place of the server's, along with the keys
+    // the server lets repeat, one bit each, and the key repeating now
+    key_repeat: Option<KeyRepeat>,
+    repeating_keys: [u8; 32],
+    repeating: Option<Repeating>,
+
+    // Major opcode of XInput2, None when raw events are unavailable
+    xinput2_opcode: Option<i32>,
+    device_events: DeviceEvents,
+    // Window of this loop holding keyboard focus
+    focused: Option<xlib::Window>,
+    // Every XInput2 device, along with DeviceAdded and DeviceRemoved
+    // events of the last hierarchy change
+    devices: Vec<DeviceInfo>,
+    hotplugged: Vec<Event>,
+    // Pen tablets by XInput2 device id
+    tablets: HashMap<i32, Tablet>,
+    // Pointers with scroll valuators by XInput2 device id, along with the
+    // time of their last scroll. The server emulates core wheel buttons
+    // from the valuators at the same time
+    scroll_devices: HashMap<i32, ScrollDevice>,
+    smooth_scroll_time: xlib::Time,
+    // Set while the server has several master pointers, their XInput2
+    // events are translated in place of the merged core events then,
+    // see seat.rs. The device of the event translated right now
+    seats: bool,
+    event_device: Option<DeviceId>,
+    // Read end of the pipe SIGTERM and SIGINT are forwarded to
+    exit_signals: Option<RawFd>,
+    // Chords grabbed with register_global_hotkey
+    hotkeys: Hotkeys,
+
+    // RandR event base when monitor changes are reported, along with the
+    // monitors they keep up to date
+    randr_event_base: Option<i32>,
+    monitors: Option<Vec<MonitorHandle>>,
+    monitors_changed: bool,
+    // _NET_WORKAREA of the current desktop as last reported
+    work_area: Option<Rect>,
+    work_area_changed: bool,
+
+    frame_deadline: Option<Duration>,
+    // Start of the current frame and the time its poll spent translating
+    frame_start: Option<Instant>,
+    translation_time: Duration,
+    // Set for the frame delivering a miss, so a slow handler for it is
+    // not reported in turn
+    deadline_reported: bool,
+
+    // Connection to logind when sleep events are delivered
+    #[cfg(feature = "dbus")]
+    sleep: Option<SleepMonitor>,
+    // Tray icons created with the loop, dropped icons are skipped
+    #[cfg(feature = "dbus")]
+    status_icons: RefCell<Vec<Weak<RefCell<Tray>>>>,
+    // Scripts of the ScriptHosts created with the loop
+    #[cfg(feature = "scripting")]
+    scripts: RefCell<Vec<Weak<RefCell<Script>>>>,
+
+    // Set by exit, reported by pump_events
+    exit_code: Cell<Option<i32>>,
+    panic_policy: PanicPolicy,
+    // Whether pump_events exits once no window is left open
+    exit_on_last_window_closed: bool,
+
+    // Watches for a stalled server, see set_connection_timeout
+    watchdog: Option<Watchdog>,
+    // Set once Event::ConnectionLost was delivered
+    connection_lost: bool,
+
+    // Messages of the GpuError events delivered by the last poll
+    gpu_errors: Vec<String>,
+    // Messages of the ScriptError events delivered by the last poll
+    #[cfg(feature = "scripting")]
+    script_errors: Vec<String>,
+    // Strings of the Text events delivered by the last poll
+    texts: Vec<String>,
+    // Input state updated at the end of every poll
+    input: InputHandle,
+    // Message queues of create_channel, shared with their proxies
+    channels: Arc<Channels>,
+    // Counters of metrics, with the end of the last poll and the time
+    // the handler of run took since
+    metrics: Arc<Metrics>,
+    poll_end: Option<Instant>,
+    handler_time: Duration,
+    // Whether SIGUSR1 prints the metrics, see print_metrics_on_signal
+    metrics_on_signal: bool,
+    // The last events delivered, see recent_events
+    history: History,
+
+    pub(crate) event_filter: EventFilter,
+
+    // Xlib calls must stay on the thread owning the loop
+    _not_send: PhantomData<*mut ()>,
+}
+
+/// Options for connecting an EventLoop.
+///
+/// By default the first backend which connects is used, see
+/// Backend::Auto, and Xlib is driven from the thread polling the loop
+#[derive(Debug, Clone, Default)]
+pub struct EventLoopBuilder {
+    backend: Backend,
+    connection_thread: Option<usize>,
+}
+
+impl EventLoopBuilder {
+    pub fn new() -> EventLoopBuilder {
+        EventLoopBuilder::default()
+    }
+
+    /// The backend to connect with, Auto by default. DASWIN_BACKEND
+    /// overrides it when set to x11, wayland or auto
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Own the connection with a dedicated thread buffering up to
+    /// `capacity` events, see EventLoop::new_threaded
+    pub fn with_connection_thread(mut self, capacity: usize) -> Self {
+        self.connection_thread = Some(capacity);
+        self
+    }
+
+    /// Connect with the first backend which works, the error lists why
+    /// each backend tried failed
+    pub fn build(self) -> Result<EventLoop, BackendError> {
+        let backend = Backend::from_env().unwrap_or(self.backend);
+        let var = |name: &str| env::var_os(name).map(|value| value.to_string_lossy().into_owned());
+        backend::connect(backend, var, || self.connect_x11())
+    }
+
+    fn connect_x11(&self) -> Option<EventLoop> {
+        match self.connection_thread {
+            Some(capacity) => {
+                let (conn, thread) = ConnectionThread::spawn(capacity)?;
+                Some(EventLoop::with_connection(conn, Some(thread)))
+            }
+            None => {
+                let conn = XConnection::open(None)?;
+                Some(EventLoop::with_connection(Arc::new(conn), None))
+            }
+        }
+    }
+}
+
+impl Default for EventLoop {
+    fn default() -> Self {
+        EventLoop::new()
+    }
+}
+
+impl EventLoop {
+    /// Connect to the display server, panicking when none is available.
+    /// EventLoopBuilder reports why instead
+    pub fn new() -> EventLoop {
+        EventLoopBuilder::new()
+            .build()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Connect to the X server with a dedicated thread owning the
+    /// connection, so rendering continues while event delivery stalls
+    /// during window manager grabs.
+    ///
+    /// The thread waits for events and forwards them over a channel holding
+    /// up to `capacity` events, window requests are marshalled to it so
+    /// Xlib is only ever driven from one thread. This calls XInitThreads,
+    /// so it must be created before any other Xlib use in the process
+    pub fn new_threaded(capacity: usize) -> EventLoop {
+        EventLoopBuilder::new()
+            .with_connection_thread(capacity)
+            .build()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Use a display the application opened itself, e.g. to share atoms
+    /// and selections with older Xlib code. Windows are created on it as
+    /// usual and existing ones can be adopted with X11Window::from_raw.
+    ///
+    /// The loop only takes the events of its own windows and of the
+    /// extensions it selected from the queue, the application keeps
+    /// reading those of its windows. Detectable autorepeat is left as the
+    /// application set it. Only the display is closed when `owns` is set,
+    /// until then it is the application's: losing the connection goes to
+    /// its IO error handlers and EventLoop::reconnect is unsupported.
+    ///
+    /// # Safety
+    ///
+    /// `display` must be an open connection, which is not closed before
+    /// the loop and all its windows, surfaces and proxies are dropped,
+    /// and with `owns` is not closed by the application at all. It must
+    /// be used on the calling thread alone while the loop is alive, unless
+    /// the application called XInitThreads and locks the display itself.
+    /// daswin installs its own process wide X error handler, recording
+    /// errors for take_x_errors in place of the application's
+    pub unsafe fn from_raw_display(display: *mut xlib::Display, owns: bool) -> EventLoop {
+        debug_assert!(!display.is_null(), "from_raw_display with a null display");
+        debug_assert!(
+            xlib::XConnectionNumber(display) >= 0,
+            "from_raw_display with a closed display"
+        );
+        let conn = XConnection::from_display(display, owns);
+        EventLoop::with_connection(Arc::new(conn), None)
+    }
+
+    /// The backend the loop connected with
+    pub fn backend(&self) -> Backend {
+        Backend::X11
+    }
+
+    fn with_connection(conn: Arc<XConnection>, thread: Option<ConnectionThread>) -> EventLoop {
+        conn.request(XConnection::set_command);
+        // Per client, a shared display keeps the application's setting
+        if !conn.shared {
+            conn.request(XConnection::set_detectable_autorepeat);
+        }
+        let (xinput2_opcode, devices, tablets, scroll_devices) =
+            conn.request(|conn| match query_xinput2(conn) {
+                Some(opcode) => {
+                    select_raw_events(conn, true);
+                    tablet::select_hierarchy_events(conn);
+                    let core_pointer = seat::query_client_pointer(conn).into_raw();
+                    conn.core_pointer.store(core_pointer, Ordering::Relaxed);
+                    let scroll_devices = scroll::query_scroll_devices(conn, &HashMap::new());
+                    (
+                        Some(opcode),
+                        device::query_devices(conn),
+                        tablet::query_tablets(conn),
+                        scroll_devices,
+                    )
+                }
+                None => (None, Vec::new(), HashMap::new(), HashMap::new()),
+            });
+        let (randr_event_base, monitors) =
+            conn.request(|conn| match monitor::select_notify(conn) {
+                Some(event_base) => (Some(event_base), Some(monitor::monitors(conn))),
+                None => (None, None),
+            });
+        let work_area = conn.request(|conn| {
+            monitor::select_work_area_changes(conn);
+            monitor::work_area(conn)
+        });
+        *conn.wm.lock().unwrap() = conn.request(wm::detect);
+        let hotkeys = Hotkeys::new(&conn);
+        let wake = conn.wake.clone();
+        EventLoop {
+            conn,
+            thread,
+            windows: RefCell::new(HashMap::new()),
+            helpers: RefCell::new(HashMap::new()),
+            generation: Cell::new(0),
+            created: RefCell::new(Vec::new()),
+            motion_coalescing: false,
+            modal_input_blocking: false,
+            blocked_presses: BlockedPresses::default(),
+            late_latch: false,
+            snapping: None,
+            cursors: HashMap::new(),
+            sizes: HashMap::new(),
+            resize_throttle: Some(resize::DEFAULT_THROTTLE),
+            resizes: HashMap::new(),
+            queue_limit: None,
+            events_lost: 0,
+            backlogged: false,
+            key_repeat: None,
+            repeating_keys: [0; 32],
+            repeating: None,
+            xinput2_opcode,
+            device_events: DeviceEvents::WhenFocused,
+            focused: None,
+            seats: has_seats(&devices),
+            devices,
+            hotplugged: Vec::new(),
+            tablets,
+            scroll_devices,
+            smooth_scroll_time: 0,
+            event_device: None,
+            exit_signals: None,
+            hotkeys,
+            randr_event_base,
+            monitors,
+            monitors_changed: false,
+            work_area,
+            work_area_changed: false,
+            frame_deadline: None,
+            frame_start: None,
+            translation_time: Duration::ZERO,
+            deadline_reported: false,
+            #[cfg(feature = "dbus")]
+            sleep: None,
+            #[cfg(feature = "dbus")]
+            status_icons: RefCell::new(Vec::new()),
+            #[cfg(feature = "scripting")]
+            scripts: RefCell::new(Vec::new()),
+            exit_code: Cell::new(None),
+            panic_policy: PanicPolicy::default(),
+            exit_on_last_window_closed: true,
+            watchdog: None,
+            connection_lost: false,
+            gpu_errors: Vec::new(),
+            #[cfg(feature = "scripting")]
+            script_errors: Vec::new(),
+            texts: Vec::new(),
+            input: InputHandle::new(),
+            channels: Arc::new(Channels::new(wake)),
+            metrics: Arc::new(Metrics::new()),
+            poll_end: None,
+            handler_time: Duration::ZERO,
+            metrics_on_signal: false,
+            history: History::new(),
+            event_filter: EventFilter::all(),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// The underlying Xlib display connection
+    pub fn display(&self) -> *mut xlib::Display {
+        self.conn.display
+    }
+
+    /// The monitors currently connected, cached and refreshed on
+    /// Event::MonitorConfigurationChanged and Event::WorkAreaChanged when
+    /// RandR is available
+    pub fn available_monitors(&self) -> Vec<MonitorHandle> {
+        match &self.monitors {
+            Some(monitors) => monitors.clone(),
+            None => self.conn.request(monitor::monitors),
+        }
+    }
+
+    /// The input devices of the server sorted by id, cached and refreshed
+    /// before Event::DeviceAdded and Event::DeviceRemoved. Empty without
+    /// XInput2.
+    ///
+    /// Device and tablet events carry the id of the slave device causing
+    /// them, see DeviceType, so settings can be kept per physical device.
+    /// Pointer events carry the master pointer
+    pub fn devices(&self) -> Vec<DeviceInfo> {
+        self.devices.clone()
+    }
+
+    /// The device with the id, None once it was removed
+    pub fn device(&self, id: DeviceId) -> Option<DeviceInfo> {
+        self.devices
+            .iter()
+            .find(|device| device.id() == id)
+            .cloned()
+    }
+
+    /// The master pointer core requests like X11Window::cursor_position
+    /// refer to, the virtual core pointer unless another client pointer
+    /// was set for the connection
+    pub fn core_pointer(&self) -> DeviceId {
+        self.conn.core_pointer()
+    }
+
+    /// Every master pointer, one per seat. CursorMoved and MouseInput
+    /// tell them apart while there are several, the core pointer is the
+    /// only one otherwise
+    pub fn master_pointers(&self) -> Vec<DeviceId> {
+        self.devices
+            .iter()
+            .filter(|device| device.device_type() == DeviceType::MasterPointer)
+            .map(|device| device.id())
+            .collect()
+    }
+
+    /// The server time of the newest key, button or tablet input, e.g.
+    /// to mark the input a frame reflects when measuring latency. None
+    /// before the first input
+    pub fn last_input_time(&self) -> Option<EventTime> {
+        match self.conn.last_event_time.load(Ordering::Relaxed) {
+            xlib::CurrentTime => None,
+            time => Some(EventTime::from_millis(time as u32)),
+        }
+    }
+
+    /// Relate server timestamps and Present times to Instant, timing a
+    /// few round trips to the server
+    pub fn calibrate_clock(&self) -> ServerClock {
+        self.conn.request(|conn| unsafe { clock::calibrate(conn) })
+    }
+
+    /// The connected monitor with the id returned by
+    /// MonitorHandle::into_raw, if it is still connected
+    pub fn monitor_from_raw(&self, raw: RROutput) -> Option<MonitorHandle> {
+        self.available_monitors()
+            .into_iter()
+            .find(|monitor| monitor.into_raw() == raw)
+    }
+
+    /// The monitor marked as primary, if any
+    pub fn primary_monitor(&self) -> Option<MonitorHandle> {
+        self.available_monitors()
+            .into_iter()
+            .find(MonitorHandle::is_primary)
+    }
+
+    /// The window manager running, found with the handshake of
+    /// _NET_SUPPORTING_WM_CHECK. None without one following EWMH. Kept up
+    /// to date when another one takes over
+    pub fn window_manager(&self) -> Option<WmInfo> {
+        self.conn.wm.lock().unwrap().clone()
+    }
+
+    /// The pointer position relative to the root window of the screen
+    pub fn global_cursor_position(&self) -> PhysicalPosition<i32> {
+        self.pointer_state().position
+    }
+
+    /// The pointer position on the root window together with the pressed
+    /// modifiers and buttons, queried from the server
+    pub fn pointer_state(&self) -> PointerState {
+        let (position, _, mask) = self.conn.request(|conn| conn.query_pointer(conn.root));
+        PointerState {
+            position: position.into(),
+            mask,
+        }
+    }
+
+    /// Select pen and smooth scroll events from all known devices on the
+    /// windows, as far as the event filter subscribes to them
+    pub(crate) fn select_device_events(&self, windows: Vec<xlib::Window>) {
+        let tablets: Vec<i32> = self.tablets.keys().copied().collect();
+        // Selections replace each other, tablets keep their own
+        let scroll_devices: Vec<i32> = self
+            .scroll_devices
+            .keys()
+            .filter(|device| !self.tablets.contains_key(device))
+            .copied()
+            .collect();
+        let pen = self.event_filter.contains(EventFilter::TOUCH);
+        let scroll = self.event_filter.contains(EventFilter::POINTER_BUTTON);
+        let motion = self.event_filter.contains(EventFilter::POINTER_MOTION);
+        let seats = self.seats;
+        self.conn.request(move |conn| {
+            let enter = scroll && !scroll_devices.is_empty();
+            for window in windows {
+                tablet::select_tablet_events(conn, window, &tablets, pen);
+                scroll::select_scroll_events(conn, window, &scroll_devices, scroll);
+                seat::select_seat_events(conn, window, seats, (enter, motion, scroll));
+            }
+        });
+    }
+
+    /// Only deliver events of the given categories. Input which is not
+    /// subscribed is deselected on the X server, so e.g. pointer motion
+    /// costs nothing while POINTER_MOTION is left out. Events of removed
+    /// categories which are already queued are dropped, queued events of
+    /// subscribed categories are kept.
+    ///
+    /// Without KEYBOARD the grab escape chord and IME input do not work
+    pub fn set_event_filter(&mut self, filter: EventFilter) {
+        self.event_filter = filter;
+        let mask = filter.event_mask();
+        // Dropped windows may be destroyed already, adopted ones keep the
+        // events the application selected
+        let mut selected = Vec::new();
+        let mut windows = Vec::new();
+        for (&window, (_, state)) in self.windows.borrow().iter() {
+            let adopted_mask = match state.upgrade() {
+                Some(state) => state.borrow().adopted_mask,
+                None => continue,
+            };
+            selected.push((window, mask | adopted_mask.unwrap_or(0)));
+            // XInput2 selections replace those of the whole client, the
+            // application's stay on the windows it handed in
+            if adopted_mask.is_none() {
+                windows.push(window);
+            }
+        }
+        self.conn.request(move |conn| unsafe {
+            for (window, mask) in selected {
+                xlib::XSelectInput(conn.display, window, mask);
+            }
+            xlib::XFlush(conn.display);
+        });
+        self.select_device_events(windows);
+    }
+
+    /// The categories set with set_event_filter
+    pub fn event_filter(&self) -> EventFilter {
+        self.event_filter
+    }
+
+    /// Enumerate tablets and scroll devices again after devices were
+    /// added or removed
+    fn update_devices(&mut self) {
+        let devices = self.conn.request(device::query_devices);
+        let removed = self
+            .devices
+            .iter()
+            .filter(|old| !devices.iter().any(|device| device.id() == old.id()))
+            .map(|old| Event::DeviceRemoved { device: old.id() });
+        let added = devices
+            .iter()
+            .filter(|new| !self.devices.iter().any(|device| device.id() == new.id()))
+            .map(|new| Event::DeviceAdded { device: new.id() });
+        self.hotplugged = removed.chain(added).collect();
+        self.seats = has_seats(&devices);
+        self.devices = devices;
+        self.tablets = self.conn.request(tablet::query_tablets);
+        self.update_scroll_devices();
+        let windows: Vec<xlib::Window> = self.windows.borrow().keys().copied().collect();
+        self.select_device_events(windows);
+    }
+
+    /// Read the scroll valuators again, they moved while the pointer was
+    /// over other windows
+    fn update_scroll_devices(&mut self) {
+        let previous = mem::take(&mut self.scroll_devices);
+        self.scroll_devices = self
+            .conn
+            .request(move |conn| scroll::query_scroll_devices(conn, &previous));
+    }
+
+    /// Track a new window, returning its id
+    pub(crate) fn register_window(
+        &self,
+        window: xlib::Window,
+        state: &Rc<RefCell<WindowState>>,
+    ) -> WindowId {
+        let generation = self.generation.get() + 1;
+        self.generation.set(generation);
+        let id = WindowId::new(window, generation);
+        self.windows
+            .borrow_mut()
+            .insert(window, (id, Rc::downgrade(state)));
+        self.created.borrow_mut().push(id);
+        id
+    }
+
+    pub(crate) fn register_helper(
+        &self,
+        window: xlib::Window,
+        events: &Rc<RefCell<Vec<xlib::XEvent>>>,
+    ) {
+        let mut helpers = self.helpers.borrow_mut();
+        helpers.retain(|_, events| events.strong_count() > 0);
+        helpers.insert(window, Rc::downgrade(events));
+    }
+
+    /// Keep an event of a HelperWindow for it, true when it was one
+    fn queue_helper_event(&self, xevent: &xlib::XEvent) -> bool {
+        if xevent.get_type() == xlib::GenericEvent {
+            return false;
+        }
+        let window = unsafe { xevent.any.window };
+        match self.helpers.borrow().get(&window).map(Weak::upgrade) {
+            Some(Some(queue)) => queue.borrow_mut().push(*xevent),
+            // Dropped, its DestroyNotify is not selected
+            Some(None) => {}
+            None => return false,
+        }
+        true
+    }
+
+    /// The id of a window of this loop, windows not created by it get
+    /// generation 0
+    fn window_id(&self, window: xlib::Window) -> WindowId {
+        match self.windows.borrow().get(&window) {
+            Some(&(id, _)) => id,
+            None => WindowId::new(window, 0),
+        }
+    }
+
+    pub(crate) fn window_state(&self, window: xlib::Window) -> Option<Rc<RefCell<WindowState>>> {
+        self.windows
+            .borrow()
+            .get(&window)
+            .and_then(|(_, state)| state.upgrade())
+    }
+
+    /// Collapse consecutive pointer motion for the same window into a
+    /// single CursorMoved carrying the latest position and summed delta.
+    ///
+    /// Motion is never merged across any other event, so buttons and keys
+    /// keep their position in the stream
+    pub fn set_motion_coalescing(&mut self, enabled: bool) {
+        self.motion_coalescing = enabled;
+    }
+
+    /// Drop the input of windows while a modal window they own is shown,
+    /// see X11Window::set_modal, for window managers which leave it to
+    /// the application. Off by default.
+    ///
+    /// Presses and releases are dropped in pairs: the releases of keys and
+    /// buttons held when the modal window opened still arrive, those of
+    /// presses dropped are dropped too, even after it closed. A click on the owner delivers
+    /// Event::ModalBlocked, and focusing the owner passes focus on to the
+    /// modal window
+    pub fn set_modal_input_blocking(&mut self, block: bool) {
+        self.modal_input_blocking = block;
+    }
+
+    /// Make run read input once more after the handler returned from
+    /// AboutToWait, when it requested a redraw, so frames include input
+    /// which arrived while the batch was handled. Off by default, loops
+    /// driven with poll_events call poll_late_input instead.
+    ///
+    /// The handler then sees AboutToWait followed by a second batch of
+    /// Event::LateInput, the late keys, buttons, motion and scrolling,
+    /// and the redraws. Late input is always newer than the input before
+    /// it. Other events which arrived meanwhile wait for the next batch,
+    /// so late input may come before e.g. a FocusOut the server sent
+    /// first. Loops with a connection thread do not latch, the thread
+    /// reads ahead on its own
+    pub fn set_late_latch(&mut self, enabled: bool) {
+        self.late_latch = enabled;
+    }
+
+    pub fn late_latch(&self) -> bool {
+        self.late_latch
+    }
+
+    /// Snap windows moved with X11Window::begin_manual_drag to the work
+    /// area of each monitor and to the edges of the loop's other windows,
+    /// reporting Event::Snapped. Off by default
+    pub fn set_snapping(&mut self, snapping: Option<Snapping>) {
+        self.snapping = snapping;
+    }
+
+    pub fn snapping(&self) -> Option<Snapping> {
+        self.snapping
+    }
+
+    /// Limit how many events a single poll delivers, so a consumer that
+    /// stalled does not replay a long burst of stale input afterwards.
+    ///
+    /// Close and exit requests, focus changes, resizes and releases of
+    /// keys and buttons are never dropped, the number of dropped events is reported with
+    /// Event::EventsLost
+    pub fn set_queue_policy(&mut self, policy: QueuePolicy, capacity: usize) {
+        self.queue_limit = Some((policy, capacity.max(1)));
+    }
+
+    /// Repeat held keys with the given timing instead of the server's,
+    /// None goes back to the server's repeats, the default.
+    ///
+    /// The most recently pressed key repeats until it is released, focus
+    /// moves elsewhere or another key is pressed. Keys the server does not
+    /// repeat, such as modifiers, never do. Repeats are delivered by the
+    /// first poll after they are due, along with Text for keys which
+    /// typed any
+    pub fn set_key_repeat(&mut self, repeat: Option<KeyRepeat>) {
+        self.key_repeat = repeat.map(|repeat| KeyRepeat {
+            interval: repeat.interval.max(Duration::from_millis(1)),
+            ..repeat
+        });
+        self.repeating = None;
+        if repeat.is_some() {
+            self.repeating_keys = self.conn.request(XConnection::auto_repeat_keys);
+        }
+    }
+
+    pub fn key_repeat(&self) -> Option<KeyRepeat> {
+        self.key_repeat
+    }
+
+    /// Choose when raw keyboard and mouse input is delivered as
+    /// Event::DeviceEvent, the default is DeviceEvents::WhenFocused.
+    ///
+    /// Device events come from XInput2 raw events on the root window and
+    /// only carry physical keycodes, never text. Fails with
+    /// Error::Unsupported when the server or libXi lacks XInput 2.0
+    pub fn listen_device_events(&mut self, device_events: DeviceEvents) -> Result<(), Error> {
+        if self.xinput2_opcode.is_none() {
+            return Err(Error::Unsupported("XInput2 raw events"));
+        }
+        let enabled = device_events != DeviceEvents::Never;
+        self.conn
+            .request(move |conn| select_raw_events(conn, enabled));
+        self.device_events = device_events;
+        Ok(())
+    }
+
+    /// Deliver SIGTERM and SIGINT as Event::ExitRequested instead of
+    /// terminating the process, so the application can save its state
+    /// and exit at the end of the frame.
+    ///
+    /// The handlers are installed for the whole process and stay in place
+    pub fn catch_exit_signals(&mut self) {
+        self.exit_signals = Some(signal::install());
+    }
+
+    /// Report frames taking longer than `budget` from one poll_events
+    /// call to the next with Event::FrameDeadlineMissed, None disables
+    /// the check.
+    ///
+    /// The measurement costs two clock reads per poll. A miss is never
+    /// reported for the frame handling the previous miss
+    pub fn set_frame_deadline(&mut self, budget: Option<Duration>) {
+        self.frame_deadline = budget;
+        self.frame_start = None;
+        self.deadline_reported = false;
+    }
+
+    /// Deliver Event::Suspending and Event::Resumed around system sleep,
+    /// as announced by systemd-logind on the system bus. Without logind
+    /// the events are simply never delivered
+    #[cfg(feature = "dbus")]
+    pub fn listen_sleep_events(&mut self) {
+        match SleepMonitor::connect() {
+            Ok(monitor) => self.sleep = Some(monitor),
+            Err(err) => log::info!("no sleep notifications from logind: {}", err),
+        }
+    }
+
+    #[cfg(feature = "dbus")]
+    pub(crate) fn register_status_icon(&self, tray: &Rc<RefCell<Tray>>) {
+        self.status_icons.borrow_mut().push(Rc::downgrade(tray));
+    }
+
+    #[cfg(feature = "scripting")]
+    pub(crate) fn register_script(&self, script: &Rc<RefCell<Script>>) {
+        self.scripts.borrow_mut().push(Rc::downgrade(script));
+    }
+
+    /// Deliver at most one Event::Resized per `interval` for each window,
+    /// 50 ms by default, None delivers one for every size.
+    ///
+    /// Dragging a frame edge configures the window for every pointer
+    /// motion, and reconfiguring the surface and drawing at each size
+    /// makes the resize lag behind the pointer. The sizes in between
+    /// are dropped, the last one is always delivered, at the latest
+    /// 100 ms after the window manager stopped resizing. Its sync
+    /// requests are acknowledged for every size, so it never waits on
+    /// frames which are not drawn
+    pub fn set_resize_throttle(&mut self, interval: Option<Duration>) {
+        self.resize_throttle = interval;
+        if interval.is_none() {
+            // The held sizes are dropped, surfaces catch up on acquire
+            for (window, _) in mem::take(&mut self.resizes) {
+                if let Some(state) = self.window_state(window) {
+                    state.borrow_mut().resize_held = false;
+                }
+            }
+        }
+    }
+
+    /// Treat the X server as gone once a request waited longer than
+    /// `timeout` for it, None disables the check, the default. An idle
+    /// connection is checked with a round trip every `timeout`, so a
+    /// server which stopped answering while nothing was asked of it is
+    /// noticed as well.
+    ///
+    /// Either way the loop delivers Event::ConnectionLost, as it does
+    /// when the server closes the connection
+    pub fn set_connection_timeout(&mut self, timeout: Option<Duration>) {
+        self.watchdog = None;
+        self.watchdog = timeout.map(|timeout| Watchdog::spawn(&self.conn, timeout));
+    }
+
+    /// Open the display again after Event::ConnectionLost and create the
+    /// windows still alive anew with their last known title, size,
+    /// position, size limits, cursor and visibility.
+    ///
+    /// The new windows are returned along with the ids of the windows
+    /// they replace, and are reported with Event::WindowCreated like any
+    /// other. The old windows should be dropped: they, their proxies and
+    /// surfaces belong to the lost connection, see
+    /// render::WindowSurface::recreate. The settings of the loop carry
+    /// over, fullscreen, grabs, input regions and embedding do not.
+    ///
+    /// Fails with Error::ConnectionFailed while the server is still
+    /// unreachable, the call can be repeated. Displays handed in with
+    /// from_raw_display are the application's to open again, and windows
+    /// adopted with X11Window::from_raw are not recreated
+    pub fn reconnect(&mut self) -> Result<Vec<(WindowId, X11Window)>, Error> {
+        if self.conn.shared {
+            return Err(Error::Unsupported(
+                "reconnecting a display opened by the application",
+            ));
+        }
+        let builder = EventLoopBuilder {
+            backend: Backend::X11,
+            connection_thread: self.thread.as_ref().map(|thread| thread.capacity),
+        };
+        let fresh = builder.connect_x11().ok_or(Error::ConnectionFailed)?;
+
+        // Parents before the dialogs transient for them, the generation
+        // is in the high bits
+        let mut windows: Vec<(WindowId, LastKnown)> = self
+            .windows
+            .borrow()
+            .values()
+            .filter_map(|(id, state)| {
+                let state = state.upgrade()?;
+                let state = state.borrow();
+                state
+                    .adopted_mask
+                    .is_none()
+                    .then(|| (*id, state.last_known.clone()))
+            })
+            .collect();
+        windows.sort_by_key(|(id, _)| id.into_raw());
+
+        let mut old = mem::replace(self, fresh);
+        log::info!(
+            "reconnected to the X server, recreating {} windows",
+            windows.len()
+        );
+        self.generation.set(old.generation.get());
+        self.motion_coalescing = old.motion_coalescing;
+        self.modal_input_blocking = old.modal_input_blocking;
+        self.late_latch = old.late_latch;
+        self.snapping = old.snapping;
+        self.resize_throttle = old.resize_throttle;
+        self.queue_limit = old.queue_limit;
+        self.set_key_repeat(old.key_repeat);
+        self.exit_signals = old.exit_signals.take();
+        self.hotkeys.carry_over(&mut old.hotkeys);
+        self.set_frame_deadline(old.frame_deadline);
+        #[cfg(feature = "dbus")]
+        {
+            self.sleep = old.sleep.take();
+            self.status_icons = RefCell::new(old.status_icons.take());
+        }
+        #[cfg(feature = "scripting")]
+        {
+            self.scripts = RefCell::new(old.scripts.take());
+        }
+        self.exit_code.set(old.exit_code.get());
+        self.panic_policy = old.panic_policy;
+        self.exit_on_last_window_closed = old.exit_on_last_window_closed;
+        self.input = old.input.clone();
+        self.channels = old.channels.clone();
+        self.channels.set_wake(self.conn.wake.clone());
+        self.metrics = old.metrics.clone();
+        self.metrics_on_signal = old.metrics_on_signal;
+        self.history = mem::replace(&mut old.history, History::new());
+        self.event_filter = old.event_filter;
+        if old.device_events != self.device_events {
+            // Without XInput2 on the new server there are none to select
+            let _ = self.listen_device_events(old.device_events);
+        }
+        self.set_connection_timeout(old.watchdog.take().map(|watchdog| watchdog.timeout()));
+        drop(old);
+
+        let mut recreated: Vec<(WindowId, X11Window)> = Vec::with_capacity(windows.len());
+        for (id, last) in windows {
+            let owner = last.transient_for.and_then(|parent| {
+                recreated
+                    .iter()
+                    .find(|(old, _)| old.xid() == parent)
+                    .map(|(_, window)| window)
+            });
+            let window = X11Window::recreate(self, last, owner);
+            recreated.push((id, window));
+        }
+        Ok(recreated)
+    }
+
+    /// Deliver Event::GlobalHotkey whenever the key chord is pressed,
+    /// whichever application has focus, e.g. "Ctrl+Print" or
+    /// "Super+Shift+S". Modifiers are Ctrl, Shift, Alt, Super and AltGr,
+    /// the key is named by its keysym, matching case aside.
+    ///
+    /// The chord works with Caps Lock and Num Lock on, and moves along
+    /// when the keyboard layout changes. Fails with InvalidHotkey when the
+    /// layout has no such key and AlreadyGrabbed when the window manager
+    /// or another client took the chord, or it is registered already.
+    /// A hotkey whose key a later layout lacks stays registered, and is
+    /// delivered again once a layout has the key
+    pub fn register_global_hotkey(&mut self, keys: &str) -> Result<HotkeyId, Error> {
+        self.hotkeys.register(keys)
+    }
+
+    /// Release a hotkey, false when it was not registered
+    pub fn unregister_global_hotkey(&mut self, id: HotkeyId) -> bool {
+        self.hotkeys.unregister(id)
+    }
+
+    /// A channel for messages of type M, e.g. one per subsystem of the
+    /// application. Messages sent through the proxy from any thread are
+    /// announced by Event::Channel with the next poll and taken with
+    /// drain_channel. Proxies fail with EventLoopClosed once the loop is
+    /// dropped, they survive reconnect
+    pub fn create_channel<M: 'static + Send>(&self) -> (ChannelProxy<M>, ChannelId) {
+        self.channels.create()
+    }
+
+    /// A channel holding at most `capacity` messages not drained yet, for
+    /// async senders. AsyncChannelProxy::send waits while the channel is
+    /// full and resumes once the loop drained it, so a producer outpacing
+    /// the loop slows down instead of queueing without bound
+    #[cfg(feature = "async")]
+    pub fn create_async_channel<M: 'static + Send>(
+        &self,
+        capacity: usize,
+    ) -> (AsyncChannelProxy<M>, ChannelId) {
+        self.channels.create_bounded(capacity)
+    }
+
+    /// The events of the loop as an async stream, in place of run or a
+    /// loop of its own calling poll_events, see EventStream.
+    ///
+    /// The task sleeps while nothing happens and is woken by the server,
+    /// channel sends, exit signals and synthesized key repeats and held
+    /// resizes coming due. Fails for loops with a connection thread
+    #[cfg(feature = "async")]
+    pub fn event_stream(&mut self) -> Result<EventStream<'_>, Error> {
+        if self.thread.is_some() {
+            return Err(Error::Unsupported("event streams with a connection thread"));
+        }
+        let fds = self.wait_fds();
+        Ok(EventStream::new(self, fds))
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn channels(&self) -> &Channels {
+        &self.channels
+    }
+
+    /// Send the requests buffered by Xlib, before waiting on the server
+    #[cfg(feature = "async")]
+    pub(crate) fn flush(&self) {
+        if !self.conn.is_lost() {
+            self.conn.request(|conn| unsafe {
+                xlib::XFlush(conn.display);
+            });
+        }
+    }
+
+    /// How long until a synthesized key repeat, held resize or one of
+    /// the checks made on every poll is due
+    pub(crate) fn next_timer(&self) -> Option<Duration> {
+        let now = Instant::now();
+        let repeat = self
+            .repeating
+            .as_ref()
+            .map(|repeating| repeating.next.saturating_duration_since(now));
+        let resize = match self.resize_throttle {
+            Some(interval) if !self.resizes.is_empty() => Some(interval),
+            _ => None,
+        };
+        #[cfg(feature = "dbus")]
+        let dialogs = dialogs::pending(&self.conn).then_some(dialogs::POLL_INTERVAL);
+        #[cfg(not(feature = "dbus"))]
+        let dialogs = None;
+        #[cfg(feature = "wgpu")]
+        let readbacks =
+            (!self.conn.readbacks.lock().unwrap().is_empty()).then_some(readback::POLL_INTERVAL);
+        #[cfg(not(feature = "wgpu"))]
+        let readbacks = None;
+        #[cfg(feature = "scripting")]
+        let scripts = (!self.scripts.borrow().is_empty()).then_some(script::RELOAD_INTERVAL);
+        #[cfg(not(feature = "scripting"))]
+        let scripts = None;
+        let heartbeat = self.watchdog.as_ref().map(Watchdog::until_heartbeat);
+        repeat
+            .into_iter()
+            .chain(resize)
+            .chain(dialogs)
+            .chain(readbacks)
+            .chain(scripts)
+            .chain(heartbeat)
+            .min()
+    }
+
+    /// What wait_events blocks on besides timers: the server unless the
+    /// connection thread reads it, the wake pipe, exit signals and the
+    /// buses
+    pub(crate) fn wait_fds(&self) -> Vec<RawFd> {
+        let mut fds = vec![self.conn.wake.fd()];
+        if self.thread.is_none() {
+            fds.push(self.conn.fd());
+        }
+        fds.extend(self.exit_signals);
+        #[cfg(feature = "dbus")]
+        {
+            fds.extend(self.sleep.as_ref().map(SleepMonitor::fd));
+            let icons = self.status_icons.borrow();
+            let icons = icons.iter().filter_map(Weak::upgrade);
+            fds.extend(icons.map(|tray| tray.borrow().fd()));
+        }
+        fds
+    }
+
+    /// Block until the next poll has something to deliver: an event from
+    /// the server, a channel send, a window proxy command, a GPU error,
+    /// an exit signal or a timer of the loop coming due, or until
+    /// `timeout` passed. Returns right away when a redraw was requested,
+    /// a window was created, exit was called or the last poll left
+    /// events behind.
+    ///
+    /// The blocking point of run, for loops of their own to call after
+    /// handling AboutToWait, e.g. with the time until their next frame
+    pub fn wait_events(&self, timeout: Option<Duration>) {
+        let redraw = self
+            .windows
+            .borrow()
+            .values()
+            .filter_map(|(_, state)| state.upgrade())
+            .any(|state| state.borrow().redraw_requested);
+        let queued = self.thread.is_none()
+            && !self.conn.is_lost()
+            && self.conn.request(|conn| unsafe {
+                xlib::XEventsQueued(conn.display, QUEUED_AFTER_FLUSH) != 0
+            });
+        if redraw
+            || queued
+            || self.backlogged
+            || self.exit_code.get().is_some()
+            || !self.created.borrow().is_empty()
+        {
+            return;
+        }
+        let mut fds = self.wait_fds();
+        // The socket stays readable once the server is gone
+        if self.conn.is_lost() {
+            fds.retain(|&fd| fd != self.conn.fd());
+        }
+        let timeout = self.next_timer().into_iter().chain(timeout).min();
+        wake::wait(&fds, timeout);
+    }
+
+    /// Take the messages sent over the channel since the last call,
+    /// oldest first. Fails when the loop has no such channel or M is not
+    /// the type it was created with
+    pub fn drain_channel<M: 'static + Send>(&self, id: ChannelId) -> Result<Vec<M>, ChannelError> {
+        self.channels.drain(id)
+    }
+
+    /// Undo the video modes of exclusive fullscreen and the keyboard and
+    /// pointer grabs of the loop's windows once the guard is dropped, or
+    /// the process dies of SIGSEGV, SIGBUS or SIGABRT while it is alive.
+    /// Keep it next to the loop in main, e.g. `let _guard =
+    /// event_loop.session_guard();`.
+    ///
+    /// Cursors, barriers and grabs also go away with the connection, but
+    /// video modes stay and a process hanging in its crash keeps them all.
+    /// The signal handlers are installed with the first guard and stay,
+    /// passing each signal on to the action set before them
+    pub fn session_guard(&self) -> SessionGuard {
+        SessionGuard::new(&self.conn)
+    }
+
+    /// The X errors recorded since the last call, oldest first, after a
+    /// round trip so the errors of every request made so far arrived.
+    ///
+    /// Errors are logged and recorded instead of exiting the process.
+    /// The record is shared by every connection of the process and
+    /// includes those closed since, up to the last 256 errors
+    pub fn take_x_errors(&self) -> Vec<XError> {
+        self.conn.request(|conn| unsafe {
+            xlib::XSync(conn.display, xlib::False);
+        });
+        error::take_x_errors()
+    }
+
+    /// The message of an Event::GpuError delivered by the last poll
+    pub fn gpu_error(&self, index: u32) -> Option<&str> {
+        self.gpu_errors.get(index as usize).map(String::as_str)
+    }
+
+    /// The message of an Event::ScriptError delivered by the last poll
+    #[cfg(feature = "scripting")]
+    pub fn script_error(&self, index: u32) -> Option<&str> {
+        self.script_errors.get(index as usize).map(String::as_str)
+    }
+
+    /// The string of an Event::Text delivered by the last poll
+    pub fn text(&self, index: u32) -> Option<&str> {
+        self.texts.get(index as usize).map(String::as_str)
+    }
+
+    /// The label of the key in the active layout, e.g. "Ö" on German
+    /// layouts or "Num 7", for showing key bindings. Bindings should store
+    /// the keycode, the label changes with the layout
+    pub fn key_label(&self, keycode: u32) -> String {
+        self.conn
+            .request(move |conn| keyboard::key_label(conn, keycode))
+    }
+
+    /// A keycode producing the keysym in the current layout, e.g. to
+    /// synthesize input or to label a binding stored as keysym with
+    /// key_label. None when the layout has no key for it. Follows layout
+    /// changes once their MappingNotify was polled
+    pub fn keysym_to_keycode(&self, keysym: xlib::KeySym) -> Option<u32> {
+        self.conn
+            .request(move |conn| keyboard::keysym_to_keycode(conn, keysym))
+    }
+
+    /// The keysym of the key without modifiers in the active layout
+    /// group, the inverse of keysym_to_keycode. None for unmapped keys
+    pub fn keycode_to_keysym(&self, keycode: u32) -> Option<xlib::KeySym> {
+        self.conn
+            .request(move |conn| keyboard::keycode_to_keysym(conn, keycode))
+    }
+
+    /// The topmost window of this loop at a position relative to the root
+    /// window, e.g. the pointer position during a drag. None when no
+    /// window of ours is there or another client's window covers it
+    pub fn window_at(&self, position: PhysicalPosition<i32>) -> Option<WindowId> {
+        let windows: Vec<_> = self
+            .windows
+            .borrow()
+            .iter()
+            .filter(|(_, (_, state))| state.strong_count() > 0)
+            .map(|(&window, &(id, _))| (window, id))
+            .collect();
+        self.conn
+            .request(move |conn| unsafe { window::window_at(conn, &windows, position.into()) })
+    }
+
+    /// Held keys and buttons, the cursor and the motion and scrolling
+    /// since the last snapshot as of the last poll
+    pub fn input_snapshot(&self) -> InputSnapshot {
+        self.input.snapshot()
+    }
+
+    /// A handle taking input snapshots from other threads. Snapshots of
+    /// all handles share the deltas, each motion is returned once
+    pub fn input_handle(&self) -> InputHandle {
+        self.input.clone()
+    }
+
+    /// What the loop delivered and where its time went since it was
+    /// created, e.g. to attach to bug reports of lagging input
+    pub fn metrics(&self) -> EventLoopMetrics {
+        self.metrics.snapshot()
+    }
+
+    /// A handle reading the metrics from other threads
+    pub fn metrics_handle(&self) -> MetricsHandle {
+        MetricsHandle::new(self.metrics.clone())
+    }
+
+    /// Print the metrics as JSON on stderr whenever the process receives
+    /// SIGUSR1, from the next poll on, e.g. `kill -USR1 <pid>` on a
+    /// deployment feeling slow. Replaces any SIGUSR1 handler installed
+    /// before
+    pub fn print_metrics_on_signal(&mut self) {
+        signal::install_metrics();
+        self.metrics_on_signal = true;
+    }
+
+    /// The last 512 events delivered by the polls, oldest first, e.g. to
+    /// attach to a bug report. AboutToWait is left out. run logs them
+    /// along with a panic of its handler
+    pub fn recent_events(&self) -> Vec<TimestampedEvent> {
+        self.history.recent()
+    }
+
+    /// Keep the text of Text events in recent_events, which is redacted
+    /// by default as it may be a password typed by the user. The keycode
+    /// and keysym of KeyboardInput are redacted with it for keys typing
+    /// text, those of function, cursor and modifier keys are kept
+    pub fn set_recent_events_include_text(&mut self, include: bool) {
+        self.history.set_include_text(include);
+    }
+
+    /// Set the SM_CLIENT_ID the session manager assigned when the
+    /// application registered with it, e.g. through libSM
+    pub fn set_session_client_id(&self, client_id: &str) {
+        let client_id = client_id.to_owned();
+        self.conn.request(move |conn| unsafe {
+            conn.set_text_property(
+                conn.client_leader,
+                conn.atoms.sm_client_id,
+                None,
+                &client_id,
+            );
+            xlib::XFlush(conn.display);
+        });
+    }
+
+    /// Translate all pending events without blocking.
+    ///
+    /// `events` is cleared first, reusing its allocation across calls keeps
+    /// the translation path free of per-event heap allocation. Changes
+    /// queued by window proxies are made first. Redraws requested since
+    /// the last poll follow the translated events, and AboutToWait always
+    /// ends the batch, even when it is otherwise empty
+    pub fn poll_events(&mut self, events: &mut Vec<Event>) {
+        events.clear();
+        // Before looking, so whatever arrives from here on wakes the
+        // next wait
+        self.conn.wake.drain();
+        let started = Instant::now();
+        let mut wakeup = Wakeup::default();
+        self.forget_released();
+        self.run_window_commands();
+        let poll_start = self.frame_deadline.map(|budget| {
+            let now = Instant::now();
+            if let Some(frame_start) = self.frame_start {
+                let elapsed = now - frame_start;
+                let missed = elapsed > budget && !self.deadline_reported;
+                if missed {
+                    events.push(Event::FrameDeadlineMissed {
+                        elapsed,
+                        budget,
+                        translation: self.translation_time,
+                    });
+                }
+                self.deadline_reported = missed;
+            }
+            now
+        });
+        if matches!(self.exit_signals, Some(fd) if signal::take(fd)) {
+            events.push(Event::ExitRequested);
+            wakeup.signal = true;
+        }
+        if self.metrics_on_signal && signal::take_metrics_request() {
+            eprintln!("daswin metrics: {}", self.metrics().to_json());
+        }
+        events.extend(
+            self.created
+                .borrow_mut()
+                .drain(..)
+                .map(|window| Event::WindowCreated { window }),
+        );
+        #[cfg(feature = "dbus")]
+        if let Some(monitor) = &mut self.sleep {
+            if let Err(err) = monitor.poll(events) {
+                log::warn!("lost the connection to logind: {}", err);
+                self.sleep = None;
+            }
+        }
+        #[cfg(feature = "dbus")]
+        self.status_icons
+            .borrow_mut()
+            .retain(|tray| match tray.upgrade() {
+                Some(tray) => match tray.borrow_mut().poll(events) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        log::warn!("lost the session bus, the tray icon is gone: {}", err);
+                        false
+                    }
+                },
+                None => false,
+            });
+        #[cfg(feature = "dbus")]
+        dialogs::poll(&self.conn);
+        self.texts.clear();
+        self.gpu_errors.clear();
+        self.gpu_errors
+            .append(&mut self.conn.gpu_errors.lock().unwrap());
+        events.extend((0..self.gpu_errors.len() as u32).map(|index| Event::GpuError { index }));
+        let before = events.len();
+        self.channels.poll(events);
+        wakeup.channel = events.len() > before;
+        if let Some(watchdog) = &mut self.watchdog {
+            if !self.conn.is_lost() && watchdog.heartbeat_due() {
+                self.conn.request(|conn| unsafe {
+                    xlib::XSync(conn.display, xlib::False);
+                });
+            }
+        }
+        if let Some(thread) = self.thread.take() {
+            while !self.is_blocked(events) {
+                match thread.events.try_recv() {
+                    Ok(mut xevent) => {
+                        self.process(&mut xevent.0, events);
+                        wakeup.x_events += 1;
+                    }
+                    Err(_) => break,
+                }
+            }
+            self.thread = Some(thread);
+        } else if self.conn.shared {
+            unsafe {
+                let mut xevent: xlib::XEvent = mem::zeroed();
+                while !self.is_blocked(events)
+                    && !self.conn.is_lost()
+                    && xlib::XCheckIfEvent(
+                        self.conn.display,
+                        &mut xevent,
+                        Some(claimed),
+                        &*self as *const EventLoop as xlib::XPointer,
+                    ) != 0
+                {
+                    self.process(&mut xevent, events);
+                    wakeup.x_events += 1;
+                }
+            }
+        } else {
+            unsafe {
+                let mut xevent: xlib::XEvent = mem::zeroed();
+                while !self.is_blocked(events)
+                    && !self.conn.is_lost()
+                    && xlib::XPending(self.conn.display) != 0
+                {
+                    xlib::XNextEvent(self.conn.display, &mut xevent);
+                    self.process(&mut xevent, events);
+                    wakeup.x_events += 1;
+                }
+            }
+        }
+
+        self.backlogged = self.is_blocked(events);
+
+        let before = events.len();
+        self.deliver_key_repeats(events);
+        wakeup.timer = events.len() > before;
+
+        if let Some((QueuePolicy::DropOldest, capacity)) = self.queue_limit {
+            self.events_lost += trim_oldest(events, capacity);
+        }
+        if self.events_lost > 0 {
+            events.push(Event::EventsLost(self.events_lost));
+            self.events_lost = 0;
+        }
+
+        let before = events.len();
+        self.deliver_held_resizes(events);
+        wakeup.timer |= events.len() > before;
+
+        if self.monitors_changed {
+            events.push(Event::MonitorConfigurationChanged);
+            self.monitors_changed = false;
+        }
+
+        if self.work_area_changed {
+            events.push(Event::WorkAreaChanged);
+            self.work_area_changed = false;
+        }
+
+        if self.conn.is_lost() && !self.connection_lost {
+            events.push(Event::ConnectionLost);
+            self.connection_lost = true;
+        }
+
+        #[cfg(feature = "wgpu")]
+        self.poll_readbacks(events);
+        #[cfg(feature = "scripting")]
+        self.run_scripts(events);
+        self.deliver_redraws(events);
+        events.push(Event::AboutToWait);
+        self.input.update(events, self.conn.core_pointer());
+
+        if let Some(poll_start) = poll_start {
+            self.translation_time = poll_start.elapsed();
+            self.frame_start = Some(poll_start);
+        }
+
+        let now = Instant::now();
+        let idle = self.poll_end.map_or(Duration::ZERO, |end| {
+            (started - end).saturating_sub(self.handler_time)
+        });
+        self.metrics
+            .record_poll(events, wakeup, now - started, idle);
+        self.history.record(now, events, &self.texts);
+        self.poll_end = Some(now);
+        self.handler_time = Duration::ZERO;
+    }
+
+    /// Deliver the redraws requested while handling the last batch right
+    /// away, preceded by the input which arrived meanwhile, see
+    /// set_late_latch. Call it after handling AboutToWait and before
+    /// drawing.
+    ///
+    /// `events` is cleared first. It stays empty when no redraw was
+    /// requested, the next poll then translates the input as usual.
+    /// Otherwise Event::LateInput is followed by the keys, buttons,
+    /// motion and scrolling queued since the last poll, then by
+    /// CanvasViewportChanged and RedrawRequested, without AboutToWait.
+    /// LateInput is left out when no input arrived
+    pub fn poll_late_input(&mut self, events: &mut Vec<Event>) {
+        events.clear();
+        let redraw = self
+            .windows
+            .borrow()
+            .values()
+            .filter_map(|(_, state)| state.upgrade())
+            .any(|state| state.borrow().redraw_requested);
+        if !redraw {
+            return;
+        }
+        self.latch_input(events);
+        self.deliver_redraws(events);
+        self.input.update(events, self.conn.core_pointer());
+        self.metrics.count(events);
+        self.history.record(Instant::now(), events, &self.texts);
+    }
+
+    /// Like poll_events, also telling whether the application should
+    /// exit, for engines driving their own main loop.
+    ///
+    /// Returns Exit after delivering ExitRequested, once exit was
+    /// called, or after delivering CloseRequested for every window still
+    /// open, see set_exit_on_last_window_closed. A loop which never had
+    /// a window keeps running. Protocol messages from the window manager
+    /// are answered here as in poll_events
+    pub fn pump_events(&mut self, sink: &mut Vec<Event>) -> PumpStatus {
+        self.poll_events(sink);
+        let exit_requested = sink
+            .iter()
+            .any(|event| matches!(event, Event::ExitRequested));
+        let closing: Vec<WindowId> = sink
+            .iter()
+            .filter_map(|event| match *event {
+                Event::CloseRequested { window } => Some(window),
+                _ => None,
+            })
+            .collect();
+        let last_closed = self.exit_on_last_window_closed
+            && !closing.is_empty()
+            && self
+                .windows
+                .borrow()
+                .values()
+                .filter(|(_, state)| state.strong_count() > 0)
+                .all(|(id, _)| closing.contains(id));
+        match self.exit_code.get() {
+            Some(code) => PumpStatus::Exit { code },
+            None if exit_requested || last_closed => PumpStatus::Exit { code: 0 },
+            None => PumpStatus::Continue,
+        }
+    }
+
+    /// Whether pump_events returns Exit once CloseRequested was
+    /// delivered for the last open window, true by default. Applications
+    /// living on without windows, e.g. in the tray, turn it off, drop
+    /// closed windows and create new ones at any later point
+    pub fn set_exit_on_last_window_closed(&mut self, exit: bool) {
+        self.exit_on_last_window_closed = exit;
+    }
+
+    /// Whether an event on a shared display is for the loop rather than
+    /// the application: one of the windows of the loop, including dropped
+    /// ones whose DestroyNotify is pending, or of an extension it selected
+    fn claims(&self, xevent: &xlib::XEvent) -> bool {
+        let kind = xevent.get_type();
+        if kind == xlib::GenericEvent {
+            let extension = unsafe { xevent.generic_event_cookie.extension };
+            return Some(extension) == self.xinput2_opcode
+                || Some(extension) == self.conn.present_opcode();
+        }
+        if let Some(event_base) = self.randr_event_base {
+            if kind - event_base == RRScreenChangeNotify || kind - event_base == RRNotify {
+                return true;
+            }
+        }
+        let window = unsafe { xevent.any.window };
+        window == self.conn.client_leader
+            || self.windows.borrow().contains_key(&window)
+            || self.helpers.borrow().contains_key(&window)
+            || self.hotkeys.grabbed(xevent)
+            || self.is_work_area_change(xevent)
+            || self.is_wm_change(xevent)
+    }
+
+    /// Whether the event is a change of the work area or of the desktop
+    /// it is read for
+    fn is_work_area_change(&self, xevent: &xlib::XEvent) -> bool {
+        if xevent.get_type() != xlib::PropertyNotify {
+            return false;
+        }
+        let xproperty = unsafe { xevent.property };
+        let atoms = &self.conn.atoms;
+        xproperty.window == self.conn.root
+            && (xproperty.atom == atoms.net_workarea || xproperty.atom == atoms.net_current_desktop)
+    }
+
+    /// Whether the event is a change of the window manager or of what it
+    /// supports
+    fn is_wm_change(&self, xevent: &xlib::XEvent) -> bool {
+        if xevent.get_type() != xlib::PropertyNotify {
+            return false;
+        }
+        let xproperty = unsafe { xevent.property };
+        let atoms = &self.conn.atoms;
+        xproperty.window == self.conn.root
+            && (xproperty.atom == atoms.net_supporting_wm_check
+                || xproperty.atom == atoms.net_supported)
+    }
+
+    /// Find the window manager again, e.g. after `i3 restart` or another
+    /// one replacing it
+    fn update_window_manager(&self) {
+        let wm = self.conn.request(wm::detect);
+        let mut current = self.conn.wm.lock().unwrap();
+        if wm.as_ref().map(|wm| &wm.name) != current.as_ref().map(|wm| &wm.name) {
+            log::info!(
+                "window manager is now {}",
+                wm.as_ref().map_or("gone", |wm| wm.name.as_str())
+            );
+        }
+        *current = wm;
+    }
+
+    /// Read the work area again, reported once per poll when it changed.
+    /// Switching desktops changes the property read, often not the area
+    fn update_work_area(&mut self) {
+        let work_area = self.conn.request(monitor::work_area);
+        if work_area == self.work_area {
+            return;
+        }
+        self.work_area = work_area;
+        if self.monitors.is_some() {
+            self.monitors = Some(self.conn.request(monitor::monitors));
+        }
+        self.work_area_changed = true;
+    }
+
+    /// Forget adopted windows dropped since the last poll, their events
+    /// are the application's again
+    fn forget_released(&self) {
+        let released = mem::take(&mut *self.conn.released.lock().unwrap());
+        let mut windows = self.windows.borrow_mut();
+        for window in released {
+            if matches!(windows.get(&window), Some((_, state)) if state.strong_count() == 0) {
+                windows.remove(&window);
+            }
+        }
+    }
+
+    /// Make the changes queued by window proxies in the order they were
+    /// requested, skipping windows dropped since
+    fn run_window_commands(&self) {
+        let commands = mem::take(&mut *self.conn.window_commands.lock().unwrap());
+        for (id, command) in commands {
+            let state = match self.windows.borrow().get(&id.xid()) {
+                Some((entry, state)) if *entry == id => state.upgrade(),
+                _ => None,
+            };
+            let state = match state {
+                Some(state) => state,
+                None => continue,
+            };
+            match command {
+                WindowCommand::SetTitle(title) => {
+                    window::set_title(&self.conn, id.xid(), &title);
+                    state.borrow_mut().last_known.title = title;
+                }
+                WindowCommand::SetInnerSize(size) => {
+                    window::set_inner_size(&self.conn, id.xid(), size)
+                }
+                WindowCommand::SetCursorIcon(icon) => {
+                    window::set_cursor_icon(&self.conn, id.xid(), &mut state.borrow_mut(), icon)
+                }
+                WindowCommand::RequestRedraw => state.borrow_mut().redraw_requested = true,
+                WindowCommand::ToggleFullscreen => {
+                    window::toggle_fullscreen(&self.conn, id.xid(), &mut state.borrow_mut())
+                }
+            }
+        }
+    }
+
+    /// Run the scripts of ScriptHosts, then make the window changes they
+    /// asked for, so the redraws delivered next see them
+    #[cfg(feature = "scripting")]
+    fn run_scripts(&mut self, events: &mut Vec<Event>) {
+        let now = Instant::now();
+        let mut errors = mem::take(&mut self.script_errors);
+        errors.clear();
+        self.scripts
+            .borrow_mut()
+            .retain(|script| match script.upgrade() {
+                Some(script) => {
+                    script.borrow_mut().poll(now, &mut errors);
+                    true
+                }
+                None => false,
+            });
+        events.extend((0..errors.len() as u32).map(|index| Event::ScriptError { index }));
+        self.script_errors = errors;
+        self.run_window_commands();
+    }
+
+    /// Run the callbacks of readbacks mapped since the last poll,
+    /// cancelling those of windows closed meanwhile
+    #[cfg(feature = "wgpu")]
+    fn poll_readbacks(&self, events: &mut Vec<Event>) {
+        let windows = self.windows.borrow();
+        let open = |id: WindowId| match windows.get(&id.xid()) {
+            Some((entry, state)) if *entry == id => state
+                .upgrade()
+                .is_some_and(|state| !state.borrow().destroyed),
+            _ => false,
+        };
+        readback::poll(&self.conn, open, events);
+    }
+
+    /// Deliver the sizes held back by the resize throttle once due
+    fn deliver_redraws(&mut self, events: &mut Vec<Event>) {
+        for (_, (id, state)) in self.windows.borrow().iter() {
+            let state = match state.upgrade() {
+                Some(state) => state,
+                None => continue,
+            };
+            let mut state = state.borrow_mut();
+            if let Some(viewport) = state.canvas_viewport.take() {
+                events.push(Event::CanvasViewportChanged {
+                    window: *id,
+                    viewport,
+                });
+            }
+            if mem::take(&mut state.redraw_requested) {
+                events.push(Event::RedrawRequested { window: *id });
+            }
+        }
+    }
+
+    fn deliver_held_resizes(&mut self, events: &mut Vec<Event>) {
+        let interval = match self.resize_throttle {
+            Some(interval) => interval,
+            None => return,
+        };
+        let now = Instant::now();
+        let due: Vec<_> = self
+            .resizes
+            .iter_mut()
+            .filter_map(|(&window, storm)| Some((window, storm.due(interval, now)?)))
+            .collect();
+        for &(window, _) in &due {
+            if let Some(state) = self.window_state(window) {
+                state.borrow_mut().resize_held = false;
+            }
+        }
+        self.resizes
+            .retain(|_, storm| !storm.is_settled(interval, now));
+        for (window, size) in due {
+            let event = Event::Resized {
+                window: self.window_id(window),
+                size: size.into(),
+            };
+            if self.event_filter.contains(EventFilter::of(&event)) {
+                self.push(events, event);
+            }
+        }
+    }
+
+    /// Make every following pump_events return Exit with `code`
+    pub fn exit(&self, code: i32) {
+        self.exit_code.set(Some(code));
+    }
+
+    /// Pump events into `handler` until pump_events returns Exit, then
+    /// return its code. AboutToWait ends every batch, only the late input
+    /// and redraws of set_late_latch may follow it. Unless a redraw was
+    /// requested the loop then blocks in wait_events, an idle loop takes
+    /// no CPU time, also without windows.
+    ///
+    /// A panicking handler is caught and its message logged, then handled
+    /// as set with set_panic_policy. The handler is run as if unwind
+    /// safe: state it leaves half updated is only seen again by Drop
+    /// implementations while unwinding, or not at all after
+    /// ConvertToExit, as the loop is consumed
+    pub fn run<F>(mut self, mut handler: F) -> i32
+    where
+        F: FnMut(&mut EventLoop, Event),
+    {
+        let mut events = Vec::new();
+        loop {
+            let status = self.pump_events(&mut events);
+            let mut latched = false;
+            loop {
+                for &event in &events {
+                    let dispatch = panic::AssertUnwindSafe(|| handler(&mut self, event));
+                    let start = Instant::now();
+                    let result = panic::catch_unwind(dispatch);
+                    let elapsed = start.elapsed();
+                    self.handler_time += elapsed;
+                    self.metrics.record_handler(elapsed);
+                    if let Err(payload) = result {
+                        let message = payload
+                            .downcast_ref::<&str>()
+                            .copied()
+                            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                            .unwrap_or("Box<dyn Any>");
+                        log::error!(
+                            "event handler panicked on {:?}: {}\nrecent events:\n{}",
+                            event,
+                            message,
+                            self.history.table(Instant::now())
+                        );
+                        match self.panic_policy {
+                            PanicPolicy::Abort => {
+                                self.conn.request(|conn| unsafe { session::restore(conn) });
+                                process::abort()
+                            }
+                            PanicPolicy::CleanupThenResume => {
+                                self.clean_up_after_panic();
+                                drop(self);
+                                panic::resume_unwind(payload);
+                            }
+                            PanicPolicy::ConvertToExit(code) => {
+                                self.clean_up_after_panic();
+                                return code;
+                            }
+                        }
+                    }
+                }
+                // The handler requested its redraws while handling AboutToWait
+                if latched || !self.late_latch || status != PumpStatus::Continue {
+                    break;
+                }
+                self.poll_late_input(&mut events);
+                latched = true;
+            }
+            if let PumpStatus::Exit { code } = status {
+                return code;
+            }
+            self.wait_events(None);
+        }
+    }
+
+    /// What run does when its handler panics, CleanupThenResume by
+    /// default
+    pub fn set_panic_policy(&mut self, policy: PanicPolicy) {
+        self.panic_policy = policy;
+    }
+
+    pub fn panic_policy(&self) -> PanicPolicy {
+        self.panic_policy
+    }
+
+    /// Wait for in-flight GPU work, undo grabs and video modes, then
+    /// destroy every window still open
+    /// and wait for the server to process that. The X11Windows are left
+    /// to drop without another request, the display closes along with the
+    /// last of them
+    fn clean_up_after_panic(&self) {
+        #[cfg(feature = "wgpu")]
+        for instance in self.conn.gpu_instances.lock().unwrap().iter() {
+            if let Some(instance) = instance.upgrade() {
+                instance.poll_all(true);
+            }
+        }
+        if self.conn.is_lost() {
+            return;
+        }
+        self.conn.request(|conn| unsafe { session::restore(conn) });
+        for (&xid, (_, state)) in self.windows.borrow().iter() {
+            if let Some(state) = state.upgrade() {
+                window::destroy(&self.conn, xid, &mut state.borrow_mut());
+            }
+        }
+        self.conn.request(|conn| unsafe {
+            xlib::XSync(conn.display, xlib::False);
+        });
+    }
+
+    /// Translate the input queued since the batch was read, behind
+    /// Event::LateInput, see set_late_latch
+    fn latch_input(&mut self, events: &mut Vec<Event>) {
+        if self.thread.is_some() || self.conn.is_lost() || self.is_blocked(events) {
+            return;
+        }
+        events.push(Event::LateInput);
+        unsafe {
+            let mut xevent: xlib::XEvent = mem::zeroed();
+            // Flushes, then reads what the server sent without blocking
+            while !self.is_blocked(events)
+                && xlib::XEventsQueued(self.conn.display, QUEUED_AFTER_FLUSH) != 0
+                && xlib::XCheckIfEvent(
+                    self.conn.display,
+                    &mut xevent,
+                    Some(late_input),
+                    &*self as *const EventLoop as xlib::XPointer,
+                ) != 0
+            {
+                self.process(&mut xevent, events);
+            }
+        }
+        self.deliver_key_repeats(events);
+        // Nothing arrived
+        if events.last() == Some(&Event::LateInput) {
+            events.pop();
+        }
+    }
+
+    /// Whether the event is input which may be latched late
+    fn is_late_input(&self, xevent: &xlib::XEvent) -> bool {
+        let input = match xevent.get_type() {
+            xlib::KeyPress
+            | xlib::KeyRelease
+            | xlib::ButtonPress
+            | xlib::ButtonRelease
+            | xlib::MotionNotify => true,
+            xlib::GenericEvent => {
+                let cookie = unsafe { xevent.generic_event_cookie };
+                Some(cookie.extension) == self.xinput2_opcode
+                    && LATE_XI_EVENTS.contains(&cookie.evtype)
+            }
+            _ => false,
+        };
+        input && (!self.conn.shared || self.claims(xevent))
+    }
+
+    fn is_blocked(&self, events: &[Event]) -> bool {
+        matches!(self.queue_limit, Some((QueuePolicy::Block, capacity)) if events.len() >= capacity)
+    }
+
+    fn process(&mut self, xevent: &mut xlib::XEvent, events: &mut Vec<Event>) {
+        if self.queue_helper_event(xevent) {
+            return;
+        }
+        // let input methods consume their events
+        if unsafe { xlib::XFilterEvent(xevent, 0) } != 0 {
+            return;
+        }
+        if xevent.get_type() == xlib::GenericEvent {
+            unsafe {
+                let cookie = &mut xevent.generic_event_cookie;
+                // The connection thread claims the data before reading on
+                if cookie.data.is_null() {
+                    xlib::XGetEventData(self.conn.display, cookie);
+                }
+                let seat_event = self.seat_event(cookie);
+                let event = self
+                    .translate_xinput2(cookie)
+                    .or_else(|| self.translate_present(cookie));
+                xlib::XFreeEventData(self.conn.display, cookie);
+                if let Some(event) = event {
+                    self.push(events, event);
+                }
+                if let Some((device, mut core)) = seat_event {
+                    self.event_device = Some(device);
+                    self.process(&mut core, events);
+                    self.event_device = None;
+                }
+                for event in mem::take(&mut self.hotplugged) {
+                    self.push(events, event);
+                }
+            }
+            return;
+        }
+        if self.is_work_area_change(xevent) {
+            self.update_work_area();
+            return;
+        }
+        if self.is_wm_change(xevent) {
+            self.update_window_manager();
+            return;
+        }
+        if self.hotkeys.grabbed(xevent) {
+            if let Some(event) = self.hotkeys.translate(unsafe { &xevent.key }) {
+                self.push(events, event);
+            }
+            return;
+        }
+        if xevent.get_type() == xlib::MappingNotify {
+            let request = unsafe {
+                xlib::XRefreshKeyboardMapping(&mut xevent.mapping);
+                xevent.mapping.request
+            };
+            if request != xlib::MappingPointer && !self.hotkeys.is_empty() {
+                self.hotkeys.regrab();
+            }
+            return;
+        }
+        if let Some(child) = substructure_child(xevent) {
+            let parent = unsafe { xevent.any.window };
+            if let Some(event) = self.translate_foreign(parent, child, xevent) {
+                self.push(events, event);
+            }
+            return;
+        }
+        // With several master pointers the merged core pointer events
+        // are left for those of each master pointer
+        let pointer_event = matches!(
+            xevent.get_type(),
+            xlib::MotionNotify | xlib::ButtonPress | xlib::ButtonRelease | xlib::LeaveNotify
+        );
+        if self.seats && pointer_event && self.event_device.is_none() {
+            return;
+        }
+        // Input deselected by set_event_filter may still be queued
+        let category = match xevent.get_type() {
+            xlib::KeyPress | xlib::KeyRelease => EventFilter::KEYBOARD,
+            xlib::MotionNotify => EventFilter::POINTER_MOTION,
+            xlib::ButtonPress | xlib::ButtonRelease => EventFilter::POINTER_BUTTON,
+            _ => EventFilter::empty(),
+        };
+        if !self.event_filter.contains(category) {
+            return;
+        }
+        // Server repeats make way for those of set_key_repeat, along with
+        // the text they would type
+        if self.key_repeat.is_some()
+            && xevent.get_type() == xlib::KeyPress
+            && self.is_key_held(unsafe { &xevent.key })
+        {
+            return;
+        }
+        let filter = self.event_filter;
+        // The button release of a drag never arrives once the pointer is
+        // grabbed away or focus moves on, and key releases go wherever
+        // focus went, e.g. to the window manager switching windows.
+        // Release them here instead
+        let lost_buttons = match xevent.get_type() {
+            xlib::LeaveNotify => {
+                let xcrossing = unsafe { xevent.crossing };
+                let device = self.pointer_device();
+                matches!(xcrossing.mode, xlib::NotifyGrab | xlib::NotifyUngrab).then_some((
+                    xcrossing.window,
+                    Some((xcrossing.x, xcrossing.y)),
+                    Some(device),
+                ))
+            }
+            xlib::FocusOut if lost_focus(unsafe { &xevent.focus_change }) => {
+                let window = unsafe { xevent.focus_change.window };
+                if filter.contains(EventFilter::KEYBOARD) {
+                    self.release_held_keys(window, events);
+                }
+                Some((window, None, None))
+            }
+            _ => None,
+        };
+        if let Some((window, position, device)) = lost_buttons {
+            if let Some(state) = self.window_state(window) {
+                state.borrow_mut().manual_drag = None;
+            }
+            if filter.contains(EventFilter::POINTER_BUTTON) {
+                self.release_held_buttons(window, position, device, events);
+            }
+        }
+        match xevent.get_type() {
+            xlib::MotionNotify => self.continue_manual_drag(unsafe { &xevent.motion }, events),
+            xlib::ButtonRelease => self.end_manual_drag(unsafe { &xevent.button }),
+            _ => {}
+        }
+        if let Some(event) = self
+            .translate(xevent)
+            .filter(|event| filter.contains(EventFilter::of(event)))
+        {
+            self.push(events, event);
+        }
+        if xevent.get_type() == xlib::KeyPress {
+            if let Some(event) = self.lookup_text(unsafe { &mut xevent.key }) {
+                self.push(events, event);
+                let keycode = unsafe { xevent.key.keycode };
+                if let Some(repeating) = self
+                    .repeating
+                    .as_mut()
+                    .filter(|repeating| repeating.keycode == keycode)
+                {
+                    repeating.text = self.texts.last().cloned();
+                }
+            }
+        }
+        if xevent.get_type() == xlib::FocusIn {
+            let xfocus = unsafe { xevent.focus_change };
+            if xfocus.detail != xlib::NotifyPointer
+                && matches!(xfocus.mode, xlib::NotifyNormal | xlib::NotifyUngrab)
+            {
+                self.resync_held_input(xfocus.window, filter, events);
+            }
+        }
+        if xevent.get_type() == xlib::PropertyNotify && filter.contains(EventFilter::WINDOW_STATE) {
+            self.update_wm_state(unsafe { &xevent.property }, events);
+        }
+        if xevent.get_type() == xlib::ConfigureNotify && filter.contains(EventFilter::WINDOW_STATE)
+        {
+            self.update_monitor(unsafe { &xevent.configure }, events);
+        }
+    }
+
+    /// Move a window dragged with begin_manual_drag along with the
+    /// pointer, snapped to the nearest edges
+    fn continue_manual_drag(&mut self, xmotion: &xlib::XMotionEvent, events: &mut Vec<Event>) {
+        let state = match self.window_state(xmotion.window) {
+            Some(state) => state,
+            None => return,
+        };
+        let mut state = state.borrow_mut();
+        let size = state.last_known.size;
+        let drag = match &mut state.manual_drag {
+            Some(drag) => drag,
+            None => return,
+        };
+        let rect = Rect {
+            x: xmotion.x_root - drag.grab.0,
+            y: xmotion.y_root - drag.grab.1,
+            width: size.width,
+            height: size.height,
+        };
+        let snapping = self
+            .snapping
+            .filter(|snapping| xmotion.state & snapping.bypass == 0);
+        let ((x, y), snapped) = match snapping {
+            Some(snapping) => {
+                let targets = drag
+                    .targets
+                    .get_or_insert_with(|| self.snap_targets(xmotion.window));
+                snap::snap(rect, targets, snapping.threshold)
+            }
+            None => ((rect.x, rect.y), (None, None)),
+        };
+        let engaged = drag.engage(snapped);
+        state.last_known.position = Some(PhysicalPosition::new(x, y));
+        drop(state);
+
+        let window = xmotion.window;
+        self.conn.request(move |conn| unsafe {
+            xlib::XMoveWindow(conn.display, window, x, y);
+            xlib::XFlush(conn.display);
+        });
+        let id = self.window_id(window);
+        for edge in engaged {
+            self.push(events, Event::Snapped { window: id, edge });
+        }
+    }
+
+    /// End a drag of begin_manual_drag once the last held button was
+    /// released
+    fn end_manual_drag(&self, xbutton: &xlib::XButtonEvent) {
+        let buttons = xlib::Button1Mask
+            | xlib::Button2Mask
+            | xlib::Button3Mask
+            | xlib::Button4Mask
+            | xlib::Button5Mask;
+        // The state is the one before the release
+        let released = 1u32.checked_shl(xbutton.button + 7).unwrap_or(0);
+        if xbutton.state & buttons & !released != 0 {
+            return;
+        }
+        if let Some(state) = self.window_state(xbutton.window) {
+            state.borrow_mut().manual_drag = None;
+        }
+    }
+
+    /// The work area of every monitor and the bounds of the loop's other
+    /// viewable windows, in root coordinates
+    fn snap_targets(&self, dragged: xlib::Window) -> Targets {
+        let areas = self
+            .available_monitors()
+            .iter()
+            .map(MonitorHandle::work_area)
+            .collect();
+        let others: Vec<xlib::Window> = self
+            .windows
+            .borrow()
+            .iter()
+            .filter(|(&window, (_, state))| window != dragged && state.strong_count() > 0)
+            .map(|(&window, _)| window)
+            .collect();
+        let windows = self.conn.request(move |conn| {
+            others
+                .iter()
+                .filter_map(|&window| snap::viewable_rect(conn, window))
+                .collect()
+        });
+        Targets { areas, windows }
+    }
+
+    /// Find the monitor under the center of the window after it moved or
+    /// resized, reporting when it is another one
+    fn update_monitor(&mut self, xconfigure: &xlib::XConfigureEvent, events: &mut Vec<Event>) {
+        let (monitors, state) = match (&self.monitors, self.window_state(xconfigure.window)) {
+            (Some(monitors), Some(state)) => (monitors.clone(), state),
+            _ => return,
+        };
+        let (width, height) = (xconfigure.width, xconfigure.height);
+        // The window manager sends synthetic events relative to the root,
+        // the server's are relative to the parent, usually a WM frame
+        let (x, y) = if xconfigure.send_event != 0 {
+            (xconfigure.x, xconfigure.y)
+        } else {
+            let window = xconfigure.window;
+            self.conn.request(move |conn| unsafe {
+                let (mut x, mut y, mut child) = (0, 0, 0);
+                xlib::XTranslateCoordinates(
+                    conn.display,
+                    window,
+                    conn.root,
+                    0,
+                    0,
+                    &mut x,
+                    &mut y,
+                    &mut child,
+                );
+                (x, y)
+            })
+        };
+        let monitor = match monitor::containing(monitors, (x + width / 2, y + height / 2)) {
+            Some(monitor) => monitor.into_raw(),
+            None => return,
+        };
+        if state.borrow_mut().monitor.replace(monitor) == Some(monitor) {
+            return;
+        }
+        let window = self.window_id(xconfigure.window);
+        self.push(events, Event::MonitorChanged { window, monitor });
+    }
+
+    /// Report releases of the buttons held in the window by one master
+    /// pointer or all of them, at the given position or the last known
+    /// one
+    fn release_held_buttons(
+        &mut self,
+        window: xlib::Window,
+        position: Option<(i32, i32)>,
+        device: Option<DeviceId>,
+        events: &mut Vec<Event>,
+    ) {
+        let held: Vec<(DeviceId, u32)> = match self.window_state(window) {
+            Some(state) => {
+                let mut state = state.borrow_mut();
+                match device {
+                    Some(device) => state
+                        .held_buttons
+                        .remove(&device)
+                        .map(|held| (device, held))
+                        .into_iter()
+                        .collect(),
+                    None => state.held_buttons.drain().collect(),
+                }
+            }
+            None => return,
+        };
+        let id = self.window_id(window);
+        for (device, held) in held {
+            let (x, y) = match (position, self.cursors.get(&device)) {
+                (Some((x, y)), _) => (x as f64, y as f64),
+                (None, Some(&(w, x, y))) if w == window => (x, y),
+                _ => (0.0, 0.0),
+            };
+            let root_position = self.root_position(window, x, y);
+            for button in (0..32).filter(|button| held & 1 << button != 0) {
+                self.push(
+                    events,
+                    Event::MouseInput {
+                        window: id,
+                        button,
+                        pressed: false,
+                        position: PhysicalPosition::new(x, y),
+                        root_position,
+                        synthetic: true,
+                        device,
+                    },
+                );
+            }
+        }
+    }
+
+    fn release_held_keys(&mut self, window: xlib::Window, events: &mut Vec<Event>) {
+        let held = match self.window_state(window) {
+            Some(state) => mem::take(&mut state.borrow_mut().held_keys),
+            None => return,
+        };
+        let id = self.window_id(window);
+        for (keycode, keysym) in held {
+            self.push(
+                events,
+                Event::KeyboardInput {
+                    window: id,
+                    keycode,
+                    keysym,
+                    pressed: false,
+                    synthetic: true,
+                    repeat: false,
+                },
+            );
+        }
+        if self
+            .repeating
+            .as_ref()
+            .is_some_and(|repeating| repeating.window == window)
+        {
+            self.repeating = None;
+        }
+    }
+
+    fn is_key_held(&self, key: &xlib::XKeyEvent) -> bool {
+        self.window_state(key.window).is_some_and(|state| {
+            state
+                .borrow()
+                .held_keys
+                .iter()
+                .any(|&(keycode, _)| keycode == key.keycode)
+        })
+    }
+
+    /// Repeat a key just pressed for set_key_repeat, replacing the key
+    /// repeating so far
+    fn start_key_repeat(&mut self, window: xlib::Window, keycode: u32, keysym: xlib::KeySym) {
+        let repeats = self
+            .repeating_keys
+            .get(keycode as usize / 8)
+            .is_some_and(|bits| bits & 1 << (keycode % 8) != 0)
+            && !keyboard::is_modifier(keysym);
+        self.repeating = self
+            .key_repeat
+            .filter(|_| repeats)
+            .map(|key_repeat| Repeating {
+                window,
+                keycode,
+                keysym,
+                text: None,
+                next: Instant::now() + key_repeat.delay,
+            });
+    }
+
+    /// Deliver the repeats of set_key_repeat which are due
+    fn deliver_key_repeats(&mut self, events: &mut Vec<Event>) {
+        let interval = match self.key_repeat {
+            Some(key_repeat) => key_repeat.interval,
+            None => return,
+        };
+        if !self.event_filter.contains(EventFilter::KEYBOARD) {
+            self.repeating = None;
+        }
+        let now = Instant::now();
+        while let Some(repeating) = self
+            .repeating
+            .as_mut()
+            .filter(|repeating| repeating.next <= now)
+        {
+            repeating.next += interval;
+            let (window, keycode, keysym) = (repeating.window, repeating.keycode, repeating.keysym);
+            let text = repeating.text.clone();
+            if self.window_state(window).is_none() {
+                self.repeating = None;
+                return;
+            }
+            let id = self.window_id(window);
+            self.push(
+                events,
+                Event::KeyboardInput {
+                    window: id,
+                    keycode,
+                    keysym,
+                    pressed: true,
+                    synthetic: true,
+                    repeat: true,
+                },
+            );
+            if let Some(text) = text {
+                self.texts.push(text);
+                let index = self.texts.len() as u32 - 1;
+                self.push(events, Event::Text { window: id, index });
+            }
+        }
+    }
+
+    /// Bring the held keys and buttons in line with the server's when the
+    /// window gains focus, pressing those held down elsewhere and
+    /// releasing those let go while focus was away
+    fn resync_held_input(
+        &mut self,
+        window: xlib::Window,
+        filter: EventFilter,
+        events: &mut Vec<Event>,
+    ) {
+        let state = match self.window_state(window) {
+            Some(state) => state,
+            None => return,
+        };
+        let id = self.window_id(window);
+        let mut synthesized = Vec::new();
+
+        if filter.contains(EventFilter::KEYBOARD) {
+            let keymap = self.conn.query_keymap();
+            let is_down = |keycode: u32| keymap[keycode as usize / 8] & 1 << (keycode % 8) != 0;
+            let mut state = state.borrow_mut();
+            state.held_keys.retain(|&(keycode, keysym)| {
+                let down = is_down(keycode);
+                if !down {
+                    synthesized.push(Event::KeyboardInput {
+                        window: id,
+                        keycode,
+                        keysym,
+                        pressed: false,
+                        synthetic: true,
+                        repeat: false,
+                    });
+                }
+                down
+            });
+            // X keycodes start at 8
+            for keycode in (8..256).filter(|&keycode| is_down(keycode)) {
+                if state.held_keys.iter().any(|&(held, _)| held == keycode) {
+                    continue;
+                }
+                let keysym =
+                    unsafe { xlib::XkbKeycodeToKeysym(self.conn.display, keycode as u8, 0, 0) };
+                state.held_keys.push((keycode, keysym));
+                synthesized.push(Event::KeyboardInput {
+                    window: id,
+                    keycode,
+                    keysym,
+                    pressed: true,
+                    synthetic: true,
+                    repeat: false,
+                });
+            }
+        }
+
+        if filter.contains(EventFilter::POINTER_BUTTON) {
+            // Only the core pointer is queried, the buttons of other master
+            // pointers are released on focus loss and not pressed again
+            let ((root_x, root_y), position, mask) = self.conn.query_pointer(window);
+            let (x, y) = position.unwrap_or((0, 0));
+            let device = self.conn.core_pointer();
+            let mut state = state.borrow_mut();
+            if position.is_some() {
+                state.root_origin = Some(PhysicalPosition::new(root_x - x, root_y - y));
+            }
+            let held_buttons = state.held_buttons.entry(device).or_default();
+            // The core mask only knows buttons 1 to 5, of which 4 and 5
+            // are the wheel
+            for button in 1..=3 {
+                let down = mask & xlib::Button1Mask << (button - 1) != 0;
+                let held = *held_buttons & 1 << button != 0;
+                if down == held {
+                    continue;
+                }
+                *held_buttons ^= 1 << button;
+                synthesized.push(Event::MouseInput {
+                    window: id,
+                    button,
+                    pressed: down,
+                    position: PhysicalPosition::new(x as f64, y as f64),
+                    root_position: PhysicalPosition::new(root_x as f64, root_y as f64),
+                    synthetic: true,
+                    device,
+                });
+            }
+        }
+
+        for event in synthesized {
+            self.push(events, event);
+        }
+    }
+
+    /// Compare _NET_WM_STATE with the cached state when the window
+    /// manager rewrites it, reporting each transition
+    fn update_wm_state(&mut self, xproperty: &xlib::XPropertyEvent, events: &mut Vec<Event>) {
+        if xproperty.atom != self.conn.atoms.net_wm_state {
+            return;
+        }
+        let state = match self.window_state(xproperty.window) {
+            Some(state) => state,
+            None => return,
+        };
+        let window = xproperty.window;
+        let next = self
+            .conn
+            .request(move |conn| window::WmState::query(conn, window));
+        let previous = mem::replace(&mut state.borrow_mut().wm_state, next);
+        let id = self.window_id(window);
+        for change in previous.changes(next) {
+            // Borderless fullscreen left through the window manager is
+            // left for set_fullscreen too
+            if change == StateChange::FullscreenExited {
+                let mut state = state.borrow_mut();
+                if matches!(state.fullscreen, Some(Fullscreen::Borderless(_))) {
+                    state.fullscreen = None;
+                }
+            }
+            self.push(events, Event::StateChanged { window: id, change });
+        }
+    }
+
+    /// The text of a key press, or of the synthetic key press delivering
+    /// what the input method composed
+    fn lookup_text(&mut self, key: &mut xlib::XKeyEvent) -> Option<Event> {
+        let context = self.window_state(key.window).and_then(|state| {
+            let state = state.borrow();
+            state.input_context.filter(|_| state.ime_allowed)
+        })?;
+        let text = unsafe { context.lookup(key)? };
+        self.texts.push(text);
+        Some(Event::Text {
+            window: self.window_id(key.window),
+            index: self.texts.len() as u32 - 1,
+        })
+    }
+
+    /// Cache the root origin of a window from a pointer event, which
+    /// carries the position relative to both
+    fn note_root_origin(
+        &self,
+        window: xlib::Window,
+        same_screen: xlib::Bool,
+        (x, y): (i32, i32),
+        (root_x, root_y): (i32, i32),
+    ) {
+        // The window relative position is zero on other screens
+        if same_screen == xlib::False {
+            return;
+        }
+        if let Some(state) = self.window_state(window) {
+            state.borrow_mut().root_origin = Some(PhysicalPosition::new(root_x - x, root_y - y));
+        }
+    }
+
+    /// A window relative position relative to the root through the
+    /// cached origin of the window, for events the server did not send
+    fn root_position(&self, window: xlib::Window, x: f64, y: f64) -> PhysicalPosition<f64> {
+        let origin = self
+            .window_state(window)
+            .and_then(|state| state.borrow().root_origin)
+            .unwrap_or_default();
+        PhysicalPosition::new(origin.x as f64 + x, origin.y as f64 + y)
+    }
+
+    fn push(&mut self, events: &mut Vec<Event>, event: Event) {
+        if self.block_modal_owner(&event, events) {
+            return;
+        }
+        if self.motion_coalescing {
+            if let (
+                Some(Event::CursorMoved {
+                    window: last_window,
+                    position: last_position,
+                    root_position: last_root_position,
+                    delta: last_delta,
+                    device: last_device,
+                }),
+                Event::CursorMoved {
+                    window,
+                    position,
+                    root_position,
+                    delta,
+                    device,
+                },
+            ) = (events.last_mut(), event)
+            {
+                if *last_window == window && *last_device == device {
+                    *last_position = position;
+                    *last_root_position = root_position;
+                    last_delta.0 += delta.0;
+                    last_delta.1 += delta.1;
+                    return;
+                }
+            }
+        }
+
+        self.events_lost += enqueue(events, event, self.queue_limit);
+    }
+
+    /// Whether `event` is input for the owner of a shown modal window,
+    /// which is dropped along with the releases of the presses dropped.
+    /// The first click since the modal window had focus delivers
+    /// ModalBlocked in its place
+    fn block_modal_owner(&mut self, event: &Event, events: &mut Vec<Event>) -> bool {
+        let (window, pressed) = match *event {
+            Event::KeyboardInput {
+                window, pressed, ..
+            }
+            | Event::MouseInput {
+                window, pressed, ..
+            } => (window, pressed),
+            Event::Text { window, .. }
+            | Event::MouseWheel { window, .. }
+            | Event::CursorMoved { window, .. }
+            | Event::Tablet { window, .. } => (window, true),
+            _ => return false,
+        };
+        if !pressed {
+            return self.blocked_presses.release(event);
+        }
+        if !self.modal_input_blocking {
+            return false;
+        }
+        let (modal, state) = match self.modal_child(window.xid()) {
+            Some(modal) => modal,
+            None => return false,
+        };
+        self.blocked_presses.press(event);
+        if matches!(event, Event::MouseInput { .. })
+            && !mem::replace(&mut state.borrow_mut().modal_blocked, true)
+        {
+            events.push(Event::ModalBlocked { window, modal });
+        }
+        true
+    }
+
+    /// The shown modal window owned by `owner`
+    fn modal_child(&self, owner: xlib::Window) -> Option<(WindowId, Rc<RefCell<WindowState>>)> {
+        let modal = *self.conn.modals.lock().unwrap().get(&owner)?;
+        let windows = self.windows.borrow();
+        let (id, state) = windows.get(&modal)?;
+        Some((*id, state.upgrade()?))
+    }
+
+    /// Restore the desktop mode while an exclusive fullscreen window is
+    /// unfocused and switch back when it regains focus
+    fn update_mode_switch(&self, window: xlib::Window, focused: bool) {
+        let state = match self.window_state(window) {
+            Some(state) => state,
+            None => return,
+        };
+        let mut state = state.borrow_mut();
+        let grab = state.exclusive_grab;
+        let switch = match state.mode_switch.as_mut() {
+            Some(switch) => switch,
+            None => return,
+        };
+
+        if focused {
+            switch.reapply();
+        } else {
+            switch.restore();
+        }
+        self.conn.request(move |conn| unsafe {
+            if focused {
+                xlib::XRaiseWindow(conn.display, window);
+                if grab {
+                    window::grab_input(conn, window);
+                }
+            } else {
+                window::ungrab_pointer(conn);
+                window::ungrab_keyboard(conn);
+                xlib::XLowerWindow(conn.display, window);
+            }
+            xlib::XFlush(conn.display);
+        });
+    }
+
+    /// Release the keyboard and pointer grabs while the window is
+    /// unfocused and take them again when it regains focus
+    fn update_grabs(&self, window: xlib::Window, focused: bool) {
+        let (keyboard, pointer) = match self.window_state(window) {
+            Some(state) => {
+                let state = state.borrow();
+                (state.keyboard_grab, state.pointer_grab)
+            }
+            None => return,
+        };
+        if !keyboard && !pointer {
+            return;
+        }
+
+        self.conn.request(move |conn| unsafe {
+            if focused {
+                if keyboard {
+                    if let Err(err) = window::grab_keyboard(conn, window) {
+                        log::warn!("failed to take the keyboard grab again: {}", err);
+                    }
+                }
+                if pointer {
+                    if let Err(err) = window::grab_pointer(conn, window) {
+                        log::warn!("failed to take the pointer grab again: {}", err);
+                    }
+                }
+            } else {
+                if keyboard {
+                    window::ungrab_keyboard(conn);
+                }
+                if pointer {
+                    window::ungrab_pointer(conn);
+                }
+            }
+            xlib::XFlush(conn.display);
+        });
+    }
+
+    /// Drop both grabs of the window for good when its escape chord is
+    /// pressed, so a stuck application cannot lock up the desktop
+    fn check_grab_escape(
+        &self,
+        window: xlib::Window,
+        modifiers: raw::c_uint,
+        keysym: xlib::KeySym,
+    ) {
+        let state = match self.window_state(window) {
+            Some(state) => state,
+            None => return,
+        };
+        let mut state = state.borrow_mut();
+        let matches = matches!(state.grab_escape,
+            Some((mask, key)) if key == keysym && modifiers & mask == mask);
+        if !matches || !(state.keyboard_grab || state.pointer_grab) {
+            return;
+        }
+
+        state.keyboard_grab = false;
+        state.pointer_grab = false;
+        log::warn!("grab escape pressed, releasing the keyboard and pointer");
+        self.conn.request(move |conn| unsafe {
+            window::ungrab_keyboard(conn);
+            window::ungrab_pointer(conn);
+            xlib::XFlush(conn.display);
+        });
+    }
+
+    fn translate(&mut self, xevent: &mut xlib::XEvent) -> Option<Event> {
+        if let Some(event_base) = self.randr_event_base {
+            let randr_event = xevent.get_type() - event_base;
+            if randr_event == RRScreenChangeNotify || randr_event == RRNotify {
+                if randr_event == RRScreenChangeNotify {
+                    monitor::update_configuration(xevent);
+                }
+                // Reported once per poll, a mode switch sends several
+                self.monitors = Some(self.conn.request(monitor::monitors));
+                self.monitors_changed = true;
+                return None;
+            }
+        }
+        let atoms = &self.conn.atoms;
+        unsafe {
+            match xevent.get_type() {
+                xlib::ClientMessage => {
+                    let xclient = xevent.client_message;
+                    if xclient.message_type == atoms.wm_protocols
+                        && xclient.format == 32
+                        && xclient.data.get_long(0) as xlib::Atom == atoms.wm_delete_window
+                    {
+                        return Some(Event::CloseRequested {
+                            window: self.window_id(xclient.window),
+                        });
+                    }
+                    // The window manager checks we are responsive by
+                    // having the ping bounced back to the root window
+                    if xclient.message_type == atoms.wm_protocols
+                        && xclient.format == 32
+                        && xclient.data.get_long(0) as xlib::Atom == atoms.net_wm_ping
+                    {
+                        let data = [0, 1, 2, 3, 4].map(|i| xclient.data.get_long(i));
+                        self.conn.request(move |conn| {
+                            let mut reply: xlib::XClientMessageEvent = mem::zeroed();
+                            reply.type_ = xlib::ClientMessage;
+                            reply.window = conn.root;
+                            reply.message_type = conn.atoms.wm_protocols;
+                            reply.format = 32;
+                            for (i, &value) in data.iter().enumerate() {
+                                reply.data.set_long(i, value);
+                            }
+                            let mut event = xlib::XEvent::from(reply);
+                            xlib::XSendEvent(
+                                conn.display,
+                                conn.root,
+                                xlib::False,
+                                xlib::SubstructureNotifyMask | xlib::SubstructureRedirectMask,
+                                &mut event,
+                            );
+                            xlib::XFlush(conn.display);
+                        });
+                        return None;
+                    }
+                    // Acknowledged once the configure it announces arrived
+                    if xclient.message_type == atoms.wm_protocols
+                        && xclient.format == 32
+                        && xclient.data.get_long(0) as xlib::Atom == atoms.net_wm_sync_request
+                    {
+                        let value = resize::sync_request_value(
+                            xclient.data.get_long(2),
+                            xclient.data.get_long(3),
+                        );
+                        if let Some(state) = self.window_state(xclient.window) {
+                            state.borrow_mut().sync_request = Some(value);
+                        }
+                        return None;
+                    }
+                    if xclient.message_type == atoms.xembed && xclient.format == 32 {
+                        return self.translate_xembed(&xclient);
+                    }
+                    // Rewriting WM_COMMAND tells the session manager the
+                    // client state is saved
+                    if xclient.message_type == atoms.wm_protocols
+                        && xclient.data.get_long(0) as xlib::Atom == atoms.wm_save_yourself
+                    {
+                        self.conn.request(XConnection::set_command);
+                    }
+                    None
+                }
+                xlib::ConfigureNotify => {
+                    let xconfigure = xevent.configure;
+                    if let Some(state) = self.window_state(xconfigure.window) {
+                        let mut state = state.borrow_mut();
+                        let last_known = &mut state.last_known;
+                        last_known.size =
+                            PhysicalSize::new(xconfigure.width as u32, xconfigure.height as u32);
+                        // Only the window manager's synthetic events are
+                        // relative to the root
+                        if xconfigure.send_event != 0 {
+                            last_known.position =
+                                Some(PhysicalPosition::new(xconfigure.x, xconfigure.y));
+                        }
+                        if xconfigure.send_event != 0 || state.parent_is_root {
+                            let border = xconfigure.border_width;
+                            state.root_origin = Some(PhysicalPosition::new(
+                                xconfigure.x + border,
+                                xconfigure.y + border,
+                            ));
+                        }
+                        let normal = !state.wm_state.maximized
+                            && !state.wm_state.fullscreen
+                            && state.fullscreen.is_none();
+                        if let Some(origin) = state.root_origin.filter(|_| normal) {
+                            state.normal_geometry = Some(Rect {
+                                x: origin.x,
+                                y: origin.y,
+                                width: xconfigure.width as u32,
+                                height: xconfigure.height as u32,
+                            });
+                        }
+                        if let (Some(counter), Some(value)) =
+                            (state.sync_counter, state.sync_request.take())
+                        {
+                            self.conn.request(move |conn| {
+                                resize::set_sync_counter(conn, counter, value)
+                            });
+                        }
+                        if !state.foreign.is_empty() {
+                            let size = (xconfigure.width as u32, xconfigure.height as u32);
+                            let placed: Vec<_> = state
+                                .foreign
+                                .iter()
+                                .map(|&(child, rect)| (child, foreign::clip(rect, size)))
+                                .collect();
+                            self.conn.request(move |conn| {
+                                for (child, rect) in placed {
+                                    foreign::place(conn, child, rect);
+                                }
+                                xlib::XFlush(conn.display);
+                            });
+                        }
+                        if state.barrier_confinement {
+                            let focused = self.focused == Some(xconfigure.window);
+                            window::update_barriers(
+                                &self.conn,
+                                xconfigure.window,
+                                &mut state,
+                                focused,
+                            );
+                        }
+                    }
+                    let size = (xconfigure.width as u32, xconfigure.height as u32);
+                    if self.sizes.insert(xconfigure.window, size) == Some(size) {
+                        return None;
+                    }
+                    let size = match self.resize_throttle {
+                        Some(interval) => {
+                            let now = Instant::now();
+                            let delivered = self
+                                .resizes
+                                .entry(xconfigure.window)
+                                .or_insert_with(|| ResizeStorm::new(now))
+                                .configure(size, interval, now);
+                            if let Some(state) = self.window_state(xconfigure.window) {
+                                state.borrow_mut().resize_held = delivered.is_none();
+                            }
+                            delivered?
+                        }
+                        None => size,
+                    };
+                    Some(Event::Resized {
+                        window: self.window_id(xconfigure.window),
+                        size: size.into(),
+                    })
+                }
+                xlib::ReparentNotify => {
+                    // Back on the root window the embedder is gone
+                    let xreparent = xevent.reparent;
+                    let on_root = xreparent.parent == self.conn.root;
+                    if let Some(state) = self.window_state(xreparent.window) {
+                        let mut state = state.borrow_mut();
+                        state.parent_is_root = on_root;
+                        if on_root {
+                            state.embedder = None;
+                            state.root_origin =
+                                Some(PhysicalPosition::new(xreparent.x, xreparent.y));
+                        }
+                    }
+                    None
+                }
+                xlib::DestroyNotify => {
+                    let window = xevent.destroy_window.window;
+                    // The X id may already belong to a window created after
+                    // this one was dropped
+                    let mut windows = self.windows.borrow_mut();
+                    let id = windows.get(&window).map(|&(id, _)| id);
+                    if matches!(windows.get(&window), Some((_, state)) if state.strong_count() == 0)
+                    {
+                        windows.remove(&window);
+                    }
+                    drop(windows);
+                    self.sizes.remove(&window);
+                    self.resizes.remove(&window);
+                    self.cursors.retain(|_, &mut (w, _, _)| w != window);
+                    if self.focused == Some(window) {
+                        self.focused = None;
+                    }
+                    id.map(|window| Event::Destroyed { window })
+                }
+                xlib::FocusIn | xlib::FocusOut => {
+                    let xfocus = xevent.focus_change;
+                    let focused = xevent.get_type() == xlib::FocusIn;
+                    if focused {
+                        self.focused = Some(xfocus.window);
+                    } else if self.focused == Some(xfocus.window) {
+                        self.focused = None;
+                    }
+                    // Grabs move focus too, only focus changes by the user
+                    // give the desktop mode back
+                    if xfocus.mode == xlib::NotifyNormal {
+                        self.update_mode_switch(xfocus.window, focused);
+                        if focused && self.modal_input_blocking {
+                            if let Some((modal, _)) = self.modal_child(xfocus.window) {
+                                window::activate(&self.conn, modal.xid());
+                            }
+                        }
+                    }
+                    if xfocus.mode == xlib::NotifyNormal || xfocus.mode == xlib::NotifyWhileGrabbed
+                    {
+                        self.update_grabs(xfocus.window, focused);
+                    }
+                    if let Some(state) = self.window_state(xfocus.window) {
+                        let mut state = state.borrow_mut();
+                        state.focused = focused;
+                        state.modal_blocked &= !focused;
+                        window::update_input_context(
+                            &self.conn,
+                            xfocus.window,
+                            &mut state,
+                            focused,
+                        );
+                        window::update_barriers(&self.conn, xfocus.window, &mut state, focused);
+                        // Focus coming back from the child itself stays
+                        if let Some(child) = state.foreign_focus.filter(|_| {
+                            focused
+                                && xfocus.mode == xlib::NotifyNormal
+                                && xfocus.detail != xlib::NotifyInferior
+                        }) {
+                            let window = xfocus.window;
+                            self.conn.request(move |conn| {
+                                foreign::focus(conn, window, Some(child));
+                                xlib::XFlush(conn.display);
+                            });
+                        }
+                    }
+                    Some(Event::Focused {
+                        window: self.window_id(xfocus.window),
+                        focused,
+                    })
+                }
+                xlib::KeyPress | xlib::KeyRelease => {
+                    self.conn
+                        .last_event_time
+                        .store(xevent.key.time, Ordering::Relaxed);
+                    // Input methods deliver composed text with keycode 0,
+                    // reported by lookup_text alone
+                    if xevent.key.keycode == 0 {
+                        return None;
+                    }
+                    let keysym = xlib::XLookupKeysym(&mut xevent.key, 0);
+                    let pressed = xevent.get_type() == xlib::KeyPress;
+                    if pressed {
+                        self.check_grab_escape(xevent.key.window, xevent.key.state, keysym);
+                    }
+                    // Releases of keys already released on focus loss
+                    // are dropped, presses of held keys are repeats
+                    let keycode = xevent.key.keycode;
+                    let mut repeat = false;
+                    if let Some(state) = self.window_state(xevent.key.window) {
+                        let mut state = state.borrow_mut();
+                        let held = state
+                            .held_keys
+                            .iter()
+                            .position(|&(held, _)| held == keycode);
+                        match (pressed, held) {
+                            (true, None) => state.held_keys.push((keycode, keysym)),
+                            (true, Some(_)) => repeat = true,
+                            (false, Some(index)) => {
+                                state.held_keys.swap_remove(index);
+                            }
+                            (false, None) => return None,
+                        }
+                    }
+                    if pressed && !repeat {
+                        self.start_key_repeat(xevent.key.window, keycode, keysym);
+                    } else if self
+                        .repeating
+                        .as_ref()
+                        .is_some_and(|repeating| !pressed && repeating.keycode == keycode)
+                    {
+                        self.repeating = None;
+                    }
+                    Some(Event::KeyboardInput {
+                        window: self.window_id(xevent.key.window),
+                        keycode,
+                        keysym,
+                        pressed,
+                        synthetic: false,
+                        repeat,
+                    })
+                }
+                xlib::ButtonPress | xlib::ButtonRelease => {
+                    let xbutton = xevent.button;
+                    self.conn
+                        .last_event_time
+                        .store(xbutton.time, Ordering::Relaxed);
+                    if (4..=7).contains(&xbutton.button) {
+                        // Wheel clicks only press, and smooth scrolling
+                        // was already reported through XInput2
+                        if xevent.get_type() == xlib::ButtonRelease
+                            || xbutton.time == self.smooth_scroll_time
+                        {
+                            return None;
+                        }
+                        let delta = match xbutton.button {
+                            4 => (0.0, 1.0),
+                            5 => (0.0, -1.0),
+                            6 => (1.0, 0.0),
+                            _ => (-1.0, 0.0),
+                        };
+                        return Some(Event::MouseWheel {
+                            window: self.window_id(xbutton.window),
+                            delta: ScrollDelta::Lines(delta.0, delta.1),
+                        });
+                    }
+                    // Every press is followed by one release, releases of
+                    // buttons reported released already are dropped, as
+                    // are presses of buttons pressed again on focus
+                    let pressed = xevent.get_type() == xlib::ButtonPress;
+                    let bit = 1u32.checked_shl(xbutton.button);
+                    let device = self.pointer_device();
+                    self.note_root_origin(
+                        xbutton.window,
+                        xbutton.same_screen,
+                        (xbutton.x, xbutton.y),
+                        (xbutton.x_root, xbutton.y_root),
+                    );
+                    if let (Some(state), Some(bit)) = (self.window_state(xbutton.window), bit) {
+                        let mut state = state.borrow_mut();
+                        let held = state.held_buttons.entry(device).or_default();
+                        if pressed && *held & bit != 0 {
+                            return None;
+                        } else if pressed {
+                            *held |= bit;
+                        } else if *held & bit != 0 {
+                            *held &= !bit;
+                        } else {
+                            return None;
+                        }
+                    }
+                    Some(Event::MouseInput {
+                        window: self.window_id(xbutton.window),
+                        button: xbutton.button,
+                        pressed,
+                        position: PhysicalPosition::new(xbutton.x as f64, xbutton.y as f64),
+                        root_position: PhysicalPosition::new(
+                            xbutton.x_root as f64,
+                            xbutton.y_root as f64,
+                        ),
+                        synthetic: false,
+                        device,
+                    })
+                }
+                xlib::LeaveNotify => {
+                    let xcrossing = xevent.crossing;
+                    if xcrossing.detail != xlib::NotifyInferior {
+                        let device = self.pointer_device();
+                        if let Some(state) = self.window_state(xcrossing.window) {
+                            state.borrow_mut().pointers.remove(&device);
+                        }
+                    }
+                    None
+                }
+                xlib::MotionNotify => {
+                    let xmotion = xevent.motion;
+                    self.conn
+                        .last_event_time
+                        .store(xmotion.time, Ordering::Relaxed);
+                    let (x, y) = (xmotion.x as f64, xmotion.y as f64);
+                    let device = self.pointer_device();
+                    let delta = match self.cursors.get(&device) {
+                        Some(&(window, last_x, last_y)) if window == xmotion.window => {
+                            (x - last_x, y - last_y)
+                        }
+                        _ => (0.0, 0.0),
+                    };
+                    self.cursors.insert(device, (xmotion.window, x, y));
+                    self.note_root_origin(
+                        xmotion.window,
+                        xmotion.same_screen,
+                        (xmotion.x, xmotion.y),
+                        (xmotion.x_root, xmotion.y_root),
+                    );
+                    if let Some(state) = self.window_state(xmotion.window) {
+                        state.borrow_mut().pointers.insert(device, (x, y));
+                    }
+                    Some(Event::CursorMoved {
+                        window: self.window_id(xmotion.window),
+                        position: PhysicalPosition::new(x, y),
+                        root_position: PhysicalPosition::new(
+                            xmotion.x_root as f64,
+                            xmotion.y_root as f64,
+                        ),
+                        delta,
+                        device,
+                    })
+                }
+                _ => None,
+            }
+        }
+    }
+
+    /// Changes of a child window, those of foreign windows embedded with
+    /// X11Window::embed_foreign are followed
+    fn translate_foreign(
+        &mut self,
+        parent: xlib::Window,
+        child: xlib::Window,
+        xevent: &xlib::XEvent,
+    ) -> Option<Event> {
+        let state = self.window_state(parent)?;
+        let mut state = state.borrow_mut();
+        let index = state
+            .foreign
+            .iter()
+            .position(|&(embedded, _)| embedded == child)?;
+        let gone = match xevent.get_type() {
+            xlib::DestroyNotify => true,
+            // Taken away by another client, e.g. a window manager
+            xlib::ReparentNotify => unsafe { xevent.reparent.parent != parent },
+            _ => false,
+        };
+        if !gone {
+            return None;
+        }
+        state.foreign.remove(index);
+        if state.foreign_focus == Some(child) {
+            state.foreign_focus = None;
+        }
+        (xevent.get_type() == xlib::DestroyNotify).then(|| Event::EmbeddedChildDestroyed {
+            window: self.window_id(parent),
+            child,
+        })
+    }
+
+    /// Messages from the socket of an embedded window
+    fn translate_xembed(&mut self, xclient: &xlib::XClientMessageEvent) -> Option<Event> {
+        let window = xclient.window;
+        match xclient.data.get_long(1) {
+            xembed::EMBEDDED_NOTIFY => {
+                let state = self.window_state(window)?;
+                state.borrow_mut().embedder = Some(xclient.data.get_long(3) as xlib::Window);
+                None
+            }
+            message @ (xembed::FOCUS_IN | xembed::FOCUS_OUT) => {
+                let focused = message == xembed::FOCUS_IN;
+                if focused {
+                    self.focused = Some(window);
+                } else if self.focused == Some(window) {
+                    self.focused = None;
+                }
+                if let Some(state) = self.window_state(window) {
+                    state.borrow_mut().focused = focused;
+                }
+                Some(Event::Focused {
+                    window: self.window_id(window),
+                    focused,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    unsafe fn translate_xinput2(&mut self, cookie: &xlib::XGenericEventCookie) -> Option<Event> {
+        if Some(cookie.extension) != self.xinput2_opcode || cookie.data.is_null() {
+            return None;
+        }
+        match cookie.evtype {
+            xinput2::XI_Motion | xinput2::XI_ButtonPress | xinput2::XI_ButtonRelease => {
+                let xevent = &*(cookie.data as *const xinput2::XIDeviceEvent);
+                // Those of master pointers are handled by seat_event
+                if self.is_master_pointer(xevent.deviceid) {
+                    return None;
+                }
+                if !self.tablets.contains_key(&xevent.sourceid) {
+                    return self.translate_scroll_event(cookie.evtype, xevent);
+                }
+                if !self.event_filter.contains(EventFilter::TOUCH) {
+                    return None;
+                }
+                self.translate_tablet_event(cookie.evtype, xevent)
+            }
+            xinput2::XI_Enter => {
+                self.update_scroll_devices();
+                None
+            }
+            xinput2::XI_Leave => None,
+            xinput2::XI_HierarchyChanged => {
+                self.update_devices();
+                None
+            }
+            _ => self.translate_device_event(cookie),
+        }
+    }
+
+    /// The core event standing in for a motion, button or crossing event
+    /// of a master pointer while there are several, along with the
+    /// device
+    unsafe fn seat_event(
+        &self,
+        cookie: &xlib::XGenericEventCookie,
+    ) -> Option<(DeviceId, xlib::XEvent)> {
+        if !self.seats || Some(cookie.extension) != self.xinput2_opcode || cookie.data.is_null() {
+            return None;
+        }
+        let (device, core) = match cookie.evtype {
+            xinput2::XI_Motion | xinput2::XI_ButtonPress | xinput2::XI_ButtonRelease => {
+                let xevent = &*(cookie.data as *const xinput2::XIDeviceEvent);
+                (xevent.deviceid, seat::device_event_to_core(xevent))
+            }
+            xinput2::XI_Enter | xinput2::XI_Leave => {
+                let xevent = &*(cookie.data as *const xinput2::XIEnterEvent);
+                (xevent.deviceid, seat::crossing_to_core(xevent))
+            }
+            _ => return None,
+        };
+        self.is_master_pointer(device)
+            .then_some((DeviceId(device as u32), core))
+    }
+
+    fn is_master_pointer(&self, device: i32) -> bool {
+        self.devices.iter().any(|info| {
+            info.id() == DeviceId(device as u32) && info.device_type() == DeviceType::MasterPointer
+        })
+    }
+
+    /// The master pointer of the event translated right now
+    fn pointer_device(&self) -> DeviceId {
+        self.event_device
+            .unwrap_or_else(|| self.conn.core_pointer())
+    }
+
+    unsafe fn translate_scroll_event(
+        &mut self,
+        evtype: i32,
+        xevent: &xinput2::XIDeviceEvent,
+    ) -> Option<Event> {
+        if evtype != xinput2::XI_Motion || !self.event_filter.contains(EventFilter::POINTER_BUTTON)
+        {
+            return None;
+        }
+        let device = self.scroll_devices.get_mut(&xevent.sourceid)?;
+        let delta = device.update(&xevent.valuators)?;
+        self.smooth_scroll_time = xevent.time;
+        Some(Event::MouseWheel {
+            window: self.window_id(xevent.event),
+            delta,
+        })
+    }
+
+    unsafe fn translate_tablet_event(
+        &mut self,
+        evtype: i32,
+        xevent: &xinput2::XIDeviceEvent,
+    ) -> Option<Event> {
+        let window = self.window_id(xevent.event);
+        let tablet = self.tablets.get_mut(&xevent.sourceid)?;
+        self.conn
+            .last_event_time
+            .store(xevent.time, Ordering::Relaxed);
+        tablet.update(&xevent.valuators);
+        // Button 1 is the tip touching the tablet
+        if xevent.detail == 1 {
+            match evtype {
+                xinput2::XI_ButtonPress => tablet.contact = true,
+                xinput2::XI_ButtonRelease => tablet.contact = false,
+                _ => {}
+            }
+        }
+
+        Some(Event::Tablet {
+            window,
+            device: DeviceId(xevent.sourceid as u32),
+            position: PhysicalPosition::new(xevent.event_x, xevent.event_y),
+            pressure: tablet.pressure,
+            tilt: tablet.tilt,
+            tool: tablet.tool,
+            contact: tablet.contact,
+        })
+    }
+
+    unsafe fn translate_present(&self, cookie: &xlib::XGenericEventCookie) -> Option<Event> {
+        if Some(cookie.extension) != self.conn.present_opcode() || cookie.data.is_null() {
+            return None;
+        }
+        let completion = &*(cookie.data as *const Completion);
+        completion.is_presented().then(|| Event::FramePresented {
+            window: self.window_id(completion.window),
+            msc: completion.msc,
+            ust: completion.ust(),
+        })
+    }
+
+    unsafe fn translate_device_event(&self, cookie: &xlib::XGenericEventCookie) -> Option<Event> {
+        match self.device_events {
+            DeviceEvents::Always => {}
+            DeviceEvents::WhenFocused if self.focused.is_some() => {}
+            _ => return None,
+        }
+
+        let raw = &*(cookie.data as *const xinput2::XIRawEvent);
+        let event = match cookie.evtype {
+            xinput2::XI_RawKeyPress | xinput2::XI_RawKeyRelease => DeviceEvent::Key {
+                keycode: raw.detail as u32,
+                pressed: cookie.evtype == xinput2::XI_RawKeyPress,
+            },
+            xinput2::XI_RawButtonPress | xinput2::XI_RawButtonRelease => DeviceEvent::Button {
+                button: raw.detail as u32,
+                pressed: cookie.evtype == xinput2::XI_RawButtonPress,
+            },
+            xinput2::XI_RawMotion => {
+                // raw_values only holds the axes set in the mask, in order
+                let mask =
+                    slice::from_raw_parts(raw.valuators.mask, raw.valuators.mask_len as usize);
+                let mut value = raw.raw_values;
+                let mut delta = (0.0, 0.0);
+                for axis in 0..2.min(mask.len() as i32 * 8) {
+                    if xinput2::XIMaskIsSet(mask, axis) {
+                        if axis == 0 {
+                            delta.0 = *value;
+                        } else {
+                            delta.1 = *value;
+                        }
+                        value = value.add(1);
+                    }
+                }
+                if delta == (0.0, 0.0) {
+                    return None;
+                }
+                DeviceEvent::Motion { delta }
+            }
+            _ => return None,
+        };
+
+        // sourceid is the physical device, deviceid its master
+        Some(Event::DeviceEvent {
+            device: DeviceId(raw.sourceid as u32),
+            event,
+        })
+    }
+}
+
+/// The major opcode of XInput2, None when the library or the server
+/// does not support version 2.0
+fn query_xinput2(conn: &XConnection) -> Option<i32> {
+    let xinput2 = ext::xinput2()?;
+    unsafe {
+        let (mut opcode, mut event, mut error) = (0, 0, 0);
+        let name = b"XInputExtension\0";
+        if xlib::XQueryExtension(
+            conn.display,
+            name.as_ptr() as *const _,
+            &mut opcode,
+            &mut event,
+            &mut error,
+        ) == 0
+        {
+            return None;
+        }
+
+        let (mut major, mut minor) = (2, 0);
+        if (xinput2.XIQueryVersion)(conn.display, &mut major, &mut minor) != xlib::Success as i32 {
+            return None;
+        }
+        Some(opcode)
+    }
+}
+
+/// Whether the server has several master pointers
+fn has_seats(devices: &[DeviceInfo]) -> bool {
+    let masters = devices
+        .iter()
+        .filter(|device| device.device_type() == DeviceType::MasterPointer);
+    masters.count() > 1
+}
+
+/// Select or deselect raw input events from all master devices
+fn select_raw_events(conn: &XConnection, enabled: bool) {
+    let xinput2 = match ext::xinput2() {
+        Some(xinput2) => xinput2,
+        None => return,
+    };
+    let mut mask = [0u8; (xinput2::XI_RawMotion as usize >> 3) + 1];
+    if enabled {
+        for &event in &[
+            xinput2::XI_RawKeyPress,
+            xinput2::XI_RawKeyRelease,
+            xinput2::XI_RawButtonPress,
+            xinput2::XI_RawButtonRelease,
+            xinput2::XI_RawMotion,
+        ] {
+            xinput2::XISetMask(&mut mask, event);
+        }
+    }
+    let mut event_mask = xinput2::XIEventMask {
+        deviceid: xinput2::XIAllMasterDevices,
+        mask_len: mask.len() as i32,
+        mask: mask.as_mut_ptr(),
+    };
+    unsafe {
+        (xinput2.XISelectEvents)(conn.display, conn.root, &mut event_mask, 1);
+        xlib::XFlush(conn.display);
+    }
+}
+
+/// The child a structure event of a window's children is about, None
+/// for those about the window itself
+fn substructure_child(xevent: &xlib::XEvent) -> Option<xlib::Window> {
+    let (event, window) = unsafe {
+        match xevent.get_type() {
+            xlib::CreateNotify => return Some(xevent.create_window.window),
+            xlib::DestroyNotify => (xevent.destroy_window.event, xevent.destroy_window.window),
+            xlib::UnmapNotify => (xevent.unmap.event, xevent.unmap.window),
+            xlib::MapNotify => (xevent.map.event, xevent.map.window),
+            xlib::ReparentNotify => (xevent.reparent.event, xevent.reparent.window),
+            xlib::ConfigureNotify => (xevent.configure.event, xevent.configure.window),
+            xlib::GravityNotify => (xevent.gravity.event, xevent.gravity.window),
+            xlib::CirculateNotify => (xevent.circulate.event, xevent.circulate.window),
+            _ => return None,
+        }
+    };
+    (event != window).then_some(window)
+}
+
+// Mode of XEventsQueued flushing before reading, missing from the x11 crate
+const QUEUED_AFTER_FLUSH: raw::c_int = 2;
+
+// XInput2 events latched late along with the core input events
+const LATE_XI_EVENTS: [raw::c_int; 10] = [
+    xinput2::XI_KeyPress,
+    xinput2::XI_KeyRelease,
+    xinput2::XI_ButtonPress,
+    xinput2::XI_ButtonRelease,
+    xinput2::XI_Motion,
+    xinput2::XI_RawKeyPress,
+    xinput2::XI_RawKeyRelease,
+    xinput2::XI_RawButtonPress,
+    xinput2::XI_RawButtonRelease,
+    xinput2::XI_RawMotion,
+];
+
+/// XCheckIfEvent predicate taking the input latched by poll_late_input
+unsafe extern "C" fn late_input(
+    _display: *mut xlib::Display,
+    xevent: *mut xlib::XEvent,
+    event_loop: xlib::XPointer,
+) -> xlib::Bool {
+    (*(event_loop as *const EventLoop)).is_late_input(&*xevent) as xlib::Bool
+}
+
+/// XCheckIfEvent predicate taking the events EventLoop::claims
+unsafe extern "C" fn claimed(
+    _display: *mut xlib::Display,
+    xevent: *mut xlib::XEvent,
+    event_loop: xlib::XPointer,
+) -> xlib::Bool {
+    (*(event_loop as *const EventLoop)).claims(&*xevent) as xlib::Bool
+}
+
+/// Whether input stops reaching the window: focus moved elsewhere or
+/// another client grabbed the keyboard. The pointer root focus model
+/// reports the pointer's window, which never had the keys
+fn lost_focus(xfocus: &xlib::XFocusChangeEvent) -> bool {
+    xfocus.detail != xlib::NotifyPointer
+        && matches!(xfocus.mode, xlib::NotifyNormal | xlib::NotifyGrab)
+}
+
+/// Keys and buttons whose press was dropped for a modal window. Their
+/// releases are dropped too, so the application sees both or neither
+#[derive(Default)]
+struct BlockedPresses(HashSet<(WindowId, Held)>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Held {
+    Key(u32),
+    Button(u32),
+}
+
+impl BlockedPresses {
+    /// Remember a dropped press. Repeats of a key whose first press was
+    /// delivered are not remembered, its release belongs to that press
+    fn press(&mut self, event: &Event) {
+        if matches!(event, Event::KeyboardInput { repeat: true, .. }) {
+            return;
+        }
+        if let Some(held) = Held::of(event) {
+            self.0.insert(held);
+        }
+    }
+
+    /// Whether `event` releases a press that was dropped
+    fn release(&mut self, event: &Event) -> bool {
+        Held::of(event).is_some_and(|held| self.0.remove(&held))
+    }
+}
+
+impl Held {
+    /// The key or button an input event presses or releases
+    fn of(event: &Event) -> Option<(WindowId, Held)> {
+        match *event {
+            Event::KeyboardInput {
+                window, keycode, ..
+            } => Some((window, Held::Key(keycode))),
+            Event::MouseInput { window, button, .. } => Some((window, Held::Button(button))),
+            _ => None,
+        }
+    }
+}
+
+/// Queue `event` within `limit`, returning how many events were dropped.
+/// DropNewest turns non-critical events away once the queue is full.
+/// DropOldest lets the queue run to twice its capacity, then drops the
+/// oldest non-critical events in one pass, see trim_oldest
+fn enqueue(events: &mut Vec<Event>, event: Event, limit: Option<(QueuePolicy, usize)>) -> u32 {
+    let mut dropped = 0;
+    match limit {
+        Some((QueuePolicy::DropNewest, capacity))
+            if events.len() >= capacity && !is_critical(&event) =>
+        {
+            return 1;
+        }
+        Some((QueuePolicy::DropOldest, capacity)) if events.len() >= capacity.saturating_mul(2) => {
+            dropped = trim_oldest(events, capacity);
+        }
+        _ => {}
+    }
+    events.push(event);
+    dropped
+}
+
+/// Drop the oldest non-critical events until at most `capacity` are
+/// left or only critical ones, returning how many were dropped
+fn trim_oldest(events: &mut Vec<Event>, capacity: usize) -> u32 {
+    let mut excess = events.len().saturating_sub(capacity);
+    let before = events.len();
+    events.retain(|event| {
+        let drop = excess > 0 && !is_critical(event);
+        excess -= drop as usize;
+        !drop
+    });
+    (before - events.len()) as u32
+}
+
+/// Events which are delivered even when the queue is full. Releases of
+/// keys and buttons are, so a delivered press is never left held
+fn is_critical(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::CloseRequested { .. }
+            | Event::WindowCreated { .. }
+            | Event::Destroyed { .. }
+            | Event::EmbeddedChildDestroyed { .. }
+            | Event::ExitRequested
+            | Event::MonitorConfigurationChanged
+            | Event::WorkAreaChanged
+            | Event::DeviceAdded { .. }
+            | Event::DeviceRemoved { .. }
+            | Event::TrayIconClicked { .. }
+            | Event::TrayMenuItem { .. }
+            | Event::Suspending
+            | Event::Resumed
+            | Event::Focused { .. }
+            | Event::Resized { .. }
+            | Event::StateChanged { .. }
+            | Event::MonitorChanged { .. }
+            | Event::CanvasViewportChanged { .. }
+            | Event::Channel(_)
+            | Event::LateInput
+            | Event::RedrawRequested { .. }
+            | Event::AboutToWait
+            | Event::ConnectionLost
+            | Event::KeyboardInput { pressed: false, .. }
+            | Event::MouseInput { pressed: false, .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moved(window: WindowId, x: f64) -> Event {
+        Event::CursorMoved {
+            window,
+            position: PhysicalPosition::new(x, 0.0),
+            root_position: PhysicalPosition::new(x, 0.0),
+            delta: (1.0, 0.0),
+            device: DeviceId(2),
+        }
+    }
+
+    // 100 motions with a close request, a resize and a destruction in
+    // between, queued under `limit` as a poll would
+    fn overflow(limit: (QueuePolicy, usize)) -> (Vec<Event>, u32) {
+        let window = WindowId::new(0x40_0001, 0);
+        let critical = [
+            (10, Event::CloseRequested { window }),
+            (
+                50,
+                Event::Resized {
+                    window,
+                    size: PhysicalSize::new(800, 600),
+                },
+            ),
+            (90, Event::Destroyed { window }),
+        ];
+        let mut events = Vec::new();
+        let mut lost = 0;
+        for i in 0..100 {
+            if let Some(&(_, event)) = critical.iter().find(|(at, _)| *at == i) {
+                lost += enqueue(&mut events, event, Some(limit));
+            }
+            lost += enqueue(&mut events, moved(window, i as f64), Some(limit));
+        }
+        if limit.0 == QueuePolicy::DropOldest {
+            lost += trim_oldest(&mut events, limit.1);
+        }
+        (events, lost)
+    }
+
+    fn survivors(events: &[Event]) -> Vec<&'static str> {
+        events
+            .iter()
+            .filter(|event| is_critical(event))
+            .map(|event| match event {
+                Event::CloseRequested { .. } => "close",
+                Event::Resized { .. } => "resize",
+                Event::Destroyed { .. } => "destroy",
+                _ => "other",
+            })
+            .collect()
+    }
+
+    fn positions(events: &[Event]) -> Vec<f64> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                Event::CursorMoved { position, .. } => Some(position.x),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn drop_oldest_keeps_critical_events() {
+        let (events, lost) = overflow((QueuePolicy::DropOldest, 8));
+        assert_eq!(events.len(), 8);
+        assert_eq!(survivors(&events), ["close", "resize", "destroy"]);
+        assert_eq!(lost as usize, 103 - events.len());
+        // The newest motion is kept, in order
+        assert_eq!(positions(&events), [95.0, 96.0, 97.0, 98.0, 99.0]);
+    }
+
+    #[test]
+    fn drop_newest_keeps_critical_events() {
+        let (events, lost) = overflow((QueuePolicy::DropNewest, 8));
+        assert_eq!(survivors(&events), ["close", "resize", "destroy"]);
+        assert_eq!(events.len(), 8 + 3);
+        assert_eq!(lost as usize, 103 - events.len());
+        // The oldest motion is kept, critical events go past the limit
+        assert_eq!(positions(&events), [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+    }
+
+    #[test]
+    fn releases_are_never_dropped() {
+        let window = WindowId::new(0x40_0001, 0);
+        let key = |keycode, pressed| Event::KeyboardInput {
+            window,
+            keycode,
+            keysym: 0,
+            pressed,
+            synthetic: false,
+            repeat: false,
+        };
+        let button = |button, pressed| Event::MouseInput {
+            window,
+            button,
+            pressed,
+            position: PhysicalPosition::new(0.0, 0.0),
+            root_position: PhysicalPosition::new(0.0, 0.0),
+            synthetic: false,
+            device: DeviceId(2),
+        };
+        for &policy in &[QueuePolicy::DropOldest, QueuePolicy::DropNewest] {
+            let mut events = Vec::new();
+            for i in 0..40 {
+                enqueue(&mut events, key(i, true), Some((policy, 8)));
+                enqueue(&mut events, button(i, true), Some((policy, 8)));
+                enqueue(&mut events, moved(window, i as f64), Some((policy, 8)));
+                enqueue(&mut events, key(i, false), Some((policy, 8)));
+                enqueue(&mut events, button(i, false), Some((policy, 8)));
+            }
+            trim_oldest(&mut events, 8);
+            let released = |event: &Event| match *event {
+                Event::KeyboardInput {
+                    pressed: false,
+                    keycode,
+                    ..
+                } => Some(Held::Key(keycode)),
+                Event::MouseInput {
+                    pressed: false,
+                    button,
+                    ..
+                } => Some(Held::Button(button)),
+                _ => None,
+            };
+            assert_eq!(events.iter().filter_map(released).count(), 80);
+            // Every press delivered is followed by its release
+            for (i, event) in events.iter().enumerate() {
+                if let Some((_, held)) = Held::of(event).filter(|_| released(event).is_none()) {
+                    assert!(events[i..]
+                        .iter()
+                        .any(|later| released(later) == Some(held)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn only_critical_events_overflow() {
+        let window = WindowId::new(0x40_0001, 0);
+        for &policy in &[QueuePolicy::DropOldest, QueuePolicy::DropNewest] {
+            let mut events = Vec::new();
+            let mut lost = 0;
+            for _ in 0..20 {
+                lost += enqueue(
+                    &mut events,
+                    Event::CloseRequested { window },
+                    Some((policy, 4)),
+                );
+            }
+            lost += trim_oldest(&mut events, 4);
+            assert_eq!((events.len(), lost), (20, 0));
+        }
+    }
+
+    #[test]
+    fn unlimited_keeps_everything() {
+        let window = WindowId::new(0x40_0001, 0);
+        let mut events = Vec::new();
+        for i in 0..1000 {
+            assert_eq!(enqueue(&mut events, moved(window, i as f64), None), 0);
+        }
+        assert_eq!(events.len(), 1000);
+    }
+
+    fn key(window: WindowId, pressed: bool, repeat: bool) -> Event {
+        Event::KeyboardInput {
+            window,
+            keycode: 38,
+            keysym: 0x61,
+            pressed,
+            synthetic: false,
+            repeat,
+        }
+    }
+
+    fn click(window: WindowId, button: u32, pressed: bool) -> Event {
+        Event::MouseInput {
+            window,
+            button,
+            pressed,
+            position: PhysicalPosition::new(0.0, 0.0),
+            root_position: PhysicalPosition::new(0.0, 0.0),
+            synthetic: false,
+            device: DeviceId(2),
+        }
+    }
+
+    #[test]
+    fn releases_of_dropped_presses_are_dropped() {
+        let window = WindowId::new(0x40_0001, 0);
+        let mut blocked = BlockedPresses::default();
+        blocked.press(&key(window, true, false));
+        blocked.press(&click(window, 1, true));
+        // The modal window closed in between, the releases still go
+        assert!(blocked.release(&key(window, false, false)));
+        assert!(blocked.release(&click(window, 1, false)));
+        assert!(!blocked.release(&key(window, false, false)));
+        assert!(!blocked.release(&click(window, 1, false)));
+    }
+
+    #[test]
+    fn releases_of_delivered_presses_are_kept() {
+        let window = WindowId::new(0x40_0001, 0);
+        let other = WindowId::new(0x40_0001, 1);
+        let mut blocked = BlockedPresses::default();
+        // Held since before the modal window opened
+        blocked.press(&key(window, true, true));
+        assert!(!blocked.release(&key(window, false, false)));
+        blocked.press(&click(window, 1, true));
+        assert!(!blocked.release(&click(window, 3, false)));
+        assert!(!blocked.release(&click(other, 1, false)));
+        assert!(blocked.release(&click(window, 1, false)));
+    }
+
+    #[test]
+    fn repeats_of_dropped_presses_keep_their_release_dropped() {
+        let window = WindowId::new(0x40_0001, 0);
+        let mut blocked = BlockedPresses::default();
+        blocked.press(&key(window, true, false));
+        blocked.press(&key(window, true, true));
+        blocked.press(&key(window, true, true));
+        assert!(blocked.release(&key(window, false, false)));
+    }
+}
@@ -0,0 +1,213 @@
+//! X extension libraries loaded at runtime, so a missing library only
+//! disables the features built on it instead of the whole crate
+
+use std::os::raw::{c_char, c_double, c_int, c_long, c_uchar, c_uint, c_ulong};
+use std::sync::OnceLock;
+
+use libloading::Library;
+use x11::sync::{XSyncCounter, XSyncValue};
+use x11::xinput2::{XIButtonState, XIDeviceInfo, XIEventMask, XIModifierState};
+use x11::xlib::{
+    Atom, Bool, Cursor, Display, Pixmap, Status, Time, Window, XEvent, XRectangle, XID,
+};
+use x11::xrandr::{
+    RRCrtc, RRMode, RROutput, Rotation, XRRCrtcInfo, XRROutputInfo, XRRScreenResources,
+};
+
+macro_rules! dynamic_library {
+    (
+        $(#[$meta:meta])*
+        $name:ident, $getter:ident, [$($lib:expr),+],
+        { $(fn $func:ident($($arg:ty),*) -> $ret:ty;)* }
+    ) => {
+        $(#[$meta])*
+        #[allow(non_snake_case)]
+        pub(crate) struct $name {
+            $(pub $func: unsafe extern "C" fn($($arg),*) -> $ret,)*
+            _library: Library,
+        }
+
+        impl $name {
+            unsafe fn load() -> Option<$name> {
+                let library = [$($lib),+].iter().find_map(|name| Library::new(name).ok())?;
+                Some($name {
+                    $($func: *library.get(concat!(stringify!($func), "\0").as_bytes()).ok()?,)*
+                    _library: library,
+                })
+            }
+        }
+
+        /// The loaded library, None when it is not installed
+        pub(crate) fn $getter() -> Option<&'static $name> {
+            static LIBRARY: OnceLock<Option<$name>> = OnceLock::new();
+            LIBRARY.get_or_init(|| unsafe { $name::load() }).as_ref()
+        }
+    };
+}
+
+dynamic_library! {
+    /// libXrandr, for monitor enumeration and mode switching
+    Xrandr, xrandr, ["libXrandr.so.2", "libXrandr.so"],
+    {
+        fn XRRQueryExtension(*mut Display, *mut c_int, *mut c_int) -> Bool;
+        fn XRRGetScreenResourcesCurrent(*mut Display, Window) -> *mut XRRScreenResources;
+        fn XRRFreeScreenResources(*mut XRRScreenResources) -> ();
+        fn XRRGetOutputInfo(*mut Display, *mut XRRScreenResources, RROutput) -> *mut XRROutputInfo;
+        fn XRRFreeOutputInfo(*mut XRROutputInfo) -> ();
+        fn XRRGetCrtcInfo(*mut Display, *mut XRRScreenResources, RRCrtc) -> *mut XRRCrtcInfo;
+        fn XRRFreeCrtcInfo(*mut XRRCrtcInfo) -> ();
+        fn XRRGetOutputPrimary(*mut Display, Window) -> RROutput;
+        fn XRRSelectInput(*mut Display, Window, c_int) -> ();
+        fn XRRUpdateConfiguration(*mut XEvent) -> c_int;
+        fn XRRGetOutputProperty(
+            *mut Display,
+            RROutput,
+            Atom,
+            c_long,
+            c_long,
+            Bool,
+            Bool,
+            Atom,
+            *mut Atom,
+            *mut c_int,
+            *mut c_ulong,
+            *mut c_ulong,
+            *mut *mut c_uchar
+        ) -> c_int;
+        fn XRRSetCrtcConfig(
+            *mut Display,
+            *mut XRRScreenResources,
+            RRCrtc,
+            Time,
+            c_int,
+            c_int,
+            RRMode,
+            Rotation,
+            *mut RROutput,
+            c_int
+        ) -> Status;
+    }
+}
+
+dynamic_library! {
+    /// libXi, for raw input events from XInput2
+    XInput2, xinput2, ["libXi.so.6", "libXi.so"],
+    {
+        fn XIQueryVersion(*mut Display, *mut c_int, *mut c_int) -> Status;
+        fn XISelectEvents(*mut Display, Window, *mut XIEventMask, c_int) -> c_int;
+        fn XIQueryDevice(*mut Display, c_int, *mut c_int) -> *mut XIDeviceInfo;
+        fn XIFreeDeviceInfo(*mut XIDeviceInfo) -> ();
+        fn XIGetClientPointer(*mut Display, Window, *mut c_int) -> Bool;
+        fn XIQueryPointer(
+            *mut Display,
+            c_int,
+            Window,
+            *mut Window,
+            *mut Window,
+            *mut c_double,
+            *mut c_double,
+            *mut c_double,
+            *mut c_double,
+            *mut XIButtonState,
+            *mut XIModifierState,
+            *mut XIModifierState
+        ) -> Bool;
+    }
+}
+
+// Shape kinds and operations from X11/extensions/shape.h
+pub(crate) const SHAPE_BOUNDING: c_int = 0;
+pub(crate) const SHAPE_INPUT: c_int = 2;
+pub(crate) const SHAPE_SET: c_int = 0;
+
+dynamic_library! {
+    /// libXext, for the SHAPE and SYNC extensions
+    Xext, xext, ["libXext.so.6", "libXext.so"],
+    {
+        fn XShapeQueryExtension(*mut Display, *mut c_int, *mut c_int) -> Bool;
+        fn XShapeQueryVersion(*mut Display, *mut c_int, *mut c_int) -> Status;
+        fn XShapeCombineRectangles(
+            *mut Display,
+            Window,
+            c_int,
+            c_int,
+            c_int,
+            *mut XRectangle,
+            c_int,
+            c_int,
+            c_int
+        ) -> ();
+        fn XShapeCombineMask(*mut Display, Window, c_int, c_int, c_int, Pixmap, c_int) -> ();
+        fn XSyncQueryExtension(*mut Display, *mut c_int, *mut c_int) -> Bool;
+        fn XSyncInitialize(*mut Display, *mut c_int, *mut c_int) -> Status;
+        fn XSyncCreateCounter(*mut Display, XSyncValue) -> XSyncCounter;
+        fn XSyncSetCounter(*mut Display, XSyncCounter, XSyncValue) -> Status;
+        fn XSyncDestroyCounter(*mut Display, XSyncCounter) -> Status;
+    }
+}
+
+// Directions a pointer barrier lets the pointer through, from
+// X11/extensions/Xfixes.h
+pub(crate) const BARRIER_POSITIVE_X: c_int = 1 << 0;
+pub(crate) const BARRIER_POSITIVE_Y: c_int = 1 << 1;
+pub(crate) const BARRIER_NEGATIVE_X: c_int = 1 << 2;
+pub(crate) const BARRIER_NEGATIVE_Y: c_int = 1 << 3;
+
+dynamic_library! {
+    /// libXfixes, for pointer barriers
+    Xfixes, xfixes, ["libXfixes.so.3", "libXfixes.so"],
+    {
+        fn XFixesQueryExtension(*mut Display, *mut c_int, *mut c_int) -> Bool;
+        fn XFixesQueryVersion(*mut Display, *mut c_int, *mut c_int) -> Status;
+        fn XFixesCreatePointerBarrier(
+            *mut Display,
+            Window,
+            c_int,
+            c_int,
+            c_int,
+            c_int,
+            c_int,
+            c_int,
+            *mut c_int
+        ) -> XID;
+        fn XFixesDestroyPointerBarrier(*mut Display, XID) -> ();
+    }
+}
+
+dynamic_library! {
+    /// libXcursor, for cursors of the user's cursor theme
+    Xcursor, xcursor, ["libXcursor.so.1", "libXcursor.so"],
+    {
+        fn XcursorLibraryLoadCursor(*mut Display, *const c_char) -> Cursor;
+    }
+}
+
+/// An xcb connection, only passed on to xcb functions
+pub(crate) enum XcbConnection {}
+
+/// The sequence number of a request without reply
+#[repr(C)]
+pub(crate) struct XcbVoidCookie {
+    pub sequence: c_uint,
+}
+
+dynamic_library! {
+    /// libX11-xcb, for the xcb connection under an Xlib display
+    X11Xcb, x11_xcb, ["libX11-xcb.so.1", "libX11-xcb.so"],
+    {
+        fn XGetXCBConnection(*mut Display) -> *mut XcbConnection;
+    }
+}
+
+// Event masks of the Present extension, from xcb/present.h
+pub(crate) const PRESENT_COMPLETE_NOTIFY_MASK: u32 = 1 << 1;
+
+dynamic_library! {
+    /// libxcb-present, for Present extension requests Xlib has no
+    /// binding for
+    XcbPresent, xcb_present, ["libxcb-present.so.0", "libxcb-present.so"],
+    {
+        fn xcb_generate_id(*mut XcbConnection) -> u32;
+        fn xcb_present_select_input(*mut XcbConnection, u32, u32, u32) -> XcbVoidCookie;
+    }
+}
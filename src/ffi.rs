@@ -0,0 +1,1014 @@
+//! C interface for engines bringing their own renderer, declared in
+//! include/daswin.h.
+//!
+//! Each DaswinWindow owns its own EventLoop. Panics are caught at the
+//! boundary and reported as DASWIN_PANIC or a null pointer
+
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_ulong, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::time::Duration;
+
+use crate::event::{DeviceEvent, Event, ScrollDelta, TabletTool};
+use crate::event_loop::{EventLoop, EventLoopBuilder};
+use crate::window::X11Window;
+
+pub const DASWIN_OK: c_int = 0;
+pub const DASWIN_NULL_ARGUMENT: c_int = -1;
+pub const DASWIN_PANIC: c_int = -2;
+pub const DASWIN_NO_STRING: c_int = -3;
+
+/// A window and the event loop delivering its events
+pub struct DaswinWindow {
+    // Dropped before the event loop sharing its connection
+    window: X11Window,
+    event_loop: EventLoop,
+    events: Vec<Event>,
+    // Only refilled once empty, so the indices of its Text, GpuError and
+    // ScriptError events stay those of the event loop's last poll
+    pending: VecDeque<Event>,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DaswinEventTag {
+    CloseRequested = 0,
+    Resized = 1,
+    Focused = 2,
+    KeyboardInput = 3,
+    MouseInput = 4,
+    CursorMoved = 5,
+    DeviceEvent = 6,
+    Tablet = 7,
+    ExitRequested = 8,
+    EventsLost = 9,
+    FrameDeadlineMissed = 10,
+    MonitorConfigurationChanged = 11,
+    GpuError = 12,
+    Suspending = 13,
+    Resumed = 14,
+    WindowCreated = 15,
+    Destroyed = 16,
+    Text = 17,
+    StateChanged = 18,
+    MouseWheel = 19,
+    RedrawRequested = 20,
+    AboutToWait = 21,
+    MonitorChanged = 22,
+    ConnectionLost = 23,
+    DeviceAdded = 24,
+    DeviceRemoved = 25,
+    TrayIconClicked = 26,
+    TrayMenuItem = 27,
+    FramePresented = 28,
+    CanvasViewportChanged = 29,
+    Channel = 30,
+    Snapped = 31,
+    EmbeddedChildDestroyed = 32,
+    GlobalHotkey = 33,
+    WorkAreaChanged = 34,
+    LateInput = 35,
+    ScriptError = 36,
+    GpuReadbackReady = 37,
+    ModalBlocked = 38,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinCloseRequested {
+    pub window: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinWindowCreated {
+    pub window: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinDestroyed {
+    pub window: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinRedrawRequested {
+    pub window: u64,
+}
+
+/// The string is read with daswin_event_text
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinText {
+    pub window: u64,
+    pub index: u32,
+}
+
+/// `pixels` tells ScrollDelta::Pixels from ScrollDelta::Lines
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinMouseWheel {
+    pub window: u64,
+    pub pixels: bool,
+    pub delta_x: f64,
+    pub delta_y: f64,
+}
+
+/// `change` is the StateChange in declaration order, 0 for Maximized
+/// up to 4 for AttentionCleared
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinStateChanged {
+    pub window: u64,
+    pub change: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinMonitorChanged {
+    pub window: u64,
+    pub monitor: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinResized {
+    pub window: u64,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinFocused {
+    pub window: u64,
+    pub focused: bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinKeyboardInput {
+    pub window: u64,
+    pub keycode: u32,
+    pub keysym: c_ulong,
+    pub pressed: bool,
+    pub synthetic: bool,
+    pub repeat: bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinMouseInput {
+    pub window: u64,
+    pub button: u32,
+    pub pressed: bool,
+    pub synthetic: bool,
+    pub x: f64,
+    pub y: f64,
+    pub root_x: f64,
+    pub root_y: f64,
+    pub device: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinCursorMoved {
+    pub window: u64,
+    pub x: f64,
+    pub y: f64,
+    pub root_x: f64,
+    pub root_y: f64,
+    pub delta_x: f64,
+    pub delta_y: f64,
+    pub device: u32,
+}
+
+/// Raw device input, `kind` is 0 for keys, 1 for buttons and 2 for
+/// motion. `code` is the keycode or button number
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinDeviceEvent {
+    pub device: u32,
+    pub kind: u32,
+    pub code: u32,
+    pub pressed: bool,
+    pub delta_x: f64,
+    pub delta_y: f64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinTablet {
+    pub window: u64,
+    pub device: u32,
+    pub x: f64,
+    pub y: f64,
+    pub pressure: f32,
+    pub tilt_x: f32,
+    pub tilt_y: f32,
+    pub eraser: bool,
+    pub contact: bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinTrayIconClicked {
+    pub secondary: bool,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// `ust_ns` is on the monotonic clock
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinFramePresented {
+    pub window: u64,
+    pub msc: u64,
+    pub ust_ns: u64,
+}
+
+/// `edge` is the Edge in declaration order, 0 for Left up to 3 for
+/// Bottom
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinSnapped {
+    pub window: u64,
+    pub edge: u32,
+}
+
+/// `child` is the X id of the foreign window
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinEmbeddedChildDestroyed {
+    pub window: u64,
+    pub child: u64,
+}
+
+/// `modal` is the window id of the modal window
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinModalBlocked {
+    pub window: u64,
+    pub modal: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinCanvasViewportChanged {
+    pub window: u64,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Durations in nanoseconds
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinFrameDeadlineMissed {
+    pub elapsed_ns: u64,
+    pub budget_ns: u64,
+    pub translation_ns: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union DaswinEventData {
+    pub close_requested: DaswinCloseRequested,
+    pub resized: DaswinResized,
+    pub focused: DaswinFocused,
+    pub keyboard_input: DaswinKeyboardInput,
+    pub mouse_input: DaswinMouseInput,
+    pub cursor_moved: DaswinCursorMoved,
+    pub device_event: DaswinDeviceEvent,
+    pub tablet: DaswinTablet,
+    pub events_lost: u32,
+    pub frame_deadline_missed: DaswinFrameDeadlineMissed,
+    pub gpu_error: u32,
+    pub device: u32,
+    pub window_created: DaswinWindowCreated,
+    pub destroyed: DaswinDestroyed,
+    pub text: DaswinText,
+    pub state_changed: DaswinStateChanged,
+    pub mouse_wheel: DaswinMouseWheel,
+    pub redraw_requested: DaswinRedrawRequested,
+    pub monitor_changed: DaswinMonitorChanged,
+    pub tray_icon_clicked: DaswinTrayIconClicked,
+    pub tray_menu_item: u32,
+    pub frame_presented: DaswinFramePresented,
+    pub canvas_viewport_changed: DaswinCanvasViewportChanged,
+    pub channel: u32,
+    pub snapped: DaswinSnapped,
+    pub embedded_child_destroyed: DaswinEmbeddedChildDestroyed,
+    pub global_hotkey: u32,
+    pub script_error: u32,
+    pub gpu_readback_ready: u32,
+    pub modal_blocked: DaswinModalBlocked,
+}
+
+/// An Event, `data` holds the member named after the tag. ExitRequested,
+/// MonitorConfigurationChanged, WorkAreaChanged, Suspending, Resumed,
+/// LateInput, AboutToWait and ConnectionLost carry no data
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DaswinEvent {
+    pub tag: DaswinEventTag,
+    pub data: DaswinEventData,
+}
+
+impl From<Event> for DaswinEvent {
+    fn from(event: Event) -> DaswinEvent {
+        let (tag, data) = match event {
+            Event::CloseRequested { window } => (
+                DaswinEventTag::CloseRequested,
+                DaswinEventData {
+                    close_requested: DaswinCloseRequested {
+                        window: window.into_raw(),
+                    },
+                },
+            ),
+            Event::Resized { window, size } => (
+                DaswinEventTag::Resized,
+                DaswinEventData {
+                    resized: DaswinResized {
+                        window: window.into_raw(),
+                        width: size.width,
+                        height: size.height,
+                    },
+                },
+            ),
+            Event::Focused { window, focused } => (
+                DaswinEventTag::Focused,
+                DaswinEventData {
+                    focused: DaswinFocused {
+                        window: window.into_raw(),
+                        focused,
+                    },
+                },
+            ),
+            Event::KeyboardInput {
+                window,
+                keycode,
+                keysym,
+                pressed,
+                synthetic,
+                repeat,
+            } => (
+                DaswinEventTag::KeyboardInput,
+                DaswinEventData {
+                    keyboard_input: DaswinKeyboardInput {
+                        window: window.into_raw(),
+                        keycode,
+                        keysym,
+                        pressed,
+                        synthetic,
+                        repeat,
+                    },
+                },
+            ),
+            Event::MouseInput {
+                window,
+                button,
+                pressed,
+                position,
+                root_position,
+                synthetic,
+                device,
+            } => (
+                DaswinEventTag::MouseInput,
+                DaswinEventData {
+                    mouse_input: DaswinMouseInput {
+                        window: window.into_raw(),
+                        button,
+                        pressed,
+                        synthetic,
+                        x: position.x,
+                        y: position.y,
+                        root_x: root_position.x,
+                        root_y: root_position.y,
+                        device: device.0,
+                    },
+                },
+            ),
+            Event::CursorMoved {
+                window,
+                position,
+                root_position,
+                delta,
+                device,
+            } => (
+                DaswinEventTag::CursorMoved,
+                DaswinEventData {
+                    cursor_moved: DaswinCursorMoved {
+                        window: window.into_raw(),
+                        x: position.x,
+                        y: position.y,
+                        root_x: root_position.x,
+                        root_y: root_position.y,
+                        delta_x: delta.0,
+                        delta_y: delta.1,
+                        device: device.0,
+                    },
+                },
+            ),
+            Event::DeviceEvent { device, event } => {
+                let (kind, code, pressed, delta) = match event {
+                    DeviceEvent::Key { keycode, pressed } => (0, keycode, pressed, (0.0, 0.0)),
+                    DeviceEvent::Button { button, pressed } => (1, button, pressed, (0.0, 0.0)),
+                    DeviceEvent::Motion { delta } => (2, 0, false, delta),
+                };
+                (
+                    DaswinEventTag::DeviceEvent,
+                    DaswinEventData {
+                        device_event: DaswinDeviceEvent {
+                            device: device.into_raw(),
+                            kind,
+                            code,
+                            pressed,
+                            delta_x: delta.0,
+                            delta_y: delta.1,
+                        },
+                    },
+                )
+            }
+            Event::Tablet {
+                window,
+                device,
+                position,
+                pressure,
+                tilt,
+                tool,
+                contact,
+            } => (
+                DaswinEventTag::Tablet,
+                DaswinEventData {
+                    tablet: DaswinTablet {
+                        window: window.into_raw(),
+                        device: device.into_raw(),
+                        x: position.x,
+                        y: position.y,
+                        pressure,
+                        tilt_x: tilt.0,
+                        tilt_y: tilt.1,
+                        eraser: tool == TabletTool::Eraser,
+                        contact,
+                    },
+                },
+            ),
+            Event::ExitRequested => (
+                DaswinEventTag::ExitRequested,
+                DaswinEventData { events_lost: 0 },
+            ),
+            Event::EventsLost(count) => (
+                DaswinEventTag::EventsLost,
+                DaswinEventData { events_lost: count },
+            ),
+            Event::RedrawRequested { window } => (
+                DaswinEventTag::RedrawRequested,
+                DaswinEventData {
+                    redraw_requested: DaswinRedrawRequested {
+                        window: window.into_raw(),
+                    },
+                },
+            ),
+            Event::AboutToWait => (
+                DaswinEventTag::AboutToWait,
+                DaswinEventData { events_lost: 0 },
+            ),
+            Event::ConnectionLost => (
+                DaswinEventTag::ConnectionLost,
+                DaswinEventData { events_lost: 0 },
+            ),
+            Event::MonitorConfigurationChanged => (
+                DaswinEventTag::MonitorConfigurationChanged,
+                DaswinEventData { events_lost: 0 },
+            ),
+            Event::WorkAreaChanged => (
+                DaswinEventTag::WorkAreaChanged,
+                DaswinEventData { events_lost: 0 },
+            ),
+            Event::LateInput => (
+                DaswinEventTag::LateInput,
+                DaswinEventData { events_lost: 0 },
+            ),
+            Event::FrameDeadlineMissed {
+                elapsed,
+                budget,
+                translation,
+            } => (
+                DaswinEventTag::FrameDeadlineMissed,
+                DaswinEventData {
+                    frame_deadline_missed: DaswinFrameDeadlineMissed {
+                        elapsed_ns: elapsed.as_nanos() as u64,
+                        budget_ns: budget.as_nanos() as u64,
+                        translation_ns: translation.as_nanos() as u64,
+                    },
+                },
+            ),
+            Event::GpuError { index } => (
+                DaswinEventTag::GpuError,
+                DaswinEventData { gpu_error: index },
+            ),
+            Event::DeviceAdded { device } => (
+                DaswinEventTag::DeviceAdded,
+                DaswinEventData {
+                    device: device.into_raw(),
+                },
+            ),
+            Event::DeviceRemoved { device } => (
+                DaswinEventTag::DeviceRemoved,
+                DaswinEventData {
+                    device: device.into_raw(),
+                },
+            ),
+            Event::TrayIconClicked {
+                secondary,
+                position,
+            } => (
+                DaswinEventTag::TrayIconClicked,
+                DaswinEventData {
+                    tray_icon_clicked: DaswinTrayIconClicked {
+                        secondary,
+                        x: position.x,
+                        y: position.y,
+                    },
+                },
+            ),
+            Event::TrayMenuItem { id } => (
+                DaswinEventTag::TrayMenuItem,
+                DaswinEventData { tray_menu_item: id },
+            ),
+            Event::FramePresented { window, msc, ust } => (
+                DaswinEventTag::FramePresented,
+                DaswinEventData {
+                    frame_presented: DaswinFramePresented {
+                        window: window.into_raw(),
+                        msc,
+                        ust_ns: ust.as_nanos() as u64,
+                    },
+                },
+            ),
+            Event::CanvasViewportChanged { window, viewport } => (
+                DaswinEventTag::CanvasViewportChanged,
+                DaswinEventData {
+                    canvas_viewport_changed: DaswinCanvasViewportChanged {
+                        window: window.into_raw(),
+                        x: viewport.x,
+                        y: viewport.y,
+                        width: viewport.width,
+                        height: viewport.height,
+                    },
+                },
+            ),
+            Event::Snapped { window, edge } => (
+                DaswinEventTag::Snapped,
+                DaswinEventData {
+                    snapped: DaswinSnapped {
+                        window: window.into_raw(),
+                        edge: edge as u32,
+                    },
+                },
+            ),
+            Event::EmbeddedChildDestroyed { window, child } => (
+                DaswinEventTag::EmbeddedChildDestroyed,
+                DaswinEventData {
+                    embedded_child_destroyed: DaswinEmbeddedChildDestroyed {
+                        window: window.into_raw(),
+                        child,
+                    },
+                },
+            ),
+            Event::Channel(channel) => (
+                DaswinEventTag::Channel,
+                DaswinEventData {
+                    channel: channel.into_raw(),
+                },
+            ),
+            Event::GlobalHotkey(hotkey) => (
+                DaswinEventTag::GlobalHotkey,
+                DaswinEventData {
+                    global_hotkey: hotkey.into_raw(),
+                },
+            ),
+            Event::ScriptError { index } => (
+                DaswinEventTag::ScriptError,
+                DaswinEventData {
+                    script_error: index,
+                },
+            ),
+            Event::GpuReadbackReady(readback) => (
+                DaswinEventTag::GpuReadbackReady,
+                DaswinEventData {
+                    gpu_readback_ready: readback.into_raw(),
+                },
+            ),
+            Event::ModalBlocked { window, modal } => (
+                DaswinEventTag::ModalBlocked,
+                DaswinEventData {
+                    modal_blocked: DaswinModalBlocked {
+                        window: window.into_raw(),
+                        modal: modal.into_raw(),
+                    },
+                },
+            ),
+            Event::Text { window, index } => (
+                DaswinEventTag::Text,
+                DaswinEventData {
+                    text: DaswinText {
+                        window: window.into_raw(),
+                        index,
+                    },
+                },
+            ),
+            Event::MouseWheel { window, delta } => {
+                let (pixels, (delta_x, delta_y)) = match delta {
+                    ScrollDelta::Lines(x, y) => (false, (x as f64, y as f64)),
+                    ScrollDelta::Pixels(x, y) => (true, (x, y)),
+                };
+                (
+                    DaswinEventTag::MouseWheel,
+                    DaswinEventData {
+                        mouse_wheel: DaswinMouseWheel {
+                            window: window.into_raw(),
+                            pixels,
+                            delta_x,
+                            delta_y,
+                        },
+                    },
+                )
+            }
+            Event::StateChanged { window, change } => (
+                DaswinEventTag::StateChanged,
+                DaswinEventData {
+                    state_changed: DaswinStateChanged {
+                        window: window.into_raw(),
+                        change: change as u32,
+                    },
+                },
+            ),
+            Event::MonitorChanged { window, monitor } => (
+                DaswinEventTag::MonitorChanged,
+                DaswinEventData {
+                    monitor_changed: DaswinMonitorChanged {
+                        window: window.into_raw(),
+                        monitor,
+                    },
+                },
+            ),
+            Event::WindowCreated { window } => (
+                DaswinEventTag::WindowCreated,
+                DaswinEventData {
+                    window_created: DaswinWindowCreated {
+                        window: window.into_raw(),
+                    },
+                },
+            ),
+            Event::Destroyed { window } => (
+                DaswinEventTag::Destroyed,
+                DaswinEventData {
+                    destroyed: DaswinDestroyed {
+                        window: window.into_raw(),
+                    },
+                },
+            ),
+            Event::Suspending => (
+                DaswinEventTag::Suspending,
+                DaswinEventData { events_lost: 0 },
+            ),
+            Event::Resumed => (DaswinEventTag::Resumed, DaswinEventData { events_lost: 0 }),
+        };
+        DaswinEvent { tag, data }
+    }
+}
+
+/// The next event of `pending`, refilled with `poll` once the last batch
+/// was taken. The AboutToWait ending each batch is returned as None, so
+/// every batch ends once, even while events keep arriving
+fn next_event(
+    pending: &mut VecDeque<Event>,
+    events: &mut Vec<Event>,
+    poll: impl FnOnce(&mut Vec<Event>),
+) -> Option<Event> {
+    if pending.is_empty() {
+        poll(events);
+        pending.extend(events.drain(..));
+    }
+    pending
+        .pop_front()
+        .filter(|event| !matches!(event, Event::AboutToWait))
+}
+
+/// Copy as much of `string` into `buf` as fits with a terminating nul,
+/// cut at a character boundary. Returns the length of the whole string
+fn copy_str(string: &str, buf: &mut [u8]) -> usize {
+    if let Some(room) = buf.len().checked_sub(1) {
+        let mut end = string.len().min(room);
+        while !string.is_char_boundary(end) {
+            end -= 1;
+        }
+        buf[..end].copy_from_slice(&string.as_bytes()[..end]);
+        buf[end] = 0;
+    }
+    string.len()
+}
+
+/// A timeout in milliseconds from C, negative for none
+fn timeout(timeout_ms: i64) -> Option<Duration> {
+    u64::try_from(timeout_ms).ok().map(Duration::from_millis)
+}
+
+/// Run f, turning a panic into `on_panic` instead of unwinding into C
+fn guard<R>(on_panic: R, f: impl FnOnce() -> R) -> R {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(on_panic)
+}
+
+/// Open a connection and create a window with a UTF-8 title, returns
+/// null on failure.
+///
+/// # Safety
+/// `title` must be null or a nul-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn daswin_window_new(
+    title: *const c_char,
+    width: u32,
+    height: u32,
+) -> *mut DaswinWindow {
+    if title.is_null() {
+        return ptr::null_mut();
+    }
+    let title = CStr::from_ptr(title).to_string_lossy().into_owned();
+    guard(ptr::null_mut(), move || {
+        let event_loop = match EventLoopBuilder::new().build() {
+            Ok(event_loop) => event_loop,
+            Err(err) => {
+                log::warn!("{}", err);
+                return ptr::null_mut();
+            }
+        };
+        let window = X11Window::new(&event_loop, &title, (width, height));
+        Box::into_raw(Box::new(DaswinWindow {
+            window,
+            event_loop,
+            events: Vec::new(),
+            pending: VecDeque::new(),
+        }))
+    })
+}
+
+/// Map the window
+///
+/// # Safety
+/// `window` must be null or returned by daswin_window_new and not destroyed
+#[no_mangle]
+pub unsafe extern "C" fn daswin_window_show(window: *mut DaswinWindow) -> c_int {
+    let window = match window.as_mut() {
+        Some(window) => window,
+        None => return DASWIN_NULL_ARGUMENT,
+    };
+    guard(DASWIN_PANIC, || {
+        window.window.show();
+        DASWIN_OK
+    })
+}
+
+/// Take the next event without blocking, returns false when none is
+/// pending. AboutToWait is never returned, false ends the batch
+///
+/// # Safety
+/// `window` must be null or a live window, `out` null or writable
+#[no_mangle]
+pub unsafe extern "C" fn daswin_poll_event(
+    window: *mut DaswinWindow,
+    out: *mut DaswinEvent,
+) -> bool {
+    let window = match window.as_mut() {
+        Some(window) => window,
+        None => return false,
+    };
+    if out.is_null() {
+        return false;
+    }
+    let event = guard(None, || {
+        let event_loop = &mut window.event_loop;
+        next_event(&mut window.pending, &mut window.events, |events| {
+            event_loop.poll_events(events)
+        })
+    });
+    match event {
+        Some(event) => {
+            out.write(event.into());
+            true
+        }
+        None => false,
+    }
+}
+
+/// Block until events arrive or `timeout_ms` passed, negative to wait
+/// without a timeout. Returns right away while a batch is only partly
+/// taken. The wait for daswin_poll_event to find something, see
+/// EventLoop::wait_events
+///
+/// # Safety
+/// `window` must be null or a live window
+#[no_mangle]
+pub unsafe extern "C" fn daswin_wait_events(window: *mut DaswinWindow, timeout_ms: i64) -> c_int {
+    let window = match window.as_mut() {
+        Some(window) => window,
+        None => return DASWIN_NULL_ARGUMENT,
+    };
+    guard(DASWIN_PANIC, || {
+        if window.pending.is_empty() {
+            window.event_loop.wait_events(timeout(timeout_ms));
+        }
+        DASWIN_OK
+    })
+}
+
+/// Copy the string of a Text, GpuError or ScriptError event into `buf`
+/// as UTF-8 with a terminating nul, cut to fit `len` bytes. Returns the
+/// length of the whole string without the nul, so a return of `len` or
+/// more means it was cut, like snprintf, and DASWIN_NO_STRING for other
+/// events. The event must be of the current batch: once
+/// daswin_poll_event returned false the next call polls again and the
+/// strings of the batch before are gone
+///
+/// # Safety
+/// `window` must be null or a live window, `event` null or an event it
+/// returned, `buf` writable for `len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn daswin_event_text(
+    window: *const DaswinWindow,
+    event: *const DaswinEvent,
+    buf: *mut c_char,
+    len: usize,
+) -> isize {
+    let (window, event) = match (window.as_ref(), event.as_ref()) {
+        (Some(window), Some(event)) => (window, event),
+        _ => return DASWIN_NULL_ARGUMENT as isize,
+    };
+    if buf.is_null() && len > 0 {
+        return DASWIN_NULL_ARGUMENT as isize;
+    }
+    let event_loop = &window.event_loop;
+    let string = match event.tag {
+        DaswinEventTag::Text => event_loop.text(event.data.text.index),
+        DaswinEventTag::GpuError => event_loop.gpu_error(event.data.gpu_error),
+        #[cfg(feature = "scripting")]
+        DaswinEventTag::ScriptError => event_loop.script_error(event.data.script_error),
+        _ => None,
+    };
+    let string = match string {
+        Some(string) => string,
+        None => return DASWIN_NO_STRING as isize,
+    };
+    let buf = if len == 0 {
+        &mut []
+    } else {
+        std::slice::from_raw_parts_mut(buf as *mut u8, len)
+    };
+    copy_str(string, buf) as isize
+}
+
+/// Write the Xlib Display pointer and window id, for creating a surface
+/// in another renderer
+///
+/// # Safety
+/// `window` must be null or a live window, the out pointers null or
+/// writable
+#[no_mangle]
+pub unsafe extern "C" fn daswin_window_raw_xlib(
+    window: *const DaswinWindow,
+    display_out: *mut *mut c_void,
+    window_out: *mut c_ulong,
+) -> c_int {
+    let window = match window.as_ref() {
+        Some(window) => window,
+        None => return DASWIN_NULL_ARGUMENT,
+    };
+    if display_out.is_null() || window_out.is_null() {
+        return DASWIN_NULL_ARGUMENT;
+    }
+    display_out.write(window.window.display as *mut c_void);
+    window_out.write(window.window.window);
+    DASWIN_OK
+}
+
+/// Destroy the window and close its connection, null is ignored
+///
+/// # Safety
+/// `window` must be null or a live window, it must not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn daswin_window_destroy(window: *mut DaswinWindow) {
+    if !window.is_null() {
+        let window = Box::from_raw(window);
+        guard((), move || drop(window));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::dpi::PhysicalSize;
+    use crate::event::WindowId;
+
+    fn resized(width: u32) -> Event {
+        Event::Resized {
+            window: WindowId::from_raw(1),
+            size: PhysicalSize::new(width, 1),
+        }
+    }
+
+    #[test]
+    fn negative_timeouts_wait_forever() {
+        assert_eq!(timeout(-1), None);
+        assert_eq!(timeout(i64::MIN), None);
+        assert_eq!(timeout(0), Some(Duration::ZERO));
+        assert_eq!(timeout(1500), Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn strings_are_cut_at_characters() {
+        let mut buf = [0xaa; 8];
+        assert_eq!(copy_str("héllo", &mut buf), 6);
+        assert_eq!(&buf[..7], b"h\xc3\xa9llo\0");
+        assert_eq!(copy_str("héllo", &mut buf[..3]), 6);
+        assert_eq!(&buf[..2], b"h\0");
+        assert_eq!(copy_str("héllo", &mut buf[..4]), 6);
+        assert_eq!(&buf[..4], b"h\xc3\xa9\0");
+        assert_eq!(copy_str("✓", &mut buf[..1]), 3);
+        assert_eq!(buf[0], 0);
+    }
+
+    #[test]
+    fn empty_buffers_only_measure() {
+        let mut buf = [0xaa; 1];
+        assert_eq!(copy_str("text", &mut buf[..0]), 4);
+        assert_eq!(buf[0], 0xaa);
+    }
+
+    #[test]
+    fn empty_polls_end_the_batch() {
+        let mut pending = VecDeque::new();
+        let mut events = Vec::new();
+        let poll = |events: &mut Vec<Event>| events.push(Event::AboutToWait);
+        assert_eq!(next_event(&mut pending, &mut events, poll), None);
+        assert_eq!(next_event(&mut pending, &mut events, poll), None);
+    }
+
+    #[test]
+    fn batches_are_taken_before_polling_again() {
+        let mut pending = VecDeque::new();
+        let mut events = Vec::new();
+        let mut polls = 0;
+        let mut poll = |events: &mut Vec<Event>| {
+            polls += 1;
+            events.extend([resized(1), resized(2), Event::AboutToWait]);
+        };
+        let mut taken = Vec::new();
+        while let Some(event) = next_event(&mut pending, &mut events, &mut poll) {
+            taken.push(event);
+        }
+        assert_eq!(taken, [resized(1), resized(2)]);
+        assert_eq!(polls, 1);
+    }
+
+    #[test]
+    fn busy_batches_still_end() {
+        let mut pending = VecDeque::new();
+        let mut events = Vec::new();
+        let poll = |events: &mut Vec<Event>| events.extend([resized(1), Event::AboutToWait]);
+        for _ in 0..3 {
+            assert_eq!(
+                next_event(&mut pending, &mut events, poll),
+                Some(resized(1))
+            );
+            assert_eq!(next_event(&mut pending, &mut events, poll), None);
+        }
+    }
+
+    #[test]
+    fn a_batch_ends_with_false() {
+        let mut pending = VecDeque::new();
+        let mut events = Vec::new();
+        let mut batches = vec![
+            vec![resized(1), Event::AboutToWait],
+            vec![Event::AboutToWait],
+        ];
+        let mut next = |batches: &mut Vec<Vec<Event>>| {
+            next_event(&mut pending, &mut events, |events| {
+                events.extend(batches.remove(0))
+            })
+        };
+        assert_eq!(next(&mut batches), Some(resized(1)));
+        assert_eq!(next(&mut batches), None);
+        assert_eq!(batches.len(), 1);
+        // Polling again after the end finds nothing
+        assert_eq!(next(&mut batches), None);
+        assert!(batches.is_empty());
+    }
+}
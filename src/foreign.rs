@@ -0,0 +1,129 @@
+//! Embedder side of foreign windows hosted in a region of a window, e.g.
+//! the video window of a media player. The child is reparented into the
+//! window and kept inside its region, X routes input to whichever of
+//! the two is under the pointer
+
+use std::os::raw::c_long;
+use std::sync::atomic::Ordering;
+
+use x11::xlib;
+
+use crate::window::Rect;
+use crate::xconn::XConnection;
+use crate::xembed;
+
+/// The geometry of a child inside a window of `size`: its region clipped
+/// to the window, a single pixel at the region's corner once nothing is
+/// left
+pub(crate) fn clip(rect: Rect, size: (u32, u32)) -> Rect {
+    let bounds = Rect {
+        x: 0,
+        y: 0,
+        width: size.0,
+        height: size.1,
+    };
+    rect.intersection(&bounds).unwrap_or(Rect {
+        x: rect.x,
+        y: rect.y,
+        width: 1,
+        height: 1,
+    })
+}
+
+/// Reparent the child into `parent` at `rect`, false when it does not
+/// exist. Kept in the save set, the server gives it back to the root
+/// window rather than destroying it should this client go away first
+pub(crate) unsafe fn embed(
+    conn: &XConnection,
+    parent: xlib::Window,
+    child: xlib::Window,
+    rect: Rect,
+) -> bool {
+    let mut attributes = std::mem::zeroed();
+    if xlib::XGetWindowAttributes(conn.display, child, &mut attributes) == 0 {
+        return false;
+    }
+    xlib::XAddToSaveSet(conn.display, child);
+    // Mapped windows are unmapped and mapped again by reparenting
+    xlib::XReparentWindow(conn.display, child, parent, rect.x, rect.y);
+    place(conn, child, rect);
+    if attributes.map_state == xlib::IsUnmapped {
+        xlib::XMapWindow(conn.display, child);
+    }
+    // Clients speaking XEMBED learn who embeds them, the others ignore it
+    send(conn, child, xembed::EMBEDDED_NOTIFY, parent);
+    true
+}
+
+pub(crate) unsafe fn place(conn: &XConnection, child: xlib::Window, rect: Rect) {
+    xlib::XMoveResizeWindow(
+        conn.display,
+        child,
+        rect.x,
+        rect.y,
+        rect.width.max(1),
+        rect.height.max(1),
+    );
+}
+
+/// Reparent the child back to the root window where it is now on the
+/// screen, leaving it alive
+pub(crate) unsafe fn release(conn: &XConnection, child: xlib::Window) {
+    let (mut x, mut y, mut unused) = (0, 0, 0);
+    xlib::XTranslateCoordinates(
+        conn.display,
+        child,
+        conn.root,
+        0,
+        0,
+        &mut x,
+        &mut y,
+        &mut unused,
+    );
+    // Reparenting maps the child again if it was mapped
+    xlib::XReparentWindow(conn.display, child, conn.root, x, y);
+    xlib::XRemoveFromSaveSet(conn.display, child);
+}
+
+/// Give keyboard focus to the child, or back to `parent`
+pub(crate) unsafe fn focus(conn: &XConnection, parent: xlib::Window, child: Option<xlib::Window>) {
+    let time = conn.last_event_time.load(Ordering::Relaxed);
+    match child {
+        Some(child) => {
+            xlib::XSetInputFocus(conn.display, child, xlib::RevertToParent, time);
+            send(conn, child, xembed::FOCUS_IN, 0);
+        }
+        None => {
+            xlib::XSetInputFocus(conn.display, parent, xlib::RevertToParent, time);
+        }
+    }
+}
+
+/// Tell a child it lost focus
+pub(crate) unsafe fn unfocus(conn: &XConnection, child: xlib::Window) {
+    send(conn, child, xembed::FOCUS_OUT, 0);
+}
+
+// An XEMBED message to the child, `embedder` and the protocol version
+// only matter to EMBEDDED_NOTIFY
+unsafe fn send(conn: &XConnection, child: xlib::Window, message: c_long, embedder: xlib::Window) {
+    let mut xclient: xlib::XClientMessageEvent = std::mem::zeroed();
+    xclient.type_ = xlib::ClientMessage;
+    xclient.window = child;
+    xclient.message_type = conn.atoms.xembed;
+    xclient.format = 32;
+    xclient
+        .data
+        .set_long(0, conn.last_event_time.load(Ordering::Relaxed) as c_long);
+    xclient.data.set_long(1, message);
+    xclient.data.set_long(3, embedder as c_long);
+    xclient.data.set_long(4, xembed::VERSION);
+    let mut event = xlib::XEvent::from(xclient);
+    xlib::XSendEvent(
+        conn.display,
+        child,
+        xlib::False,
+        xlib::NoEventMask,
+        &mut event,
+    );
+}
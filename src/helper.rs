@@ -0,0 +1,211 @@
+//! Windows which are never shown, for owning selections, receiving
+//! client messages and grabbing input while the application has no
+//! visible window, see HelperWindow
+
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::mem;
+use std::os::raw;
+use std::ptr;
+use std::rc::Rc;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use x11::xlib;
+
+use crate::event_loop::EventLoop;
+use crate::xconn::XConnection;
+
+/// An InputOnly window which is never mapped, e.g. to own the clipboard
+/// after the last visible window closed.
+///
+/// Its events are kept for take_events rather than delivered as Events,
+/// and input methods never see them. Helpers do not count as windows for
+/// EventLoop::set_exit_on_last_window_closed, and are not recreated by
+/// EventLoop::reconnect
+pub struct HelperWindow {
+    pub window: xlib::Window,
+    conn: Arc<XConnection>,
+    events: Rc<RefCell<Vec<xlib::XEvent>>>,
+}
+
+impl HelperWindow {
+    /// Create a helper on the event loop's connection, selecting
+    /// PropertyNotify on it. Selection and client messages arrive
+    /// without selecting them
+    pub fn new(event_loop: &EventLoop) -> HelperWindow {
+        let conn = event_loop.conn.clone();
+        let window = conn.request(|conn| unsafe {
+            let mut attributes: xlib::XSetWindowAttributes = mem::zeroed();
+            attributes.event_mask = xlib::PropertyChangeMask;
+            let window = xlib::XCreateWindow(
+                conn.display,
+                conn.root,
+                -1,
+                -1,
+                1,
+                1,
+                0,
+                0,
+                xlib::InputOnly as raw::c_uint,
+                ptr::null_mut(),
+                xlib::CWEventMask,
+                &mut attributes,
+            );
+            xlib::XFlush(conn.display);
+            window
+        });
+        let events = Rc::new(RefCell::new(Vec::new()));
+        event_loop.register_helper(window, &events);
+        HelperWindow {
+            window,
+            conn,
+            events,
+        }
+    }
+
+    /// The events of the window read by the polls since the last call,
+    /// oldest first
+    pub fn take_events(&self) -> Vec<xlib::XEvent> {
+        mem::take(&mut *self.events.borrow_mut())
+    }
+
+    /// Intern an atom, e.g. CLIPBOARD or UTF8_STRING
+    pub fn atom(&self, name: &str) -> xlib::Atom {
+        let name = CString::new(name).unwrap_or_default();
+        self.conn.request(move |conn| unsafe {
+            xlib::XInternAtom(conn.display, name.as_ptr(), xlib::False)
+        })
+    }
+
+    /// Take ownership of a selection as of the latest event, answering
+    /// its SelectionRequests with send_selection until a SelectionClear
+    /// arrives. False when another client took it at a later time
+    pub fn own_selection(&self, selection: xlib::Atom) -> bool {
+        let window = self.window;
+        let time = self.conn.last_event_time.load(Ordering::Relaxed);
+        self.conn.request(move |conn| unsafe {
+            xlib::XSetSelectionOwner(conn.display, selection, window, time);
+            xlib::XGetSelectionOwner(conn.display, selection) == window
+        })
+    }
+
+    /// Answer a SelectionRequest with data of a type in format 8, 16 or
+    /// 32, or refuse it with None. Format 32 data is an array of c_long,
+    /// as Xlib takes it. Data larger than the server accepts at once
+    /// would need the INCR protocol, which is not spoken
+    pub fn send_selection(
+        &self,
+        request: &xlib::XSelectionRequestEvent,
+        data: Option<(xlib::Atom, i32, &[u8])>,
+    ) {
+        let (requestor, selection, target, time) = (
+            request.requestor,
+            request.selection,
+            request.target,
+            request.time,
+        );
+        // Obsolete clients leave the property to the owner
+        let property = match request.property {
+            0 => target,
+            property => property,
+        };
+        let data = data.map(|(type_, format, bytes)| (type_, format, bytes.to_vec()));
+        self.conn.request(move |conn| unsafe {
+            let property = match data {
+                Some((type_, format, bytes)) => {
+                    let unit = match format {
+                        8 => 1,
+                        16 => mem::size_of::<raw::c_short>(),
+                        _ => mem::size_of::<raw::c_long>(),
+                    };
+                    xlib::XChangeProperty(
+                        conn.display,
+                        requestor,
+                        property,
+                        type_,
+                        format,
+                        xlib::PropModeReplace,
+                        bytes.as_ptr(),
+                        (bytes.len() / unit) as raw::c_int,
+                    );
+                    property
+                }
+                None => 0,
+            };
+            let mut notify: xlib::XSelectionEvent = mem::zeroed();
+            notify.type_ = xlib::SelectionNotify;
+            notify.requestor = requestor;
+            notify.selection = selection;
+            notify.target = target;
+            notify.property = property;
+            notify.time = time;
+            let mut xevent = xlib::XEvent { selection: notify };
+            xlib::XSendEvent(conn.display, requestor, xlib::False, 0, &mut xevent);
+            xlib::XFlush(conn.display);
+        });
+    }
+
+    /// Ask the owner of a selection for its contents converted to a
+    /// target, announced by a SelectionNotify for read_selection
+    pub fn convert_selection(&self, selection: xlib::Atom, target: xlib::Atom) {
+        let window = self.window;
+        let time = self.conn.last_event_time.load(Ordering::Relaxed);
+        self.conn.request(move |conn| unsafe {
+            let property = conn.atoms.daswin_selection;
+            xlib::XConvertSelection(conn.display, selection, target, property, window, time);
+            xlib::XFlush(conn.display);
+        });
+    }
+
+    /// The type and data a SelectionNotify announced, None when the
+    /// owner refused the conversion or used INCR
+    pub fn read_selection(&self, notify: &xlib::XSelectionEvent) -> Option<(xlib::Atom, Vec<u8>)> {
+        let (window, property) = (self.window, notify.property);
+        if property == 0 {
+            return None;
+        }
+        self.conn.request(move |conn| unsafe {
+            let mut type_ = 0;
+            let mut format = 0;
+            let mut count = 0;
+            let mut remaining = 0;
+            let mut data: *mut raw::c_uchar = ptr::null_mut();
+            let status = xlib::XGetWindowProperty(
+                conn.display,
+                window,
+                property,
+                0,
+                (u32::MAX / 4) as raw::c_long,
+                xlib::True,
+                xlib::AnyPropertyType as xlib::Atom,
+                &mut type_,
+                &mut format,
+                &mut count,
+                &mut remaining,
+                &mut data,
+            );
+            if status != xlib::Success as raw::c_int || data.is_null() {
+                return None;
+            }
+            let unit = match format {
+                8 => 1,
+                16 => mem::size_of::<raw::c_short>(),
+                _ => mem::size_of::<raw::c_long>(),
+            };
+            let bytes = std::slice::from_raw_parts(data, count as usize * unit).to_vec();
+            xlib::XFree(data as *mut _);
+            (type_ != conn.atoms.incr).then_some((type_, bytes))
+        })
+    }
+}
+
+impl Drop for HelperWindow {
+    fn drop(&mut self) {
+        let window = self.window;
+        self.conn.request(move |conn| unsafe {
+            xlib::XDestroyWindow(conn.display, window);
+            xlib::XFlush(conn.display);
+        });
+    }
+}
@@ -0,0 +1,264 @@
+//! The last events an event loop delivered, kept for bug reports, see
+//! EventLoop::recent_events.
+//!
+//! A ring of TimestampedEvent allocated once with the loop. Events are
+//! Copy, so recording one is a copy into the ring and stays on in
+//! release builds
+
+use std::fmt::{self, Write};
+use std::str;
+use std::time::Instant;
+
+use x11::xlib;
+
+use crate::event::Event;
+
+/// Events kept by the ring
+pub(crate) const CAPACITY: usize = 512;
+
+// Bytes of text kept with a Text event once included, longer text is
+// cut at a character boundary
+const TEXT_CAPACITY: usize = 32;
+
+/// An event along with when its poll delivered it
+#[derive(Debug, Clone, Copy)]
+pub struct TimestampedEvent {
+    pub time: Instant,
+    pub event: Event,
+    text: [u8; TEXT_CAPACITY],
+    text_len: u8,
+    // The keycode and keysym of a KeyboardInput typing text were zeroed
+    redacted: bool,
+}
+
+impl TimestampedEvent {
+    /// The text of a Text event, kept only after
+    /// EventLoop::set_recent_events_include_text. Cut to its first 32
+    /// bytes
+    pub fn text(&self) -> Option<&str> {
+        match self.event {
+            Event::Text { .. } if self.text_len > 0 => {
+                str::from_utf8(&self.text[..self.text_len as usize]).ok()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TimestampedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.event)?;
+        match (self.event, self.text()) {
+            (Event::Text { .. }, Some(text)) => write!(f, " {:?}", text),
+            (Event::Text { .. }, None) => write!(f, " <redacted>"),
+            (Event::KeyboardInput { .. }, _) if self.redacted => write!(f, " <redacted>"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The ring of an event loop
+pub(crate) struct History {
+    entries: Vec<TimestampedEvent>,
+    // Where the next event goes once the ring is full, the oldest entry
+    next: usize,
+    include_text: bool,
+}
+
+impl History {
+    pub fn new() -> History {
+        History {
+            entries: Vec::with_capacity(CAPACITY),
+            next: 0,
+            include_text: false,
+        }
+    }
+
+    /// Keep the text of Text events and the keys typing it from now on,
+    /// redacted otherwise
+    pub fn set_include_text(&mut self, include: bool) {
+        self.include_text = include;
+    }
+
+    /// Record the events of a poll, `texts` holding the strings of its
+    /// Text events. AboutToWait ends every poll and is left out, so idle
+    /// polls do not push out the events before them
+    pub fn record(&mut self, time: Instant, events: &[Event], texts: &[String]) {
+        for &event in events {
+            let mut entry = TimestampedEvent {
+                time,
+                event,
+                text: [0; TEXT_CAPACITY],
+                text_len: 0,
+                redacted: false,
+            };
+            match &mut entry.event {
+                Event::AboutToWait => continue,
+                Event::Text { index, .. } if self.include_text => {
+                    if let Some(text) = texts.get(*index as usize) {
+                        let mut len = text.len().min(TEXT_CAPACITY);
+                        while !text.is_char_boundary(len) {
+                            len -= 1;
+                        }
+                        entry.text[..len].copy_from_slice(&text.as_bytes()[..len]);
+                        entry.text_len = len as u8;
+                    }
+                }
+                Event::KeyboardInput {
+                    keycode, keysym, ..
+                } if !self.include_text && types_text(*keysym) => {
+                    *keycode = 0;
+                    *keysym = 0;
+                    entry.redacted = true;
+                }
+                _ => {}
+            }
+            if self.entries.len() < CAPACITY {
+                self.entries.push(entry);
+            } else {
+                self.entries[self.next] = entry;
+                self.next = (self.next + 1) % CAPACITY;
+            }
+        }
+    }
+
+    /// The recorded events, oldest first
+    pub fn recent(&self) -> Vec<TimestampedEvent> {
+        let (newer, older) = self.entries.split_at(self.next);
+        older.iter().chain(newer).copied().collect()
+    }
+
+    /// The recorded events as a table, one per line with its age relative
+    /// to `now`
+    pub fn table(&self, now: Instant) -> String {
+        let mut table = format!("{:>10}  event\n", "age");
+        for entry in self.recent() {
+            let age = now.saturating_duration_since(entry.time);
+            let _ = writeln!(table, "{:>9.3}s  {}", age.as_secs_f64(), entry);
+        }
+        table
+    }
+}
+
+/// Whether the key of `keysym` types text, whose keycode and keysym are
+/// redacted along with the text. Function, cursor and modifier keys are
+/// kept
+fn types_text(keysym: xlib::KeySym) -> bool {
+    // The keypad types digits and operators
+    !(0xfe00..=0xffff).contains(&keysym) || (0xff80..=0xffbd).contains(&keysym)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::dpi::PhysicalSize;
+    use crate::event::WindowId;
+
+    fn resized(width: u32) -> Event {
+        Event::Resized {
+            window: WindowId::new(0x40_0001, 0),
+            size: PhysicalSize::new(width, 0),
+        }
+    }
+
+    fn key(keysym: xlib::KeySym) -> Event {
+        Event::KeyboardInput {
+            window: WindowId::new(0x40_0001, 0),
+            keycode: 38,
+            keysym,
+            pressed: true,
+            synthetic: false,
+            repeat: false,
+        }
+    }
+
+    fn width(entry: &TimestampedEvent) -> u32 {
+        match entry.event {
+            Event::Resized { size, .. } => size.width,
+            _ => panic!("unexpected {:?}", entry.event),
+        }
+    }
+
+    #[test]
+    fn ring_wraps_around() {
+        let start = Instant::now();
+        let mut history = History::new();
+        for i in 0..CAPACITY as u32 + 10 {
+            let time = start + Duration::from_millis(i as u64);
+            history.record(time, &[resized(i), Event::AboutToWait], &[]);
+        }
+        let recent = history.recent();
+        assert_eq!(recent.len(), CAPACITY);
+        let widths: Vec<u32> = recent.iter().map(width).collect();
+        let expected: Vec<u32> = (10..CAPACITY as u32 + 10).collect();
+        assert_eq!(widths, expected);
+
+        let table = history.table(start + Duration::from_secs(1));
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), CAPACITY + 1);
+        assert_eq!(lines[0], "       age  event");
+        assert!(
+            lines[1].starts_with("    0.990s  Resized {"),
+            "{}",
+            lines[1]
+        );
+        assert!(lines[1].contains("width: 10,"), "{}", lines[1]);
+        assert!(lines[CAPACITY].starts_with("    0.479s  Resized {"));
+        assert!(lines[CAPACITY].contains("width: 521,"));
+    }
+
+    #[test]
+    fn text_is_redacted() {
+        let now = Instant::now();
+        let text = Event::Text {
+            window: WindowId::new(0x40_0001, 0),
+            index: 0,
+        };
+        let events = [text, key(0x61), key(0xff1b), key(0xffb1)];
+        let texts = ["hunter2".to_owned()];
+        let mut history = History::new();
+        history.record(now, &events, &texts);
+        let recent = history.recent();
+        assert_eq!(recent[0].text(), None);
+        assert!(recent[0].to_string().ends_with("} <redacted>"));
+        // a and keypad 1 type text, Escape does not
+        for (entry, redacted) in recent[1..].iter().zip([true, false, true]) {
+            match entry.event {
+                Event::KeyboardInput {
+                    keycode, keysym, ..
+                } => assert_eq!((keycode, keysym) == (0, 0), redacted),
+                _ => unreachable!(),
+            }
+            assert_eq!(entry.to_string().ends_with("<redacted>"), redacted);
+        }
+
+        history.set_include_text(true);
+        history.record(now, &events, &texts);
+        let recent = history.recent();
+        assert_eq!(recent[4].text(), Some("hunter2"));
+        assert!(recent[4].to_string().ends_with("} \"hunter2\""));
+        for entry in &recent[5..] {
+            assert!(matches!(
+                entry.event,
+                Event::KeyboardInput { keycode: 38, .. }
+            ));
+            assert!(!entry.to_string().contains("<redacted>"));
+        }
+    }
+
+    #[test]
+    fn long_text_is_cut_at_a_character() {
+        let mut history = History::new();
+        history.set_include_text(true);
+        let text = Event::Text {
+            window: WindowId::new(0x40_0001, 0),
+            index: 0,
+        };
+        // 31 bytes, then a character of 3 which would end past 32
+        let long = format!("{}€", "a".repeat(31));
+        history.record(Instant::now(), &[text], &[long]);
+        assert_eq!(history.recent()[0].text(), Some(&*"a".repeat(31)));
+    }
+}
@@ -0,0 +1,378 @@
+//! Key chords grabbed on the root window, so they reach the application
+//! whichever client has focus. The server matches grabs against the
+//! exact modifier state, so every chord is grabbed once per combination
+//! of Caps Lock and Num Lock
+
+use std::ffi::CString;
+use std::os::raw;
+use std::sync::Arc;
+
+use x11::{keysym, xlib};
+
+use crate::error::{self, Error};
+use crate::event::{Event, HotkeyId};
+use crate::keyboard;
+use crate::xconn::XConnection;
+
+// Request code of GrabKey and the error of a grab held by another client
+const X_GRAB_KEY: u8 = 33;
+const BAD_ACCESS: u8 = 10;
+
+struct Hotkey {
+    id: HotkeyId,
+    modifiers: u32,
+    keysym: xlib::KeySym,
+    // Keycode the chord is grabbed with and the modifier states grabbed.
+    // register refuses keysyms without a key, this is only None after a
+    // layout change or reconnect left the keysym without one, until
+    // regrab finds it on a key again
+    grabbed: Option<(u32, Vec<u32>)>,
+}
+
+/// The hotkeys of an event loop, ungrabbed on drop
+pub(crate) struct Hotkeys {
+    conn: Arc<XConnection>,
+    next: u32,
+    registered: Vec<Hotkey>,
+    // Keycode of the hotkey held down, the server repeats it
+    held: Option<u32>,
+}
+
+impl Hotkeys {
+    pub fn new(conn: &Arc<XConnection>) -> Hotkeys {
+        Hotkeys {
+            conn: conn.clone(),
+            next: 0,
+            registered: Vec::new(),
+            held: None,
+        }
+    }
+
+    pub fn register(&mut self, keys: &str) -> Result<HotkeyId, Error> {
+        let (modifiers, keysym) = parse(keys).ok_or(Error::InvalidHotkey)?;
+        if self
+            .registered
+            .iter()
+            .any(|hotkey| hotkey.modifiers == modifiers && hotkey.keysym == keysym)
+        {
+            return Err(Error::AlreadyGrabbed);
+        }
+        let grabbed = self
+            .conn
+            .request(move |conn| unsafe { grab(conn, modifiers, keysym) })?;
+        if grabbed.is_none() {
+            return Err(Error::InvalidHotkey);
+        }
+        self.next += 1;
+        let id = HotkeyId(self.next);
+        self.registered.push(Hotkey {
+            id,
+            modifiers,
+            keysym,
+            grabbed,
+        });
+        Ok(id)
+    }
+
+    pub fn unregister(&mut self, id: HotkeyId) -> bool {
+        let index = match self.registered.iter().position(|hotkey| hotkey.id == id) {
+            Some(index) => index,
+            None => return false,
+        };
+        let hotkey = self.registered.remove(index);
+        if let Some(grabbed) = hotkey.grabbed {
+            self.conn.request(move |conn| unsafe {
+                ungrab(conn, &grabbed);
+                xlib::XFlush(conn.display);
+            });
+        }
+        true
+    }
+
+    /// Take over the hotkeys of a lost connection, grabbing them anew
+    pub fn carry_over(&mut self, lost: &mut Hotkeys) {
+        self.next = lost.next;
+        self.registered = std::mem::take(&mut lost.registered);
+        for hotkey in &mut self.registered {
+            hotkey.grabbed = None;
+        }
+        self.regrab();
+    }
+
+    /// Grab every hotkey again after the keyboard mapping changed, keys
+    /// may have moved to other keycodes and Num Lock to another modifier
+    pub fn regrab(&mut self) {
+        let keys: Vec<_> = self
+            .registered
+            .iter_mut()
+            .map(|hotkey| (hotkey.modifiers, hotkey.keysym, hotkey.grabbed.take()))
+            .collect();
+        let grabbed = self.conn.request(move |conn| unsafe {
+            for (_, _, grabbed) in &keys {
+                if let Some(grabbed) = grabbed {
+                    ungrab(conn, grabbed);
+                }
+            }
+            keys.iter()
+                .map(|&(modifiers, keysym, _)| grab(conn, modifiers, keysym).ok().flatten())
+                .collect::<Vec<_>>()
+        });
+        for (hotkey, grabbed) in self.registered.iter_mut().zip(grabbed) {
+            if grabbed.is_none() {
+                log::warn!("hotkey {:?} could not be grabbed again", hotkey.id);
+            }
+            hotkey.grabbed = grabbed;
+        }
+        self.held = None;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.registered.is_empty()
+    }
+
+    /// Whether the event is a key event of a hotkey grab
+    pub fn grabbed(&self, xevent: &xlib::XEvent) -> bool {
+        matches!(xevent.get_type(), xlib::KeyPress | xlib::KeyRelease)
+            && unsafe { xevent.key.window } == self.conn.root
+            && !self.registered.is_empty()
+    }
+
+    pub fn translate(&mut self, xkey: &xlib::XKeyEvent) -> Option<Event> {
+        if xkey.type_ == xlib::KeyRelease {
+            if self.held == Some(xkey.keycode) {
+                self.held = None;
+            }
+            return None;
+        }
+        if self.held.replace(xkey.keycode) == Some(xkey.keycode) {
+            return None;
+        }
+        let state = xkey.state & 0xff;
+        self.registered
+            .iter()
+            .find(|hotkey| match &hotkey.grabbed {
+                Some((keycode, states)) => *keycode == xkey.keycode && states.contains(&state),
+                None => false,
+            })
+            .map(|hotkey| Event::GlobalHotkey(hotkey.id))
+    }
+}
+
+impl Drop for Hotkeys {
+    /// Release the grabs, which outlive the loop on displays of the
+    /// application
+    fn drop(&mut self) {
+        if self.registered.is_empty() || self.conn.is_lost() {
+            return;
+        }
+        let grabbed: Vec<_> = self
+            .registered
+            .iter_mut()
+            .filter_map(|hotkey| hotkey.grabbed.take())
+            .collect();
+        self.conn.request(move |conn| unsafe {
+            for grabbed in &grabbed {
+                ungrab(conn, grabbed);
+            }
+            xlib::XFlush(conn.display);
+        });
+    }
+}
+
+/// The modifier mask and keysym of a chord such as "Ctrl+Alt+T", with
+/// modifiers named Ctrl, Shift, Alt, Super or AltGr in any case
+pub(crate) fn parse(keys: &str) -> Option<(u32, xlib::KeySym)> {
+    let mut parts: Vec<&str> = keys.split('+').map(str::trim).collect();
+    // "Ctrl++" is the plus key
+    if keys.trim_end().ends_with("++") {
+        parts.truncate(parts.len() - 2);
+        parts.push("plus");
+    }
+    let key = parts.pop().filter(|key| !key.is_empty())?;
+    let mut modifiers = 0;
+    for part in parts {
+        modifiers |= match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => xlib::ControlMask,
+            "shift" => xlib::ShiftMask,
+            "alt" | "mod1" => xlib::Mod1Mask,
+            "super" | "win" | "logo" | "mod4" => xlib::Mod4Mask,
+            "altgr" | "mod5" => xlib::Mod5Mask,
+            _ => return None,
+        };
+    }
+    // Keysym names are case sensitive, "print" means Print
+    let lowercase = key.to_ascii_lowercase();
+    let mut chars = lowercase.chars();
+    let capitalized: String = chars
+        .next()
+        .map(|first| first.to_ascii_uppercase())
+        .into_iter()
+        .chain(chars)
+        .collect();
+    [key.to_owned(), lowercase, capitalized]
+        .iter()
+        .map(|name| string_to_keysym(name))
+        .find(|&keysym| keysym != 0)
+        .map(|keysym| (modifiers, keysym))
+}
+
+fn string_to_keysym(name: &str) -> xlib::KeySym {
+    match CString::new(name) {
+        Ok(name) => unsafe { xlib::XStringToKeysym(name.as_ptr()) },
+        Err(_) => 0,
+    }
+}
+
+/// Grab the chord in every combination of the lock modifiers. None when
+/// the layout has no key for the keysym, fails with AlreadyGrabbed when
+/// another client, usually the window manager, grabbed it first
+unsafe fn grab(
+    conn: &XConnection,
+    modifiers: u32,
+    keysym: xlib::KeySym,
+) -> Result<Option<(u32, Vec<u32>)>, Error> {
+    let keycode = match keyboard::keysym_to_keycode(conn, keysym) {
+        Some(keycode) => keycode,
+        None => return Ok(None),
+    };
+    let num_lock = num_lock_mask(conn);
+    let mut states = Vec::with_capacity(4);
+    for locks in [0, xlib::LockMask, num_lock, num_lock | xlib::LockMask] {
+        let state = modifiers | locks;
+        if states.contains(&state) {
+            continue;
+        }
+        states.push(state);
+        xlib::XGrabKey(
+            conn.display,
+            keycode as raw::c_int,
+            state,
+            conn.root,
+            xlib::False,
+            xlib::GrabModeAsync,
+            xlib::GrabModeAsync,
+        );
+    }
+    // The server refuses with BadAccess, recorded by the error handler
+    xlib::XSync(conn.display, xlib::False);
+    let refused = error::take_x_errors_where(|error| {
+        error.request_code == X_GRAB_KEY && error.error_code == BAD_ACCESS
+    });
+    let grabbed = (keycode, states);
+    if !refused.is_empty() {
+        // Some of the combinations may have been granted
+        ungrab(conn, &grabbed);
+        xlib::XFlush(conn.display);
+        return Err(Error::AlreadyGrabbed);
+    }
+    Ok(Some(grabbed))
+}
+
+unsafe fn ungrab(conn: &XConnection, (keycode, states): &(u32, Vec<u32>)) {
+    for &state in states {
+        xlib::XUngrabKey(conn.display, *keycode as raw::c_int, state, conn.root);
+    }
+}
+
+/// The modifier Num Lock is mapped to, 0 when it is on none
+unsafe fn num_lock_mask(conn: &XConnection) -> u32 {
+    let keycode = match keyboard::keysym_to_keycode(conn, keysym::XK_Num_Lock as xlib::KeySym) {
+        Some(keycode) => keycode,
+        None => return 0,
+    };
+    let map = xlib::XGetModifierMapping(conn.display);
+    if map.is_null() {
+        return 0;
+    }
+    let per_modifier = (*map).max_keypermod as usize;
+    let keycodes = std::slice::from_raw_parts((*map).modifiermap, 8 * per_modifier);
+    let mask = match keycodes.iter().position(|&code| code as u32 == keycode) {
+        Some(index) => 1 << (index / per_modifier),
+        None => 0,
+    };
+    xlib::XFreeModifiermap(map);
+    mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(keysym: u32) -> xlib::KeySym {
+        keysym as xlib::KeySym
+    }
+
+    #[test]
+    fn chords() {
+        assert_eq!(
+            parse("Ctrl+Alt+T"),
+            Some((xlib::ControlMask | xlib::Mod1Mask, key(keysym::XK_T)))
+        );
+        assert_eq!(
+            parse("Super+Shift+s"),
+            Some((xlib::Mod4Mask | xlib::ShiftMask, key(keysym::XK_s)))
+        );
+        assert_eq!(parse("Print"), Some((0, key(keysym::XK_Print))));
+        assert_eq!(
+            parse(" Ctrl + F1 "),
+            Some((xlib::ControlMask, key(keysym::XK_F1)))
+        );
+    }
+
+    #[test]
+    fn plus_key() {
+        assert_eq!(
+            parse("Ctrl++"),
+            Some((xlib::ControlMask, key(keysym::XK_plus)))
+        );
+        assert_eq!(
+            parse("Ctrl+Shift++"),
+            Some((xlib::ControlMask | xlib::ShiftMask, key(keysym::XK_plus)))
+        );
+        assert_eq!(parse("Ctrl+plus"), parse("Ctrl++"));
+    }
+
+    #[test]
+    fn case_is_folded() {
+        assert_eq!(parse("CTRL+ALT+print"), parse("Ctrl+Alt+Print"));
+        assert_eq!(parse("ctrl+PRINT"), parse("Ctrl+Print"));
+        assert_eq!(parse("f12"), Some((0, key(keysym::XK_F12))));
+        assert_eq!(parse("RETURN"), Some((0, key(keysym::XK_Return))));
+        // Names found as written are kept, letters keep their case
+        assert_eq!(parse("A"), Some((0, key(keysym::XK_A))));
+        assert_eq!(parse("a"), Some((0, key(keysym::XK_a))));
+    }
+
+    #[test]
+    fn modifier_aliases() {
+        let aliases = [
+            ("Control", xlib::ControlMask),
+            ("Mod1", xlib::Mod1Mask),
+            ("Win", xlib::Mod4Mask),
+            ("Logo", xlib::Mod4Mask),
+            ("Mod4", xlib::Mod4Mask),
+            ("AltGr", xlib::Mod5Mask),
+            ("Mod5", xlib::Mod5Mask),
+        ];
+        for &(name, mask) in &aliases {
+            let chord = format!("{}+x", name);
+            assert_eq!(parse(&chord), Some((mask, key(keysym::XK_x))), "{}", name);
+        }
+    }
+
+    #[test]
+    fn invalid_chords() {
+        for keys in [
+            "",
+            "+",
+            "Ctrl+",
+            "Ctrl+ ",
+            "Hyper+A",
+            "Ctrl+Nothing",
+            "Ctrl+A\0",
+            "A+B",
+        ] {
+            assert_eq!(parse(keys), None, "{:?}", keys);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Images shown for windows and tray icons
+
+use std::os::raw::c_ulong;
+
+/// An RGBA image with straight alpha, rows top to bottom without
+/// padding. Window managers and trays scale it as needed, 32x32 to
+/// 64x64 pixels suit most of them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Icon {
+    rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl Icon {
+    /// None when `rgba` does not hold exactly width * height pixels or
+    /// the image is empty
+    pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Option<Icon> {
+        let len = (width as usize)
+            .checked_mul(height as usize)?
+            .checked_mul(4)?;
+        (len != 0 && rgba.len() == len).then_some(Icon {
+            rgba,
+            width,
+            height,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    fn argb(&self) -> impl Iterator<Item = u32> + '_ {
+        self.rgba
+            .chunks_exact(4)
+            .map(|pixel| u32::from_be_bytes([pixel[3], pixel[0], pixel[1], pixel[2]]))
+    }
+
+    /// _NET_WM_ICON data: width, height, then one ARGB pixel per long
+    pub(crate) fn to_cardinals(&self) -> Vec<c_ulong> {
+        [self.width, self.height]
+            .iter()
+            .copied()
+            .chain(self.argb())
+            .map(c_ulong::from)
+            .collect()
+    }
+
+    /// ARGB pixels in network byte order, as StatusNotifierItem pixmaps
+    #[cfg(feature = "dbus")]
+    pub(crate) fn to_argb_be(&self) -> Vec<u8> {
+        self.argb().flat_map(u32::to_be_bytes).collect()
+    }
+}
@@ -0,0 +1,224 @@
+//! X input methods, which compose text such as CJK input for the focused
+//! window and draw their candidate window next to the text cursor
+
+use std::ffi::CStr;
+use std::os::raw;
+use std::ptr;
+
+use x11::xlib;
+
+use crate::window::Rect;
+
+/// Input method of the connection, opened on first use
+pub(crate) struct InputMethod(xlib::XIM);
+
+// Only used under the connection's Xlib lock, like the display
+unsafe impl Send for InputMethod {}
+unsafe impl Sync for InputMethod {}
+
+/// Input context of one window
+#[derive(Clone, Copy)]
+pub(crate) struct InputContext(xlib::XIC);
+
+unsafe impl Send for InputContext {}
+
+type XIMStyle = raw::c_ulong;
+
+#[repr(C)]
+struct XIMStyles {
+    count_styles: raw::c_ushort,
+    supported_styles: *mut XIMStyle,
+}
+
+// Preedit at a spot the application moves with the text cursor, falling
+// back to styles where the input method places its window itself
+const STYLES: [XIMStyle; 3] = [
+    (xlib::XIMPreeditPosition | xlib::XIMStatusNothing) as XIMStyle,
+    (xlib::XIMPreeditNothing | xlib::XIMStatusNothing) as XIMStyle,
+    (xlib::XIMPreeditNone | xlib::XIMStatusNone) as XIMStyle,
+];
+
+impl InputMethod {
+    /// Open the input method named by $XMODIFIERS, None when no input
+    /// method server is running
+    pub unsafe fn open(display: *mut xlib::Display) -> Option<InputMethod> {
+        init_locale();
+        xlib::XSetLocaleModifiers(b"\0".as_ptr() as *const raw::c_char);
+
+        let im = xlib::XOpenIM(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+        if im.is_null() {
+            log::info!("no X input method available");
+            return None;
+        }
+        Some(InputMethod(im))
+    }
+
+    /// The first of STYLES the input method supports
+    unsafe fn style(&self) -> Option<XIMStyle> {
+        let mut styles: *mut XIMStyles = ptr::null_mut();
+        let failed = xlib::XGetIMValues(
+            self.0,
+            xlib::XNQueryInputStyle_0.as_ptr(),
+            &mut styles as *mut *mut XIMStyles,
+            ptr::null_mut::<raw::c_void>(),
+        );
+        if !failed.is_null() || styles.is_null() {
+            return None;
+        }
+        let supported =
+            std::slice::from_raw_parts((*styles).supported_styles, (*styles).count_styles as usize);
+        let style = STYLES
+            .iter()
+            .copied()
+            .find(|style| supported.contains(style));
+        xlib::XFree(styles as *mut raw::c_void);
+        style
+    }
+
+    /// Create a context for the window, positioned at the cursor area
+    /// if the input method draws its preedit at a spot
+    pub unsafe fn create_context(
+        &self,
+        window: xlib::Window,
+        cursor_area: Option<Rect>,
+    ) -> Option<InputContext> {
+        let style = self.style()?;
+        let ic = if style & xlib::XIMPreeditPosition as XIMStyle != 0 {
+            let mut spot = spot(cursor_area.unwrap_or(Rect {
+                x: 0,
+                y: 0,
+                width: 0,
+                height: 0,
+            }));
+            let attributes = xlib::XVaCreateNestedList(
+                0,
+                xlib::XNSpotLocation_0.as_ptr(),
+                &mut spot as *mut xlib::XPoint,
+                ptr::null_mut::<raw::c_void>(),
+            );
+            let ic = xlib::XCreateIC(
+                self.0,
+                xlib::XNInputStyle_0.as_ptr(),
+                style,
+                xlib::XNClientWindow_0.as_ptr(),
+                window,
+                xlib::XNFocusWindow_0.as_ptr(),
+                window,
+                xlib::XNPreeditAttributes_0.as_ptr(),
+                attributes,
+                ptr::null_mut::<raw::c_void>(),
+            );
+            xlib::XFree(attributes);
+            ic
+        } else {
+            xlib::XCreateIC(
+                self.0,
+                xlib::XNInputStyle_0.as_ptr(),
+                style,
+                xlib::XNClientWindow_0.as_ptr(),
+                window,
+                xlib::XNFocusWindow_0.as_ptr(),
+                window,
+                ptr::null_mut::<raw::c_void>(),
+            )
+        };
+        if ic.is_null() {
+            log::warn!("failed to create an input context");
+            return None;
+        }
+        Some(InputContext(ic))
+    }
+}
+
+impl Drop for InputMethod {
+    fn drop(&mut self) {
+        unsafe {
+            xlib::XCloseIM(self.0);
+        }
+    }
+}
+
+impl InputContext {
+    /// Tell the input method the window receives composed text
+    pub unsafe fn set_focus(self, focused: bool) {
+        if focused {
+            xlib::XSetICFocus(self.0);
+        } else {
+            xlib::XUnsetICFocus(self.0);
+        }
+    }
+
+    /// Move the preedit spot, input methods without spot placement
+    /// ignore it
+    pub unsafe fn set_cursor_area(self, area: Rect) {
+        let mut spot = spot(area);
+        let attributes = xlib::XVaCreateNestedList(
+            0,
+            xlib::XNSpotLocation_0.as_ptr(),
+            &mut spot as *mut xlib::XPoint,
+            ptr::null_mut::<raw::c_void>(),
+        );
+        xlib::XSetICValues(
+            self.0,
+            xlib::XNPreeditAttributes_0.as_ptr(),
+            attributes,
+            ptr::null_mut::<raw::c_void>(),
+        );
+        xlib::XFree(attributes);
+    }
+
+    /// The text a key press typed or the input method committed, control
+    /// characters such as Return and BackSpace are left to KeyboardInput
+    pub unsafe fn lookup(self, key: &mut xlib::XKeyEvent) -> Option<String> {
+        let mut buffer = vec![0u8; 64];
+        let mut status = 0;
+        let mut len = 0;
+        for _ in 0..2 {
+            len = xlib::Xutf8LookupString(
+                self.0,
+                key,
+                buffer.as_mut_ptr() as *mut raw::c_char,
+                buffer.len() as raw::c_int,
+                ptr::null_mut(),
+                &mut status,
+            );
+            if status != xlib::XBufferOverflow {
+                break;
+            }
+            buffer.resize(len as usize, 0);
+        }
+        if status != xlib::XLookupChars && status != xlib::XLookupBoth {
+            return None;
+        }
+        buffer.truncate(len.max(0) as usize);
+        let text = String::from_utf8(buffer).ok()?;
+        if text.chars().all(char::is_control) {
+            return None;
+        }
+        Some(text)
+    }
+
+    pub unsafe fn destroy(self) {
+        xlib::XDestroyIC(self.0);
+    }
+}
+
+/// Take the character set from the environment unless the application
+/// set a locale. Xlib picks input methods and encodes text by LC_CTYPE,
+/// which stays "C" otherwise
+pub(crate) unsafe fn init_locale() {
+    let current = libc::setlocale(libc::LC_CTYPE, ptr::null());
+    if current.is_null() || CStr::from_ptr(current).to_bytes() == b"C" {
+        libc::setlocale(libc::LC_CTYPE, b"\0".as_ptr() as *const raw::c_char);
+    }
+}
+
+/// The preedit spot is the baseline origin of the text, below the area
+/// keeps the candidate window from covering it
+fn spot(area: Rect) -> xlib::XPoint {
+    let clamp = |v: i32| v.clamp(raw::c_short::MIN as i32, raw::c_short::MAX as i32);
+    xlib::XPoint {
+        x: clamp(area.x) as raw::c_short,
+        y: clamp(area.y + area.height as i32) as raw::c_short,
+    }
+}
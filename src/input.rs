@@ -0,0 +1,240 @@
+//! Keyboard and mouse state sampled once per frame, for code such as game
+//! movement that asks what is held rather than reacting to each event
+
+use std::sync::{Arc, Mutex};
+
+use x11::xlib;
+
+use crate::dpi::PhysicalPosition;
+use crate::event::{DeviceEvent, DeviceId, Event, WindowId};
+
+// X keycodes run from 8 to 255
+const KEYCODES: usize = 256;
+// Master pointers followed one by one, more seats than this are rare
+const POINTERS: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+struct Pointer {
+    device: DeviceId,
+    buttons: u32,
+    cursor: Option<(WindowId, PhysicalPosition<f64>)>,
+}
+
+/// Keyboard and mouse state at the time of EventLoop::input_snapshot.
+///
+/// Held keys, buttons and the cursor reflect every event translated
+/// before the snapshot, the deltas add up everything since the previous
+/// snapshot. Events filtered out through EventLoop::set_event_filter are
+/// not seen either. Keys and buttons held when focus is lost count as
+/// released, and those still held when it returns as held again.
+///
+/// Buttons and the cursor are those of the core pointer, with several
+/// master pointers the others are asked for with is_button_down_on and
+/// cursor_of
+#[derive(Debug, Clone, Copy)]
+pub struct InputSnapshot {
+    // Keysym each held key produced when pressed by keycode, 0 when up
+    keys: [xlib::KeySym; KEYCODES],
+    buttons: u32,
+    // Every master pointer seen so far, the first of them oldest
+    pointers: [Option<Pointer>; POINTERS],
+    /// The window under the core pointer with the position inside it,
+    /// from its last CursorMoved
+    pub cursor: Option<(WindowId, PhysicalPosition<f64>)>,
+    /// Core pointer motion in pixels since the previous snapshot
+    pub cursor_delta: (f64, f64),
+    /// Unaccelerated motion since the previous snapshot, only counted
+    /// while EventLoop::listen_device_events delivers motion
+    pub raw_delta: (f64, f64),
+    /// Scrolling in pixels since the previous snapshot, positive values
+    /// scroll up and left
+    pub scroll: (f64, f64),
+}
+
+impl InputSnapshot {
+    fn new() -> InputSnapshot {
+        InputSnapshot {
+            keys: [0; KEYCODES],
+            buttons: 0,
+            pointers: [None; POINTERS],
+            cursor: None,
+            cursor_delta: (0.0, 0.0),
+            raw_delta: (0.0, 0.0),
+            scroll: (0.0, 0.0),
+        }
+    }
+
+    /// Whether the physical key is held, independent of the layout
+    pub fn is_key_down(&self, keycode: u32) -> bool {
+        self.keys
+            .get(keycode as usize)
+            .is_some_and(|&keysym| keysym != 0)
+    }
+
+    /// Whether a held key produced the keysym when it was pressed, e.g.
+    /// x11::keysym::XK_w wherever the layout puts it
+    pub fn is_keysym_down(&self, keysym: xlib::KeySym) -> bool {
+        keysym != 0 && self.keys.contains(&keysym)
+    }
+
+    /// Keycodes of the held keys along with the keysym they produced
+    pub fn keys_down(&self) -> impl Iterator<Item = (u32, xlib::KeySym)> + '_ {
+        self.keys
+            .iter()
+            .enumerate()
+            .filter(|(_, &keysym)| keysym != 0)
+            .map(|(keycode, &keysym)| (keycode as u32, keysym))
+    }
+
+    /// Whether the mouse button is held, 1 is the left button
+    pub fn is_button_down(&self, button: u32) -> bool {
+        button < 32 && self.buttons & (1 << button) != 0
+    }
+
+    /// Whether the mouse button is held on the master pointer, see
+    /// EventLoop::master_pointers
+    pub fn is_button_down_on(&self, device: DeviceId, button: u32) -> bool {
+        button < 32
+            && self
+                .pointer(device)
+                .is_some_and(|pointer| pointer.buttons & (1 << button) != 0)
+    }
+
+    /// The window under the master pointer with the position inside it
+    pub fn cursor_of(&self, device: DeviceId) -> Option<(WindowId, PhysicalPosition<f64>)> {
+        self.pointer(device).and_then(|pointer| pointer.cursor)
+    }
+
+    fn pointer(&self, device: DeviceId) -> Option<&Pointer> {
+        self.pointers
+            .iter()
+            .flatten()
+            .find(|pointer| pointer.device == device)
+    }
+
+    // The state of the master pointer, taking the place of the oldest
+    // one when all are in use
+    fn pointer_mut(&mut self, device: DeviceId) -> &mut Pointer {
+        let index = match self
+            .pointers
+            .iter()
+            .position(|pointer| pointer.is_none_or(|pointer| pointer.device == device))
+        {
+            Some(index) => index,
+            None => {
+                self.pointers.rotate_left(1);
+                self.pointers[POINTERS - 1] = None;
+                POINTERS - 1
+            }
+        };
+        self.pointers[index].get_or_insert(Pointer {
+            device,
+            buttons: 0,
+            cursor: None,
+        })
+    }
+
+    fn update(&mut self, event: &Event, core: DeviceId) {
+        match *event {
+            Event::KeyboardInput {
+                keycode,
+                keysym,
+                pressed,
+                ..
+            } => {
+                if let Some(key) = self.keys.get_mut(keycode as usize) {
+                    // Keys without a keysym still count as held
+                    *key = if pressed { keysym.max(1) } else { 0 };
+                }
+            }
+            Event::MouseInput {
+                button,
+                pressed,
+                device,
+                ..
+            } if button < 32 => {
+                let pointer = self.pointer_mut(device);
+                if pressed {
+                    pointer.buttons |= 1 << button;
+                } else {
+                    pointer.buttons &= !(1 << button);
+                }
+                if device == core {
+                    self.buttons = pointer.buttons;
+                }
+            }
+            Event::CursorMoved {
+                window,
+                position,
+                delta,
+                device,
+                ..
+            } => {
+                self.pointer_mut(device).cursor = Some((window, position));
+                if device == core {
+                    self.cursor = Some((window, position));
+                    self.cursor_delta.0 += delta.0;
+                    self.cursor_delta.1 += delta.1;
+                }
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::Motion { delta },
+                ..
+            } => {
+                self.raw_delta.0 += delta.0;
+                self.raw_delta.1 += delta.1;
+            }
+            Event::MouseWheel { delta, .. } => {
+                let (x, y) = delta.to_pixels();
+                self.scroll.0 += x;
+                self.scroll.1 += y;
+            }
+            Event::Destroyed { window } => {
+                if self.cursor.is_some_and(|(w, ..)| w == window) {
+                    self.cursor = None;
+                }
+                for pointer in self.pointers.iter_mut().flatten() {
+                    if pointer.cursor.is_some_and(|(w, ..)| w == window) {
+                        pointer.cursor = None;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Samples the input state of an EventLoop from any thread, e.g. a
+/// render thread, see EventLoop::input_handle
+#[derive(Debug, Clone)]
+pub struct InputHandle {
+    state: Arc<Mutex<InputSnapshot>>,
+}
+
+impl InputHandle {
+    pub(crate) fn new() -> InputHandle {
+        InputHandle {
+            state: Arc::new(Mutex::new(InputSnapshot::new())),
+        }
+    }
+
+    /// The current state, resetting the deltas it returns. A poll
+    /// applies its events all at once, so the snapshot never holds half
+    /// of one
+    pub fn snapshot(&self) -> InputSnapshot {
+        let mut state = self.state.lock().unwrap();
+        let snapshot = *state;
+        state.cursor_delta = (0.0, 0.0);
+        state.raw_delta = (0.0, 0.0);
+        state.scroll = (0.0, 0.0);
+        snapshot
+    }
+
+    /// Apply the events of one poll, `core` being the core pointer
+    pub(crate) fn update(&self, events: &[Event], core: DeviceId) {
+        let mut state = self.state.lock().unwrap();
+        for event in events {
+            state.update(event, core);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Labels of keys in the active keyboard layout, for showing bindings to
+//! the user
+
+use std::ffi::CStr;
+use std::os::raw;
+
+use x11::{keysym, xlib};
+
+use crate::ime;
+use crate::xconn::XConnection;
+
+// From X11/XKB.h
+const XKB_USE_CORE_KBD: raw::c_uint = 0x0100;
+
+/// The label printed on the key in the current layout group, e.g. "Ö" on
+/// German layouts, "Num 7" for keypad keys and the keysym name for keys
+/// without text such as "XF86AudioPlay"
+pub(crate) fn key_label(conn: &XConnection, keycode: u32) -> String {
+    let sym = match keycode_to_keysym(conn, keycode) {
+        Some(sym) => sym,
+        None => return format!("Key {}", keycode),
+    };
+    unsafe {
+        if let Some(base) = dead_key_base(sym) {
+            return base.to_string();
+        }
+        let keypad =
+            (keysym::XK_KP_Space as xlib::KeySym..=keysym::XK_KP_9 as xlib::KeySym).contains(&sym);
+        match keysym_text(conn, sym) {
+            Some(text) if keypad => format!("Num {}", text),
+            Some(text) => text.to_uppercase(),
+            None => {
+                let name = xlib::XKeysymToString(sym);
+                if name.is_null() {
+                    return format!("Key {}", keycode);
+                }
+                let name = CStr::from_ptr(name).to_string_lossy();
+                match name.strip_prefix("KP_") {
+                    Some(name) => format!("Num {}", name),
+                    None => name.into_owned(),
+                }
+            }
+        }
+    }
+}
+
+/// The keysym of the key without modifiers in the current layout group,
+/// None for keycodes the layout leaves unmapped
+pub(crate) fn keycode_to_keysym(conn: &XConnection, keycode: u32) -> Option<xlib::KeySym> {
+    if keycode > u8::MAX as u32 {
+        return None;
+    }
+    unsafe {
+        let mut state: xlib::XkbStateRec = std::mem::zeroed();
+        xlib::XkbGetState(conn.display, XKB_USE_CORE_KBD, &mut state);
+        // Groups without a symbol for the key fall back to the first
+        [state.group as raw::c_int, 0]
+            .iter()
+            .map(|&group| xlib::XkbKeycodeToKeysym(conn.display, keycode as u8, group, 0))
+            .find(|&sym| sym != 0)
+    }
+}
+
+/// A keycode producing the keysym in the current mapping, at any level
+/// or group. None when no key of the layout does, where Xlib gives 0
+pub(crate) fn keysym_to_keycode(conn: &XConnection, keysym: xlib::KeySym) -> Option<u32> {
+    match unsafe { xlib::XKeysymToKeycode(conn.display, keysym) } {
+        0 => None,
+        keycode => Some(keycode as u32),
+    }
+}
+
+/// Modifier and lock keys, which never repeat
+pub(crate) fn is_modifier(keysym: xlib::KeySym) -> bool {
+    let range = |first: u32, last: u32| first as xlib::KeySym..=last as xlib::KeySym;
+    range(keysym::XK_Shift_L, keysym::XK_Hyper_R).contains(&keysym)
+        || range(keysym::XK_ISO_Lock, keysym::XK_ISO_Level5_Lock).contains(&keysym)
+        || keysym == keysym::XK_Mode_switch as xlib::KeySym
+        || keysym == keysym::XK_Num_Lock as xlib::KeySym
+}
+
+/// The printable text of the keysym, None for function and control keys
+unsafe fn keysym_text(conn: &XConnection, mut sym: xlib::KeySym) -> Option<String> {
+    // Xkb encodes the text in the locale's character set
+    ime::init_locale();
+    let mut buffer = [0u8; 16];
+    let mut extra = 0;
+    let len = xlib::XkbTranslateKeySym(
+        conn.display,
+        &mut sym,
+        0,
+        buffer.as_mut_ptr() as *mut raw::c_char,
+        buffer.len() as raw::c_int,
+        &mut extra,
+    );
+    if len <= 0 {
+        return None;
+    }
+    let text = std::str::from_utf8(&buffer[..len as usize]).ok()?;
+    if text.chars().any(char::is_control) || text.trim().is_empty() {
+        return None;
+    }
+    Some(text.to_string())
+}
+
+/// The spacing accent a dead key types when pressed twice, which is
+/// what layouts print on the key
+fn dead_key_base(sym: xlib::KeySym) -> Option<char> {
+    let base = match sym as raw::c_uint {
+        keysym::XK_dead_grave => '`',
+        keysym::XK_dead_acute => '´',
+        keysym::XK_dead_circumflex => '^',
+        keysym::XK_dead_tilde => '~',
+        keysym::XK_dead_macron => '¯',
+        keysym::XK_dead_breve => '˘',
+        keysym::XK_dead_abovedot => '˙',
+        keysym::XK_dead_diaeresis => '¨',
+        keysym::XK_dead_abovering => '°',
+        keysym::XK_dead_doubleacute => '˝',
+        keysym::XK_dead_caron => 'ˇ',
+        keysym::XK_dead_cedilla => '¸',
+        keysym::XK_dead_ogonek => '˛',
+        _ => return None,
+    };
+    Some(base)
+}
@@ -0,0 +1,122 @@
+//! Minimal X11 windowing for wgpu applications
+//!
+//! Most applications only need the types of the prelude:
+//! `use daswin::prelude::*`.
+//!
+//! # Stability
+//!
+//! daswin follows semantic versioning with the 0.x convention: breaking
+//! changes bump the minor version, everything else the patch version.
+//! Adding variants to the enums marked `#[non_exhaustive]`, such as
+//! Event and Error, and adding methods or builder options is not a
+//! breaking change. Builder defaults are documented on the builders and
+//! only change with the minor version. Event tags of the C interface
+//! and of recordings are only ever appended to, so older files replay
+//! and older C callers keep working
+//!
+//! # Teardown
+//!
+//! Everything may be dropped in any order. Windows, proxies, GPU contexts
+//! and surfaces each hold the X connection, so the display closes after
+//! the last of them, whether the EventLoop goes first or not. Surfaces
+//! drop before their window in WindowSurface and RenderSurface, input
+//! contexts are destroyed with their window and the input method just
+//! before the display closes. A WindowProxy outliving its window does
+//! nothing, a ChannelProxy outliving its loop returns EventLoopClosed.
+//! X errors are recorded rather than fatal, see EventLoop::take_x_errors
+
+pub mod backend;
+mod channel;
+mod clock;
+mod conn_thread;
+#[cfg(feature = "dbus")]
+mod dbus;
+mod debug;
+pub mod device;
+#[cfg(feature = "dbus")]
+pub mod dialogs;
+pub mod dpi;
+mod error;
+pub mod event;
+pub mod event_loop;
+mod ext;
+#[cfg(feature = "capi")]
+pub mod ffi;
+mod foreign;
+mod helper;
+mod history;
+mod hotkey;
+mod icon;
+mod ime;
+mod input;
+mod keyboard;
+mod metrics;
+pub mod monitor;
+mod persist;
+pub mod prelude;
+mod present;
+mod proxy;
+pub mod record;
+pub mod render;
+mod resize;
+#[cfg(feature = "scripting")]
+pub mod script;
+mod scroll;
+mod seat;
+mod session;
+mod signal;
+#[cfg(feature = "dbus")]
+mod sleep;
+mod snap;
+#[cfg(feature = "async")]
+mod stream;
+mod tablet;
+#[cfg(feature = "dbus")]
+mod tray;
+mod wake;
+mod watchdog;
+pub mod window;
+pub mod wm;
+mod xconn;
+mod xembed;
+
+pub use backend::{Backend, BackendError};
+#[cfg(feature = "async")]
+pub use channel::{AsyncChannelProxy, SendMessage};
+pub use channel::{ChannelError, ChannelProxy, EventLoopClosed};
+pub use clock::{EventTime, ServerClock};
+pub use device::{DeviceInfo, DeviceType};
+pub use dpi::{
+    LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize, Size, DEFAULT_MIN_INNER_SIZE,
+    DEFAULT_SIZE_FRACTION,
+};
+pub use error::{Error, XError};
+pub use event::{
+    ChannelId, DeviceEvent, DeviceId, Edge, Event, HotkeyId, ReadbackId, ScrollDelta, StateChange,
+    TabletTool, WindowId,
+};
+pub use event_loop::{
+    DeviceEvents, EventFilter, EventLoop, EventLoopBuilder, KeyRepeat, PanicPolicy, PointerState,
+    PumpStatus, QueuePolicy,
+};
+pub use helper::HelperWindow;
+pub use history::TimestampedEvent;
+pub use icon::Icon;
+pub use input::{InputHandle, InputSnapshot};
+pub use metrics::{EventLoopMetrics, MetricsHandle, Wakeups};
+pub use monitor::{default_inner_size, MonitorHandle, VideoMode};
+pub use persist::SavedWindowState;
+pub use proxy::WindowProxy;
+#[cfg(feature = "scripting")]
+pub use script::{ScriptError, ScriptHost};
+pub use session::SessionGuard;
+pub use snap::Snapping;
+#[cfg(feature = "async")]
+pub use stream::{EventStream, NextEvent};
+#[cfg(feature = "dbus")]
+pub use tray::{MenuItem, StatusIcon};
+pub use window::{
+    CursorIcon, Fullscreen, Gravity, Position, Protection, Rect, SoftwareCursor, WindowBuilder,
+    X11Window,
+};
+pub use wm::WmInfo;
@@ -1,252 +1,263 @@
-use pollster::block_on;
 use std::borrow::Cow;
-use std::ffi::{c_void, CString};
-use std::mem;
-use std::os::raw;
-use std::ptr;
-use wgpu;
-
-use raw_window_handle::{
-    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle, XlibDisplayHandle,
-    XlibWindowHandle,
-};
-use x11::xlib;
-
-/// Provides a basic framework for connecting to an X Display,
-/// creating a window, displaying it and running the event loop
-pub struct X11Window {
-    pub display: *mut xlib::Display,
-    pub window: xlib::Window,
-    pub screen: i32,
-
-    wm_protocols: xlib::Atom,
-    wm_delete_window: xlib::Atom,
+use std::env;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use daswin::prelude::*;
+#[cfg(feature = "scripting")]
+use daswin::ScriptHost;
+
+const FRAME_TIME: Duration = Duration::from_micros(16_667);
+// Between attempts to reach the X server again after losing it
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Parameters of demo.wgsl, a script may animate them
+struct Params {
+    color: [f32; 4],
+    scale: f32,
+    angle: f32,
 }
 
-impl X11Window {
-    /// Create a new window with a given title and size
-    pub fn new(title: &str, width: u32, height: u32) -> X11Window {
-        unsafe {
-            // Open display connection.
-            let display = xlib::XOpenDisplay(ptr::null());
-
-            if display.is_null() {
-                panic!("XOpenDisplay failed");
-            }
-
-            // Create window.
-            let screen = xlib::XDefaultScreen(display);
-            let root = xlib::XRootWindow(display, screen);
-
-            let mut attributes: xlib::XSetWindowAttributes =
-                mem::MaybeUninit::uninit().assume_init();
-            attributes.background_pixel = xlib::XWhitePixel(display, screen);
-
-            let window = xlib::XCreateWindow(
-                display,
-                root,
-                0,
-                0,
-                width,
-                height,
-                0,
-                0,
-                xlib::InputOutput as raw::c_uint,
-                ptr::null_mut(),
-                xlib::CWBackPixel,
-                &mut attributes,
-            );
-
-            // Set window title.
-            let title_str = CString::new(title).unwrap();
-            xlib::XStoreName(display, window, title_str.as_ptr() as *mut raw::c_char);
-
-            // Hook close requests.
-            let wm_protocols_str = CString::new("WM_PROTOCOLS").unwrap();
-            let wm_delete_window_str = CString::new("WM_DELETE_WINDOW").unwrap();
-
-            let wm_protocols = xlib::XInternAtom(display, wm_protocols_str.as_ptr(), xlib::False);
-            let wm_delete_window =
-                xlib::XInternAtom(display, wm_delete_window_str.as_ptr(), xlib::False);
-
-            let mut protocols = [wm_delete_window];
-
-            xlib::XSetWMProtocols(
-                display,
-                window,
-                protocols.as_mut_ptr(),
-                protocols.len() as raw::c_int,
-            );
-
-            X11Window {
-                display,
-                window,
-                screen,
-                wm_protocols,
-                wm_delete_window,
-            }
+impl Params {
+    #[cfg(feature = "scripting")]
+    fn from_script(host: &ScriptHost) -> Params {
+        let uniform = |name, default| host.uniform(name).map_or(default, |value| value as f32);
+        Params {
+            color: [
+                uniform("red", 1.0),
+                uniform("green", 0.0),
+                uniform("blue", 0.0),
+                uniform("alpha", 1.0),
+            ],
+            scale: uniform("scale", 1.0),
+            angle: uniform("angle", 0.0),
         }
     }
 
-    /// Display the window
-    pub fn show(&mut self) {
-        unsafe {
-            xlib::XMapWindow(self.display, self.window);
-        }
-    }
-
-    /// Poll for events
-    pub fn poll(&mut self, event: &mut xlib::XEvent) {
-        unsafe {
-            while xlib::XPending(self.display) != 0 {
-                xlib::XNextEvent(self.display, event);
-                // discard events to other windows
-                if xlib::XFilterEvent(event, self.window) != 0 {
-                    continue;
-                }
-                match event.get_type() {
-                    xlib::ClientMessage => {}
-                    xlib::KeyPress => {}
-                    xlib::KeyRelease => {}
-                    xlib::ButtonPress => {}
-                    xlib::ButtonRelease => {}
-                    xlib::MotionNotify => {}
-                    _ => {}
-                }
-            }
-        };
+    // Laid out as the struct of the shader, padded to 16 bytes
+    fn to_bytes(&self) -> Vec<u8> {
+        let [r, g, b, a] = self.color;
+        [r, g, b, a, self.scale, self.angle, 0.0, 0.0]
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect()
     }
 }
 
-unsafe impl HasRawWindowHandle for X11Window {
-    fn raw_window_handle(&self) -> RawWindowHandle {
-        let mut xlib_handle = XlibWindowHandle::empty();
-        xlib_handle.visual_id = 0;
-        xlib_handle.window = self.window;
-        RawWindowHandle::Xlib(xlib_handle)
-    }
-}
-
-unsafe impl HasRawDisplayHandle for X11Window {
-    fn raw_display_handle(&self) -> RawDisplayHandle {
-        let mut xlib_handle = XlibDisplayHandle::empty();
-        xlib_handle.display = self.display as *mut c_void;
-        xlib_handle.screen = self.screen;
-        RawDisplayHandle::Xlib(xlib_handle)
+impl Default for Params {
+    fn default() -> Params {
+        Params {
+            color: [1.0, 0.0, 0.0, 1.0],
+            scale: 1.0,
+            angle: 0.0,
+        }
     }
 }
 
-impl Drop for X11Window {
-    /// Destroys the window and disconnects from the display
-    fn drop(&mut self) {
-        unsafe {
-            xlib::XDestroyWindow(self.display, self.window);
-            xlib::XCloseDisplay(self.display);
+#[cfg(feature = "scripting")]
+fn start_script(event_loop: &EventLoop, window: &X11Window, path: Option<&str>) -> ScriptHost {
+    let mut host = ScriptHost::new(event_loop, window);
+    if let Some(path) = path {
+        if let Err(err) = host.load_script(path) {
+            eprintln!("failed to read {}: {}", path, err);
         }
     }
+    host
 }
 
-fn main() {
-    let width = 800;
-    let height = 600;
-    let mut window = X11Window::new("hello-sailor", width, height);
-    window.show();
-
-    // init wgpu
-    let instance = wgpu::Instance::new(wgpu::Backends::all());
-    let surface = unsafe { instance.create_surface(&window) };
-    let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-        power_preference: wgpu::PowerPreference::default(),
-        // Request an adapter which can render to our surface
-        compatible_surface: Some(&surface),
-        force_fallback_adapter: false,
-    }))
-    .expect("Failed to find an appropriate adapter");
-
-    // Create the logical device and command queue
-    let (device, queue) = block_on(adapter.request_device(
-        &wgpu::DeviceDescriptor {
-            label: None,
-            features: wgpu::Features::empty(),
-            limits: wgpu::Limits::default(),
-        },
-        None,
-    ))
-    .expect("Failed to create device");
-
-    // Load the shaders from disk
-    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: None,
-        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
-    });
-
+fn create_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
     let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: None,
-        bind_group_layouts: &[],
+        bind_group_layouts: &[bind_group_layout],
         push_constant_ranges: &[],
     });
 
-    let swapchain_format = surface.get_supported_formats(&adapter)[0];
-
-    let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: None,
         layout: Some(&pipeline_layout),
         vertex: wgpu::VertexState {
-            module: &shader,
+            module: shader,
             entry_point: "vs_main",
             buffers: &[],
         },
         fragment: Some(wgpu::FragmentState {
-            module: &shader,
+            module: shader,
             entry_point: "fs_main",
-            targets: &[Some(swapchain_format.into())],
+            targets: &[Some(format.into())],
         }),
         primitive: wgpu::PrimitiveState::default(),
         depth_stencil: None,
         multisample: wgpu::MultisampleState::default(),
         multiview: None,
-    });
+    })
+}
 
-    let config = wgpu::SurfaceConfiguration {
-        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-        format: swapchain_format,
-        width,
-        height,
-        present_mode: wgpu::PresentMode::AutoVsync,
-        alpha_mode: surface.get_supported_alpha_modes(&adapter)[0],
+fn main() {
+    // --script <file> animates the demo from a script such as
+    // demo.script, written in the language of ScriptHost and reloaded on
+    // save
+    let args: Vec<String> = env::args().collect();
+    let script = match &args[1..] {
+        [flag, path] if flag == "--script" => Some(path.clone()),
+        [] => None,
+        _ => {
+            eprintln!("usage: daswin [--script <file>]");
+            return;
+        }
     };
+    if cfg!(not(feature = "scripting")) && script.is_some() {
+        eprintln!("built without the scripting feature, --script is ignored");
+    }
 
-    surface.configure(&device, &config);
-    // Main loop.
-    let mut event: xlib::XEvent = unsafe { mem::MaybeUninit::uninit().assume_init() };
+    let width = 800;
+    let height = 600;
+    let mut event_loop = EventLoop::new();
+    let mut window = X11Window::new(&event_loop, "hello-sailor", (width, height));
+    // Map once there is a frame, so the window never shows up blank
+    window.on_first_present(|window| window.show());
 
-    loop {
-        window.poll(&mut event);
+    // init wgpu, the window is owned by the bundle so it outlives its surface
+    let (gpu, mut window) = GpuContext::with_window(window);
+    #[cfg(feature = "dbus")]
+    event_loop.listen_sleep_events();
+    event_loop.set_connection_timeout(Some(Duration::from_secs(5)));
 
-        match event.get_type() {
-            xlib::ClientMessage => {
-                let xclient = xlib::XClientMessageEvent::from(event);
+    #[cfg(feature = "scripting")]
+    let mut host = start_script(&event_loop, window.window(), script.as_deref());
 
-                if xclient.message_type == window.wm_protocols && xclient.format == 32 {
-                    let protocol = xclient.data.get_long(0) as xlib::Atom;
+    // Load the shaders from disk
+    let shader = gpu
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("demo.wgsl"))),
+        });
+    let params = gpu.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("params"),
+        size: 32,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let bind_group_layout = gpu
+        .device
+        .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+    let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: params.as_entire_binding(),
+        }],
+    });
 
-                    if protocol == window.wm_delete_window {
-                        break;
+    let mut pipeline_format = window.surface().format();
+    let mut render_pipeline =
+        create_pipeline(&gpu.device, &shader, &bind_group_layout, pipeline_format);
+
+    // Main loop, paced by hand to 60 frames per second: the tick in
+    // AboutToWait requests a redraw once the next frame is due and waits
+    // for events until then otherwise, the frame is drawn when the redraw
+    // arrives
+    let mut events = Vec::new();
+    let mut next_frame = Instant::now();
+    let mut connected = true;
+    loop {
+        if let PumpStatus::Exit { .. } = event_loop.pump_events(&mut events) {
+            break;
+        }
+        // Wait for the server to come back, e.g. after a crash or a
+        // dropped SSH tunnel, and carry on with a new window
+        if !connected {
+            match event_loop.reconnect() {
+                Ok(recreated) => {
+                    for (_, new_window) in recreated {
+                        drop(window.recreate(&gpu, new_window));
                     }
+                    // The host changed the window that is gone
+                    #[cfg(feature = "scripting")]
+                    {
+                        host = start_script(&event_loop, window.window(), script.as_deref());
+                    }
+                    connected = true;
+                }
+                Err(err) => {
+                    println!("{}, retrying", err);
+                    thread::sleep(RECONNECT_INTERVAL);
                 }
             }
+            continue;
+        }
+        let mut redraw = false;
+        for event in &events {
+            window.handle_event(&gpu, event);
+            match *event {
+                Event::ConnectionLost => connected = false,
+                Event::RedrawRequested { .. } => redraw = true,
+                #[cfg(feature = "scripting")]
+                Event::ScriptError { index } => {
+                    eprintln!("{}", event_loop.script_error(index).unwrap_or_default())
+                }
+                Event::AboutToWait => {
+                    let now = Instant::now();
+                    if now >= next_frame {
+                        window.window().request_redraw();
+                        next_frame = next_frame.max(now) + FRAME_TIME;
+                    } else {
+                        event_loop.wait_events(Some(next_frame - now));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !redraw {
+            continue;
+        }
+
+        let surface = window.surface_mut();
+        let frame = match surface.acquire(&gpu) {
+            Some(frame) => frame,
+            None => continue,
+        };
 
-            _ => (),
+        // The surface format can change when it is reconfigured
+        if surface.format() != pipeline_format {
+            pipeline_format = surface.format();
+            render_pipeline =
+                create_pipeline(&gpu.device, &shader, &bind_group_layout, pipeline_format);
         }
 
-        let frame = surface
-            .get_current_texture()
-            .expect("Failed to acquire next swap chain texture");
+        #[cfg(feature = "scripting")]
+        let (clear, frame_params) = {
+            let [r, g, b, a] = host.clear_color().unwrap_or([0.0, 1.0, 0.0, 1.0]);
+            (wgpu::Color { r, g, b, a }, Params::from_script(&host))
+        };
+        #[cfg(not(feature = "scripting"))]
+        let (clear, frame_params) = (wgpu::Color::GREEN, Params::default());
+        gpu.queue.write_buffer(&params, 0, &frame_params.to_bytes());
+
         let view = frame
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
-        let mut encoder =
-            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
@@ -254,18 +265,20 @@ fn main() {
                     view: &view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::GREEN),
+                        load: wgpu::LoadOp::Clear(clear),
                         store: true,
                     },
                 })],
                 depth_stencil_attachment: None,
             });
             rpass.set_pipeline(&render_pipeline);
+            rpass.set_bind_group(0, &bind_group, &[]);
             rpass.draw(0..3, 0..1);
         }
 
-        queue.submit(Some(encoder.finish()));
-        drop(view);
-        drop(frame);
+        gpu.queue.submit(Some(encoder.finish()));
+        window.present(frame);
     }
+
+    window.destroy(&gpu);
 }
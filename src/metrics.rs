@@ -0,0 +1,342 @@
+//! Counters of what an event loop delivered and where its time went, for
+//! diagnosing lag reported from the field, see EventLoop::metrics.
+//!
+//! Updated at the end of every poll with a few relaxed atomic additions
+//! per event, so they stay on in release builds
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::event::Event;
+
+/// Names of the event kinds counted, in the order of the Event variants
+const EVENT_KINDS: [&str; 39] = [
+    "WindowCreated",
+    "Destroyed",
+    "CloseRequested",
+    "Resized",
+    "Focused",
+    "StateChanged",
+    "KeyboardInput",
+    "Text",
+    "MouseInput",
+    "MouseWheel",
+    "CursorMoved",
+    "DeviceEvent",
+    "Tablet",
+    "ExitRequested",
+    "EventsLost",
+    "MonitorConfigurationChanged",
+    "WorkAreaChanged",
+    "MonitorChanged",
+    "FrameDeadlineMissed",
+    "GpuError",
+    "Suspending",
+    "Resumed",
+    "LateInput",
+    "RedrawRequested",
+    "AboutToWait",
+    "ConnectionLost",
+    "DeviceAdded",
+    "DeviceRemoved",
+    "TrayIconClicked",
+    "TrayMenuItem",
+    "FramePresented",
+    "CanvasViewportChanged",
+    "Snapped",
+    "EmbeddedChildDestroyed",
+    "Channel",
+    "GlobalHotkey",
+    "ScriptError",
+    "GpuReadbackReady",
+    "ModalBlocked",
+];
+
+fn kind(event: &Event) -> usize {
+    match event {
+        Event::WindowCreated { .. } => 0,
+        Event::Destroyed { .. } => 1,
+        Event::CloseRequested { .. } => 2,
+        Event::Resized { .. } => 3,
+        Event::Focused { .. } => 4,
+        Event::StateChanged { .. } => 5,
+        Event::KeyboardInput { .. } => 6,
+        Event::Text { .. } => 7,
+        Event::MouseInput { .. } => 8,
+        Event::MouseWheel { .. } => 9,
+        Event::CursorMoved { .. } => 10,
+        Event::DeviceEvent { .. } => 11,
+        Event::Tablet { .. } => 12,
+        Event::ExitRequested => 13,
+        Event::EventsLost(_) => 14,
+        Event::MonitorConfigurationChanged => 15,
+        Event::WorkAreaChanged => 16,
+        Event::MonitorChanged { .. } => 17,
+        Event::FrameDeadlineMissed { .. } => 18,
+        Event::GpuError { .. } => 19,
+        Event::Suspending => 20,
+        Event::Resumed => 21,
+        Event::LateInput => 22,
+        Event::RedrawRequested { .. } => 23,
+        Event::AboutToWait => 24,
+        Event::ConnectionLost => 25,
+        Event::DeviceAdded { .. } => 26,
+        Event::DeviceRemoved { .. } => 27,
+        Event::TrayIconClicked { .. } => 28,
+        Event::TrayMenuItem { .. } => 29,
+        Event::FramePresented { .. } => 30,
+        Event::CanvasViewportChanged { .. } => 31,
+        Event::Snapped { .. } => 32,
+        Event::EmbeddedChildDestroyed { .. } => 33,
+        Event::Channel(_) => 34,
+        Event::GlobalHotkey(_) => 35,
+        Event::ScriptError { .. } => 36,
+        Event::GpuReadbackReady(_) => 37,
+        Event::ModalBlocked { .. } => 38,
+    }
+}
+
+/// What made a poll deliver something besides AboutToWait
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Wakeup {
+    /// Events read from the X server
+    pub x_events: usize,
+    /// Synthesized key repeats or held resizes came due
+    pub timer: bool,
+    /// A channel was sent to
+    pub channel: bool,
+    /// SIGTERM or SIGINT arrived
+    pub signal: bool,
+}
+
+/// The counters of a loop, shared with its MetricsHandles
+pub(crate) struct Metrics {
+    started: Instant,
+    events: [AtomicU64; EVENT_KINDS.len()],
+    polls: AtomicU64,
+    x_events: AtomicU64,
+    max_x_events: AtomicU64,
+    max_batch: AtomicU64,
+    // Polls woken by the server, timers, channels and signals
+    wakeups: [AtomicU64; 4],
+    // Nanoseconds polling, in the handler of run and between polls
+    polling: AtomicU64,
+    handler: AtomicU64,
+    idle: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics {
+            started: Instant::now(),
+            events: std::array::from_fn(|_| AtomicU64::new(0)),
+            polls: AtomicU64::new(0),
+            x_events: AtomicU64::new(0),
+            max_x_events: AtomicU64::new(0),
+            max_batch: AtomicU64::new(0),
+            wakeups: Default::default(),
+            polling: AtomicU64::new(0),
+            handler: AtomicU64::new(0),
+            idle: AtomicU64::new(0),
+        }
+    }
+
+    /// Count the events of a poll which took `polling`, `idle` being the
+    /// time since the previous poll ended outside the handler
+    pub fn record_poll(&self, events: &[Event], wakeup: Wakeup, polling: Duration, idle: Duration) {
+        self.count(events);
+        self.polls.fetch_add(1, Ordering::Relaxed);
+        let x_events = wakeup.x_events as u64;
+        self.x_events.fetch_add(x_events, Ordering::Relaxed);
+        self.max_x_events.fetch_max(x_events, Ordering::Relaxed);
+        self.max_batch
+            .fetch_max(events.len() as u64, Ordering::Relaxed);
+        let reasons = [x_events > 0, wakeup.timer, wakeup.channel, wakeup.signal];
+        for (counter, woken) in self.wakeups.iter().zip(reasons) {
+            if woken {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.polling
+            .fetch_add(polling.as_nanos() as u64, Ordering::Relaxed);
+        self.idle
+            .fetch_add(idle.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Count delivered events, for batches besides those of poll_events
+    pub fn count(&self, events: &[Event]) {
+        for event in events {
+            self.events[kind(event)].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Count time spent in the handler of EventLoop::run
+    pub fn record_handler(&self, time: Duration) {
+        self.handler
+            .fetch_add(time.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> EventLoopMetrics {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let nanos = |counter: &AtomicU64| Duration::from_nanos(load(counter));
+        EventLoopMetrics {
+            uptime: self.started.elapsed(),
+            polls: load(&self.polls),
+            events: EVENT_KINDS
+                .iter()
+                .zip(&self.events)
+                .map(|(&name, count)| (name, load(count)))
+                .collect(),
+            x_events: load(&self.x_events),
+            max_x_events_per_poll: load(&self.max_x_events),
+            max_events_per_poll: load(&self.max_batch),
+            wakeups: Wakeups {
+                x_server: load(&self.wakeups[0]),
+                timers: load(&self.wakeups[1]),
+                channels: load(&self.wakeups[2]),
+                signals: load(&self.wakeups[3]),
+            },
+            polling: nanos(&self.polling),
+            handler: nanos(&self.handler),
+            idle: nanos(&self.idle),
+        }
+    }
+}
+
+/// Polls woken by each source, a poll woken by several counts for each
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Wakeups {
+    /// Events were read from the X server
+    pub x_server: u64,
+    /// Synthesized key repeats or held resizes came due
+    pub timers: u64,
+    /// A channel or proxy was sent to
+    pub channels: u64,
+    /// SIGTERM or SIGINT arrived, see EventLoop::catch_exit_signals
+    pub signals: u64,
+}
+
+/// The counters of a loop since it was created, see EventLoop::metrics.
+///
+/// Time is split between polling, the handler of EventLoop::run and
+/// idling, the rest of the time between polls. Loops driven by
+/// poll_events count their event handling as idle, along with the time
+/// blocked on vsync or waiting for events
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventLoopMetrics {
+    /// Time since the loop was created
+    pub uptime: Duration,
+    pub polls: u64,
+    /// Events delivered by kind, in the order of the Event variants
+    pub events: Vec<(&'static str, u64)>,
+    /// Events read from the X server, including those translated to
+    /// nothing
+    pub x_events: u64,
+    /// The most X events one poll read
+    pub max_x_events_per_poll: u64,
+    /// The most events one poll delivered, see
+    /// EventLoop::set_queue_policy
+    pub max_events_per_poll: u64,
+    pub wakeups: Wakeups,
+    pub polling: Duration,
+    pub handler: Duration,
+    pub idle: Duration,
+}
+
+impl EventLoopMetrics {
+    /// How many events of a kind were delivered, by variant name, e.g.
+    /// "CursorMoved"
+    pub fn event_count(&self, kind: &str) -> u64 {
+        self.events
+            .iter()
+            .find(|(name, _)| *name == kind)
+            .map_or(0, |&(_, count)| count)
+    }
+
+    /// The counters since an earlier snapshot of the same loop, e.g. for
+    /// rates per second. The maxima are kept, they cannot be taken apart
+    pub fn since(&self, earlier: &EventLoopMetrics) -> EventLoopMetrics {
+        let events = self
+            .events
+            .iter()
+            .map(|&(name, count)| (name, count.saturating_sub(earlier.event_count(name))))
+            .collect();
+        let (now, then) = (&self.wakeups, &earlier.wakeups);
+        EventLoopMetrics {
+            uptime: self.uptime.saturating_sub(earlier.uptime),
+            polls: self.polls.saturating_sub(earlier.polls),
+            events,
+            x_events: self.x_events.saturating_sub(earlier.x_events),
+            max_x_events_per_poll: self.max_x_events_per_poll,
+            max_events_per_poll: self.max_events_per_poll,
+            wakeups: Wakeups {
+                x_server: now.x_server.saturating_sub(then.x_server),
+                timers: now.timers.saturating_sub(then.timers),
+                channels: now.channels.saturating_sub(then.channels),
+                signals: now.signals.saturating_sub(then.signals),
+            },
+            polling: self.polling.saturating_sub(earlier.polling),
+            handler: self.handler.saturating_sub(earlier.handler),
+            idle: self.idle.saturating_sub(earlier.idle),
+        }
+    }
+
+    /// The counters as a JSON object on one line, durations in seconds.
+    /// Event kinds which never occurred are left out
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{");
+        let _ = write!(
+            json,
+            "\"uptime\":{},\"polls\":{},\"events\":{{",
+            self.uptime.as_secs_f64(),
+            self.polls
+        );
+        let events = self.events.iter().filter(|(_, count)| *count > 0);
+        for (i, (name, count)) in events.enumerate() {
+            let comma = if i > 0 { "," } else { "" };
+            let _ = write!(json, "{}\"{}\":{}", comma, name, count);
+        }
+        let _ = write!(
+            json,
+            "}},\"x_events\":{},\"max_x_events_per_poll\":{},\"max_events_per_poll\":{},\
+             \"wakeups\":{{\"x_server\":{},\"timers\":{},\"channels\":{},\"signals\":{}}},\
+             \"polling\":{},\"handler\":{},\"idle\":{}}}",
+            self.x_events,
+            self.max_x_events_per_poll,
+            self.max_events_per_poll,
+            self.wakeups.x_server,
+            self.wakeups.timers,
+            self.wakeups.channels,
+            self.wakeups.signals,
+            self.polling.as_secs_f64(),
+            self.handler.as_secs_f64(),
+            self.idle.as_secs_f64(),
+        );
+        json
+    }
+}
+
+/// Reads the metrics of an EventLoop from any thread, e.g. one serving
+/// them to a monitoring system, see EventLoop::metrics_handle
+#[derive(Clone)]
+pub struct MetricsHandle {
+    metrics: Arc<Metrics>,
+}
+
+impl MetricsHandle {
+    pub(crate) fn new(metrics: Arc<Metrics>) -> MetricsHandle {
+        MetricsHandle { metrics }
+    }
+
+    pub fn snapshot(&self) -> EventLoopMetrics {
+        self.metrics.snapshot()
+    }
+}
+
+impl std::fmt::Debug for MetricsHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("MetricsHandle").finish_non_exhaustive()
+    }
+}
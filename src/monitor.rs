@@ -0,0 +1,708 @@
+use std::cmp::Ordering;
+use std::ffi::CStr;
+use std::hash::{Hash, Hasher};
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
+
+use x11::xlib;
+use x11::xrandr::{
+    RRCrtc, RRCrtcChangeNotifyMask, RRMode, RROutput, RROutputChangeNotifyMask,
+    RRScreenChangeNotifyMask, RR_Rotate_270, RR_Rotate_90, Rotation, XRRModeInfo,
+};
+
+use crate::dpi::{
+    PhysicalPosition, PhysicalSize, Size, DEFAULT_MIN_INNER_SIZE, DEFAULT_SIZE_FRACTION,
+};
+use crate::ext::{self, Xrandr};
+use crate::session::{self, CrtcConfig, Undo};
+use crate::window::Rect;
+use crate::xconn::XConnection;
+
+/// A resolution and refresh rate supported by a monitor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VideoMode {
+    size: (u32, u32),
+    refresh_rate_millihertz: u32,
+    pub(crate) mode: RRMode,
+    pub(crate) crtc: RRCrtc,
+}
+
+impl VideoMode {
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size.into()
+    }
+
+    pub fn refresh_rate_millihertz(&self) -> u32 {
+        self.refresh_rate_millihertz
+    }
+}
+
+/// A monitor connected to the X screen.
+///
+/// Handles compare and hash by the RandR output, so a handle queried
+/// before a mode change equals one queried after it
+#[derive(Debug, Clone)]
+pub struct MonitorHandle {
+    pub(crate) output: RROutput,
+    name: String,
+    friendly_name: Option<String>,
+    physical_size_mm: Option<(u32, u32)>,
+    scale_factor: f64,
+    position: (i32, i32),
+    size: (u32, u32),
+    refresh_rate_millihertz: Option<u32>,
+    primary: bool,
+    video_modes: Vec<VideoMode>,
+    work_area: Rect,
+}
+
+impl PartialEq for MonitorHandle {
+    fn eq(&self, other: &MonitorHandle) -> bool {
+        self.output == other.output
+    }
+}
+
+impl Eq for MonitorHandle {}
+
+impl Hash for MonitorHandle {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.output.hash(state);
+    }
+}
+
+impl PartialOrd for MonitorHandle {
+    fn partial_cmp(&self, other: &MonitorHandle) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MonitorHandle {
+    fn cmp(&self, other: &MonitorHandle) -> Ordering {
+        self.output.cmp(&other.output)
+    }
+}
+
+impl MonitorHandle {
+    /// The RandR output id, for use with other X libraries and
+    /// EventLoop::monitor_from_raw. Without RandR this is 0
+    pub fn into_raw(&self) -> RROutput {
+        self.output
+    }
+
+    /// The output name, e.g. "DP-1"
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The model name from the monitor's EDID, e.g. "DELL U2720Q", or the
+    /// output name when the EDID has none
+    pub fn friendly_name(&self) -> &str {
+        self.friendly_name.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Width and height of the visible area in millimetres, from the EDID
+    /// or else as reported by RandR, in the monitor's current rotation
+    pub fn physical_size_mm(&self) -> Option<(u32, u32)> {
+        self.physical_size_mm
+    }
+
+    /// Horizontal pixels per inch at the current mode
+    pub fn dpi(&self) -> Option<f64> {
+        let (width_mm, _) = self.physical_size_mm?;
+        Some(self.size.0 as f64 * 25.4 / width_mm as f64)
+    }
+
+    /// The factor to scale UI by, Xft.dpi / 96 when the resource is set
+    /// and otherwise derived from the monitor's DPI in steps of 0.25
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Position of the monitor's top left corner on the X screen
+    pub fn position(&self) -> PhysicalPosition<i32> {
+        self.position.into()
+    }
+
+    /// Size of the monitor in pixels
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size.into()
+    }
+
+    pub fn refresh_rate_millihertz(&self) -> Option<u32> {
+        self.refresh_rate_millihertz
+    }
+
+    pub fn is_primary(&self) -> bool {
+        self.primary
+    }
+
+    /// The part of the monitor left free by panels and docks, as of the
+    /// current desktop's _NET_WORKAREA. The whole monitor without a window
+    /// manager setting it
+    pub fn work_area(&self) -> Rect {
+        self.work_area
+    }
+
+    /// The size of a fraction of the work area, at least a pixel, the
+    /// default size, or a physical or logical size at the monitor's scale
+    /// factor
+    pub fn resolve_size(&self, size: Size) -> PhysicalSize<u32> {
+        match size {
+            Size::Default => {
+                let min = DEFAULT_MIN_INNER_SIZE.to_physical(self.scale_factor);
+                default_inner_size(self.work_area, min)
+            }
+            Size::WorkArea(fraction) => {
+                let fraction = fraction.clamp(0.0, 1.0);
+                PhysicalSize::new(
+                    ((self.work_area.width as f64 * fraction).round() as u32).max(1),
+                    ((self.work_area.height as f64 * fraction).round() as u32).max(1),
+                )
+            }
+            size => size.to_physical(self.scale_factor),
+        }
+    }
+
+    /// The modes usable for exclusive fullscreen on this monitor
+    pub fn video_modes(&self) -> impl Iterator<Item = VideoMode> + '_ {
+        self.video_modes.iter().copied()
+    }
+}
+
+/// The size of Size::Default windows on a work area: DEFAULT_SIZE_FRACTION
+/// of it, at least `min` and at most the work area itself, which wins
+/// over `min` on screens too small for it. Scale `min` to the monitor
+/// beforehand, e.g. DEFAULT_MIN_INNER_SIZE.to_physical(scale_factor)
+pub fn default_inner_size(work_area: Rect, min: PhysicalSize<u32>) -> PhysicalSize<u32> {
+    let fit = |length: u32, min: u32| {
+        let length = length.max(1);
+        let share = (length as f64 * DEFAULT_SIZE_FRACTION).round() as u32;
+        share.max(min).min(length)
+    };
+    PhysicalSize::new(
+        fit(work_area.width, min.width),
+        fit(work_area.height, min.height),
+    )
+}
+
+/// RandR is usable when both the library and the server extension exist
+fn xrandr(conn: &XConnection) -> Option<&'static Xrandr> {
+    let xrandr = ext::xrandr()?;
+    let (mut event_base, mut error_base) = (0, 0);
+    let present =
+        unsafe { (xrandr.XRRQueryExtension)(conn.display, &mut event_base, &mut error_base) };
+    (present != 0).then_some(xrandr)
+}
+
+fn refresh_rate_millihertz(mode: &XRRModeInfo) -> Option<u32> {
+    let dots = mode.hTotal as u64 * mode.vTotal as u64;
+    if dots == 0 {
+        return None;
+    }
+    Some((mode.dotClock * 1000 / dots) as u32)
+}
+
+/// Get notified of monitor and mode changes, returns the RandR event base
+pub(crate) fn select_notify(conn: &XConnection) -> Option<i32> {
+    let xrandr = ext::xrandr()?;
+    let (mut event_base, mut error_base) = (0, 0);
+    unsafe {
+        if (xrandr.XRRQueryExtension)(conn.display, &mut event_base, &mut error_base) == 0 {
+            return None;
+        }
+        (xrandr.XRRSelectInput)(
+            conn.display,
+            conn.root,
+            RRScreenChangeNotifyMask | RRCrtcChangeNotifyMask | RROutputChangeNotifyMask,
+        );
+    }
+    Some(event_base)
+}
+
+/// Let Xlib update its screen size after a RandR screen change
+pub(crate) fn update_configuration(xevent: &mut xlib::XEvent) {
+    if let Some(xrandr) = ext::xrandr() {
+        unsafe {
+            (xrandr.XRRUpdateConfiguration)(xevent);
+        }
+    }
+}
+
+/// Report changes of the work area and the current desktop with
+/// PropertyNotify on the root window, keeping the events selected there
+/// before, e.g. by the application owning a shared display
+pub(crate) fn select_work_area_changes(conn: &XConnection) {
+    unsafe {
+        let mut attributes: xlib::XWindowAttributes = std::mem::zeroed();
+        xlib::XGetWindowAttributes(conn.display, conn.root, &mut attributes);
+        xlib::XSelectInput(
+            conn.display,
+            conn.root,
+            attributes.your_event_mask | xlib::PropertyChangeMask,
+        );
+    }
+}
+
+/// The area of the current desktop left free by panels and docks, from
+/// _NET_WORKAREA. None without a window manager setting it
+pub(crate) fn work_area(conn: &XConnection) -> Option<Rect> {
+    let atoms = &conn.atoms;
+    let desktop = conn
+        .cardinal_list(conn.root, atoms.net_current_desktop)
+        .first()
+        .copied()
+        .unwrap_or(0) as usize;
+    let areas = conn.cardinal_list(conn.root, atoms.net_workarea);
+    let area = areas.get(desktop * 4..desktop * 4 + 4)?;
+    Some(Rect {
+        x: area[0] as i32,
+        y: area[1] as i32,
+        width: area[2] as u32,
+        height: area[3] as u32,
+    })
+}
+
+/// The work area within a monitor's bounds. _NET_WORKAREA spans every
+/// monitor, panels on one edge of the screen cut into the monitors along
+/// that edge
+fn usable_area(work_area: Option<Rect>, (x, y): (i32, i32), (width, height): (u32, u32)) -> Rect {
+    let bounds = Rect {
+        x,
+        y,
+        width,
+        height,
+    };
+    work_area
+        .and_then(|area| area.intersection(&bounds))
+        .unwrap_or(bounds)
+}
+
+/// The monitor containing a point of the X screen
+pub(crate) fn containing(
+    monitors: Vec<MonitorHandle>,
+    (x, y): (i32, i32),
+) -> Option<MonitorHandle> {
+    monitors.into_iter().find(|monitor| {
+        let (left, top) = monitor.position;
+        let (width, height) = monitor.size;
+        x >= left && y >= top && x < left + width as i32 && y < top + height as i32
+    })
+}
+
+/// Query the active monitors, without RandR the whole screen is reported
+/// as a single monitor
+pub(crate) fn monitors(conn: &XConnection) -> Vec<MonitorHandle> {
+    let xrandr = match xrandr(conn) {
+        Some(xrandr) => xrandr,
+        None => return vec![screen_monitor(conn)],
+    };
+
+    let mut monitors = Vec::new();
+    unsafe {
+        let resources = (xrandr.XRRGetScreenResourcesCurrent)(conn.display, conn.root);
+        if resources.is_null() {
+            return vec![screen_monitor(conn)];
+        }
+        let modes = slice::from_raw_parts((*resources).modes, (*resources).nmode as usize);
+        let outputs = slice::from_raw_parts((*resources).outputs, (*resources).noutput as usize);
+        let primary = (xrandr.XRRGetOutputPrimary)(conn.display, conn.root);
+        let xft_dpi = xft_dpi(conn);
+        let work_area = work_area(conn);
+
+        for &output in outputs {
+            let info = (xrandr.XRRGetOutputInfo)(conn.display, resources, output);
+            if info.is_null() {
+                continue;
+            }
+            // Only outputs driving a CRTC are active monitors
+            if (*info).crtc != 0 {
+                let crtc = (xrandr.XRRGetCrtcInfo)(conn.display, resources, (*info).crtc);
+                if !crtc.is_null() {
+                    let output_modes = slice::from_raw_parts((*info).modes, (*info).nmode as usize);
+                    let video_modes = modes
+                        .iter()
+                        .filter(|mode| output_modes.contains(&mode.id))
+                        .map(|mode| VideoMode {
+                            size: (mode.width, mode.height),
+                            refresh_rate_millihertz: refresh_rate_millihertz(mode).unwrap_or(0),
+                            mode: mode.id,
+                            crtc: (*info).crtc,
+                        })
+                        .collect();
+                    let edid = output_edid(conn, xrandr, output);
+                    let edid = edid.as_deref().and_then(Edid::parse);
+                    let reported = ((*info).mm_width as u32, (*info).mm_height as u32);
+                    let mut physical_size_mm = edid
+                        .as_ref()
+                        .and_then(|edid| edid.size_mm)
+                        .or_else(|| (reported.0 > 0 && reported.1 > 0).then_some(reported));
+                    if (*crtc).rotation as i32 & (RR_Rotate_90 | RR_Rotate_270) != 0 {
+                        physical_size_mm = physical_size_mm.map(|(w, h)| (h, w));
+                    }
+                    let mut monitor = MonitorHandle {
+                        output,
+                        name: CStr::from_ptr((*info).name).to_string_lossy().into_owned(),
+                        friendly_name: edid.and_then(|edid| edid.name),
+                        physical_size_mm,
+                        scale_factor: 1.0,
+                        position: ((*crtc).x, (*crtc).y),
+                        size: ((*crtc).width, (*crtc).height),
+                        refresh_rate_millihertz: modes
+                            .iter()
+                            .find(|mode| mode.id == (*crtc).mode)
+                            .and_then(refresh_rate_millihertz),
+                        primary: output == primary,
+                        video_modes,
+                        work_area: usable_area(
+                            work_area,
+                            ((*crtc).x, (*crtc).y),
+                            ((*crtc).width, (*crtc).height),
+                        ),
+                    };
+                    monitor.scale_factor = scale_factor(xft_dpi, monitor.dpi());
+                    monitors.push(monitor);
+                    (xrandr.XRRFreeCrtcInfo)(crtc);
+                }
+            }
+            (xrandr.XRRFreeOutputInfo)(info);
+        }
+        (xrandr.XRRFreeScreenResources)(resources);
+    }
+
+    if monitors.is_empty() {
+        monitors.push(screen_monitor(conn));
+    }
+    monitors
+}
+
+#[cfg(test)]
+impl MonitorHandle {
+    /// A monitor at `area` of the root which leaves `work_area` to windows
+    pub(crate) fn fake(output: RROutput, name: &str, area: Rect, work_area: Rect) -> MonitorHandle {
+        MonitorHandle {
+            output,
+            name: name.to_owned(),
+            friendly_name: None,
+            physical_size_mm: None,
+            scale_factor: 1.0,
+            position: (area.x, area.y),
+            size: (area.width, area.height),
+            refresh_rate_millihertz: None,
+            primary: false,
+            video_modes: Vec::new(),
+            work_area,
+        }
+    }
+}
+
+fn screen_monitor(conn: &XConnection) -> MonitorHandle {
+    let size = unsafe {
+        (
+            xlib::XDisplayWidth(conn.display, conn.screen) as u32,
+            xlib::XDisplayHeight(conn.display, conn.screen) as u32,
+        )
+    };
+    MonitorHandle {
+        output: 0,
+        name: String::from("screen"),
+        friendly_name: None,
+        physical_size_mm: None,
+        scale_factor: scale_factor(xft_dpi(conn), None),
+        position: (0, 0),
+        size,
+        refresh_rate_millihertz: None,
+        primary: true,
+        video_modes: Vec::new(),
+        work_area: usable_area(work_area(conn), (0, 0), size),
+    }
+}
+
+/// The parts of an EDID block daswin reports
+struct Edid {
+    name: Option<String>,
+    size_mm: Option<(u32, u32)>,
+}
+
+impl Edid {
+    /// Parse the base block, None when the header or checksum is wrong
+    fn parse(data: &[u8]) -> Option<Edid> {
+        const HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+        let block = data.get(..128)?;
+        if block[..8] != HEADER || block.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)) != 0 {
+            return None;
+        }
+
+        // The first detailed timing has the size in millimetres, the basic
+        // parameters only in centimetres
+        let timing = &block[54..72];
+        let size_mm = if timing[0] != 0 || timing[1] != 0 {
+            let width = timing[12] as u32 | (timing[14] as u32 & 0xf0) << 4;
+            let height = timing[13] as u32 | (timing[14] as u32 & 0x0f) << 8;
+            Some((width, height))
+        } else {
+            Some((block[21] as u32 * 10, block[22] as u32 * 10))
+        }
+        .filter(|&(width, height)| width > 0 && height > 0);
+
+        // Display descriptor 0xfc holds up to 13 bytes of name, ended by a
+        // newline and padded with spaces
+        let name = block[54..126]
+            .chunks_exact(18)
+            .find(|d| d[..3] == [0, 0, 0] && d[3] == 0xfc)
+            .map(|d| {
+                let text = &d[5..];
+                let end = text.iter().position(|&b| b == b'\n').unwrap_or(text.len());
+                String::from_utf8_lossy(&text[..end]).trim().to_owned()
+            })
+            .filter(|name| !name.is_empty());
+
+        Some(Edid { name, size_mm })
+    }
+}
+
+/// The raw EDID property of an output
+unsafe fn output_edid(conn: &XConnection, xrandr: &Xrandr, output: RROutput) -> Option<Vec<u8>> {
+    let mut actual_type = 0;
+    let mut actual_format = 0;
+    let mut count = 0;
+    let mut remaining = 0;
+    let mut data = ptr::null_mut();
+    let status = (xrandr.XRRGetOutputProperty)(
+        conn.display,
+        output,
+        conn.atoms.edid,
+        0,
+        // in 32 bit units, enough for the base block and extensions
+        256,
+        xlib::False,
+        xlib::False,
+        xlib::AnyPropertyType as xlib::Atom,
+        &mut actual_type,
+        &mut actual_format,
+        &mut count,
+        &mut remaining,
+        &mut data,
+    );
+    if status != xlib::Success as i32 || data.is_null() {
+        return None;
+    }
+    let edid = (actual_format == 8).then(|| slice::from_raw_parts(data, count as usize).to_vec());
+    xlib::XFree(data as *mut _);
+    edid
+}
+
+/// The Xft.dpi resource desktops set for font scaling
+fn xft_dpi(conn: &XConnection) -> Option<f64> {
+    let resources = unsafe { xlib::XResourceManagerString(conn.display) };
+    if resources.is_null() {
+        return None;
+    }
+    let resources = unsafe { CStr::from_ptr(resources) }.to_string_lossy();
+    resources.lines().find_map(|line| {
+        let value = line.strip_prefix("Xft.dpi:")?;
+        value.trim().parse().ok().filter(|&dpi: &f64| dpi > 0.0)
+    })
+}
+
+/// Xft.dpi decides when set, otherwise the monitor's DPI is rounded to a
+/// step of 0.25 and clamped, as bogus EDID sizes are common
+fn scale_factor(xft_dpi: Option<f64>, monitor_dpi: Option<f64>) -> f64 {
+    match (xft_dpi, monitor_dpi) {
+        (Some(dpi), _) => dpi / 96.0,
+        (None, Some(dpi)) => ((dpi / 96.0 * 4.0).round() / 4.0).clamp(1.0, 4.0),
+        (None, None) => 1.0,
+    }
+}
+
+/// A CRTC configuration saved before switching its mode, put back when
+/// restored or dropped so the desktop mode survives panics
+pub(crate) struct ModeSwitch {
+    conn: Arc<XConnection>,
+    target: VideoMode,
+    crtc: RRCrtc,
+    mode: RRMode,
+    position: (i32, i32),
+    rotation: Rotation,
+    outputs: Vec<RROutput>,
+    config_timestamp: xlib::Time,
+    active: bool,
+}
+
+impl ModeSwitch {
+    /// Switch the CRTC to the video mode, None when RandR refused
+    pub fn new(conn: &Arc<XConnection>, target: VideoMode) -> Option<ModeSwitch> {
+        let saved = conn.request(move |conn| unsafe {
+            let xrandr = xrandr(conn)?;
+            let resources = (xrandr.XRRGetScreenResourcesCurrent)(conn.display, conn.root);
+            if resources.is_null() {
+                return None;
+            }
+            let info = (xrandr.XRRGetCrtcInfo)(conn.display, resources, target.crtc);
+            let config_timestamp = (*resources).configTimestamp;
+            (xrandr.XRRFreeScreenResources)(resources);
+            if info.is_null() {
+                return None;
+            }
+            let saved = (
+                (*info).mode,
+                ((*info).x, (*info).y),
+                (*info).rotation,
+                slice::from_raw_parts((*info).outputs, (*info).noutput as usize).to_vec(),
+                config_timestamp,
+            );
+            (xrandr.XRRFreeCrtcInfo)(info);
+            Some(saved)
+        })?;
+
+        let (mode, position, rotation, outputs, config_timestamp) = saved;
+        let mut switch = ModeSwitch {
+            conn: conn.clone(),
+            target,
+            crtc: target.crtc,
+            mode,
+            position,
+            rotation,
+            outputs,
+            config_timestamp,
+            active: false,
+        };
+        if switch.apply(target.mode) {
+            switch.active = true;
+            Some(switch)
+        } else {
+            None
+        }
+    }
+
+    /// Position of the switched CRTC on the X screen
+    pub fn position(&self) -> (i32, i32) {
+        self.position
+    }
+
+    /// Put the desktop mode back, does nothing when already restored
+    pub fn restore(&mut self) {
+        if self.active {
+            self.apply(self.mode);
+            self.active = false;
+        }
+    }
+
+    /// Switch to the target mode again after a restore
+    pub fn reapply(&mut self) {
+        if !self.active {
+            self.active = self.apply(self.target.mode);
+        }
+    }
+
+    // Switching away from the desktop mode registers putting it back with
+    // the session, see SessionGuard
+    fn apply(&self, mode: RRMode) -> bool {
+        let (crtc, position, rotation) = (self.crtc, self.position, self.rotation);
+        let mut outputs = self.outputs.clone();
+        let undo = Undo::RestoreCrtc(CrtcConfig {
+            crtc,
+            mode: self.mode,
+            position,
+            rotation,
+            outputs: self.outputs.clone(),
+            config_timestamp: self.config_timestamp,
+        });
+        let desktop = mode == self.mode;
+        self.conn.request(move |conn| unsafe {
+            let applied = set_crtc_config(conn, crtc, position, mode, rotation, &mut outputs);
+            if desktop {
+                session::done(conn, &undo);
+            } else if applied {
+                session::register(conn, undo);
+            }
+            xlib::XFlush(conn.display);
+            applied
+        })
+    }
+}
+
+/// Configure a CRTC, false when RandR refused
+pub(crate) unsafe fn set_crtc_config(
+    conn: &XConnection,
+    crtc: RRCrtc,
+    position: (i32, i32),
+    mode: RRMode,
+    rotation: Rotation,
+    outputs: &mut [RROutput],
+) -> bool {
+    let xrandr = match xrandr(conn) {
+        Some(xrandr) => xrandr,
+        None => return false,
+    };
+    let resources = (xrandr.XRRGetScreenResourcesCurrent)(conn.display, conn.root);
+    if resources.is_null() {
+        return false;
+    }
+    let status = (xrandr.XRRSetCrtcConfig)(
+        conn.display,
+        resources,
+        crtc,
+        xlib::CurrentTime,
+        position.0,
+        position.1,
+        mode,
+        rotation,
+        outputs.as_mut_ptr(),
+        outputs.len() as i32,
+    );
+    (xrandr.XRRFreeScreenResources)(resources);
+    status == 0
+}
+
+impl Drop for ModeSwitch {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(width: u32, height: u32) -> Rect {
+        Rect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn laptop_panel() {
+        let min = DEFAULT_MIN_INNER_SIZE.to_physical(1.0);
+        // The height share of 460.8 is below the minimum
+        let size = default_inner_size(area(1366, 768), min);
+        assert_eq!(size, PhysicalSize::new(820, 480));
+    }
+
+    #[test]
+    fn uhd_at_scale_two() {
+        let min = DEFAULT_MIN_INNER_SIZE.to_physical(2.0);
+        assert_eq!(min, PhysicalSize::new(1280, 960));
+        let size = default_inner_size(area(3840, 2160), min);
+        assert_eq!(size, PhysicalSize::new(2304, 1296));
+    }
+
+    #[test]
+    fn work_area_below_the_minimum() {
+        let min = DEFAULT_MIN_INNER_SIZE.to_physical(2.0);
+        let size = default_inner_size(area(800, 400), min);
+        assert_eq!(size, PhysicalSize::new(800, 400));
+        let size = default_inner_size(area(0, 0), min);
+        assert_eq!(size, PhysicalSize::new(1, 1));
+    }
+
+    #[test]
+    fn no_monitor() {
+        // Windows placed without a monitor take the minimum unscaled
+        let size = Size::Default.to_physical(1.0);
+        assert_eq!(size, PhysicalSize::new(640, 480));
+    }
+}
@@ -0,0 +1,370 @@
+//! Saving where a window was and how it was shown, so the application
+//! reopens it the same way on its next launch, see X11Window::save_state
+//! and WindowBuilder::with_restored_state
+
+use std::fmt::Write;
+
+use crate::dpi::PhysicalPosition;
+use crate::monitor::MonitorHandle;
+use crate::window::Rect;
+
+/// The geometry and state of a window as saved by X11Window::save_state.
+///
+/// Stored with to_toml and read back with from_toml, as daswin has no
+/// serde dependency. Exclusive fullscreen is restored as borderless
+/// fullscreen, the video mode is not saved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedWindowState {
+    /// The contents relative to the root while the window was last
+    /// neither maximized nor fullscreen, which it returns to when they
+    /// are left
+    pub geometry: Rect,
+    pub maximized: bool,
+    pub fullscreen: bool,
+    /// Friendly name of the monitor containing the center of the window,
+    /// see MonitorHandle::friendly_name
+    pub monitor: Option<String>,
+    /// Where that monitor was on the root, to follow it when monitors are
+    /// arranged differently
+    pub monitor_position: PhysicalPosition<i32>,
+}
+
+impl SavedWindowState {
+    /// The state as TOML, one key per line
+    pub fn to_toml(&self) -> String {
+        let mut toml = String::new();
+        let geometry = &self.geometry;
+        let _ = writeln!(toml, "x = {}", geometry.x);
+        let _ = writeln!(toml, "y = {}", geometry.y);
+        let _ = writeln!(toml, "width = {}", geometry.width);
+        let _ = writeln!(toml, "height = {}", geometry.height);
+        let _ = writeln!(toml, "maximized = {}", self.maximized);
+        let _ = writeln!(toml, "fullscreen = {}", self.fullscreen);
+        if let Some(monitor) = &self.monitor {
+            let escaped = monitor.replace('\\', "\\\\").replace('"', "\\\"");
+            let _ = writeln!(toml, "monitor = \"{}\"", escaped);
+        }
+        let _ = writeln!(toml, "monitor_x = {}", self.monitor_position.x);
+        let _ = writeln!(toml, "monitor_y = {}", self.monitor_position.y);
+        toml
+    }
+
+    /// Read what to_toml wrote, None when a key is missing or malformed.
+    /// Unknown keys, comments and blank lines are skipped
+    pub fn from_toml(toml: &str) -> Option<SavedWindowState> {
+        let mut state = SavedWindowState {
+            geometry: Rect {
+                x: 0,
+                y: 0,
+                width: 0,
+                height: 0,
+            },
+            maximized: false,
+            fullscreen: false,
+            monitor: None,
+            monitor_position: PhysicalPosition::new(0, 0),
+        };
+        // Keys which have no default
+        let mut found = 0;
+        for line in toml.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=')?;
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "x" => state.geometry.x = value.parse().ok()?,
+                "y" => state.geometry.y = value.parse().ok()?,
+                "width" => state.geometry.width = value.parse().ok()?,
+                "height" => state.geometry.height = value.parse().ok()?,
+                "maximized" => state.maximized = value.parse().ok()?,
+                "fullscreen" => state.fullscreen = value.parse().ok()?,
+                "monitor" => state.monitor = Some(unquote(value)?),
+                "monitor_x" => state.monitor_position.x = value.parse().ok()?,
+                "monitor_y" => state.monitor_position.y = value.parse().ok()?,
+                _ => continue,
+            }
+            if ["x", "y", "width", "height"].contains(&key) {
+                found += 1;
+            }
+        }
+        (found == 4 && state.geometry.width > 0 && state.geometry.height > 0).then_some(state)
+    }
+}
+
+// A basic TOML string, escaping only quotes and backslashes
+fn unquote(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        unquoted.push(if c == '\\' { chars.next()? } else { c });
+    }
+    Some(unquoted)
+}
+
+/// Where a saved window reopens among the current monitors, along with
+/// the monitor it reopens on.
+///
+/// The window follows its monitor when that still exists, otherwise it
+/// goes to the monitor whose work area is nearest to its center. Either
+/// way it is moved and shrunk into the work area, so a window saved
+/// maximized on a larger monitor is maximized on the smaller one and
+/// returns to a geometry fitting it
+pub(crate) fn placement(
+    saved: &SavedWindowState,
+    monitors: Vec<MonitorHandle>,
+) -> (Rect, Option<MonitorHandle>) {
+    let geometry = saved.geometry;
+    // Identical monitors share a friendly name, the one where the window
+    // was is preferred
+    let named =
+        |monitor: &&MonitorHandle| Some(monitor.friendly_name()) == saved.monitor.as_deref();
+    let same = monitors
+        .iter()
+        .filter(named)
+        .find(|monitor| monitor.position() == saved.monitor_position)
+        .or_else(|| monitors.iter().find(named));
+    let (geometry, monitor) = match same {
+        Some(monitor) => {
+            let (from, to) = (saved.monitor_position, monitor.position());
+            // Positions of a corrupt file may be anywhere, the window then
+            // stays put and is only fitted into the work area
+            let follow = |at: i32, to: i32, from: i32| at.checked_add(to.checked_sub(from)?);
+            let moved = match (
+                follow(geometry.x, to.x, from.x),
+                follow(geometry.y, to.y, from.y),
+            ) {
+                (Some(x), Some(y)) => Rect { x, y, ..geometry },
+                _ => geometry,
+            };
+            (moved, monitor)
+        }
+        None => {
+            let center = (
+                geometry.x as i64 + geometry.width as i64 / 2,
+                geometry.y as i64 + geometry.height as i64 / 2,
+            );
+            match monitors
+                .iter()
+                .min_by_key(|monitor| distance(monitor.work_area(), center))
+            {
+                Some(monitor) => (geometry, monitor),
+                None => return (geometry, None),
+            }
+        }
+    };
+    (
+        fit_into(geometry, monitor.work_area()),
+        Some(monitor.clone()),
+    )
+}
+
+// Squared distance of a point from a rectangle, 0 inside it. Saturates
+// for the far away points of corrupt files
+fn distance(rect: Rect, (x, y): (i64, i64)) -> i64 {
+    let (left, top) = (rect.x as i64, rect.y as i64);
+    let (right, bottom) = (left + rect.width as i64, top + rect.height as i64);
+    let dx = (left - x).max(x - right).max(0);
+    let dy = (top - y).max(y - bottom).max(0);
+    dx.saturating_mul(dx).saturating_add(dy.saturating_mul(dy))
+}
+
+// Shrink a rectangle to the size of an area and move it inside
+fn fit_into(rect: Rect, area: Rect) -> Rect {
+    let width = rect.width.min(area.width);
+    let height = rect.height.min(area.height);
+    let right = area.x + (area.width - width) as i32;
+    let bottom = area.y + (area.height - height) as i32;
+    Rect {
+        x: rect.x.clamp(area.x, right),
+        y: rect.y.clamp(area.y, bottom),
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn saved(geometry: Rect, monitor: &str, (x, y): (i32, i32)) -> SavedWindowState {
+        SavedWindowState {
+            geometry,
+            maximized: false,
+            fullscreen: false,
+            monitor: Some(monitor.to_owned()),
+            monitor_position: PhysicalPosition::new(x, y),
+        }
+    }
+
+    // A monitor whose panel takes the top 32 pixels of the work area
+    fn screen(output: u64, name: &str, area: Rect) -> MonitorHandle {
+        let work_area = rect(area.x, area.y + 32, area.width, area.height - 32);
+        MonitorHandle::fake(output as _, name, area, work_area)
+    }
+
+    #[test]
+    fn toml_round_trips() {
+        let mut state = saved(rect(-10, 20, 800, 600), r#"DELL "U2720Q" \ 2"#, (-1920, 0));
+        state.maximized = true;
+        assert_eq!(
+            SavedWindowState::from_toml(&state.to_toml()),
+            Some(state.clone())
+        );
+        state.monitor = None;
+        state.fullscreen = true;
+        assert_eq!(SavedWindowState::from_toml(&state.to_toml()), Some(state));
+    }
+
+    #[test]
+    fn toml_skips_comments_and_unknown_keys() {
+        let toml = "# window\n\nx = 1\n  y=2  \nwidth = 3\nheight = 4\ntheme = \"dark\"\n";
+        let state = SavedWindowState::from_toml(toml).unwrap();
+        assert_eq!(state.geometry, rect(1, 2, 3, 4));
+        assert_eq!(state.monitor, None);
+        assert!(!state.maximized && !state.fullscreen);
+    }
+
+    #[test]
+    fn malformed_toml() {
+        let valid = "x = 1\ny = 2\nwidth = 3\nheight = 4\n";
+        assert!(SavedWindowState::from_toml(valid).is_some());
+        for broken in [
+            "x = 1\ny = 2\nwidth = 3\n",
+            "x = 1\ny = 2\nwidth = 0\nheight = 4\n",
+            "x = one\ny = 2\nwidth = 3\nheight = 4\n",
+            "x = 1\ny = 2\nwidth = -3\nheight = 4\n",
+            "x = 99999999999\ny = 2\nwidth = 3\nheight = 4\n",
+            "x\ny = 2\nwidth = 3\nheight = 4\n",
+        ] {
+            assert_eq!(SavedWindowState::from_toml(broken), None, "{:?}", broken);
+        }
+        for monitor in ["monitor = plain", "monitor = \"open", "monitor = \"a\\\""] {
+            let toml = format!("{}{}\n", valid, monitor);
+            assert_eq!(SavedWindowState::from_toml(&toml), None, "{:?}", monitor);
+        }
+        let toml = format!("{}maximized = yes\n", valid);
+        assert_eq!(SavedWindowState::from_toml(&toml), None);
+    }
+
+    #[test]
+    fn follows_its_monitor() {
+        // The right monitor moved from 1920 to the left of the other one
+        let monitors = vec![
+            screen(1, "left", rect(-1920, 0, 1920, 1080)),
+            screen(2, "main", rect(0, 0, 1920, 1080)),
+        ];
+        let state = saved(rect(2020, 132, 800, 600), "left", (1920, 0));
+        let (geometry, monitor) = placement(&state, monitors);
+        assert_eq!(geometry, rect(-1820, 132, 800, 600));
+        assert_eq!(monitor.unwrap().into_raw(), 1);
+    }
+
+    #[test]
+    fn prefers_the_same_of_identical_monitors() {
+        let monitors = vec![
+            screen(1, "twin", rect(0, 0, 1920, 1080)),
+            screen(2, "twin", rect(1920, 0, 1920, 1080)),
+        ];
+        let state = saved(rect(2020, 132, 800, 600), "twin", (1920, 0));
+        let (geometry, monitor) = placement(&state, monitors.clone());
+        assert_eq!(geometry, rect(2020, 132, 800, 600));
+        assert_eq!(monitor.unwrap().into_raw(), 2);
+        // Gone from where it was, the first of the name is taken
+        let state = saved(rect(3940, 132, 800, 600), "twin", (3840, 0));
+        let (geometry, monitor) = placement(&state, monitors);
+        assert_eq!(geometry, rect(100, 132, 800, 600));
+        assert_eq!(monitor.unwrap().into_raw(), 1);
+    }
+
+    #[test]
+    fn monitor_gone() {
+        let monitors = vec![
+            screen(1, "main", rect(0, 0, 1920, 1080)),
+            screen(2, "side", rect(1920, 0, 1280, 1024)),
+        ];
+        // Saved on a monitor further right, the nearest is the side one
+        let state = saved(rect(3500, 200, 800, 600), "unplugged", (3200, 0));
+        let (geometry, monitor) = placement(&state, monitors);
+        assert_eq!(geometry, rect(2400, 200, 800, 600));
+        assert_eq!(monitor.unwrap().into_raw(), 2);
+    }
+
+    #[test]
+    fn maximized_on_a_smaller_monitor() {
+        // Saved maximized on a 2560x1440 monitor, now 1920x1080
+        let mut state = saved(rect(100, 100, 2400, 1300), "main", (0, 0));
+        state.maximized = true;
+        let monitors = vec![screen(1, "main", rect(0, 0, 1920, 1080))];
+        let (geometry, monitor) = placement(&state, monitors);
+        assert_eq!(geometry, rect(0, 32, 1920, 1048));
+        assert_eq!(monitor.unwrap().into_raw(), 1);
+        // The same monitor gone, another smaller one takes the window
+        let monitors = vec![screen(2, "laptop", rect(0, 0, 1366, 768))];
+        let (geometry, _) = placement(&state, monitors);
+        assert_eq!(geometry, rect(0, 32, 1366, 736));
+    }
+
+    #[test]
+    fn no_monitors() {
+        let state = saved(rect(10, 20, 800, 600), "main", (0, 0));
+        assert_eq!(
+            placement(&state, Vec::new()),
+            (rect(10, 20, 800, 600), None)
+        );
+    }
+
+    #[test]
+    fn corrupt_positions() {
+        let monitors = vec![screen(1, "main", rect(0, 0, 1920, 1080))];
+        for (x, monitor_x) in [(i32::MAX, i32::MIN), (i32::MIN, 1), (1, i32::MIN)] {
+            let state = saved(
+                rect(x, x, u32::MAX, u32::MAX),
+                "main",
+                (monitor_x, monitor_x),
+            );
+            let (geometry, _) = placement(&state, monitors.clone());
+            assert_eq!(geometry, rect(0, 32, 1920, 1048));
+            let mut other = state.clone();
+            other.monitor = None;
+            let (geometry, _) = placement(&other, monitors.clone());
+            assert_eq!(geometry, rect(0, 32, 1920, 1048));
+        }
+    }
+
+    #[test]
+    fn fits_into_the_work_area() {
+        let area = rect(100, 50, 1000, 500);
+        assert_eq!(
+            fit_into(rect(200, 100, 300, 200), area),
+            rect(200, 100, 300, 200)
+        );
+        assert_eq!(
+            fit_into(rect(0, 0, 300, 200), area),
+            rect(100, 50, 300, 200)
+        );
+        assert_eq!(
+            fit_into(rect(1000, 500, 300, 200), area),
+            rect(800, 350, 300, 200)
+        );
+        assert_eq!(fit_into(rect(0, 0, 3000, 2000), area), area);
+    }
+
+    #[test]
+    fn distance_from_rect() {
+        let area = rect(0, 0, 100, 100);
+        assert_eq!(distance(area, (50, 50)), 0);
+        assert_eq!(distance(area, (103, 104)), 3 * 3 + 4 * 4);
+        assert_eq!(distance(area, (-10, 50)), 100);
+    }
+}
@@ -0,0 +1,11 @@
+//! The types most applications use, for importing with
+//! `use daswin::prelude::*`
+
+pub use crate::dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize};
+pub use crate::error::Error;
+pub use crate::event::{Event, WindowId};
+pub use crate::event_loop::{EventLoop, EventLoopBuilder, PumpStatus};
+pub use crate::icon::Icon;
+#[cfg(feature = "wgpu")]
+pub use crate::render::{GpuContext, WindowSurface};
+pub use crate::window::{CursorIcon, Fullscreen, Position, WindowBuilder, X11Window};
@@ -0,0 +1,113 @@
+//! Completion events of the Present extension, telling when frames
+//! reached the screen. See X11Window::enable_present_feedback
+
+use std::mem;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+use std::time::Duration;
+
+use x11::xlib;
+
+use crate::ext;
+use crate::xconn::XConnection;
+
+// Event types and completion kinds and modes, from
+// X11/extensions/presentproto.h
+const COMPLETE_NOTIFY: u16 = 1;
+const COMPLETE_KIND_PIXMAP: u8 = 0;
+const COMPLETE_MODE_SKIP: u8 = 3;
+
+/// The cookie data of a PresentCompleteNotify
+#[repr(C)]
+pub(crate) struct Completion {
+    pub window: xlib::Window,
+    kind: u8,
+    mode: u8,
+    ust: u64,
+    pub msc: u64,
+}
+
+impl Completion {
+    /// Whether a frame reached the screen, rather than being replaced
+    /// by a newer one
+    pub fn is_presented(&self) -> bool {
+        self.kind == COMPLETE_KIND_PIXMAP && self.mode != COMPLETE_MODE_SKIP
+    }
+
+    pub fn ust(&self) -> Duration {
+        Duration::from_micros(self.ust)
+    }
+}
+
+/// The major opcode of Present, after installing the decoder for its
+/// events. None when the server or the xcb libraries lack it
+pub(crate) unsafe fn init(display: *mut xlib::Display) -> Option<i32> {
+    ext::x11_xcb()?;
+    ext::xcb_present()?;
+    let (mut opcode, mut event, mut error) = (0, 0, 0);
+    let name = b"Present\0";
+    if xlib::XQueryExtension(
+        display,
+        name.as_ptr() as *const _,
+        &mut opcode,
+        &mut event,
+        &mut error,
+    ) == 0
+    {
+        return None;
+    }
+    xlib::XESetWireToEventCookie(display, opcode, Some(wire_to_cookie));
+    Some(opcode)
+}
+
+/// Select completion events for frames presented to the window, by
+/// anyone including the Vulkan driver
+pub(crate) unsafe fn select_completions(conn: &XConnection, window: xlib::Window) {
+    let (x11_xcb, present) = match (ext::x11_xcb(), ext::xcb_present()) {
+        (Some(x11_xcb), Some(present)) => (x11_xcb, present),
+        _ => return,
+    };
+    let xcb = (x11_xcb.XGetXCBConnection)(conn.display);
+    let id = (present.xcb_generate_id)(xcb);
+    (present.xcb_present_select_input)(xcb, id, window as u32, ext::PRESENT_COMPLETE_NOTIFY_MASK);
+}
+
+/// Decodes Present events for XGetEventData, Xlib only knows the
+/// extensions it has libraries for
+unsafe extern "C" fn wire_to_cookie(
+    display: *mut xlib::Display,
+    cookie: *mut xlib::XGenericEventCookie,
+    wire: *mut xlib::xEvent,
+) -> c_int {
+    // xPresentCompleteNotify, in the byte order of the client
+    let wire = wire as *const u8;
+    let read_u16 = |offset| ptr::read_unaligned(wire.add(offset) as *const u16);
+    let read_u32 = |offset| ptr::read_unaligned(wire.add(offset) as *const u32);
+    let read_u64 = |offset| ptr::read_unaligned(wire.add(offset) as *const u64);
+    let evtype = read_u16(8);
+    if evtype != COMPLETE_NOTIFY {
+        return xlib::False;
+    }
+    // Xlib frees the data along with the event
+    let data = libc::malloc(mem::size_of::<Completion>()) as *mut Completion;
+    if data.is_null() {
+        return xlib::False;
+    }
+    data.write(Completion {
+        kind: *wire.add(10),
+        mode: *wire.add(11),
+        window: read_u32(16) as xlib::Window,
+        ust: read_u64(24),
+        msc: read_u64(32),
+    });
+
+    let cookie = &mut *cookie;
+    cookie.type_ = xlib::GenericEvent;
+    cookie.serial = xlib::XLastKnownRequestProcessed(display);
+    cookie.send_event = (*wire & 0x80 != 0) as xlib::Bool;
+    cookie.display = display;
+    cookie.extension = *wire.add(1) as c_int;
+    cookie.evtype = evtype as c_int;
+    cookie.data = data as *mut c_void;
+    xlib::True
+}
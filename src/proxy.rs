@@ -0,0 +1,104 @@
+//! Window changes requested from threads other than the one running the
+//! event loop, which carries them out on its next poll
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::dpi::Size;
+use crate::event::WindowId;
+use crate::window::CursorIcon;
+use crate::xconn::XConnection;
+
+/// A change to a window queued by a WindowProxy
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum WindowCommand {
+    SetTitle(String),
+    SetInnerSize(Size),
+    SetCursorIcon(CursorIcon),
+    RequestRedraw,
+    ToggleFullscreen,
+}
+
+/// Changes a window from any thread, e.g. showing progress of a
+/// simulation thread in the title, see X11Window::proxy.
+///
+/// Xlib is only driven from the thread of the event loop, so the
+/// changes are queued and made by its next poll_events in the order
+/// they were requested. Changes to a dropped window are discarded
+#[derive(Clone)]
+pub struct WindowProxy {
+    window: WindowId,
+    conn: Arc<XConnection>,
+    // Cleared when the X11Window is dropped
+    alive: Arc<AtomicBool>,
+}
+
+impl WindowProxy {
+    pub(crate) fn new(window: WindowId, conn: Arc<XConnection>, alive: Arc<AtomicBool>) -> Self {
+        WindowProxy {
+            window,
+            conn,
+            alive,
+        }
+    }
+
+    /// The window the proxy changes
+    pub fn id(&self) -> WindowId {
+        self.window
+    }
+
+    /// Whether the window still exists. Changes may still be discarded
+    /// when it is dropped before the next poll
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::Acquire)
+    }
+
+    /// See X11Window::set_title
+    pub fn set_title(&self, title: &str) {
+        self.send(WindowCommand::SetTitle(title.to_owned()));
+    }
+
+    /// See X11Window::set_inner_size
+    pub fn set_inner_size(&self, size: impl Into<Size>) {
+        self.send(WindowCommand::SetInnerSize(size.into()));
+    }
+
+    /// See X11Window::set_cursor_icon
+    pub fn set_cursor_icon(&self, icon: CursorIcon) {
+        self.send(WindowCommand::SetCursorIcon(icon));
+    }
+
+    /// See X11Window::request_redraw, the RedrawRequested event comes
+    /// with the poll running the request
+    pub fn request_redraw(&self) {
+        self.send(WindowCommand::RequestRedraw);
+    }
+
+    /// Enter borderless fullscreen on the current monitor or leave it,
+    /// see X11Window::set_fullscreen. Exclusive fullscreen is left as it
+    /// is
+    pub fn toggle_fullscreen(&self) {
+        self.send(WindowCommand::ToggleFullscreen);
+    }
+
+    fn send(&self, command: WindowCommand) {
+        if self.is_alive() {
+            self.conn
+                .window_commands
+                .lock()
+                .unwrap()
+                .push_back((self.window, command));
+            self.conn.wake.wake();
+        }
+    }
+}
+
+impl fmt::Debug for WindowProxy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WindowProxy")
+            .field("window", &self.window)
+            .field("alive", &self.is_alive())
+            .finish()
+    }
+}
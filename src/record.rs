@@ -0,0 +1,1161 @@
+//! Recording translated events to a file and playing them back without
+//! an X server, to reproduce input dependent bugs
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use x11::xlib;
+
+use crate::dpi::{PhysicalPosition, PhysicalSize};
+use crate::event::{
+    ChannelId, DeviceEvent, DeviceId, Edge, Event, HotkeyId, ReadbackId, ScrollDelta, StateChange,
+    TabletTool, WindowId,
+};
+use crate::event_loop::EventLoop;
+use crate::window::{Rect, X11Window};
+
+const MAGIC: &[u8; 8] = b"DASWREC5";
+
+/// Writes every event passed to it along with the time since recording
+/// started. The strings of Text, GpuError and ScriptError events are
+/// written in place of their index.
+///
+/// The file starts with the size of each window handed to new, in order,
+/// which EventPlayer::attach compares against the windows of the replay
+pub struct EventRecorder<W: Write> {
+    writer: W,
+    start: Instant,
+}
+
+impl<W: Write> EventRecorder<W> {
+    /// Start recording events of the given windows
+    pub fn new(mut writer: W, windows: &[&X11Window]) -> io::Result<EventRecorder<W>> {
+        writer.write_all(MAGIC)?;
+        write_u32(&mut writer, windows.len() as u32)?;
+        for window in windows {
+            let (width, height) = window.inner_size().into();
+            write_window(&mut writer, window.id())?;
+            write_u32(&mut writer, width)?;
+            write_u32(&mut writer, height)?;
+        }
+        Ok(EventRecorder {
+            writer,
+            start: Instant::now(),
+        })
+    }
+
+    /// Append the events of one poll, before `event_loop` polls again
+    pub fn record(&mut self, events: &[Event], event_loop: &EventLoop) -> io::Result<()> {
+        self.record_with(events, |event| match *event {
+            Event::Text { index, .. } => event_loop.text(index),
+            Event::GpuError { index } => event_loop.gpu_error(index),
+            #[cfg(feature = "scripting")]
+            Event::ScriptError { index } => event_loop.script_error(index),
+            _ => None,
+        })
+    }
+
+    fn record_with<'a>(
+        &mut self,
+        events: &[Event],
+        string: impl Fn(&Event) -> Option<&'a str>,
+    ) -> io::Result<()> {
+        let micros = self.start.elapsed().as_micros() as u64;
+        for event in events {
+            write_u64(&mut self.writer, micros)?;
+            write_event(&mut self.writer, event, string(event).unwrap_or(""))?;
+        }
+        Ok(())
+    }
+
+    /// Flush and return the writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// How recorded events are paced during playback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Playback {
+    /// With the time between events as recorded
+    RealTime,
+    /// Every remaining event on the first poll
+    AsFastAsPossible,
+}
+
+/// Plays back a recording made by EventRecorder
+pub struct EventPlayer<R: Read> {
+    reader: R,
+    playback: Playback,
+    // Recorded window ids and sizes, in the order they were recorded
+    windows: Vec<(WindowId, (u32, u32))>,
+    // Recorded window ids to those of the attached windows
+    window_map: HashMap<WindowId, WindowId>,
+    start: Option<Instant>,
+    // Event read ahead of its time, with its string
+    next: Option<Recorded>,
+    finished: bool,
+    // Strings of the events of the last poll, by index
+    texts: Vec<String>,
+    gpu_errors: Vec<String>,
+    script_errors: Vec<String>,
+}
+
+impl<R: Read> EventPlayer<R> {
+    /// Read the header of a recording
+    pub fn new(mut reader: R, playback: Playback) -> io::Result<EventPlayer<R>> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a daswin event recording"));
+        }
+        let count = read_u32(&mut reader)?;
+        let windows = (0..count)
+            .map(|_| {
+                let window = read_window(&mut reader)?;
+                let width = read_u32(&mut reader)?;
+                let height = read_u32(&mut reader)?;
+                Ok((window, (width, height)))
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(EventPlayer {
+            reader,
+            playback,
+            windows,
+            window_map: HashMap::new(),
+            start: None,
+            next: None,
+            finished: false,
+            texts: Vec::new(),
+            gpu_errors: Vec::new(),
+            script_errors: Vec::new(),
+        })
+    }
+
+    /// The sizes of the recorded windows, in the order they were passed
+    /// to EventRecorder::new
+    pub fn recorded_sizes(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.windows.iter().map(|&(_, size)| size)
+    }
+
+    /// Deliver the events of the recorded windows as events of these
+    /// windows, given in the same order as when recording.
+    ///
+    /// Fails when the number of windows or their sizes differ from the
+    /// recording, as replayed positions would not hit the same targets.
+    /// Without attaching, events carry the recorded window ids
+    pub fn attach(&mut self, windows: &[&X11Window]) -> io::Result<()> {
+        let windows: Vec<_> = windows
+            .iter()
+            .map(|window| (window.id(), window.inner_size().into()))
+            .collect();
+        self.attach_sizes(&windows)
+    }
+
+    fn attach_sizes(&mut self, windows: &[(WindowId, (u32, u32))]) -> io::Result<()> {
+        if windows.len() != self.windows.len() {
+            return Err(invalid_data(&format!(
+                "recorded {} windows, {} attached",
+                self.windows.len(),
+                windows.len()
+            )));
+        }
+        for (&(_, recorded), &(window, size)) in self.windows.iter().zip(windows) {
+            if size != recorded {
+                return Err(invalid_data(&format!(
+                    "window 0x{:x} is {:?}, it was recorded at {:?}",
+                    window.xid(),
+                    size,
+                    recorded
+                )));
+            }
+        }
+        for (&(recorded, _), &(window, _)) in self.windows.iter().zip(windows) {
+            self.window_map.insert(recorded, window);
+        }
+        Ok(())
+    }
+
+    /// Whether every recorded event was delivered
+    pub fn is_finished(&self) -> bool {
+        self.finished && self.next.is_none()
+    }
+
+    /// The string of a Text event delivered by the last poll
+    pub fn text(&self, index: u32) -> Option<&str> {
+        self.texts.get(index as usize).map(String::as_str)
+    }
+
+    /// The message of a GpuError event delivered by the last poll
+    pub fn gpu_error(&self, index: u32) -> Option<&str> {
+        self.gpu_errors.get(index as usize).map(String::as_str)
+    }
+
+    /// The message of a ScriptError event delivered by the last poll
+    pub fn script_error(&self, index: u32) -> Option<&str> {
+        self.script_errors.get(index as usize).map(String::as_str)
+    }
+
+    /// Deliver the events which are due, replacing the contents of
+    /// `events` like EventLoop::poll_events. The strings of the events
+    /// delivered before are forgotten
+    pub fn poll_events(&mut self, events: &mut Vec<Event>) -> io::Result<()> {
+        events.clear();
+        self.texts.clear();
+        self.gpu_errors.clear();
+        self.script_errors.clear();
+        let start = *self.start.get_or_insert_with(Instant::now);
+
+        loop {
+            let (time, event, string) = match self.next.take() {
+                Some(next) => next,
+                None => match self.read_next()? {
+                    Some(next) => next,
+                    None => return Ok(()),
+                },
+            };
+            if self.playback == Playback::RealTime && start.elapsed() < time {
+                self.next = Some((time, event, string));
+                return Ok(());
+            }
+            let event = self.store(event, string);
+            events.push(self.remap(event));
+        }
+    }
+
+    fn read_next(&mut self) -> io::Result<Option<Recorded>> {
+        if self.finished {
+            return Ok(None);
+        }
+        let micros = match read_u64(&mut self.reader) {
+            Ok(micros) => micros,
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                self.finished = true;
+                return Ok(None);
+            }
+            Err(err) => return Err(err),
+        };
+        let (event, string) = read_event(&mut self.reader)?;
+        Ok(Some((Duration::from_micros(micros), event, string)))
+    }
+
+    /// Keep the string of an event for this poll, pointing its index at it
+    fn store(&mut self, mut event: Event, string: Option<String>) -> Event {
+        let (strings, index) = match &mut event {
+            Event::Text { index, .. } => (&mut self.texts, index),
+            Event::GpuError { index } => (&mut self.gpu_errors, index),
+            Event::ScriptError { index } => (&mut self.script_errors, index),
+            _ => return event,
+        };
+        *index = strings.len() as u32;
+        strings.push(string.unwrap_or_default());
+        event
+    }
+
+    fn remap(&self, mut event: Event) -> Event {
+        let window = match &mut event {
+            Event::CloseRequested { window }
+            | Event::WindowCreated { window }
+            | Event::Destroyed { window }
+            | Event::Resized { window, .. }
+            | Event::Focused { window, .. }
+            | Event::StateChanged { window, .. }
+            | Event::KeyboardInput { window, .. }
+            | Event::Text { window, .. }
+            | Event::MouseInput { window, .. }
+            | Event::MouseWheel { window, .. }
+            | Event::CursorMoved { window, .. }
+            | Event::Tablet { window, .. }
+            | Event::RedrawRequested { window }
+            | Event::MonitorChanged { window, .. }
+            | Event::FramePresented { window, .. }
+            | Event::CanvasViewportChanged { window, .. }
+            | Event::Snapped { window, .. }
+            | Event::EmbeddedChildDestroyed { window, .. } => window,
+            Event::ModalBlocked { window, modal } => {
+                if let Some(&attached) = self.window_map.get(modal) {
+                    *modal = attached;
+                }
+                window
+            }
+            Event::DeviceEvent { .. }
+            | Event::LateInput
+            | Event::AboutToWait
+            | Event::ConnectionLost
+            | Event::DeviceAdded { .. }
+            | Event::DeviceRemoved { .. }
+            | Event::TrayIconClicked { .. }
+            | Event::TrayMenuItem { .. }
+            | Event::Channel(_)
+            | Event::GlobalHotkey(_)
+            | Event::ExitRequested
+            | Event::EventsLost(_)
+            | Event::MonitorConfigurationChanged
+            | Event::WorkAreaChanged
+            | Event::FrameDeadlineMissed { .. }
+            | Event::GpuError { .. }
+            | Event::ScriptError { .. }
+            | Event::GpuReadbackReady(_)
+            | Event::Suspending
+            | Event::Resumed => return event,
+        };
+        if let Some(&attached) = self.window_map.get(window) {
+            *window = attached;
+        }
+        event
+    }
+}
+
+type Recorded = (Duration, Event, Option<String>);
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Text, GpuError and ScriptError events are written with `string`
+fn write_event<W: Write>(w: &mut W, event: &Event, string: &str) -> io::Result<()> {
+    match *event {
+        Event::CloseRequested { window } => {
+            write_u8(w, 0)?;
+            write_window(w, window)
+        }
+        Event::Resized { window, size } => {
+            write_u8(w, 1)?;
+            write_window(w, window)?;
+            write_u32(w, size.width)?;
+            write_u32(w, size.height)
+        }
+        Event::Focused { window, focused } => {
+            write_u8(w, 2)?;
+            write_window(w, window)?;
+            write_u8(w, focused as u8)
+        }
+        Event::KeyboardInput {
+            window,
+            keycode,
+            keysym,
+            pressed,
+            synthetic,
+            repeat,
+        } => {
+            write_u8(w, 3)?;
+            write_window(w, window)?;
+            write_u32(w, keycode)?;
+            write_xid(w, keysym)?;
+            write_u8(w, pressed as u8)?;
+            write_u8(w, synthetic as u8)?;
+            write_u8(w, repeat as u8)
+        }
+        Event::MouseInput {
+            window,
+            button,
+            pressed,
+            position,
+            root_position,
+            synthetic,
+            device,
+        } => {
+            write_u8(w, 4)?;
+            write_window(w, window)?;
+            write_u32(w, button)?;
+            write_u8(w, pressed as u8)?;
+            write_f64(w, position.x)?;
+            write_f64(w, position.y)?;
+            write_f64(w, root_position.x)?;
+            write_f64(w, root_position.y)?;
+            write_u8(w, synthetic as u8)?;
+            write_u32(w, device.0)
+        }
+        Event::CursorMoved {
+            window,
+            position,
+            root_position,
+            delta,
+            device,
+        } => {
+            write_u8(w, 5)?;
+            write_window(w, window)?;
+            write_f64(w, position.x)?;
+            write_f64(w, position.y)?;
+            write_f64(w, root_position.x)?;
+            write_f64(w, root_position.y)?;
+            write_f64(w, delta.0)?;
+            write_f64(w, delta.1)?;
+            write_u32(w, device.0)
+        }
+        Event::DeviceEvent { device, event } => {
+            write_u8(w, 6)?;
+            write_u32(w, device.0)?;
+            match event {
+                DeviceEvent::Key { keycode, pressed } => {
+                    write_u8(w, 0)?;
+                    write_u32(w, keycode)?;
+                    write_u8(w, pressed as u8)
+                }
+                DeviceEvent::Button { button, pressed } => {
+                    write_u8(w, 1)?;
+                    write_u32(w, button)?;
+                    write_u8(w, pressed as u8)
+                }
+                DeviceEvent::Motion { delta } => {
+                    write_u8(w, 2)?;
+                    write_f64(w, delta.0)?;
+                    write_f64(w, delta.1)
+                }
+            }
+        }
+        Event::Tablet {
+            window,
+            device,
+            position,
+            pressure,
+            tilt,
+            tool,
+            contact,
+        } => {
+            write_u8(w, 7)?;
+            write_window(w, window)?;
+            write_u32(w, device.0)?;
+            write_f64(w, position.x)?;
+            write_f64(w, position.y)?;
+            write_f64(w, pressure as f64)?;
+            write_f64(w, tilt.0 as f64)?;
+            write_f64(w, tilt.1 as f64)?;
+            write_u8(w, (tool == TabletTool::Eraser) as u8)?;
+            write_u8(w, contact as u8)
+        }
+        Event::ExitRequested => write_u8(w, 8),
+        Event::EventsLost(count) => {
+            write_u8(w, 9)?;
+            write_u32(w, count)
+        }
+        Event::MonitorConfigurationChanged => write_u8(w, 11),
+        Event::WorkAreaChanged => write_u8(w, 34),
+        Event::LateInput => write_u8(w, 35),
+        Event::FrameDeadlineMissed {
+            elapsed,
+            budget,
+            translation,
+        } => {
+            write_u8(w, 10)?;
+            write_duration(w, elapsed)?;
+            write_duration(w, budget)?;
+            write_duration(w, translation)
+        }
+        Event::GpuError { .. } => {
+            write_u8(w, 12)?;
+            write_str(w, string)
+        }
+        Event::Suspending => write_u8(w, 13),
+        Event::Resumed => write_u8(w, 14),
+        Event::WindowCreated { window } => {
+            write_u8(w, 15)?;
+            write_window(w, window)
+        }
+        Event::Destroyed { window } => {
+            write_u8(w, 16)?;
+            write_window(w, window)
+        }
+        Event::Text { window, .. } => {
+            write_u8(w, 17)?;
+            write_window(w, window)?;
+            write_str(w, string)
+        }
+        Event::StateChanged { window, change } => {
+            write_u8(w, 18)?;
+            write_window(w, window)?;
+            write_u8(w, change as u8)
+        }
+        Event::RedrawRequested { window } => {
+            write_u8(w, 20)?;
+            write_window(w, window)
+        }
+        Event::AboutToWait => write_u8(w, 21),
+        Event::MonitorChanged { window, monitor } => {
+            write_u8(w, 22)?;
+            write_window(w, window)?;
+            write_u64(w, monitor)
+        }
+        Event::ConnectionLost => write_u8(w, 23),
+        Event::DeviceAdded { device } => {
+            write_u8(w, 24)?;
+            write_u32(w, device.0)
+        }
+        Event::DeviceRemoved { device } => {
+            write_u8(w, 25)?;
+            write_u32(w, device.0)
+        }
+        Event::TrayIconClicked {
+            secondary,
+            position,
+        } => {
+            write_u8(w, 26)?;
+            write_u8(w, secondary as u8)?;
+            write_u32(w, position.x as u32)?;
+            write_u32(w, position.y as u32)
+        }
+        Event::TrayMenuItem { id } => {
+            write_u8(w, 27)?;
+            write_u32(w, id)
+        }
+        Event::FramePresented { window, msc, ust } => {
+            write_u8(w, 28)?;
+            write_window(w, window)?;
+            write_u64(w, msc)?;
+            write_duration(w, ust)
+        }
+        Event::CanvasViewportChanged { window, viewport } => {
+            write_u8(w, 29)?;
+            write_window(w, window)?;
+            write_u32(w, viewport.x as u32)?;
+            write_u32(w, viewport.y as u32)?;
+            write_u32(w, viewport.width)?;
+            write_u32(w, viewport.height)
+        }
+        Event::Snapped { window, edge } => {
+            write_u8(w, 31)?;
+            write_window(w, window)?;
+            write_u8(w, edge as u8)
+        }
+        Event::EmbeddedChildDestroyed { window, child } => {
+            write_u8(w, 32)?;
+            write_window(w, window)?;
+            write_u64(w, child)
+        }
+        Event::Channel(channel) => {
+            write_u8(w, 30)?;
+            write_u32(w, channel.into_raw())
+        }
+        Event::GlobalHotkey(hotkey) => {
+            write_u8(w, 33)?;
+            write_u32(w, hotkey.into_raw())
+        }
+        Event::ScriptError { .. } => {
+            write_u8(w, 36)?;
+            write_str(w, string)
+        }
+        Event::GpuReadbackReady(readback) => {
+            write_u8(w, 37)?;
+            write_u32(w, readback.into_raw())
+        }
+        Event::ModalBlocked { window, modal } => {
+            write_u8(w, 38)?;
+            write_window(w, window)?;
+            write_window(w, modal)
+        }
+        Event::MouseWheel { window, delta } => {
+            write_u8(w, 19)?;
+            write_window(w, window)?;
+            let (pixels, x, y) = match delta {
+                ScrollDelta::Lines(x, y) => (false, x as f64, y as f64),
+                ScrollDelta::Pixels(x, y) => (true, x, y),
+            };
+            write_u8(w, pixels as u8)?;
+            write_f64(w, x)?;
+            write_f64(w, y)
+        }
+    }
+}
+
+/// The event with the string of Text, GpuError and ScriptError events,
+/// whose index is left 0
+fn read_event<R: Read>(r: &mut R) -> io::Result<(Event, Option<String>)> {
+    let mut string = None;
+    let event = match read_u8(r)? {
+        0 => Event::CloseRequested {
+            window: read_window(r)?,
+        },
+        1 => Event::Resized {
+            window: read_window(r)?,
+            size: PhysicalSize::new(read_u32(r)?, read_u32(r)?),
+        },
+        2 => Event::Focused {
+            window: read_window(r)?,
+            focused: read_u8(r)? != 0,
+        },
+        3 => Event::KeyboardInput {
+            window: read_window(r)?,
+            keycode: read_u32(r)?,
+            keysym: read_xid(r)?,
+            pressed: read_u8(r)? != 0,
+            synthetic: read_u8(r)? != 0,
+            repeat: read_u8(r)? != 0,
+        },
+        4 => Event::MouseInput {
+            window: read_window(r)?,
+            button: read_u32(r)?,
+            pressed: read_u8(r)? != 0,
+            position: PhysicalPosition::new(read_f64(r)?, read_f64(r)?),
+            root_position: PhysicalPosition::new(read_f64(r)?, read_f64(r)?),
+            synthetic: read_u8(r)? != 0,
+            device: DeviceId(read_u32(r)?),
+        },
+        5 => Event::CursorMoved {
+            window: read_window(r)?,
+            position: PhysicalPosition::new(read_f64(r)?, read_f64(r)?),
+            root_position: PhysicalPosition::new(read_f64(r)?, read_f64(r)?),
+            delta: (read_f64(r)?, read_f64(r)?),
+            device: DeviceId(read_u32(r)?),
+        },
+        6 => {
+            let device = DeviceId(read_u32(r)?);
+            let event = match read_u8(r)? {
+                0 => DeviceEvent::Key {
+                    keycode: read_u32(r)?,
+                    pressed: read_u8(r)? != 0,
+                },
+                1 => DeviceEvent::Button {
+                    button: read_u32(r)?,
+                    pressed: read_u8(r)? != 0,
+                },
+                2 => DeviceEvent::Motion {
+                    delta: (read_f64(r)?, read_f64(r)?),
+                },
+                tag => return Err(invalid_data(&format!("unknown device event {}", tag))),
+            };
+            Event::DeviceEvent { device, event }
+        }
+        7 => Event::Tablet {
+            window: read_window(r)?,
+            device: DeviceId(read_u32(r)?),
+            position: PhysicalPosition::new(read_f64(r)?, read_f64(r)?),
+            pressure: read_f64(r)? as f32,
+            tilt: (read_f64(r)? as f32, read_f64(r)? as f32),
+            tool: if read_u8(r)? != 0 {
+                TabletTool::Eraser
+            } else {
+                TabletTool::Pen
+            },
+            contact: read_u8(r)? != 0,
+        },
+        8 => Event::ExitRequested,
+        9 => Event::EventsLost(read_u32(r)?),
+        11 => Event::MonitorConfigurationChanged,
+        10 => Event::FrameDeadlineMissed {
+            elapsed: read_duration(r)?,
+            budget: read_duration(r)?,
+            translation: read_duration(r)?,
+        },
+        12 => {
+            string = Some(read_string(r)?);
+            Event::GpuError { index: 0 }
+        }
+        13 => Event::Suspending,
+        14 => Event::Resumed,
+        15 => Event::WindowCreated {
+            window: read_window(r)?,
+        },
+        16 => Event::Destroyed {
+            window: read_window(r)?,
+        },
+        17 => {
+            let window = read_window(r)?;
+            string = Some(read_string(r)?);
+            Event::Text { window, index: 0 }
+        }
+        18 => Event::StateChanged {
+            window: read_window(r)?,
+            change: match read_u8(r)? {
+                0 => StateChange::Maximized,
+                1 => StateChange::Unmaximized,
+                2 => StateChange::FullscreenEntered,
+                3 => StateChange::FullscreenExited,
+                4 => StateChange::AttentionCleared,
+                change => return Err(invalid_data(&format!("unknown state change {}", change))),
+            },
+        },
+        19 => Event::MouseWheel {
+            window: read_window(r)?,
+            delta: {
+                let pixels = read_u8(r)? != 0;
+                let (x, y) = (read_f64(r)?, read_f64(r)?);
+                if pixels {
+                    ScrollDelta::Pixels(x, y)
+                } else {
+                    ScrollDelta::Lines(x as f32, y as f32)
+                }
+            },
+        },
+        20 => Event::RedrawRequested {
+            window: read_window(r)?,
+        },
+        21 => Event::AboutToWait,
+        22 => Event::MonitorChanged {
+            window: read_window(r)?,
+            monitor: read_u64(r)?,
+        },
+        23 => Event::ConnectionLost,
+        24 => Event::DeviceAdded {
+            device: DeviceId(read_u32(r)?),
+        },
+        25 => Event::DeviceRemoved {
+            device: DeviceId(read_u32(r)?),
+        },
+        26 => Event::TrayIconClicked {
+            secondary: read_u8(r)? != 0,
+            position: PhysicalPosition::new(read_u32(r)? as i32, read_u32(r)? as i32),
+        },
+        27 => Event::TrayMenuItem { id: read_u32(r)? },
+        28 => Event::FramePresented {
+            window: read_window(r)?,
+            msc: read_u64(r)?,
+            ust: read_duration(r)?,
+        },
+        29 => Event::CanvasViewportChanged {
+            window: read_window(r)?,
+            viewport: Rect {
+                x: read_u32(r)? as i32,
+                y: read_u32(r)? as i32,
+                width: read_u32(r)?,
+                height: read_u32(r)?,
+            },
+        },
+        30 => Event::Channel(ChannelId::from_raw(read_u32(r)?)),
+        31 => Event::Snapped {
+            window: read_window(r)?,
+            edge: match read_u8(r)? {
+                0 => Edge::Left,
+                1 => Edge::Top,
+                2 => Edge::Right,
+                3 => Edge::Bottom,
+                edge => return Err(invalid_data(&format!("unknown edge {}", edge))),
+            },
+        },
+        32 => Event::EmbeddedChildDestroyed {
+            window: read_window(r)?,
+            child: read_u64(r)?,
+        },
+        33 => Event::GlobalHotkey(HotkeyId::from_raw(read_u32(r)?)),
+        34 => Event::WorkAreaChanged,
+        35 => Event::LateInput,
+        36 => {
+            string = Some(read_string(r)?);
+            Event::ScriptError { index: 0 }
+        }
+        37 => Event::GpuReadbackReady(ReadbackId::from_raw(read_u32(r)?)),
+        38 => Event::ModalBlocked {
+            window: read_window(r)?,
+            modal: read_window(r)?,
+        },
+        tag => return Err(invalid_data(&format!("unknown event {}", tag))),
+    };
+    Ok((event, string))
+}
+
+fn write_u8<W: Write>(w: &mut W, value: u8) -> io::Result<()> {
+    w.write_all(&[value])
+}
+
+fn write_u32<W: Write>(w: &mut W, value: u32) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_u64<W: Write>(w: &mut W, value: u64) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_f64<W: Write>(w: &mut W, value: f64) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_duration<W: Write>(w: &mut W, duration: Duration) -> io::Result<()> {
+    write_u64(w, duration.as_nanos() as u64)
+}
+
+fn write_str<W: Write>(w: &mut W, string: &str) -> io::Result<()> {
+    write_u32(w, string.len() as u32)?;
+    w.write_all(string.as_bytes())
+}
+
+fn write_window<W: Write>(w: &mut W, window: WindowId) -> io::Result<()> {
+    write_u64(w, window.into_raw())
+}
+
+// Keysyms are unsigned longs, 32 bit on some targets
+#[allow(clippy::unnecessary_cast)]
+fn write_xid<W: Write>(w: &mut W, xid: xlib::XID) -> io::Result<()> {
+    write_u64(w, xid as u64)
+}
+
+fn read_u8<R: Read>(r: &mut R) -> io::Result<u8> {
+    let mut bytes = [0; 1];
+    r.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    r.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f64<R: Read>(r: &mut R) -> io::Result<f64> {
+    let mut bytes = [0; 8];
+    r.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+fn read_duration<R: Read>(r: &mut R) -> io::Result<Duration> {
+    Ok(Duration::from_nanos(read_u64(r)?))
+}
+
+fn read_string<R: Read>(r: &mut R) -> io::Result<String> {
+    let len = read_u32(r)? as u64;
+    let mut bytes = Vec::new();
+    r.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(bytes).map_err(|_| invalid_data("string is not UTF-8"))
+}
+
+fn read_window<R: Read>(r: &mut R) -> io::Result<WindowId> {
+    Ok(WindowId::from_raw(read_u64(r)?))
+}
+
+#[allow(clippy::unnecessary_cast)]
+fn read_xid<R: Read>(r: &mut R) -> io::Result<xlib::XID> {
+    Ok(read_u64(r)? as xlib::XID)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(bytes: &[u8]) -> io::Result<Event> {
+        read_event(&mut &bytes[..]).map(|(event, _)| event)
+    }
+
+    fn window() -> [u8; 8] {
+        7u64.to_le_bytes()
+    }
+
+    fn every_event() -> Vec<Event> {
+        let window = WindowId::new(0x40_0001, 2);
+        let other = WindowId::new(0x40_0002, 0);
+        let device = DeviceId(3);
+        let position = PhysicalPosition::new(1.5, -2.25);
+        let mut events = vec![
+            Event::WindowCreated { window },
+            Event::Destroyed { window },
+            Event::CloseRequested { window },
+            Event::Resized {
+                window,
+                size: PhysicalSize::new(640, 480),
+            },
+            Event::Focused {
+                window,
+                focused: true,
+            },
+            Event::KeyboardInput {
+                window,
+                keycode: 38,
+                keysym: 0x61,
+                pressed: true,
+                synthetic: false,
+                repeat: true,
+            },
+            Event::Text { window, index: 0 },
+            Event::Text { window, index: 1 },
+            Event::MouseInput {
+                window,
+                button: 3,
+                pressed: false,
+                position,
+                root_position: PhysicalPosition::new(101.5, 98.0),
+                synthetic: true,
+                device,
+            },
+            Event::MouseWheel {
+                window,
+                delta: ScrollDelta::Lines(0.5, -1.0),
+            },
+            Event::MouseWheel {
+                window,
+                delta: ScrollDelta::Pixels(3.25, 0.0),
+            },
+            Event::CursorMoved {
+                window,
+                position,
+                root_position: position,
+                delta: (0.5, -0.5),
+                device,
+            },
+            Event::DeviceEvent {
+                device,
+                event: DeviceEvent::Key {
+                    keycode: 9,
+                    pressed: true,
+                },
+            },
+            Event::DeviceEvent {
+                device,
+                event: DeviceEvent::Button {
+                    button: 1,
+                    pressed: false,
+                },
+            },
+            Event::DeviceEvent {
+                device,
+                event: DeviceEvent::Motion { delta: (-4.0, 2.5) },
+            },
+            Event::ExitRequested,
+            Event::EventsLost(12),
+            Event::MonitorConfigurationChanged,
+            Event::WorkAreaChanged,
+            Event::MonitorChanged {
+                window,
+                monitor: 0x55,
+            },
+            Event::FrameDeadlineMissed {
+                elapsed: Duration::from_micros(20_500),
+                budget: Duration::from_millis(16),
+                translation: Duration::from_nanos(700),
+            },
+            Event::GpuError { index: 0 },
+            Event::Suspending,
+            Event::Resumed,
+            Event::LateInput,
+            Event::RedrawRequested { window },
+            Event::AboutToWait,
+            Event::ConnectionLost,
+            Event::DeviceAdded { device },
+            Event::DeviceRemoved { device },
+            Event::TrayIconClicked {
+                secondary: true,
+                position: PhysicalPosition::new(-10, 20),
+            },
+            Event::TrayMenuItem { id: 4 },
+            Event::FramePresented {
+                window,
+                msc: 1234,
+                ust: Duration::from_nanos(987_654_321),
+            },
+            Event::CanvasViewportChanged {
+                window,
+                viewport: Rect {
+                    x: -8,
+                    y: 16,
+                    width: 320,
+                    height: 200,
+                },
+            },
+            Event::EmbeddedChildDestroyed {
+                window,
+                child: 0x60_0001,
+            },
+            Event::Channel(ChannelId::from_raw(5)),
+            Event::GlobalHotkey(HotkeyId::from_raw(6)),
+            Event::ScriptError { index: 0 },
+            Event::GpuReadbackReady(ReadbackId::from_raw(7)),
+            Event::ModalBlocked {
+                window,
+                modal: other,
+            },
+        ];
+        for &change in &[
+            StateChange::Maximized,
+            StateChange::Unmaximized,
+            StateChange::FullscreenEntered,
+            StateChange::FullscreenExited,
+            StateChange::AttentionCleared,
+        ] {
+            events.push(Event::StateChanged { window, change });
+        }
+        for &tool in &[TabletTool::Pen, TabletTool::Eraser] {
+            events.push(Event::Tablet {
+                window,
+                device,
+                position,
+                pressure: 0.75,
+                tilt: (0.25, -0.5),
+                tool,
+                contact: tool == TabletTool::Pen,
+            });
+        }
+        for &edge in &[Edge::Left, Edge::Top, Edge::Right, Edge::Bottom] {
+            events.push(Event::Snapped { window, edge });
+        }
+        events
+    }
+
+    fn string(event: &Event) -> Option<&'static str> {
+        match *event {
+            Event::Text { index: 0, .. } => Some("héllo"),
+            Event::Text { .. } => Some("wörld ✓"),
+            Event::GpuError { .. } => Some("validation error"),
+            Event::ScriptError { .. } => Some("line 3: unknown function"),
+            _ => None,
+        }
+    }
+
+    fn recording(windows: &[(WindowId, (u32, u32))], events: &[Event]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        write_u32(&mut bytes, windows.len() as u32).unwrap();
+        for &(window, (width, height)) in windows {
+            write_window(&mut bytes, window).unwrap();
+            write_u32(&mut bytes, width).unwrap();
+            write_u32(&mut bytes, height).unwrap();
+        }
+        let mut recorder = EventRecorder {
+            writer: bytes,
+            start: Instant::now(),
+        };
+        recorder.record_with(events, string).unwrap();
+        recorder.finish().unwrap()
+    }
+
+    #[test]
+    fn every_event_round_trips() {
+        let events = every_event();
+        let bytes = recording(&[], &events);
+        let mut player = EventPlayer::new(&bytes[..], Playback::AsFastAsPossible).unwrap();
+        let mut replayed = Vec::new();
+        player.poll_events(&mut replayed).unwrap();
+        assert_eq!(replayed, events);
+        assert!(player.is_finished());
+        assert_eq!(player.text(0), Some("héllo"));
+        assert_eq!(player.text(1), Some("wörld ✓"));
+        assert_eq!(player.text(2), None);
+        assert_eq!(player.gpu_error(0), Some("validation error"));
+        assert_eq!(player.script_error(0), Some("line 3: unknown function"));
+    }
+
+    #[test]
+    fn every_tag_is_written() {
+        let mut tags: Vec<u8> = every_event()
+            .iter()
+            .map(|event| {
+                let mut bytes = Vec::new();
+                write_event(&mut bytes, event, "").unwrap();
+                bytes[0]
+            })
+            .collect();
+        tags.sort_unstable();
+        tags.dedup();
+        assert_eq!(tags, (0..39).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn strings_belong_to_their_poll() {
+        let window = WindowId::new(0x40_0001, 0);
+        let text = |index| Event::Text { window, index };
+        let mut bytes = MAGIC.to_vec();
+        write_u32(&mut bytes, 0).unwrap();
+        for (micros, string) in [(0, "a"), (0, "b"), (3_600_000_000, "c")] {
+            write_u64(&mut bytes, micros).unwrap();
+            write_event(&mut bytes, &text(9), string).unwrap();
+        }
+        let mut player = EventPlayer::new(&bytes[..], Playback::RealTime).unwrap();
+        let mut events = Vec::new();
+        player.poll_events(&mut events).unwrap();
+        assert_eq!(events, [text(0), text(1)]);
+        assert_eq!((player.text(0), player.text(1)), (Some("a"), Some("b")));
+        // The third is read ahead but not due, its string waits with it
+        player.poll_events(&mut events).unwrap();
+        assert!(events.is_empty());
+        assert_eq!(player.text(0), None);
+        player.playback = Playback::AsFastAsPossible;
+        player.poll_events(&mut events).unwrap();
+        assert_eq!(events, [text(0)]);
+        assert_eq!(player.text(0), Some("c"));
+    }
+
+    #[test]
+    fn attach_maps_windows() {
+        let recorded = WindowId::new(0x40_0001, 0);
+        let attached = WindowId::new(0x80_0001, 0);
+        let bytes = recording(
+            &[(recorded, (640, 480))],
+            &[Event::ModalBlocked {
+                window: recorded,
+                modal: recorded,
+            }],
+        );
+        let mut player = EventPlayer::new(&bytes[..], Playback::AsFastAsPossible).unwrap();
+        assert_eq!(player.recorded_sizes().collect::<Vec<_>>(), [(640, 480)]);
+        player.attach_sizes(&[(attached, (640, 480))]).unwrap();
+        let mut events = Vec::new();
+        player.poll_events(&mut events).unwrap();
+        assert_eq!(
+            events,
+            [Event::ModalBlocked {
+                window: attached,
+                modal: attached,
+            }]
+        );
+    }
+
+    #[test]
+    fn attach_rejects_other_sizes() {
+        let recorded = WindowId::new(0x40_0001, 0);
+        let attached = WindowId::new(0x80_0001, 0);
+        let bytes = recording(&[(recorded, (640, 480))], &[]);
+        let mut player = EventPlayer::new(&bytes[..], Playback::AsFastAsPossible).unwrap();
+        let err = player.attach_sizes(&[(attached, (641, 480))]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "window 0x800001 is (641, 480), it was recorded at (640, 480)"
+        );
+        let err = player.attach_sizes(&[]).unwrap_err();
+        assert_eq!(err.to_string(), "recorded 1 windows, 0 attached");
+        // Nothing was mapped by the failed attempts
+        assert!(player.window_map.is_empty());
+    }
+
+    #[test]
+    fn unknown_events_are_errors() {
+        let err = read(&[200]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "unknown event 200");
+    }
+
+    #[test]
+    fn unknown_variants_are_errors() {
+        let mut device = vec![6, 1, 0, 0, 0, 9];
+        device.extend([0; 16]);
+        assert_eq!(
+            read(&device).unwrap_err().to_string(),
+            "unknown device event 9"
+        );
+        let mut change = vec![18];
+        change.extend(window());
+        change.push(5);
+        assert_eq!(
+            read(&change).unwrap_err().to_string(),
+            "unknown state change 5"
+        );
+        let mut edge = vec![31];
+        edge.extend(window());
+        edge.push(4);
+        assert_eq!(read(&edge).unwrap_err().to_string(), "unknown edge 4");
+    }
+
+    #[test]
+    fn truncated_events_are_errors() {
+        let err = read(&[1, 7, 0, 0]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = read(&[12, 200, 0, 0, 0, b'a']).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = read(&[12, 2, 0, 0, 0, 0xff, 0xfe]).unwrap_err();
+        assert_eq!(err.to_string(), "string is not UTF-8");
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let err = EventPlayer::new(&b"DASWREC3\0\0\0\0"[..], Playback::AsFastAsPossible)
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "not a daswin event recording");
+    }
+}
@@ -0,0 +1,349 @@
+//! Choosing the adapter a GpuContext renders with, and reporting why each
+//! one was passed over when none fits.
+//!
+//! Adapters are tried best first, and every adapter which cannot present
+//! to the window or lacks what GpuOptions asks for is recorded. On PRIME
+//! laptops only the GPU driving the X screen can present without render
+//! offload, which DRI_PRIME or __NV_PRIME_RENDER_OFFLOAD set up in the
+//! drivers, so those make discrete adapters go first. DASWIN_DEBUG prints
+//! each step
+
+use std::env;
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+use pollster::block_on;
+
+use crate::debug;
+use crate::window::X11Window;
+
+// Environment variables asking the drivers for render offload
+const PRIME_HINTS: [&str; 2] = ["DRI_PRIME", "__NV_PRIME_RENDER_OFFLOAD"];
+
+/// What GpuContext::with_options asks of the adapter and device
+#[derive(Debug, Clone, Default)]
+pub struct GpuOptions {
+    /// Only try adapters of one backend, e.g. wgpu::Backend::Gl to rule
+    /// out the Vulkan driver while debugging. None tries every backend,
+    /// and GL on its own instance last
+    pub require_backend: Option<wgpu::Backend>,
+    /// Integrated adapters go first with LowPower, discrete ones with
+    /// HighPerformance or a PRIME hint. Software adapters always go last
+    pub power_preference: wgpu::PowerPreference,
+    /// Features the device is created with
+    pub features: wgpu::Features,
+    /// Limits the device is created with. None takes the defaults, or the
+    /// downlevel ones on GL and adapters falling short of the defaults
+    pub limits: Option<wgpu::Limits>,
+}
+
+/// Why an adapter was passed over
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AdapterFailure {
+    /// The adapter cannot present to the window, e.g. a GPU not driving
+    /// the X screen on a PRIME laptop without render offload
+    IncompatibleSurface,
+    /// The features of GpuOptions the adapter lacks
+    MissingFeatures(wgpu::Features),
+    /// Limits of GpuOptions the adapter does not allow, by name with the
+    /// requested and the allowed value
+    LimitsExceeded(Vec<(&'static str, u64, u64)>),
+    /// The driver failed to create the device, wgpu does not say why
+    RequestDevice,
+}
+
+impl fmt::Display for AdapterFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AdapterFailure::IncompatibleSurface => write!(f, "cannot present to the window"),
+            AdapterFailure::MissingFeatures(features) => {
+                write!(f, "missing features {:?}", features)
+            }
+            AdapterFailure::LimitsExceeded(limits) => {
+                write!(f, "limits exceeded:")?;
+                for (name, requested, allowed) in limits {
+                    write!(f, " {} {} > {}", name, requested, allowed)?;
+                }
+                Ok(())
+            }
+            AdapterFailure::RequestDevice => write!(f, "creating the device failed"),
+        }
+    }
+}
+
+/// No adapter could render to the window, see GpuContext::with_options
+#[derive(Debug, Clone)]
+pub struct GpuInitError {
+    /// Every adapter tried in order, with why it failed
+    pub adapters: Vec<(wgpu::AdapterInfo, AdapterFailure)>,
+    /// The PRIME hints which were set, e.g. "DRI_PRIME=1"
+    pub prime_hints: Vec<String>,
+    pub require_backend: Option<wgpu::Backend>,
+}
+
+impl fmt::Display for GpuInitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no adapter can render to the window")?;
+        if let Some(backend) = self.require_backend {
+            write!(f, " with backend {:?}", backend)?;
+        }
+        if !self.prime_hints.is_empty() {
+            write!(f, " with {}", self.prime_hints.join(" "))?;
+        }
+        if self.adapters.is_empty() {
+            return write!(f, ", none were found");
+        }
+        for (info, failure) in &self.adapters {
+            write!(f, "\n  {}: {}", describe(info), failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for GpuInitError {}
+
+/// The adapter chosen for a window, with the device created on it
+pub(crate) struct Selection {
+    pub instance: Arc<wgpu::Instance>,
+    pub surface: wgpu::Surface,
+    pub adapter: wgpu::Adapter,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+}
+
+/// Try the adapters for the window best first, until one presents to it
+/// and creates a device with the options
+pub(crate) fn select(window: &X11Window, options: &GpuOptions) -> Result<Selection, GpuInitError> {
+    let prime_hints: Vec<String> = PRIME_HINTS
+        .iter()
+        .filter_map(|name| {
+            let value = env::var(name)
+                .ok()
+                .filter(|value| !value.is_empty() && value != "0")?;
+            Some(format!("{}={}", name, value))
+        })
+        .collect();
+    let offload = !prime_hints.is_empty();
+    if offload {
+        log::info!(
+            "render offload requested with {}, trying discrete adapters first",
+            prime_hints.join(" ")
+        );
+    }
+
+    // GL is tried on an instance of its own too, which may work where
+    // Vulkan is broken
+    let passes = match options.require_backend {
+        Some(backend) => vec![wgpu::Backends::from(backend)],
+        None => vec![wgpu::Backends::all(), wgpu::Backends::GL],
+    };
+    let mut failures = Vec::new();
+    for backends in passes {
+        trace(format_args!("enumerating adapters of {:?}", backends));
+        let instance = Arc::new(wgpu::Instance::new(backends));
+        let surface = unsafe { instance.create_surface(window) };
+        let adapters = instance
+            .enumerate_adapters(backends)
+            .map(|adapter| (adapter.get_info(), adapter))
+            .collect();
+        let create = |adapter: &wgpu::Adapter| create_device(adapter, &surface, options);
+        if let Some((adapter, (device, queue))) =
+            pick(adapters, options, offload, &mut failures, create)
+        {
+            return Ok(Selection {
+                instance,
+                surface,
+                adapter,
+                device,
+                queue,
+            });
+        }
+    }
+    Err(GpuInitError {
+        adapters: failures,
+        prime_hints,
+        require_backend: options.require_backend,
+    })
+}
+
+/// Try `adapters` best first with `create` and return the first it
+/// succeeds with, appending the failures before it to `failures`
+fn pick<A, R>(
+    mut adapters: Vec<(wgpu::AdapterInfo, A)>,
+    options: &GpuOptions,
+    offload: bool,
+    failures: &mut Vec<(wgpu::AdapterInfo, AdapterFailure)>,
+    mut create: impl FnMut(&A) -> Result<R, AdapterFailure>,
+) -> Option<(A, R)> {
+    // Stable, so adapters of a rank keep the order of the backend
+    adapters.sort_by_key(|(info, _)| rank(info, options, offload));
+    for (info, adapter) in adapters {
+        match create(&adapter) {
+            Ok(created) => {
+                trace(format_args!("  {}: selected", describe(&info)));
+                return Some((adapter, created));
+            }
+            Err(failure) => {
+                trace(format_args!("  {}: {}", describe(&info), failure));
+                failures.push((info, failure));
+            }
+        }
+    }
+    None
+}
+
+// Lower goes first
+fn rank(info: &wgpu::AdapterInfo, options: &GpuOptions, offload: bool) -> u8 {
+    let high_performance =
+        offload || options.power_preference == wgpu::PowerPreference::HighPerformance;
+    match info.device_type {
+        wgpu::DeviceType::DiscreteGpu if high_performance => 0,
+        wgpu::DeviceType::IntegratedGpu if !high_performance => 0,
+        wgpu::DeviceType::DiscreteGpu | wgpu::DeviceType::IntegratedGpu => 1,
+        wgpu::DeviceType::VirtualGpu | wgpu::DeviceType::Other => 2,
+        wgpu::DeviceType::Cpu => 3,
+    }
+}
+
+fn create_device(
+    adapter: &wgpu::Adapter,
+    surface: &wgpu::Surface,
+    options: &GpuOptions,
+) -> Result<(wgpu::Device, wgpu::Queue), AdapterFailure> {
+    if !adapter.is_surface_supported(surface) {
+        return Err(AdapterFailure::IncompatibleSurface);
+    }
+    let missing = options.features - adapter.features();
+    if !missing.is_empty() {
+        return Err(AdapterFailure::MissingFeatures(missing));
+    }
+
+    let allowed = adapter.limits();
+    let limits = match &options.limits {
+        Some(limits) => limits.clone(),
+        // GL adapters rarely meet the default limits
+        None if adapter.get_info().backend == wgpu::Backend::Gl => {
+            wgpu::Limits::downlevel_webgl2_defaults().using_resolution(allowed.clone())
+        }
+        None if wgpu::Limits::default().check_limits(&allowed) => wgpu::Limits::default(),
+        None => wgpu::Limits::downlevel_defaults().using_resolution(allowed.clone()),
+    };
+    let mut exceeded = Vec::new();
+    limits.check_limits_with_fail_fn(&allowed, false, |name, requested, allowed| {
+        exceeded.push((name, requested, allowed))
+    });
+    if !exceeded.is_empty() {
+        return Err(AdapterFailure::LimitsExceeded(exceeded));
+    }
+
+    block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            features: options.features,
+            limits,
+        },
+        None,
+    ))
+    .map_err(|_| AdapterFailure::RequestDevice)
+}
+
+fn describe(info: &wgpu::AdapterInfo) -> String {
+    format!("{:?} {} ({:?})", info.backend, info.name, info.device_type)
+}
+
+fn trace(step: fmt::Arguments) {
+    if debug::enabled() {
+        eprintln!("daswin: gpu: {}", step);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(name: &str, device_type: wgpu::DeviceType) -> wgpu::AdapterInfo {
+        wgpu::AdapterInfo {
+            name: name.into(),
+            vendor: 0,
+            device: 0,
+            device_type,
+            driver: String::new(),
+            driver_info: String::new(),
+            backend: wgpu::Backend::Vulkan,
+        }
+    }
+
+    // Enumerated worst first, as drivers may list them
+    fn adapters() -> Vec<(wgpu::AdapterInfo, &'static str)> {
+        vec![
+            (info("llvmpipe", wgpu::DeviceType::Cpu), "llvmpipe"),
+            (info("Intel", wgpu::DeviceType::IntegratedGpu), "Intel"),
+            (info("NVIDIA", wgpu::DeviceType::DiscreteGpu), "NVIDIA"),
+        ]
+    }
+
+    fn high_performance() -> GpuOptions {
+        GpuOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..GpuOptions::default()
+        }
+    }
+
+    // Every adapter fails but `works`, returning the one picked and the
+    // names of those tried before it
+    fn pick_with(
+        options: &GpuOptions,
+        offload: bool,
+        works: Option<&str>,
+    ) -> (Option<&'static str>, Vec<String>) {
+        let mut failures = Vec::new();
+        let picked = pick(adapters(), options, offload, &mut failures, |&name| {
+            if Some(name) == works {
+                Ok(())
+            } else {
+                Err(AdapterFailure::IncompatibleSurface)
+            }
+        });
+        let tried = failures.into_iter().map(|(info, _)| info.name).collect();
+        (picked.map(|(name, ())| name), tried)
+    }
+
+    #[test]
+    fn falls_back_from_discrete_to_integrated_to_software() {
+        let options = high_performance();
+        assert_eq!(
+            pick_with(&options, false, Some("NVIDIA")),
+            (Some("NVIDIA"), vec![])
+        );
+        assert_eq!(
+            pick_with(&options, false, Some("Intel")),
+            (Some("Intel"), vec!["NVIDIA".to_owned()])
+        );
+        assert_eq!(
+            pick_with(&options, false, Some("llvmpipe")),
+            (
+                Some("llvmpipe"),
+                vec!["NVIDIA".to_owned(), "Intel".to_owned()]
+            )
+        );
+    }
+
+    #[test]
+    fn reports_every_adapter_when_none_works() {
+        let (picked, tried) = pick_with(&high_performance(), false, None);
+        assert_eq!(picked, None);
+        assert_eq!(tried, ["NVIDIA", "Intel", "llvmpipe"]);
+    }
+
+    #[test]
+    fn low_power_prefers_integrated() {
+        let options = GpuOptions::default();
+        let (_, tried) = pick_with(&options, false, None);
+        assert_eq!(tried, ["Intel", "NVIDIA", "llvmpipe"]);
+        // Unless a PRIME hint asks for offload
+        let (_, tried) = pick_with(&options, true, None);
+        assert_eq!(tried, ["NVIDIA", "Intel", "llvmpipe"]);
+    }
+}
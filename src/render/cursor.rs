@@ -0,0 +1,235 @@
+//! Pictures of the cursor shapes, for windows drawing their cursor in
+//! software, see X11Window::software_cursor
+
+use crate::dpi::{PhysicalPosition, PhysicalSize};
+use crate::window::{CursorIcon, SoftwareCursor};
+
+// Rows of the shapes, X is the black outline, . the white fill and
+// blanks are transparent
+#[rustfmt::skip]
+const ARROW: &[&str] = &[
+    "X",
+    "XX",
+    "X.X",
+    "X..X",
+    "X...X",
+    "X....X",
+    "X.....X",
+    "X......X",
+    "X.......X",
+    "X........X",
+    "X.....XXXXX",
+    "X..X..X",
+    "X.X X..X",
+    "XX  X..X",
+    "X    X..X",
+    "     X..X",
+    "      XX",
+];
+
+#[rustfmt::skip]
+const TEXT: &[&str] = &[
+    "XX XX",
+    "X.X.X",
+    " X.X",
+    " X.X",
+    " X.X",
+    " X.X",
+    " X.X",
+    " X.X",
+    " X.X",
+    " X.X",
+    " X.X",
+    " X.X",
+    " X.X",
+    "X.X.X",
+    "XX XX",
+];
+
+#[rustfmt::skip]
+const HAND: &[&str] = &[
+    "    XX",
+    "   X..X",
+    "   X..X",
+    "   X..X",
+    "   X..XXX",
+    "   X..X..XXX",
+    "   X..X..X..XX",
+    "XX X..X..X..X.X",
+    "X..X..........X",
+    "X...X.........X",
+    " X............X",
+    "  X...........X",
+    "  X..........X",
+    "   X.........X",
+    "   X........X",
+    "    XXXXXXXX",
+];
+
+#[rustfmt::skip]
+const CROSSHAIR: &[&str] = &[
+    "      XXX",
+    "      X.X",
+    "      X.X",
+    "      X.X",
+    "      X.X",
+    "      X.X",
+    "XXXXXXX.XXXXXXX",
+    "X.............X",
+    "XXXXXXX.XXXXXXX",
+    "      X.X",
+    "      X.X",
+    "      X.X",
+    "      X.X",
+    "      X.X",
+    "      XXX",
+];
+
+#[rustfmt::skip]
+const MOVE: &[&str] = &[
+    "       X",
+    "      X.X",
+    "     X...X",
+    "    XXX.XXX",
+    "   X  X.X  X",
+    "  XX  X.X  XX",
+    " X.XXXX.XXXX.X",
+    "X.............X",
+    " X.XXXX.XXXX.X",
+    "  XX  X.X  XX",
+    "   X  X.X  X",
+    "    XXX.XXX",
+    "     X...X",
+    "      X.X",
+    "       X",
+];
+
+#[rustfmt::skip]
+const WAIT: &[&str] = &[
+    "XXXXXXXXXXX",
+    "X.........X",
+    "XXXXXXXXXXX",
+    " X.......X",
+    "  X.....X",
+    "   X...X",
+    "    X.X",
+    "    X.X",
+    "   X...X",
+    "  X.....X",
+    " X.......X",
+    "XXXXXXXXXXX",
+    "X.........X",
+    "XXXXXXXXXXX",
+];
+
+// Also the vertical arrow, transposed
+#[rustfmt::skip]
+const RESIZE: &[&str] = &[
+    "   X         X",
+    "  XX         XX",
+    " X.XXXXXXXXXXX.X",
+    "X...............X",
+    " X.XXXXXXXXXXX.X",
+    "  XX         XX",
+    "   X         X",
+];
+
+/// The pixels of a cursor shape and the point of it which goes where
+/// the pointer is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorImage {
+    size: PhysicalSize<u32>,
+    hotspot: PhysicalPosition<u32>,
+    rgba: Vec<u8>,
+}
+
+impl CursorImage {
+    /// The picture of a shape, CursorIcon::Default is the arrow
+    pub fn new(icon: CursorIcon) -> CursorImage {
+        let (rows, hotspot, transposed) = match icon {
+            CursorIcon::Default | CursorIcon::Arrow => (ARROW, (0, 0), false),
+            CursorIcon::Text => (TEXT, (2, 7), false),
+            CursorIcon::Hand => (HAND, (4, 0), false),
+            CursorIcon::Crosshair => (CROSSHAIR, (7, 7), false),
+            CursorIcon::Move => (MOVE, (7, 7), false),
+            CursorIcon::Wait => (WAIT, (5, 6), false),
+            CursorIcon::ResizeHorizontal => (RESIZE, (8, 3), false),
+            CursorIcon::ResizeVertical => (RESIZE, (3, 8), true),
+        };
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0) as u32;
+        let height = rows.len() as u32;
+        let (width, height) = if transposed {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        let mut rgba = vec![0; (width * height * 4) as usize];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.bytes().enumerate() {
+                let color = match c {
+                    b'X' => [0, 0, 0, 255],
+                    b'.' => [255, 255, 255, 255],
+                    _ => continue,
+                };
+                let (x, y) = if transposed { (y, x) } else { (x, y) };
+                let start = (y * width as usize + x) * 4;
+                rgba[start..start + 4].copy_from_slice(&color);
+            }
+        }
+        CursorImage {
+            size: PhysicalSize::new(width, height),
+            hotspot: PhysicalPosition::new(hotspot.0, hotspot.1),
+            rgba,
+        }
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    /// Offset of the hotspot from the top left of the image
+    pub fn hotspot(&self) -> PhysicalPosition<u32> {
+        self.hotspot
+    }
+
+    /// Tightly packed RGBA8 rows, every pixel either opaque or fully
+    /// transparent
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// Where the top left of the image goes for the hotspot to land on
+    /// the cursor position, in whole pixels
+    pub fn origin(&self, cursor: SoftwareCursor) -> PhysicalPosition<i32> {
+        PhysicalPosition::new(
+            cursor.position.x.floor() as i32 - self.hotspot.x as i32,
+            cursor.position.y.floor() as i32 - self.hotspot.y as i32,
+        )
+    }
+
+    /// Draw the image onto an RGBA8 frame of `size`, e.g. of a
+    /// SoftwareSurface, clipped to the frame
+    pub fn draw_onto(&self, frame: &mut [u8], size: PhysicalSize<u32>, cursor: SoftwareCursor) {
+        let origin = self.origin(cursor);
+        for y in 0..self.size.height as i32 {
+            let frame_y = origin.y + y;
+            if frame_y < 0 || frame_y >= size.height as i32 {
+                continue;
+            }
+            for x in 0..self.size.width as i32 {
+                let frame_x = origin.x + x;
+                if frame_x < 0 || frame_x >= size.width as i32 {
+                    continue;
+                }
+                let from = ((y * self.size.width as i32 + x) * 4) as usize;
+                if self.rgba[from + 3] == 0 {
+                    continue;
+                }
+                let to = ((frame_y as usize) * size.width as usize + frame_x as usize) * 4;
+                if let Some(pixel) = frame.get_mut(to..to + 4) {
+                    pixel.copy_from_slice(&self.rgba[from..from + 4]);
+                }
+            }
+        }
+    }
+}
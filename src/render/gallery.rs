@@ -0,0 +1,207 @@
+//! Cycling through fullscreen effects loaded from a directory of WGSL
+//! files, as a playground for fragment shaders
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::helpers::FullscreenShader;
+
+/// Effects kept compiled at once, the least recently viewed others are
+/// released
+pub const CACHED_EFFECTS: usize = 4;
+
+/// A WGSL file and its pipeline when compiled
+struct Effect {
+    path: PathBuf,
+    name: String,
+    // Modification time of the file the shader was compiled from
+    modified: Option<SystemTime>,
+    shader: Option<FullscreenShader>,
+    error: Option<String>,
+    // Value of Gallery::views when last viewed, for releasing pipelines
+    viewed: u64,
+}
+
+/// The .wgsl files of a directory as FullscreenShaders, see
+/// FullscreenShader for what the fragment sources define.
+///
+/// Effects are compiled when first viewed and again whenever their file
+/// changes, a failing recompile keeps the previous pipeline and records
+/// the error. Only CACHED_EFFECTS pipelines are kept
+pub struct Gallery {
+    effects: Vec<Effect>,
+    current: usize,
+    format: wgpu::TextureFormat,
+    views: u64,
+}
+
+impl Gallery {
+    /// List the .wgsl files of the directory, sorted by name. Nothing is
+    /// compiled yet
+    pub fn scan(dir: impl AsRef<Path>, format: wgpu::TextureFormat) -> io::Result<Gallery> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "wgsl") && path.is_file() {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let effects = paths
+            .into_iter()
+            .map(|path| Effect {
+                name: path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                path,
+                modified: None,
+                shader: None,
+                error: None,
+                viewed: 0,
+            })
+            .collect();
+        Ok(Gallery {
+            effects,
+            current: 0,
+            format,
+            views: 0,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.effects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    /// Names of the effects, the file names without extension
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.effects.iter().map(|effect| effect.name.as_str())
+    }
+
+    /// Index of the effect being viewed
+    pub fn index(&self) -> usize {
+        self.current
+    }
+
+    /// Name of the effect being viewed, None when the directory held none
+    pub fn name(&self) -> Option<&str> {
+        self.effects.get(self.current).map(|e| e.name.as_str())
+    }
+
+    /// Names of the effects which failed to compile along with the error
+    pub fn errors(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.effects
+            .iter()
+            .filter_map(|effect| Some((effect.name.as_str(), effect.error.as_deref()?)))
+    }
+
+    /// Pipelines compiled at the moment
+    pub fn compiled(&self) -> usize {
+        self.effects.iter().filter(|e| e.shader.is_some()).count()
+    }
+
+    /// Target format of the pipelines, they are rebuilt when next viewed
+    pub fn set_format(&mut self, format: wgpu::TextureFormat) {
+        self.format = format;
+    }
+
+    /// View the next effect which compiles, wrapping around
+    pub fn next(&mut self, device: &wgpu::Device) {
+        self.step(device, 1);
+    }
+
+    /// View the previous effect which compiles, wrapping around
+    pub fn previous(&mut self, device: &wgpu::Device) {
+        self.step(device, self.effects.len().saturating_sub(1));
+    }
+
+    fn step(&mut self, device: &wgpu::Device, offset: usize) {
+        for _ in 0..self.effects.len() {
+            self.current = (self.current + offset) % self.effects.len();
+            if self.load(device) {
+                return;
+            }
+        }
+    }
+
+    /// The pipeline of the effect being viewed, compiled or recompiled
+    /// when needed. An effect which never compiled is skipped for the
+    /// next one, None when none compiles
+    pub fn current(&mut self, device: &wgpu::Device) -> Option<&FullscreenShader> {
+        if !self.load(device) {
+            self.step(device, 1);
+        }
+        self.effects.get(self.current)?.shader.as_ref()
+    }
+
+    // Bring the pipeline of the current effect up to date with its file
+    // and the format, returns whether there is one
+    fn load(&mut self, device: &wgpu::Device) -> bool {
+        let (format, views) = (self.format, self.views + 1);
+        let effect = match self.effects.get_mut(self.current) {
+            Some(effect) => effect,
+            None => return false,
+        };
+        self.views = views;
+        effect.viewed = views;
+
+        let modified = fs::metadata(&effect.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let stale =
+            effect.modified != modified || (effect.shader.is_none() && effect.error.is_none());
+        if stale {
+            effect.modified = modified;
+            let compiled = fs::read_to_string(&effect.path)
+                .map_err(|err| err.to_string())
+                .and_then(|source| {
+                    FullscreenShader::try_new(device, format, &source)
+                        .map_err(|err| err.to_string())
+                });
+            match compiled {
+                Ok(shader) => {
+                    log::info!("compiled effect {}", effect.name);
+                    effect.shader = Some(shader);
+                    effect.error = None;
+                }
+                Err(err) => {
+                    log::warn!("effect {} failed to compile: {}", effect.name, err);
+                    effect.error = Some(err);
+                }
+            }
+        }
+
+        let loaded = match &mut effect.shader {
+            Some(shader) => {
+                shader.rebuild(device, format);
+                true
+            }
+            None => false,
+        };
+        self.release();
+        loaded
+    }
+
+    // Drop the least recently viewed pipelines beyond CACHED_EFFECTS
+    fn release(&mut self) {
+        while self.compiled() > CACHED_EFFECTS {
+            let oldest = self
+                .effects
+                .iter_mut()
+                .filter(|effect| effect.shader.is_some())
+                .min_by_key(|effect| effect.viewed)
+                .unwrap();
+            log::trace!("releasing effect {}", oldest.name);
+            oldest.shader = None;
+            // Compiled again when viewed, as if the file had changed
+            oldest.modified = None;
+        }
+    }
+}
@@ -0,0 +1,1227 @@
+//! wgpu setup and surface management for windows
+
+use std::error;
+use std::fmt;
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
+
+use super::adapter::{self, GpuInitError, GpuOptions, Selection};
+use super::helpers::Blit;
+use super::surface::{AcquireError, PresentParams, RenderBackend, RenderSurface};
+use crate::dpi::PhysicalSize;
+use crate::event::{Event, ReadbackId, WindowId};
+use crate::window::X11Window;
+use crate::xconn::XConnection;
+
+/// The wgpu objects shared by everything rendering to our windows.
+///
+/// GL adapters keep using the display the context was created on, which
+/// stays open until the context is dropped
+pub struct GpuContext {
+    pub instance: Arc<wgpu::Instance>,
+    pub adapter: wgpu::Adapter,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    // Dropped last, after everything using the display
+    _display: Arc<XConnection>,
+}
+
+impl GpuContext {
+    /// Create a device able to present to the window, taking ownership of
+    /// the window to bundle it with its surface so they are torn down in
+    /// the right order. Panics with every adapter tried and why it failed
+    /// when none can
+    pub fn with_window(window: X11Window) -> (GpuContext, WindowSurface) {
+        GpuContext::with_options(window, &GpuOptions::default())
+            .unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Like with_window, but hands the window back when every adapter
+    /// failed so the caller can fall back to software::SoftwareSurface
+    #[allow(clippy::result_large_err)]
+    pub fn try_with_window(window: X11Window) -> Result<(GpuContext, WindowSurface), X11Window> {
+        match GpuContext::select(&window, &GpuOptions::default()) {
+            Ok((gpu, surface)) => Ok(GpuContext::bundle(gpu, surface, window)),
+            Err(error) => {
+                log::warn!("{}", error);
+                Err(window)
+            }
+        }
+    }
+
+    /// Like with_window on the first adapter meeting the options, best
+    /// first as described in GpuOptions. The error lists every adapter
+    /// tried, the window is dropped with it
+    pub fn with_options(
+        window: X11Window,
+        options: &GpuOptions,
+    ) -> Result<(GpuContext, WindowSurface), GpuInitError> {
+        let (gpu, surface) = GpuContext::select(&window, options)?;
+        Ok(GpuContext::bundle(gpu, surface, window))
+    }
+
+    fn bundle(
+        gpu: GpuContext,
+        surface: wgpu::Surface,
+        window: X11Window,
+    ) -> (GpuContext, WindowSurface) {
+        let surface = SurfaceState::new(&gpu, surface, window.inner_size());
+        (gpu, WindowSurface { surface, window })
+    }
+
+    fn select(
+        window: &X11Window,
+        options: &GpuOptions,
+    ) -> Result<(GpuContext, wgpu::Surface), GpuInitError> {
+        let Selection {
+            instance,
+            surface,
+            adapter,
+            device,
+            queue,
+        } = adapter::select(window, options)?;
+
+        // Report errors outside an error scope as Event::GpuError instead
+        // of panicking, or log them once the event loop is gone
+        let conn = Arc::downgrade(&window.conn);
+        device.on_uncaptured_error(move |error| match conn.upgrade() {
+            Some(conn) => {
+                conn.gpu_errors.lock().unwrap().push(error.to_string());
+                conn.wake.wake();
+            }
+            None => log::error!("wgpu: {}", error),
+        });
+        let mut instances = window.conn.gpu_instances.lock().unwrap();
+        instances.retain(|instance| instance.strong_count() > 0);
+        instances.push(Arc::downgrade(&instance));
+        drop(instances);
+
+        let gpu = GpuContext {
+            instance,
+            adapter,
+            device,
+            queue,
+            _display: window.conn.clone(),
+        };
+        Ok((gpu, surface))
+    }
+
+    /// Map `bounds` of `buffer` for reading without waiting for the GPU,
+    /// then run `callback` with the bytes on the event loop thread, e.g.
+    /// after copying a frame into the buffer. The buffer needs
+    /// BufferUsages::MAP_READ and stays alive until the callback ran.
+    ///
+    /// The loop polls the device on every iteration and runs the callback
+    /// in the first poll after the GPU finished, followed by
+    /// Event::GpuReadbackReady with the id returned. Any number of
+    /// readbacks may be pending. Those for `window` are cancelled once it
+    /// is closed or dropped, as are all of them when the GpuContext is
+    /// dropped. Cancelled callbacks are dropped without running
+    pub fn map_async_callback<F>(
+        &self,
+        buffer: Arc<wgpu::Buffer>,
+        bounds: impl RangeBounds<wgpu::BufferAddress>,
+        window: Option<WindowId>,
+        callback: F,
+    ) -> ReadbackId
+    where
+        F: FnOnce(Result<&[u8], wgpu::BufferAsyncError>) + Send + 'static,
+    {
+        let start = match bounds.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match bounds.end_bound() {
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => buffer.size(),
+        };
+        self._display.readbacks.lock().unwrap().start(
+            &self.instance,
+            buffer,
+            start..end,
+            window,
+            Box::new(callback),
+        )
+    }
+
+    /// Cancel a readback of map_async_callback, dropping its callback
+    /// unrun. False when it was delivered or cancelled before
+    pub fn cancel_readback(&self, readback: ReadbackId) -> bool {
+        self._display.readbacks.lock().unwrap().cancel(readback)
+    }
+
+    /// Whether a readback of map_async_callback is still waiting for the
+    /// GPU or the next poll
+    pub fn is_readback_pending(&self, readback: ReadbackId) -> bool {
+        self._display.readbacks.lock().unwrap().is_pending(readback)
+    }
+
+    /// Deliver `message` as Event::GpuError with the next poll, like the
+    /// uncaptured errors of the device
+    fn report_error(&self, message: &str) {
+        log::error!("wgpu: {}", message);
+        self._display
+            .gpu_errors
+            .lock()
+            .unwrap()
+            .push(message.to_owned());
+        self._display.wake.wake();
+    }
+}
+
+/// Usages an intermediate texture can provide for a surface which only
+/// renders, see SurfaceOptions
+const INTERMEDIATE_USAGES: wgpu::TextureUsages = wgpu::TextureUsages::RENDER_ATTACHMENT
+    .union(wgpu::TextureUsages::COPY_SRC)
+    .union(wgpu::TextureUsages::COPY_DST)
+    .union(wgpu::TextureUsages::TEXTURE_BINDING);
+
+/// What a surface supports on an adapter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SurfaceCapabilities {
+    pub formats: Vec<wgpu::TextureFormat>,
+    pub alpha_modes: Vec<wgpu::CompositeAlphaMode>,
+    pub usages: wgpu::TextureUsages,
+}
+
+impl SurfaceCapabilities {
+    /// Query the capabilities of the surface on the adapter of `gpu`.
+    ///
+    /// wgpu does not report the usages, GL surfaces are only rendered to
+    /// while the Vulkan drivers of X11 also allow copies
+    pub fn query(gpu: &GpuContext, surface: &wgpu::Surface) -> SurfaceCapabilities {
+        SurfaceCapabilities {
+            formats: surface.get_supported_formats(&gpu.adapter),
+            alpha_modes: surface.get_supported_alpha_modes(&gpu.adapter),
+            usages: SurfaceCapabilities::usages(gpu.adapter.get_info().backend),
+        }
+    }
+
+    /// The usages surfaces of `backend` allow
+    fn usages(backend: wgpu::Backend) -> wgpu::TextureUsages {
+        match backend {
+            wgpu::Backend::Vulkan => {
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::COPY_SRC
+                    | wgpu::TextureUsages::COPY_DST
+            }
+            _ => wgpu::TextureUsages::RENDER_ATTACHMENT,
+        }
+    }
+}
+
+/// Formats, usage and alpha mode requested for the textures of a surface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceOptions {
+    /// Formats to use in order of preference, e.g. Rgba16Float ahead of
+    /// Bgra8UnormSrgb to render HDR where the output supports it. The
+    /// first supported one is taken again whenever the window moves to
+    /// another monitor, see WindowSurface::handle_event. Empty or
+    /// unsupported lists take the first format the surface supports
+    pub formats: &'static [wgpu::TextureFormat],
+    /// Usages of the frames. Copying and sampling frames of a surface
+    /// which only renders goes through an intermediate texture, blitted
+    /// onto the frame by SurfaceState::resolve
+    pub usage: wgpu::TextureUsages,
+    /// How the compositor blends the window, None takes the first mode
+    /// the surface supports. Use PreMultiplied for overlays
+    pub alpha_mode: Option<wgpu::CompositeAlphaMode>,
+}
+
+impl Default for SurfaceOptions {
+    fn default() -> SurfaceOptions {
+        SurfaceOptions {
+            formats: &[],
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            alpha_mode: None,
+        }
+    }
+}
+
+impl SurfaceOptions {
+    /// The most preferred of the supported formats, which the surface
+    /// lists best first. Bgra8UnormSrgb when none are supported, as for a
+    /// surface the adapter cannot present to
+    pub fn choose_format(&self, supported: &[wgpu::TextureFormat]) -> wgpu::TextureFormat {
+        self.formats
+            .iter()
+            .copied()
+            .find(|format| supported.contains(format))
+            .or_else(|| supported.first().copied())
+            .unwrap_or(wgpu::TextureFormat::Bgra8UnormSrgb)
+    }
+
+    /// Decide how to meet the options with the capabilities: the alpha
+    /// mode and usage to configure the surface with, and whether frames
+    /// are rendered into an intermediate texture
+    pub fn negotiate(
+        &self,
+        capabilities: &SurfaceCapabilities,
+    ) -> Result<(wgpu::CompositeAlphaMode, wgpu::TextureUsages, bool), SurfaceConfigError> {
+        let alpha_mode = match self.alpha_mode {
+            None => capabilities
+                .alpha_modes
+                .first()
+                .copied()
+                .unwrap_or(wgpu::CompositeAlphaMode::Auto),
+            // wgpu picks a supported mode for Auto
+            Some(mode @ wgpu::CompositeAlphaMode::Auto) => mode,
+            Some(mode) if capabilities.alpha_modes.contains(&mode) => mode,
+            Some(mode) => {
+                return Err(SurfaceConfigError::UnsupportedAlphaMode {
+                    requested: mode,
+                    supported: capabilities.alpha_modes.clone(),
+                })
+            }
+        };
+
+        let usage = self.usage | wgpu::TextureUsages::RENDER_ATTACHMENT;
+        let render = wgpu::TextureUsages::RENDER_ATTACHMENT;
+        if capabilities.usages.contains(usage) {
+            Ok((alpha_mode, usage, false))
+        } else if capabilities.usages.contains(render) && INTERMEDIATE_USAGES.contains(usage) {
+            Ok((alpha_mode, render, true))
+        } else {
+            Err(SurfaceConfigError::UnsupportedUsage {
+                requested: self.usage,
+                supported: capabilities.usages,
+            })
+        }
+    }
+}
+
+/// SurfaceOptions the surface cannot meet, along with what it supports
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SurfaceConfigError {
+    UnsupportedAlphaMode {
+        requested: wgpu::CompositeAlphaMode,
+        supported: Vec<wgpu::CompositeAlphaMode>,
+    },
+    /// Neither the surface nor an intermediate texture has the usage
+    UnsupportedUsage {
+        requested: wgpu::TextureUsages,
+        supported: wgpu::TextureUsages,
+    },
+}
+
+impl fmt::Display for SurfaceConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SurfaceConfigError::UnsupportedAlphaMode {
+                requested,
+                supported,
+            } => write!(
+                f,
+                "alpha mode {:?} is not supported, the surface supports {:?}",
+                requested, supported
+            ),
+            SurfaceConfigError::UnsupportedUsage {
+                requested,
+                supported,
+            } => write!(
+                f,
+                "usage {:?} is not supported, the surface supports {:?}",
+                requested, supported
+            ),
+        }
+    }
+}
+
+impl error::Error for SurfaceConfigError {}
+
+/// Texture rendered to in place of the frames of a surface lacking a
+/// requested usage, and the blit copying it onto them
+struct Intermediate {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    blit: Blit,
+}
+
+impl Intermediate {
+    fn new(
+        gpu: &GpuContext,
+        config: &wgpu::SurfaceConfiguration,
+        usage: wgpu::TextureUsages,
+    ) -> Intermediate {
+        let texture = gpu.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("daswin intermediate frame"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: usage
+                | wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let blit = Blit::with_filter(
+            &gpu.device,
+            config.format,
+            config.format,
+            wgpu::FilterMode::Nearest,
+        );
+        Intermediate {
+            texture,
+            view,
+            blit,
+        }
+    }
+}
+
+/// Called with the new configuration whenever a surface is configured
+pub type ReconfigureCallback<C = GpuContext> =
+    Box<dyn FnMut(&C, &wgpu::SurfaceConfiguration) + Send>;
+
+/// A surface SurfaceConfigScheduler configures with the context `C`,
+/// wgpu::Surface with the device of a GpuContext
+pub trait ConfigureSurface<C = GpuContext> {
+    fn configure(&self, context: &C, config: &wgpu::SurfaceConfiguration);
+}
+
+impl ConfigureSurface for wgpu::Surface {
+    fn configure(&self, gpu: &GpuContext, config: &wgpu::SurfaceConfiguration) {
+        wgpu::Surface::configure(self, &gpu.device, config);
+    }
+}
+
+/// Applies the changes made to a surface configuration at once, so that
+/// e.g. a resize and a present mode change in one frame configure the
+/// surface a single time, which flickers on some drivers. Configurations
+/// equal to the applied one are skipped
+pub struct SurfaceConfigScheduler<C = GpuContext> {
+    // What the surface was last configured with, None configures it on
+    // the next apply regardless
+    applied: Option<wgpu::SurfaceConfiguration>,
+    callbacks: Vec<ReconfigureCallback<C>>,
+}
+
+impl<C> SurfaceConfigScheduler<C> {
+    pub fn new() -> SurfaceConfigScheduler<C> {
+        SurfaceConfigScheduler {
+            applied: None,
+            callbacks: Vec::new(),
+        }
+    }
+
+    /// Rebuild resources depending on the configuration, e.g. a depth
+    /// buffer of the surface size, in the step configuring the surface
+    pub fn on_reconfigure<F>(&mut self, f: F)
+    where
+        F: FnMut(&C, &wgpu::SurfaceConfiguration) + Send + 'static,
+    {
+        self.callbacks.push(Box::new(f));
+    }
+
+    /// Configure the surface on the next apply even if the configuration
+    /// is unchanged, e.g. after it was lost
+    pub fn invalidate(&mut self) {
+        self.applied = None;
+    }
+
+    /// Whether the next apply configures the surface
+    pub fn is_pending(&self, config: &wgpu::SurfaceConfiguration) -> bool {
+        self.applied.as_ref() != Some(config)
+    }
+
+    /// The configuration last applied, None after invalidate
+    pub fn applied(&self) -> Option<&wgpu::SurfaceConfiguration> {
+        self.applied.as_ref()
+    }
+
+    /// Configure the surface unless it already has the configuration,
+    /// then call the reconfigure callbacks. Returns whether it did
+    pub fn apply<S: ConfigureSurface<C> + ?Sized>(
+        &mut self,
+        context: &C,
+        surface: &S,
+        config: &wgpu::SurfaceConfiguration,
+    ) -> bool {
+        if !self.is_pending(config) {
+            return false;
+        }
+        surface.configure(context, config);
+        self.applied = Some(config.clone());
+        for callback in &mut self.callbacks {
+            callback(context, config);
+        }
+        true
+    }
+}
+
+impl<C> Default for SurfaceConfigScheduler<C> {
+    fn default() -> SurfaceConfigScheduler<C> {
+        SurfaceConfigScheduler::new()
+    }
+}
+
+/// A window surface and the configuration applied to it.
+///
+/// Changes to the configuration, through the methods or the config field
+/// itself, are applied together before the next frame is acquired.
+///
+/// Frames have current_size, the size the surface was last configured
+/// with, which is what render targets and viewports must match. The
+/// window may already be larger or smaller, see
+/// X11Window::inner_size_physical, until the Resized event reached
+/// resize and the next acquire applied it
+pub struct SurfaceState {
+    pub surface: wgpu::Surface,
+    pub config: wgpu::SurfaceConfiguration,
+
+    scheduler: SurfaceConfigScheduler,
+    options: SurfaceOptions,
+    // Whether the options need an intermediate texture, which is created
+    // when the configuration is applied
+    wants_intermediate: bool,
+    intermediate: Option<Intermediate>,
+
+    // Set when a frame was acquired from a suboptimal swapchain
+    suboptimal: bool,
+    // Set on Event::ConnectionLost, the window is gone with the server
+    connection_lost: bool,
+}
+
+impl SurfaceState {
+    /// Configure the surface with the first supported format and alpha mode
+    pub fn new(gpu: &GpuContext, surface: wgpu::Surface, size: PhysicalSize<u32>) -> SurfaceState {
+        SurfaceState::with_options(gpu, surface, size, SurfaceOptions::default())
+            .expect("Surface does not support rendering")
+    }
+
+    /// Configure the surface with the requested format, usage and alpha
+    /// mode
+    pub fn with_options(
+        gpu: &GpuContext,
+        surface: wgpu::Surface,
+        size: PhysicalSize<u32>,
+        options: SurfaceOptions,
+    ) -> Result<SurfaceState, SurfaceConfigError> {
+        let capabilities = SurfaceCapabilities::query(gpu, &surface);
+        let (alpha_mode, usage, intermediate) = options.negotiate(&capabilities)?;
+        let config = wgpu::SurfaceConfiguration {
+            usage,
+            format: options.choose_format(&capabilities.formats),
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: wgpu::PresentMode::AutoVsync,
+            alpha_mode,
+        };
+        let mut state = SurfaceState {
+            surface,
+            config,
+            scheduler: SurfaceConfigScheduler::new(),
+            options,
+            wants_intermediate: intermediate,
+            intermediate: None,
+            suboptimal: false,
+            connection_lost: false,
+        };
+        state.flush(gpu);
+        Ok(state)
+    }
+
+    /// What the surface supports
+    pub fn capabilities(&self, gpu: &GpuContext) -> SurfaceCapabilities {
+        SurfaceCapabilities::query(gpu, &self.surface)
+    }
+
+    /// The formats, usage and alpha mode requested for the surface
+    pub fn options(&self) -> SurfaceOptions {
+        self.options
+    }
+
+    /// Request other formats, usage or alpha mode, keeping the current
+    /// configuration when the surface cannot meet them
+    pub fn set_options(
+        &mut self,
+        gpu: &GpuContext,
+        options: SurfaceOptions,
+    ) -> Result<(), SurfaceConfigError> {
+        let capabilities = self.capabilities(gpu);
+        let (alpha_mode, usage, intermediate) = options.negotiate(&capabilities)?;
+        self.options = options;
+        self.config.format = options.choose_format(&capabilities.formats);
+        self.config.alpha_mode = alpha_mode;
+        self.config.usage = usage;
+        self.wants_intermediate = intermediate;
+        // Rebuilt for the new usage by the next flush
+        self.intermediate = None;
+        Ok(())
+    }
+
+    /// Rebuild resources depending on the configuration in the step
+    /// configuring the surface, see SurfaceConfigScheduler::on_reconfigure
+    pub fn on_reconfigure<F>(&mut self, f: F)
+    where
+        F: FnMut(&GpuContext, &wgpu::SurfaceConfiguration) + Send + 'static,
+    {
+        self.scheduler.on_reconfigure(f);
+    }
+
+    /// Apply the changes to the configuration now instead of before the
+    /// next acquire. Returns whether the surface was configured
+    pub fn flush(&mut self, gpu: &GpuContext) -> bool {
+        let configured = self.scheduler.apply(gpu, &self.surface, &self.config);
+        if configured || self.wants_intermediate != self.intermediate.is_some() {
+            self.intermediate = self
+                .wants_intermediate
+                .then(|| Intermediate::new(gpu, &self.config, self.options.usage));
+        }
+        configured
+    }
+
+    /// Whether frames are rendered into an intermediate texture, see
+    /// SurfaceOptions::usage
+    pub fn uses_intermediate(&self) -> bool {
+        self.wants_intermediate
+    }
+
+    /// The texture to render an acquired frame into, with the usages of
+    /// the options: the frame's own or the intermediate texture
+    pub fn texture<'a>(&'a self, frame: &'a wgpu::SurfaceTexture) -> &'a wgpu::Texture {
+        match &self.intermediate {
+            Some(intermediate) => &intermediate.texture,
+            None => &frame.texture,
+        }
+    }
+
+    /// Copy the intermediate texture onto the frame, after submitting the
+    /// frame's work and before presenting it. Does nothing when frames
+    /// are rendered directly
+    pub fn resolve(&self, gpu: &GpuContext, frame: &wgpu::SurfaceTexture) {
+        let intermediate = match &self.intermediate {
+            Some(intermediate) => intermediate,
+            None => return,
+        };
+        let target = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("daswin resolve"),
+            });
+        intermediate
+            .blit
+            .blit(&gpu.device, &mut encoder, &intermediate.view, &target);
+        gpu.queue.submit(Some(encoder.finish()));
+    }
+
+    /// The format of the surface textures, which may change when the
+    /// surface is reconfigured
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
+    /// How frames are presented
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.config.present_mode
+    }
+
+    /// Choose how frames are presented, e.g. AutoNoVsync so a variable
+    /// refresh rate display follows the frame rate. Modes the surface
+    /// does not support fall back to AutoVsync. Applied before the next
+    /// acquire
+    pub fn set_present_mode(&mut self, gpu: &GpuContext, mode: wgpu::PresentMode) {
+        let supported = matches!(
+            mode,
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
+        ) || self
+            .surface
+            .get_supported_present_modes(&gpu.adapter)
+            .contains(&mode);
+        self.config.present_mode = if supported {
+            mode
+        } else {
+            log::warn!("present mode {:?} is not supported", mode);
+            wgpu::PresentMode::AutoVsync
+        };
+    }
+
+    /// The size of the configuration last applied, which the frames of
+    /// acquire have. Unlike the Resized events and config, it does not
+    /// run ahead of the frames while a resize is pending, so depth
+    /// buffers and other attachments sized from it always match
+    pub fn current_size(&self) -> PhysicalSize<u32> {
+        let config = self.scheduler.applied().unwrap_or(&self.config);
+        PhysicalSize::new(config.width, config.height)
+    }
+
+    /// Resize the surface before the next acquire, zero sizes are
+    /// clamped as wgpu rejects them
+    pub fn resize(&mut self, _gpu: &GpuContext, size: PhysicalSize<u32>) {
+        self.config.width = size.width.max(1);
+        self.config.height = size.height.max(1);
+    }
+
+    /// Acquire the next frame, returns None when the frame should be skipped.
+    ///
+    /// Pending configuration changes are applied first. A frame acquired
+    /// from a suboptimal swapchain is still rendered and presented, the
+    /// surface is reconfigured before the following acquire. Running out
+    /// of memory skips the frame and is reported as Event::GpuError. Once
+    /// the connection of the window was lost every frame is skipped
+    pub fn acquire(&mut self, gpu: &GpuContext) -> Option<wgpu::SurfaceTexture> {
+        if self.connection_lost {
+            return None;
+        }
+        if prepare(self.suboptimal, self.scheduler.is_pending(&self.config)) == Prepare::Reconfigure
+        {
+            self.reconfigure(gpu);
+        }
+        self.flush(gpu);
+
+        let frame = match self.surface.get_current_texture() {
+            Ok(frame) => frame,
+            Err(error) => match recover(&error) {
+                Recovery::Skip => return None,
+                Recovery::Reconfigure => {
+                    self.reconfigure(gpu);
+                    self.flush(gpu);
+                    self.surface.get_current_texture().ok()?
+                }
+                Recovery::Report => {
+                    gpu.report_error("out of memory acquiring a frame");
+                    return None;
+                }
+            },
+        };
+
+        self.suboptimal = frame.suboptimal;
+        if frame.suboptimal {
+            log::trace!("surface is suboptimal, reconfiguring after this frame");
+        }
+        Some(frame)
+    }
+
+    /// Reconfigure the surface before the next acquire, even when the
+    /// configuration is unchanged. The supported formats and alpha modes
+    /// are queried again as they can change along with the output
+    pub fn reconfigure(&mut self, gpu: &GpuContext) {
+        let formats = self.surface.get_supported_formats(&gpu.adapter);
+        if !formats.contains(&self.config.format) {
+            log::trace!(
+                "surface format {:?} no longer supported",
+                self.config.format
+            );
+            self.config.format = self.options.choose_format(&formats);
+        }
+        let alpha_modes = self.surface.get_supported_alpha_modes(&gpu.adapter);
+        if self.config.alpha_mode != wgpu::CompositeAlphaMode::Auto
+            && !alpha_modes.contains(&self.config.alpha_mode)
+        {
+            log::warn!(
+                "alpha mode {:?} no longer supported",
+                self.config.alpha_mode
+            );
+            // Surfaces listing none still take Auto
+            self.config.alpha_mode = alpha_modes
+                .first()
+                .copied()
+                .unwrap_or(wgpu::CompositeAlphaMode::Auto);
+        }
+
+        self.scheduler.invalidate();
+        self.suboptimal = false;
+    }
+
+    /// Take the most preferred format of the options again, as the
+    /// supported formats change between SDR and HDR outputs. Returns
+    /// whether the format changed, pipelines built for the old one must
+    /// be rebuilt before the next frame. Applied before the next acquire
+    pub fn renegotiate_format(&mut self, gpu: &GpuContext) -> bool {
+        let formats = self.surface.get_supported_formats(&gpu.adapter);
+        let format = self.options.choose_format(&formats);
+        if format == self.config.format {
+            return false;
+        }
+        log::info!(
+            "surface format changes from {:?} to {:?}",
+            self.config.format,
+            format
+        );
+        self.config.format = format;
+        true
+    }
+}
+
+/// How SurfaceState::acquire readies the surface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prepare {
+    /// Acquire right away
+    Nothing,
+    /// Configure the surface with the changes made since the last frame
+    Apply,
+    /// Query the formats and alpha modes again and configure the surface,
+    /// also when the configuration is unchanged
+    Reconfigure,
+}
+
+/// How to ready the surface for a frame when the last one came from a
+/// `suboptimal` swapchain and configuration changes are `pending`. A
+/// suboptimal swapchain is replaced even at the same size, so the
+/// scheduler must not skip it as unchanged
+fn prepare(suboptimal: bool, pending: bool) -> Prepare {
+    if suboptimal {
+        Prepare::Reconfigure
+    } else if pending {
+        Prepare::Apply
+    } else {
+        Prepare::Nothing
+    }
+}
+
+/// What SurfaceState::acquire does when getting a frame failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Recovery {
+    /// Skip the frame, the next acquire tries again
+    Skip,
+    /// Reconfigure the surface and try once more
+    Reconfigure,
+    /// Skip the frame and report the error as Event::GpuError
+    Report,
+}
+
+fn recover(error: &wgpu::SurfaceError) -> Recovery {
+    match error {
+        wgpu::SurfaceError::Timeout => Recovery::Skip,
+        wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost => Recovery::Reconfigure,
+        wgpu::SurfaceError::OutOfMemory => Recovery::Report,
+    }
+}
+
+/// The wgpu path as a RenderBackend, for driving SurfaceState with
+/// RenderSurface, see WindowSurface::into_render_surface. Frames
+/// rendered to an intermediate texture are resolved when presented
+pub struct WgpuBackend<'a> {
+    pub gpu: &'a GpuContext,
+    pub surface: SurfaceState,
+}
+
+impl RenderBackend for WgpuBackend<'_> {
+    type Frame = wgpu::SurfaceTexture;
+
+    fn configure(&mut self, size: PhysicalSize<u32>, params: PresentParams) {
+        let mode = if params.vsync {
+            wgpu::PresentMode::AutoVsync
+        } else {
+            wgpu::PresentMode::AutoNoVsync
+        };
+        self.surface.resize(self.gpu, size);
+        self.surface.set_present_mode(self.gpu, mode);
+    }
+
+    /// SurfaceState::acquire, which reconfigures outdated surfaces itself
+    fn acquire(&mut self) -> Result<wgpu::SurfaceTexture, AcquireError> {
+        self.surface.acquire(self.gpu).ok_or(AcquireError::Timeout)
+    }
+
+    fn present(&mut self, frame: wgpu::SurfaceTexture) {
+        self.surface.resolve(self.gpu, &frame);
+        frame.present();
+    }
+
+    fn destroy(&mut self) {
+        self.gpu.device.poll(wgpu::Maintain::Wait);
+    }
+}
+
+/// A window together with the surface presenting to it.
+///
+/// The surface refers to the X window by id, so it must be destroyed
+/// first; presenting to a destroyed window fails with BadWindow or
+/// crashes the driver. Owning both here makes it impossible to drop the
+/// window while the surface is alive, and GpuContext::with_window is
+/// the only way to a surface short of the unsafe
+/// wgpu::Instance::create_surface:
+///
+/// ```compile_fail,E0382
+/// use daswin::prelude::*;
+///
+/// let event_loop = EventLoop::new();
+/// let window = X11Window::new(&event_loop, "surface", (640, 480));
+/// let (gpu, mut surface) = GpuContext::with_window(window);
+/// drop(window);
+/// surface.acquire(&gpu);
+/// ```
+pub struct WindowSurface {
+    // Fields drop in declaration order, the surface before the window
+    surface: SurfaceState,
+    window: X11Window,
+}
+
+impl WindowSurface {
+    /// Create and configure a surface for the window at its current size
+    pub fn new(gpu: &GpuContext, window: X11Window) -> WindowSurface {
+        let surface = unsafe { gpu.instance.create_surface(&window) };
+        let surface = SurfaceState::new(gpu, surface, window.inner_size());
+        WindowSurface { surface, window }
+    }
+
+    /// Request other formats, usage or alpha mode for the surface, see
+    /// SurfaceState::set_options
+    pub fn set_options(
+        &mut self,
+        gpu: &GpuContext,
+        options: SurfaceOptions,
+    ) -> Result<(), SurfaceConfigError> {
+        self.surface.set_options(gpu, options)
+    }
+
+    pub fn window(&self) -> &X11Window {
+        &self.window
+    }
+
+    pub fn window_mut(&mut self) -> &mut X11Window {
+        &mut self.window
+    }
+
+    pub fn surface(&self) -> &SurfaceState {
+        &self.surface
+    }
+
+    pub fn surface_mut(&mut self) -> &mut SurfaceState {
+        &mut self.surface
+    }
+
+    /// Acquire the next frame, see SurfaceState::acquire.
+    ///
+    /// Heals a surface which fell behind the window: when its size
+    /// differs from X11Window::inner_size_physical while no resize is
+    /// held back by the throttle, e.g. because a Resized event was not
+    /// passed to handle_event, a warning is logged and the surface is
+    /// resized before acquiring
+    pub fn acquire(&mut self, gpu: &GpuContext) -> Option<wgpu::SurfaceTexture> {
+        let live = self.window.inner_size_physical();
+        let size = PhysicalSize::new(self.surface.config.width, self.surface.config.height);
+        if live != size && live.width > 0 && live.height > 0 && !self.window.resize_held() {
+            log::warn!(
+                "surface at {}x{} fell behind the window at {}x{}, resizing it",
+                size.width,
+                size.height,
+                live.width,
+                live.height
+            );
+            self.surface.resize(gpu, live);
+        }
+        self.surface.acquire(gpu)
+    }
+
+    /// Present a frame acquired from the surface, see
+    /// X11Window::on_first_present. Frames rendered to an intermediate
+    /// texture must be resolved first
+    pub fn present(&mut self, frame: wgpu::SurfaceTexture) {
+        frame.present();
+        self.window.notify_presented();
+    }
+
+    /// Move to a window recreated by EventLoop::reconnect, creating a
+    /// surface for it with the options of the current one. Returns the
+    /// window of the lost connection, to be dropped by the caller.
+    ///
+    /// The device must not depend on the old connection, which holds
+    /// for Vulkan but not for the GL backend
+    pub fn recreate(&mut self, gpu: &GpuContext, window: X11Window) -> X11Window {
+        let surface = unsafe { gpu.instance.create_surface(&window) };
+        let options = self.surface.options();
+        self.surface = SurfaceState::with_options(gpu, surface, window.inner_size(), options)
+            .expect("Surface does not support rendering");
+        mem::replace(&mut self.window, window)
+    }
+
+    /// Keep the surface in step with the window: resize it along with the
+    /// window, renegotiate its format when the window moves to another
+    /// monitor, wait for the device before a suspend and reconfigure it
+    /// after resume, as some drivers lose surfaces while asleep. After
+    /// the connection was lost frames are skipped until recreate
+    pub fn handle_event(&mut self, gpu: &GpuContext, event: &Event) {
+        match *event {
+            Event::Resized { window, size } if window == self.window.id() => {
+                self.surface.resize(gpu, size)
+            }
+            Event::MonitorChanged { window, .. } if window == self.window.id() => {
+                self.surface.renegotiate_format(gpu);
+            }
+            Event::Suspending => {
+                gpu.device.poll(wgpu::Maintain::Wait);
+            }
+            Event::Resumed => self.surface.reconfigure(gpu),
+            Event::ConnectionLost => self.surface.connection_lost = true,
+            _ => {}
+        }
+    }
+
+    /// Drive the surface and window with RenderSurface through
+    /// WgpuBackend
+    pub fn into_render_surface(self, gpu: &GpuContext) -> RenderSurface<WgpuBackend<'_>> {
+        let WindowSurface { surface, window } = self;
+        RenderSurface::new(window, WgpuBackend { gpu, surface })
+    }
+
+    /// Destroy the surface once the device finished the work submitted
+    /// for it, then the window.
+    ///
+    /// Dropping does the same without waiting on the device, which is
+    /// fine unless frames may still be in flight
+    pub fn destroy(self, gpu: &GpuContext) {
+        gpu.device.poll(wgpu::Maintain::Wait);
+        drop(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    // Counts the configure calls in place of a surface
+    #[derive(Default)]
+    struct Counted(Cell<u32>);
+
+    impl ConfigureSurface<()> for Counted {
+        fn configure(&self, _: &(), _: &wgpu::SurfaceConfiguration) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    fn config() -> wgpu::SurfaceConfiguration {
+        wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            width: 640,
+            height: 480,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+        }
+    }
+
+    #[test]
+    fn changes_in_one_frame_configure_once() {
+        let mut scheduler = SurfaceConfigScheduler::new();
+        let surface = Counted::default();
+        let reconfigured = Arc::new(AtomicU32::new(0));
+        let counter = reconfigured.clone();
+        scheduler.on_reconfigure(move |_, _| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+        let mut config = config();
+        assert!(scheduler.apply(&(), &surface, &config));
+        surface.0.set(0);
+        reconfigured.store(0, Ordering::Relaxed);
+
+        config.width = 1280;
+        config.height = 720;
+        config.present_mode = wgpu::PresentMode::Mailbox;
+        config.format = wgpu::TextureFormat::Rgba16Float;
+        assert!(scheduler.is_pending(&config));
+        assert!(scheduler.apply(&(), &surface, &config));
+        assert_eq!(surface.0.get(), 1);
+        assert_eq!(reconfigured.load(Ordering::Relaxed), 1);
+        assert_eq!(scheduler.applied(), Some(&config));
+    }
+
+    #[test]
+    fn unchanged_frame_configures_nothing() {
+        let mut scheduler = SurfaceConfigScheduler::new();
+        let surface = Counted::default();
+        let config = config();
+        scheduler.apply(&(), &surface, &config);
+        surface.0.set(0);
+        assert!(!scheduler.is_pending(&config));
+        assert!(!scheduler.apply(&(), &surface, &config));
+        assert_eq!(surface.0.get(), 0);
+    }
+
+    #[test]
+    fn invalidate_configures_again() {
+        let mut scheduler = SurfaceConfigScheduler::new();
+        let surface = Counted::default();
+        let config = config();
+        scheduler.apply(&(), &surface, &config);
+        scheduler.invalidate();
+        assert_eq!(scheduler.applied(), None);
+        assert!(scheduler.apply(&(), &surface, &config));
+        assert_eq!(surface.0.get(), 2);
+    }
+
+    #[test]
+    fn suboptimal_at_the_same_size_reconfigures() {
+        // The configuration is unchanged, so only prepare forces it
+        assert_eq!(prepare(true, false), Prepare::Reconfigure);
+        assert_eq!(prepare(true, true), Prepare::Reconfigure);
+    }
+
+    #[test]
+    fn resize_in_flight_is_applied() {
+        let mut scheduler = SurfaceConfigScheduler::new();
+        let surface = Counted::default();
+        let mut config = config();
+        scheduler.apply(&(), &surface, &config);
+        assert_eq!(
+            prepare(false, scheduler.is_pending(&config)),
+            Prepare::Nothing
+        );
+        config.width = 1024;
+        assert_eq!(
+            prepare(false, scheduler.is_pending(&config)),
+            Prepare::Apply
+        );
+    }
+
+    #[test]
+    fn outdated_surface_reconfigures() {
+        assert_eq!(
+            recover(&wgpu::SurfaceError::Outdated),
+            Recovery::Reconfigure
+        );
+        assert_eq!(recover(&wgpu::SurfaceError::Lost), Recovery::Reconfigure);
+        assert_eq!(recover(&wgpu::SurfaceError::Timeout), Recovery::Skip);
+        assert_eq!(recover(&wgpu::SurfaceError::OutOfMemory), Recovery::Report);
+    }
+
+    #[test]
+    fn no_alpha_modes_fall_back_to_auto() {
+        let capabilities = SurfaceCapabilities {
+            formats: vec![wgpu::TextureFormat::Bgra8UnormSrgb],
+            alpha_modes: Vec::new(),
+            usages: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        };
+        let (alpha_mode, _, intermediate) =
+            SurfaceOptions::default().negotiate(&capabilities).unwrap();
+        assert_eq!(alpha_mode, wgpu::CompositeAlphaMode::Auto);
+        assert!(!intermediate);
+    }
+
+    fn capabilities(backend: wgpu::Backend) -> SurfaceCapabilities {
+        SurfaceCapabilities {
+            formats: vec![
+                wgpu::TextureFormat::Bgra8UnormSrgb,
+                wgpu::TextureFormat::Rgba16Float,
+            ],
+            alpha_modes: vec![
+                wgpu::CompositeAlphaMode::Opaque,
+                wgpu::CompositeAlphaMode::PreMultiplied,
+            ],
+            usages: SurfaceCapabilities::usages(backend),
+        }
+    }
+
+    fn requesting(usage: wgpu::TextureUsages) -> SurfaceOptions {
+        SurfaceOptions {
+            usage,
+            ..SurfaceOptions::default()
+        }
+    }
+
+    #[test]
+    fn copies_need_vulkan_or_an_intermediate() {
+        let copy = wgpu::TextureUsages::COPY_SRC;
+        let render = wgpu::TextureUsages::RENDER_ATTACHMENT;
+        let vulkan = capabilities(wgpu::Backend::Vulkan);
+        let gl = capabilities(wgpu::Backend::Gl);
+        assert!(vulkan.usages.contains(copy));
+        assert_eq!(gl.usages, render);
+
+        let (_, usage, intermediate) = requesting(copy).negotiate(&vulkan).unwrap();
+        assert_eq!((usage, intermediate), (render | copy, false));
+        let (_, usage, intermediate) = requesting(copy).negotiate(&gl).unwrap();
+        assert_eq!((usage, intermediate), (render, true));
+        let sampled = wgpu::TextureUsages::TEXTURE_BINDING;
+        let (_, usage, intermediate) = requesting(sampled).negotiate(&vulkan).unwrap();
+        assert_eq!((usage, intermediate), (render, true));
+        let (_, usage, intermediate) = requesting(render).negotiate(&gl).unwrap();
+        assert_eq!((usage, intermediate), (render, false));
+    }
+
+    #[test]
+    fn usages_nothing_provides_are_errors() {
+        let storage = wgpu::TextureUsages::STORAGE_BINDING;
+        let err = requesting(storage)
+            .negotiate(&capabilities(wgpu::Backend::Gl))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            SurfaceConfigError::UnsupportedUsage {
+                requested: storage,
+                supported: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            }
+        );
+        // No intermediate without rendering to the surface
+        let capabilities = SurfaceCapabilities {
+            usages: wgpu::TextureUsages::COPY_DST,
+            ..capabilities(wgpu::Backend::Vulkan)
+        };
+        assert!(SurfaceOptions::default().negotiate(&capabilities).is_err());
+    }
+
+    #[test]
+    fn alpha_modes() {
+        let options = |alpha_mode| SurfaceOptions {
+            alpha_mode,
+            ..SurfaceOptions::default()
+        };
+        let capabilities = capabilities(wgpu::Backend::Vulkan);
+        let alpha_mode = |mode| {
+            options(mode)
+                .negotiate(&capabilities)
+                .map(|(mode, ..)| mode)
+        };
+        assert_eq!(alpha_mode(None), Ok(wgpu::CompositeAlphaMode::Opaque));
+        assert_eq!(
+            alpha_mode(Some(wgpu::CompositeAlphaMode::PreMultiplied)),
+            Ok(wgpu::CompositeAlphaMode::PreMultiplied)
+        );
+        assert_eq!(
+            alpha_mode(Some(wgpu::CompositeAlphaMode::Auto)),
+            Ok(wgpu::CompositeAlphaMode::Auto)
+        );
+        assert_eq!(
+            alpha_mode(Some(wgpu::CompositeAlphaMode::PostMultiplied)),
+            Err(SurfaceConfigError::UnsupportedAlphaMode {
+                requested: wgpu::CompositeAlphaMode::PostMultiplied,
+                supported: capabilities.alpha_modes.clone(),
+            })
+        );
+        let err = alpha_mode(Some(wgpu::CompositeAlphaMode::PostMultiplied)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "alpha mode PostMultiplied is not supported, the surface supports \
+             [Opaque, PreMultiplied]"
+        );
+    }
+
+    #[test]
+    fn formats_by_preference() {
+        let supported = capabilities(wgpu::Backend::Vulkan).formats;
+        let options = |formats| SurfaceOptions {
+            formats,
+            ..SurfaceOptions::default()
+        };
+        let hdr = &[
+            wgpu::TextureFormat::Rgba16Float,
+            wgpu::TextureFormat::Bgra8UnormSrgb,
+        ];
+        assert_eq!(
+            options(hdr).choose_format(&supported),
+            wgpu::TextureFormat::Rgba16Float
+        );
+        let unsupported = &[wgpu::TextureFormat::Rgb10a2Unorm];
+        assert_eq!(options(unsupported).choose_format(&supported), supported[0]);
+        assert_eq!(options(&[]).choose_format(&supported), supported[0]);
+        assert_eq!(
+            options(hdr).choose_format(&[]),
+            wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+    }
+}
@@ -0,0 +1,1217 @@
+//! Canned pipelines for drawing a fullscreen triangle, either with a user
+//! fragment shader or to copy a texture onto another target, a stage
+//! post-processing frames with compute shaders and a target redrawing
+//! only damaged regions
+
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::num::NonZeroU32;
+
+use pollster::block_on;
+
+use super::cursor::CursorImage;
+use crate::dpi::{PhysicalPosition, PhysicalSize};
+use crate::window::{CursorIcon, Rect, SoftwareCursor};
+
+/// Vertex stage shared by the helpers, covering the target with a single
+/// triangle and passing uv coordinates with the origin at the top left
+const FULLSCREEN_VERTEX: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let x = f32(i32(index & 1u) * 4 - 1);
+    let y = f32(i32(index >> 1u) * 4 - 1);
+    var out: VertexOutput;
+    out.position = vec4<f32>(x, y, 0.0, 1.0);
+    out.uv = vec2<f32>(x * 0.5 + 0.5, 0.5 - y * 0.5);
+    return out;
+}
+"#;
+
+const GLOBALS: &str = r#"
+struct Globals {
+    resolution: vec2<f32>,
+    time: f32,
+    cursor: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+"#;
+
+const BLIT_FRAGMENT: &str = r#"
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(source, source_sampler, in.uv);
+}
+"#;
+
+// Writes the blend constant, set to the clear color
+const FILL_FRAGMENT: &str = r#"
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0);
+}
+"#;
+
+const CURSOR_SHADER: &str = r#"
+struct Cursor {
+    origin: vec2<f32>,
+    size: vec2<f32>,
+    resolution: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> cursor: Cursor;
+@group(0) @binding(1)
+var image: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) texel: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let corner = vec2<f32>(f32(index & 1u), f32(index >> 1u)) * cursor.size;
+    let pixel = cursor.origin + corner;
+    var out: VertexOutput;
+    out.position = vec4<f32>(
+        pixel.x / cursor.resolution.x * 2.0 - 1.0,
+        1.0 - pixel.y / cursor.resolution.y * 2.0,
+        0.0,
+        1.0,
+    );
+    out.texel = corner;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureLoad(image, vec2<i32>(floor(in.texel)), 0);
+    if color.a == 0.0 {
+        discard;
+    }
+    return color;
+}
+"#;
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+/// Run `f` within validation and out of memory error scopes, returning
+/// the first error raised by the wgpu calls it made instead of reporting
+/// it as an Event::GpuError
+pub fn error_scope<T>(device: &wgpu::Device, f: impl FnOnce() -> T) -> Result<T, wgpu::Error> {
+    device.push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = f();
+    let validation = block_on(device.pop_error_scope());
+    let out_of_memory = block_on(device.pop_error_scope());
+    match validation.or(out_of_memory) {
+        Some(error) => Err(error),
+        None => Ok(value),
+    }
+}
+
+/// Draws a fullscreen triangle shaded by a user fragment shader.
+///
+/// The fragment source must define `fs_main(in: VertexOutput)`, where
+/// `in.uv` runs from (0, 0) at the top left to (1, 1) at the bottom right.
+/// A `globals` uniform holding `resolution`, `time` and `cursor` is in
+/// scope, its values are updated with set_globals and set_cursor
+pub struct FullscreenShader {
+    shader: wgpu::ShaderModule,
+    layout: wgpu::PipelineLayout,
+    globals: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+}
+
+impl FullscreenShader {
+    /// Compile the fragment shader into a pipeline rendering to `format`
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        fragment_source: &str,
+    ) -> FullscreenShader {
+        FullscreenShader::with_bind_group_layouts(device, format, fragment_source, &[])
+    }
+
+    /// Like new, but a shader failing to compile or validate is returned
+    /// as an error. When reloading a shader, keep drawing with the old
+    /// one on failure
+    pub fn try_new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        fragment_source: &str,
+    ) -> Result<FullscreenShader, wgpu::Error> {
+        FullscreenShader::try_with_bind_group_layouts(device, format, fragment_source, &[])
+    }
+
+    /// Like with_bind_group_layouts, returning errors as try_new does
+    pub fn try_with_bind_group_layouts(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        fragment_source: &str,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+    ) -> Result<FullscreenShader, wgpu::Error> {
+        error_scope(device, || {
+            FullscreenShader::with_bind_group_layouts(
+                device,
+                format,
+                fragment_source,
+                bind_group_layouts,
+            )
+        })
+    }
+
+    /// Like new, with further bind groups for the fragment shader at
+    /// groups 1 and up, which the caller binds before drawing
+    pub fn with_bind_group_layouts(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        fragment_source: &str,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+    ) -> FullscreenShader {
+        let source = format!("{}{}{}", FULLSCREEN_VERTEX, GLOBALS, fragment_source);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("daswin fullscreen shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+        });
+
+        let globals = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("daswin fullscreen globals"),
+            size: 32,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: globals.as_entire_binding(),
+            }],
+        });
+        let mut layouts = vec![&bind_group_layout];
+        layouts.extend_from_slice(bind_group_layouts);
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &layouts,
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = create_pipeline(device, "daswin fullscreen", &layout, &shader, format);
+        FullscreenShader {
+            shader,
+            layout,
+            globals,
+            bind_group,
+            pipeline,
+            format,
+        }
+    }
+
+    /// The format of the target the pipeline renders to
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    /// Recreate the pipeline for a new target format, does nothing when
+    /// the format is unchanged
+    pub fn rebuild(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        if format != self.format {
+            self.pipeline = create_pipeline(
+                device,
+                "daswin fullscreen",
+                &self.layout,
+                &self.shader,
+                format,
+            );
+            self.format = format;
+        }
+    }
+
+    /// Update the values of the `globals` uniform
+    pub fn set_globals(&self, queue: &wgpu::Queue, resolution: PhysicalSize<u32>, time: f32) {
+        let values = [resolution.width as f32, resolution.height as f32, time, 0.0];
+        let mut bytes = [0; 16];
+        for (chunk, value) in bytes.chunks_exact_mut(4).zip(values) {
+            chunk.copy_from_slice(&value.to_ne_bytes());
+        }
+        queue.write_buffer(&self.globals, 0, &bytes);
+    }
+
+    /// Update `globals.cursor`, in pixels from the top left of the target
+    pub fn set_cursor(&self, queue: &wgpu::Queue, cursor: PhysicalPosition<f64>) {
+        let mut bytes = [0; 8];
+        bytes[..4].copy_from_slice(&(cursor.x as f32).to_ne_bytes());
+        bytes[4..].copy_from_slice(&(cursor.y as f32).to_ne_bytes());
+        queue.write_buffer(&self.globals, 16, &bytes);
+    }
+
+    /// Record the fullscreen draw into a render pass
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+/// Copies a texture onto a target of another size or format with a
+/// sampled fullscreen draw, used to present offscreen render targets
+pub struct Blit {
+    shader: wgpu::ShaderModule,
+    bind_group_layout: wgpu::BindGroupLayout,
+    layout: wgpu::PipelineLayout,
+    sampler: wgpu::Sampler,
+    pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+}
+
+impl Blit {
+    /// Create a blit from textures of `src_format` to targets of
+    /// `dst_format`, filtering linearly when the source format allows it
+    pub fn new(
+        device: &wgpu::Device,
+        src_format: wgpu::TextureFormat,
+        dst_format: wgpu::TextureFormat,
+    ) -> Blit {
+        Blit::with_filter(device, src_format, dst_format, wgpu::FilterMode::Linear)
+    }
+
+    /// Like new with a choice of filter, nearest keeps pixel art crisp.
+    /// Sources which cannot be filtered are always sampled nearest
+    pub fn with_filter(
+        device: &wgpu::Device,
+        src_format: wgpu::TextureFormat,
+        dst_format: wgpu::TextureFormat,
+        filter: wgpu::FilterMode,
+    ) -> Blit {
+        let source = format!("{}{}", FULLSCREEN_VERTEX, BLIT_FRAGMENT);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("daswin blit shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+        });
+
+        let filterable = matches!(
+            src_format.describe().sample_type,
+            wgpu::TextureSampleType::Float { filterable: true }
+        );
+        let filter = if filterable {
+            filter
+        } else {
+            wgpu::FilterMode::Nearest
+        };
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("daswin blit sampler"),
+            mag_filter: filter,
+            min_filter: filter,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(if filterable {
+                        wgpu::SamplerBindingType::Filtering
+                    } else {
+                        wgpu::SamplerBindingType::NonFiltering
+                    }),
+                    count: None,
+                },
+            ],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = create_pipeline(device, "daswin blit", &layout, &shader, dst_format);
+        Blit {
+            shader,
+            bind_group_layout,
+            layout,
+            sampler,
+            pipeline,
+            format: dst_format,
+        }
+    }
+
+    /// The format of the target the blit renders to
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    /// Recreate the pipeline for a new target format, does nothing when
+    /// the format is unchanged
+    pub fn rebuild(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        if format != self.format {
+            self.pipeline =
+                create_pipeline(device, "daswin blit", &self.layout, &self.shader, format);
+            self.format = format;
+        }
+    }
+
+    /// Bind a source texture, the bind group can be reused until the
+    /// texture is recreated
+    pub fn bind_group(&self, device: &wgpu::Device, source: &wgpu::TextureView) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("daswin blit source"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+
+    /// Record the copy of a bound source into a render pass
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, source: &'a wgpu::BindGroup) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, source, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+
+    /// Copy `source` over the whole of `target` in a render pass of its own
+    pub fn blit(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::TextureView,
+        target: &wgpu::TextureView,
+    ) {
+        let bind_group = self.bind_group(device, source);
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("daswin blit"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        self.draw(&mut rpass, &bind_group);
+    }
+}
+
+/// The workgroups covering `size` with workgroups of `workgroup_size`,
+/// rounded up. Invocations past the edges of sizes which are no multiple
+/// of it must return early, e.g. when the id is outside
+/// textureDimensions
+pub fn workgroups(size: PhysicalSize<u32>, workgroup_size: (u32, u32)) -> (u32, u32) {
+    (
+        size.width.div_ceil(workgroup_size.0.max(1)),
+        size.height.div_ceil(workgroup_size.1.max(1)),
+    )
+}
+
+/// A compute stage between rendering and presenting: the scene is
+/// rendered into a texture of the surface size, compute passes read it
+/// and write a storage texture, which ComputeStage::resolve blits onto
+/// the frame. Surface textures themselves can rarely be storage bound.
+///
+/// Compute pipelines take the stage's bind group layout as group 0, with
+/// the scene at binding 0 and the output at binding 1, see
+/// ComputeStage::declarations. Group 1 is left to the application
+pub struct ComputeStage {
+    format: wgpu::TextureFormat,
+    size: PhysicalSize<u32>,
+    bind_group_layout: wgpu::BindGroupLayout,
+    blit: Blit,
+    textures: StageTextures,
+    // Whether a pass wrote the output since the last resolve
+    dispatched: Cell<bool>,
+}
+
+impl ComputeStage {
+    /// Create the textures of `format` for targets of `target_format`.
+    /// Panics unless the format is Rgba8Unorm or Rgba16Float, which every
+    /// device can storage bind and filter
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        target_format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+    ) -> ComputeStage {
+        assert!(
+            storage_format_name(format).is_some(),
+            "{:?} cannot be storage bound on every device",
+            format
+        );
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("daswin compute stage"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let blit = Blit::with_filter(device, format, target_format, wgpu::FilterMode::Nearest);
+        let textures = StageTextures::new(device, format, size, &bind_group_layout, &blit);
+        ComputeStage {
+            format,
+            size,
+            bind_group_layout,
+            blit,
+            textures,
+            dispatched: Cell::new(false),
+        }
+    }
+
+    /// The format of the scene and output textures, which pipelines
+    /// rendering the scene target
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    /// The texture to render the scene into
+    pub fn scene(&self) -> &wgpu::Texture {
+        &self.textures.scene
+    }
+
+    /// Group 0 of the compute pipelines
+    pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    /// The WGSL declarations of group 0, `scene` and `output`
+    pub fn declarations(&self) -> String {
+        format!(
+            "@group(0) @binding(0)\nvar scene: texture_2d<f32>;\n\
+             @group(0) @binding(1)\nvar output: texture_storage_2d<{}, write>;\n",
+            storage_format_name(self.format).unwrap()
+        )
+    }
+
+    /// Recreate the textures for a surface of another size, dropping the
+    /// old ones. Does nothing when the size is unchanged
+    pub fn resize(&mut self, device: &wgpu::Device, size: PhysicalSize<u32>) {
+        let size = PhysicalSize::new(size.width.max(1), size.height.max(1));
+        if size == self.size {
+            return;
+        }
+        self.textures = StageTextures::new(
+            device,
+            self.format,
+            size,
+            &self.bind_group_layout,
+            &self.blit,
+        );
+        self.size = size;
+        self.dispatched.set(false);
+    }
+
+    /// Recreate the blit for targets of a new format, see Blit::rebuild
+    pub fn rebuild(&mut self, device: &wgpu::Device, target_format: wgpu::TextureFormat) {
+        self.blit.rebuild(device, target_format);
+    }
+
+    /// Record a compute pass running `pipeline` over the whole texture,
+    /// with the application's `bind_group` as group 1 if any. The
+    /// workgroup size must be the one declared in the shader
+    pub fn dispatch(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::ComputePipeline,
+        bind_group: Option<&wgpu::BindGroup>,
+        workgroup_size: (u32, u32),
+    ) {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("daswin compute stage"),
+        });
+        cpass.set_pipeline(pipeline);
+        cpass.set_bind_group(0, &self.textures.bind_group, &[]);
+        if let Some(bind_group) = bind_group {
+            cpass.set_bind_group(1, bind_group, &[]);
+        }
+        let (x, y) = workgroups(self.size, workgroup_size);
+        cpass.dispatch_workgroups(x, y, 1);
+        self.dispatched.set(true);
+    }
+
+    /// Copy the output onto `target`, or the scene when nothing was
+    /// dispatched since the last resolve
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let source = if self.dispatched.replace(false) {
+            &self.textures.output_source
+        } else {
+            &self.textures.scene_source
+        };
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("daswin compute stage resolve"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        self.blit.draw(&mut rpass, source);
+    }
+}
+
+// The WGSL name of the formats every device can storage bind and filter
+fn storage_format_name(format: wgpu::TextureFormat) -> Option<&'static str> {
+    match format {
+        wgpu::TextureFormat::Rgba8Unorm => Some("rgba8unorm"),
+        wgpu::TextureFormat::Rgba16Float => Some("rgba16float"),
+        _ => None,
+    }
+}
+
+// The textures of a compute stage along with the bind groups using them,
+// replaced together on resize
+struct StageTextures {
+    scene: wgpu::Texture,
+    _output: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    // Blit sources for frames with and without a dispatch
+    output_source: wgpu::BindGroup,
+    scene_source: wgpu::BindGroup,
+}
+
+impl StageTextures {
+    fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        blit: &Blit,
+    ) -> StageTextures {
+        let create = |label, usage| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: size.width.max(1),
+                    height: size.height.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: usage | wgpu::TextureUsages::TEXTURE_BINDING,
+            })
+        };
+        let scene = create(
+            "daswin compute stage scene",
+            wgpu::TextureUsages::RENDER_ATTACHMENT,
+        );
+        let output = create(
+            "daswin compute stage output",
+            wgpu::TextureUsages::STORAGE_BINDING,
+        );
+        let scene_view = scene.create_view(&wgpu::TextureViewDescriptor::default());
+        let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("daswin compute stage"),
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&scene_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&output_view),
+                },
+            ],
+        });
+        StageTextures {
+            output_source: blit.bind_group(device, &output_view),
+            scene_source: blit.bind_group(device, &scene_view),
+            scene,
+            _output: output,
+            bind_group,
+        }
+    }
+}
+
+/// A render target kept between frames, so a frame only redraws the
+/// regions which changed, see DamageTarget::set_damage.
+///
+/// DamageTarget::pass clears the damaged regions and clips drawing to
+/// them, DamageTarget::resolve copies the target onto the surface. wgpu
+/// cannot pass damage on to the present, so whole surfaces are presented
+/// and only the drawing is saved, see supports_damage
+pub struct DamageTarget {
+    format: wgpu::TextureFormat,
+    size: PhysicalSize<u32>,
+    blit: Blit,
+    fill: wgpu::RenderPipeline,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    source: wgpu::BindGroup,
+    // A pixel taking the draws of frames without damage, scissor rects
+    // cannot be empty
+    discard: wgpu::TextureView,
+    // Merged and clipped to the target, reset by resolve
+    damage: RefCell<Vec<Rect>>,
+    // Set while the texture holds no complete frame, after creation and
+    // resizes, when all of it is redrawn whatever the damage
+    invalid: Cell<bool>,
+}
+
+impl DamageTarget {
+    /// Create the target of `format` for surfaces of `target_format`
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        target_format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+    ) -> DamageTarget {
+        let source = format!("{}{}", FULLSCREEN_VERTEX, FILL_FRAGMENT);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("daswin damage fill shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        // Render pass clears ignore the scissor, the fill draw does not
+        let constant = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Constant,
+            dst_factor: wgpu::BlendFactor::Zero,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let fill = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("daswin damage fill"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState {
+                        color: constant,
+                        alpha: constant,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        let blit = Blit::with_filter(device, format, target_format, wgpu::FilterMode::Nearest);
+        let size = PhysicalSize::new(size.width.max(1), size.height.max(1));
+        let (texture, view, source) = DamageTarget::create_texture(device, format, size, &blit);
+        let (_, discard, _) =
+            DamageTarget::create_texture(device, format, PhysicalSize::new(1, 1), &blit);
+        DamageTarget {
+            format,
+            size,
+            blit,
+            fill,
+            texture,
+            view,
+            source,
+            discard,
+            damage: RefCell::new(Vec::new()),
+            invalid: Cell::new(true),
+        }
+    }
+
+    fn create_texture(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+        blit: &Blit,
+    ) -> (wgpu::Texture, wgpu::TextureView, wgpu::BindGroup) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("daswin damage target"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let source = blit.bind_group(device, &view);
+        (texture, view, source)
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    /// The texture frames are drawn into
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    /// Whether presents carry the damage to the compositor. Never with
+    /// the wgpu this crate builds on, whose presents have no damage
+    /// hints, so every present is a full one
+    pub fn supports_damage(&self) -> bool {
+        false
+    }
+
+    /// Mark the regions the next frame redraws, replacing those marked
+    /// before. Regions are clipped to the target, empty ones are dropped
+    /// and overlapping ones merged. Ignored while the whole target needs
+    /// redrawing, after creation and resizes
+    pub fn set_damage(&self, rects: &[Rect]) {
+        let bounds = Rect {
+            x: 0,
+            y: 0,
+            width: self.size.width,
+            height: self.size.height,
+        };
+        let clipped = rects.iter().filter_map(|rect| rect.intersection(&bounds));
+        *self.damage.borrow_mut() = merge_rects(clipped.collect());
+    }
+
+    /// The regions the next frame redraws, all of the target while it
+    /// holds no complete frame
+    pub fn damage(&self) -> Vec<Rect> {
+        if self.invalid.get() {
+            return vec![Rect {
+                x: 0,
+                y: 0,
+                width: self.size.width,
+                height: self.size.height,
+            }];
+        }
+        self.damage.borrow().clone()
+    }
+
+    /// Begin a render pass on the target clearing the damaged regions, with the scissor set to the box around them.
+    /// Draws outside it are discarded, the rest of the target keeps the
+    /// previous frame. Without damage all draws are discarded
+    pub fn pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        clear: wgpu::Color,
+    ) -> wgpu::RenderPass<'a> {
+        let damage = self.damage();
+        let view = if damage.is_empty() {
+            &self.discard
+        } else {
+            &self.view
+        };
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("daswin damage"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(&self.fill);
+        rpass.set_blend_constant(clear);
+        for rect in &damage {
+            rpass.set_scissor_rect(rect.x as u32, rect.y as u32, rect.width, rect.height);
+            rpass.draw(0..3, 0..1);
+        }
+        if let Some(bounds) = damage.into_iter().reduce(|a, b| a.union(&b)) {
+            rpass.set_scissor_rect(
+                bounds.x as u32,
+                bounds.y as u32,
+                bounds.width,
+                bounds.height,
+            );
+        }
+        rpass
+    }
+
+    /// Recreate the texture for a surface of another size, after which
+    /// the whole target is redrawn. Does nothing when the size is
+    /// unchanged
+    pub fn resize(&mut self, device: &wgpu::Device, size: PhysicalSize<u32>) {
+        let size = PhysicalSize::new(size.width.max(1), size.height.max(1));
+        if size == self.size {
+            return;
+        }
+        let (texture, view, source) =
+            DamageTarget::create_texture(device, self.format, size, &self.blit);
+        self.texture = texture;
+        self.view = view;
+        self.source = source;
+        self.size = size;
+        self.damage.get_mut().clear();
+        self.invalid.set(true);
+    }
+
+    /// Recreate the blit for surfaces of a new format, see Blit::rebuild
+    pub fn rebuild(&mut self, device: &wgpu::Device, target_format: wgpu::TextureFormat) {
+        self.blit.rebuild(device, target_format);
+    }
+
+    /// Copy the target onto the surface texture `target` and reset the
+    /// damage for the next frame
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("daswin damage resolve"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        self.blit.draw(&mut rpass, &self.source);
+        self.damage.borrow_mut().clear();
+        self.invalid.set(false);
+    }
+}
+
+// Merge overlapping rectangles into the box around them until none
+// overlap, so no region is cleared or drawn twice
+fn merge_rects(mut rects: Vec<Rect>) -> Vec<Rect> {
+    let mut merged = true;
+    while merged {
+        merged = false;
+        'outer: for i in 0..rects.len() {
+            for j in i + 1..rects.len() {
+                if rects[i].intersection(&rects[j]).is_some() {
+                    let other = rects.swap_remove(j);
+                    rects[i] = rects[i].union(&other);
+                    merged = true;
+                    break 'outer;
+                }
+            }
+        }
+    }
+    rects
+}
+
+/// Draws the cursor of a window using a software cursor, see
+/// X11Window::software_cursor.
+///
+/// Record it as the last draw of a frame so the cursor is on top of
+/// everything. Frames copied for captures before the pass leave the
+/// cursor out of them, frames copied after it include it
+pub struct CursorPass {
+    shader: wgpu::ShaderModule,
+    bind_group_layout: wgpu::BindGroupLayout,
+    layout: wgpu::PipelineLayout,
+    uniform: wgpu::Buffer,
+    pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+    // Image of the shape drawn last, made again when the shape changes
+    image: Option<(CursorIcon, wgpu::BindGroup)>,
+    visible: bool,
+}
+
+impl CursorPass {
+    /// Build the pipeline drawing to `format`
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> CursorPass {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("daswin cursor shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(CURSOR_SHADER)),
+        });
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("daswin cursor uniform"),
+            size: 24,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = create_cursor_pipeline(device, &layout, &shader, format);
+        CursorPass {
+            shader,
+            bind_group_layout,
+            layout,
+            uniform,
+            pipeline,
+            format,
+            image: None,
+            visible: false,
+        }
+    }
+
+    /// The format of the target the pipeline renders to
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    /// Recreate the pipeline for a new target format, does nothing when
+    /// the format is unchanged
+    pub fn rebuild(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        if format != self.format {
+            self.pipeline = create_cursor_pipeline(device, &self.layout, &self.shader, format);
+            self.format = format;
+        }
+    }
+
+    /// Upload the cursor for a target of `resolution`, to be drawn until
+    /// the next prepare. None, e.g. while the window has no focus, draws
+    /// nothing
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        resolution: PhysicalSize<u32>,
+        cursor: Option<SoftwareCursor>,
+    ) {
+        self.visible = cursor.is_some();
+        let cursor = match cursor {
+            Some(cursor) => cursor,
+            None => return,
+        };
+        let image = CursorImage::new(cursor.icon);
+        if self.image.as_ref().map(|(icon, _)| *icon) != Some(cursor.icon) {
+            let bind_group = self.upload(device, queue, &image);
+            self.image = Some((cursor.icon, bind_group));
+        }
+
+        let origin = image.origin(cursor);
+        let size = image.size();
+        let values = [
+            origin.x as f32,
+            origin.y as f32,
+            size.width as f32,
+            size.height as f32,
+            resolution.width as f32,
+            resolution.height as f32,
+        ];
+        let mut bytes = [0; 24];
+        for (chunk, value) in bytes.chunks_exact_mut(4).zip(values) {
+            chunk.copy_from_slice(&value.to_ne_bytes());
+        }
+        queue.write_buffer(&self.uniform, 0, &bytes);
+    }
+
+    /// Record the cursor uploaded by the last prepare into a render pass
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        let bind_group = match &self.image {
+            Some((_, bind_group)) if self.visible => bind_group,
+            _ => return,
+        };
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, bind_group, &[]);
+        rpass.draw(0..4, 0..1);
+    }
+
+    fn upload(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &CursorImage,
+    ) -> wgpu::BindGroup {
+        let size = wgpu::Extent3d {
+            width: image.size().width,
+            height: image.size().height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("daswin cursor image"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            image.rgba(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(size.width * 4),
+                rows_per_image: NonZeroU32::new(size.height),
+            },
+            size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+            ],
+        })
+    }
+}
+
+fn create_cursor_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("daswin cursor"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
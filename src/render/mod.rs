@@ -0,0 +1,40 @@
+//! Presenting to windows with wgpu, or on the CPU without it.
+//!
+//! RenderSurface drives either, or a renderer of the application's own,
+//! through the RenderBackend trait
+
+#[cfg(feature = "wgpu")]
+mod adapter;
+mod cursor;
+#[cfg(feature = "wgpu")]
+mod font;
+#[cfg(feature = "wgpu")]
+pub mod gallery;
+#[cfg(feature = "wgpu")]
+mod gpu;
+#[cfg(feature = "wgpu")]
+pub mod helpers;
+#[cfg(feature = "wgpu")]
+pub mod pacing;
+#[cfg(feature = "wgpu")]
+pub mod pixel;
+#[cfg(feature = "wgpu")]
+pub(crate) mod readback;
+pub mod software;
+mod surface;
+#[cfg(feature = "wgpu")]
+pub mod text;
+#[cfg(feature = "wgpu")]
+pub mod texture;
+mod viewport;
+
+#[cfg(feature = "wgpu")]
+pub use adapter::{AdapterFailure, GpuInitError, GpuOptions};
+pub use cursor::CursorImage;
+#[cfg(feature = "wgpu")]
+pub use gpu::{
+    ConfigureSurface, GpuContext, ReconfigureCallback, SurfaceCapabilities, SurfaceConfigError,
+    SurfaceConfigScheduler, SurfaceOptions, SurfaceState, WgpuBackend, WindowSurface,
+};
+pub use surface::{AcquireError, PresentParams, RenderBackend, RenderSurface, SurfaceStats};
+pub use viewport::{AspectPolicy, CanvasViewport};
@@ -0,0 +1,123 @@
+//! Limiting how many frames the GPU may queue up
+
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Returned by FramePacer::begin_frame when it does not block and the
+/// limit of frames in flight is reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WouldBlock;
+
+impl fmt::Display for WouldBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("too many frames in flight")
+    }
+}
+
+impl error::Error for WouldBlock {}
+
+/// Limits the number of submitted frames the GPU has not finished yet.
+///
+/// One frame in flight gives the lowest input latency as every frame
+/// starts from fresh input, two or three keep the GPU busy while the next
+/// frame is recorded at the cost of a frame of latency each
+pub struct FramePacer {
+    max_in_flight: u64,
+    blocking: bool,
+
+    submitted: u64,
+    // Incremented from on_submitted_work_done callbacks
+    completed: Arc<AtomicU64>,
+    // Submissions which may still be running, oldest first
+    pending: VecDeque<wgpu::SubmissionIndex>,
+
+    frame_start: Option<Instant>,
+    // Nanoseconds from begin_frame until the GPU finished the frame
+    latency: Arc<AtomicU64>,
+}
+
+impl FramePacer {
+    /// Allow up to `max_in_flight` frames, at least one
+    pub fn new(max_in_flight: u32) -> FramePacer {
+        FramePacer {
+            max_in_flight: max_in_flight.max(1) as u64,
+            blocking: true,
+            submitted: 0,
+            completed: Arc::new(AtomicU64::new(0)),
+            pending: VecDeque::new(),
+            frame_start: None,
+            latency: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Change the limit, frames already in flight are kept track of
+    pub fn set_max_in_flight(&mut self, max_in_flight: u32) {
+        self.max_in_flight = max_in_flight.max(1) as u64;
+    }
+
+    /// Whether begin_frame waits for the GPU or returns WouldBlock when
+    /// the limit is reached, blocking is the default
+    pub fn set_blocking(&mut self, blocking: bool) {
+        self.blocking = blocking;
+    }
+
+    /// The number of submitted frames the GPU has not finished
+    pub fn frames_in_flight(&self) -> u64 {
+        self.submitted - self.completed.load(Ordering::Acquire)
+    }
+
+    /// Time from begin_frame until the GPU finished the most recently
+    /// completed frame
+    pub fn latency(&self) -> Duration {
+        Duration::from_nanos(self.latency.load(Ordering::Relaxed))
+    }
+
+    /// Wait until another frame may be submitted, call before gathering
+    /// input and acquiring the surface texture so the frame starts as
+    /// late as possible
+    pub fn begin_frame(&mut self, device: &wgpu::Device) -> Result<(), WouldBlock> {
+        device.poll(wgpu::Maintain::Poll);
+        self.retire();
+
+        while self.frames_in_flight() >= self.max_in_flight {
+            if !self.blocking {
+                return Err(WouldBlock);
+            }
+            match self.pending.pop_front() {
+                Some(index) => device.poll(wgpu::Maintain::WaitForSubmissionIndex(index)),
+                None => device.poll(wgpu::Maintain::Wait),
+            };
+            self.retire();
+        }
+
+        self.frame_start = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Track the submission holding the frame's work, call after
+    /// queue.submit and before presenting
+    pub fn end_frame(&mut self, queue: &wgpu::Queue, submission: wgpu::SubmissionIndex) {
+        self.submitted += 1;
+        self.pending.push_back(submission);
+
+        let completed = self.completed.clone();
+        let latency = self.latency.clone();
+        let start = self.frame_start.take().unwrap_or_else(Instant::now);
+        queue.on_submitted_work_done(move || {
+            latency.store(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+            completed.fetch_add(1, Ordering::Release);
+        });
+    }
+
+    /// Forget submissions which are known to have finished
+    fn retire(&mut self) {
+        let in_flight = self.frames_in_flight() as usize;
+        while self.pending.len() > in_flight {
+            self.pending.pop_front();
+        }
+    }
+}
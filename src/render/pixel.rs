@@ -0,0 +1,158 @@
+//! Presenting a fixed resolution canvas scaled up by whole pixels
+
+use super::helpers::Blit;
+use super::viewport::{self, AspectPolicy, CanvasViewport};
+use crate::dpi::{PhysicalPosition, PhysicalSize};
+
+/// Renders go into a fixed size canvas texture, which is presented
+/// scaled by the largest integer factor fitting the target and centered
+/// between black bars.
+///
+/// When the target is smaller than the canvas it is scaled down to fit
+/// instead, as no integer factor does. Other aspect policies are chosen
+/// with set_policy, under FixedHeight the canvas is drawn where
+/// CanvasViewport::canvas_rect puts it
+pub struct PixelPerfectPresenter {
+    canvas: wgpu::Texture,
+    view: wgpu::TextureView,
+    blit: Blit,
+    bind_group: wgpu::BindGroup,
+    placement: CanvasViewport,
+}
+
+impl PixelPerfectPresenter {
+    /// Create a canvas of `size` in `canvas_format`, presented to targets
+    /// of `target_format`
+    pub fn new(
+        device: &wgpu::Device,
+        size: PhysicalSize<u32>,
+        canvas_format: wgpu::TextureFormat,
+        target_format: wgpu::TextureFormat,
+    ) -> PixelPerfectPresenter {
+        let canvas = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("daswin pixel canvas"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: canvas_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST,
+        });
+        let view = canvas.create_view(&wgpu::TextureViewDescriptor::default());
+        let blit = Blit::with_filter(
+            device,
+            canvas_format,
+            target_format,
+            wgpu::FilterMode::Nearest,
+        );
+        let bind_group = blit.bind_group(device, &view);
+
+        PixelPerfectPresenter {
+            canvas,
+            view,
+            blit,
+            bind_group,
+            placement: CanvasViewport::new(size, AspectPolicy::IntegerScale, size),
+        }
+    }
+
+    /// The canvas texture, e.g. to upload pixels with Queue::write_texture
+    pub fn canvas(&self) -> &wgpu::Texture {
+        &self.canvas
+    }
+
+    /// The view to render the canvas contents into
+    pub fn canvas_view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn canvas_size(&self) -> (u32, u32) {
+        self.placement.canvas_size().into()
+    }
+
+    /// The integer scale, 0 when the target is smaller than the canvas
+    pub fn scale(&self) -> u32 {
+        let (canvas, target) = (self.placement.canvas_size(), self.placement.target_size());
+        (target.width / canvas.width).min(target.height / canvas.height)
+    }
+
+    /// AspectPolicy::IntegerScale unless set_policy changed it
+    pub fn policy(&self) -> AspectPolicy {
+        self.placement.policy()
+    }
+
+    pub fn set_policy(&mut self, policy: AspectPolicy) {
+        self.placement.set_policy(policy);
+    }
+
+    /// Where the canvas lands in the target
+    pub fn placement(&self) -> &CanvasViewport {
+        &self.placement
+    }
+
+    /// Present where another placement of the canvas puts it, e.g.
+    /// RenderSurface::canvas after Event::CanvasViewportChanged
+    pub fn set_placement(&mut self, placement: CanvasViewport) {
+        self.placement = placement;
+    }
+
+    /// Recompute the scale and placement for a new target size, the
+    /// canvas is kept
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.placement.resize(size);
+    }
+
+    /// Recreate the pipeline for a new target format
+    pub fn rebuild(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        self.blit.rebuild(device, format);
+    }
+
+    /// Map a position in the target, e.g. from Event::CursorMoved, to
+    /// canvas pixels. None in the black bars
+    pub fn window_to_canvas_coords(&self, position: PhysicalPosition<f64>) -> Option<(f64, f64)> {
+        let (x, y) = self.placement.surface_to_canvas(position.x, position.y)?;
+        let (width, height) = self.canvas_size();
+        ((0.0..width as f64).contains(&x) && (0.0..height as f64).contains(&y)).then_some((x, y))
+    }
+
+    /// Clear `target` to black and draw the canvas onto it
+    pub fn present(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("daswin pixel present"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        // Under Cover the canvas reaches past the target, which only the
+        // scissor has to stay inside of
+        let rect = self.placement.canvas_rect();
+        let scissor = viewport::clip(rect, self.placement.target_size());
+        rpass.set_viewport(
+            rect.x as f32,
+            rect.y as f32,
+            rect.width as f32,
+            rect.height as f32,
+            0.0,
+            1.0,
+        );
+        rpass.set_scissor_rect(
+            scissor.x as u32,
+            scissor.y as u32,
+            scissor.width,
+            scissor.height,
+        );
+        self.blit.draw(&mut rpass, &self.bind_group);
+    }
+}
@@ -0,0 +1,173 @@
+//! Reading buffers back from the GPU without blocking the event loop,
+//! see GpuContext::map_async_callback.
+//!
+//! Mapping completes inside a device poll. The event loop polls the
+//! devices of pending readbacks with Maintain::Poll on every iteration,
+//! which returns right away, and runs the closures of the readbacks
+//! mapped since on its own thread
+
+use std::mem;
+use std::ops::Range;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use crate::event::{Event, ReadbackId, WindowId};
+use crate::xconn::XConnection;
+
+/// The closure of a readback, given the mapped range or why mapping
+/// failed
+pub(crate) type Callback = Box<dyn FnOnce(Result<&[u8], wgpu::BufferAsyncError>) + Send>;
+
+/// How often the loop polls the devices while readbacks are pending,
+/// wgpu has no way to wake it once a mapping completes
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// Set by the callback of map_async
+type Status = Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>;
+
+struct Readback {
+    id: ReadbackId,
+    window: Option<WindowId>,
+    instance: Weak<wgpu::Instance>,
+    // Kept alive until the closure ran or the readback was cancelled
+    buffer: Arc<wgpu::Buffer>,
+    range: Range<u64>,
+    status: Status,
+    callback: Callback,
+}
+
+impl Readback {
+    fn is_done(&self) -> bool {
+        self.status.lock().unwrap().is_some()
+    }
+
+    /// Drop the closure unrun. Unmapping aborts a mapping still pending
+    fn cancel(self) {
+        log::debug!("readback {} cancelled", self.id.into_raw());
+        self.buffer.unmap();
+    }
+
+    fn deliver(self) {
+        let status = self.status.lock().unwrap().take();
+        match status {
+            Some(Ok(())) => {
+                let slice = self.buffer.slice(self.range.clone());
+                let view = slice.get_mapped_range();
+                (self.callback)(Ok(&view));
+                drop(view);
+                self.buffer.unmap();
+            }
+            Some(Err(err)) => (self.callback)(Err(err)),
+            None => unreachable!("readback delivered before it was mapped"),
+        }
+    }
+}
+
+/// The readbacks of every GpuContext on a connection, waiting for their
+/// buffers to be mapped
+#[derive(Default)]
+pub(crate) struct Readbacks {
+    next: u32,
+    pending: Vec<Readback>,
+}
+
+impl Readbacks {
+    /// Map `range` of `buffer` for reading, running `callback` on the
+    /// event loop once it is
+    pub fn start(
+        &mut self,
+        instance: &Arc<wgpu::Instance>,
+        buffer: Arc<wgpu::Buffer>,
+        range: Range<u64>,
+        window: Option<WindowId>,
+        callback: Callback,
+    ) -> ReadbackId {
+        let id = ReadbackId(self.next);
+        self.next = self.next.wrapping_add(1);
+        let status = Status::default();
+        let mapped = status.clone();
+        buffer
+            .slice(range.clone())
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *mapped.lock().unwrap() = Some(result);
+            });
+        self.pending.push(Readback {
+            id,
+            window,
+            instance: Arc::downgrade(instance),
+            buffer,
+            range,
+            status,
+            callback,
+        });
+        id
+    }
+
+    /// Cancel the readback, false when it was delivered or cancelled
+    /// before
+    pub fn cancel(&mut self, id: ReadbackId) -> bool {
+        match self.pending.iter().position(|readback| readback.id == id) {
+            Some(index) => {
+                self.pending.swap_remove(index).cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_pending(&self, id: ReadbackId) -> bool {
+        self.pending.iter().any(|readback| readback.id == id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Poll the devices of pending readbacks without waiting, then run the
+/// closures of those mapped, each followed by Event::GpuReadbackReady.
+/// Readbacks of windows no longer `open` and of GpuContexts dropped are
+/// cancelled first
+pub(crate) fn poll(conn: &XConnection, open: impl Fn(WindowId) -> bool, events: &mut Vec<Event>) {
+    let mut instances: Vec<Arc<wgpu::Instance>> = Vec::new();
+    {
+        let mut readbacks = conn.readbacks.lock().unwrap();
+        if readbacks.pending.is_empty() {
+            return;
+        }
+        let (stale, pending): (Vec<_>, Vec<_>) = mem::take(&mut readbacks.pending)
+            .into_iter()
+            .partition(|readback| {
+                readback.window.is_some_and(|window| !open(window))
+                    || readback.instance.strong_count() == 0
+            });
+        readbacks.pending = pending;
+        stale.into_iter().for_each(Readback::cancel);
+        for readback in &readbacks.pending {
+            if let Some(instance) = readback.instance.upgrade() {
+                if !instances.iter().any(|known| Arc::ptr_eq(known, &instance)) {
+                    instances.push(instance);
+                }
+            }
+        }
+    }
+    // Unlocked, so map_async_callback may be called from other threads
+    // meanwhile
+    for instance in &instances {
+        instance.poll_all(false);
+    }
+    let done = {
+        let mut readbacks = conn.readbacks.lock().unwrap();
+        let (done, pending): (Vec<_>, Vec<_>) = mem::take(&mut readbacks.pending)
+            .into_iter()
+            .partition(Readback::is_done);
+        readbacks.pending = pending;
+        done
+    };
+    // The closures may start further readbacks
+    for readback in done {
+        let id = readback.id;
+        readback.deliver();
+        events.push(Event::GpuReadbackReady(id));
+    }
+}
@@ -0,0 +1,271 @@
+//! CPU presentation for machines without a usable wgpu adapter
+
+use std::mem;
+use std::os::raw;
+use std::ptr;
+use std::sync::Arc;
+
+use x11::xlib;
+
+use super::surface::{AcquireError, PresentParams, RenderBackend};
+use crate::dpi::PhysicalSize;
+use crate::error::Error;
+use crate::window::X11Window;
+use crate::xconn::XConnection;
+
+/// Server objects needed to draw into the window
+#[derive(Clone, Copy)]
+struct Target {
+    window: xlib::Window,
+    visual: *mut xlib::Visual,
+    gc: xlib::GC,
+    depth: u32,
+}
+
+// Only dereferenced by Xlib on the thread owning the connection.
+unsafe impl Send for Target {}
+
+/// Position and width of a color channel within a pixel
+#[derive(Clone, Copy)]
+struct Channel {
+    shift: u32,
+    bits: u32,
+}
+
+impl Channel {
+    fn from_mask(mask: u32) -> Channel {
+        Channel {
+            shift: mask.trailing_zeros() % 32,
+            bits: mask.count_ones().min(8),
+        }
+    }
+
+    fn encode(self, value: u8) -> u32 {
+        if self.bits == 0 {
+            return 0;
+        }
+        (value as u32 >> (8 - self.bits)) << self.shift
+    }
+}
+
+/// Presents RGBA8 pixel buffers to a window with XPutImage, for when
+/// GpuContext::try_with_window finds no adapter.
+///
+/// Supports TrueColor visuals of depth 24 and 32, the alpha channel is
+/// only kept at depth 32
+pub struct SoftwareSurface {
+    conn: Arc<XConnection>,
+    target: Target,
+    channels: [Channel; 4],
+    width: u32,
+    height: u32,
+    // Pixels in the visual's format, reused across presents
+    pixels: Vec<u32>,
+    // The buffer of the last presented SoftwareFrame, reused by acquire
+    spare: Vec<u8>,
+}
+
+/// A frame of SoftwareSurface as a RenderBackend, RGBA8 rows of the
+/// surface size
+pub struct SoftwareFrame {
+    rgba: Vec<u8>,
+    size: PhysicalSize<u32>,
+}
+
+impl SoftwareFrame {
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    /// Tightly packed rows, top to bottom, holding the pixels of an
+    /// earlier frame
+    pub fn rgba_mut(&mut self) -> &mut [u8] {
+        &mut self.rgba
+    }
+}
+
+impl SoftwareSurface {
+    /// Prepare presentation to the window, fails with Error::Unsupported
+    /// when its visual is not 24 or 32 bits deep in 32 bit pixels
+    pub fn new(window: &X11Window, size: PhysicalSize<u32>) -> Result<SoftwareSurface, Error> {
+        let (width, height) = size.into();
+        let conn = window.conn.clone();
+        let window = window.window;
+        let target = conn.request(move |conn| unsafe {
+            let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+            xlib::XGetWindowAttributes(conn.display, window, &mut attributes);
+
+            // Depth 24 is usually padded to 32 bits per pixel, but the
+            // server decides
+            let depth = attributes.depth as u32;
+            let probe = xlib::XCreateImage(
+                conn.display,
+                attributes.visual,
+                depth,
+                xlib::ZPixmap,
+                0,
+                ptr::null_mut(),
+                1,
+                1,
+                32,
+                0,
+            );
+            if probe.is_null() {
+                return None;
+            }
+            let bits_per_pixel = (*probe).bits_per_pixel;
+            xlib::XDestroyImage(probe);
+            if (depth != 24 && depth != 32) || bits_per_pixel != 32 {
+                return None;
+            }
+
+            let gc = xlib::XCreateGC(conn.display, window, 0, ptr::null_mut());
+            Some(Target {
+                window,
+                visual: attributes.visual,
+                gc,
+                depth,
+            })
+        });
+        let target = target.ok_or(Error::Unsupported("software presentation at this depth"))?;
+
+        unsafe {
+            let visual = &*target.visual;
+            let (red, green, blue) = (
+                visual.red_mask as u32,
+                visual.green_mask as u32,
+                visual.blue_mask as u32,
+            );
+            let alpha = if target.depth == 32 {
+                !(red | green | blue)
+            } else {
+                0
+            };
+            Ok(SoftwareSurface {
+                conn,
+                target,
+                channels: [
+                    Channel::from_mask(red),
+                    Channel::from_mask(green),
+                    Channel::from_mask(blue),
+                    Channel::from_mask(alpha),
+                ],
+                width,
+                height,
+                pixels: Vec::new(),
+                spare: Vec::new(),
+            })
+        }
+    }
+
+    /// Change the size of the buffers passed to present
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.width = size.width;
+        self.height = size.height;
+    }
+
+    /// The size buffers passed to present must have
+    pub fn size(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(self.width, self.height)
+    }
+
+    /// Draw tightly packed RGBA8 rows of the surface size at the top left
+    /// of the window.
+    ///
+    /// Panics when the buffer does not match the surface size
+    pub fn present(&mut self, rgba: &[u8]) {
+        assert_eq!(
+            rgba.len(),
+            self.width as usize * self.height as usize * 4,
+            "RGBA8 data does not match the surface size"
+        );
+
+        let [red, green, blue, alpha] = self.channels;
+        self.pixels.clear();
+        self.pixels.extend(rgba.chunks_exact(4).map(|pixel| {
+            red.encode(pixel[0])
+                | green.encode(pixel[1])
+                | blue.encode(pixel[2])
+                | alpha.encode(pixel[3])
+        }));
+
+        let target = self.target;
+        let (width, height) = (self.width, self.height);
+        let mut pixels = mem::take(&mut self.pixels);
+        self.pixels = self.conn.request(move |conn| unsafe {
+            let image = xlib::XCreateImage(
+                conn.display,
+                target.visual,
+                target.depth,
+                xlib::ZPixmap,
+                0,
+                pixels.as_mut_ptr() as *mut raw::c_char,
+                width,
+                height,
+                32,
+                0,
+            );
+            if !image.is_null() {
+                // Pixels are in our byte order, Xlib swaps them for the
+                // server if it differs
+                (*image).byte_order = if cfg!(target_endian = "little") {
+                    xlib::LSBFirst
+                } else {
+                    xlib::MSBFirst
+                };
+                xlib::XPutImage(
+                    conn.display,
+                    target.window,
+                    target.gc,
+                    image,
+                    0,
+                    0,
+                    0,
+                    0,
+                    width,
+                    height,
+                );
+                // The data belongs to the Vec, not Xlib
+                (*image).data = ptr::null_mut();
+                xlib::XDestroyImage(image);
+            }
+            xlib::XFlush(conn.display);
+            pixels
+        });
+    }
+}
+
+impl RenderBackend for SoftwareSurface {
+    type Frame = SoftwareFrame;
+
+    /// Resizes, presents are never synchronized to the vertical blank
+    fn configure(&mut self, size: PhysicalSize<u32>, _params: PresentParams) {
+        self.resize(size);
+    }
+
+    fn acquire(&mut self) -> Result<SoftwareFrame, AcquireError> {
+        let mut rgba = mem::take(&mut self.spare);
+        rgba.resize(self.width as usize * self.height as usize * 4, 0);
+        Ok(SoftwareFrame {
+            rgba,
+            size: self.size(),
+        })
+    }
+
+    /// Frames acquired before a resize are dropped
+    fn present(&mut self, frame: SoftwareFrame) {
+        if frame.size == self.size() {
+            SoftwareSurface::present(self, &frame.rgba);
+        }
+        self.spare = frame.rgba;
+    }
+}
+
+impl Drop for SoftwareSurface {
+    fn drop(&mut self) {
+        let target = self.target;
+        self.conn.request(move |conn| unsafe {
+            xlib::XFreeGC(conn.display, target.gc);
+        });
+    }
+}
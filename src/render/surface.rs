@@ -0,0 +1,267 @@
+//! Frame orchestration independent of the renderer: RenderSurface keeps
+//! any RenderBackend in step with its window
+
+use std::error;
+use std::fmt;
+
+use super::viewport::{AspectPolicy, CanvasViewport};
+use crate::dpi::PhysicalSize;
+use crate::event::Event;
+use crate::window::X11Window;
+
+/// How frames are presented
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresentParams {
+    /// Wait for the vertical blank, so frames never tear. On by default
+    pub vsync: bool,
+}
+
+impl Default for PresentParams {
+    fn default() -> PresentParams {
+        PresentParams { vsync: true }
+    }
+}
+
+/// Why RenderBackend::acquire returned no frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AcquireError {
+    /// No frame is available yet, skip this one
+    Timeout,
+    /// The surface no longer matches the window, configure it again
+    Outdated,
+    /// The surface is gone for good, e.g. along with the connection
+    Lost,
+}
+
+impl fmt::Display for AcquireError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            AcquireError::Timeout => "timed out acquiring a frame",
+            AcquireError::Outdated => "the surface is outdated",
+            AcquireError::Lost => "the surface was lost",
+        })
+    }
+}
+
+impl error::Error for AcquireError {}
+
+/// A renderer presenting to one window, driven by RenderSurface.
+///
+/// configure is called before the first acquire and again whenever the
+/// size or the parameters changed, acquire once per frame, and present
+/// with the frame acquire returned. destroy is called while the window
+/// still exists
+pub trait RenderBackend {
+    /// A frame being drawn, e.g. a swapchain image or a pixel buffer
+    type Frame;
+
+    /// Size the surface, which is never zero, and apply the parameters
+    fn configure(&mut self, size: PhysicalSize<u32>, params: PresentParams);
+
+    fn acquire(&mut self) -> Result<Self::Frame, AcquireError>;
+
+    fn present(&mut self, frame: Self::Frame);
+
+    /// Release the surface before the window is destroyed, e.g. after
+    /// waiting for frames still in flight
+    fn destroy(&mut self) {}
+}
+
+/// Counts kept by RenderSurface
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SurfaceStats {
+    pub presented: u64,
+    /// Frames skipped as the window had no area, the backend had no
+    /// frame or the connection was lost
+    pub skipped: u64,
+    pub configures: u64,
+}
+
+/// A window with the renderer presenting to it.
+///
+/// The backend is configured again after the window was resized or the
+/// system resumed, frames are skipped while the window has no area and
+/// after the connection was lost, and X11Window::on_first_present runs
+/// after the first present. A canvas set with set_canvas is placed again
+/// whenever the backend is configured. Dropping destroys the backend
+/// before the window
+pub struct RenderSurface<B: RenderBackend> {
+    // Fields drop in declaration order, the backend before the window
+    backend: B,
+    window: X11Window,
+    size: PhysicalSize<u32>,
+    params: PresentParams,
+    canvas: Option<CanvasViewport>,
+    // Cleared when the backend must be configured before the next acquire
+    configured: bool,
+    lost: bool,
+    stats: SurfaceStats,
+}
+
+impl<B: RenderBackend> RenderSurface<B> {
+    /// Drive `backend`, which presents to `window`
+    pub fn new(window: X11Window, backend: B) -> RenderSurface<B> {
+        RenderSurface {
+            backend,
+            size: window.inner_size(),
+            window,
+            params: PresentParams::default(),
+            canvas: None,
+            configured: false,
+            lost: false,
+            stats: SurfaceStats::default(),
+        }
+    }
+
+    pub fn window(&self) -> &X11Window {
+        &self.window
+    }
+
+    pub fn window_mut(&mut self) -> &mut X11Window {
+        &mut self.window
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    /// The size frames are acquired at, following Event::Resized. The
+    /// window may be ahead of it, see X11Window::inner_size_physical
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    pub fn params(&self) -> PresentParams {
+        self.params
+    }
+
+    /// Applied before the next acquire
+    pub fn set_params(&mut self, params: PresentParams) {
+        if params != self.params {
+            self.params = params;
+            self.configured = false;
+        }
+    }
+
+    /// Render to a canvas of `size` fitted into the window under
+    /// `policy`. Event::CanvasViewportChanged reports where it lands, for
+    /// the first time with the next poll
+    pub fn set_canvas(&mut self, size: PhysicalSize<u32>, policy: AspectPolicy) {
+        let canvas = CanvasViewport::new(size, policy, self.size);
+        self.window.report_canvas_viewport(canvas.viewport());
+        self.canvas = Some(canvas);
+    }
+
+    /// Fit the canvas under another policy, without a canvas nothing
+    /// happens
+    pub fn set_aspect_policy(&mut self, policy: AspectPolicy) {
+        if let Some(canvas) = &mut self.canvas {
+            if canvas.set_policy(policy) {
+                self.window.report_canvas_viewport(canvas.viewport());
+            }
+        }
+    }
+
+    /// The placement of the canvas as of the last configure
+    pub fn canvas(&self) -> Option<&CanvasViewport> {
+        self.canvas.as_ref()
+    }
+
+    /// Map a window position, e.g. from Event::MouseInput, into canvas
+    /// coordinates. None in the bars or without a canvas
+    pub fn surface_to_canvas(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        self.canvas.as_ref()?.surface_to_canvas(x, y)
+    }
+
+    pub fn stats(&self) -> SurfaceStats {
+        self.stats
+    }
+
+    /// Keep the backend in step with the window, call with every event
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::Resized { window, size } if window == self.window.id() && size != self.size => {
+                self.size = size;
+                self.configured = false;
+            }
+            // Some drivers lose surfaces while asleep
+            Event::Resumed => self.configured = false,
+            Event::ConnectionLost => self.lost = true,
+            _ => {}
+        }
+    }
+
+    /// The next frame, None when it should be skipped. Pending changes
+    /// are applied first, an outdated surface is configured again once.
+    /// A size behind the window while no resize is held back by the
+    /// throttle is corrected with a warning, as in WindowSurface::acquire
+    pub fn acquire(&mut self) -> Option<B::Frame> {
+        let live = self.window.inner_size_physical();
+        if live != self.size && live.width > 0 && live.height > 0 && !self.window.resize_held() {
+            log::warn!(
+                "surface at {}x{} fell behind the window at {}x{}, resizing it",
+                self.size.width,
+                self.size.height,
+                live.width,
+                live.height
+            );
+            self.size = live;
+            self.configured = false;
+        }
+        if self.lost || self.size.width == 0 || self.size.height == 0 {
+            self.stats.skipped += 1;
+            return None;
+        }
+        if !self.configured {
+            self.configure();
+        }
+        let frame = match self.backend.acquire() {
+            Err(AcquireError::Outdated) => {
+                self.configure();
+                self.backend.acquire()
+            }
+            result => result,
+        };
+        match frame {
+            Ok(frame) => Some(frame),
+            Err(err) => {
+                if err == AcquireError::Lost {
+                    self.lost = true;
+                }
+                log::trace!("skipping frame: {}", err);
+                self.stats.skipped += 1;
+                None
+            }
+        }
+    }
+
+    /// Present a frame returned by acquire
+    pub fn present(&mut self, frame: B::Frame) {
+        self.backend.present(frame);
+        self.stats.presented += 1;
+        self.window.notify_presented();
+    }
+
+    fn configure(&mut self) {
+        self.backend.configure(self.size, self.params);
+        self.configured = true;
+        self.stats.configures += 1;
+        if let Some(canvas) = &mut self.canvas {
+            if canvas.resize(self.size) {
+                self.window.report_canvas_viewport(canvas.viewport());
+            }
+        }
+    }
+}
+
+impl<B: RenderBackend> Drop for RenderSurface<B> {
+    fn drop(&mut self) {
+        // The window is dropped after this
+        self.backend.destroy();
+    }
+}
@@ -0,0 +1,362 @@
+//! Debug text drawn with an embedded 8x16 bitmap font, for frame rates,
+//! diagnostics and labels on top of a scene
+
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::num::NonZeroU32;
+
+use super::font::GLYPHS;
+use crate::dpi::PhysicalSize;
+
+/// Size of a glyph in pixels, lines advance by its height
+pub const GLYPH_SIZE: (u32, u32) = (8, 16);
+
+// The atlas holds the glyphs in rows of 16
+const ATLAS_COLUMNS: u32 = 16;
+const ATLAS_ROWS: u32 = 6;
+// Glyph drawn for characters outside printable ASCII
+const BOX_GLYPH: u32 = 95;
+// Position, glyph index and color of an instance
+const INSTANCE_SIZE: u64 = 16;
+
+const SHADER: &str = r#"
+struct Globals {
+    resolution: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+@group(0) @binding(1)
+var atlas: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) texel: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) index: u32,
+    @location(0) origin: vec2<f32>,
+    @location(1) glyph: u32,
+    @location(2) color: vec4<f32>,
+) -> VertexOutput {
+    let size = vec2<f32>(8.0, 16.0);
+    let corner = vec2<f32>(f32(index & 1u), f32(index >> 1u)) * size;
+    let pixel = origin + corner;
+    var out: VertexOutput;
+    out.position = vec4<f32>(
+        pixel.x / globals.resolution.x * 2.0 - 1.0,
+        1.0 - pixel.y / globals.resolution.y * 2.0,
+        0.0,
+        1.0,
+    );
+    out.texel = vec2<f32>(f32(glyph % 16u), f32(glyph / 16u)) * size + corner;
+    out.color = color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coverage = textureLoad(atlas, vec2<i32>(floor(in.texel)), 0).r;
+    if coverage == 0.0 {
+        discard;
+    }
+    return in.color;
+}
+"#;
+
+/// Draws text queued during a frame in one instanced draw call.
+///
+/// Text is queued in window pixels with the origin at the top left, `\n`
+/// starts a new line and characters outside printable ASCII show as a
+/// box. Glyphs entirely off the target are dropped on prepare, the queue
+/// is emptied by every prepare
+pub struct DebugText {
+    shader: wgpu::ShaderModule,
+    layout: wgpu::PipelineLayout,
+    globals: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+    instances: wgpu::Buffer,
+    // Instances queued since the last prepare, and the glyph count
+    // uploaded by it
+    queued: Vec<u8>,
+    count: u32,
+}
+
+impl DebugText {
+    /// Bake the font into a texture and build the pipeline drawing to
+    /// `format`
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+    ) -> DebugText {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("daswin debug text shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+
+        let (width, height) = (ATLAS_COLUMNS * GLYPH_SIZE.0, ATLAS_ROWS * GLYPH_SIZE.1);
+        let mut pixels = vec![0u8; (width * height) as usize];
+        for (index, rows) in GLYPHS.iter().enumerate() {
+            let x = index as u32 % ATLAS_COLUMNS * GLYPH_SIZE.0;
+            let y = index as u32 / ATLAS_COLUMNS * GLYPH_SIZE.1;
+            for (row, bits) in rows.iter().enumerate() {
+                let start = ((y + row as u32) * width + x) as usize;
+                for (column, pixel) in pixels[start..start + 8].iter_mut().enumerate() {
+                    if bits & (0x80 >> column) != 0 {
+                        *pixel = 0xff;
+                    }
+                }
+            }
+        }
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let atlas = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("daswin debug text atlas"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &atlas,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(width),
+                rows_per_image: NonZeroU32::new(height),
+            },
+            size,
+        );
+        let atlas = atlas.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let globals = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("daswin debug text globals"),
+            size: 8,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: globals.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&atlas),
+                },
+            ],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = create_pipeline(device, &layout, &shader, format);
+        DebugText {
+            shader,
+            layout,
+            globals,
+            bind_group,
+            pipeline,
+            format,
+            instances: create_instances(device, 1024),
+            queued: Vec::new(),
+            count: 0,
+        }
+    }
+
+    /// The format of the target the pipeline renders to
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    /// Recreate the pipeline for a new target format, does nothing when
+    /// the format is unchanged
+    pub fn rebuild(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        if format != self.format {
+            self.pipeline = create_pipeline(device, &self.layout, &self.shader, format);
+            self.format = format;
+        }
+    }
+
+    /// Queue a string with the top left of its first glyph at (x, y)
+    pub fn queue_text(&mut self, x: f32, y: f32, color: wgpu::Color, text: &str) {
+        let color =
+            [color.r, color.g, color.b, color.a].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        // Whole pixels keep the glyphs crisp
+        let (x, mut y) = (x.round(), y.round());
+        let mut column = 0;
+        for c in text.chars() {
+            if c == '\n' {
+                column = 0;
+                y += GLYPH_SIZE.1 as f32;
+                continue;
+            }
+            let glyph = match c {
+                ' '..='~' => c as u32 - ' ' as u32,
+                _ => BOX_GLYPH,
+            };
+            let glyph_x = x + (column * GLYPH_SIZE.0) as f32;
+            column += 1;
+            if c == ' ' {
+                continue;
+            }
+            self.queued.extend_from_slice(&glyph_x.to_ne_bytes());
+            self.queued.extend_from_slice(&y.to_ne_bytes());
+            self.queued.extend_from_slice(&glyph.to_ne_bytes());
+            self.queued.extend_from_slice(&color);
+        }
+    }
+
+    /// Upload the queued text for a target of `resolution`, to be drawn
+    /// until the next prepare. Call it every frame before the render
+    /// pass, with the current surface size
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        resolution: PhysicalSize<u32>,
+    ) {
+        let (width, height) = (resolution.width as f32, resolution.height as f32);
+        let mut bytes = [0; 8];
+        bytes[..4].copy_from_slice(&width.to_ne_bytes());
+        bytes[4..].copy_from_slice(&height.to_ne_bytes());
+        queue.write_buffer(&self.globals, 0, &bytes);
+
+        // Drop glyphs off the target, the rasterizer clips the others
+        let (glyph_width, glyph_height) = (GLYPH_SIZE.0 as f32, GLYPH_SIZE.1 as f32);
+        let mut kept = 0;
+        for i in 0..self.queued.len() / INSTANCE_SIZE as usize {
+            let start = i * INSTANCE_SIZE as usize;
+            let float = |at: usize| {
+                f32::from_ne_bytes(self.queued[start + at..start + at + 4].try_into().unwrap())
+            };
+            let (x, y) = (float(0), float(4));
+            if x + glyph_width <= 0.0 || y + glyph_height <= 0.0 || x >= width || y >= height {
+                continue;
+            }
+            let to = kept * INSTANCE_SIZE as usize;
+            self.queued
+                .copy_within(start..start + INSTANCE_SIZE as usize, to);
+            kept += 1;
+        }
+        self.queued.truncate(kept * INSTANCE_SIZE as usize);
+
+        let size = self.queued.len() as u64;
+        if size > self.instances.size() {
+            self.instances = create_instances(device, size.next_power_of_two() / INSTANCE_SIZE);
+        }
+        if size > 0 {
+            queue.write_buffer(&self.instances, 0, &self.queued);
+        }
+        self.count = kept as u32;
+        self.queued.clear();
+    }
+
+    /// Record the text uploaded by the last prepare into a render pass,
+    /// after the scene so it is drawn on top
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        if self.count == 0 {
+            return;
+        }
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.instances.slice(..self.count as u64 * INSTANCE_SIZE));
+        rpass.draw(0..4, 0..self.count);
+    }
+}
+
+fn create_instances(device: &wgpu::Device, glyphs: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("daswin debug text glyphs"),
+        size: glyphs * INSTANCE_SIZE,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("daswin debug text"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: INSTANCE_SIZE,
+                step_mode: wgpu::VertexStepMode::Instance,
+                attributes: &wgpu::vertex_attr_array![
+                    0 => Float32x2,
+                    1 => Uint32,
+                    2 => Unorm8x4,
+                ],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
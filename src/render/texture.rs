@@ -0,0 +1,242 @@
+//! Uploading images to sampled textures
+
+use std::num::NonZeroU32;
+
+use super::helpers::{error_scope, Blit};
+
+/// Builds a sampled texture from RGBA8 pixel data
+pub struct TextureBuilder<'a> {
+    device: &'a wgpu::Device,
+    queue: &'a wgpu::Queue,
+    data: &'a [u8],
+    width: u32,
+    height: u32,
+    srgb: bool,
+    mipmaps: bool,
+    label: Option<&'a str>,
+}
+
+impl<'a> TextureBuilder<'a> {
+    /// Start from tightly packed rows of `width` RGBA8 pixels, the data
+    /// is sRGB encoded and gets no mipmaps unless configured otherwise
+    pub fn from_rgba8(
+        device: &'a wgpu::Device,
+        queue: &'a wgpu::Queue,
+        data: &'a [u8],
+        width: u32,
+        height: u32,
+    ) -> TextureBuilder<'a> {
+        TextureBuilder {
+            device,
+            queue,
+            data,
+            width,
+            height,
+            srgb: true,
+            mipmaps: false,
+            label: None,
+        }
+    }
+
+    /// Whether the data is sRGB encoded color, use false for linear data
+    /// such as normal maps or lookup tables
+    pub fn with_srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+
+    /// Generate the full mipmap chain down to 1x1
+    pub fn with_mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    /// Label shown in graphics debuggers and validation errors
+    pub fn with_label(mut self, label: &'a str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Like build, but returns the error when creating or uploading the
+    /// texture fails, e.g. when it does not fit in GPU memory
+    pub fn try_build(self) -> Result<Texture, wgpu::Error> {
+        let device = self.device;
+        error_scope(device, || self.build())
+    }
+
+    /// Create the texture and upload the data.
+    ///
+    /// Panics when either dimension is zero or the data does not hold
+    /// exactly width * height pixels
+    pub fn build(self) -> Texture {
+        assert!(
+            self.width > 0 && self.height > 0,
+            "texture size must be non-zero"
+        );
+        assert_eq!(
+            self.data.len(),
+            self.width as usize * self.height as usize * 4,
+            "RGBA8 data does not match the texture size"
+        );
+
+        let format = if self.srgb {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        };
+        let mip_level_count = if self.mipmaps {
+            32 - self.width.max(self.height).leading_zeros()
+        } else {
+            1
+        };
+        let size = wgpu::Extent3d {
+            width: self.width,
+            height: self.height,
+            depth_or_array_layers: 1,
+        };
+
+        let mut usage = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST;
+        if mip_level_count > 1 {
+            usage |= wgpu::TextureUsages::RENDER_ATTACHMENT;
+        }
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: self.label,
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+        });
+
+        // Unlike buffer copies, write_texture takes unpadded rows and wgpu
+        // aligns them to COPY_BYTES_PER_ROW_ALIGNMENT while staging
+        self.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            self.data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(self.width * 4),
+                rows_per_image: NonZeroU32::new(self.height),
+            },
+            size,
+        );
+
+        if mip_level_count > 1 {
+            generate_mipmaps(self.device, self.queue, &texture, format, mip_level_count);
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = self.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: self.label,
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Texture {
+            texture,
+            view,
+            sampler,
+            format,
+            size: (self.width, self.height),
+            mip_level_count,
+        }
+    }
+}
+
+/// Render each level from the one above with a linear downsample
+fn generate_mipmaps(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    format: wgpu::TextureFormat,
+    mip_level_count: u32,
+) {
+    let blit = Blit::new(device, format, format);
+    let views: Vec<_> = (0..mip_level_count)
+        .map(|level| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                base_mip_level: level,
+                mip_level_count: NonZeroU32::new(1),
+                ..Default::default()
+            })
+        })
+        .collect();
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("daswin mipmaps"),
+    });
+    for pair in views.windows(2) {
+        blit.blit(device, &mut encoder, &pair[0], &pair[1]);
+    }
+    queue.submit(Some(encoder.finish()));
+}
+
+/// A sampled texture with a view and sampler ready for binding
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub format: wgpu::TextureFormat,
+    pub size: (u32, u32),
+    pub mip_level_count: u32,
+}
+
+impl Texture {
+    /// Layout of a texture at binding 0 and its sampler at binding 1,
+    /// visible to fragment shaders
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("daswin texture"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    /// Bind the texture for a layout from bind_group_layout
+    pub fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("daswin texture"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&self.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+}
@@ -0,0 +1,394 @@
+//! Fitting a canvas of fixed size into a window of any size
+
+use crate::dpi::PhysicalSize;
+use crate::window::Rect;
+
+/// How a canvas is fitted into the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AspectPolicy {
+    /// Fill the window, distorting the canvas
+    Stretch,
+    /// The whole canvas as large as it fits, centered between bars
+    #[default]
+    Contain,
+    /// Fill the window at the aspect ratio of the canvas, cropping the
+    /// edges which do not fit
+    Cover,
+    /// Scale the canvas to the window height, showing more or less of it
+    /// to the left and right
+    FixedHeight,
+    /// The largest whole multiple of the canvas which fits, centered
+    /// between bars. Windows smaller than the canvas fall back to Contain
+    IntegerScale,
+}
+
+/// Where a canvas lands in a window under an AspectPolicy, computed again
+/// with resize whenever the window changes size.
+///
+/// Canvas coordinates run from the top left of the canvas in canvas
+/// pixels, surface coordinates from the top left of the window in
+/// physical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CanvasViewport {
+    canvas: PhysicalSize<u32>,
+    policy: AspectPolicy,
+    target: PhysicalSize<u32>,
+    // Where the canvas lands, it reaches past the window under Cover
+    canvas_rect: Rect,
+    // The part of the window drawn to, and the canvas area it shows as
+    // left, top, width and height
+    viewport: Rect,
+    visible: (f64, f64, f64, f64),
+}
+
+impl CanvasViewport {
+    /// A canvas of `canvas` pixels, placed in a window of `target`
+    pub fn new(
+        canvas: PhysicalSize<u32>,
+        policy: AspectPolicy,
+        target: PhysicalSize<u32>,
+    ) -> CanvasViewport {
+        let canvas = PhysicalSize::new(canvas.width.max(1), canvas.height.max(1));
+        let full = Rect {
+            x: 0,
+            y: 0,
+            width: canvas.width,
+            height: canvas.height,
+        };
+        let mut viewport = CanvasViewport {
+            canvas,
+            policy,
+            target,
+            canvas_rect: full,
+            viewport: full,
+            visible: (0.0, 0.0, canvas.width as f64, canvas.height as f64),
+        };
+        viewport.place();
+        viewport
+    }
+
+    pub fn canvas_size(&self) -> PhysicalSize<u32> {
+        self.canvas
+    }
+
+    pub fn policy(&self) -> AspectPolicy {
+        self.policy
+    }
+
+    /// Returns whether the viewport changed
+    pub fn set_policy(&mut self, policy: AspectPolicy) -> bool {
+        self.policy = policy;
+        self.place()
+    }
+
+    pub fn target_size(&self) -> PhysicalSize<u32> {
+        self.target
+    }
+
+    /// Place the canvas in a window of the new size, returns whether the
+    /// viewport changed
+    pub fn resize(&mut self, target: PhysicalSize<u32>) -> bool {
+        self.target = target;
+        self.place()
+    }
+
+    /// The part of the window to render to, in surface coordinates. It
+    /// reaches past the window under Cover, see scissor
+    pub fn viewport(&self) -> Rect {
+        self.viewport
+    }
+
+    /// The viewport clipped to the window
+    pub fn scissor(&self) -> Rect {
+        clip(self.viewport, self.target)
+    }
+
+    /// Where the whole canvas lands in surface coordinates. Differs from
+    /// viewport only under FixedHeight, where the viewport covers the
+    /// window and shows more or less than the canvas
+    pub fn canvas_rect(&self) -> Rect {
+        self.canvas_rect
+    }
+
+    /// The canvas area the viewport shows, as left, top, width and height
+    /// in canvas coordinates, e.g. for a projection. It only reaches past
+    /// the canvas under FixedHeight
+    pub fn visible_canvas(&self) -> (f64, f64, f64, f64) {
+        self.visible
+    }
+
+    /// Surface pixels per canvas pixel, horizontally and vertically
+    pub fn scale(&self) -> (f64, f64) {
+        (
+            self.viewport.width as f64 / self.visible.2,
+            self.viewport.height as f64 / self.visible.3,
+        )
+    }
+
+    /// Map a surface position, e.g. from Event::CursorMoved, into canvas
+    /// coordinates. None outside the scissor, e.g. in the bars
+    pub fn surface_to_canvas(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let scissor = self.scissor();
+        let inside = |value: f64, start: i32, length: u32| {
+            value >= start as f64 && value < start as f64 + length as f64
+        };
+        if !inside(x, scissor.x, scissor.width) || !inside(y, scissor.y, scissor.height) {
+            return None;
+        }
+        let (scale_x, scale_y) = self.scale();
+        Some((
+            self.visible.0 + (x - self.viewport.x as f64) / scale_x,
+            self.visible.1 + (y - self.viewport.y as f64) / scale_y,
+        ))
+    }
+
+    /// Map canvas coordinates to the surface, e.g. to place the IME
+    /// cursor area
+    pub fn canvas_to_surface(&self, x: f64, y: f64) -> (f64, f64) {
+        let (scale_x, scale_y) = self.scale();
+        (
+            self.viewport.x as f64 + (x - self.visible.0) * scale_x,
+            self.viewport.y as f64 + (y - self.visible.1) * scale_y,
+        )
+    }
+
+    fn place(&mut self) -> bool {
+        // Windows without area are placed as one pixel ones, so every
+        // scale stays finite and above zero
+        let (width, height) = (
+            self.target.width.max(1) as f64,
+            self.target.height.max(1) as f64,
+        );
+        let (canvas_width, canvas_height) = (self.canvas.width as f64, self.canvas.height as f64);
+        let fit = (width / canvas_width).min(height / canvas_height);
+        let (scale_x, scale_y) = match self.policy {
+            AspectPolicy::Stretch => (width / canvas_width, height / canvas_height),
+            AspectPolicy::Contain => (fit, fit),
+            AspectPolicy::Cover => {
+                let cover = (width / canvas_width).max(height / canvas_height);
+                (cover, cover)
+            }
+            AspectPolicy::FixedHeight => (height / canvas_height, height / canvas_height),
+            AspectPolicy::IntegerScale if fit >= 1.0 => (fit.floor(), fit.floor()),
+            AspectPolicy::IntegerScale => (fit, fit),
+        };
+        let scaled_width = (canvas_width * scale_x).round().max(1.0);
+        let scaled_height = (canvas_height * scale_y).round().max(1.0);
+        let canvas_rect = Rect {
+            x: ((width - scaled_width) / 2.0).round() as i32,
+            y: ((height - scaled_height) / 2.0).round() as i32,
+            width: scaled_width as u32,
+            height: scaled_height as u32,
+        };
+        let (viewport, visible) = if self.policy == AspectPolicy::FixedHeight {
+            let visible_width = width / scale_x;
+            let viewport = Rect {
+                x: 0,
+                y: 0,
+                width: width as u32,
+                height: height as u32,
+            };
+            let left = (canvas_width - visible_width) / 2.0;
+            (viewport, (left, 0.0, visible_width, canvas_height))
+        } else {
+            (canvas_rect, (0.0, 0.0, canvas_width, canvas_height))
+        };
+
+        let changed = viewport != self.viewport || canvas_rect != self.canvas_rect;
+        self.canvas_rect = canvas_rect;
+        self.viewport = viewport;
+        self.visible = visible;
+        changed
+    }
+}
+
+/// The part of `rect` inside a window of `size`, at least one pixel
+pub(crate) fn clip(rect: Rect, size: PhysicalSize<u32>) -> Rect {
+    let (width, height) = (size.width.max(1) as i64, size.height.max(1) as i64);
+    let left = (rect.x as i64).clamp(0, width - 1);
+    let top = (rect.y as i64).clamp(0, height - 1);
+    let right = (rect.x as i64 + rect.width as i64).clamp(left + 1, width);
+    let bottom = (rect.y as i64 + rect.height as i64).clamp(top + 1, height);
+    Rect {
+        x: left as i32,
+        y: top as i32,
+        width: (right - left) as u32,
+        height: (bottom - top) as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICIES: [AspectPolicy; 5] = [
+        AspectPolicy::Stretch,
+        AspectPolicy::Contain,
+        AspectPolicy::Cover,
+        AspectPolicy::FixedHeight,
+        AspectPolicy::IntegerScale,
+    ];
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn place(policy: AspectPolicy, width: u32, height: u32) -> CanvasViewport {
+        CanvasViewport::new((200, 100).into(), policy, (width, height).into())
+    }
+
+    fn close(a: (f64, f64), b: (f64, f64)) -> bool {
+        (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9
+    }
+
+    #[test]
+    fn stretch() {
+        let viewport = place(AspectPolicy::Stretch, 1100, 1000);
+        assert_eq!(viewport.viewport(), rect(0, 0, 1100, 1000));
+        assert_eq!(viewport.scissor(), rect(0, 0, 1100, 1000));
+        assert_eq!(viewport.scale(), (5.5, 10.0));
+        assert_eq!(viewport.visible_canvas(), (0.0, 0.0, 200.0, 100.0));
+    }
+
+    #[test]
+    fn contain() {
+        let viewport = place(AspectPolicy::Contain, 1100, 1000);
+        assert_eq!(viewport.viewport(), rect(0, 225, 1100, 550));
+        assert_eq!(viewport.canvas_rect(), viewport.viewport());
+        assert_eq!(viewport.scale(), (5.5, 5.5));
+        assert_eq!(viewport.surface_to_canvas(10.0, 100.0), None);
+    }
+
+    #[test]
+    fn cover() {
+        let viewport = place(AspectPolicy::Cover, 1100, 1000);
+        assert_eq!(viewport.viewport(), rect(-450, 0, 2000, 1000));
+        assert_eq!(viewport.scissor(), rect(0, 0, 1100, 1000));
+        assert_eq!(viewport.scale(), (10.0, 10.0));
+        assert_eq!(viewport.surface_to_canvas(0.0, 0.0), Some((45.0, 0.0)));
+    }
+
+    #[test]
+    fn fixed_height() {
+        let viewport = place(AspectPolicy::FixedHeight, 1100, 1000);
+        assert_eq!(viewport.viewport(), rect(0, 0, 1100, 1000));
+        assert_eq!(viewport.canvas_rect(), rect(-450, 0, 2000, 1000));
+        assert_eq!(viewport.visible_canvas(), (45.0, 0.0, 110.0, 100.0));
+        assert_eq!(viewport.scale(), (10.0, 10.0));
+    }
+
+    #[test]
+    fn integer_scale() {
+        let viewport = place(AspectPolicy::IntegerScale, 1100, 1000);
+        assert_eq!(viewport.viewport(), rect(50, 250, 1000, 500));
+        assert_eq!(viewport.scale(), (5.0, 5.0));
+        // Smaller than the canvas, placed as under Contain
+        let small = place(AspectPolicy::IntegerScale, 100, 100);
+        assert_eq!(
+            small.viewport(),
+            place(AspectPolicy::Contain, 100, 100).viewport()
+        );
+        assert_eq!(small.viewport(), rect(0, 25, 100, 50));
+    }
+
+    #[test]
+    fn degenerate_sizes_clamp() {
+        let sizes = [(0, 0), (1, 500), (500, 1), (1, 1), (0, 300)];
+        for &policy in &POLICIES {
+            for &(width, height) in &sizes {
+                let viewport = place(policy, width, height);
+                let (scale_x, scale_y) = viewport.scale();
+                assert!(
+                    scale_x.is_finite() && scale_x > 0.0,
+                    "{:?} {}x{}",
+                    policy,
+                    width,
+                    height
+                );
+                assert!(
+                    scale_y.is_finite() && scale_y > 0.0,
+                    "{:?} {}x{}",
+                    policy,
+                    width,
+                    height
+                );
+                let scissor = viewport.scissor();
+                assert!(scissor.width >= 1 && scissor.height >= 1);
+                assert!(scissor.x >= 0 && scissor.x as u32 + scissor.width <= width.max(1));
+                assert!(scissor.y >= 0 && scissor.y as u32 + scissor.height <= height.max(1));
+                let (x, y) = viewport.canvas_to_surface(100.0, 50.0);
+                assert!(x.is_finite() && y.is_finite());
+                let inside = (scissor.x as f64, scissor.y as f64);
+                let (x, y) = viewport.surface_to_canvas(inside.0, inside.1).unwrap();
+                assert!(x.is_finite() && y.is_finite());
+            }
+        }
+    }
+
+    #[test]
+    fn empty_canvas_clamps() {
+        let viewport = CanvasViewport::new((0, 0).into(), AspectPolicy::Contain, (640, 480).into());
+        assert_eq!(viewport.canvas_size(), PhysicalSize::new(1, 1));
+        assert_eq!(viewport.viewport(), rect(80, 0, 480, 480));
+    }
+
+    #[test]
+    fn round_trips() {
+        for &policy in &POLICIES {
+            for &(width, height) in &[(1100, 1000), (640, 480), (333, 777), (1, 500)] {
+                let viewport = place(policy, width, height);
+                let scissor = viewport.scissor();
+                for &(x, y) in &[(0.0, 0.0), (0.5, 0.25), (0.9, 0.75)] {
+                    let surface = (
+                        scissor.x as f64 + x * scissor.width as f64,
+                        scissor.y as f64 + y * scissor.height as f64,
+                    );
+                    let canvas = viewport.surface_to_canvas(surface.0, surface.1).unwrap();
+                    let back = viewport.canvas_to_surface(canvas.0, canvas.1);
+                    assert!(
+                        close(back, surface),
+                        "{:?} {:?} {:?}",
+                        policy,
+                        surface,
+                        back
+                    );
+                    assert_eq!(viewport.surface_to_canvas(back.0, back.1), Some(canvas));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn resize_reports_changes() {
+        let mut viewport = place(AspectPolicy::Contain, 400, 200);
+        assert!(!viewport.resize((400, 200).into()));
+        assert!(viewport.resize((400, 400).into()));
+        assert_eq!(viewport.viewport(), rect(0, 100, 400, 200));
+        // Taller windows keep the canvas under FixedHeight at 2x
+        assert!(viewport.set_policy(AspectPolicy::FixedHeight));
+        assert_eq!(viewport.visible_canvas(), (50.0, 0.0, 100.0, 100.0));
+        assert!(!viewport.set_policy(AspectPolicy::FixedHeight));
+    }
+
+    #[test]
+    fn clip_to_window() {
+        let size = PhysicalSize::new(100, 50);
+        assert_eq!(clip(rect(10, 10, 20, 20), size), rect(10, 10, 20, 20));
+        assert_eq!(clip(rect(-50, -50, 400, 400), size), rect(0, 0, 100, 50));
+        assert_eq!(clip(rect(90, 40, 20, 20), size), rect(90, 40, 10, 10));
+        // Outside or empty, still a pixel at the nearest edge
+        assert_eq!(clip(rect(-20, -20, 10, 10), size), rect(0, 0, 1, 1));
+        assert_eq!(clip(rect(200, 200, 10, 10), size), rect(99, 49, 1, 1));
+        assert_eq!(clip(rect(5, 5, 0, 0), size), rect(5, 5, 1, 1));
+        assert_eq!(clip(rect(0, 0, 10, 10), (0, 0).into()), rect(0, 0, 1, 1));
+        assert_eq!(
+            clip(rect(i32::MAX, i32::MIN, u32::MAX, u32::MAX), size),
+            rect(99, 0, 1, 50)
+        );
+    }
+}
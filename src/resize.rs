@@ -0,0 +1,224 @@
+//! Interactive resizes, where the window manager configures the window
+//! for every pointer motion while a frame edge is dragged
+
+use std::os::raw::c_long;
+use std::time::{Duration, Instant};
+
+use x11::sync::XSyncValue;
+use x11::xlib;
+
+use crate::ext;
+use crate::xconn::XConnection;
+
+/// Default of EventLoop::set_resize_throttle
+pub(crate) const DEFAULT_THROTTLE: Duration = Duration::from_millis(50);
+
+/// Time without configures after which the held size is delivered
+/// whatever the throttle
+pub(crate) const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// Sizes of a window configured less than the throttle apart, only the
+/// last one held back is delivered
+#[derive(Debug)]
+pub(crate) struct ResizeStorm {
+    last_configure: Instant,
+    last_delivered: Option<Instant>,
+    held: Option<(u32, u32)>,
+}
+
+impl ResizeStorm {
+    pub fn new(now: Instant) -> ResizeStorm {
+        ResizeStorm {
+            last_configure: now,
+            last_delivered: None,
+            held: None,
+        }
+    }
+
+    /// Record a new size, returned when it is to be delivered right away
+    pub fn configure(
+        &mut self,
+        size: (u32, u32),
+        interval: Duration,
+        now: Instant,
+    ) -> Option<(u32, u32)> {
+        self.last_configure = now;
+        if self
+            .last_delivered
+            .is_some_and(|delivered| now - delivered < interval)
+        {
+            self.held = Some(size);
+            return None;
+        }
+        self.deliver(now);
+        Some(size)
+    }
+
+    /// The size held back, once the throttle allows another or the
+    /// resize settled
+    pub fn due(&mut self, interval: Duration, now: Instant) -> Option<(u32, u32)> {
+        let held = self.held?;
+        let throttled = self
+            .last_delivered
+            .is_some_and(|delivered| now - delivered < interval);
+        if throttled && now - self.last_configure < SETTLE_TIME {
+            return None;
+        }
+        self.deliver(now);
+        Some(held)
+    }
+
+    /// Whether the storm is over and nothing is held back
+    pub fn is_settled(&self, interval: Duration, now: Instant) -> bool {
+        self.held.is_none() && now - self.last_configure >= interval.max(SETTLE_TIME)
+    }
+
+    fn deliver(&mut self, now: Instant) {
+        self.held = None;
+        self.last_delivered = Some(now);
+    }
+}
+
+/// Create the counter window managers implementing _NET_WM_SYNC_REQUEST
+/// wait on before configuring the window again, None without the SYNC
+/// extension
+pub(crate) unsafe fn create_sync_counter(conn: &XConnection) -> Option<xlib::XID> {
+    let xext = ext::xext()?;
+    let (mut event_base, mut error_base) = (0, 0);
+    let (mut major, mut minor) = (0, 0);
+    if (xext.XSyncQueryExtension)(conn.display, &mut event_base, &mut error_base) == 0
+        || (xext.XSyncInitialize)(conn.display, &mut major, &mut minor) == 0
+    {
+        return None;
+    }
+    let counter = (xext.XSyncCreateCounter)(conn.display, sync_value(0));
+    (counter != 0).then_some(counter)
+}
+
+/// Tell the window manager the configure of the sync request carrying
+/// `value` was handled
+pub(crate) unsafe fn set_sync_counter(conn: &XConnection, counter: xlib::XID, value: i64) {
+    if let Some(xext) = ext::xext() {
+        (xext.XSyncSetCounter)(conn.display, counter, sync_value(value));
+    }
+}
+
+pub(crate) unsafe fn destroy_sync_counter(conn: &XConnection, counter: xlib::XID) {
+    if let Some(xext) = ext::xext() {
+        (xext.XSyncDestroyCounter)(conn.display, counter);
+    }
+}
+
+/// The counter value of a _NET_WM_SYNC_REQUEST from its low and high
+/// halves
+pub(crate) fn sync_request_value(low: c_long, high: c_long) -> i64 {
+    ((high as u64) << 32 | low as u32 as u64) as i64
+}
+
+fn sync_value(value: i64) -> XSyncValue {
+    XSyncValue {
+        hi: (value >> 32) as i32,
+        lo: value as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THROTTLE: Duration = Duration::from_millis(50);
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn first_size_is_delivered() {
+        let start = Instant::now();
+        let mut storm = ResizeStorm::new(start);
+        assert_eq!(
+            storm.configure((100, 100), THROTTLE, start),
+            Some((100, 100))
+        );
+        assert_eq!(storm.due(THROTTLE, start), None);
+    }
+
+    #[test]
+    fn only_the_last_held_size_is_delivered() {
+        let start = Instant::now();
+        let mut storm = ResizeStorm::new(start);
+        storm.configure((100, 100), THROTTLE, start);
+        assert_eq!(storm.configure((110, 100), THROTTLE, start + ms(10)), None);
+        assert_eq!(storm.configure((120, 100), THROTTLE, start + ms(20)), None);
+        assert_eq!(storm.due(THROTTLE, start + ms(49)), None);
+        assert_eq!(storm.due(THROTTLE, start + ms(50)), Some((120, 100)));
+        assert_eq!(storm.due(THROTTLE, start + ms(60)), None);
+        // Throttled from the held size on
+        assert_eq!(storm.configure((130, 100), THROTTLE, start + ms(90)), None);
+        assert_eq!(
+            storm.configure((140, 100), THROTTLE, start + ms(100)),
+            Some((140, 100))
+        );
+    }
+
+    #[test]
+    fn held_size_is_delivered_once_settled() {
+        let interval = Duration::from_secs(1);
+        let start = Instant::now();
+        let mut storm = ResizeStorm::new(start);
+        storm.configure((100, 100), interval, start);
+        storm.configure((200, 200), interval, start + ms(10));
+        assert_eq!(
+            storm.due(interval, start + ms(10) + SETTLE_TIME - ms(1)),
+            None
+        );
+        assert_eq!(
+            storm.due(interval, start + ms(10) + SETTLE_TIME),
+            Some((200, 200))
+        );
+    }
+
+    #[test]
+    fn without_throttle_every_size_is_delivered() {
+        let start = Instant::now();
+        let mut storm = ResizeStorm::new(start);
+        for (i, width) in (100..110).enumerate() {
+            let now = start + ms(i as u64);
+            assert_eq!(
+                storm.configure((width, 100), Duration::ZERO, now),
+                Some((width, 100))
+            );
+        }
+        assert_eq!(storm.due(Duration::ZERO, start + ms(10)), None);
+    }
+
+    #[test]
+    fn settles() {
+        let start = Instant::now();
+        let mut storm = ResizeStorm::new(start);
+        storm.configure((100, 100), THROTTLE, start);
+        storm.configure((110, 100), THROTTLE, start + ms(10));
+        // Quiet for long enough, but a size is still held back
+        let quiet = start + ms(10) + SETTLE_TIME;
+        assert!(!storm.is_settled(THROTTLE, quiet));
+        storm.due(THROTTLE, quiet);
+        assert!(storm.is_settled(THROTTLE, quiet));
+        // Settling takes the longer of the throttle and SETTLE_TIME
+        let interval = SETTLE_TIME * 3;
+        assert!(!storm.is_settled(interval, quiet));
+        assert!(storm.is_settled(interval, start + ms(10) + interval));
+        assert!(!ResizeStorm::new(start).is_settled(THROTTLE, start + SETTLE_TIME - ms(1)));
+    }
+
+    #[test]
+    fn sync_values() {
+        assert_eq!(sync_request_value(5, 0), 5);
+        assert_eq!(sync_request_value(0xffff_ffff, 1), 0x1_ffff_ffff);
+        // Format 32 client messages carry the low half sign extended
+        assert_eq!(sync_request_value(-2, -1), -2);
+        let value = sync_value(-2);
+        assert_eq!((value.hi, value.lo), (-1, 0xffff_fffe));
+        let value = sync_value(0x1_0000_0002);
+        assert_eq!((value.hi, value.lo), (1, 2));
+    }
+}
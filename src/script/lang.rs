@@ -0,0 +1,1240 @@
+//! The script language: lexer, parser and evaluator.
+//!
+//! Scripts are parsed once into a tree and walked on every run. Every
+//! statement and expression evaluated costs one instruction of the
+//! budget, so loops that never end fail instead of hanging the loop
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::rc::Rc;
+
+/// Why a script failed to compile or run
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptError {
+    /// Line of the script, counted from 1
+    pub line: u32,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+fn error<T>(line: u32, message: impl Into<String>) -> Result<T, ScriptError> {
+    Err(ScriptError {
+        line,
+        message: message.into(),
+    })
+}
+
+// Expressions nest deeper than this fail to parse rather than overflow
+// the stack of the parser or the evaluator
+const MAX_DEPTH: u32 = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Unit,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(Rc<str>),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Unit => "()",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Str(_) => "string",
+        }
+    }
+
+    /// The value of a number, ints converted
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Int(value) => Some(value as f64),
+            Value::Float(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Unit => write!(f, "()"),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Int(value) => write!(f, "{}", value),
+            // Floats keep their point, so they do not print as ints
+            Value::Float(value) if value.fract() == 0.0 && value.is_finite() => {
+                write!(f, "{:.1}", value)
+            }
+            Value::Float(value) => write!(f, "{}", value),
+            Value::Str(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Ident(String),
+    // Operators and punctuation, e.g. "+=" or "{"
+    Punct(&'static str),
+    Eof,
+}
+
+// Longer operators first, so "+=" is not read as "+" and "="
+const PUNCTS: [&str; 27] = [
+    "**", "==", "!=", "<=", ">=", "&&", "||", "+=", "-=", "*=", "/=", "%=", "+", "-", "*", "/",
+    "%", "<", ">", "!", "=", "(", ")", "{", "}", ",", ";",
+];
+
+const KEYWORDS: [&str; 11] = [
+    "let", "const", "if", "else", "while", "loop", "break", "continue", "return", "true", "false",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(Token, u32)>, ScriptError> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &source[i..];
+        let byte = bytes[i];
+        if byte == b'\n' {
+            line += 1;
+            i += 1;
+        } else if byte.is_ascii_whitespace() {
+            i += 1;
+        } else if rest.starts_with("//") {
+            i += rest.find('\n').unwrap_or(rest.len());
+        } else if rest.starts_with("/*") {
+            let end = match rest.find("*/") {
+                Some(end) => end + 2,
+                None => return error(line, "unterminated comment"),
+            };
+            line += rest[..end].matches('\n').count() as u32;
+            i += end;
+        } else if byte.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len());
+            let literal = rest[..len].replace('_', "");
+            let token = if literal.contains('.') || literal.contains('e') {
+                literal.parse().map(Token::Float).ok()
+            } else {
+                literal.parse().map(Token::Int).ok()
+            };
+            match token {
+                Some(token) => tokens.push((token, line)),
+                None => return error(line, format!("invalid number {}", &rest[..len])),
+            }
+            i += len;
+        } else if byte.is_ascii_alphabetic() || byte == b'_' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push((Token::Ident(rest[..len].to_owned()), line));
+            i += len;
+        } else if byte == b'"' {
+            let mut text = String::new();
+            let mut chars = rest[1..].char_indices();
+            let end = loop {
+                match chars.next() {
+                    Some((end, '"')) => break end + 2,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, 'n')) => text.push('\n'),
+                        Some((_, 't')) => text.push('\t'),
+                        Some((_, c)) if c == '"' || c == '\\' => text.push(c),
+                        _ => return error(line, "invalid escape in string"),
+                    },
+                    Some((_, '\n')) | None => return error(line, "unterminated string"),
+                    Some((_, c)) => text.push(c),
+                }
+            };
+            tokens.push((Token::Str(text), line));
+            i += end;
+        } else {
+            match PUNCTS.iter().find(|punct| rest.starts_with(*punct)) {
+                Some(punct) => {
+                    tokens.push((Token::Punct(punct), line));
+                    i += punct.len();
+                }
+                None => {
+                    let c = rest.chars().next().unwrap_or_default();
+                    return error(line, format!("unexpected character {:?}", c));
+                }
+            }
+        }
+    }
+    tokens.push((Token::Eof, line));
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinaryOp {
+    fn of(punct: &str) -> Option<BinaryOp> {
+        Some(match punct {
+            "+" | "+=" => BinaryOp::Add,
+            "-" | "-=" => BinaryOp::Sub,
+            "*" | "*=" => BinaryOp::Mul,
+            "/" | "/=" => BinaryOp::Div,
+            "%" | "%=" => BinaryOp::Rem,
+            "**" => BinaryOp::Pow,
+            "==" => BinaryOp::Eq,
+            "!=" => BinaryOp::Ne,
+            "<" => BinaryOp::Lt,
+            "<=" => BinaryOp::Le,
+            ">" => BinaryOp::Gt,
+            ">=" => BinaryOp::Ge,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Expr {
+    line: u32,
+    kind: ExprKind,
+}
+
+#[derive(Debug)]
+enum ExprKind {
+    Literal(Value),
+    Variable(String),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Debug)]
+struct Stmt {
+    line: u32,
+    kind: StmtKind,
+}
+
+#[derive(Debug)]
+enum StmtKind {
+    Let {
+        name: String,
+        constant: bool,
+        value: Option<Expr>,
+    },
+    // `op` is Some for compound assignments such as +=
+    Assign {
+        name: String,
+        op: Option<BinaryOp>,
+        value: Expr,
+    },
+    If {
+        condition: Expr,
+        then: Vec<Stmt>,
+        otherwise: Vec<Stmt>,
+    },
+    While {
+        condition: Option<Expr>,
+        body: Vec<Stmt>,
+    },
+    Break,
+    Continue,
+    Return,
+    Block(Vec<Stmt>),
+    Expr(Expr),
+}
+
+/// A parsed script
+#[derive(Debug)]
+pub(crate) struct Program {
+    statements: Vec<Stmt>,
+}
+
+impl Program {
+    pub fn compile(source: &str) -> Result<Program, ScriptError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            next: 0,
+            depth: 0,
+        };
+        let mut statements = Vec::new();
+        while parser.peek() != &Token::Eof {
+            statements.push(parser.statement()?);
+        }
+        Ok(Program { statements })
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, u32)>,
+    next: usize,
+    depth: u32,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next].0
+    }
+
+    fn line(&self) -> u32 {
+        self.tokens[self.next].1
+    }
+
+    fn at(&self, punct: &str) -> bool {
+        matches!(self.peek(), Token::Punct(p) if *p == punct)
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Token::Ident(name) if name == keyword)
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        let at = self.at(punct);
+        if at {
+            self.next += 1;
+        }
+        at
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), ScriptError> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            self.unexpected(&format!("expected {}", punct))
+        }
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T, ScriptError> {
+        let found = match self.peek() {
+            Token::Int(value) => value.to_string(),
+            Token::Float(value) => value.to_string(),
+            Token::Str(text) => format!("{:?}", text),
+            Token::Ident(name) => name.clone(),
+            Token::Punct(punct) => punct.to_string(),
+            Token::Eof => "the end of the script".to_owned(),
+        };
+        error(self.line(), format!("{}, found {}", expected, found))
+    }
+
+    fn identifier(&mut self) -> Result<String, ScriptError> {
+        match self.peek() {
+            Token::Ident(name) if !KEYWORDS.contains(&name.as_str()) => {
+                let name = name.clone();
+                self.next += 1;
+                Ok(name)
+            }
+            _ => self.unexpected("expected a name"),
+        }
+    }
+
+    // Statements end with a semicolon, except after a block and before
+    // the end of their block or script
+    fn end_statement(&mut self) -> Result<(), ScriptError> {
+        if self.eat(";") || self.at("}") || self.peek() == &Token::Eof {
+            Ok(())
+        } else {
+            self.unexpected("expected ;")
+        }
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, ScriptError> {
+        self.expect("{")?;
+        self.nest()?;
+        let mut statements = Vec::new();
+        while !self.eat("}") {
+            if self.peek() == &Token::Eof {
+                return self.unexpected("expected }");
+            }
+            statements.push(self.statement()?);
+        }
+        self.depth -= 1;
+        Ok(statements)
+    }
+
+    fn nest(&mut self) -> Result<(), ScriptError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return error(self.line(), "nested too deeply");
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ScriptError> {
+        let line = self.line();
+        let kind = if self.eat(";") {
+            StmtKind::Block(Vec::new())
+        } else if self.at_keyword("let") || self.at_keyword("const") {
+            let constant = self.at_keyword("const");
+            self.next += 1;
+            let name = self.identifier()?;
+            let value = if self.eat("=") {
+                Some(self.expression()?)
+            } else if constant {
+                return self.unexpected("expected =");
+            } else {
+                None
+            };
+            self.end_statement()?;
+            StmtKind::Let {
+                name,
+                constant,
+                value,
+            }
+        } else if self.at_keyword("if") {
+            self.if_statement()?
+        } else if self.at_keyword("while") {
+            self.next += 1;
+            let condition = Some(self.expression()?);
+            let body = self.block()?;
+            StmtKind::While { condition, body }
+        } else if self.at_keyword("loop") {
+            self.next += 1;
+            let body = self.block()?;
+            StmtKind::While {
+                condition: None,
+                body,
+            }
+        } else if self.at_keyword("break") || self.at_keyword("continue") {
+            let kind = if self.at_keyword("break") {
+                StmtKind::Break
+            } else {
+                StmtKind::Continue
+            };
+            self.next += 1;
+            self.end_statement()?;
+            kind
+        } else if self.at_keyword("return") {
+            // Scripts return nothing, the value is only evaluated
+            self.next += 1;
+            let kind = if self.at(";") || self.at("}") || self.peek() == &Token::Eof {
+                StmtKind::Return
+            } else {
+                let value = self.expression()?;
+                StmtKind::Block(vec![
+                    Stmt {
+                        line,
+                        kind: StmtKind::Expr(value),
+                    },
+                    Stmt {
+                        line,
+                        kind: StmtKind::Return,
+                    },
+                ])
+            };
+            self.end_statement()?;
+            kind
+        } else if self.at("{") {
+            StmtKind::Block(self.block()?)
+        } else {
+            let expr = self.expression()?;
+            let op = match self.peek() {
+                Token::Punct(op) if ["=", "+=", "-=", "*=", "/=", "%="].contains(op) => {
+                    Some(BinaryOp::of(op))
+                }
+                _ => None,
+            };
+            let kind = match (op, expr.kind) {
+                (Some(op), ExprKind::Variable(name)) => {
+                    self.next += 1;
+                    let value = self.expression()?;
+                    StmtKind::Assign { name, op, value }
+                }
+                (Some(_), _) => return error(line, "only variables can be assigned to"),
+                (None, kind) => StmtKind::Expr(Expr { line, kind }),
+            };
+            self.end_statement()?;
+            kind
+        };
+        Ok(Stmt { line, kind })
+    }
+
+    fn if_statement(&mut self) -> Result<StmtKind, ScriptError> {
+        self.next += 1;
+        let condition = self.expression()?;
+        let then = self.block()?;
+        let otherwise = if self.at_keyword("else") {
+            self.next += 1;
+            if self.at_keyword("if") {
+                let line = self.line();
+                vec![Stmt {
+                    line,
+                    kind: self.if_statement()?,
+                }]
+            } else {
+                self.block()?
+            }
+        } else {
+            Vec::new()
+        };
+        Ok(StmtKind::If {
+            condition,
+            then,
+            otherwise,
+        })
+    }
+
+    fn expression(&mut self) -> Result<Expr, ScriptError> {
+        self.nest()?;
+        let expr = self.binary(0);
+        self.depth -= 1;
+        expr
+    }
+
+    // Binary operators by precedence, lowest first
+    fn binary(&mut self, level: usize) -> Result<Expr, ScriptError> {
+        const LEVELS: [&[&str]; 6] = [
+            &["||"],
+            &["&&"],
+            &["==", "!="],
+            &["<", "<=", ">", ">="],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+        if level == LEVELS.len() {
+            return self.power();
+        }
+        let mut left = self.binary(level + 1)?;
+        loop {
+            let op = match self.peek() {
+                Token::Punct(op) if LEVELS[level].contains(op) => *op,
+                _ => return Ok(left),
+            };
+            let line = self.line();
+            self.next += 1;
+            let right = Box::new(self.binary(level + 1)?);
+            let left_box = Box::new(left);
+            let kind = match op {
+                "||" => ExprKind::Or(left_box, right),
+                "&&" => ExprKind::And(left_box, right),
+                _ => ExprKind::Binary(BinaryOp::of(op).unwrap(), left_box, right),
+            };
+            left = Expr { line, kind };
+        }
+    }
+
+    // ** binds tighter than the other operators and to the right
+    fn power(&mut self) -> Result<Expr, ScriptError> {
+        let base = self.unary()?;
+        if !self.at("**") {
+            return Ok(base);
+        }
+        let line = self.line();
+        self.next += 1;
+        self.nest()?;
+        let exponent = self.power();
+        self.depth -= 1;
+        Ok(Expr {
+            line,
+            kind: ExprKind::Binary(BinaryOp::Pow, Box::new(base), Box::new(exponent?)),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Expr, ScriptError> {
+        let line = self.line();
+        if self.at("-") || self.at("!") {
+            let not = self.at("!");
+            self.next += 1;
+            self.nest()?;
+            let operand = self.unary();
+            self.depth -= 1;
+            let operand = Box::new(operand?);
+            let kind = if not {
+                ExprKind::Not(operand)
+            } else {
+                ExprKind::Negate(operand)
+            };
+            return Ok(Expr { line, kind });
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, ScriptError> {
+        let line = self.line();
+        let kind = match self.peek().clone() {
+            Token::Int(value) => {
+                self.next += 1;
+                ExprKind::Literal(Value::Int(value))
+            }
+            Token::Float(value) => {
+                self.next += 1;
+                ExprKind::Literal(Value::Float(value))
+            }
+            Token::Str(text) => {
+                self.next += 1;
+                ExprKind::Literal(Value::Str(text.into()))
+            }
+            Token::Ident(name) if name == "true" || name == "false" => {
+                self.next += 1;
+                ExprKind::Literal(Value::Bool(name == "true"))
+            }
+            Token::Ident(_) => {
+                let name = self.identifier()?;
+                if self.eat("(") {
+                    let mut args = Vec::new();
+                    while !self.eat(")") {
+                        if !args.is_empty() {
+                            self.expect(",")?;
+                        }
+                        args.push(self.expression()?);
+                    }
+                    ExprKind::Call(name, args)
+                } else {
+                    ExprKind::Variable(name)
+                }
+            }
+            Token::Punct("(") => {
+                self.next += 1;
+                let expr = self.expression()?;
+                self.expect(")")?;
+                return Ok(expr);
+            }
+            _ => return self.unexpected("expected an expression"),
+        };
+        Ok(Expr { line, kind })
+    }
+}
+
+/// What the host functions of a run asked for
+#[derive(Debug, Default)]
+pub(crate) struct Requests {
+    pub title: Option<String>,
+    pub clear_color: Option<[f64; 4]>,
+    pub uniforms: Vec<(String, f64)>,
+    pub redraw: bool,
+    pub toggle_fullscreen: bool,
+}
+
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return,
+}
+
+/// Runs a program once with the given variables in scope
+pub(crate) struct Run<'a> {
+    scope: Vec<(String, Value, bool)>,
+    remaining: u64,
+    budget: u64,
+    requests: &'a mut Requests,
+}
+
+impl<'a> Run<'a> {
+    pub fn new(budget: u64, requests: &'a mut Requests) -> Run<'a> {
+        Run {
+            scope: Vec::new(),
+            remaining: budget,
+            budget,
+            requests,
+        }
+    }
+
+    /// Define a constant, e.g. the time of the run
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.scope.push((name.to_owned(), value, true));
+    }
+
+    pub fn execute(&mut self, program: &Program) -> Result<(), ScriptError> {
+        self.block(&program.statements).map(|_| ())
+    }
+
+    fn tick(&mut self, line: u32) -> Result<(), ScriptError> {
+        if self.remaining == 0 {
+            return error(
+                line,
+                format!("exceeded the budget of {} instructions", self.budget),
+            );
+        }
+        self.remaining -= 1;
+        Ok(())
+    }
+
+    fn block(&mut self, statements: &[Stmt]) -> Result<Flow, ScriptError> {
+        let depth = self.scope.len();
+        let mut flow = Ok(Flow::Normal);
+        for statement in statements {
+            flow = self.statement(statement);
+            if !matches!(flow, Ok(Flow::Normal)) {
+                break;
+            }
+        }
+        self.scope.truncate(depth);
+        flow
+    }
+
+    fn statement(&mut self, statement: &Stmt) -> Result<Flow, ScriptError> {
+        let line = statement.line;
+        self.tick(line)?;
+        match &statement.kind {
+            StmtKind::Let {
+                name,
+                constant,
+                value,
+            } => {
+                let value = match value {
+                    Some(value) => self.expression(value)?,
+                    None => Value::Unit,
+                };
+                self.scope.push((name.clone(), value, *constant));
+            }
+            StmtKind::Assign { name, op, value } => {
+                let value = self.expression(value)?;
+                let (_, variable, constant) = match self
+                    .scope
+                    .iter_mut()
+                    .rev()
+                    .find(|(variable, ..)| variable == name)
+                {
+                    Some(entry) => entry,
+                    None => return error(line, format!("unknown variable {}", name)),
+                };
+                if *constant {
+                    return error(line, format!("{} is a constant", name));
+                }
+                *variable = match op {
+                    Some(op) => binary(line, *op, variable.clone(), value)?,
+                    None => value,
+                };
+            }
+            StmtKind::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                return if self.condition(condition)? {
+                    self.block(then)
+                } else {
+                    self.block(otherwise)
+                };
+            }
+            StmtKind::While { condition, body } => loop {
+                if let Some(condition) = condition {
+                    if !self.condition(condition)? {
+                        break;
+                    }
+                }
+                match self.block(body)? {
+                    Flow::Break => break,
+                    Flow::Return => return Ok(Flow::Return),
+                    Flow::Normal | Flow::Continue => self.tick(line)?,
+                }
+            },
+            StmtKind::Break => return Ok(Flow::Break),
+            StmtKind::Continue => return Ok(Flow::Continue),
+            StmtKind::Return => return Ok(Flow::Return),
+            StmtKind::Block(statements) => return self.block(statements),
+            StmtKind::Expr(expr) => {
+                self.expression(expr)?;
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    fn condition(&mut self, expr: &Expr) -> Result<bool, ScriptError> {
+        match self.expression(expr)? {
+            Value::Bool(value) => Ok(value),
+            other => error(
+                expr.line,
+                format!("expected a bool condition, found {}", other.type_name()),
+            ),
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr) -> Result<Value, ScriptError> {
+        let line = expr.line;
+        self.tick(line)?;
+        Ok(match &expr.kind {
+            ExprKind::Literal(value) => value.clone(),
+            ExprKind::Variable(name) => {
+                match self
+                    .scope
+                    .iter()
+                    .rev()
+                    .find(|(variable, ..)| variable == name)
+                {
+                    Some((_, value, _)) => value.clone(),
+                    None => return error(line, format!("unknown variable {}", name)),
+                }
+            }
+            ExprKind::Negate(operand) => match self.expression(operand)? {
+                Value::Int(value) => match value.checked_neg() {
+                    Some(value) => Value::Int(value),
+                    None => return error(line, "integer overflow"),
+                },
+                Value::Float(value) => Value::Float(-value),
+                other => return error(line, format!("cannot negate {}", other.type_name())),
+            },
+            ExprKind::Not(operand) => Value::Bool(!self.condition(operand)?),
+            ExprKind::And(left, right) => {
+                Value::Bool(self.condition(left)? && self.condition(right)?)
+            }
+            ExprKind::Or(left, right) => {
+                Value::Bool(self.condition(left)? || self.condition(right)?)
+            }
+            ExprKind::Binary(op, left, right) => {
+                let left = self.expression(left)?;
+                let right = self.expression(right)?;
+                binary(line, *op, left, right)?
+            }
+            ExprKind::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.expression(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.call(line, name, &args)?
+            }
+        })
+    }
+
+    fn call(&mut self, line: u32, name: &str, args: &[Value]) -> Result<Value, ScriptError> {
+        let numbers = || {
+            args.iter()
+                .map(|arg| arg.as_f64())
+                .collect::<Option<Vec<f64>>>()
+                .ok_or_else(|| ScriptError {
+                    line,
+                    message: format!("{} takes numbers", name),
+                })
+        };
+        let arity = |counts: &[usize]| {
+            if counts.contains(&args.len()) {
+                Ok(())
+            } else {
+                error(
+                    line,
+                    format!("{} takes {:?} arguments, not {}", name, counts, args.len()),
+                )
+            }
+        };
+        let unary: Option<fn(f64) -> f64> = match name {
+            "sin" => Some(f64::sin),
+            "cos" => Some(f64::cos),
+            "tan" => Some(f64::tan),
+            "sqrt" => Some(f64::sqrt),
+            "floor" => Some(f64::floor),
+            "ceil" => Some(f64::ceil),
+            "round" => Some(f64::round),
+            "fract" => Some(f64::fract),
+            "to_float" => Some(|x| x),
+            _ => None,
+        };
+        if let Some(function) = unary {
+            arity(&[1])?;
+            return Ok(Value::Float(function(numbers()?[0])));
+        }
+        Ok(match name {
+            "abs" => {
+                arity(&[1])?;
+                match args[0] {
+                    Value::Int(value) => Value::Int(value.wrapping_abs()),
+                    _ => Value::Float(numbers()?[0].abs()),
+                }
+            }
+            "to_int" => {
+                arity(&[1])?;
+                Value::Int(numbers()?[0] as i64)
+            }
+            "min" | "max" => {
+                arity(&[2])?;
+                let pick_left = (binary(line, BinaryOp::Lt, args[0].clone(), args[1].clone())?
+                    == Value::Bool(true))
+                    == (name == "min");
+                args[if pick_left { 0 } else { 1 }].clone()
+            }
+            "clamp" => {
+                arity(&[3])?;
+                let numbers = numbers()?;
+                Value::Float(numbers[0].max(numbers[1]).min(numbers[2]))
+            }
+            "pow" => {
+                arity(&[2])?;
+                binary(line, BinaryOp::Pow, args[0].clone(), args[1].clone())?
+            }
+            "PI" => {
+                arity(&[0])?;
+                Value::Float(std::f64::consts::PI)
+            }
+            "print" => {
+                arity(&[1])?;
+                log::info!("script: {}", args[0]);
+                Value::Unit
+            }
+            "set_title" => {
+                arity(&[1])?;
+                self.requests.title = Some(args[0].to_string());
+                Value::Unit
+            }
+            "set_clear_color" => {
+                arity(&[3, 4])?;
+                let mut color = [0.0, 0.0, 0.0, 1.0];
+                color[..args.len()].copy_from_slice(&numbers()?);
+                self.requests.clear_color = Some(color);
+                Value::Unit
+            }
+            "set_uniform" => {
+                arity(&[2])?;
+                let (name, value) = match (&args[0], args[1].as_f64()) {
+                    (Value::Str(name), Some(value)) => (name, value),
+                    _ => return error(line, "set_uniform takes a name and a number"),
+                };
+                let uniforms = &mut self.requests.uniforms;
+                match uniforms.iter_mut().find(|(uniform, _)| **uniform == **name) {
+                    Some((_, uniform)) => *uniform = value,
+                    None => uniforms.push((name.to_string(), value)),
+                }
+                Value::Unit
+            }
+            "request_redraw" => {
+                arity(&[0])?;
+                self.requests.redraw = true;
+                Value::Unit
+            }
+            "toggle_fullscreen" => {
+                arity(&[0])?;
+                self.requests.toggle_fullscreen = !self.requests.toggle_fullscreen;
+                Value::Unit
+            }
+            _ => return error(line, format!("unknown function {}", name)),
+        })
+    }
+}
+
+fn binary(line: u32, op: BinaryOp, left: Value, right: Value) -> Result<Value, ScriptError> {
+    use BinaryOp::*;
+    let value = match (op, &left, &right) {
+        (Add, Value::Str(left), right) => Value::Str(format!("{}{}", left, right).into()),
+        (Add, left, Value::Str(right)) => Value::Str(format!("{}{}", left, right).into()),
+        (Eq, left, right) | (Ne, left, right) if left.as_f64().is_none() => {
+            Value::Bool((left == right) == (op == Eq))
+        }
+        (_, &Value::Int(a), &Value::Int(b)) => {
+            let value = match op {
+                Add => a.checked_add(b),
+                Sub => a.checked_sub(b),
+                Mul => a.checked_mul(b),
+                Div if b == 0 => return error(line, "division by zero"),
+                Div => a.checked_div(b),
+                Rem if b == 0 => return error(line, "division by zero"),
+                Rem => a.checked_rem(b),
+                Pow if b < 0 => return binary(line, op, Value::Float(a as f64), right),
+                Pow => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+                Eq => return Ok(Value::Bool(a == b)),
+                Ne => return Ok(Value::Bool(a != b)),
+                Lt => return Ok(Value::Bool(a < b)),
+                Le => return Ok(Value::Bool(a <= b)),
+                Gt => return Ok(Value::Bool(a > b)),
+                Ge => return Ok(Value::Bool(a >= b)),
+            };
+            match value {
+                Some(value) => Value::Int(value),
+                None => return error(line, "integer overflow"),
+            }
+        }
+        _ => match (left.as_f64(), right.as_f64()) {
+            (Some(a), Some(b)) => match op {
+                Add => Value::Float(a + b),
+                Sub => Value::Float(a - b),
+                Mul => Value::Float(a * b),
+                Div => Value::Float(a / b),
+                Rem => Value::Float(a % b),
+                Pow => Value::Float(a.powf(b)),
+                Eq => Value::Bool(a == b),
+                Ne => Value::Bool(a != b),
+                Lt => Value::Bool(a < b),
+                Le => Value::Bool(a <= b),
+                Gt => Value::Bool(a > b),
+                Ge => Value::Bool(a >= b),
+            },
+            _ => {
+                return error(
+                    line,
+                    format!(
+                        "cannot apply {:?} to {} and {}",
+                        op,
+                        left.type_name(),
+                        right.type_name()
+                    ),
+                )
+            }
+        },
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_with(budget: u64, source: &str) -> Result<Requests, ScriptError> {
+        let program = Program::compile(source)?;
+        let mut requests = Requests::default();
+        let mut run = Run::new(budget, &mut requests);
+        run.define("time", Value::Float(2.5));
+        run.execute(&program)?;
+        Ok(requests)
+    }
+
+    fn run(source: &str) -> Result<Requests, ScriptError> {
+        run_with(10_000, source)
+    }
+
+    // The title a script sets, to see the values it computed
+    fn title(source: &str) -> String {
+        run(source).unwrap().title.unwrap()
+    }
+
+    fn fails(source: &str) -> ScriptError {
+        run(source).unwrap_err()
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(title("set_title(1 + 2 * 3 - 4 / 2)"), "5");
+        assert_eq!(title("set_title((1 + 2) * 3)"), "9");
+        assert_eq!(title("set_title(2 ** 3 ** 2)"), "512");
+        assert_eq!(title("set_title(2 * 3 ** 2)"), "18");
+        assert_eq!(title("set_title(-2 ** 2)"), "4");
+        assert_eq!(title("set_title(1 < 2 == 2 < 3)"), "true");
+        assert_eq!(title("set_title(true || false && false)"), "true");
+        assert_eq!(title("set_title(!(1 > 2) && 7 % 4 == 3)"), "true");
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(title("set_title(7 / 2)"), "3");
+        assert_eq!(title("set_title(7.0 / 2)"), "3.5");
+        assert_eq!(title("set_title(1_000 + 0.5)"), "1000.5");
+        assert_eq!(title("set_title(2.0 * 2)"), "4.0");
+        assert_eq!(title("set_title(1e3)"), "1000.0");
+        assert_eq!(title("set_title(2 ** -1)"), "0.5");
+        assert_eq!(title("set_title(to_int(time) + abs(-3))"), "5");
+        assert_eq!(title("set_title(min(3, 2) + max(1, 4))"), "6");
+        assert_eq!(title("set_title(clamp(5, 0, 1))"), "1.0");
+        assert_eq!(title("set_title(floor(PI()))"), "3.0");
+        assert_eq!(title("set_title(1 == 1.0)"), "true");
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(title(r#"set_title("t=" + 1 + ", " + 1.5)"#), "t=1, 1.5");
+        assert_eq!(title(r#"set_title(1 + "x")"#), "1x");
+        assert_eq!(title(r#"set_title("a\tb\n\"\\")"#), "a\tb\n\"\\");
+        assert_eq!(title(r#"set_title("a" == "a" && "a" != "b")"#), "true");
+    }
+
+    #[test]
+    fn variables_and_scopes() {
+        assert_eq!(title("let x = 2; x *= 3; x -= 1; set_title(x)"), "5");
+        assert_eq!(title("let x = 1; { let x = 2; } set_title(x)"), "1");
+        assert_eq!(title("let x; set_title(x)"), "()");
+        assert_eq!(
+            fails("{ let y = 1; } set_title(y)").message,
+            "unknown variable y"
+        );
+        assert_eq!(fails("const c = 1; c = 2").message, "c is a constant");
+        assert_eq!(fails("time = 0").message, "time is a constant");
+        assert_eq!(fails("z += 1").message, "unknown variable z");
+    }
+
+    #[test]
+    fn control_flow() {
+        let source = "
+            let sum = 0;
+            let i = 0;
+            while i < 10 {
+                i += 1;
+                if i % 2 == 0 { continue; }
+                if i > 7 { break; }
+                sum += i;
+            }
+            set_title(sum);
+        ";
+        assert_eq!(title(source), "16");
+        let source = "
+            let n = 0;
+            loop { n += 1; if n == 3 { break } }
+            if n < 3 { set_title(\"less\") } else if n == 3 { set_title(\"three\") } else { set_title(\"more\") }
+        ";
+        assert_eq!(title(source), "three");
+        assert_eq!(title("set_title(1); return; set_title(2)"), "1");
+        assert_eq!(title("set_title(1); loop { return 5; } set_title(2)"), "1");
+    }
+
+    #[test]
+    fn short_circuits() {
+        assert_eq!(title("set_title(false && missing)"), "false");
+        assert_eq!(title("set_title(true || missing)"), "true");
+    }
+
+    #[test]
+    fn host_functions() {
+        let requests = run(r#"
+            set_clear_color(0.5, 0, 1);
+            set_uniform("scale", 2);
+            set_uniform("scale", time);
+            set_uniform("angle", 1.5);
+            request_redraw();
+            toggle_fullscreen();
+        "#)
+        .unwrap();
+        assert_eq!(requests.clear_color, Some([0.5, 0.0, 1.0, 1.0]));
+        assert_eq!(
+            requests.uniforms,
+            [("scale".to_owned(), 2.5), ("angle".to_owned(), 1.5)]
+        );
+        assert!(requests.redraw);
+        assert!(requests.toggle_fullscreen);
+        assert!(
+            !run("toggle_fullscreen(); toggle_fullscreen()")
+                .unwrap()
+                .toggle_fullscreen
+        );
+        assert_eq!(
+            run("set_clear_color(1, 1, 1, 0.5)").unwrap().clear_color,
+            Some([1.0, 1.0, 1.0, 0.5])
+        );
+    }
+
+    #[test]
+    fn runtime_errors() {
+        assert_eq!(fails("1 / 0").message, "division by zero");
+        assert_eq!(fails("1 % 0").message, "division by zero");
+        assert_eq!(fails("9223372036854775807 + 1").message, "integer overflow");
+        assert_eq!(
+            fails("let x = -9223372036854775807 - 1; -x").message,
+            "integer overflow"
+        );
+        assert_eq!(fails("2 ** 64").message, "integer overflow");
+        assert_eq!(
+            fails("if 1 { }").message,
+            "expected a bool condition, found int"
+        );
+        assert_eq!(fails("-true").message, "cannot negate bool");
+        assert_eq!(
+            fails("true * 2").message,
+            "cannot apply Mul to bool and int"
+        );
+        assert_eq!(fails("launch()").message, "unknown function launch");
+        assert_eq!(fails("sin(1, 2)").message, "sin takes [1] arguments, not 2");
+        assert_eq!(fails(r#"sqrt("4")"#).message, "sqrt takes numbers");
+        assert_eq!(
+            fails("set_uniform(1, 2)").message,
+            "set_uniform takes a name and a number"
+        );
+    }
+
+    #[test]
+    fn changes_before_an_error_are_kept() {
+        let program = Program::compile("set_title(\"before\"); 1 / 0").unwrap();
+        let mut requests = Requests::default();
+        assert!(Run::new(100, &mut requests).execute(&program).is_err());
+        assert_eq!(requests.title.as_deref(), Some("before"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let compile = |source| Program::compile(source).unwrap_err().message;
+        assert_eq!(compile("let = 1"), "expected a name, found =");
+        assert_eq!(compile("let if = 1"), "expected a name, found if");
+        assert_eq!(compile("const c;"), "expected =, found ;");
+        assert_eq!(compile("1 2"), "expected ;, found 2");
+        assert_eq!(compile("(1 + 2"), "expected ), found the end of the script");
+        assert_eq!(
+            compile("if true { 1"),
+            "expected }, found the end of the script"
+        );
+        assert_eq!(compile("f(1 2)"), "expected ,, found 2");
+        assert_eq!(compile("1 = 2"), "only variables can be assigned to");
+        assert_eq!(compile("+"), "expected an expression, found +");
+        assert_eq!(compile("\"open"), "unterminated string");
+        assert_eq!(compile(r#""\q""#), "invalid escape in string");
+        assert_eq!(compile("/* open"), "unterminated comment");
+        assert_eq!(compile("1.2.3"), "invalid number 1.2.3");
+        assert_eq!(
+            compile("99999999999999999999"),
+            "invalid number 99999999999999999999"
+        );
+        assert_eq!(compile("a @ b"), "unexpected character '@'");
+    }
+
+    #[test]
+    fn error_lines() {
+        let compile = |source| Program::compile(source).unwrap_err().line;
+        assert_eq!(compile("let a = 1;\n\nlet = 2;"), 3);
+        assert_eq!(compile("/* one\ntwo */ // three\n\n@"), 4);
+        assert_eq!(compile("let a = \"x\";\n\"y"), 2);
+        assert_eq!(fails("let a = 1;\nlet b = 0;\nset_title(a / b);").line, 3);
+        assert_eq!(fails("let a = 1;\nif a > 0 {\n  missing\n}").line, 3);
+        // Binary operators report the line of the operator
+        assert_eq!(fails("let x = 1\n  + true;").line, 2);
+        let err = fails("\n\nnope()");
+        assert_eq!(err.to_string(), "line 3: unknown function nope");
+    }
+
+    #[test]
+    fn budget() {
+        // A statement and its expression
+        assert!(run_with(2, "let x = 1;").is_ok());
+        assert_eq!(
+            run_with(1, "let x = 1;").unwrap_err().message,
+            "exceeded the budget of 1 instructions"
+        );
+        let err = run_with(1000, "let i = 0;\nloop { i += 1; }").unwrap_err();
+        assert_eq!(err.message, "exceeded the budget of 1000 instructions");
+        assert_eq!(err.line, 2);
+        // Empty loops cost their iterations too
+        assert!(run_with(1000, "loop {}").is_err());
+        assert!(run_with(1000, "while true {}").is_err());
+    }
+
+    #[test]
+    fn depth_limit() {
+        let nested = |open: &str, inner: &str, close: &str, depth| {
+            format!("{}{}{}", open.repeat(depth), inner, close.repeat(depth))
+        };
+        let compile = |source: String| Program::compile(&source).map(|_| ());
+        assert!(compile(nested("(", "1", ")", 60)).is_ok());
+        assert!(compile(nested("{", "", "}", 60)).is_ok());
+
+        let too_deep = ScriptError {
+            line: 1,
+            message: "nested too deeply".into(),
+        };
+        assert_eq!(compile(nested("(", "1", ")", 100)), Err(too_deep.clone()));
+        assert_eq!(compile(nested("{", "", "}", 100)), Err(too_deep.clone()));
+        assert_eq!(compile(nested("-", "1", "", 100)), Err(too_deep.clone()));
+        assert_eq!(compile(nested("!", "true", "", 100)), Err(too_deep.clone()));
+        assert_eq!(
+            compile(nested("2 ** ", "1", "", 100)),
+            Err(too_deep.clone())
+        );
+        assert_eq!(compile(nested("f(", "1", ")", 100)), Err(too_deep));
+        // Long flat expressions are fine
+        assert!(compile(format!("1{}", " + 1".repeat(10_000))).is_ok());
+    }
+}
@@ -0,0 +1,234 @@
+//! Live tweaking of a running application from scripts, see ScriptHost.
+//!
+//! Scripts are written in a small scripting language built into the
+//! crate and interpreted by the lang module: `let` and `const`,
+//! `if`/`else`, `while` and `loop`, the arithmetic, comparison and logic
+//! operators on ints, floats, bools and strings, and calls of the
+//! functions listed on ScriptHost. There are no user functions, arrays
+//! or objects, and no dependency. Embedding rhai as requested is left
+//! for when the crate can depend on it
+
+mod lang;
+
+use std::cell::RefCell;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::event_loop::EventLoop;
+use crate::proxy::WindowProxy;
+use crate::window::X11Window;
+use lang::{Program, Requests, Run, Value};
+
+pub use lang::ScriptError;
+
+/// Instructions a run may take by default, see
+/// ScriptHost::set_instruction_budget
+pub const DEFAULT_INSTRUCTION_BUDGET: u64 = 100_000;
+
+// How often the file of load_script is checked for changes
+pub(crate) const RELOAD_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Runs a script on every poll of the event loop it was created with,
+/// letting it change one window and the parameters an application draws
+/// with.
+///
+/// The script runs on the loop thread after the events of the poll were
+/// read and before the redraws and AboutToWait are delivered, so what
+/// it changed is seen by the frame drawn for that poll. Each run starts
+/// with a fresh scope holding `time`, the seconds since the script was
+/// loaded, and `frame`, the number of runs since. Besides sin, cos, tan,
+/// sqrt, abs, floor, ceil, round, fract, min, max, clamp, pow, PI,
+/// to_int, to_float and print, scripts may call:
+///
+/// - `set_title(text)`
+/// - `set_clear_color(r, g, b)` or `set_clear_color(r, g, b, a)`, read
+///   with ScriptHost::clear_color
+/// - `set_uniform(name, value)`, read with ScriptHost::uniform
+/// - `request_redraw()`
+/// - `toggle_fullscreen()`, borderless on the current monitor. Guard it,
+///   e.g. with `if frame == 0`, as it toggles on every run otherwise
+///
+/// Errors do not panic. They are delivered as Event::ScriptError and
+/// stop the script until it is loaded again or its file changes
+pub struct ScriptHost {
+    script: Rc<RefCell<Script>>,
+}
+
+impl ScriptHost {
+    /// A host changing `window`, without a script until load_script
+    pub fn new(event_loop: &EventLoop, window: &X11Window) -> ScriptHost {
+        let script = Rc::new(RefCell::new(Script {
+            window: window.proxy(),
+            program: None,
+            file: None,
+            budget: DEFAULT_INSTRUCTION_BUDGET,
+            loaded: Instant::now(),
+            frame: 0,
+            title: None,
+            requests: Requests::default(),
+            errors: Vec::new(),
+        }));
+        event_loop.register_script(&script);
+        ScriptHost { script }
+    }
+
+    /// Run the script at `path` from the next poll on, reloading it
+    /// whenever the file changes. Fails only when the file cannot be
+    /// read, errors in the script are delivered as events
+    pub fn load_script(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)?;
+        let modified = fs::metadata(path)?.modified().ok();
+        let mut script = self.script.borrow_mut();
+        script.file = Some(ScriptFile {
+            path: path.to_owned(),
+            modified,
+            checked: Instant::now(),
+        });
+        script.compile(&source);
+        Ok(())
+    }
+
+    /// Run `source` once right away, e.g. a line typed into a console.
+    /// Its changes are made by the next poll, like those of the script
+    pub fn eval(&mut self, source: &str) -> Result<(), ScriptError> {
+        let program = Program::compile(source)?;
+        self.script.borrow_mut().run(&program, Instant::now())
+    }
+
+    /// Fail runs taking more than `budget` instructions, each statement
+    /// and each operation counting as one, 100000 by default. Keeps a
+    /// script looping forever from hanging the application
+    pub fn set_instruction_budget(&mut self, budget: u64) {
+        self.script.borrow_mut().budget = budget;
+    }
+
+    /// The color last set with set_clear_color, None until then
+    pub fn clear_color(&self) -> Option<[f64; 4]> {
+        self.script.borrow().requests.clear_color
+    }
+
+    /// The value last set with set_uniform for `name`
+    pub fn uniform(&self, name: &str) -> Option<f64> {
+        let script = self.script.borrow();
+        let uniforms = &script.requests.uniforms;
+        uniforms
+            .iter()
+            .find(|(uniform, _)| uniform == name)
+            .map(|&(_, value)| value)
+    }
+}
+
+struct ScriptFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    checked: Instant,
+}
+
+/// The state of a ScriptHost, run by the polls of its event loop
+pub(crate) struct Script {
+    window: WindowProxy,
+    // None before a script was loaded and after it failed
+    program: Option<Program>,
+    file: Option<ScriptFile>,
+    budget: u64,
+    loaded: Instant,
+    frame: u64,
+    // The title last set, so scripts setting it on every run do not
+    // rewrite the property each time
+    title: Option<String>,
+    requests: Requests,
+    // Errors since the last poll
+    errors: Vec<String>,
+}
+
+impl Script {
+    fn compile(&mut self, source: &str) {
+        self.loaded = Instant::now();
+        self.frame = 0;
+        match Program::compile(source) {
+            Ok(program) => self.program = Some(program),
+            Err(err) => {
+                self.program = None;
+                self.report(err);
+            }
+        }
+    }
+
+    fn report(&mut self, err: ScriptError) {
+        let message = match &self.file {
+            Some(file) => format!("{}: {}", file.path.display(), err),
+            None => err.to_string(),
+        };
+        log::warn!("script error, {}", message);
+        self.errors.push(message);
+    }
+
+    /// Reload the file when it changed, then run the script. Errors are
+    /// appended to `errors`
+    pub fn poll(&mut self, now: Instant, errors: &mut Vec<String>) {
+        self.reload_if_changed(now);
+        if let Some(program) = self.program.take() {
+            match self.run(&program, now) {
+                Ok(()) => self.program = Some(program),
+                Err(err) => self.report(err),
+            }
+            self.frame += 1;
+        }
+        errors.append(&mut self.errors);
+    }
+
+    fn reload_if_changed(&mut self, now: Instant) {
+        let file = match &mut self.file {
+            Some(file) if now - file.checked >= RELOAD_INTERVAL => file,
+            _ => return,
+        };
+        file.checked = now;
+        // Editors saving by renaming leave no file for a moment, the
+        // next check finds the new one
+        let modified = fs::metadata(&file.path).and_then(|metadata| metadata.modified());
+        let modified = match modified {
+            Ok(modified) if Some(modified) != file.modified => modified,
+            _ => return,
+        };
+        file.modified = Some(modified);
+        match fs::read_to_string(&file.path) {
+            Ok(source) => {
+                log::info!("reloading {}", file.path.display());
+                self.compile(&source);
+            }
+            Err(err) => log::debug!("failed to read {}: {}", file.path.display(), err),
+        }
+    }
+
+    fn run(&mut self, program: &Program, now: Instant) -> Result<(), ScriptError> {
+        let mut requests = Requests {
+            clear_color: self.requests.clear_color,
+            uniforms: self.requests.uniforms.clone(),
+            ..Requests::default()
+        };
+        let mut run = Run::new(self.budget, &mut requests);
+        run.define("time", Value::Float((now - self.loaded).as_secs_f64()));
+        run.define("frame", Value::Int(self.frame as i64));
+        // Changes made before an error are kept
+        let result = run.execute(program);
+
+        if let Some(title) = requests.title.take() {
+            if self.title.as_ref() != Some(&title) {
+                self.window.set_title(&title);
+                self.title = Some(title);
+            }
+        }
+        if requests.redraw {
+            self.window.request_redraw();
+        }
+        if requests.toggle_fullscreen {
+            self.window.toggle_fullscreen();
+        }
+        self.requests = requests;
+        result
+    }
+}
@@ -0,0 +1,184 @@
+//! Smooth scrolling, read from the scroll valuators of XInput 2.1
+//! devices such as touchpads
+
+use std::collections::HashMap;
+use std::slice;
+
+use x11::{xinput2, xlib};
+
+use crate::event::ScrollDelta;
+use crate::ext;
+use crate::tablet;
+use crate::xconn::XConnection;
+
+/// Pixels scrolled per line, i.e. per scroll increment of the device
+pub(crate) const PIXELS_PER_LINE: f64 = 20.0;
+
+/// A scroll valuator and the distance of one wheel click on it
+struct ScrollAxis {
+    number: i32,
+    increment: f64,
+    horizontal: bool,
+    // Valuators report absolute values, deltas are taken from the last
+    last: f64,
+}
+
+/// A slave pointer device with scroll valuators
+pub(crate) struct ScrollDevice {
+    axes: Vec<ScrollAxis>,
+    // Whether the device ever scrolled by a fraction of an increment,
+    // wheels only scroll in whole increments
+    smooth: bool,
+}
+
+impl ScrollDevice {
+    /// The scroll since the previous event, None when no scroll valuator
+    /// changed. Positive values scroll up and left like wheel buttons 4
+    /// and 6
+    pub unsafe fn update(&mut self, state: &xinput2::XIValuatorState) -> Option<ScrollDelta> {
+        let (mut x, mut y) = (0.0, 0.0);
+        let mut scrolled = false;
+        for axis in &mut self.axes {
+            let value = match tablet::valuator(state, axis.number) {
+                Some(value) => value,
+                None => continue,
+            };
+            // The valuators grow scrolling down and right
+            let lines = -(value - axis.last) / axis.increment;
+            axis.last = value;
+            if lines == 0.0 {
+                continue;
+            }
+            if (lines - lines.round()).abs() > 1e-3 {
+                self.smooth = true;
+            }
+            scrolled = true;
+            if axis.horizontal {
+                x += lines;
+            } else {
+                y += lines;
+            }
+        }
+        if !scrolled {
+            return None;
+        }
+        Some(if self.smooth {
+            ScrollDelta::Pixels(x * PIXELS_PER_LINE, y * PIXELS_PER_LINE)
+        } else {
+            ScrollDelta::Lines(x as f32, y as f32)
+        })
+    }
+}
+
+/// Enumerate the enabled pointer devices with scroll valuators by device
+/// id. Devices found before keep whether they scroll smoothly, the
+/// valuators restart from their current values
+pub(crate) fn query_scroll_devices(
+    conn: &XConnection,
+    previous: &HashMap<i32, ScrollDevice>,
+) -> HashMap<i32, ScrollDevice> {
+    let mut devices = HashMap::new();
+    let xinput2 = match ext::xinput2() {
+        Some(xinput2) => xinput2,
+        None => return devices,
+    };
+
+    unsafe {
+        let mut count = 0;
+        let info = (xinput2.XIQueryDevice)(conn.display, xinput2::XIAllDevices, &mut count);
+        if info.is_null() {
+            return devices;
+        }
+
+        for device in slice::from_raw_parts(info, count as usize) {
+            if device._use != xinput2::XISlavePointer || device.enabled == 0 {
+                continue;
+            }
+            let classes = slice::from_raw_parts(device.classes, device.num_classes as usize);
+            let value = |number| {
+                classes.iter().find_map(|&class| {
+                    if (*class)._type != xinput2::XIValuatorClass {
+                        return None;
+                    }
+                    let class = &*(class as *const xinput2::XIValuatorClassInfo);
+                    Some(class.value).filter(|_| class.number == number)
+                })
+            };
+
+            let mut axes = Vec::new();
+            for &class in classes {
+                if (*class)._type != xinput2::XIScrollClass {
+                    continue;
+                }
+                let class = &*(class as *const xinput2::XIScrollClassInfo);
+                if class.increment == 0.0 {
+                    continue;
+                }
+                axes.push(ScrollAxis {
+                    number: class.number,
+                    increment: class.increment,
+                    horizontal: class.scroll_type == xinput2::XIScrollTypeHorizontal,
+                    last: value(class.number).unwrap_or(0.0),
+                });
+            }
+
+            if !axes.is_empty() {
+                let smooth = previous
+                    .get(&device.deviceid)
+                    .is_some_and(|device| device.smooth);
+                devices.insert(device.deviceid, ScrollDevice { axes, smooth });
+            }
+        }
+        (xinput2.XIFreeDeviceInfo)(info);
+    }
+    devices
+}
+
+/// Select or deselect scroll valuator motion from the devices on a
+/// window, along with the pointer entering it, after which the valuators
+/// are read again
+pub(crate) fn select_scroll_events(
+    conn: &XConnection,
+    window: xlib::Window,
+    devices: &[i32],
+    enabled: bool,
+) {
+    let xinput2 = match ext::xinput2() {
+        Some(xinput2) => xinput2,
+        None => return,
+    };
+
+    let mut motion = [0u8; (xinput2::XI_Motion as usize >> 3) + 1];
+    let mut enter = [0u8; (xinput2::XI_Enter as usize >> 3) + 1];
+    if enabled {
+        xinput2::XISetMask(&mut motion, xinput2::XI_Motion);
+        xinput2::XISetMask(&mut enter, xinput2::XI_Enter);
+    }
+    let mut event_masks: Vec<_> = devices
+        .iter()
+        .map(|&deviceid| xinput2::XIEventMask {
+            deviceid,
+            mask_len: motion.len() as i32,
+            mask: motion.as_mut_ptr(),
+        })
+        .collect();
+    if event_masks.is_empty() {
+        return;
+    }
+    // Core events are not selected for crossing, so the master device's
+    // XI2 enter events take nothing away from them
+    event_masks.push(xinput2::XIEventMask {
+        deviceid: xinput2::XIAllMasterDevices,
+        mask_len: enter.len() as i32,
+        mask: enter.as_mut_ptr(),
+    });
+    unsafe {
+        (xinput2.XISelectEvents)(
+            conn.display,
+            window,
+            event_masks.as_mut_ptr(),
+            event_masks.len() as i32,
+        );
+        xlib::XFlush(conn.display);
+    }
+}
@@ -0,0 +1,197 @@
+//! Several master pointers, as with XInput2 multi-seat setups. Core
+//! events merge every master pointer into one, so while there are
+//! several their XInput2 events are selected instead and turned into
+//! core events carrying the device they came from
+
+use std::mem;
+use std::slice;
+
+use x11::{xinput2, xlib};
+
+use crate::event::DeviceId;
+use crate::ext;
+use crate::xconn::XConnection;
+
+/// The master pointer core events come from, the virtual core pointer
+/// without XInput2
+pub(crate) fn query_client_pointer(conn: &XConnection) -> DeviceId {
+    let mut device = xinput2::XIAllMasterDevices;
+    if let Some(xinput2) = ext::xinput2() {
+        unsafe {
+            (xinput2.XIGetClientPointer)(conn.display, 0, &mut device);
+        }
+    }
+    DeviceId(if device > 1 { device as u32 } else { 2 })
+}
+
+/// Select the crossing, motion and button events of every master pointer
+/// on a window, or only entering when `seats` is false and `enter` is
+/// true. The selection for all master devices is replaced as a whole, so
+/// this runs after scroll::select_scroll_events
+pub(crate) fn select_seat_events(
+    conn: &XConnection,
+    window: xlib::Window,
+    seats: bool,
+    (enter, motion, buttons): (bool, bool, bool),
+) {
+    let xinput2 = match ext::xinput2() {
+        Some(xinput2) => xinput2,
+        None => return,
+    };
+    let mut mask = [0u8; (xinput2::XI_Leave as usize >> 3) + 1];
+    if enter || seats {
+        xinput2::XISetMask(&mut mask, xinput2::XI_Enter);
+    }
+    if seats {
+        xinput2::XISetMask(&mut mask, xinput2::XI_Leave);
+        if motion {
+            xinput2::XISetMask(&mut mask, xinput2::XI_Motion);
+        }
+        if buttons {
+            xinput2::XISetMask(&mut mask, xinput2::XI_ButtonPress);
+            xinput2::XISetMask(&mut mask, xinput2::XI_ButtonRelease);
+        }
+    }
+    let mut event_mask = xinput2::XIEventMask {
+        deviceid: xinput2::XIAllMasterDevices,
+        mask_len: mask.len() as i32,
+        mask: mask.as_mut_ptr(),
+    };
+    unsafe {
+        (xinput2.XISelectEvents)(conn.display, window, &mut event_mask, 1);
+        xlib::XFlush(conn.display);
+    }
+}
+
+/// The core event matching the motion or button event of a master
+/// pointer
+pub(crate) fn device_event_to_core(xevent: &xinput2::XIDeviceEvent) -> xlib::XEvent {
+    let state = modifier_state(&xevent.mods, &xevent.buttons);
+    let (x, y) = (xevent.event_x as i32, xevent.event_y as i32);
+    let (x_root, y_root) = (xevent.root_x as i32, xevent.root_y as i32);
+    let mut core: xlib::XEvent = unsafe { mem::zeroed() };
+    if xevent.evtype == xinput2::XI_Motion {
+        core.motion = xlib::XMotionEvent {
+            type_: xlib::MotionNotify,
+            serial: xevent.serial,
+            send_event: xlib::False,
+            display: xevent.display,
+            window: xevent.event,
+            root: xevent.root,
+            subwindow: xevent.child,
+            time: xevent.time,
+            x,
+            y,
+            x_root,
+            y_root,
+            state,
+            is_hint: 0,
+            same_screen: xlib::True,
+        };
+    } else {
+        let type_ = match xevent.evtype {
+            xinput2::XI_ButtonPress => xlib::ButtonPress,
+            _ => xlib::ButtonRelease,
+        };
+        core.button = xlib::XButtonEvent {
+            type_,
+            serial: xevent.serial,
+            send_event: xlib::False,
+            display: xevent.display,
+            window: xevent.event,
+            root: xevent.root,
+            subwindow: xevent.child,
+            time: xevent.time,
+            x,
+            y,
+            x_root,
+            y_root,
+            state,
+            button: xevent.detail as u32,
+            same_screen: xlib::True,
+        };
+    }
+    core
+}
+
+/// The core crossing event matching the one of a master pointer
+pub(crate) fn crossing_to_core(xevent: &xinput2::XIEnterEvent) -> xlib::XEvent {
+    let type_ = match xevent.evtype {
+        xinput2::XI_Enter => xlib::EnterNotify,
+        _ => xlib::LeaveNotify,
+    };
+    let mut core: xlib::XEvent = unsafe { mem::zeroed() };
+    core.crossing = xlib::XCrossingEvent {
+        type_,
+        serial: xevent.serial,
+        send_event: xlib::False,
+        display: xevent.display,
+        window: xevent.event,
+        root: xevent.root,
+        subwindow: xevent.child,
+        time: xevent.time,
+        x: xevent.event_x as i32,
+        y: xevent.event_y as i32,
+        x_root: xevent.root_x as i32,
+        y_root: xevent.root_y as i32,
+        // XInput2 shares the core values of mode and detail
+        mode: xevent.mode,
+        detail: xevent.detail,
+        same_screen: xevent.same_screen,
+        focus: xevent.focus,
+        state: modifier_state(&xevent.mods, &xevent.buttons),
+    };
+    core
+}
+
+/// The position of a master pointer relative to the window, None when
+/// it is on another screen or the device is no master pointer
+pub(crate) fn query_pointer(
+    conn: &XConnection,
+    device: DeviceId,
+    window: xlib::Window,
+) -> Option<(f64, f64)> {
+    let xinput2 = ext::xinput2()?;
+    unsafe {
+        let (mut root, mut child) = (0, 0);
+        let (mut root_x, mut root_y, mut x, mut y) = (0.0, 0.0, 0.0, 0.0);
+        let mut buttons: xinput2::XIButtonState = mem::zeroed();
+        let mut mods: xinput2::XIModifierState = mem::zeroed();
+        let mut group: xinput2::XIGroupState = mem::zeroed();
+        let same_screen = (xinput2.XIQueryPointer)(
+            conn.display,
+            device.0 as i32,
+            window,
+            &mut root,
+            &mut child,
+            &mut root_x,
+            &mut root_y,
+            &mut x,
+            &mut y,
+            &mut buttons,
+            &mut mods,
+            &mut group,
+        );
+        if !buttons.mask.is_null() {
+            xlib::XFree(buttons.mask as *mut _);
+        }
+        (same_screen != xlib::False).then_some((x, y))
+    }
+}
+
+// The core state mask: modifiers along with buttons 1 to 5
+fn modifier_state(mods: &xinput2::XIModifierState, buttons: &xinput2::XIButtonState) -> u32 {
+    let mut state = mods.effective as u32;
+    if !buttons.mask.is_null() {
+        let mask = unsafe { slice::from_raw_parts(buttons.mask, buttons.mask_len as usize) };
+        for button in 1..=5 {
+            if mask
+                .get(button / 8)
+                .is_some_and(|byte| byte & 1 << (button % 8) != 0)
+            {
+                state |= xlib::Button1Mask << (button - 1);
+            }
+        }
+    }
+    state
+}
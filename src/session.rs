@@ -0,0 +1,338 @@
+//! Undoing changes which lock up or outlive the session should the
+//! application go down without cleaning up: the video modes of exclusive
+//! fullscreen and keyboard and pointer grabs. Cursors and barriers belong
+//! to the connection and go away with it.
+//!
+//! Every such change registers how to undo it with its connection and
+//! unregisters once undone. SessionGuard undoes what is left on drop,
+//! and while one is alive the requests doing it are kept serialized for
+//! fatal signals, whose handler writes them straight to the X socket
+
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::c_int;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use x11::xlib;
+use x11::xrandr::{RRCrtc, RRMode, RROutput, Rotation};
+
+use crate::monitor;
+use crate::xconn::XConnection;
+
+// Core and RandR request codes
+const UNGRAB_POINTER: u8 = 27;
+const UNGRAB_KEYBOARD: u8 = 32;
+const RR_SET_CRTC_CONFIG: u8 = 21;
+
+// Signals after which the process is not expected to clean up
+const FATAL_SIGNALS: [c_int; 3] = [libc::SIGSEGV, libc::SIGBUS, libc::SIGABRT];
+
+/// How to undo a change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Undo {
+    UngrabKeyboard,
+    UngrabPointer,
+    RestoreCrtc(CrtcConfig),
+}
+
+impl Undo {
+    // Whether both undo the same change, the newer replacing the older
+    fn same_change(&self, other: &Undo) -> bool {
+        match (self, other) {
+            (Undo::RestoreCrtc(a), Undo::RestoreCrtc(b)) => a.crtc == b.crtc,
+            (a, b) => a == b,
+        }
+    }
+}
+
+/// The configuration of a CRTC to put back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CrtcConfig {
+    pub crtc: RRCrtc,
+    pub mode: RRMode,
+    pub position: (i32, i32),
+    pub rotation: Rotation,
+    pub outputs: Vec<RROutput>,
+    // Of the screen resources, RandR refuses configs made against stale
+    // ones
+    pub config_timestamp: xlib::Time,
+}
+
+/// The changes of a connection not undone yet
+#[derive(Default)]
+pub(crate) struct Session {
+    pending: Mutex<Vec<Undo>>,
+    guards: AtomicUsize,
+    randr_opcode: OnceLock<Option<u8>>,
+}
+
+/// Undoes the video modes and grabs of the event loop's windows when
+/// dropped, and on SIGSEGV, SIGBUS and SIGABRT while it is alive, see
+/// EventLoop::session_guard.
+///
+/// Restoring twice does nothing the second time, whichever path gets
+/// there first. The signal path writes the requests to the X socket in
+/// between whatever Xlib had written, which the server only misreads
+/// when the crash came in the middle of a request
+pub struct SessionGuard {
+    conn: Arc<XConnection>,
+    // Xlib requests are made on the event loop thread
+    _thread: PhantomData<*const ()>,
+}
+
+impl SessionGuard {
+    pub(crate) fn new(conn: &Arc<XConnection>) -> SessionGuard {
+        install_handlers();
+        conn.session.guards.fetch_add(1, Ordering::AcqRel);
+        conn.request(publish);
+        SessionGuard {
+            conn: conn.clone(),
+            _thread: PhantomData,
+        }
+    }
+
+    /// Undo every change left now rather than on drop
+    pub fn restore(&self) {
+        self.conn.request(|conn| unsafe { restore(conn) });
+    }
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.restore();
+        self.conn.session.guards.fetch_sub(1, Ordering::AcqRel);
+        self.conn.request(publish);
+    }
+}
+
+/// Remember how to undo a change made on the connection
+pub(crate) fn register(conn: &XConnection, undo: Undo) {
+    let mut pending = conn.session.pending.lock().unwrap();
+    pending.retain(|pending| !pending.same_change(&undo));
+    pending.push(undo);
+    drop(pending);
+    publish(conn);
+}
+
+/// Forget a change once it was undone
+pub(crate) fn done(conn: &XConnection, undo: &Undo) {
+    let mut pending = conn.session.pending.lock().unwrap();
+    let count = pending.len();
+    pending.retain(|pending| !pending.same_change(undo));
+    let changed = pending.len() != count;
+    drop(pending);
+    if changed {
+        publish(conn);
+    }
+}
+
+/// Undo every change left on the connection
+pub(crate) unsafe fn restore(conn: &XConnection) {
+    let pending = mem::take(&mut *conn.session.pending.lock().unwrap());
+    if pending.is_empty() || conn.is_lost() {
+        return;
+    }
+    for undo in &pending {
+        match undo {
+            Undo::UngrabKeyboard => {
+                xlib::XUngrabKeyboard(conn.display, xlib::CurrentTime);
+            }
+            Undo::UngrabPointer => {
+                xlib::XUngrabPointer(conn.display, xlib::CurrentTime);
+            }
+            Undo::RestoreCrtc(config) => {
+                let mut outputs = config.outputs.clone();
+                monitor::set_crtc_config(
+                    conn,
+                    config.crtc,
+                    config.position,
+                    config.mode,
+                    config.rotation,
+                    &mut outputs,
+                );
+            }
+        }
+    }
+    xlib::XFlush(conn.display);
+    publish(conn);
+}
+
+/// Serialize the pending undo requests of the connection for the signal
+/// handler while a guard is alive, or drop them
+fn publish(conn: &XConnection) {
+    let fd = conn.fd();
+    let requests = if conn.session.guards.load(Ordering::Acquire) > 0 {
+        let opcode = *conn.session.randr_opcode.get_or_init(|| randr_opcode(conn));
+        let pending = conn.session.pending.lock().unwrap();
+        let mut requests = Vec::new();
+        for undo in pending.iter() {
+            serialize(undo, opcode, &mut requests);
+        }
+        requests
+    } else {
+        Vec::new()
+    };
+
+    let mut planned = PLANNED.lock().unwrap();
+    planned.retain(|&(planned, _)| planned != fd);
+    if !requests.is_empty() {
+        planned.push((fd, requests));
+    }
+    let next = 1 - ACTIVE.load(Ordering::Acquire);
+    // Only the writer holding PLANNED touches the inactive buffer
+    let buffer = unsafe { &mut *PLANS[next].bytes.get() };
+    let mut len = 0;
+    for (fd, requests) in planned.iter() {
+        let end = len + 8 + requests.len();
+        if end > PLAN_CAPACITY {
+            log::warn!("too many changes to undo on fatal signals");
+            break;
+        }
+        buffer[len..len + 4].copy_from_slice(&fd.to_ne_bytes());
+        buffer[len + 4..len + 8].copy_from_slice(&(requests.len() as u32).to_ne_bytes());
+        buffer[len + 8..end].copy_from_slice(requests);
+        len = end;
+    }
+    PLANS[next].len.store(len, Ordering::Release);
+    ACTIVE.store(next, Ordering::Release);
+}
+
+fn randr_opcode(conn: &XConnection) -> Option<u8> {
+    let (mut opcode, mut event, mut error) = (0, 0, 0);
+    let found = unsafe {
+        xlib::XQueryExtension(
+            conn.display,
+            b"RANDR\0".as_ptr() as *const _,
+            &mut opcode,
+            &mut event,
+            &mut error,
+        )
+    };
+    (found != 0).then_some(opcode as u8)
+}
+
+// The request in the byte order of the client, which Xlib announced as
+// the native one when connecting
+fn serialize(undo: &Undo, randr_opcode: Option<u8>, out: &mut Vec<u8>) {
+    match undo {
+        Undo::UngrabKeyboard | Undo::UngrabPointer => {
+            let opcode = match undo {
+                Undo::UngrabKeyboard => UNGRAB_KEYBOARD,
+                _ => UNGRAB_POINTER,
+            };
+            out.extend_from_slice(&[opcode, 0]);
+            out.extend_from_slice(&2u16.to_ne_bytes());
+            // CurrentTime
+            out.extend_from_slice(&0u32.to_ne_bytes());
+        }
+        Undo::RestoreCrtc(config) => {
+            let opcode = match randr_opcode {
+                Some(opcode) => opcode,
+                None => return,
+            };
+            out.extend_from_slice(&[opcode, RR_SET_CRTC_CONFIG]);
+            out.extend_from_slice(&(7 + config.outputs.len() as u16).to_ne_bytes());
+            out.extend_from_slice(&(config.crtc as u32).to_ne_bytes());
+            out.extend_from_slice(&0u32.to_ne_bytes());
+            out.extend_from_slice(&(config.config_timestamp as u32).to_ne_bytes());
+            out.extend_from_slice(&(config.position.0 as i16).to_ne_bytes());
+            out.extend_from_slice(&(config.position.1 as i16).to_ne_bytes());
+            out.extend_from_slice(&(config.mode as u32).to_ne_bytes());
+            out.extend_from_slice(&config.rotation.to_ne_bytes());
+            out.extend_from_slice(&[0, 0]);
+            for &output in &config.outputs {
+                out.extend_from_slice(&(output as u32).to_ne_bytes());
+            }
+        }
+    }
+}
+
+// Serialized requests by socket, as (fd, length, requests) records. The
+// handler reads the active buffer while publish fills the other one
+const PLAN_CAPACITY: usize = 4096;
+
+struct Plan {
+    len: AtomicUsize,
+    bytes: UnsafeCell<[u8; PLAN_CAPACITY]>,
+}
+
+unsafe impl Sync for Plan {}
+
+impl Plan {
+    const fn new() -> Plan {
+        Plan {
+            len: AtomicUsize::new(0),
+            bytes: UnsafeCell::new([0; PLAN_CAPACITY]),
+        }
+    }
+}
+
+static PLANS: [Plan; 2] = [Plan::new(), Plan::new()];
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+static PLANNED: Mutex<Vec<(RawFd, Vec<u8>)>> = Mutex::new(Vec::new());
+static CRASHED: AtomicBool = AtomicBool::new(false);
+// Actions replaced by on_fatal_signal, in the order of FATAL_SIGNALS
+static PREVIOUS: OnceLock<[libc::sigaction; 3]> = OnceLock::new();
+
+fn install_handlers() {
+    PREVIOUS.get_or_init(|| unsafe {
+        let mut previous: [libc::sigaction; 3] = mem::zeroed();
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = on_fatal_signal as extern "C" fn(c_int) as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        for (signal, previous) in FATAL_SIGNALS.iter().zip(previous.iter_mut()) {
+            libc::sigaction(*signal, &action, previous);
+        }
+        previous
+    });
+}
+
+// Only async-signal-safe calls from here on
+extern "C" fn on_fatal_signal(signal: c_int) {
+    unsafe {
+        let errno = *libc::__errno_location();
+        if !CRASHED.swap(true, Ordering::AcqRel) {
+            write_plan();
+        }
+        // Raised again once the handler returns, with the action it had
+        // before, usually ending the process
+        if let Some(previous) = PREVIOUS.get() {
+            if let Some(index) = FATAL_SIGNALS.iter().position(|&fatal| fatal == signal) {
+                libc::sigaction(signal, &previous[index], std::ptr::null_mut());
+            }
+        }
+        libc::raise(signal);
+        *libc::__errno_location() = errno;
+    }
+}
+
+unsafe fn write_plan() {
+    let plan = &PLANS[ACTIVE.load(Ordering::Acquire)];
+    let len = plan.len.load(Ordering::Acquire);
+    let bytes = &*plan.bytes.get();
+    let mut at = 0;
+    while at + 8 <= len {
+        let fd = i32::from_ne_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+        let count =
+            u32::from_ne_bytes([bytes[at + 4], bytes[at + 5], bytes[at + 6], bytes[at + 7]]);
+        let end = (at + 8 + count as usize).min(len);
+        write_all(fd, &bytes[at + 8..end]);
+        at = end;
+    }
+}
+
+unsafe fn write_all(fd: RawFd, mut bytes: &[u8]) {
+    while !bytes.is_empty() {
+        let written = libc::write(fd, bytes.as_ptr() as *const _, bytes.len());
+        if written > 0 {
+            bytes = &bytes[written as usize..];
+        } else if written < 0 && *libc::__errno_location() == libc::EINTR {
+            continue;
+        } else {
+            return;
+        }
+    }
+}
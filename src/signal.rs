@@ -0,0 +1,76 @@
+//! SIGTERM and SIGINT forwarded to the event loop through a self-pipe,
+//! and SIGUSR1 asking for the loop's metrics through a flag
+
+use std::os::raw::c_int;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{Once, OnceLock};
+
+// Write end of the pipe, read by the async-signal-safe handler
+static WRITE_FD: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn on_signal(_signal: c_int) {
+    unsafe {
+        let errno = *libc::__errno_location();
+        let fd = WRITE_FD.load(Ordering::Relaxed);
+        if fd >= 0 {
+            libc::write(fd, [1u8].as_ptr() as *const _, 1);
+        }
+        *libc::__errno_location() = errno;
+    }
+}
+
+/// Install the handlers once per process, returning the read end of the
+/// pipe they write to
+pub(crate) fn install() -> RawFd {
+    static READ_FD: OnceLock<RawFd> = OnceLock::new();
+    *READ_FD.get_or_init(|| unsafe {
+        let mut fds = [0; 2];
+        if libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) != 0 {
+            panic!("failed to create signal pipe");
+        }
+        WRITE_FD.store(fds[1], Ordering::Relaxed);
+
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_signal as extern "C" fn(c_int) as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGTERM, &action, std::ptr::null_mut());
+        libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut());
+        fds[0]
+    })
+}
+
+// Set by SIGUSR1, the loop prints its metrics on the next poll
+static METRICS_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_metrics_signal(_signal: c_int) {
+    METRICS_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Install the SIGUSR1 handler once per process
+pub(crate) fn install_metrics() {
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_metrics_signal as extern "C" fn(c_int) as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGUSR1, &action, std::ptr::null_mut());
+    });
+}
+
+/// True when SIGUSR1 arrived since the last call
+pub(crate) fn take_metrics_request() -> bool {
+    METRICS_REQUESTED.swap(false, Ordering::Relaxed)
+}
+
+/// Drain the pipe, true when a signal arrived since the last call
+pub(crate) fn take(fd: RawFd) -> bool {
+    let mut received = false;
+    let mut buf = [0u8; 16];
+    while unsafe { libc::read(fd, buf.as_mut_ptr() as *mut _, buf.len()) } > 0 {
+        received = true;
+    }
+    received
+}
@@ -0,0 +1,147 @@
+//! Suspend and resume notifications from systemd-logind
+
+use std::io;
+use std::os::unix::io::{OwnedFd, RawFd};
+
+use crate::dbus::{Connection, Message, Value};
+use crate::event::Event;
+
+const LOGIN1: &str = "org.freedesktop.login1";
+const LOGIN1_PATH: &str = "/org/freedesktop/login1";
+const MANAGER: &str = "org.freedesktop.login1.Manager";
+
+/// Watches PrepareForSleep on the system bus, holding a delay inhibitor
+/// lock so logind waits for the application before suspending
+pub(crate) struct SleepMonitor {
+    bus: Connection,
+    inhibitor: Option<OwnedFd>,
+    // Set after delivering Suspending, the lock is released on the next
+    // poll once the application had a frame to finish its GPU work
+    release_pending: bool,
+}
+
+impl SleepMonitor {
+    pub fn connect() -> io::Result<SleepMonitor> {
+        let mut bus = Connection::system()?;
+        bus.add_match(&format!(
+            "type='signal',interface='{}',member='PrepareForSleep',path='{}'",
+            MANAGER, LOGIN1_PATH
+        ))?;
+        let mut monitor = SleepMonitor {
+            bus,
+            inhibitor: None,
+            release_pending: false,
+        };
+        monitor.inhibit();
+        Ok(monitor)
+    }
+
+    /// Take a delay lock, logind delays sleep until it is closed or
+    /// its InhibitDelayMaxSec passes
+    fn inhibit(&mut self) {
+        let call = Message::method_call(
+            LOGIN1,
+            LOGIN1_PATH,
+            MANAGER,
+            "Inhibit",
+            vec![
+                Value::Str("sleep".into()),
+                Value::Str("daswin".into()),
+                Value::Str("Finishing GPU work before suspend".into()),
+                Value::Str("delay".into()),
+            ],
+        );
+        match self.bus.call(call) {
+            Ok(mut reply) => match reply.body.first() {
+                Some(&Value::Fd(index)) if (index as usize) < reply.fds.len() => {
+                    self.inhibitor = Some(reply.fds.swap_remove(index as usize));
+                }
+                _ => log::warn!("logind returned no inhibitor lock"),
+            },
+            Err(err) => log::warn!("failed to take a sleep inhibitor lock: {}", err),
+        }
+    }
+
+    /// The bus socket, readable once a signal arrives
+    pub fn fd(&self) -> RawFd {
+        self.bus.fd()
+    }
+
+    /// Translate the PrepareForSleep signals received since the last poll
+    pub fn poll(&mut self, events: &mut Vec<Event>) -> io::Result<()> {
+        if self.release_pending {
+            self.inhibitor = None;
+            self.release_pending = false;
+        }
+        while let Some(message) = self.bus.poll_message()? {
+            match prepare_for_sleep(&message) {
+                Some(true) => {
+                    events.push(Event::Suspending);
+                    self.release_pending = true;
+                }
+                Some(false) => {
+                    events.push(Event::Resumed);
+                    self.release_pending = false;
+                    if self.inhibitor.is_none() {
+                        self.inhibit();
+                    }
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `message` announces sleep or resume, None for other messages
+fn prepare_for_sleep(message: &Message) -> Option<bool> {
+    if !message.is_signal(MANAGER, "PrepareForSleep") {
+        return None;
+    }
+    match message.body.first() {
+        Some(&Value::Bool(sleeping)) => Some(sleeping),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(member: &str, body: Vec<Value>) -> Message {
+        Message::signal(LOGIN1_PATH, MANAGER, member, body)
+    }
+
+    #[test]
+    fn prepare_for_sleep_signals() {
+        assert_eq!(
+            prepare_for_sleep(&signal("PrepareForSleep", vec![Value::Bool(true)])),
+            Some(true)
+        );
+        assert_eq!(
+            prepare_for_sleep(&signal("PrepareForSleep", vec![Value::Bool(false)])),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn other_messages_are_ignored() {
+        assert_eq!(
+            prepare_for_sleep(&signal("PrepareForShutdown", vec![Value::Bool(true)])),
+            None
+        );
+        assert_eq!(
+            prepare_for_sleep(&signal("PrepareForSleep", vec![Value::U32(1)])),
+            None
+        );
+        assert_eq!(prepare_for_sleep(&signal("PrepareForSleep", vec![])), None);
+        let call = Message::method_call(
+            LOGIN1,
+            LOGIN1_PATH,
+            MANAGER,
+            "PrepareForSleep",
+            vec![Value::Bool(true)],
+        );
+        assert_eq!(prepare_for_sleep(&call), None);
+    }
+}
@@ -0,0 +1,185 @@
+//! Snapping windows dragged with X11Window::begin_manual_drag to the
+//! edges of work areas and of the loop's other windows
+
+use std::mem;
+
+use x11::xlib;
+
+use crate::event::Edge;
+use crate::window::Rect;
+use crate::xconn::XConnection;
+
+/// How windows dragged with X11Window::begin_manual_drag snap, see
+/// EventLoop::set_snapping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Snapping {
+    /// Distance in physical pixels from which an edge snaps
+    pub threshold: u32,
+    /// Modifier mask, e.g. xlib::ShiftMask, snapping is off while any of
+    /// these modifiers is held
+    pub bypass: u32,
+}
+
+impl Default for Snapping {
+    /// 16 pixels, off while Shift is held
+    fn default() -> Snapping {
+        Snapping {
+            threshold: 16,
+            bypass: xlib::ShiftMask,
+        }
+    }
+}
+
+/// A drag started by begin_manual_drag
+pub(crate) struct ManualDrag {
+    // Pointer position relative to the window when the drag started
+    pub grab: (i32, i32),
+    // Edges to snap to, gathered with the first motion
+    pub targets: Option<Targets>,
+    // Edges of the window snapped by the last motion, horizontally and
+    // vertically
+    pub snapped: (Option<Edge>, Option<Edge>),
+}
+
+impl ManualDrag {
+    pub fn new(grab: (i32, i32)) -> ManualDrag {
+        ManualDrag {
+            grab,
+            targets: None,
+            snapped: (None, None),
+        }
+    }
+
+    /// Remember the edges snapped now, returning those which were not
+    /// snapped before
+    pub fn engage(&mut self, snapped: (Option<Edge>, Option<Edge>)) -> Vec<Edge> {
+        let before = mem::replace(&mut self.snapped, snapped);
+        [(before.0, snapped.0), (before.1, snapped.1)]
+            .iter()
+            .filter(|(before, now)| before != now)
+            .filter_map(|&(_, now)| now)
+            .collect()
+    }
+}
+
+/// Edges in root coordinates: the work area of every monitor, snapped to
+/// from inside, and other windows, snapped to from outside
+pub(crate) struct Targets {
+    pub areas: Vec<Rect>,
+    pub windows: Vec<Rect>,
+}
+
+/// Move `rect` onto the nearest edges within `threshold`, returning its
+/// new top left corner and the edges of it which snapped
+pub(crate) fn snap(
+    rect: Rect,
+    targets: &Targets,
+    threshold: u32,
+) -> ((i32, i32), (Option<Edge>, Option<Edge>)) {
+    let (left, top, right, bottom) = edges(rect);
+    let mut x = Nearest::new(threshold);
+    let mut y = Nearest::new(threshold);
+    for &area in &targets.areas {
+        let (area_left, area_top, area_right, area_bottom) = edges(area);
+        if overlaps((top, bottom), (area_top, area_bottom)) {
+            x.consider(area_left - left, Edge::Left);
+            x.consider(area_right - right, Edge::Right);
+        }
+        if overlaps((left, right), (area_left, area_right)) {
+            y.consider(area_top - top, Edge::Top);
+            y.consider(area_bottom - bottom, Edge::Bottom);
+        }
+    }
+    for &window in &targets.windows {
+        let (window_left, window_top, window_right, window_bottom) = edges(window);
+        if overlaps((top, bottom), (window_top, window_bottom)) {
+            x.consider(window_right - left, Edge::Left);
+            x.consider(window_left - right, Edge::Right);
+        }
+        if overlaps((left, right), (window_left, window_right)) {
+            y.consider(window_bottom - top, Edge::Top);
+            y.consider(window_top - bottom, Edge::Bottom);
+        }
+    }
+    let position = (
+        rect.x + x.offset().unwrap_or(0) as i32,
+        rect.y + y.offset().unwrap_or(0) as i32,
+    );
+    (position, (x.edge(), y.edge()))
+}
+
+/// The root relative bounds of a window while it is viewable
+pub(crate) fn viewable_rect(conn: &XConnection, window: xlib::Window) -> Option<Rect> {
+    unsafe {
+        let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+        if xlib::XGetWindowAttributes(conn.display, window, &mut attributes) == 0
+            || attributes.map_state != xlib::IsViewable
+        {
+            return None;
+        }
+        let (mut x, mut y, mut child) = (0, 0, 0);
+        xlib::XTranslateCoordinates(
+            conn.display,
+            window,
+            conn.root,
+            0,
+            0,
+            &mut x,
+            &mut y,
+            &mut child,
+        );
+        Some(Rect {
+            x,
+            y,
+            width: attributes.width as u32,
+            height: attributes.height as u32,
+        })
+    }
+}
+
+// The closest edge within the threshold along one axis
+struct Nearest {
+    threshold: i64,
+    best: Option<(i64, Edge)>,
+}
+
+impl Nearest {
+    fn new(threshold: u32) -> Nearest {
+        Nearest {
+            threshold: threshold as i64,
+            best: None,
+        }
+    }
+
+    fn consider(&mut self, offset: i64, edge: Edge) {
+        let closer = match self.best {
+            Some((best, _)) => offset.abs() < best.abs(),
+            None => true,
+        };
+        if offset.abs() <= self.threshold && closer {
+            self.best = Some((offset, edge));
+        }
+    }
+
+    fn offset(&self) -> Option<i64> {
+        self.best.map(|(offset, _)| offset)
+    }
+
+    fn edge(&self) -> Option<Edge> {
+        self.best.map(|(_, edge)| edge)
+    }
+}
+
+fn edges(rect: Rect) -> (i64, i64, i64, i64) {
+    let (left, top) = (rect.x as i64, rect.y as i64);
+    (
+        left,
+        top,
+        left + rect.width as i64,
+        top + rect.height as i64,
+    )
+}
+
+fn overlaps((start, end): (i64, i64), (other_start, other_end): (i64, i64)) -> bool {
+    start < other_end && other_start < end
+}
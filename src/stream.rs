@@ -0,0 +1,335 @@
+//! Events as a stream for async applications, see EventLoop::event_stream.
+//!
+//! A watcher thread waits on the fds EventLoop::run blocks on and a stop
+//! pipe with poll(2) while the stream is idle and wakes the task. Channel
+//! sends wake it directly. Nothing depends on an executor, the
+//! stream works with any of them
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::event::Event;
+use crate::event_loop::{EventLoop, PumpStatus};
+use crate::wake;
+
+/// The events of a loop as they arrive, see EventLoop::event_stream.
+///
+/// Batches are delivered as poll_events makes them, ending with
+/// AboutToWait. Batches holding nothing else are skipped while the task
+/// sleeps. The stream ends once pump_events returns Exit
+pub struct EventStream<'a> {
+    event_loop: &'a mut EventLoop,
+    buffer: Buffer,
+    watch: Arc<Watch>,
+    // Write end of the pipe stopping the watcher
+    stop: RawFd,
+    watcher: Option<JoinHandle<()>>,
+}
+
+#[derive(Default)]
+struct Watch {
+    state: Mutex<WatchState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct WatchState {
+    waker: Option<Waker>,
+    // Set when the stream went idle, with how long until a timer of the
+    // loop is due
+    armed: Option<Option<Duration>>,
+    stopped: bool,
+}
+
+impl<'a> EventStream<'a> {
+    pub(crate) fn new(event_loop: &'a mut EventLoop, fds: Vec<RawFd>) -> EventStream<'a> {
+        let mut pipe = [0; 2];
+        unsafe {
+            if libc::pipe2(pipe.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) != 0 {
+                panic!("failed to create event stream stop pipe");
+            }
+        }
+        let watch = Arc::new(Watch::default());
+        let watcher = {
+            let watch = watch.clone();
+            thread::Builder::new()
+                .name("daswin-stream".into())
+                .spawn(move || run_watcher(&watch, fds, pipe[0]))
+                .expect("failed to spawn event stream watcher")
+        };
+        EventStream {
+            event_loop,
+            buffer: Buffer::default(),
+            watch,
+            stop: pipe[1],
+            watcher: Some(watcher),
+        }
+    }
+
+    /// The loop, e.g. to drain channels or open windows while streaming
+    pub fn event_loop(&mut self) -> &mut EventLoop {
+        self.event_loop
+    }
+
+    /// The next event, None once the loop exited.
+    ///
+    /// Cancel safe: an event is only taken from the stream when the
+    /// future completes, dropping it beforehand loses nothing
+    pub fn next_event(&mut self) -> NextEvent<'_, 'a> {
+        NextEvent { stream: self }
+    }
+
+    /// Poll for the next event, as with futures' Stream::poll_next
+    pub fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<Event>> {
+        // Registered before pumping, so a send in between wakes the task
+        // rather than being missed
+        self.register(cx.waker());
+        let event_loop = &mut *self.event_loop;
+        match self.buffer.next(|batch| event_loop.pump_events(batch)) {
+            Next::Event(event) => Poll::Ready(Some(event)),
+            Next::Ended => Poll::Ready(None),
+            Next::Idle => {
+                self.event_loop.flush();
+                self.arm(self.event_loop.next_timer());
+                Poll::Pending
+            }
+        }
+    }
+
+    fn register(&self, waker: &Waker) {
+        let mut state = self.watch.state.lock().unwrap();
+        if !matches!(&state.waker, Some(registered) if registered.will_wake(waker)) {
+            state.waker = Some(waker.clone());
+            self.event_loop.channels().set_listener(Some(waker.clone()));
+        }
+    }
+
+    fn arm(&self, timeout: Option<Duration>) {
+        self.watch.state.lock().unwrap().armed = Some(timeout);
+        self.watch.changed.notify_one();
+    }
+}
+
+impl Drop for EventStream<'_> {
+    fn drop(&mut self) {
+        self.watch.state.lock().unwrap().stopped = true;
+        self.watch.changed.notify_one();
+        unsafe {
+            libc::write(self.stop, [1u8].as_ptr() as *const _, 1);
+        }
+        if let Some(watcher) = self.watcher.take() {
+            let _ = watcher.join();
+        }
+        unsafe {
+            libc::close(self.stop);
+        }
+        self.event_loop.channels().set_listener(None);
+    }
+}
+
+/// The events of the batches pumped, handed out one at a time. They stay
+/// buffered until taken, so dropping a future polling them loses nothing
+#[derive(Default)]
+struct Buffer {
+    batch: Vec<Event>,
+    buffered: VecDeque<Event>,
+    exited: bool,
+}
+
+enum Next {
+    Event(Event),
+    Ended,
+    // The batch pumped held nothing but AboutToWait
+    Idle,
+}
+
+impl Buffer {
+    /// Take the next event, pumping batches with `pump` while none is
+    /// buffered
+    fn next(&mut self, mut pump: impl FnMut(&mut Vec<Event>) -> PumpStatus) -> Next {
+        loop {
+            if let Some(event) = self.buffered.pop_front() {
+                return Next::Event(event);
+            }
+            if self.exited {
+                return Next::Ended;
+            }
+            let status = pump(&mut self.batch);
+            let idle = matches!(self.batch[..], [Event::AboutToWait]);
+            if let PumpStatus::Exit { .. } = status {
+                self.exited = true;
+            } else if idle {
+                return Next::Idle;
+            }
+            self.buffered.extend(self.batch.drain(..));
+        }
+    }
+}
+
+/// The future of EventStream::next_event
+pub struct NextEvent<'s, 'a> {
+    stream: &'s mut EventStream<'a>,
+}
+
+impl Future for NextEvent<'_, '_> {
+    type Output = Option<Event>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Event>> {
+        self.stream.poll_next(cx)
+    }
+}
+
+// Wait until armed, then until one of the fds is readable or the timeout
+// passed, and wake the task. Readable fds are left to the loop to read
+fn run_watcher(watch: &Watch, mut fds: Vec<RawFd>, stop: RawFd) {
+    fds.push(stop);
+    let mut pollfds: Vec<libc::pollfd> = fds
+        .iter()
+        .map(|&fd| libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        })
+        .collect();
+    loop {
+        let timeout = {
+            let mut state = watch.state.lock().unwrap();
+            while state.armed.is_none() && !state.stopped {
+                state = watch.changed.wait(state).unwrap();
+            }
+            if state.stopped {
+                break;
+            }
+            state.armed.take().unwrap()
+        };
+        unsafe {
+            libc::poll(
+                pollfds.as_mut_ptr(),
+                pollfds.len() as libc::nfds_t,
+                wake::timeout_ms(timeout),
+            );
+        }
+        if pollfds.last().unwrap().revents != 0 {
+            break;
+        }
+        let waker = watch.state.lock().unwrap().waker.clone();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+    unsafe {
+        libc::close(stop);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+    use std::pin::pin;
+    use std::task::Waker;
+
+    use super::*;
+    use crate::event::ChannelId;
+
+    type Batches = VecDeque<(Vec<Event>, PumpStatus)>;
+
+    fn sent(channel: u32) -> Event {
+        Event::Channel(ChannelId(channel))
+    }
+
+    // next_event over `buffer`, pumping `batches` in turn and idle batches
+    // once they ran out
+    fn next_event<'b>(
+        buffer: &'b mut Buffer,
+        batches: &'b mut Batches,
+    ) -> impl Future<Output = Option<Event>> + 'b {
+        poll_fn(move |_| {
+            let pump = |batch: &mut Vec<Event>| {
+                batch.clear();
+                match batches.pop_front() {
+                    Some((events, status)) => {
+                        batch.extend(events);
+                        status
+                    }
+                    None => {
+                        batch.push(Event::AboutToWait);
+                        PumpStatus::Continue
+                    }
+                }
+            };
+            match buffer.next(pump) {
+                Next::Event(event) => Poll::Ready(Some(event)),
+                Next::Ended => Poll::Ready(None),
+                Next::Idle => Poll::Pending,
+            }
+        })
+    }
+
+    fn poll<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    fn drain(buffer: &mut Buffer, batches: &mut Batches) -> Vec<Event> {
+        let mut events = Vec::new();
+        while let Poll::Ready(Some(event)) = poll(pin!(next_event(buffer, batches))) {
+            events.push(event);
+        }
+        events
+    }
+
+    #[test]
+    fn dropped_future_loses_no_buffered_event() {
+        let mut buffer = Buffer::default();
+        let batch = vec![sent(1), sent(2), sent(3), Event::AboutToWait];
+        let mut batches: Batches = vec![(batch, PumpStatus::Continue)].into();
+
+        let first = poll(pin!(next_event(&mut buffer, &mut batches)));
+        assert_eq!(first, Poll::Ready(Some(sent(1))));
+        // Cancelled, e.g. by losing a select, before it was polled
+        drop(next_event(&mut buffer, &mut batches));
+
+        let rest = drain(&mut buffer, &mut batches);
+        assert_eq!(rest, [sent(2), sent(3), Event::AboutToWait]);
+    }
+
+    #[test]
+    fn dropped_pending_future_loses_nothing() {
+        let mut buffer = Buffer::default();
+        let mut batches = Batches::new();
+        {
+            let mut pending = pin!(next_event(&mut buffer, &mut batches));
+            assert_eq!(poll(pending.as_mut()), Poll::Pending);
+        }
+        let batch = vec![sent(1), sent(2), Event::AboutToWait];
+        batches.push_back((batch, PumpStatus::Continue));
+        let batch = vec![sent(3), Event::AboutToWait];
+        batches.push_back((batch, PumpStatus::Continue));
+
+        let events = drain(&mut buffer, &mut batches);
+        let expected = [
+            sent(1),
+            sent(2),
+            Event::AboutToWait,
+            sent(3),
+            Event::AboutToWait,
+        ];
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn ends_after_the_last_batch() {
+        let mut buffer = Buffer::default();
+        let batch = vec![Event::ExitRequested, Event::AboutToWait];
+        let mut batches: Batches = vec![(batch, PumpStatus::Exit { code: 0 })].into();
+        let events = drain(&mut buffer, &mut batches);
+        assert_eq!(events, [Event::ExitRequested, Event::AboutToWait]);
+        let end = poll(pin!(next_event(&mut buffer, &mut batches)));
+        assert_eq!(end, Poll::Ready(None));
+    }
+}
@@ -0,0 +1,207 @@
+//! Pen tablets, found through the labels of XInput2 valuators
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::slice;
+
+use x11::{xinput2, xlib};
+
+use crate::event::TabletTool;
+use crate::ext;
+use crate::xconn::XConnection;
+
+/// A valuator and the range it reports in
+#[derive(Clone, Copy)]
+pub(crate) struct Axis {
+    number: i32,
+    min: f64,
+    max: f64,
+}
+
+impl Axis {
+    /// The value scaled to 0..1
+    fn normalize(self, value: f64) -> f32 {
+        if self.max > self.min {
+            ((value - self.min) / (self.max - self.min)).clamp(0.0, 1.0) as f32
+        } else {
+            0.0
+        }
+    }
+
+    /// The latest value of the axis if the event carries one
+    unsafe fn read(self, state: &xinput2::XIValuatorState) -> Option<f32> {
+        let value = valuator(state, self.number)?;
+        Some(self.normalize(value))
+    }
+}
+
+/// A slave pointer device with a pressure axis
+pub(crate) struct Tablet {
+    pub tool: TabletTool,
+    pressure_axis: Axis,
+    tilt_axes: (Option<Axis>, Option<Axis>),
+
+    // Events only carry the axes which changed
+    pub pressure: f32,
+    pub tilt: (f32, f32),
+    // Whether the pen touches the tablet, pressure alone can be 0 both
+    // while hovering and on a light touch
+    pub contact: bool,
+}
+
+impl Tablet {
+    /// Update the axes from an event's valuators
+    pub unsafe fn update(&mut self, state: &xinput2::XIValuatorState) {
+        if let Some(pressure) = self.pressure_axis.read(state) {
+            self.pressure = pressure;
+        }
+        // Tilt is reported from -1 to 1 across the device's range
+        if let Some(x) = self.tilt_axes.0.and_then(|axis| axis.read(state)) {
+            self.tilt.0 = x * 2.0 - 1.0;
+        }
+        if let Some(y) = self.tilt_axes.1.and_then(|axis| axis.read(state)) {
+            self.tilt.1 = y * 2.0 - 1.0;
+        }
+    }
+}
+
+/// The value of a valuator, `values` only holds those set in the mask
+pub(crate) unsafe fn valuator(state: &xinput2::XIValuatorState, number: i32) -> Option<f64> {
+    let mask = slice::from_raw_parts(state.mask, state.mask_len as usize);
+    if number < 0 || number >= mask.len() as i32 * 8 || !xinput2::XIMaskIsSet(mask, number) {
+        return None;
+    }
+    let index = (0..number)
+        .filter(|&axis| xinput2::XIMaskIsSet(mask, axis))
+        .count();
+    Some(*state.values.add(index))
+}
+
+/// Enumerate the enabled pointer devices reporting pressure, by device id
+pub(crate) fn query_tablets(conn: &XConnection) -> HashMap<i32, Tablet> {
+    let mut tablets = HashMap::new();
+    let xinput2 = match ext::xinput2() {
+        Some(xinput2) => xinput2,
+        None => return tablets,
+    };
+
+    unsafe {
+        let mut count = 0;
+        let info = (xinput2.XIQueryDevice)(conn.display, xinput2::XIAllDevices, &mut count);
+        if info.is_null() {
+            return tablets;
+        }
+
+        for device in slice::from_raw_parts(info, count as usize) {
+            if device._use != xinput2::XISlavePointer || device.enabled == 0 {
+                continue;
+            }
+
+            let (mut pressure, mut tilt_x, mut tilt_y) = (None, None, None);
+            for &class in slice::from_raw_parts(device.classes, device.num_classes as usize) {
+                if (*class)._type != xinput2::XIValuatorClass {
+                    continue;
+                }
+                let class = &*(class as *const xinput2::XIValuatorClassInfo);
+                let axis = Some(Axis {
+                    number: class.number,
+                    min: class.min,
+                    max: class.max,
+                });
+                if class.label == conn.atoms.abs_pressure {
+                    pressure = axis;
+                } else if class.label == conn.atoms.abs_tilt_x {
+                    tilt_x = axis;
+                } else if class.label == conn.atoms.abs_tilt_y {
+                    tilt_y = axis;
+                }
+            }
+
+            if let Some(pressure_axis) = pressure {
+                // Drivers expose the eraser end as a device of its own
+                let name = CStr::from_ptr(device.name).to_string_lossy();
+                let tool = if name.to_lowercase().contains("eraser") {
+                    TabletTool::Eraser
+                } else {
+                    TabletTool::Pen
+                };
+                tablets.insert(
+                    device.deviceid,
+                    Tablet {
+                        tool,
+                        pressure_axis,
+                        tilt_axes: (tilt_x, tilt_y),
+                        pressure: 0.0,
+                        tilt: (0.0, 0.0),
+                        contact: false,
+                    },
+                );
+            }
+        }
+        (xinput2.XIFreeDeviceInfo)(info);
+    }
+    tablets
+}
+
+/// Select or deselect pen motion and buttons from the tablets on a window
+pub(crate) fn select_tablet_events(
+    conn: &XConnection,
+    window: xlib::Window,
+    devices: &[i32],
+    enabled: bool,
+) {
+    let xinput2 = match ext::xinput2() {
+        Some(xinput2) => xinput2,
+        None => return,
+    };
+
+    let mut mask = [0u8; (xinput2::XI_Motion as usize >> 3) + 1];
+    if enabled {
+        for &event in &[
+            xinput2::XI_Motion,
+            xinput2::XI_ButtonPress,
+            xinput2::XI_ButtonRelease,
+        ] {
+            xinput2::XISetMask(&mut mask, event);
+        }
+    }
+    let mut event_masks: Vec<_> = devices
+        .iter()
+        .map(|&deviceid| xinput2::XIEventMask {
+            deviceid,
+            mask_len: mask.len() as i32,
+            mask: mask.as_mut_ptr(),
+        })
+        .collect();
+    if event_masks.is_empty() {
+        return;
+    }
+    unsafe {
+        (xinput2.XISelectEvents)(
+            conn.display,
+            window,
+            event_masks.as_mut_ptr(),
+            event_masks.len() as i32,
+        );
+        xlib::XFlush(conn.display);
+    }
+}
+
+/// Get notified when devices are added or removed
+pub(crate) fn select_hierarchy_events(conn: &XConnection) {
+    let xinput2 = match ext::xinput2() {
+        Some(xinput2) => xinput2,
+        None => return,
+    };
+
+    let mut mask = [0u8; (xinput2::XI_HierarchyChanged as usize >> 3) + 1];
+    xinput2::XISetMask(&mut mask, xinput2::XI_HierarchyChanged);
+    let mut event_mask = xinput2::XIEventMask {
+        deviceid: xinput2::XIAllDevices,
+        mask_len: mask.len() as i32,
+        mask: mask.as_mut_ptr(),
+    };
+    unsafe {
+        (xinput2.XISelectEvents)(conn.display, conn.root, &mut event_mask, 1);
+    }
+}
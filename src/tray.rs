@@ -0,0 +1,492 @@
+//! Tray icons with a menu, exported on the session bus following the
+//! StatusNotifierItem and dbusmenu specifications
+
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::io;
+use std::os::unix::io::RawFd;
+use std::process;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::dbus::{Connection, Message, MessageType, Value};
+use crate::dpi::PhysicalPosition;
+use crate::error::Error;
+use crate::event::Event;
+use crate::event_loop::EventLoop;
+use crate::icon::Icon;
+
+const WATCHER: &str = "org.kde.StatusNotifierWatcher";
+const WATCHER_PATH: &str = "/StatusNotifierWatcher";
+const ITEM: &str = "org.kde.StatusNotifierItem";
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const MENU: &str = "com.canonical.dbusmenu";
+const MENU_PATH: &str = "/MenuBar";
+const PROPERTIES: &str = "org.freedesktop.DBus.Properties";
+const BUS: &str = "org.freedesktop.DBus";
+
+const INTROSPECTION: &str = r#"<node>
+ <interface name="org.kde.StatusNotifierItem">
+  <method name="Activate"><arg type="i" direction="in"/><arg type="i" direction="in"/></method>
+  <method name="SecondaryActivate"><arg type="i" direction="in"/><arg type="i" direction="in"/></method>
+  <method name="ContextMenu"><arg type="i" direction="in"/><arg type="i" direction="in"/></method>
+  <method name="Scroll"><arg type="i" direction="in"/><arg type="s" direction="in"/></method>
+  <signal name="NewTitle"/>
+  <signal name="NewIcon"/>
+  <signal name="NewToolTip"/>
+ </interface>
+ <interface name="com.canonical.dbusmenu">
+  <method name="GetLayout"><arg type="i" direction="in"/><arg type="i" direction="in"/><arg type="as" direction="in"/><arg type="u" direction="out"/><arg type="(ia{sv}av)" direction="out"/></method>
+  <method name="GetGroupProperties"><arg type="ai" direction="in"/><arg type="as" direction="in"/><arg type="a(ia{sv})" direction="out"/></method>
+  <method name="GetProperty"><arg type="i" direction="in"/><arg type="s" direction="in"/><arg type="v" direction="out"/></method>
+  <method name="Event"><arg type="i" direction="in"/><arg type="s" direction="in"/><arg type="v" direction="in"/><arg type="u" direction="in"/></method>
+  <method name="EventGroup"><arg type="a(isvu)" direction="in"/><arg type="ai" direction="out"/></method>
+  <method name="AboutToShow"><arg type="i" direction="in"/><arg type="b" direction="out"/></method>
+  <method name="AboutToShowGroup"><arg type="ai" direction="in"/><arg type="ai" direction="out"/><arg type="ai" direction="out"/></method>
+  <signal name="LayoutUpdated"><arg type="u"/><arg type="i"/></signal>
+ </interface>
+</node>"#;
+
+/// An entry of a tray icon's menu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MenuItem {
+    id: u32,
+    label: String,
+    enabled: bool,
+    separator: bool,
+}
+
+impl MenuItem {
+    /// An item delivering Event::TrayMenuItem with `id` when clicked.
+    /// An underscore before a letter makes it the access key
+    pub fn new(id: u32, label: &str) -> MenuItem {
+        MenuItem {
+            id,
+            label: label.to_owned(),
+            enabled: true,
+            separator: false,
+        }
+    }
+
+    /// A line between groups of items
+    pub fn separator() -> MenuItem {
+        MenuItem {
+            separator: true,
+            ..MenuItem::new(0, "")
+        }
+    }
+
+    /// Shown greyed out, clicks are not delivered
+    pub fn disabled(mut self) -> MenuItem {
+        self.enabled = false;
+        self
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+/// The exported objects of one icon, polled by the EventLoop
+pub(crate) struct Tray {
+    bus: Connection,
+    service: String,
+    title: String,
+    tooltip: String,
+    icon: Icon,
+    menu: Vec<MenuItem>,
+    // Counted up with every menu change, hosts refetch older layouts
+    revision: u32,
+}
+
+impl Tray {
+    fn connect(title: &str, icon: &Icon) -> io::Result<Tray> {
+        static ICONS: AtomicU32 = AtomicU32::new(0);
+        let mut bus = Connection::session()?;
+        let service = format!(
+            "org.kde.StatusNotifierItem-{}-{}",
+            process::id(),
+            ICONS.fetch_add(1, Ordering::Relaxed) + 1
+        );
+        bus.request_name(&service)?;
+        // Register again when the tray restarts
+        bus.add_match(&format!(
+            "type='signal',sender='{}',interface='{}',member='NameOwnerChanged',arg0='{}'",
+            BUS, BUS, WATCHER
+        ))?;
+        let mut tray = Tray {
+            bus,
+            service,
+            title: title.to_owned(),
+            tooltip: String::new(),
+            icon: icon.clone(),
+            menu: Vec::new(),
+            revision: 1,
+        };
+        tray.register()?;
+        Ok(tray)
+    }
+
+    /// Announce the item to the watcher, failing when no tray shows
+    /// items
+    fn register(&mut self) -> io::Result<()> {
+        self.bus.call(Message::method_call(
+            WATCHER,
+            WATCHER_PATH,
+            WATCHER,
+            "RegisterStatusNotifierItem",
+            vec![Value::Str(self.service.clone())],
+        ))?;
+        let reply = self.bus.call(Message::method_call(
+            WATCHER,
+            WATCHER_PATH,
+            PROPERTIES,
+            "Get",
+            vec![
+                Value::Str(WATCHER.into()),
+                Value::Str("IsStatusNotifierHostRegistered".into()),
+            ],
+        ))?;
+        match reply.body.first() {
+            Some(Value::Variant(value)) if **value == Value::Bool(true) => Ok(()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no StatusNotifier host is registered",
+            )),
+        }
+    }
+
+    /// The bus socket, readable once a call arrives
+    pub fn fd(&self) -> RawFd {
+        self.bus.fd()
+    }
+
+    /// Answer the calls of the tray received since the last poll,
+    /// translating clicks
+    pub fn poll(&mut self, events: &mut Vec<Event>) -> io::Result<()> {
+        while let Some(message) = self.bus.poll_message()? {
+            if message.is_signal(BUS, "NameOwnerChanged") {
+                if matches!(message.body.get(2), Some(Value::Str(owner)) if !owner.is_empty()) {
+                    if let Err(err) = self.register() {
+                        log::warn!("failed to register the tray icon again: {}", err);
+                    }
+                }
+                continue;
+            }
+            if message.kind != MessageType::MethodCall {
+                continue;
+            }
+            let reply = match self.handle(&message, events) {
+                Some(body) => Message::method_return(&message, body),
+                None => Message::error(
+                    &message,
+                    "org.freedesktop.DBus.Error.UnknownMethod",
+                    "no such method",
+                ),
+            };
+            self.bus.send(&reply)?;
+        }
+        Ok(())
+    }
+
+    // The reply body of a method call, None for unknown methods
+    fn handle(&mut self, call: &Message, events: &mut Vec<Event>) -> Option<Vec<Value>> {
+        let path = call.path.as_deref()?;
+        let args = &call.body[..];
+        if call.is_method_call(PROPERTIES, "Get") {
+            let (interface, name) = match args {
+                [Value::Str(interface), Value::Str(name), ..] => (interface, name),
+                _ => return None,
+            };
+            let value = self
+                .properties(path, interface)?
+                .into_iter()
+                .find_map(|(key, value)| (key == name).then_some(value))?;
+            return Some(vec![Value::Variant(Box::new(value))]);
+        }
+        if call.is_method_call(PROPERTIES, "GetAll") {
+            let interface = match args {
+                [Value::Str(interface), ..] => interface,
+                _ => return None,
+            };
+            return Some(vec![dict(self.properties(path, interface)?)]);
+        }
+        if call.is_method_call("org.freedesktop.DBus.Introspectable", "Introspect") {
+            return Some(vec![Value::Str(INTROSPECTION.into())]);
+        }
+        if call.is_method_call("org.freedesktop.DBus.Peer", "Ping") {
+            return Some(Vec::new());
+        }
+
+        let member = call.member.as_deref()?;
+        match (path, call.interface.as_deref()?) {
+            (ITEM_PATH, ITEM) => {
+                let secondary = match member {
+                    "Activate" => false,
+                    "SecondaryActivate" => true,
+                    // The host shows the menu itself
+                    "ContextMenu" | "Scroll" => return Some(Vec::new()),
+                    _ => return None,
+                };
+                let position = match args {
+                    [Value::I32(x), Value::I32(y), ..] => PhysicalPosition::new(*x, *y),
+                    _ => PhysicalPosition::default(),
+                };
+                events.push(Event::TrayIconClicked {
+                    secondary,
+                    position,
+                });
+                Some(Vec::new())
+            }
+            (MENU_PATH, MENU) => self.handle_menu(member, args, events),
+            _ => None,
+        }
+    }
+
+    fn handle_menu(
+        &mut self,
+        member: &str,
+        args: &[Value],
+        events: &mut Vec<Event>,
+    ) -> Option<Vec<Value>> {
+        match member {
+            "GetLayout" => Some(vec![Value::U32(self.revision), self.layout()]),
+            "GetGroupProperties" => {
+                let ids = match args {
+                    [Value::Array(_, ids), ..] => ids.as_slice(),
+                    _ => return None,
+                };
+                // All items when no ids are given
+                let ids: Vec<i32> = if ids.is_empty() {
+                    (0..=self.menu.len() as i32).collect()
+                } else {
+                    ids.iter()
+                        .filter_map(|id| match id {
+                            Value::I32(id) => Some(*id),
+                            _ => None,
+                        })
+                        .collect()
+                };
+                let items = ids
+                    .into_iter()
+                    .map(|id| Value::Struct(vec![Value::I32(id), dict(self.item_properties(id))]))
+                    .collect();
+                Some(vec![Value::Array("(ia{sv})".into(), items)])
+            }
+            "GetProperty" => {
+                let (id, name) = match args {
+                    [Value::I32(id), Value::Str(name), ..] => (*id, name),
+                    _ => return None,
+                };
+                let value = self
+                    .item_properties(id)
+                    .into_iter()
+                    .find_map(|(key, value)| (key == name).then_some(value))?;
+                Some(vec![Value::Variant(Box::new(value))])
+            }
+            "Event" => {
+                if let [Value::I32(id), Value::Str(event), ..] = args {
+                    self.menu_event(*id, event, events);
+                }
+                Some(Vec::new())
+            }
+            "EventGroup" => {
+                if let [Value::Array(_, group), ..] = args {
+                    for event in group {
+                        if let Value::Struct(fields) = event {
+                            if let [Value::I32(id), Value::Str(event), ..] = &fields[..] {
+                                self.menu_event(*id, event, events);
+                            }
+                        }
+                    }
+                }
+                Some(vec![Value::Array("i".into(), Vec::new())])
+            }
+            "AboutToShow" => Some(vec![Value::Bool(false)]),
+            "AboutToShowGroup" => Some(vec![
+                Value::Array("i".into(), Vec::new()),
+                Value::Array("i".into(), Vec::new()),
+            ]),
+            _ => None,
+        }
+    }
+
+    fn menu_event(&self, id: i32, event: &str, events: &mut Vec<Event>) {
+        if event != "clicked" {
+            return;
+        }
+        match self.item(id) {
+            Some(item) if item.enabled && !item.separator => {
+                events.push(Event::TrayMenuItem { id: item.id });
+            }
+            _ => {}
+        }
+    }
+
+    // Menu items have their index plus one as dbusmenu id, the root is 0
+    fn item(&self, id: i32) -> Option<&MenuItem> {
+        self.menu.get(usize::try_from(id).ok()?.checked_sub(1)?)
+    }
+
+    fn item_properties(&self, id: i32) -> Vec<(&'static str, Value)> {
+        if id == 0 {
+            return vec![("children-display", Value::Str("submenu".into()))];
+        }
+        match self.item(id) {
+            Some(item) if item.separator => vec![("type", Value::Str("separator".into()))],
+            Some(item) => vec![
+                ("label", Value::Str(item.label.clone())),
+                ("enabled", Value::Bool(item.enabled)),
+            ],
+            None => Vec::new(),
+        }
+    }
+
+    /// The whole menu as a (ia{sv}av) tree, hosts ask for depth -1
+    fn layout(&self) -> Value {
+        let children = (1..=self.menu.len() as i32)
+            .map(|id| {
+                Value::Variant(Box::new(Value::Struct(vec![
+                    Value::I32(id),
+                    dict(self.item_properties(id)),
+                    Value::Array("v".into(), Vec::new()),
+                ])))
+            })
+            .collect();
+        Value::Struct(vec![
+            Value::I32(0),
+            dict(self.item_properties(0)),
+            Value::Array("v".into(), children),
+        ])
+    }
+
+    fn properties(&self, path: &str, interface: &str) -> Option<Vec<(&'static str, Value)>> {
+        match (path, interface) {
+            (ITEM_PATH, ITEM) => Some(vec![
+                ("Category", Value::Str("ApplicationStatus".into())),
+                ("Id", Value::Str(self.title.clone())),
+                ("Title", Value::Str(self.title.clone())),
+                ("Status", Value::Str("Active".into())),
+                ("WindowId", Value::I32(0)),
+                ("IconName", Value::Str(String::new())),
+                ("IconPixmap", pixmaps(&self.icon)),
+                (
+                    "ToolTip",
+                    Value::Struct(vec![
+                        Value::Str(String::new()),
+                        Value::Array("(iiay)".into(), Vec::new()),
+                        Value::Str(self.title.clone()),
+                        Value::Str(self.tooltip.clone()),
+                    ]),
+                ),
+                ("ItemIsMenu", Value::Bool(false)),
+                ("Menu", Value::Path(MENU_PATH.into())),
+            ]),
+            (MENU_PATH, MENU) => Some(vec![
+                ("Version", Value::U32(3)),
+                ("TextDirection", Value::Str("ltr".into())),
+                ("Status", Value::Str("normal".into())),
+                ("IconThemePath", Value::Array("s".into(), Vec::new())),
+            ]),
+            _ => None,
+        }
+    }
+
+    fn emit(&mut self, path: &str, interface: &str, member: &str, body: Vec<Value>) {
+        if let Err(err) = self
+            .bus
+            .send(&Message::signal(path, interface, member, body))
+        {
+            log::warn!("failed to update the tray icon: {}", err);
+        }
+    }
+}
+
+/// An icon in the system tray with a menu, e.g. for background tools.
+///
+/// Clicks on the icon and its menu items are delivered by the polls of
+/// the EventLoop the icon was created with, as Event::TrayIconClicked
+/// and Event::TrayMenuItem. Dropping the icon removes it
+pub struct StatusIcon {
+    tray: Rc<RefCell<Tray>>,
+}
+
+impl StatusIcon {
+    /// Show an icon in the tray.
+    ///
+    /// Fails with Error::Unsupported("StatusNotifier") without a session
+    /// bus or when no tray showing StatusNotifier items runs, e.g. on
+    /// desktops without one or with an XEmbed-only tray. Applications
+    /// should then keep a window open instead
+    pub fn new(event_loop: &EventLoop, title: &str, icon: &Icon) -> Result<StatusIcon, Error> {
+        let tray = Tray::connect(title, icon).map_err(|err| {
+            log::info!("no tray icon: {}", err);
+            Error::Unsupported("StatusNotifier")
+        })?;
+        let tray = Rc::new(RefCell::new(tray));
+        event_loop.register_status_icon(&tray);
+        Ok(StatusIcon { tray })
+    }
+
+    pub fn set_icon(&mut self, icon: &Icon) {
+        let mut tray = self.tray.borrow_mut();
+        tray.icon = icon.clone();
+        tray.emit(ITEM_PATH, ITEM, "NewIcon", Vec::new());
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        let mut tray = self.tray.borrow_mut();
+        tray.title = title.to_owned();
+        tray.emit(ITEM_PATH, ITEM, "NewTitle", Vec::new());
+    }
+
+    /// Text shown below the title when hovering the icon
+    pub fn set_tooltip(&mut self, text: &str) {
+        let mut tray = self.tray.borrow_mut();
+        tray.tooltip = text.to_owned();
+        tray.emit(ITEM_PATH, ITEM, "NewToolTip", Vec::new());
+    }
+
+    /// Replace the menu shown for the icon, empty by default
+    pub fn set_menu(&mut self, items: Vec<MenuItem>) {
+        let mut tray = self.tray.borrow_mut();
+        tray.menu = items;
+        tray.revision += 1;
+        let revision = tray.revision;
+        tray.emit(
+            MENU_PATH,
+            MENU,
+            "LayoutUpdated",
+            vec![Value::U32(revision), Value::I32(0)],
+        );
+    }
+}
+
+fn dict(entries: Vec<(&'static str, Value)>) -> Value {
+    let entries = entries
+        .into_iter()
+        .map(|(key, value)| {
+            Value::DictEntry(
+                Box::new(Value::Str(key.into())),
+                Box::new(Value::Variant(Box::new(value))),
+            )
+        })
+        .collect();
+    Value::Array("{sv}".into(), entries)
+}
+
+fn pixmaps(icon: &Icon) -> Value {
+    let pixels = icon.to_argb_be().into_iter().map(Value::Byte).collect();
+    Value::Array(
+        "(iiay)".into(),
+        vec![Value::Struct(vec![
+            Value::I32(icon.width() as i32),
+            Value::I32(icon.height() as i32),
+            Value::Array("y".into(), pixels),
+        ])],
+    )
+}
@@ -0,0 +1,81 @@
+//! A self-pipe waking the event loop while it blocks in run, written by
+//! channel sends, window proxies, wgpu error callbacks and the
+//! connection thread
+
+use std::os::unix::io::RawFd;
+use std::time::Duration;
+
+pub(crate) struct WakePipe {
+    read: RawFd,
+    write: RawFd,
+}
+
+impl WakePipe {
+    pub fn new() -> WakePipe {
+        let mut fds = [0; 2];
+        unsafe {
+            if libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) != 0 {
+                panic!("failed to create event loop wakeup pipe");
+            }
+        }
+        WakePipe {
+            read: fds[0],
+            write: fds[1],
+        }
+    }
+
+    /// The read end, readable once woken until drained
+    pub fn fd(&self) -> RawFd {
+        self.read
+    }
+
+    /// Wake the loop from any thread. A full pipe wakes it already
+    pub fn wake(&self) {
+        unsafe {
+            libc::write(self.write, [1u8].as_ptr() as *const _, 1);
+        }
+    }
+
+    /// Forget the wakeups so far, before looking at what woke the loop
+    pub fn drain(&self) {
+        let mut buf = [0u8; 64];
+        while unsafe { libc::read(self.read, buf.as_mut_ptr() as *mut _, buf.len()) } > 0 {}
+    }
+}
+
+impl Drop for WakePipe {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.read);
+            libc::close(self.write);
+        }
+    }
+}
+
+/// Block until one of `fds` is readable, `timeout` passed or a signal
+/// interrupted the wait. Readable fds are left to the caller to read
+pub(crate) fn wait(fds: &[RawFd], timeout: Option<Duration>) {
+    let mut pollfds: Vec<libc::pollfd> = fds
+        .iter()
+        .map(|&fd| libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        })
+        .collect();
+    unsafe {
+        libc::poll(
+            pollfds.as_mut_ptr(),
+            pollfds.len() as libc::nfds_t,
+            timeout_ms(timeout),
+        );
+    }
+}
+
+/// The timeout of poll(2), -1 for none. Rounded up, so a timer is due
+/// once the wait returns
+pub(crate) fn timeout_ms(timeout: Option<Duration>) -> i32 {
+    timeout.map_or(-1, |timeout| {
+        timeout.as_nanos().div_ceil(1_000_000).min(i32::MAX as u128) as i32
+    })
+}
@@ -0,0 +1,88 @@
+//! Detecting X servers which stopped answering, e.g. behind an SSH tunnel
+//! whose network went away, where Xlib would wait for a reply forever
+
+use std::os::unix::io::RawFd;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::xconn::{Activity, XConnection};
+
+/// A thread shutting the socket of the connection down once a request
+/// waited longer than the timeout. Xlib then sees the connection closed
+/// and marks it lost through the IO error handlers, so the stalled
+/// request returns
+pub(crate) struct Watchdog {
+    timeout: Duration,
+    // Round trip of the last heartbeat, see heartbeat_due
+    last_heartbeat: Instant,
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    pub fn spawn(conn: &XConnection, timeout: Duration) -> Watchdog {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let handle = {
+            let (activity, fd, stop) = (conn.activity.clone(), conn.fd(), stop.clone());
+            thread::Builder::new()
+                .name("daswin-watchdog".into())
+                .spawn(move || watch(activity, fd, timeout, stop))
+                .expect("failed to spawn connection watchdog")
+        };
+        Watchdog {
+            timeout,
+            last_heartbeat: Instant::now(),
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Whether the idle connection should be checked with a round trip,
+    /// a stalled server is only noticed while waiting on it
+    pub fn heartbeat_due(&mut self) -> bool {
+        let due = self.last_heartbeat.elapsed() >= self.timeout;
+        if due {
+            self.last_heartbeat = Instant::now();
+        }
+        due
+    }
+
+    /// How long until the next heartbeat is due
+    pub fn until_heartbeat(&self) -> Duration {
+        self.timeout.saturating_sub(self.last_heartbeat.elapsed())
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        let (stopped, wake) = &*self.stop;
+        *stopped.lock().unwrap() = true;
+        wake.notify_one();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn watch(activity: Arc<Activity>, fd: RawFd, timeout: Duration, stop: Arc<(Mutex<bool>, Condvar)>) {
+    let (stopped, wake) = &*stop;
+    let mut stopped = stopped.lock().unwrap();
+    while !*stopped {
+        if activity.busy_for().is_some_and(|busy| busy > timeout) {
+            log::warn!(
+                "X server did not answer for {:?}, dropping the connection",
+                timeout
+            );
+            unsafe {
+                libc::shutdown(fd, libc::SHUT_RDWR);
+            }
+            return;
+        }
+        stopped = wake.wait_timeout(stopped, timeout / 4).unwrap().0;
+    }
+}
@@ -0,0 +1,2821 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{c_void, CString};
+use std::mem;
+use std::os::raw;
+use std::ptr;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use raw_window_handle::{
+    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle, XlibDisplayHandle,
+    XlibWindowHandle,
+};
+use x11::xlib;
+use x11::xrandr::RROutput;
+
+use crate::backend;
+use crate::debug;
+use crate::dpi::{PhysicalPosition, PhysicalSize, Size};
+use crate::error::Error;
+use crate::event::{DeviceId, StateChange, WindowId};
+use crate::event_loop::EventLoop;
+use crate::ext::{self, SHAPE_BOUNDING, SHAPE_INPUT, SHAPE_SET};
+use crate::foreign;
+use crate::icon::Icon;
+use crate::ime::InputContext;
+use crate::monitor::{self, ModeSwitch, MonitorHandle, VideoMode};
+use crate::persist::{self, SavedWindowState};
+use crate::present;
+use crate::proxy::WindowProxy;
+use crate::resize;
+use crate::seat;
+use crate::session::{self, Undo};
+use crate::snap::ManualDrag;
+use crate::wm::{self, StateRequest};
+use crate::xconn::XConnection;
+use crate::xembed;
+
+/// Ways a window can cover a monitor
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fullscreen {
+    /// Switch the monitor to the video mode and cover it with the window,
+    /// the desktop mode is restored on exit, focus loss, drop or panic
+    Exclusive(VideoMode),
+    /// Cover a monitor at its current mode, None uses the monitor the
+    /// window is on
+    Borderless(Option<MonitorHandle>),
+}
+
+/// The point of a window which stays in place when it is resized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gravity {
+    NorthWest,
+    North,
+    NorthEast,
+    West,
+    Center,
+    East,
+    SouthWest,
+    South,
+    SouthEast,
+    /// The window contents stay in place relative to the root window
+    Static,
+}
+
+impl Gravity {
+    fn to_xlib(self) -> raw::c_int {
+        match self {
+            Gravity::NorthWest => xlib::NorthWestGravity,
+            Gravity::North => xlib::NorthGravity,
+            Gravity::NorthEast => xlib::NorthEastGravity,
+            Gravity::West => xlib::WestGravity,
+            Gravity::Center => xlib::CenterGravity,
+            Gravity::East => xlib::EastGravity,
+            Gravity::SouthWest => xlib::SouthWestGravity,
+            Gravity::South => xlib::SouthGravity,
+            Gravity::SouthEast => xlib::SouthEastGravity,
+            Gravity::Static => xlib::StaticGravity,
+        }
+    }
+}
+
+/// Shapes of the mouse cursor over a window, from the cursor theme or
+/// the X cursor font. Without either the application draws the cursor,
+/// see X11Window::software_cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum CursorIcon {
+    /// The cursor of the parent window, usually an arrow
+    #[default]
+    Default,
+    Arrow,
+    Text,
+    Hand,
+    Crosshair,
+    Move,
+    Wait,
+    ResizeHorizontal,
+    ResizeVertical,
+}
+
+impl CursorIcon {
+    // Names in Xcursor themes, the freedesktop name first and the legacy
+    // cursor font name second
+    fn theme_names(self) -> &'static [&'static str] {
+        match self {
+            CursorIcon::Default => &["default", "left_ptr"],
+            CursorIcon::Arrow => &["default", "left_ptr"],
+            CursorIcon::Text => &["text", "xterm"],
+            CursorIcon::Hand => &["pointer", "hand2"],
+            CursorIcon::Crosshair => &["crosshair", "cross"],
+            CursorIcon::Move => &["move", "fleur"],
+            CursorIcon::Wait => &["wait", "watch"],
+            CursorIcon::ResizeHorizontal => &["ew-resize", "sb_h_double_arrow"],
+            CursorIcon::ResizeVertical => &["ns-resize", "sb_v_double_arrow"],
+        }
+    }
+
+    // Glyphs of X11/cursorfont.h
+    fn font_glyph(self) -> Option<raw::c_uint> {
+        match self {
+            CursorIcon::Default => None,
+            CursorIcon::Arrow => Some(68),
+            CursorIcon::Text => Some(152),
+            CursorIcon::Hand => Some(60),
+            CursorIcon::Crosshair => Some(34),
+            CursorIcon::Move => Some(52),
+            CursorIcon::Wait => Some(150),
+            CursorIcon::ResizeHorizontal => Some(108),
+            CursorIcon::ResizeVertical => Some(116),
+        }
+    }
+}
+
+/// A cursor to draw in software, see X11Window::software_cursor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SoftwareCursor {
+    pub icon: CursorIcon,
+    /// Where the hotspot of the cursor goes, relative to the window
+    pub position: PhysicalPosition<f64>,
+}
+
+/// Where a window is placed when it is created
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Position {
+    /// The top left corner of the contents on the root window, with the
+    /// frame of the window manager around it. Embedded windows are placed
+    /// relative to their parent
+    At(PhysicalPosition<i32>),
+    /// Centered on the primary monitor
+    Centered,
+    /// Centered on a monitor of the screen
+    CenteredOnMonitor(MonitorHandle),
+    /// Centered on the window set with WindowBuilder::with_transient_for,
+    /// or inside the embedding parent. Without either it behaves like
+    /// Centered
+    CenteredOnParent,
+}
+
+impl Position {
+    /// The position of the window contents and the gravity the WM should
+    /// apply to its frame, centering uses CenterGravity so the frame
+    /// extents are split evenly around the requested position
+    unsafe fn resolve(
+        self,
+        conn: &XConnection,
+        (width, height): (u32, u32),
+        parent: Option<xlib::Window>,
+        embedded: bool,
+    ) -> (i32, i32, raw::c_int) {
+        let area = match (self, parent) {
+            // StaticGravity keeps the frame extents from offsetting the
+            // contents
+            (Position::At(position), _) => return (position.x, position.y, xlib::StaticGravity),
+            (Position::CenteredOnMonitor(monitor), _) => monitor.work_area(),
+            (Position::CenteredOnParent, Some(parent)) => {
+                let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+                xlib::XGetWindowAttributes(conn.display, parent, &mut attributes);
+                let (mut x, mut y, mut child) = (0, 0, 0);
+                // The embedding parent is the coordinate space itself
+                if !embedded {
+                    xlib::XTranslateCoordinates(
+                        conn.display,
+                        parent,
+                        conn.root,
+                        0,
+                        0,
+                        &mut x,
+                        &mut y,
+                        &mut child,
+                    );
+                }
+                Rect {
+                    x,
+                    y,
+                    width: attributes.width as u32,
+                    height: attributes.height as u32,
+                }
+            }
+            // Centered resolves to a monitor in from_builder, this is
+            // reached without RandR monitors only
+            (Position::Centered, _) | (Position::CenteredOnParent, None) => {
+                monitor::work_area(conn).unwrap_or(Rect {
+                    x: 0,
+                    y: 0,
+                    width: xlib::XDisplayWidth(conn.display, conn.screen) as u32,
+                    height: xlib::XDisplayHeight(conn.display, conn.screen) as u32,
+                })
+            }
+        };
+        let (x, y) = center_in(area, (width, height));
+        (x, y, xlib::CenterGravity)
+    }
+}
+
+/// The top left corner centering a size in an area, sizes larger than the
+/// area keep the top left corner inside it
+fn center_in(area: Rect, (width, height): (u32, u32)) -> (i32, i32) {
+    let x = area.x + (area.width as i32 - width as i32).max(0) / 2;
+    let y = area.y + (area.height as i32 - height as i32).max(0) / 2;
+    (x, y)
+}
+
+/// A rectangle in window coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    /// Whether the point lies inside, the right and bottom edges are
+    /// outside
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        let (x, y) = (x as i64 - self.x as i64, y as i64 - self.y as i64);
+        (0..self.width as i64).contains(&x) && (0..self.height as i64).contains(&y)
+    }
+
+    /// The area covered by both rectangles, None when they do not overlap
+    pub(crate) fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let left = self.x.max(other.x);
+        let top = self.y.max(other.y);
+        let right = (self.x as i64 + self.width as i64).min(other.x as i64 + other.width as i64);
+        let bottom = (self.y as i64 + self.height as i64).min(other.y as i64 + other.height as i64);
+        if right <= left as i64 || bottom <= top as i64 {
+            return None;
+        }
+        Some(Rect {
+            x: left,
+            y: top,
+            width: (right - left as i64) as u32,
+            height: (bottom - top as i64) as u32,
+        })
+    }
+
+    /// The smallest rectangle covering both
+    pub fn union(&self, other: &Rect) -> Rect {
+        let left = self.x.min(other.x);
+        let top = self.y.min(other.y);
+        let right = (self.x as i64 + self.width as i64).max(other.x as i64 + other.width as i64);
+        let bottom = (self.y as i64 + self.height as i64).max(other.y as i64 + other.height as i64);
+        Rect {
+            x: left,
+            y: top,
+            width: (right - left as i64) as u32,
+            height: (bottom - top as i64) as u32,
+        }
+    }
+}
+
+/// How far X11Window::set_content_protected keeps the window out of
+/// screenshots and screencasts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protection {
+    /// The hints were set and something on the server knows them, e.g.
+    /// the compositor. X11 lets any client read the window contents, so
+    /// this never stops a determined one
+    BestEffort,
+    /// The hints were set but nothing on the server is known to honor
+    /// them
+    Unsupported,
+}
+
+// Properties asking compositors and screencast services to leave a
+// window out of captures
+const SKIP_CAPTURE_HINTS: &[&str] = &["_GNOME_SHELL_SKIP_SCREENCAST"];
+
+/// What a window is created with again by EventLoop::reconnect, kept
+/// up to date as it changes
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LastKnown {
+    pub title: String,
+    // Root relative, as requested or reported by the window manager
+    pub position: Option<PhysicalPosition<i32>>,
+    pub size: PhysicalSize<u32>,
+    pub focusable: bool,
+    pub transient_for: Option<xlib::Window>,
+    // Owned with WindowBuilder::with_owner rather than only transient
+    pub dialog: bool,
+    pub modal: bool,
+    pub min_size: Option<Size>,
+    pub max_size: Option<Size>,
+    pub aspect_ratio: Option<(u32, u32)>,
+    pub cursor_icon: Option<CursorIcon>,
+    pub content_protected: bool,
+    pub undecorated: bool,
+    pub shown: bool,
+}
+
+/// Window state shared with the event loop
+#[derive(Default)]
+pub(crate) struct WindowState {
+    pub fullscreen: Option<Fullscreen>,
+    pub mode_switch: Option<ModeSwitch>,
+    // Root relative geometry to return to when leaving exclusive fullscreen
+    pub windowed_geometry: Option<(i32, i32, u32, u32)>,
+    pub exclusive_grab: bool,
+    // Socket window which sent XEMBED_EMBEDDED_NOTIFY
+    pub embedder: Option<xlib::Window>,
+    // Grabs requested with set_keyboard_grab and set_pointer_grab, held
+    // while the window has focus
+    pub keyboard_grab: bool,
+    pub pointer_grab: bool,
+    // Modifier mask and keysym releasing both grabs
+    pub grab_escape: Option<(u32, xlib::KeySym)>,
+    // Set with set_ime_allowed and set_ime_cursor_area, the context is
+    // created once allowed and picks up the cursor area set before
+    pub ime_allowed: bool,
+    pub ime_cursor_area: Option<Rect>,
+    pub input_context: Option<InputContext>,
+    // Confinement requested with confine_cursor_with_barriers, the
+    // barriers exist while the window has focus
+    pub barrier_confinement: bool,
+    pub barriers: Vec<xlib::XID>,
+    // _NET_WM_STATE as of the last PropertyNotify
+    pub wm_state: WmState,
+    // Mouse buttons pressed in the window and not released yet by master
+    // pointer, bit n for button n
+    pub held_buttons: HashMap<DeviceId, u32>,
+    // Keys pressed in the window and not released yet, with the keysym
+    // each one produced
+    pub held_keys: Vec<(u32, xlib::KeySym)>,
+    // Set by request_redraw until the next poll reports it
+    pub redraw_requested: bool,
+    // Set by RenderSurface when its canvas moved, until the next poll
+    // reports it
+    pub canvas_viewport: Option<Rect>,
+    // RandR output of the monitor last reported with MonitorChanged
+    pub monitor: Option<RROutput>,
+    pub last_known: LastKnown,
+    // SYNC counter acknowledging configures of the window manager, along
+    // with the value of its last _NET_WM_SYNC_REQUEST not acknowledged yet
+    pub sync_counter: Option<xlib::XID>,
+    pub sync_request: Option<i64>,
+    // Set while the event loop holds back the size of the last configure
+    // under its resize throttle, so Resized lags last_known.size
+    pub resize_held: bool,
+    // Root relative position of the contents for window_to_root and the
+    // other conversions, from pointer events, the window manager's
+    // configures and refresh_geometry. Configures sent by the server are
+    // root relative while the window is not reparented
+    pub root_origin: Option<PhysicalPosition<i32>>,
+    pub parent_is_root: bool,
+    // Root relative geometry as of the last configure while neither
+    // maximized nor fullscreen, for save_state
+    pub normal_geometry: Option<Rect>,
+    // Set once the window was destroyed ahead of the X11Window, see
+    // EventLoop::set_panic_policy
+    pub destroyed: bool,
+    // Set by begin_manual_drag until the buttons are released
+    pub manual_drag: Option<ManualDrag>,
+    // Shape the application draws while the server shows no cursor,
+    // along with the focus and the position of each master pointer in
+    // the window deciding whether it is drawn at all
+    pub software_cursor: Option<CursorIcon>,
+    pub focused: bool,
+    pub pointers: HashMap<DeviceId, (f64, f64)>,
+    // Windows of other clients embedded with embed_foreign and their
+    // region, along with the one given focus with focus_foreign
+    pub foreign: Vec<(xlib::Window, Rect)>,
+    pub foreign_focus: Option<xlib::Window>,
+    // Events the application had selected on a window adopted with
+    // from_raw, selected again instead of destroying it on drop
+    pub adopted_mask: Option<raw::c_long>,
+    // Leader of the WM_HINTS window group of an owned window, the window
+    // itself leads the group otherwise
+    pub group: Option<xlib::Window>,
+    // Set with set_modal. ModalBlocked was delivered for a click on the
+    // owner since the window last had focus
+    pub modal: bool,
+    pub modal_blocked: bool,
+}
+
+/// Ctrl+Alt+Escape, see X11Window::set_grab_escape
+const DEFAULT_GRAB_ESCAPE: (u32, xlib::KeySym) = (
+    xlib::ControlMask | xlib::Mod1Mask,
+    x11::keysym::XK_Escape as xlib::KeySym,
+);
+
+/// Describes a window before it is created.
+///
+/// By default the window is titled "daswin", sized to fit its monitor
+/// with Size::Default, placed by the window manager, focusable, decorated, and neither
+/// embedded nor transient for another window
+pub struct WindowBuilder {
+    title: String,
+    size: Size,
+    min_size: Option<Size>,
+    position: Option<Position>,
+    focusable: bool,
+    decorations: bool,
+    embed_parent: Option<xlib::Window>,
+    transient_for: Option<xlib::Window>,
+    // Group leader of the owner of with_owner
+    owner_group: Option<xlib::Window>,
+    restored: Option<SavedWindowState>,
+}
+
+impl Default for WindowBuilder {
+    fn default() -> Self {
+        WindowBuilder {
+            title: String::from("daswin"),
+            size: Size::Default,
+            min_size: None,
+            position: None,
+            focusable: true,
+            decorations: true,
+            embed_parent: None,
+            transient_for: None,
+            owner_group: None,
+            restored: None,
+        }
+    }
+}
+
+impl WindowBuilder {
+    pub fn new() -> WindowBuilder {
+        WindowBuilder::default()
+    }
+
+    /// Set the window title
+    pub fn with_title(mut self, title: &str) -> WindowBuilder {
+        self.title = title.to_owned();
+        self
+    }
+
+    /// Set the initial size of the window contents. Logical and work area
+    /// sizes use the monitor given to Position::CenteredOnMonitor, else
+    /// the primary monitor, e.g. Size::WorkArea(0.8) for 80% of the space
+    /// panels leave on it
+    pub fn with_inner_size(mut self, size: impl Into<Size>) -> WindowBuilder {
+        self.size = size.into();
+        self
+    }
+
+    /// Set the smallest size the user may resize the window contents to,
+    /// which the initial size is raised to. Replaces the minimum of
+    /// Size::Default, and is resolved on the same monitor as the size
+    pub fn with_min_inner_size(mut self, size: impl Into<Size>) -> WindowBuilder {
+        self.min_size = Some(size.into());
+        self
+    }
+
+    /// Ask the window manager to place the window instead of choosing a
+    /// position, computed from the monitor layout when the window is built
+    pub fn with_position(mut self, position: Position) -> WindowBuilder {
+        self.position = Some(position);
+        self
+    }
+
+    /// Mark the window as a dialog or other child of `parent`, the WM
+    /// keeps it above the parent and Position::CenteredOnParent centers on
+    /// it
+    pub fn with_transient_for(mut self, parent: &X11Window) -> WindowBuilder {
+        self.transient_for = Some(parent.window);
+        self
+    }
+
+    /// Make the window a dialog owned by `parent`, e.g. a settings
+    /// dialog. Like with_transient_for the WM keeps it above the parent
+    /// and minimizes it along with it, and it is typed as a dialog and
+    /// joins the window group of the parent, so task bars and alt-tab show
+    /// the parent alone. Unless placed otherwise it is centered on the
+    /// parent. See X11Window::set_modal
+    pub fn with_owner(mut self, parent: &X11Window) -> WindowBuilder {
+        self.transient_for = Some(parent.window);
+        self.owner_group = Some(parent.state.borrow().group.unwrap_or(parent.window));
+        self.position.get_or_insert(Position::CenteredOnParent);
+        self
+    }
+
+    /// Whether the window manager may give the window keyboard focus,
+    /// tooltips and similar windows should set this to false
+    pub fn with_focusable(mut self, focusable: bool) -> WindowBuilder {
+        self.focusable = focusable;
+        self
+    }
+
+    /// Whether the window manager draws a title bar and borders, see
+    /// X11Window::set_decorations
+    pub fn with_decorations(mut self, decorations: bool) -> WindowBuilder {
+        self.decorations = decorations;
+        self
+    }
+
+    /// Reopen a window as X11Window::save_state left it, replacing the
+    /// size and position.
+    ///
+    /// The window is placed on its monitor first, or on the nearest one
+    /// when that is gone, moved and shrunk into the work area. It is then
+    /// maximized and made fullscreen from the first map, so leaving
+    /// fullscreen returns to maximized and unmaximizing to the saved
+    /// geometry
+    pub fn with_restored_state(mut self, state: SavedWindowState) -> WindowBuilder {
+        self.restored = Some(state);
+        self
+    }
+
+    /// Create the window inside a foreign window, e.g. the id of a
+    /// GtkSocket, speaking XEMBED with it instead of being managed by the
+    /// window manager
+    pub fn with_embed_parent(mut self, parent: xlib::Window) -> WindowBuilder {
+        self.embed_parent = Some(parent);
+        self
+    }
+
+    /// Create the window on the event loop's connection
+    pub fn build(self, event_loop: &EventLoop) -> X11Window {
+        X11Window::from_builder(event_loop, self)
+    }
+}
+
+type PresentCallback = Box<dyn FnOnce(&mut X11Window)>;
+
+/// A window on the X Display, whose events are delivered by the
+/// EventLoop it was created from
+pub struct X11Window {
+    pub display: *mut xlib::Display,
+    pub window: xlib::Window,
+    pub screen: i32,
+
+    id: WindowId,
+    embedded: bool,
+    pub(crate) conn: Arc<XConnection>,
+    state: Rc<RefCell<WindowState>>,
+
+    // Properties written just before the first map, so creation does not
+    // wait on the server
+    pending: Option<WindowBuilder>,
+    created_at: Instant,
+    // Run by notify_presented after the first frame
+    first_present: Option<PresentCallback>,
+    // Shared with the proxies of the window, cleared on drop
+    alive: Arc<AtomicBool>,
+}
+
+impl X11Window {
+    /// Create a new window with a given title and size
+    pub fn new(event_loop: &EventLoop, title: &str, size: impl Into<Size>) -> X11Window {
+        WindowBuilder::new()
+            .with_title(title)
+            .with_inner_size(size)
+            .build(event_loop)
+    }
+
+    fn from_builder(event_loop: &EventLoop, mut builder: WindowBuilder) -> X11Window {
+        let created_at = Instant::now();
+        let conn = event_loop.conn.clone();
+        let placed = builder.restored.as_ref().map(|saved| {
+            let (geometry, monitor) = persist::placement(saved, event_loop.available_monitors());
+            let fullscreen = saved.fullscreen.then_some(Fullscreen::Borderless(monitor));
+            (geometry, fullscreen)
+        });
+        let mut fullscreen = None;
+        if let Some((geometry, restored)) = placed {
+            builder.size = Size::Physical(PhysicalSize::new(geometry.width, geometry.height));
+            builder.position = Some(Position::At(PhysicalPosition::new(geometry.x, geometry.y)));
+            fullscreen = restored;
+        }
+        // Sized on the monitor the window is placed on
+        let monitor = match (builder.size, &builder.position) {
+            (Size::Physical(_), _) if builder.min_size.is_none() => None,
+            (_, Some(Position::CenteredOnMonitor(monitor))) => Some(monitor.clone()),
+            _ => event_loop.primary_monitor(),
+        };
+        let resolve = |size: Size| match &monitor {
+            Some(monitor) => monitor.resolve_size(size),
+            None => size.to_physical(1.0),
+        };
+        let min_size = builder.min_size.map(resolve);
+        let (width, height) = match (builder.size, &monitor, min_size) {
+            (Size::Default, Some(monitor), Some(min)) => {
+                monitor::default_inner_size(monitor.work_area(), min).into()
+            }
+            (size, _, min) => {
+                let size = resolve(size);
+                let min = min.unwrap_or_default();
+                (size.width.max(min.width), size.height.max(min.height))
+            }
+        };
+        let embed_parent = builder.embed_parent;
+        let parent = embed_parent.or(builder.transient_for);
+        let event_mask = event_loop.event_filter.event_mask();
+        let position = match builder.position.clone() {
+            Some(Position::Centered) => {
+                let monitors = event_loop.available_monitors();
+                let primary = monitors.iter().position(MonitorHandle::is_primary);
+                monitors
+                    .into_iter()
+                    .nth(primary.unwrap_or(0))
+                    .map_or(Some(Position::Centered), |monitor| {
+                        Some(Position::CenteredOnMonitor(monitor))
+                    })
+            }
+            position => position,
+        };
+
+        let (window, position, sync_counter) = conn.request(move |conn| unsafe {
+            // Create window.
+            let mut attributes: xlib::XSetWindowAttributes = mem::zeroed();
+            attributes.background_pixel = xlib::XWhitePixel(conn.display, conn.screen);
+            attributes.event_mask = event_mask;
+
+            let placement = position.map(|position| {
+                position.resolve(conn, (width, height), parent, embed_parent.is_some())
+            });
+            let (x, y) = placement.map_or((0, 0), |(x, y, _)| (x, y));
+            let position = placement
+                .filter(|_| embed_parent.is_none())
+                .map(|(x, y, _)| PhysicalPosition::new(x, y));
+            let window = xlib::XCreateWindow(
+                conn.display,
+                embed_parent.unwrap_or(conn.root),
+                x,
+                y,
+                width,
+                height,
+                0,
+                0,
+                xlib::InputOutput as raw::c_uint,
+                ptr::null_mut(),
+                xlib::CWBackPixel | xlib::CWEventMask,
+                &mut attributes,
+            );
+
+            // Window managers ignore the creation position unless told it
+            // was requested.
+            // The size is the program's choice rather than the user's, so
+            // window managers may still fit it to the screen
+            let mut hints: xlib::XSizeHints = mem::zeroed();
+            hints.flags = xlib::PSize;
+            hints.width = width as raw::c_int;
+            hints.height = height as raw::c_int;
+            if let Some((_, _, gravity)) = placement {
+                hints.flags |= xlib::USPosition | xlib::PPosition | xlib::PWinGravity;
+                hints.x = x;
+                hints.y = y;
+                hints.win_gravity = gravity;
+            }
+            if let Some(min) = min_size {
+                hints.flags |= xlib::PMinSize;
+                hints.min_width = min.width as raw::c_int;
+                hints.min_height = min.height as raw::c_int;
+            }
+            xlib::XSetWMNormalHints(conn.display, window, &mut hints);
+            (window, position, resize::create_sync_counter(conn))
+        });
+
+        let state = Rc::new(RefCell::new(WindowState {
+            grab_escape: Some(DEFAULT_GRAB_ESCAPE),
+            last_known: LastKnown {
+                title: builder.title.clone(),
+                position,
+                size: PhysicalSize::new(width, height),
+                min_size: builder.min_size,
+                focusable: builder.focusable,
+                transient_for: builder.transient_for,
+                dialog: builder.owner_group.is_some(),
+                undecorated: !builder.decorations,
+                ..LastKnown::default()
+            },
+            sync_counter,
+            parent_is_root: embed_parent.is_none(),
+            fullscreen,
+            group: builder.owner_group,
+            ..WindowState::default()
+        }));
+        let id = event_loop.register_window(window, &state);
+        event_loop.select_device_events(vec![window]);
+
+        X11Window {
+            display: conn.display,
+            window,
+            screen: conn.screen,
+            id,
+            embedded: embed_parent.is_some(),
+            conn,
+            state,
+            pending: Some(builder),
+            created_at,
+            first_present: None,
+            alive: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Adopt a window the application created on the display of the
+    /// event loop, e.g. one of older Xlib code sharing it through
+    /// EventLoop::from_raw_display. Its events are delivered like those
+    /// of daswin's windows, any others of it are dropped.
+    ///
+    /// The events of the event filter are selected on top of those the
+    /// application selected, which are selected alone again on drop.
+    /// Dropping never destroys the window, nor are its properties written
+    /// when shown. Devices are not selected with XInput2, which would
+    /// replace the application's selections, so tablets, smooth
+    /// scrolling and master pointers other than the core one are not
+    /// reported for it.
+    ///
+    /// # Safety
+    ///
+    /// `window` must exist on the display of the event loop and outlive
+    /// the returned X11Window, and must not be adopted by it twice. The
+    /// application must not select events on it while adopted, the
+    /// selection it makes is lost on drop
+    pub unsafe fn from_raw(event_loop: &EventLoop, window: xlib::Window) -> X11Window {
+        debug_assert!(window != 0, "adopting window None");
+        debug_assert!(
+            event_loop.window_state(window).is_none(),
+            "window {:#x} adopted twice",
+            window
+        );
+        let conn = event_loop.conn.clone();
+        let event_mask = event_loop.event_filter.event_mask();
+        let (adopted_mask, position, size) = conn.request(move |conn| {
+            let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+            let exists = xlib::XGetWindowAttributes(conn.display, window, &mut attributes) != 0;
+            debug_assert!(exists, "adopted window {:#x} does not exist", window);
+            xlib::XSelectInput(
+                conn.display,
+                window,
+                attributes.your_event_mask | event_mask,
+            );
+            xlib::XFlush(conn.display);
+            (
+                attributes.your_event_mask,
+                PhysicalPosition::new(attributes.x, attributes.y),
+                PhysicalSize::new(attributes.width as u32, attributes.height as u32),
+            )
+        });
+
+        let state = Rc::new(RefCell::new(WindowState {
+            grab_escape: Some(DEFAULT_GRAB_ESCAPE),
+            last_known: LastKnown {
+                position: Some(position),
+                size,
+                ..LastKnown::default()
+            },
+            adopted_mask: Some(adopted_mask),
+            ..WindowState::default()
+        }));
+        let id = event_loop.register_window(window, &state);
+
+        X11Window {
+            display: conn.display,
+            window,
+            screen: conn.screen,
+            id,
+            embedded: false,
+            conn,
+            state,
+            pending: None,
+            created_at: Instant::now(),
+            first_present: None,
+            alive: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Create a window like one of a lost connection on the event loop's
+    /// new one, see EventLoop::reconnect
+    pub(crate) fn recreate(
+        event_loop: &EventLoop,
+        last: LastKnown,
+        owner: Option<&X11Window>,
+    ) -> X11Window {
+        let mut builder = WindowBuilder::new()
+            .with_title(&last.title)
+            .with_inner_size(last.size)
+            .with_focusable(last.focusable)
+            .with_decorations(!last.undecorated);
+        builder.position = last.position.map(Position::At);
+        if let Some(owner) = owner {
+            builder = if last.dialog {
+                builder.with_owner(owner)
+            } else {
+                builder.with_transient_for(owner)
+            };
+        }
+        let mut window = builder.build(event_loop);
+        if last.modal {
+            window.set_modal(true);
+        }
+        if last.min_size.is_some() {
+            window.set_min_inner_size(last.min_size);
+        }
+        if last.max_size.is_some() {
+            window.set_max_inner_size(last.max_size);
+        }
+        if last.aspect_ratio.is_some() {
+            window.set_aspect_ratio(last.aspect_ratio);
+        }
+        if let Some(icon) = last.cursor_icon {
+            window.set_cursor_icon(icon);
+        }
+        if last.content_protected {
+            window.set_content_protected(true);
+        }
+        if last.shown {
+            window.show();
+        }
+        window
+    }
+
+    /// Run `f` once the first frame was presented, e.g. to show the
+    /// window only when it has contents.
+    ///
+    /// WindowSurface::present reports frames, applications presenting
+    /// themselves call notify_presented
+    pub fn on_first_present(&mut self, f: impl FnOnce(&mut X11Window) + 'static) {
+        self.first_present = Some(Box::new(f));
+    }
+
+    /// Report a presented frame, running the on_first_present callback
+    /// the first time
+    pub fn notify_presented(&mut self) {
+        if let Some(f) = self.first_present.take() {
+            f(self);
+        }
+    }
+
+    /// Deliver Event::FramePresented whenever a frame of the window
+    /// reaches the screen, e.g. to measure latency with ServerClock.
+    ///
+    /// Fails with Error::Unsupported("Present") when the server lacks the
+    /// Present extension or libxcb-present is not installed. Drivers
+    /// presenting without Present, like NVIDIA's, deliver no events
+    pub fn enable_present_feedback(&mut self) -> Result<(), Error> {
+        let window = self.window;
+        self.conn.request(move |conn| unsafe {
+            conn.init_present().ok_or(Error::Unsupported("Present"))?;
+            present::select_completions(conn, window);
+            xlib::XFlush(conn.display);
+            Ok(())
+        })
+    }
+
+    /// The id events of this window carry
+    pub fn id(&self) -> WindowId {
+        self.id
+    }
+
+    /// Write the properties the WM reads when the window is first mapped
+    unsafe fn write_properties(
+        conn: &XConnection,
+        window: xlib::Window,
+        builder: WindowBuilder,
+        sync_counter: Option<xlib::XID>,
+    ) {
+        let display = conn.display;
+
+        store_title(conn, window, &builder.title);
+
+        // Embedded windows are never managed, the embedder handles focus
+        // and mapping.
+        if builder.embed_parent.is_some() {
+            xembed::set_info(conn, window, 0);
+            return;
+        }
+
+        // Tell the WM whether we accept keyboard focus, and which windows
+        // to treat as one application, e.g. to show one task bar entry.
+        let wm_hints = xlib::XAllocWMHints();
+        (*wm_hints).flags = xlib::InputHint | xlib::WindowGroupHint;
+        (*wm_hints).input = builder.focusable as xlib::Bool;
+        (*wm_hints).window_group = builder.owner_group.unwrap_or(window);
+        xlib::XSetWMHints(display, window, wm_hints);
+        xlib::XFree(wm_hints as *mut c_void);
+
+        if let Some(parent) = builder.transient_for {
+            xlib::XSetTransientForHint(display, window, parent);
+        }
+        if builder.owner_group.is_some() {
+            let dialog = conn.atoms.net_wm_window_type_dialog;
+            xlib::XChangeProperty(
+                display,
+                window,
+                conn.atoms.net_wm_window_type,
+                xlib::XA_ATOM,
+                32,
+                xlib::PropModeReplace,
+                &dialog as *const xlib::Atom as *const u8,
+                1,
+            );
+        }
+
+        if !builder.decorations {
+            set_motif_decorations(conn, window, false);
+        }
+
+        // Group the window with the others of this client for session
+        // management.
+        xlib::XChangeProperty(
+            display,
+            window,
+            conn.atoms.wm_client_leader,
+            xlib::XA_WINDOW,
+            32,
+            xlib::PropModeReplace,
+            &conn.client_leader as *const xlib::Window as *const u8,
+            1,
+        );
+
+        // Window managers apply the state found when the window is first
+        // mapped, after placing it
+        if let Some(saved) = &builder.restored {
+            let atoms = &conn.atoms;
+            let mut state = Vec::new();
+            if saved.maximized {
+                state.push(atoms.net_wm_state_maximized_vert);
+                state.push(atoms.net_wm_state_maximized_horz);
+            }
+            if saved.fullscreen {
+                state.push(atoms.net_wm_state_fullscreen);
+                let bypass: raw::c_long = 1;
+                xlib::XChangeProperty(
+                    display,
+                    window,
+                    atoms.net_wm_bypass_compositor,
+                    xlib::XA_CARDINAL,
+                    32,
+                    xlib::PropModeReplace,
+                    &bypass as *const raw::c_long as *const u8,
+                    1,
+                );
+            }
+            xlib::XChangeProperty(
+                display,
+                window,
+                atoms.net_wm_state,
+                xlib::XA_ATOM,
+                32,
+                xlib::PropModeReplace,
+                state.as_ptr() as *const u8,
+                state.len() as raw::c_int,
+            );
+        }
+
+        // Hook close requests.
+        let mut protocols = vec![conn.atoms.wm_delete_window, conn.atoms.net_wm_ping];
+
+        // Window managers wait on the counter between the configures of
+        // an interactive resize.
+        if let Some(counter) = sync_counter {
+            xlib::XChangeProperty(
+                display,
+                window,
+                conn.atoms.net_wm_sync_request_counter,
+                xlib::XA_CARDINAL,
+                32,
+                xlib::PropModeReplace,
+                &counter as *const xlib::XID as *const u8,
+                1,
+            );
+            protocols.push(conn.atoms.net_wm_sync_request);
+        }
+
+        xlib::XSetWMProtocols(
+            display,
+            window,
+            protocols.as_mut_ptr(),
+            protocols.len() as raw::c_int,
+        );
+    }
+
+    /// A handle changing the window from other threads
+    pub fn proxy(&self) -> WindowProxy {
+        WindowProxy::new(self.id, self.conn.clone(), self.alive.clone())
+    }
+
+    /// Deliver Event::RedrawRequested for the window with the next poll.
+    /// Requests made before that poll are merged into one event
+    pub fn request_redraw(&self) {
+        self.state.borrow_mut().redraw_requested = true;
+    }
+
+    /// Display the window
+    pub fn show(&mut self) {
+        self.state.borrow_mut().last_known.shown = true;
+        update_modal(&self.conn, self.window, &self.state.borrow());
+        let window = self.window;
+        let pending = self.pending.take();
+        let embedded = self.embedded;
+        let sync_counter = self.state.borrow().sync_counter;
+        self.conn.request(move |conn| unsafe {
+            if let Some(builder) = pending {
+                X11Window::write_properties(conn, window, builder, sync_counter);
+            }
+            // Ask the embedder to map us, mapping directly covers
+            // embedders which only reparented the window
+            if embedded {
+                xembed::set_info(conn, window, xembed::MAPPED);
+            }
+            xlib::XMapWindow(conn.display, window);
+            xlib::XFlush(conn.display);
+        });
+
+        if debug::enabled() {
+            eprintln!(
+                "daswin: window 0x{:x} mapped {:?} after creation",
+                self.window,
+                self.created_at.elapsed()
+            );
+        }
+    }
+
+    /// Remove the window from the screen until it is shown again
+    pub fn hide(&mut self) {
+        self.state.borrow_mut().last_known.shown = false;
+        update_modal(&self.conn, self.window, &self.state.borrow());
+        let window = self.window;
+        let embedded = self.embedded;
+        self.conn.request(move |conn| unsafe {
+            if embedded {
+                xembed::set_info(conn, window, 0);
+                xlib::XUnmapWindow(conn.display, window);
+            } else {
+                // Withdrawing tells the window manager to forget the
+                // window rather than iconify it
+                xlib::XWithdrawWindow(conn.display, window, conn.screen);
+            }
+            xlib::XFlush(conn.display);
+        });
+    }
+
+    /// The pointer position relative to the top left of the window
+    /// contents, or None when the pointer is on another screen.
+    ///
+    /// Queried from the server, so it is valid before any motion or
+    /// ConfigureNotify was received
+    pub fn cursor_position(&self) -> Option<PhysicalPosition<f64>> {
+        let window = self.window;
+        let (_, relative, _) = self.conn.request(move |conn| conn.query_pointer(window));
+        relative.map(|(x, y)| PhysicalPosition::new(x as f64, y as f64))
+    }
+
+    /// Like cursor_position for one master pointer, see
+    /// EventLoop::master_pointers. None as well for devices which are no
+    /// master pointer
+    pub fn cursor_position_of(&self, device: DeviceId) -> Option<PhysicalPosition<f64>> {
+        let window = self.window;
+        let position = self
+            .conn
+            .request(move |conn| seat::query_pointer(conn, device, window));
+        position.map(|(x, y)| PhysicalPosition::new(x, y))
+    }
+
+    /// The monitor containing the center of the window, whose refresh
+    /// rate frame pacing should follow
+    pub fn current_monitor(&self) -> Option<MonitorHandle> {
+        current_monitor(&self.conn, self.window)
+    }
+
+    /// Move the window to the center of a monitor
+    pub fn center_on(&mut self, monitor: &MonitorHandle) {
+        if self.embedded {
+            return;
+        }
+        let window = self.window;
+        let area = monitor.work_area();
+        self.conn.request(move |conn| unsafe {
+            let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+            xlib::XGetWindowAttributes(conn.display, window, &mut attributes);
+            let size = (attributes.width as u32, attributes.height as u32);
+            let (x, y) = center_in(area, size);
+
+            // The WM applies the gravity to the move as well, keeping the
+            // frame centered rather than its top left corner
+            let mut hints: xlib::XSizeHints = mem::zeroed();
+            let mut supplied = 0;
+            xlib::XGetWMNormalHints(conn.display, window, &mut hints, &mut supplied);
+            hints.flags |= xlib::USPosition | xlib::PPosition | xlib::PWinGravity;
+            hints.x = x;
+            hints.y = y;
+            hints.win_gravity = xlib::CenterGravity;
+            xlib::XSetWMNormalHints(conn.display, window, &mut hints);
+            xlib::XMoveWindow(conn.display, window, x, y);
+            xlib::XFlush(conn.display);
+        });
+    }
+
+    /// The size of the window contents as of the last configure read by
+    /// the event loop, without a round trip.
+    ///
+    /// This is the live geometry, which runs ahead of Event::Resized
+    /// while the resize throttle holds sizes back, and of the size a
+    /// surface was last configured with until it handled the event, see
+    /// SurfaceState::current_size. Render at the size of the surface
+    pub fn inner_size_physical(&self) -> PhysicalSize<u32> {
+        self.state.borrow().last_known.size
+    }
+
+    /// Whether the event loop holds back a size under its resize
+    /// throttle, so inner_size_physical may differ from the last Resized
+    pub(crate) fn resize_held(&self) -> bool {
+        self.state.borrow().resize_held
+    }
+
+    /// The current size of the window contents, asking the server
+    pub fn inner_size(&self) -> PhysicalSize<u32> {
+        let window = self.window;
+        self.conn.request(move |conn| unsafe {
+            let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+            xlib::XGetWindowAttributes(conn.display, window, &mut attributes);
+            PhysicalSize::new(attributes.width as u32, attributes.height as u32)
+        })
+    }
+
+    /// The scale factor of the monitor containing the center of the
+    /// window, 1 when there is none
+    pub fn scale_factor(&self) -> f64 {
+        self.current_monitor()
+            .map_or(1.0, |monitor| monitor.scale_factor())
+    }
+
+    fn to_physical(&self, size: Size) -> PhysicalSize<u32> {
+        to_physical(&self.conn, self.window, size)
+    }
+
+    /// Ask for a new size of the window contents, delivered as
+    /// Event::Resized once the window manager applied it. Logical sizes
+    /// use the scale factor of the window's current monitor
+    pub fn set_inner_size(&mut self, size: impl Into<Size>) {
+        set_inner_size(&self.conn, self.window, size.into());
+    }
+
+    /// Change the window title
+    pub fn set_title(&mut self, title: &str) {
+        self.state.borrow_mut().last_known.title = title.to_owned();
+        set_title(&self.conn, self.window, title);
+    }
+
+    /// The title as the window manager reads it, preferring _NET_WM_NAME
+    /// over WM_NAME. Empty when neither is set
+    pub fn title(&self) -> String {
+        let window = self.window;
+        self.conn
+            .request(move |conn| {
+                conn.text_property(window, conn.atoms.net_wm_name, xlib::XA_WM_NAME)
+            })
+            .unwrap_or_default()
+    }
+
+    /// Report Event::CanvasViewportChanged with the next poll
+    pub(crate) fn report_canvas_viewport(&self, viewport: Rect) {
+        self.state.borrow_mut().canvas_viewport = Some(viewport);
+    }
+
+    /// Set the icon the window manager shows for the window, e.g. in the
+    /// task bar, None removes it
+    pub fn set_window_icon(&mut self, icon: Option<&Icon>) {
+        let window = self.window;
+        let data = icon.map(Icon::to_cardinals);
+        self.conn.request(move |conn| unsafe {
+            match data {
+                Some(data) => {
+                    xlib::XChangeProperty(
+                        conn.display,
+                        window,
+                        conn.atoms.net_wm_icon,
+                        xlib::XA_CARDINAL,
+                        32,
+                        xlib::PropModeReplace,
+                        data.as_ptr() as *const u8,
+                        data.len() as raw::c_int,
+                    );
+                }
+                None => {
+                    xlib::XDeleteProperty(conn.display, window, conn.atoms.net_wm_icon);
+                }
+            }
+            xlib::XFlush(conn.display);
+        });
+    }
+
+    /// Change the shape of the mouse cursor over the window.
+    ///
+    /// The shape comes from the cursor theme, or the cursor font when no
+    /// theme has it. Without either, or with DASWIN_SOFTWARE_CURSOR set,
+    /// the cursor is hidden and software_cursor tells where to draw it
+    pub fn set_cursor_icon(&mut self, icon: CursorIcon) {
+        set_cursor_icon(&self.conn, self.window, &mut self.state.borrow_mut(), icon);
+    }
+
+    /// Whether the cursor of the window is drawn by the application, as
+    /// the server could show none of its shape
+    pub fn uses_software_cursor(&self) -> bool {
+        self.state.borrow().software_cursor.is_some()
+    }
+
+    /// The cursor to draw as the last thing of a frame while the window
+    /// uses a software cursor, has focus and has the pointer in it. None
+    /// otherwise, the server shows the cursor then or no cursor is due.
+    ///
+    /// render::CursorImage has the pixels and hotspot of every shape, the
+    /// helpers::CursorPass draws them with wgpu
+    pub fn software_cursor(&self) -> Option<SoftwareCursor> {
+        let state = self.state.borrow();
+        let icon = state.software_cursor.filter(|_| state.focused)?;
+        let (x, y) = *state.pointers.get(&self.conn.core_pointer())?;
+        Some(SoftwareCursor {
+            icon,
+            position: PhysicalPosition::new(x, y),
+        })
+    }
+
+    /// Set the smallest size the user may resize the window contents to
+    pub fn set_min_inner_size(&mut self, size: Option<Size>) {
+        self.state.borrow_mut().last_known.min_size = size;
+        let size = size.map(|size| self.to_physical(size));
+        self.update_size_hints(size.is_some(), move |hints| {
+            let (width, height) = size.unwrap_or_default().into();
+            hints.min_width = width as raw::c_int;
+            hints.min_height = height as raw::c_int;
+            xlib::PMinSize
+        });
+    }
+
+    /// Set the largest size the user may resize the window contents to
+    pub fn set_max_inner_size(&mut self, size: Option<Size>) {
+        self.state.borrow_mut().last_known.max_size = size;
+        let size = size.map(|size| self.to_physical(size));
+        self.update_size_hints(size.is_some(), move |hints| {
+            let (width, height) = size.unwrap_or_default().into();
+            hints.max_width = width as raw::c_int;
+            hints.max_height = height as raw::c_int;
+            xlib::PMaxSize
+        });
+    }
+
+    /// Lock the width to height ratio of the window contents while the
+    /// user resizes it, e.g. Some((16, 9))
+    pub fn set_aspect_ratio(&mut self, ratio: Option<(u32, u32)>) {
+        self.state.borrow_mut().last_known.aspect_ratio = ratio;
+        self.update_size_hints(ratio.is_some(), move |hints| {
+            let (x, y) = ratio.unwrap_or((0, 0));
+            hints.min_aspect.x = x as raw::c_int;
+            hints.min_aspect.y = y as raw::c_int;
+            hints.max_aspect = hints.min_aspect;
+            xlib::PAspect
+        });
+    }
+
+    /// Only allow the user to resize the window in steps of the given
+    /// size, e.g. the cell size of a terminal grid
+    pub fn set_size_increments(&mut self, increments: Option<Size>) {
+        let increments = increments.map(|size| self.to_physical(size));
+        self.update_size_hints(increments.is_some(), move |hints| {
+            let (width, height) = increments.unwrap_or_default().into();
+            hints.width_inc = width as raw::c_int;
+            hints.height_inc = height as raw::c_int;
+            xlib::PResizeInc
+        });
+    }
+
+    /// Set the point of the window which stays in place when it is resized
+    pub fn set_gravity(&mut self, gravity: Gravity) {
+        self.update_size_hints(true, move |hints| {
+            hints.win_gravity = gravity.to_xlib();
+            xlib::PWinGravity
+        });
+    }
+
+    /// Read-modify-write WM_NORMAL_HINTS, `update` fills in its fields and
+    /// returns their flag, which is set when `enable` and cleared otherwise
+    fn update_size_hints<F>(&self, enable: bool, update: F)
+    where
+        F: FnOnce(&mut xlib::XSizeHints) -> raw::c_long + Send + 'static,
+    {
+        let window = self.window;
+        self.conn.request(move |conn| unsafe {
+            let mut hints: xlib::XSizeHints = mem::zeroed();
+            let mut supplied = 0;
+            xlib::XGetWMNormalHints(conn.display, window, &mut hints, &mut supplied);
+
+            let flag = update(&mut hints);
+            if enable {
+                hints.flags |= flag;
+            } else {
+                hints.flags &= !flag;
+            }
+            xlib::XSetWMNormalHints(conn.display, window, &mut hints);
+            xlib::XFlush(conn.display);
+        });
+    }
+
+    /// Raise the window above its siblings
+    pub fn raise(&mut self) {
+        self.restack(None, xlib::Above);
+    }
+
+    /// Lower the window below its siblings
+    pub fn lower(&mut self) {
+        self.restack(None, xlib::Below);
+    }
+
+    /// Stack the window directly above another one
+    pub fn restack_above(&mut self, sibling: &X11Window) {
+        self.restack(Some(sibling.window), xlib::Above);
+    }
+
+    /// Stack the window directly below another one
+    pub fn restack_below(&mut self, sibling: &X11Window) {
+        self.restack(Some(sibling.window), xlib::Below);
+    }
+
+    fn restack(&self, sibling: Option<xlib::Window>, stack_mode: raw::c_int) {
+        let window = self.window;
+        self.conn.request(move |conn| unsafe {
+            if conn.wm_supports(conn.atoms.net_restack_window) {
+                // source indication 2: window managers honor pager
+                // requests under focus stealing prevention
+                let sibling = sibling.unwrap_or(0) as raw::c_long;
+                conn.send_client_message(
+                    window,
+                    conn.atoms.net_restack_window,
+                    [2, sibling, stack_mode as raw::c_long, 0, 0],
+                );
+            } else {
+                let mut changes: xlib::XWindowChanges = mem::zeroed();
+                changes.stack_mode = stack_mode;
+                let mut mask = xlib::CWStackMode;
+                if let Some(sibling) = sibling {
+                    changes.sibling = sibling;
+                    mask |= xlib::CWSibling;
+                }
+                // Falls back to a synthetic ConfigureRequest once the
+                // window is reparented and no longer a sibling
+                xlib::XReconfigureWMWindow(
+                    conn.display,
+                    window,
+                    conn.screen,
+                    mask as u32,
+                    &mut changes,
+                );
+            }
+            xlib::XFlush(conn.display);
+        });
+    }
+
+    /// Limit where the window receives pointer input, clicks elsewhere go
+    /// to the windows below.
+    ///
+    /// None restores the default of the whole window, an empty slice makes
+    /// it click-through entirely. Fails with Error::Unsupported when the
+    /// server lacks SHAPE 1.1
+    pub fn set_input_region(&mut self, region: Option<&[Rect]>) -> Result<(), Error> {
+        self.set_shape(SHAPE_INPUT, region, (1, 1))
+    }
+
+    /// Limit the visible area of the window to make it non-rectangular,
+    /// None restores the default of the whole window
+    pub fn set_shape_region(&mut self, region: Option<&[Rect]>) -> Result<(), Error> {
+        self.set_shape(SHAPE_BOUNDING, region, (1, 0))
+    }
+
+    fn set_shape(
+        &self,
+        kind: raw::c_int,
+        region: Option<&[Rect]>,
+        min_version: (raw::c_int, raw::c_int),
+    ) -> Result<(), Error> {
+        let window = self.window;
+        let rectangles = region.map(|region| {
+            region
+                .iter()
+                .map(|rect| xlib::XRectangle {
+                    x: rect.x as i16,
+                    y: rect.y as i16,
+                    width: rect.width as u16,
+                    height: rect.height as u16,
+                })
+                .collect::<Vec<_>>()
+        });
+        self.conn.request(move |conn| unsafe {
+            let xext = ext::xext().ok_or(Error::Unsupported("SHAPE"))?;
+            let (mut event_base, mut error_base) = (0, 0);
+            let (mut major, mut minor) = (0, 0);
+            if (xext.XShapeQueryExtension)(conn.display, &mut event_base, &mut error_base) == 0
+                || (xext.XShapeQueryVersion)(conn.display, &mut major, &mut minor) == 0
+                || (major, minor) < min_version
+            {
+                return Err(Error::Unsupported("SHAPE"));
+            }
+
+            match rectangles {
+                Some(mut rectangles) => (xext.XShapeCombineRectangles)(
+                    conn.display,
+                    window,
+                    kind,
+                    0,
+                    0,
+                    rectangles.as_mut_ptr(),
+                    rectangles.len() as raw::c_int,
+                    SHAPE_SET,
+                    xlib::Unsorted,
+                ),
+                // Setting no mask removes the shape
+                None => (xext.XShapeCombineMask)(conn.display, window, kind, 0, 0, 0, SHAPE_SET),
+            }
+            xlib::XFlush(conn.display);
+            Ok(())
+        })
+    }
+
+    /// Request keyboard focus for the window.
+    ///
+    /// Uses the timestamp of the last user event received by the event loop,
+    /// as window managers may ignore focus requests made with CurrentTime.
+    /// Embedded windows ask their embedder instead
+    pub fn focus(&mut self) {
+        let window = self.window;
+        if self.embedded {
+            // Only the embedder can move focus into its socket
+            if let Some(embedder) = self.state.borrow().embedder {
+                self.conn.request(move |conn| unsafe {
+                    xembed::send(conn, embedder, xembed::REQUEST_FOCUS);
+                    xlib::XFlush(conn.display);
+                });
+            }
+            return;
+        }
+        activate(&self.conn, window);
+    }
+
+    /// Ask the window manager to keep the user from interacting with the
+    /// owner of the window while it is shown, see
+    /// WindowBuilder::with_owner. Windows without an owner are modal for
+    /// every window of the application, if the WM supports it at all.
+    /// EventLoop::set_modal_input_blocking has the event loop enforce it
+    pub fn set_modal(&mut self, modal: bool) {
+        let mut state = self.state.borrow_mut();
+        state.modal = modal;
+        state.modal_blocked = false;
+        state.last_known.modal = modal;
+        update_modal(&self.conn, self.window, &state);
+        let atom = self.conn.atoms.net_wm_state_modal;
+        set_netwm_state(&self.conn, self.window, atom, modal);
+    }
+
+    /// Whether the window was made modal with set_modal
+    pub fn is_modal(&self) -> bool {
+        self.state.borrow().modal
+    }
+
+    /// Host a window of another client, e.g. the video window of a media
+    /// player, in `rect` of this window.
+    ///
+    /// The child is reparented into the window and kept inside `rect`
+    /// clipped to the window as it resizes, Event::EmbeddedChildDestroyed
+    /// tells when its client destroys it. X sends pointer input over the
+    /// child to the child, shape holes with set_input_region are not
+    /// needed. Should this client exit first the server gives the child
+    /// back to the root window. Fails with Error::NoSuchWindow when
+    /// there is no window `child`
+    pub fn embed_foreign(&mut self, child: xlib::Window, rect: Rect) -> Result<(), Error> {
+        let window = self.window;
+        let placed = foreign::clip(rect, self.state.borrow().last_known.size.into());
+        let embedded = self.conn.request(move |conn| unsafe {
+            let embedded = foreign::embed(conn, window, child, placed);
+            xlib::XFlush(conn.display);
+            embedded
+        });
+        if !embedded {
+            return Err(Error::NoSuchWindow);
+        }
+        let mut state = self.state.borrow_mut();
+        state.foreign.retain(|&(embedded, _)| embedded != child);
+        state.foreign.push((child, rect));
+        Ok(())
+    }
+
+    /// Move an embedded foreign window to `rect`, e.g. to lay it out
+    /// again on Event::Resized
+    pub fn set_foreign_rect(&mut self, child: xlib::Window, rect: Rect) {
+        let mut state = self.state.borrow_mut();
+        let size = state.last_known.size.into();
+        if let Some(entry) = state
+            .foreign
+            .iter_mut()
+            .find(|(embedded, _)| *embedded == child)
+        {
+            entry.1 = rect;
+            let placed = foreign::clip(rect, size);
+            self.conn.request(move |conn| unsafe {
+                foreign::place(conn, child, placed);
+                xlib::XFlush(conn.display);
+            });
+        }
+    }
+
+    /// Give an embedded foreign window back to the root window where it
+    /// is on the screen, without destroying it
+    pub fn remove_foreign(&mut self, child: xlib::Window) {
+        let mut state = self.state.borrow_mut();
+        let count = state.foreign.len();
+        state.foreign.retain(|&(embedded, _)| embedded != child);
+        if state.foreign.len() == count {
+            return;
+        }
+        let refocus = state.foreign_focus == Some(child) && state.focused;
+        if state.foreign_focus == Some(child) {
+            state.foreign_focus = None;
+        }
+        let window = self.window;
+        self.conn.request(move |conn| unsafe {
+            foreign::release(conn, child);
+            if refocus {
+                foreign::focus(conn, window, None);
+            }
+            xlib::XFlush(conn.display);
+        });
+    }
+
+    /// The foreign windows embedded with embed_foreign and their regions
+    pub fn foreign_windows(&self) -> Vec<(xlib::Window, Rect)> {
+        self.state.borrow().foreign.clone()
+    }
+
+    /// Pass keyboard focus on to an embedded foreign window, or take it
+    /// back with None. The child is given focus again whenever the
+    /// window is focused, the window itself reports Event::Focused with
+    /// false while keys go to the child
+    pub fn focus_foreign(&mut self, child: Option<xlib::Window>) {
+        let mut state = self.state.borrow_mut();
+        if child.is_some_and(|child| !state.foreign.iter().any(|&(embedded, _)| embedded == child))
+        {
+            return;
+        }
+        let previous = mem::replace(&mut state.foreign_focus, child);
+        let children: Vec<xlib::Window> = state.foreign.iter().map(|&(child, _)| child).collect();
+        let window = self.window;
+        self.conn.request(move |conn| unsafe {
+            if let Some(previous) = previous.filter(|&previous| Some(previous) != child) {
+                foreign::unfocus(conn, previous);
+            }
+            // Only move focus while it is with the window or its children,
+            // otherwise it moves once the window is focused
+            let (mut focus, mut revert) = (0, 0);
+            xlib::XGetInputFocus(conn.display, &mut focus, &mut revert);
+            if focus == window || children.contains(&focus) {
+                foreign::focus(conn, window, child);
+            }
+            xlib::XFlush(conn.display);
+        });
+    }
+
+    /// The current fullscreen mode
+    pub fn fullscreen(&self) -> Option<Fullscreen> {
+        self.state.borrow().fullscreen.clone()
+    }
+
+    /// Whether the window manager has the window maximized, as of the
+    /// last Event::StateChanged
+    pub fn is_maximized(&self) -> bool {
+        self.state.borrow().wm_state.maximized
+    }
+
+    /// The geometry, maximized and fullscreen state and monitor of the
+    /// window, for reopening it as it is with
+    /// WindowBuilder::with_restored_state.
+    ///
+    /// The geometry is the one of the last configure while neither
+    /// maximized nor fullscreen, which leaving both returns to
+    pub fn save_state(&self) -> SavedWindowState {
+        let normal = self.state.borrow().normal_geometry;
+        let geometry = normal.unwrap_or_else(|| {
+            let origin = self.root_origin();
+            let size = self.state.borrow().last_known.size;
+            Rect {
+                x: origin.x,
+                y: origin.y,
+                width: size.width,
+                height: size.height,
+            }
+        });
+        let monitor = self.current_monitor();
+        let state = self.state.borrow();
+        SavedWindowState {
+            geometry,
+            maximized: state.wm_state.maximized,
+            fullscreen: state.wm_state.fullscreen || state.fullscreen.is_some(),
+            monitor: monitor
+                .as_ref()
+                .map(|monitor| monitor.friendly_name().to_owned()),
+            monitor_position: monitor
+                .map_or(PhysicalPosition::new(0, 0), |monitor| monitor.position()),
+        }
+    }
+
+    /// Whether the window manager has the window fullscreen, as of the
+    /// last Event::StateChanged. Unlike fullscreen() this follows the
+    /// user toggling fullscreen through the window manager
+    pub fn is_fullscreen(&self) -> bool {
+        self.state.borrow().wm_state.fullscreen
+    }
+
+    /// Grab the pointer and keyboard while in exclusive fullscreen
+    pub fn set_exclusive_fullscreen_grab(&mut self, grab: bool) {
+        self.state.borrow_mut().exclusive_grab = grab;
+    }
+
+    /// Grab the keyboard so all keys, including window manager shortcuts
+    /// such as Alt+Tab, go to this window.
+    ///
+    /// The grab is released while the window loses focus or is unmapped
+    /// and taken again when it regains focus. Fails when the window is not
+    /// viewable yet or another client holds a keyboard grab
+    pub fn set_keyboard_grab(&mut self, grab: bool) -> Result<(), Error> {
+        let window = self.window;
+        self.conn.request(move |conn| unsafe {
+            let result = if grab {
+                grab_keyboard(conn, window)
+            } else {
+                ungrab_keyboard(conn);
+                Ok(())
+            };
+            xlib::XFlush(conn.display);
+            result
+        })?;
+        self.state.borrow_mut().keyboard_grab = grab;
+        Ok(())
+    }
+
+    /// Grab the pointer and confine it to the window, released and taken
+    /// again with focus like the keyboard grab. Both grabs can be held at
+    /// once
+    pub fn set_pointer_grab(&mut self, grab: bool) -> Result<(), Error> {
+        let window = self.window;
+        self.conn.request(move |conn| unsafe {
+            let result = if grab {
+                grab_pointer(conn, window)
+            } else {
+                ungrab_pointer(conn);
+                Ok(())
+            };
+            xlib::XFlush(conn.display);
+            result
+        })?;
+        self.state.borrow_mut().pointer_grab = grab;
+        Ok(())
+    }
+
+    /// Keep the pointer inside the window with XFixes pointer barriers
+    /// along its edges. Unlike the pointer grab other clients still get
+    /// pointer events, so drag and drop out of the window keeps working
+    /// once confinement is turned off.
+    ///
+    /// The barriers follow the window as it moves or resizes and are
+    /// removed while it is unfocused. Fails with Error::Unsupported without
+    /// XFixes 5.0, set_pointer_grab confines the pointer there instead
+    pub fn confine_cursor_with_barriers(&mut self, confine: bool) -> Result<(), Error> {
+        if confine {
+            self.conn
+                .request(|conn| unsafe { barriers_supported(conn) })?;
+        }
+        let window = self.window;
+        let focused = self.conn.request(move |conn| unsafe {
+            let (mut focus, mut revert_to) = (0, 0);
+            xlib::XGetInputFocus(conn.display, &mut focus, &mut revert_to);
+            focus == window
+        });
+        let mut state = self.state.borrow_mut();
+        state.barrier_confinement = confine;
+        update_barriers(&self.conn, window, &mut state, focused);
+        Ok(())
+    }
+
+    /// Whether the keyboard grab is requested, false after the escape
+    /// chord released it
+    pub fn has_keyboard_grab(&self) -> bool {
+        self.state.borrow().keyboard_grab
+    }
+
+    /// Whether the pointer grab is requested, see has_keyboard_grab
+    pub fn has_pointer_grab(&self) -> bool {
+        self.state.borrow().pointer_grab
+    }
+
+    /// Set the key chord which releases the keyboard and pointer grabs,
+    /// a modifier mask such as xlib::ControlMask and a keysym. It is
+    /// Ctrl+Alt+Escape by default, None disables it.
+    ///
+    /// The key press is still delivered as an Event::KeyboardInput
+    pub fn set_grab_escape(&mut self, chord: Option<(u32, xlib::KeySym)>) {
+        self.state.borrow_mut().grab_escape = chord;
+    }
+
+    /// Let the input method compose text for the window while it has
+    /// focus, delivered as Event::Text. Off by default, so games get their
+    /// key presses without the input method consuming any
+    pub fn set_ime_allowed(&mut self, allowed: bool) {
+        let window = self.window;
+        let focused = self.conn.request(move |conn| unsafe {
+            let (mut focus, mut revert_to) = (0, 0);
+            xlib::XGetInputFocus(conn.display, &mut focus, &mut revert_to);
+            focus == window
+        });
+        let mut state = self.state.borrow_mut();
+        state.ime_allowed = allowed;
+        update_input_context(&self.conn, window, &mut state, focused);
+    }
+
+    /// Tell the input method where the text cursor is, in window
+    /// coordinates, so its candidate window opens next to it
+    pub fn set_ime_cursor_area(
+        &mut self,
+        position: PhysicalPosition<i32>,
+        size: PhysicalSize<u32>,
+    ) {
+        let area = Rect {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        };
+        let mut state = self.state.borrow_mut();
+        state.ime_cursor_area = Some(area);
+        if let Some(context) = state.input_context {
+            self.conn.request(move |conn| unsafe {
+                context.set_cursor_area(area);
+                xlib::XFlush(conn.display);
+            });
+        }
+    }
+
+    /// Enter or leave fullscreen, leaving any previous fullscreen mode first
+    pub fn set_fullscreen(&mut self, fullscreen: Option<Fullscreen>) {
+        let previous = self.state.borrow().fullscreen.clone();
+        if previous == fullscreen {
+            return;
+        }
+
+        let atom = self.conn.atoms.net_wm_state_fullscreen;
+        match previous {
+            Some(Fullscreen::Exclusive(_)) => self.leave_exclusive(),
+            Some(Fullscreen::Borderless(_)) => set_netwm_state(&self.conn, self.window, atom, false),
+            None => {}
+        }
+
+        let fullscreen = match fullscreen {
+            Some(Fullscreen::Exclusive(mode)) => {
+                if self.enter_exclusive(mode) {
+                    Some(Fullscreen::Exclusive(mode))
+                } else {
+                    log::warn!("failed to switch to video mode {:?}", mode);
+                    None
+                }
+            }
+            Some(Fullscreen::Borderless(monitor)) => {
+                // The WM fullscreens on the monitor holding the window
+                if let Some(monitor) = &monitor {
+                    let window = self.window;
+                    let (x, y) = monitor.position().into();
+                    self.conn.request(move |conn| unsafe {
+                        xlib::XMoveWindow(conn.display, window, x, y);
+                    });
+                }
+                set_netwm_state(&self.conn, self.window, atom, true);
+                Some(Fullscreen::Borderless(monitor))
+            }
+            None => None,
+        };
+        set_bypass_compositor(&self.conn, self.window, fullscreen.is_some());
+        self.state.borrow_mut().fullscreen = fullscreen;
+    }
+
+    /// Ask for the window to be left out of screenshots and screencasts.
+    ///
+    /// X11 has no protocol for this, the hints compositors may read are
+    /// set instead and the result tells whether any of them is known on
+    /// the server. Properties outlive unmapping, so the hints hold while
+    /// the window is hidden and shown again
+    pub fn set_content_protected(&mut self, protected: bool) -> Protection {
+        self.state.borrow_mut().last_known.content_protected = protected;
+        let window = self.window;
+        let known = self.conn.request(move |conn| unsafe {
+            let mut known = false;
+            for hint in SKIP_CAPTURE_HINTS {
+                let name = CString::new(*hint).unwrap();
+                // Atoms exist once some client interned them, e.g. the
+                // compositor watching for the hint
+                known |= xlib::XInternAtom(conn.display, name.as_ptr(), xlib::True) != 0;
+                let atom = xlib::XInternAtom(conn.display, name.as_ptr(), xlib::False);
+                if protected {
+                    let value: raw::c_long = 1;
+                    xlib::XChangeProperty(
+                        conn.display,
+                        window,
+                        atom,
+                        xlib::XA_CARDINAL,
+                        32,
+                        xlib::PropModeReplace,
+                        &value as *const raw::c_long as *const u8,
+                        1,
+                    );
+                } else {
+                    xlib::XDeleteProperty(conn.display, window, atom);
+                }
+            }
+            xlib::XFlush(conn.display);
+            known
+        });
+        if known {
+            Protection::BestEffort
+        } else {
+            Protection::Unsupported
+        }
+    }
+
+    pub fn is_content_protected(&self) -> bool {
+        self.state.borrow().last_known.content_protected
+    }
+
+    /// Ask the window manager to draw a title bar and borders or leave
+    /// them out, e.g. for custom decorations moved with begin_manual_drag
+    pub fn set_decorations(&mut self, decorations: bool) {
+        self.state.borrow_mut().last_known.undecorated = !decorations;
+        if let Some(pending) = &mut self.pending {
+            pending.decorations = decorations;
+        }
+        if self.embedded {
+            return;
+        }
+        let window = self.window;
+        self.conn.request(move |conn| unsafe {
+            set_motif_decorations(conn, window, decorations);
+            xlib::XFlush(conn.display);
+        });
+    }
+
+    pub fn is_decorated(&self) -> bool {
+        !self.state.borrow().last_known.undecorated
+    }
+
+    /// Move the window with the pointer until the buttons held now are
+    /// released, e.g. from the title bar of custom decorations.
+    ///
+    /// Rather than leaving the move to the window manager, the event loop
+    /// moves the window on every motion, snapping it to the work area of
+    /// each monitor and to the loop's other windows as set with
+    /// EventLoop::set_snapping. Call it on a button press, it does
+    /// nothing while no button is held. Motion events must not be
+    /// filtered out, see EventLoop::set_event_filter
+    pub fn begin_manual_drag(&mut self) {
+        if self.embedded {
+            return;
+        }
+        let window = self.window;
+        let (_, relative, mask) = self.conn.request(move |conn| conn.query_pointer(window));
+        let buttons = xlib::Button1Mask
+            | xlib::Button2Mask
+            | xlib::Button3Mask
+            | xlib::Button4Mask
+            | xlib::Button5Mask;
+        if let Some(grab) = relative.filter(|_| mask & buttons != 0) {
+            self.state.borrow_mut().manual_drag = Some(ManualDrag::new(grab));
+        }
+    }
+
+    /// Move the window with the pointer until the buttons held now are
+    /// released, left to the window manager with _NET_WM_MOVERESIZE.
+    ///
+    /// Window managers without it, or ignoring it as listed among the
+    /// quirks of the wm module, get a drag of begin_manual_drag instead.
+    /// Call it on a button press, it does nothing while no button is held
+    pub fn drag_window(&mut self) {
+        if self.embedded {
+            return;
+        }
+        let by_wm = wm::drag_by_wm(self.conn.wm.lock().unwrap().as_ref());
+        if !by_wm {
+            self.begin_manual_drag();
+            return;
+        }
+        let window = self.window;
+        self.conn.request(move |conn| unsafe {
+            let ((x, y), _, mask) = conn.query_pointer(window);
+            let held = [
+                xlib::Button1Mask,
+                xlib::Button2Mask,
+                xlib::Button3Mask,
+                xlib::Button4Mask,
+                xlib::Button5Mask,
+            ];
+            let button = match held.iter().position(|&button| mask & button != 0) {
+                Some(index) => index + 1,
+                None => return,
+            };
+            // The window manager grabs the pointer for the move, which
+            // fails while the press still holds its implicit grab
+            ungrab_pointer(conn);
+            let mut xclient: xlib::XClientMessageEvent = mem::zeroed();
+            xclient.type_ = xlib::ClientMessage;
+            xclient.window = window;
+            xclient.message_type = conn.atoms.net_wm_moveresize;
+            xclient.format = 32;
+            xclient.data.set_long(0, x as raw::c_long);
+            xclient.data.set_long(1, y as raw::c_long);
+            // _NET_WM_MOVERESIZE_MOVE
+            xclient.data.set_long(2, 8);
+            xclient.data.set_long(3, button as raw::c_long);
+            // source indication 1: request from a normal application
+            xclient.data.set_long(4, 1);
+            let mut event = xlib::XEvent::from(xclient);
+            xlib::XSendEvent(
+                conn.display,
+                conn.root,
+                xlib::False,
+                xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask,
+                &mut event,
+            );
+            xlib::XFlush(conn.display);
+        });
+    }
+
+    /// Whether a point relative to the root window lies inside the window
+    /// contents while the window is viewable. Other windows covering it
+    /// are not considered, see EventLoop::window_at
+    pub fn contains_global_point(&self, point: PhysicalPosition<i32>) -> bool {
+        let window = self.window;
+        self.conn
+            .request(move |conn| unsafe { viewable_root_rect(conn, window) })
+            .is_some_and(|rect| rect.contains(point.x, point.y))
+    }
+
+    /// Convert a position in the window contents to one relative to the
+    /// root window, where Position::At places windows.
+    ///
+    /// Uses the origin cached from pointer events and the configures of
+    /// the window manager, so the server is only asked when nothing was
+    /// cached yet, see refresh_geometry
+    pub fn window_to_root(&self, x: f64, y: f64) -> PhysicalPosition<f64> {
+        let origin = self.root_origin();
+        PhysicalPosition::new(origin.x as f64 + x, origin.y as f64 + y)
+    }
+
+    /// The inverse of window_to_root, e.g. for a root position from
+    /// EventLoop::global_cursor_position
+    pub fn root_to_window(&self, x: f64, y: f64) -> PhysicalPosition<f64> {
+        let origin = self.root_origin();
+        PhysicalPosition::new(x - origin.x as f64, y - origin.y as f64)
+    }
+
+    /// Convert a position in the window contents to one relative to the
+    /// top left of a monitor, which need not contain it
+    pub fn window_to_monitor(
+        &self,
+        x: f64,
+        y: f64,
+        monitor: &MonitorHandle,
+    ) -> PhysicalPosition<f64> {
+        let root = self.window_to_root(x, y);
+        let origin = monitor.position();
+        PhysicalPosition::new(root.x - origin.x as f64, root.y - origin.y as f64)
+    }
+
+    /// Query the position and size of the window again for the
+    /// coordinate conversions, in case it moved without any event saying
+    /// so, e.g. along with a frame of a window manager not sending
+    /// synthetic configures
+    pub fn refresh_geometry(&self) {
+        let window = self.window;
+        let (origin, size) = self.conn.request(move |conn| unsafe {
+            let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+            xlib::XGetWindowAttributes(conn.display, window, &mut attributes);
+            let (mut x, mut y, mut child) = (0, 0, 0);
+            xlib::XTranslateCoordinates(
+                conn.display,
+                window,
+                conn.root,
+                0,
+                0,
+                &mut x,
+                &mut y,
+                &mut child,
+            );
+            (
+                PhysicalPosition::new(x, y),
+                PhysicalSize::new(attributes.width as u32, attributes.height as u32),
+            )
+        });
+        let mut state = self.state.borrow_mut();
+        state.root_origin = Some(origin);
+        state.last_known.size = size;
+    }
+
+    fn root_origin(&self) -> PhysicalPosition<i32> {
+        if self.state.borrow().root_origin.is_none() {
+            self.refresh_geometry();
+        }
+        self.state.borrow().root_origin.unwrap_or_default()
+    }
+
+    /// Best effort guess whether frames reach the display without the
+    /// compositor redirecting them: no compositor is running, or the
+    /// window is fullscreen and asked to bypass it. Compositors may
+    /// ignore the request
+    pub fn is_compositor_bypassed(&self) -> bool {
+        self.state.borrow().fullscreen.is_some()
+            || !self.conn.request(XConnection::compositor_running)
+    }
+
+    fn enter_exclusive(&mut self, mode: VideoMode) -> bool {
+        let switch = match ModeSwitch::new(&self.conn, mode) {
+            Some(switch) => switch,
+            None => return false,
+        };
+
+        let window = self.window;
+        let (x, y) = switch.position();
+        let (width, height) = mode.size().into();
+        let grab = self.state.borrow().exclusive_grab;
+        let geometry = self.conn.request(move |conn| unsafe {
+            let display = conn.display;
+            let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+            xlib::XGetWindowAttributes(display, window, &mut attributes);
+            let (mut root_x, mut root_y, mut child) = (0, 0, 0);
+            xlib::XTranslateCoordinates(
+                display, window, conn.root, 0, 0, &mut root_x, &mut root_y, &mut child,
+            );
+
+            // Bypass the WM so nothing decorates or moves the window
+            let mut set_attributes: xlib::XSetWindowAttributes = mem::zeroed();
+            set_attributes.override_redirect = xlib::True;
+            xlib::XUnmapWindow(display, window);
+            xlib::XChangeWindowAttributes(
+                display,
+                window,
+                xlib::CWOverrideRedirect,
+                &mut set_attributes,
+            );
+            xlib::XMoveResizeWindow(display, window, x, y, width, height);
+            xlib::XMapRaised(display, window);
+            xlib::XSync(display, xlib::False);
+
+            xlib::XSetInputFocus(display, window, xlib::RevertToParent, xlib::CurrentTime);
+            if grab {
+                grab_input(conn, window);
+            }
+            xlib::XFlush(display);
+
+            (
+                root_x,
+                root_y,
+                attributes.width as u32,
+                attributes.height as u32,
+            )
+        });
+
+        let mut state = self.state.borrow_mut();
+        state.mode_switch = Some(switch);
+        state.windowed_geometry = Some(geometry);
+        true
+    }
+
+    fn leave_exclusive(&mut self) {
+        let geometry = {
+            let mut state = self.state.borrow_mut();
+            // Dropping the switch restores the desktop mode
+            state.mode_switch = None;
+            state.windowed_geometry.take()
+        };
+
+        let window = self.window;
+        self.conn.request(move |conn| unsafe {
+            let display = conn.display;
+            // Grabs set with set_keyboard_grab and set_pointer_grab are
+            // taken again once the remapped window has focus
+            ungrab_pointer(conn);
+            ungrab_keyboard(conn);
+
+            let mut set_attributes: xlib::XSetWindowAttributes = mem::zeroed();
+            set_attributes.override_redirect = xlib::False;
+            xlib::XUnmapWindow(display, window);
+            xlib::XChangeWindowAttributes(
+                display,
+                window,
+                xlib::CWOverrideRedirect,
+                &mut set_attributes,
+            );
+            if let Some((x, y, width, height)) = geometry {
+                xlib::XMoveResizeWindow(display, window, x, y, width, height);
+            }
+            xlib::XMapWindow(display, window);
+            xlib::XFlush(display);
+        });
+    }
+}
+
+unsafe impl HasRawWindowHandle for X11Window {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        let mut xlib_handle = XlibWindowHandle::empty();
+        xlib_handle.visual_id = 0;
+        xlib_handle.window = self.window;
+        RawWindowHandle::Xlib(xlib_handle)
+    }
+}
+
+unsafe impl HasRawDisplayHandle for X11Window {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        let mut xlib_handle = XlibDisplayHandle::empty();
+        xlib_handle.display = self.display as *mut c_void;
+        xlib_handle.screen = self.screen;
+        RawDisplayHandle::Xlib(xlib_handle)
+    }
+}
+
+impl Drop for X11Window {
+    /// Destroys the window, or gives an adopted one back to the
+    /// application. The display stays open while the event loop or other
+    /// windows still use it
+    fn drop(&mut self) {
+        self.alive.store(false, Ordering::Release);
+        destroy(&self.conn, self.window, &mut self.state.borrow_mut());
+    }
+}
+
+/// Destroy the window along with its input context, barriers and sync
+/// counter, unless that already happened. Embedded foreign windows are
+/// given back to the root window, adopted windows to the application
+pub(crate) fn destroy(conn: &XConnection, window: xlib::Window, state: &mut WindowState) {
+    if mem::replace(&mut state.destroyed, true) {
+        return;
+    }
+    update_modal(conn, window, state);
+    let adopted_mask = state.adopted_mask;
+    let context = state.input_context.take();
+    let barriers = mem::take(&mut state.barriers);
+    let sync_counter = state.sync_counter.take();
+    let foreign = mem::take(&mut state.foreign);
+    conn.request(move |conn| unsafe {
+        // Destroying the window would destroy its children with it
+        for (child, _) in foreign {
+            foreign::release(conn, child);
+        }
+        if let Some(context) = context {
+            context.destroy();
+        }
+        destroy_barriers(conn, &barriers);
+        if let Some(counter) = sync_counter {
+            resize::destroy_sync_counter(conn, counter);
+        }
+        match adopted_mask {
+            Some(mask) => {
+                xlib::XSelectInput(conn.display, window, mask);
+                xlib::XFlush(conn.display);
+                conn.released.lock().unwrap().push(window);
+            }
+            None => {
+                xlib::XDestroyWindow(conn.display, window);
+            }
+        }
+    });
+}
+
+/// Record whether the window is a shown modal window of its owner, which
+/// the event loop looks up by owner to block its input
+fn update_modal(conn: &XConnection, window: xlib::Window, state: &WindowState) {
+    let owner = match state.last_known.transient_for {
+        Some(owner) => owner,
+        None => return,
+    };
+    let mut modals = conn.modals.lock().unwrap();
+    if state.modal && state.last_known.shown && !state.destroyed {
+        modals.insert(owner, window);
+    } else if modals.get(&owner) == Some(&window) {
+        modals.remove(&owner);
+    }
+}
+
+/// Set the decorations flag of _MOTIF_WM_HINTS, which window managers
+/// follow for borders and title bars
+unsafe fn set_motif_decorations(conn: &XConnection, window: xlib::Window, decorations: bool) {
+    const MWM_HINTS_DECORATIONS: raw::c_long = 1 << 1;
+    // Flags, functions, decorations, input mode and status
+    let hints: [raw::c_long; 5] = [MWM_HINTS_DECORATIONS, 0, decorations as raw::c_long, 0, 0];
+    xlib::XChangeProperty(
+        conn.display,
+        window,
+        conn.atoms.motif_wm_hints,
+        conn.atoms.motif_wm_hints,
+        32,
+        xlib::PropModeReplace,
+        hints.as_ptr() as *const u8,
+        hints.len() as raw::c_int,
+    );
+}
+
+/// The monitor containing the center of the window
+pub(crate) fn current_monitor(conn: &XConnection, window: xlib::Window) -> Option<MonitorHandle> {
+    conn.request(move |conn| unsafe {
+        let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+        xlib::XGetWindowAttributes(conn.display, window, &mut attributes);
+        let (mut x, mut y, mut child) = (0, 0, 0);
+        xlib::XTranslateCoordinates(
+            conn.display,
+            window,
+            conn.root,
+            attributes.width / 2,
+            attributes.height / 2,
+            &mut x,
+            &mut y,
+            &mut child,
+        );
+        monitor::containing(monitor::monitors(conn), (x, y))
+    })
+}
+
+/// Convert a size on the window's monitor, which is only queried for
+/// logical and work area sizes
+pub(crate) fn to_physical(
+    conn: &XConnection,
+    window: xlib::Window,
+    size: Size,
+) -> PhysicalSize<u32> {
+    match size {
+        Size::Physical(size) => size,
+        size => match current_monitor(conn, window) {
+            Some(monitor) => monitor.resolve_size(size),
+            None => size.to_physical(1.0),
+        },
+    }
+}
+
+// The requests behind X11Window setters, also made for WindowProxy
+
+pub(crate) fn set_inner_size(conn: &XConnection, window: xlib::Window, size: Size) {
+    let size = to_physical(conn, window, size);
+    conn.request(move |conn| unsafe {
+        let (width, height) = (size.width.max(1), size.height.max(1));
+        xlib::XResizeWindow(conn.display, window, width, height);
+        xlib::XFlush(conn.display);
+    });
+}
+
+/// Raise and focus the window through the window manager, see
+/// X11Window::focus
+pub(crate) fn activate(conn: &XConnection, window: xlib::Window) {
+    conn.request(move |conn| unsafe {
+        let last_event_time = conn.last_event_time.load(Ordering::Relaxed);
+
+        // EWMH window managers only reliably raise and focus through
+        // _NET_ACTIVE_WINDOW under focus stealing prevention.
+        let mut xclient: xlib::XClientMessageEvent = mem::zeroed();
+        xclient.type_ = xlib::ClientMessage;
+        xclient.window = window;
+        xclient.message_type = conn.atoms.net_active_window;
+        xclient.format = 32;
+        // source indication 1: request from a normal application
+        xclient.data.set_long(0, 1);
+        xclient.data.set_long(1, last_event_time as raw::c_long);
+        xclient.data.set_long(2, 0);
+
+        let mut event = xlib::XEvent::from(xclient);
+        xlib::XSendEvent(
+            conn.display,
+            conn.root,
+            xlib::False,
+            xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask,
+            &mut event,
+        );
+
+        // Focusing an unmapped window is a BadMatch error.
+        let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+        xlib::XGetWindowAttributes(conn.display, window, &mut attributes);
+        if attributes.map_state == xlib::IsViewable {
+            xlib::XSetInputFocus(conn.display, window, xlib::RevertToParent, last_event_time);
+        }
+
+        xlib::XFlush(conn.display);
+    });
+}
+
+/// Add `atom` to _NET_WM_STATE of the window or remove it
+fn set_netwm_state(conn: &XConnection, window: xlib::Window, atom: xlib::Atom, enabled: bool) {
+    conn.request(move |conn| unsafe {
+        let atoms = &conn.atoms;
+        let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+        xlib::XGetWindowAttributes(conn.display, window, &mut attributes);
+
+        // Before mapping the WM reads the property, afterwards it only
+        // listens to client messages, some only once managing the window
+        let mapped = attributes.map_state != xlib::IsUnmapped;
+        let request = wm::state_request(
+            conn.wm.lock().unwrap().as_ref(),
+            mapped,
+            mapped && conn.is_managed(window),
+        );
+        if request == StateRequest::Property {
+            // Keeping the other states set before mapping
+            let mut state = conn.atom_list(window, atoms.net_wm_state);
+            state.retain(|&set| set != atom);
+            if enabled {
+                state.push(atom);
+            }
+            xlib::XChangeProperty(
+                conn.display,
+                window,
+                atoms.net_wm_state,
+                xlib::XA_ATOM,
+                32,
+                xlib::PropModeReplace,
+                state.as_ptr() as *const u8,
+                state.len() as raw::c_int,
+            );
+        } else {
+            let mut xclient: xlib::XClientMessageEvent = mem::zeroed();
+            xclient.type_ = xlib::ClientMessage;
+            xclient.window = window;
+            xclient.message_type = atoms.net_wm_state;
+            xclient.format = 32;
+            xclient.data.set_long(0, enabled as raw::c_long);
+            xclient.data.set_long(1, atom as raw::c_long);
+            xclient.data.set_long(2, 0);
+            // source indication 1: request from a normal application
+            xclient.data.set_long(3, 1);
+
+            let mut event = xlib::XEvent::from(xclient);
+            xlib::XSendEvent(
+                conn.display,
+                conn.root,
+                xlib::False,
+                xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask,
+                &mut event,
+            );
+        }
+        xlib::XFlush(conn.display);
+    });
+}
+
+/// Ask the compositor to unredirect the window while fullscreen, so
+/// frames reach the display directly and variable refresh can engage
+fn set_bypass_compositor(conn: &XConnection, window: xlib::Window, bypass: bool) {
+    conn.request(move |conn| unsafe {
+        let atom = conn.atoms.net_wm_bypass_compositor;
+        if bypass {
+            let value: raw::c_long = 1;
+            xlib::XChangeProperty(
+                conn.display,
+                window,
+                atom,
+                xlib::XA_CARDINAL,
+                32,
+                xlib::PropModeReplace,
+                &value as *const raw::c_long as *const u8,
+                1,
+            );
+        } else {
+            xlib::XDeleteProperty(conn.display, window, atom);
+        }
+        xlib::XFlush(conn.display);
+    });
+}
+
+/// Enter borderless fullscreen on the current monitor or leave it, for
+/// changes made without the X11Window. Exclusive fullscreen is left as
+/// it is, leaving it restores geometry only the X11Window keeps
+pub(crate) fn toggle_fullscreen(conn: &XConnection, window: xlib::Window, state: &mut WindowState) {
+    let fullscreen = match state.fullscreen {
+        None => Some(Fullscreen::Borderless(None)),
+        Some(Fullscreen::Borderless(_)) => None,
+        Some(Fullscreen::Exclusive(_)) => return,
+    };
+    let atom = conn.atoms.net_wm_state_fullscreen;
+    set_netwm_state(conn, window, atom, fullscreen.is_some());
+    set_bypass_compositor(conn, window, fullscreen.is_some());
+    state.fullscreen = fullscreen;
+}
+
+pub(crate) fn set_title(conn: &XConnection, window: xlib::Window, title: &str) {
+    let title = title.to_owned();
+    conn.request(move |conn| unsafe {
+        store_title(conn, window, &title);
+        xlib::XFlush(conn.display);
+    });
+}
+
+/// Write the title as window and icon name, in UTF-8 for EWMH and in the
+/// legacy encodings for window managers predating it
+fn store_title(conn: &XConnection, window: xlib::Window, title: &str) {
+    let (name, icon_name) = (conn.atoms.net_wm_name, conn.atoms.net_wm_icon_name);
+    conn.set_text_property(window, xlib::XA_WM_NAME, Some(name), title);
+    conn.set_text_property(window, xlib::XA_WM_ICON_NAME, Some(icon_name), title);
+}
+
+/// Define the cursor of the window, or hide it and have the application
+/// draw it when the server has no cursor of the shape
+pub(crate) fn set_cursor_icon(
+    conn: &XConnection,
+    window: xlib::Window,
+    state: &mut WindowState,
+    icon: CursorIcon,
+) {
+    state.last_known.cursor_icon = Some(icon);
+    let forced = backend::env_set("DASWIN_SOFTWARE_CURSOR");
+    let shown = conn.request(move |conn| unsafe {
+        let cursor = if forced {
+            0
+        } else if icon == CursorIcon::Default {
+            xlib::XUndefineCursor(conn.display, window);
+            xlib::XFlush(conn.display);
+            return true;
+        } else {
+            server_cursor(conn, icon)
+        };
+        // Hidden behind a blank cursor when the application draws it
+        let shown = cursor != 0;
+        let cursor = if shown { cursor } else { blank_cursor(conn) };
+        xlib::XDefineCursor(conn.display, window, cursor);
+        // The window keeps the cursor alive
+        xlib::XFreeCursor(conn.display, cursor);
+        xlib::XFlush(conn.display);
+        shown
+    });
+    if !shown {
+        log::info!(
+            "no cursor for {:?} on the server, drawing it in software",
+            icon
+        );
+    }
+    // Software cursors show the arrow where the parent's cursor would be
+    state.software_cursor = match icon {
+        _ if shown => None,
+        CursorIcon::Default => Some(CursorIcon::Arrow),
+        icon => Some(icon),
+    };
+}
+
+/// A cursor of the shape from the cursor theme, or from the cursor font
+/// without one. 0 when the server has neither
+unsafe fn server_cursor(conn: &XConnection, icon: CursorIcon) -> xlib::Cursor {
+    if let Some(xcursor) = ext::xcursor() {
+        for name in icon.theme_names() {
+            let name = CString::new(*name).unwrap();
+            let cursor = (xcursor.XcursorLibraryLoadCursor)(conn.display, name.as_ptr());
+            if cursor != 0 {
+                return cursor;
+            }
+        }
+    }
+    match icon.font_glyph() {
+        Some(glyph) if conn.has_cursor_font() => xlib::XCreateFontCursor(conn.display, glyph),
+        _ => 0,
+    }
+}
+
+/// An invisible cursor, from an empty one pixel bitmap
+unsafe fn blank_cursor(conn: &XConnection) -> xlib::Cursor {
+    let pixmap = xlib::XCreatePixmap(conn.display, conn.root, 1, 1, 1);
+    let gc = xlib::XCreateGC(conn.display, pixmap, 0, ptr::null_mut());
+    xlib::XFillRectangle(conn.display, pixmap, gc, 0, 0, 1, 1);
+    xlib::XFreeGC(conn.display, gc);
+    let mut color: xlib::XColor = mem::zeroed();
+    let cursor =
+        xlib::XCreatePixmapCursor(conn.display, pixmap, pixmap, &mut color, &mut color, 0, 0);
+    xlib::XFreePixmap(conn.display, pixmap);
+    cursor
+}
+
+/// Create the input context once IME input is allowed, with input method
+/// focus while IME input is allowed and the window is focused
+pub(crate) fn update_input_context(
+    conn: &XConnection,
+    window: xlib::Window,
+    state: &mut WindowState,
+    focused: bool,
+) {
+    let (allowed, area, context) = (
+        state.ime_allowed,
+        state.ime_cursor_area,
+        state.input_context,
+    );
+    if !allowed && context.is_none() {
+        return;
+    }
+    state.input_context = conn.request(move |conn| unsafe {
+        let context = match context {
+            Some(context) => context,
+            None => conn.input_method()?.create_context(window, area)?,
+        };
+        context.set_focus(allowed && focused);
+        xlib::XFlush(conn.display);
+        Some(context)
+    });
+}
+
+/// The parts of _NET_WM_STATE reported through Event::StateChanged
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct WmState {
+    pub maximized: bool,
+    pub fullscreen: bool,
+    pub demands_attention: bool,
+}
+
+impl WmState {
+    /// Read the state the window manager set on the window
+    pub fn query(conn: &XConnection, window: xlib::Window) -> WmState {
+        let atoms = &conn.atoms;
+        let state = conn.atom_list(window, atoms.net_wm_state);
+        // Maximized in one direction only is not maximized
+        WmState {
+            maximized: state.contains(&atoms.net_wm_state_maximized_vert)
+                && state.contains(&atoms.net_wm_state_maximized_horz),
+            fullscreen: state.contains(&atoms.net_wm_state_fullscreen),
+            demands_attention: state.contains(&atoms.net_wm_state_demands_attention),
+        }
+    }
+
+    /// The transitions from this state to the next one
+    pub fn changes(self, next: WmState) -> impl Iterator<Item = StateChange> {
+        let maximized = match (self.maximized, next.maximized) {
+            (false, true) => Some(StateChange::Maximized),
+            (true, false) => Some(StateChange::Unmaximized),
+            _ => None,
+        };
+        let fullscreen = match (self.fullscreen, next.fullscreen) {
+            (false, true) => Some(StateChange::FullscreenEntered),
+            (true, false) => Some(StateChange::FullscreenExited),
+            _ => None,
+        };
+        let attention = Some(StateChange::AttentionCleared)
+            .filter(|_| self.demands_attention && !next.demands_attention);
+        maximized.into_iter().chain(fullscreen).chain(attention)
+    }
+}
+
+/// XFixes 5.0 introduced pointer barriers
+unsafe fn barriers_supported(conn: &XConnection) -> Result<(), Error> {
+    let xfixes = ext::xfixes().ok_or(Error::Unsupported("XFixes"))?;
+    let (mut event_base, mut error_base) = (0, 0);
+    // The version the client supports goes in, the server's comes out
+    let (mut major, mut minor) = (5, 0);
+    if (xfixes.XFixesQueryExtension)(conn.display, &mut event_base, &mut error_base) == 0
+        || (xfixes.XFixesQueryVersion)(conn.display, &mut major, &mut minor) == 0
+        || major < 5
+    {
+        return Err(Error::Unsupported("XFixes 5.0"));
+    }
+    Ok(())
+}
+
+unsafe fn destroy_barriers(conn: &XConnection, barriers: &[xlib::XID]) {
+    if let Some(xfixes) = ext::xfixes() {
+        for &barrier in barriers {
+            (xfixes.XFixesDestroyPointerBarrier)(conn.display, barrier);
+        }
+    }
+}
+
+/// Replace the pointer barriers with ones along the window's current
+/// edges while confinement is requested and the window is focused, called
+/// when it moves, resizes or changes focus
+pub(crate) fn update_barriers(
+    conn: &XConnection,
+    window: xlib::Window,
+    state: &mut WindowState,
+    focused: bool,
+) {
+    let create = state.barrier_confinement && focused;
+    let old = mem::take(&mut state.barriers);
+    if !create && old.is_empty() {
+        return;
+    }
+    state.barriers = conn.request(move |conn| unsafe {
+        destroy_barriers(conn, &old);
+        let xfixes = match ext::xfixes() {
+            Some(xfixes) if create => xfixes,
+            _ => {
+                xlib::XFlush(conn.display);
+                return Vec::new();
+            }
+        };
+
+        let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+        xlib::XGetWindowAttributes(conn.display, window, &mut attributes);
+        let (mut left, mut top, mut child) = (0, 0, 0);
+        xlib::XTranslateCoordinates(
+            conn.display,
+            window,
+            conn.root,
+            0,
+            0,
+            &mut left,
+            &mut top,
+            &mut child,
+        );
+        // The pointer stays on the inner side of each barrier, which only
+        // lets it through inwards
+        let (right, bottom) = (left + attributes.width, top + attributes.height);
+        let edges = [
+            (left, top, left, bottom, ext::BARRIER_POSITIVE_X),
+            (right, top, right, bottom, ext::BARRIER_NEGATIVE_X),
+            (left, top, right, top, ext::BARRIER_POSITIVE_Y),
+            (left, bottom, right, bottom, ext::BARRIER_NEGATIVE_Y),
+        ];
+        let barriers = edges
+            .iter()
+            .map(|&(x1, y1, x2, y2, directions)| {
+                (xfixes.XFixesCreatePointerBarrier)(
+                    conn.display,
+                    conn.root,
+                    x1,
+                    y1,
+                    x2,
+                    y2,
+                    directions,
+                    0,
+                    ptr::null_mut(),
+                )
+            })
+            .collect();
+        xlib::XFlush(conn.display);
+        barriers
+    });
+}
+
+/// Grab the keyboard to the window, reporting keys to it alone
+/// The contents of a window relative to the root, None unless the window
+/// and all its ancestors are mapped
+unsafe fn viewable_root_rect(conn: &XConnection, window: xlib::Window) -> Option<Rect> {
+    let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+    if xlib::XGetWindowAttributes(conn.display, window, &mut attributes) == 0
+        || attributes.map_state != xlib::IsViewable
+    {
+        return None;
+    }
+    let (mut x, mut y, mut child) = (0, 0, 0);
+    xlib::XTranslateCoordinates(
+        conn.display,
+        window,
+        conn.root,
+        0,
+        0,
+        &mut x,
+        &mut y,
+        &mut child,
+    );
+    Some(Rect {
+        x,
+        y,
+        width: attributes.width as u32,
+        height: attributes.height as u32,
+    })
+}
+
+/// The children of a window from the bottom to the top of the stack,
+/// along with its parent
+unsafe fn query_tree(
+    conn: &XConnection,
+    window: xlib::Window,
+) -> (Vec<xlib::Window>, xlib::Window) {
+    let (mut root, mut parent) = (0, 0);
+    let mut children = ptr::null_mut();
+    let mut count = 0;
+    if xlib::XQueryTree(
+        conn.display,
+        window,
+        &mut root,
+        &mut parent,
+        &mut children,
+        &mut count,
+    ) == 0
+    {
+        return (Vec::new(), 0);
+    }
+    let list = if children.is_null() {
+        Vec::new()
+    } else {
+        let list = std::slice::from_raw_parts(children, count as usize).to_vec();
+        xlib::XFree(children as *mut c_void);
+        list
+    };
+    (list, parent)
+}
+
+/// The topmost of `windows` at a point relative to the root. The child of
+/// the root on top at the point is found in stacking order, which for
+/// managed windows is the frame of the WM, the point must then lie in one
+/// of our windows inside it
+pub(crate) unsafe fn window_at(
+    conn: &XConnection,
+    windows: &[(xlib::Window, WindowId)],
+    (x, y): (i32, i32),
+) -> Option<WindowId> {
+    let (children, _) = query_tree(conn, conn.root);
+    let top = children.into_iter().rev().find(|&child| {
+        let mut attributes: xlib::XWindowAttributes = mem::zeroed();
+        xlib::XGetWindowAttributes(conn.display, child, &mut attributes) != 0
+            && attributes.map_state == xlib::IsViewable
+            && attributes.class == xlib::InputOutput
+            && Rect {
+                x: attributes.x,
+                y: attributes.y,
+                width: (attributes.width + 2 * attributes.border_width) as u32,
+                height: (attributes.height + 2 * attributes.border_width) as u32,
+            }
+            .contains(x, y)
+    })?;
+
+    let inside_top = |mut window: xlib::Window| {
+        while window != 0 && window != conn.root {
+            if window == top {
+                return true;
+            }
+            window = query_tree(conn, window).1;
+        }
+        false
+    };
+    windows
+        .iter()
+        .filter(|&&(window, _)| {
+            viewable_root_rect(conn, window).is_some_and(|rect| rect.contains(x, y))
+        })
+        .find(|&&(window, _)| inside_top(window))
+        .map(|&(_, id)| id)
+}
+
+// Grabs register their release with the session, see SessionGuard
+pub(crate) unsafe fn grab_keyboard(conn: &XConnection, window: xlib::Window) -> Result<(), Error> {
+    grab_status(xlib::XGrabKeyboard(
+        conn.display,
+        window,
+        xlib::False,
+        xlib::GrabModeAsync,
+        xlib::GrabModeAsync,
+        xlib::CurrentTime,
+    ))?;
+    session::register(conn, Undo::UngrabKeyboard);
+    Ok(())
+}
+
+/// Grab the pointer to the window and confine it there
+pub(crate) unsafe fn grab_pointer(conn: &XConnection, window: xlib::Window) -> Result<(), Error> {
+    let mask = xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::PointerMotionMask;
+    grab_status(xlib::XGrabPointer(
+        conn.display,
+        window,
+        xlib::False,
+        mask as raw::c_uint,
+        xlib::GrabModeAsync,
+        xlib::GrabModeAsync,
+        window,
+        0,
+        xlib::CurrentTime,
+    ))?;
+    session::register(conn, Undo::UngrabPointer);
+    Ok(())
+}
+
+pub(crate) unsafe fn ungrab_keyboard(conn: &XConnection) {
+    xlib::XUngrabKeyboard(conn.display, xlib::CurrentTime);
+    session::done(conn, &Undo::UngrabKeyboard);
+}
+
+pub(crate) unsafe fn ungrab_pointer(conn: &XConnection) {
+    xlib::XUngrabPointer(conn.display, xlib::CurrentTime);
+    session::done(conn, &Undo::UngrabPointer);
+}
+
+fn grab_status(status: raw::c_int) -> Result<(), Error> {
+    match status {
+        xlib::GrabSuccess => Ok(()),
+        xlib::GrabNotViewable => Err(Error::NotViewable),
+        _ => Err(Error::AlreadyGrabbed),
+    }
+}
+
+/// Grab the pointer and keyboard to the window
+pub(crate) unsafe fn grab_input(conn: &XConnection, window: xlib::Window) {
+    let display = conn.display;
+    let mask = xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::PointerMotionMask;
+    let pointer = xlib::XGrabPointer(
+        display,
+        window,
+        xlib::True,
+        mask as raw::c_uint,
+        xlib::GrabModeAsync,
+        xlib::GrabModeAsync,
+        window,
+        0,
+        xlib::CurrentTime,
+    );
+    if pointer == xlib::GrabSuccess {
+        session::register(conn, Undo::UngrabPointer);
+    }
+    let keyboard = xlib::XGrabKeyboard(
+        display,
+        window,
+        xlib::True,
+        xlib::GrabModeAsync,
+        xlib::GrabModeAsync,
+        xlib::CurrentTime,
+    );
+    if keyboard == xlib::GrabSuccess {
+        session::register(conn, Undo::UngrabKeyboard);
+    }
+}
@@ -0,0 +1,273 @@
+//! Which window manager runs and what it supports, see
+//! EventLoop::window_manager.
+//!
+//! The window manager names itself on the window _NET_SUPPORTING_WM_CHECK
+//! of the root window points to, and lists the hints it supports in
+//! _NET_SUPPORTED. Some of them advertise hints they handle differently
+//! from other window managers. Those differences are listed in QUIRKS,
+//! which the crate consults where they change what it asks for
+
+use x11::xlib;
+
+use crate::xconn::{Atoms, XConnection};
+
+/// What a window manager may support, named after the EWMH hints it
+/// lists in _NET_SUPPORTED for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Feature {
+    /// _NET_WM_STATE_FULLSCREEN, see X11Window::set_fullscreen
+    Fullscreen,
+    /// _NET_WM_STATE_MAXIMIZED_VERT and _HORZ
+    Maximize,
+    /// _NET_WM_STATE_DEMANDS_ATTENTION
+    DemandsAttention,
+    /// _NET_WM_MOVERESIZE, see X11Window::drag_window
+    MoveResize,
+    /// _NET_RESTACK_WINDOW, see X11Window::restack_above
+    RestackWindow,
+    /// _NET_ACTIVE_WINDOW
+    ActiveWindow,
+    /// _NET_WORKAREA, see MonitorHandle::work_area
+    WorkArea,
+    /// _NET_FRAME_EXTENTS
+    FrameExtents,
+    /// _NET_WM_PING
+    Ping,
+    /// _NET_WM_SYNC_REQUEST
+    SyncRequest,
+}
+
+const FEATURES: [Feature; 10] = [
+    Feature::Fullscreen,
+    Feature::Maximize,
+    Feature::DemandsAttention,
+    Feature::MoveResize,
+    Feature::RestackWindow,
+    Feature::ActiveWindow,
+    Feature::WorkArea,
+    Feature::FrameExtents,
+    Feature::Ping,
+    Feature::SyncRequest,
+];
+
+impl Feature {
+    /// The hints listed for the feature, all of them must be
+    fn hints(self, atoms: &Atoms) -> Vec<xlib::Atom> {
+        match self {
+            Feature::Fullscreen => vec![atoms.net_wm_state_fullscreen],
+            Feature::Maximize => vec![
+                atoms.net_wm_state_maximized_vert,
+                atoms.net_wm_state_maximized_horz,
+            ],
+            Feature::DemandsAttention => vec![atoms.net_wm_state_demands_attention],
+            Feature::MoveResize => vec![atoms.net_wm_moveresize],
+            Feature::RestackWindow => vec![atoms.net_restack_window],
+            Feature::ActiveWindow => vec![atoms.net_active_window],
+            Feature::WorkArea => vec![atoms.net_workarea],
+            Feature::FrameExtents => vec![atoms.net_frame_extents],
+            Feature::Ping => vec![atoms.net_wm_ping],
+            Feature::SyncRequest => vec![atoms.net_wm_sync_request],
+        }
+    }
+}
+
+/// A way a window manager differs from what EWMH leads to expect
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Quirk {
+    /// Drops client messages changing _NET_WM_STATE of windows it does
+    /// not manage yet and reads the property once it does, so the
+    /// property is written for windows mapped but not yet managed
+    StateByPropertyUntilManaged,
+    /// Lists _NET_WM_MOVERESIZE but only moves floating windows with it,
+    /// so drags are made by the event loop instead
+    MoveResizeIgnored,
+}
+
+/// Window managers with quirks, by the name they give themselves in
+/// _NET_WM_NAME, compared without case
+static QUIRKS: &[(&str, Quirk)] = &[
+    ("i3", Quirk::StateByPropertyUntilManaged),
+    ("i3", Quirk::MoveResizeIgnored),
+    ("dwm", Quirk::StateByPropertyUntilManaged),
+];
+
+/// The window manager running, as found by EventLoop::window_manager
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WmInfo {
+    /// _NET_WM_NAME of its check window, e.g. "Mutter" or "KWin"
+    pub name: String,
+    /// The atoms of _NET_SUPPORTED
+    pub supports: Vec<xlib::Atom>,
+    features: Vec<Feature>,
+}
+
+impl WmInfo {
+    /// A window manager named `name` supporting `features`, e.g. to
+    /// check how code adjusting to window managers handles one without
+    /// running it. `supports` is left empty
+    pub fn new(name: &str, features: &[Feature]) -> WmInfo {
+        WmInfo {
+            name: name.to_owned(),
+            supports: Vec::new(),
+            features: features.to_vec(),
+        }
+    }
+
+    /// Whether the window manager supports `feature`, as it advertises
+    /// and as far as its quirks allow
+    pub fn supports(&self, feature: Feature) -> bool {
+        let ignored = match feature {
+            Feature::MoveResize => self.has_quirk(Quirk::MoveResizeIgnored),
+            _ => false,
+        };
+        self.features.contains(&feature) && !ignored
+    }
+
+    pub(crate) fn has_quirk(&self, quirk: Quirk) -> bool {
+        QUIRKS
+            .iter()
+            .any(|&(name, q)| q == quirk && name.eq_ignore_ascii_case(&self.name))
+    }
+}
+
+/// How to change _NET_WM_STATE of a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StateRequest {
+    /// Write the property, read by the window manager once it manages
+    /// the window
+    Property,
+    /// Send the client message to the root window
+    Message,
+}
+
+/// How to change _NET_WM_STATE of a window the server reports `mapped`,
+/// which the window manager `managed` when it set WM_STATE on it
+pub(crate) fn state_request(wm: Option<&WmInfo>, mapped: bool, managed: bool) -> StateRequest {
+    let by_property = match wm {
+        // Without a window manager nothing reads the messages
+        None => true,
+        Some(wm) => !mapped || (!managed && wm.has_quirk(Quirk::StateByPropertyUntilManaged)),
+    };
+    if by_property {
+        StateRequest::Property
+    } else {
+        StateRequest::Message
+    }
+}
+
+/// Whether a drag moving a window is left to the window manager rather
+/// than made by the event loop
+pub(crate) fn drag_by_wm(wm: Option<&WmInfo>) -> bool {
+    wm.is_some_and(|wm| wm.supports(Feature::MoveResize))
+}
+
+/// Find the window manager, None when none runs or it does not follow
+/// EWMH
+pub(crate) fn detect(conn: &XConnection) -> Option<WmInfo> {
+    let atoms = &conn.atoms;
+    let check = *conn
+        .window_list(conn.root, atoms.net_supporting_wm_check)
+        .first()?;
+    // The check window names itself, else the root property is stale,
+    // left by a window manager that exited
+    if conn
+        .window_list(check, atoms.net_supporting_wm_check)
+        .first()
+        != Some(&check)
+    {
+        return None;
+    }
+    let name = conn
+        .text_property(check, atoms.net_wm_name, xlib::XA_WM_NAME)
+        .unwrap_or_default();
+    let supports = conn.atom_list(conn.root, atoms.net_supported);
+    let features = FEATURES
+        .iter()
+        .copied()
+        .filter(|feature| {
+            feature
+                .hints(atoms)
+                .iter()
+                .all(|hint| supports.contains(hint))
+        })
+        .collect();
+    Some(WmInfo {
+        name,
+        supports,
+        features,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: &[Feature] = &[Feature::Fullscreen, Feature::MoveResize, Feature::Ping];
+
+    #[test]
+    fn supports_what_is_advertised() {
+        let wm = WmInfo::new("Openbox", ALL);
+        assert!(wm.supports(Feature::Fullscreen));
+        assert!(wm.supports(Feature::MoveResize));
+        assert!(!wm.supports(Feature::Maximize));
+        assert!(!WmInfo::new("Openbox", &[]).supports(Feature::Fullscreen));
+    }
+
+    #[test]
+    fn i3_ignores_moveresize() {
+        let wm = WmInfo::new("i3", ALL);
+        assert!(wm.supports(Feature::Fullscreen));
+        assert!(!wm.supports(Feature::MoveResize));
+        // Compared without case
+        assert!(!WmInfo::new("I3", ALL).supports(Feature::MoveResize));
+    }
+
+    #[test]
+    fn dwm_keeps_moveresize() {
+        assert!(WmInfo::new("dwm", ALL).supports(Feature::MoveResize));
+    }
+
+    #[test]
+    fn state_request_without_wm() {
+        for &(mapped, managed) in &[(false, false), (true, false), (true, true)] {
+            assert_eq!(state_request(None, mapped, managed), StateRequest::Property);
+        }
+    }
+
+    #[test]
+    fn state_request_of_unknown_wm() {
+        let wm = WmInfo::new("Openbox", ALL);
+        assert_eq!(
+            state_request(Some(&wm), false, false),
+            StateRequest::Property
+        );
+        assert_eq!(state_request(Some(&wm), true, false), StateRequest::Message);
+        assert_eq!(state_request(Some(&wm), true, true), StateRequest::Message);
+    }
+
+    #[test]
+    fn state_request_until_managed() {
+        for name in &["i3", "dwm"] {
+            let wm = WmInfo::new(name, ALL);
+            assert_eq!(
+                state_request(Some(&wm), false, false),
+                StateRequest::Property
+            );
+            assert_eq!(
+                state_request(Some(&wm), true, false),
+                StateRequest::Property
+            );
+            assert_eq!(state_request(Some(&wm), true, true), StateRequest::Message);
+        }
+    }
+
+    #[test]
+    fn drags() {
+        assert!(!drag_by_wm(None));
+        assert!(!drag_by_wm(Some(&WmInfo::new("i3", ALL))));
+        assert!(drag_by_wm(Some(&WmInfo::new("dwm", ALL))));
+        assert!(drag_by_wm(Some(&WmInfo::new("Openbox", ALL))));
+        assert!(!drag_by_wm(Some(&WmInfo::new("Openbox", &[]))));
+    }
+}
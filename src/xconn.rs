@@ -0,0 +1,883 @@
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::ffi::{CStr, CString};
+use std::mem;
+use std::os::raw;
+use std::os::unix::io::RawFd;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, Once, OnceLock};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+use x11::xlib;
+
+use crate::debug;
+use crate::error::{self, XError};
+use crate::event::{DeviceId, WindowId};
+use crate::ime::InputMethod;
+use crate::present;
+use crate::proxy::WindowCommand;
+use crate::session::Session;
+use crate::wake::WakePipe;
+use crate::wm::WmInfo;
+
+macro_rules! atoms {
+    ($($field:ident: $name:expr,)*) => {
+        /// Atoms interned once per connection
+        pub(crate) struct Atoms {
+            $(pub $field: xlib::Atom,)*
+        }
+
+        impl Atoms {
+            /// Intern every atom with a single XInternAtoms round trip
+            unsafe fn intern(display: *mut xlib::Display) -> Atoms {
+                let names = [$(CString::new($name).unwrap(),)*];
+                let mut name_ptrs: Vec<*mut raw::c_char> =
+                    names.iter().map(|name| name.as_ptr() as *mut _).collect();
+                let mut atoms = vec![0; names.len()];
+                xlib::XInternAtoms(
+                    display,
+                    name_ptrs.as_mut_ptr(),
+                    names.len() as raw::c_int,
+                    xlib::False,
+                    atoms.as_mut_ptr(),
+                );
+
+                let mut atoms = atoms.into_iter();
+                Atoms {
+                    $($field: atoms.next().unwrap(),)*
+                }
+            }
+        }
+    };
+}
+
+atoms! {
+    wm_protocols: "WM_PROTOCOLS",
+    wm_delete_window: "WM_DELETE_WINDOW",
+    net_wm_ping: "_NET_WM_PING",
+    net_wm_icon: "_NET_WM_ICON",
+    net_wm_sync_request: "_NET_WM_SYNC_REQUEST",
+    net_wm_sync_request_counter: "_NET_WM_SYNC_REQUEST_COUNTER",
+    net_active_window: "_NET_ACTIVE_WINDOW",
+    net_wm_state: "_NET_WM_STATE",
+    net_wm_state_fullscreen: "_NET_WM_STATE_FULLSCREEN",
+    net_wm_state_maximized_vert: "_NET_WM_STATE_MAXIMIZED_VERT",
+    net_wm_state_maximized_horz: "_NET_WM_STATE_MAXIMIZED_HORZ",
+    net_wm_state_demands_attention: "_NET_WM_STATE_DEMANDS_ATTENTION",
+    net_wm_state_modal: "_NET_WM_STATE_MODAL",
+    net_wm_window_type: "_NET_WM_WINDOW_TYPE",
+    net_wm_window_type_dialog: "_NET_WM_WINDOW_TYPE_DIALOG",
+    net_wm_bypass_compositor: "_NET_WM_BYPASS_COMPOSITOR",
+    net_supported: "_NET_SUPPORTED",
+    net_supporting_wm_check: "_NET_SUPPORTING_WM_CHECK",
+    net_wm_moveresize: "_NET_WM_MOVERESIZE",
+    wm_state: "WM_STATE",
+    net_restack_window: "_NET_RESTACK_WINDOW",
+    net_frame_extents: "_NET_FRAME_EXTENTS",
+    net_workarea: "_NET_WORKAREA",
+    net_current_desktop: "_NET_CURRENT_DESKTOP",
+    motif_wm_hints: "_MOTIF_WM_HINTS",
+    wm_client_leader: "WM_CLIENT_LEADER",
+    wm_save_yourself: "WM_SAVE_YOURSELF",
+    sm_client_id: "SM_CLIENT_ID",
+    utf8_string: "UTF8_STRING",
+    net_wm_name: "_NET_WM_NAME",
+    net_wm_icon_name: "_NET_WM_ICON_NAME",
+    abs_pressure: "Abs Pressure",
+    abs_tilt_x: "Abs Tilt X",
+    abs_tilt_y: "Abs Tilt Y",
+    xembed: "_XEMBED",
+    xembed_info: "_XEMBED_INFO",
+    edid: "EDID",
+    daswin_timestamp: "_DASWIN_TIMESTAMP",
+    daswin_selection: "_DASWIN_SELECTION",
+    incr: "INCR",
+}
+
+/// An Xlib request marshalled to the connection thread
+pub(crate) type Request = Box<dyn FnOnce(&XConnection) + Send>;
+
+thread_local! {
+    static ON_CONNECTION_THREAD: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Sends requests to the connection thread and wakes it up
+pub(crate) struct RequestQueue {
+    pub sender: mpsc::Sender<Request>,
+    // write end of the connection thread's wakeup pipe
+    pub wake: RawFd,
+}
+
+impl RequestQueue {
+    pub fn wake(&self) {
+        unsafe {
+            libc::write(self.wake, [1u8].as_ptr() as *const _, 1);
+        }
+    }
+}
+
+impl Drop for RequestQueue {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.wake);
+        }
+    }
+}
+
+/// When the request being waited on was made, watched by the Watchdog
+pub(crate) struct Activity {
+    epoch: Instant,
+    // Nanoseconds since `epoch` plus one, 0 while no request is made
+    busy_since: AtomicU64,
+}
+
+impl Activity {
+    fn new() -> Activity {
+        Activity {
+            epoch: Instant::now(),
+            busy_since: AtomicU64::new(0),
+        }
+    }
+
+    /// How long the current request has been waiting, None when idle
+    pub fn busy_for(&self) -> Option<Duration> {
+        match self.busy_since.load(Ordering::Acquire) {
+            0 => None,
+            since => Some(
+                self.epoch
+                    .elapsed()
+                    .saturating_sub(Duration::from_nanos(since - 1)),
+            ),
+        }
+    }
+}
+
+/// Marks the connection busy from a request until it returns, nested
+/// requests keep the start of the outer one
+struct Busy<'a>(Option<&'a Activity>);
+
+impl<'a> Busy<'a> {
+    fn enter(activity: &'a Activity) -> Busy<'a> {
+        let now = activity.epoch.elapsed().as_nanos() as u64 + 1;
+        let entered = activity
+            .busy_since
+            .compare_exchange(0, now, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok();
+        Busy(Some(activity).filter(|_| entered))
+    }
+}
+
+impl Drop for Busy<'_> {
+    fn drop(&mut self) {
+        if let Some(activity) = self.0 {
+            activity.busy_since.store(0, Ordering::Release);
+        }
+    }
+}
+
+/// Connection to the X server shared by the event loop and its windows,
+/// the display is closed once the last of them is dropped
+pub(crate) struct XConnection {
+    pub display: *mut xlib::Display,
+    pub screen: i32,
+    pub root: xlib::Window,
+    pub atoms: Atoms,
+    // Unmapped window identifying the client to the session manager
+    pub client_leader: xlib::Window,
+
+    // Timestamp of the last user input, needed for focus requests
+    pub last_event_time: AtomicU64,
+    // XInput2 id of the master pointer core events come from
+    pub core_pointer: AtomicU32,
+
+    // Set when a dedicated thread owns the connection
+    pub requests: Option<RequestQueue>,
+
+    // Uncaptured errors of wgpu devices created for our windows, the
+    // event loop turns them into GpuError events
+    pub gpu_errors: Mutex<Vec<String>>,
+    // Instances of those devices, waited on before windows are destroyed
+    // after a panic
+    #[cfg(feature = "wgpu")]
+    pub gpu_instances: Mutex<Vec<std::sync::Weak<wgpu::Instance>>>,
+    // Readbacks of GpuContext::map_async_callback, polled by the loop
+    #[cfg(feature = "wgpu")]
+    pub(crate) readbacks: Mutex<crate::render::readback::Readbacks>,
+    // File dialogs awaiting the portal's response, read by every poll
+    #[cfg(feature = "dbus")]
+    pub dialogs: Mutex<Vec<std::sync::Weak<Mutex<crate::dialogs::Request>>>>,
+    // The window manager running, updated by the event loop when another
+    // one takes over
+    pub wm: Mutex<Option<WmInfo>>,
+    // Changes queued by window proxies, made by the next poll
+    pub window_commands: Mutex<VecDeque<(WindowId, WindowCommand)>>,
+    // Written whenever something arrives for the loop other than through
+    // the X socket, see EventLoop::run
+    pub wake: Arc<WakePipe>,
+    // Grabs and video modes to undo should the application crash
+    pub session: Session,
+    // Windows adopted with X11Window::from_raw and dropped since the last
+    // poll, whose events go back to the application
+    pub released: Mutex<Vec<xlib::Window>>,
+    // The shown modal window of each owner, kept by the show, hide,
+    // set_modal and drop of windows, see EventLoop::set_modal_input_blocking
+    pub modals: Mutex<HashMap<xlib::Window, xlib::Window>>,
+
+    // Whether the display was handed in by the application, which reads
+    // the events of its own windows, and whether it is closed on drop
+    pub shared: bool,
+    owns_display: bool,
+
+    // Thread which opened the connection, the only one allowed to make
+    // requests besides the connection thread
+    owner: ThreadId,
+
+    // Opened by the first window allowing IME input
+    input_method: OnceLock<Option<InputMethod>>,
+    // Major opcode of Present, set up by the first window asking for
+    // present feedback
+    present: OnceLock<Option<i32>>,
+    // Whether the server has the cursor font, asked by the first window
+    // falling back to font cursors
+    cursor_font: OnceLock<bool>,
+
+    // Set by the IO error exit handler once the server is gone, boxed
+    // so its address can be handed to Xlib
+    lost: Box<AtomicBool>,
+    pub activity: Arc<Activity>,
+}
+
+// Without a connection thread every user of the connection is confined to
+// the thread owning the event loop, with one XInitThreads has been called
+// and all requests are funneled through the connection thread.
+unsafe impl Send for XConnection {}
+unsafe impl Sync for XConnection {}
+
+impl XConnection {
+    /// Open the display named by $DISPLAY, requests are marshalled over
+    /// the given queue when a connection thread is used. None when the
+    /// server cannot be reached
+    pub fn open(requests: Option<RequestQueue>) -> Option<XConnection> {
+        let start = Instant::now();
+        unsafe {
+            // Xlib must be told before the first call that several
+            // threads will use it.
+            if requests.is_some() {
+                xlib::XInitThreads();
+            }
+
+            // Open display connection.
+            let display = xlib::XOpenDisplay(ptr::null());
+
+            if display.is_null() {
+                return None;
+            }
+
+            let conn = XConnection::with_display(display, requests, false, true);
+            if debug::enabled() {
+                eprintln!("daswin: connected in {:?}", start.elapsed());
+            }
+            Some(conn)
+        }
+    }
+
+    /// Use a display opened by the application, closed on drop only when
+    /// `owns` is set. Connection loss is left to the IO error handlers the
+    /// application installed unless daswin owns the display
+    pub unsafe fn from_display(display: *mut xlib::Display, owns: bool) -> XConnection {
+        XConnection::with_display(display, None, true, owns)
+    }
+
+    unsafe fn with_display(
+        display: *mut xlib::Display,
+        requests: Option<RequestQueue>,
+        shared: bool,
+        owns_display: bool,
+    ) -> XConnection {
+        let lost = Box::new(AtomicBool::new(false));
+        if owns_display {
+            set_io_error_handlers(display, &lost);
+        }
+        set_error_handler();
+
+        let screen = xlib::XDefaultScreen(display);
+        let root = xlib::XRootWindow(display, screen);
+
+        let atoms = Atoms::intern(display);
+        let client_leader = create_client_leader(display, root, &atoms);
+
+        XConnection {
+            display,
+            screen,
+            root,
+            atoms,
+            client_leader,
+            last_event_time: AtomicU64::new(xlib::CurrentTime),
+            core_pointer: AtomicU32::new(2),
+            requests,
+            gpu_errors: Mutex::new(Vec::new()),
+            #[cfg(feature = "wgpu")]
+            gpu_instances: Mutex::new(Vec::new()),
+            #[cfg(feature = "wgpu")]
+            readbacks: Mutex::default(),
+            #[cfg(feature = "dbus")]
+            dialogs: Mutex::new(Vec::new()),
+            wm: Mutex::new(None),
+            window_commands: Mutex::new(VecDeque::new()),
+            wake: Arc::new(WakePipe::new()),
+            session: Session::default(),
+            released: Mutex::new(Vec::new()),
+            modals: Mutex::new(HashMap::new()),
+            shared,
+            owns_display,
+            owner: thread::current().id(),
+            input_method: OnceLock::new(),
+            present: OnceLock::new(),
+            cursor_font: OnceLock::new(),
+            lost,
+            activity: Arc::new(Activity::new()),
+        }
+    }
+
+    /// Run Xlib calls on the thread owning the connection and wait for
+    /// their result, called inline when there is no connection thread
+    pub fn request<R, F>(&self, f: F) -> R
+    where
+        R: Send + 'static,
+        F: FnOnce(&XConnection) -> R + Send + 'static,
+    {
+        debug_assert!(
+            ON_CONNECTION_THREAD.with(Cell::get) || thread::current().id() == self.owner,
+            "Xlib request made off the event loop thread"
+        );
+        let _busy = Busy::enter(&self.activity);
+        let queue = match &self.requests {
+            Some(queue) if !ON_CONNECTION_THREAD.with(Cell::get) => queue,
+            _ => return f(self),
+        };
+
+        let (reply, result) = mpsc::sync_channel(1);
+        let request: Request = Box::new(move |conn| {
+            let _ = reply.send(f(conn));
+        });
+        match queue.sender.send(request) {
+            Ok(()) => queue.wake(),
+            // The connection thread has exited, the display is ours again
+            Err(mpsc::SendError(request)) => request(self),
+        }
+        result.recv().expect("connection thread dropped a request")
+    }
+
+    /// Whether the connection to the server was lost. Requests made
+    /// afterwards return without reaching it
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Acquire)
+    }
+
+    /// The socket of the connection
+    pub fn fd(&self) -> RawFd {
+        unsafe { xlib::XConnectionNumber(self.display) }
+    }
+
+    /// The input method, None when no input method server is running
+    pub fn input_method(&self) -> Option<&InputMethod> {
+        self.input_method
+            .get_or_init(|| unsafe { InputMethod::open(self.display) })
+            .as_ref()
+    }
+
+    /// The major opcode of Present, set up on first use. None when the
+    /// server or the xcb libraries lack it
+    pub fn init_present(&self) -> Option<i32> {
+        *self
+            .present
+            .get_or_init(|| unsafe { present::init(self.display) })
+    }
+
+    /// The master pointer core events come from
+    pub fn core_pointer(&self) -> DeviceId {
+        DeviceId(self.core_pointer.load(Ordering::Relaxed))
+    }
+
+    /// Whether cursors can be created with XCreateFontCursor, bare
+    /// servers may come without the cursor font
+    pub fn has_cursor_font(&self) -> bool {
+        *self.cursor_font.get_or_init(|| unsafe {
+            let font =
+                xlib::XLoadQueryFont(self.display, b"cursor\0".as_ptr() as *const raw::c_char);
+            if !font.is_null() {
+                xlib::XFreeFont(self.display, font);
+            }
+            !font.is_null()
+        })
+    }
+
+    /// The major opcode of Present once init_present succeeded, safe to
+    /// call off the connection thread
+    pub fn present_opcode(&self) -> Option<i32> {
+        self.present.get().copied().flatten()
+    }
+
+    /// Whether the window manager lists the hint in _NET_SUPPORTED
+    pub fn wm_supports(&self, hint: xlib::Atom) -> bool {
+        self.atom_list(self.root, self.atoms.net_supported)
+            .contains(&hint)
+    }
+
+    /// An ATOM[] property of the window, empty when it is not set
+    pub fn atom_list(&self, window: xlib::Window, property: xlib::Atom) -> Vec<xlib::Atom> {
+        self.list_property(window, property, xlib::XA_ATOM)
+    }
+
+    /// Whether the window manager manages the window, having set
+    /// WM_STATE on it
+    pub fn is_managed(&self, window: xlib::Window) -> bool {
+        !self
+            .list_property(window, self.atoms.wm_state, self.atoms.wm_state)
+            .is_empty()
+    }
+
+    /// A WINDOW[] property of the window, empty when it is not set
+    pub fn window_list(&self, window: xlib::Window, property: xlib::Atom) -> Vec<xlib::Window> {
+        self.list_property(window, property, xlib::XA_WINDOW)
+    }
+
+    /// A CARDINAL[] property of the window, empty when it is not set
+    pub fn cardinal_list(&self, window: xlib::Window, property: xlib::Atom) -> Vec<raw::c_ulong> {
+        self.list_property(window, property, xlib::XA_CARDINAL)
+    }
+
+    // Format 32 properties arrive as an array of longs
+    fn list_property(
+        &self,
+        window: xlib::Window,
+        property: xlib::Atom,
+        type_: xlib::Atom,
+    ) -> Vec<raw::c_ulong> {
+        unsafe {
+            let mut actual_type = 0;
+            let mut actual_format = 0;
+            let mut count = 0;
+            let mut remaining = 0;
+            let mut data: *mut raw::c_uchar = ptr::null_mut();
+            let status = xlib::XGetWindowProperty(
+                self.display,
+                window,
+                property,
+                0,
+                4096,
+                xlib::False,
+                type_,
+                &mut actual_type,
+                &mut actual_format,
+                &mut count,
+                &mut remaining,
+                &mut data,
+            );
+            if status != xlib::Success as raw::c_int || data.is_null() {
+                return Vec::new();
+            }
+
+            let values = if actual_format == 32 {
+                std::slice::from_raw_parts(data as *const raw::c_ulong, count as usize).to_vec()
+            } else {
+                Vec::new()
+            };
+            xlib::XFree(data as *mut _);
+            values
+        }
+    }
+
+    /// Write a property of 8 bit data in requests the server accepts,
+    /// appending to the first one
+    pub fn change_property_chunked(
+        &self,
+        window: xlib::Window,
+        property: xlib::Atom,
+        type_: xlib::Atom,
+        data: &[u8],
+    ) {
+        unsafe {
+            let max_request = match xlib::XExtendedMaxRequestSize(self.display) {
+                0 => xlib::XMaxRequestSize(self.display),
+                size => size,
+            };
+            // In four byte units, less the request header
+            let chunk = (max_request as usize * 4).saturating_sub(32).max(4);
+            let mut mode = xlib::PropModeReplace;
+            let mut chunks = data.chunks(chunk);
+            // Empty data still replaces the property
+            let first = chunks.next().unwrap_or(&[]);
+            for chunk in std::iter::once(first).chain(chunks) {
+                xlib::XChangeProperty(
+                    self.display,
+                    window,
+                    property,
+                    type_,
+                    8,
+                    mode,
+                    chunk.as_ptr(),
+                    chunk.len() as raw::c_int,
+                );
+                mode = xlib::PropModeAppend;
+            }
+        }
+    }
+
+    /// Set a text property as UTF8_STRING on `utf8`, and on `legacy` as
+    /// STRING or, for text outside Latin-1, COMPOUND_TEXT for clients
+    /// predating UTF-8. Text is cut at the first nul byte
+    pub fn set_text_property(
+        &self,
+        window: xlib::Window,
+        legacy: xlib::Atom,
+        utf8: Option<xlib::Atom>,
+        text: &str,
+    ) {
+        let text = text.split('\0').next().unwrap();
+        if let Some(utf8) = utf8 {
+            self.change_property_chunked(window, utf8, self.atoms.utf8_string, text.as_bytes());
+        }
+        unsafe {
+            let c_text = CString::new(text).unwrap();
+            let mut list = [c_text.as_ptr() as *mut raw::c_char];
+            let mut property: xlib::XTextProperty = mem::zeroed();
+            let status = xlib::Xutf8TextListToTextProperty(
+                self.display,
+                list.as_mut_ptr(),
+                1,
+                xlib::XStdICCTextStyle,
+                &mut property,
+            );
+            if status >= 0 && !property.value.is_null() {
+                let value = std::slice::from_raw_parts(property.value, property.nitems as usize);
+                self.change_property_chunked(window, legacy, property.encoding, value);
+                xlib::XFree(property.value as *mut _);
+            } else {
+                // Without a converter for the locale, Latin-1 as far as
+                // it goes
+                let latin1: Vec<u8> = text
+                    .chars()
+                    .map(|c| u8::try_from(c as u32).unwrap_or(b'?'))
+                    .collect();
+                self.change_property_chunked(window, legacy, xlib::XA_STRING, &latin1);
+            }
+        }
+    }
+
+    /// A text property, read from UTF8_STRING `utf8` when set and from
+    /// `legacy` otherwise. None when neither is set
+    pub fn text_property(
+        &self,
+        window: xlib::Window,
+        utf8: xlib::Atom,
+        legacy: xlib::Atom,
+    ) -> Option<String> {
+        unsafe {
+            let mut actual_type = 0;
+            let mut actual_format = 0;
+            let mut count = 0;
+            let mut remaining = 0;
+            let mut data: *mut raw::c_uchar = ptr::null_mut();
+            let status = xlib::XGetWindowProperty(
+                self.display,
+                window,
+                utf8,
+                0,
+                // In four byte units, as much as a reply holds
+                i32::MAX as raw::c_long / 4,
+                xlib::False,
+                self.atoms.utf8_string,
+                &mut actual_type,
+                &mut actual_format,
+                &mut count,
+                &mut remaining,
+                &mut data,
+            );
+            if status == xlib::Success as raw::c_int && !data.is_null() {
+                let text =
+                    (actual_type == self.atoms.utf8_string && actual_format == 8).then(|| {
+                        let bytes = std::slice::from_raw_parts(data, count as usize);
+                        String::from_utf8_lossy(bytes).into_owned()
+                    });
+                xlib::XFree(data as *mut _);
+                if text.is_some() {
+                    return text;
+                }
+            }
+
+            let mut property: xlib::XTextProperty = mem::zeroed();
+            if xlib::XGetTextProperty(self.display, window, &mut property, legacy) == 0 {
+                return None;
+            }
+            let mut list: *mut *mut raw::c_char = ptr::null_mut();
+            let mut list_count = 0;
+            let status = xlib::Xutf8TextPropertyToTextList(
+                self.display,
+                &property,
+                &mut list,
+                &mut list_count,
+            );
+            if !property.value.is_null() {
+                xlib::XFree(property.value as *mut _);
+            }
+            if status < 0 || list.is_null() {
+                return None;
+            }
+            let text = (0..list_count as usize)
+                .map(|i| CStr::from_ptr(*list.add(i)).to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("");
+            xlib::XFreeStringList(list);
+            Some(text)
+        }
+    }
+
+    /// Send an EWMH client message about the window to the root window
+    pub fn send_client_message(
+        &self,
+        window: xlib::Window,
+        message_type: xlib::Atom,
+        data: [raw::c_long; 5],
+    ) {
+        unsafe {
+            let mut xclient: xlib::XClientMessageEvent = mem::zeroed();
+            xclient.type_ = xlib::ClientMessage;
+            xclient.window = window;
+            xclient.message_type = message_type;
+            xclient.format = 32;
+            for (i, &value) in data.iter().enumerate() {
+                xclient.data.set_long(i, value);
+            }
+
+            let mut event = xlib::XEvent::from(xclient);
+            xlib::XSendEvent(
+                self.display,
+                self.root,
+                xlib::False,
+                xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask,
+                &mut event,
+            );
+        }
+    }
+
+    /// Report held keys repeating as presses alone, without the release
+    /// the server otherwise sends before each
+    pub fn set_detectable_autorepeat(&self) {
+        unsafe {
+            let mut supported = xlib::False;
+            xlib::XkbSetDetectableAutoRepeat(self.display, xlib::True, &mut supported);
+            if supported == xlib::False {
+                log::debug!("no detectable autorepeat, keys repeat as releases and presses");
+            }
+        }
+    }
+
+    /// Keycodes of the keys the server repeats while held, one bit each
+    pub fn auto_repeat_keys(&self) -> [u8; 32] {
+        unsafe {
+            let mut state: xlib::XKeyboardState = mem::zeroed();
+            xlib::XGetKeyboardControl(self.display, &mut state);
+            state.auto_repeats.map(|bits| bits as u8)
+        }
+    }
+
+    /// Keycodes of the keys held right now, one bit each
+    pub fn query_keymap(&self) -> [u8; 32] {
+        let mut keys = [0u8; 32];
+        unsafe {
+            xlib::XQueryKeymap(self.display, keys.as_mut_ptr() as *mut raw::c_char);
+        }
+        keys
+    }
+
+    /// Query the pointer relative to the root and the window, along with
+    /// the modifier and button mask. The window position is None when the
+    /// pointer is on another screen
+    pub fn query_pointer(
+        &self,
+        window: xlib::Window,
+    ) -> ((i32, i32), Option<(i32, i32)>, raw::c_uint) {
+        unsafe {
+            let (mut root, mut child) = (0, 0);
+            let (mut root_x, mut root_y, mut win_x, mut win_y) = (0, 0, 0, 0);
+            let mut mask = 0;
+            let same_screen = xlib::XQueryPointer(
+                self.display,
+                window,
+                &mut root,
+                &mut child,
+                &mut root_x,
+                &mut root_y,
+                &mut win_x,
+                &mut win_y,
+                &mut mask,
+            );
+            let relative = if same_screen != xlib::False {
+                Some((win_x, win_y))
+            } else {
+                None
+            };
+            ((root_x, root_y), relative, mask)
+        }
+    }
+
+    /// Whether a compositing manager owns the _NET_WM_CM_Sn selection of
+    /// our screen
+    pub fn compositor_running(&self) -> bool {
+        let name = CString::new(format!("_NET_WM_CM_S{}", self.screen)).unwrap();
+        unsafe {
+            let selection = xlib::XInternAtom(self.display, name.as_ptr(), xlib::False);
+            xlib::XGetSelectionOwner(self.display, selection) != 0
+        }
+    }
+
+    /// Store the command line restarting the client on the leader, which
+    /// also answers WM_SAVE_YOURSELF
+    pub fn set_command(&self) {
+        let args: Vec<CString> = std::env::args()
+            .filter_map(|arg| CString::new(arg).ok())
+            .collect();
+        let mut argv: Vec<*mut raw::c_char> =
+            args.iter().map(|arg| arg.as_ptr() as *mut _).collect();
+        unsafe {
+            xlib::XSetCommand(
+                self.display,
+                self.client_leader,
+                argv.as_mut_ptr(),
+                argv.len() as raw::c_int,
+            );
+        }
+    }
+
+    /// Mark the calling thread as the one owning the connection
+    pub fn enter_connection_thread() {
+        ON_CONNECTION_THREAD.with(|on| on.set(true));
+    }
+}
+
+// void XSetIOErrorExitHandler(Display *, XIOErrorExitHandler, void *),
+// only exported by libX11 1.7 and later
+type SetIOErrorExitHandler = unsafe extern "C" fn(
+    *mut xlib::Display,
+    Option<unsafe extern "C" fn(*mut xlib::Display, *mut raw::c_void)>,
+    *mut raw::c_void,
+);
+
+/// Keep Xlib from exiting the process when the connection breaks: the
+/// exit handler marks the connection lost and returns, after which Xlib
+/// fails further requests on it instead of blocking.
+///
+/// Older libX11 versions lack exit handlers and still exit once the IO
+/// error handler, which only logs, returns
+unsafe fn set_io_error_handlers(display: *mut xlib::Display, lost: &AtomicBool) {
+    static LOG_HANDLER: Once = Once::new();
+    LOG_HANDLER.call_once(|| {
+        xlib::XSetIOErrorHandler(Some(log_io_error));
+    });
+
+    let symbol = libc::dlsym(
+        libc::RTLD_DEFAULT,
+        b"XSetIOErrorExitHandler\0".as_ptr() as *const raw::c_char,
+    );
+    if symbol.is_null() {
+        log::info!("libX11 lacks XSetIOErrorExitHandler, losing the server exits");
+        return;
+    }
+    let set_exit_handler: SetIOErrorExitHandler = mem::transmute(symbol);
+    set_exit_handler(
+        display,
+        Some(mark_lost),
+        lost as *const AtomicBool as *mut raw::c_void,
+    );
+}
+
+/// Record errors for EventLoop::take_x_errors instead of letting Xlib
+/// exit the process on the first one
+unsafe fn set_error_handler() {
+    static HANDLER: Once = Once::new();
+    HANDLER.call_once(|| {
+        xlib::XSetErrorHandler(Some(record_error));
+    });
+}
+
+unsafe extern "C" fn record_error(
+    display: *mut xlib::Display,
+    event: *mut xlib::XErrorEvent,
+) -> raw::c_int {
+    let event = &*event;
+    let mut text = [0 as raw::c_char; 256];
+    xlib::XGetErrorText(
+        display,
+        event.error_code as raw::c_int,
+        text.as_mut_ptr(),
+        text.len() as raw::c_int,
+    );
+    let error = XError {
+        error_code: event.error_code,
+        request_code: event.request_code,
+        minor_code: event.minor_code,
+        resource: event.resourceid,
+        description: CStr::from_ptr(text.as_ptr()).to_string_lossy().into_owned(),
+    };
+    log::error!("X error: {}", error);
+    error::record_x_error(error);
+    0
+}
+
+unsafe extern "C" fn log_io_error(_display: *mut xlib::Display) -> raw::c_int {
+    log::error!("connection to the X server lost");
+    0
+}
+
+unsafe extern "C" fn mark_lost(_display: *mut xlib::Display, lost: *mut raw::c_void) {
+    (*(lost as *const AtomicBool)).store(true, Ordering::Release);
+}
+
+/// Create the window WM_CLIENT_LEADER of all our windows points to, it
+/// carries the session management properties of the whole client
+unsafe fn create_client_leader(
+    display: *mut xlib::Display,
+    root: xlib::Window,
+    atoms: &Atoms,
+) -> xlib::Window {
+    let leader = xlib::XCreateSimpleWindow(display, root, 0, 0, 1, 1, 0, 0, 0);
+    xlib::XChangeProperty(
+        display,
+        leader,
+        atoms.wm_client_leader,
+        xlib::XA_WINDOW,
+        32,
+        xlib::PropModeReplace,
+        &leader as *const xlib::Window as *const u8,
+        1,
+    );
+    let mut protocols = [atoms.wm_save_yourself];
+    xlib::XSetWMProtocols(
+        display,
+        leader,
+        protocols.as_mut_ptr(),
+        protocols.len() as raw::c_int,
+    );
+    leader
+}
+
+impl Drop for XConnection {
+    /// Disconnects from the display, or leaves a display of the
+    /// application without the windows daswin created on it
+    fn drop(&mut self) {
+        // The input method must be closed while the display is open
+        self.input_method.take();
+        unsafe {
+            if self.owns_display {
+                xlib::XCloseDisplay(self.display);
+            } else if !self.is_lost() {
+                xlib::XDestroyWindow(self.display, self.client_leader);
+                xlib::XFlush(self.display);
+            }
+        }
+    }
+}
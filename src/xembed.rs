@@ -0,0 +1,60 @@
+//! Client side of the XEMBED protocol, for windows living inside a
+//! foreign socket window such as a GtkSocket
+
+use std::os::raw::c_long;
+use std::sync::atomic::Ordering;
+
+use x11::xlib;
+
+use crate::xconn::XConnection;
+
+pub(crate) const VERSION: c_long = 0;
+
+// _XEMBED_INFO flags
+pub(crate) const MAPPED: c_long = 1 << 0;
+
+// Messages from the embedder
+pub(crate) const EMBEDDED_NOTIFY: c_long = 0;
+pub(crate) const FOCUS_IN: c_long = 4;
+pub(crate) const FOCUS_OUT: c_long = 5;
+
+// Messages to the embedder
+pub(crate) const REQUEST_FOCUS: c_long = 3;
+
+/// Advertise the protocol version and whether the embedder should map
+/// the window
+pub(crate) unsafe fn set_info(conn: &XConnection, window: xlib::Window, flags: c_long) {
+    let info: [c_long; 2] = [VERSION, flags];
+    xlib::XChangeProperty(
+        conn.display,
+        window,
+        conn.atoms.xembed_info,
+        conn.atoms.xembed_info,
+        32,
+        xlib::PropModeReplace,
+        info.as_ptr() as *const u8,
+        info.len() as i32,
+    );
+}
+
+/// Send an XEMBED message to the embedder
+pub(crate) unsafe fn send(conn: &XConnection, embedder: xlib::Window, message: c_long) {
+    let mut xclient: xlib::XClientMessageEvent = std::mem::zeroed();
+    xclient.type_ = xlib::ClientMessage;
+    xclient.window = embedder;
+    xclient.message_type = conn.atoms.xembed;
+    xclient.format = 32;
+    xclient
+        .data
+        .set_long(0, conn.last_event_time.load(Ordering::Relaxed) as c_long);
+    xclient.data.set_long(1, message);
+
+    let mut event = xlib::XEvent::from(xclient);
+    xlib::XSendEvent(
+        conn.display,
+        embedder,
+        xlib::False,
+        xlib::NoEventMask,
+        &mut event,
+    );
+}
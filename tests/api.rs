@@ -0,0 +1,883 @@
+//! Names every public type, function and re-export, so that removing or
+//! renaming one fails to build. Nothing here runs against an X server
+
+fn named<T: ?Sized>() {}
+
+fn render_backend<B: daswin::render::RenderBackend>() {
+    let _ = B::configure;
+    let _ = B::acquire;
+    let _ = B::present;
+    let _ = B::destroy;
+}
+
+#[cfg(feature = "wgpu")]
+type Readback = fn(Result<&[u8], wgpu::BufferAsyncError>);
+
+#[cfg(feature = "wgpu")]
+fn configure_surface<C: daswin::render::ConfigureSurface>() {
+    let _ = C::configure;
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn root_async() {
+    named::<daswin::AsyncChannelProxy<()>>();
+    let _ = daswin::AsyncChannelProxy::<()>::id;
+    let _ = daswin::AsyncChannelProxy::<()>::send;
+    let _ = daswin::AsyncChannelProxy::<()>::send_now;
+    named::<daswin::EventStream>();
+    let _ = daswin::EventStream::event_loop;
+    let _ = daswin::EventStream::next_event;
+    let _ = daswin::EventStream::poll_next;
+    named::<daswin::NextEvent>();
+    named::<daswin::SendMessage<'static, ()>>();
+}
+
+#[test]
+fn root() {
+    named::<daswin::ChannelProxy<()>>();
+    let _ = daswin::ChannelProxy::<()>::id;
+    let _ = daswin::ChannelProxy::<()>::send;
+    named::<daswin::EventLoopClosed<()>>();
+    named::<daswin::EventLoopMetrics>();
+    let _ = daswin::EventLoopMetrics::event_count;
+    let _ = daswin::EventLoopMetrics::since;
+    let _ = daswin::EventLoopMetrics::to_json;
+    named::<daswin::EventTime>();
+    let _ = daswin::EventTime::from_millis;
+    let _ = daswin::EventTime::as_millis;
+    let _ = daswin::EventTime::duration_since;
+    named::<daswin::HelperWindow>();
+    let _ = daswin::HelperWindow::new;
+    let _ = daswin::HelperWindow::take_events;
+    let _ = daswin::HelperWindow::atom;
+    let _ = daswin::HelperWindow::own_selection;
+    let _ = daswin::HelperWindow::send_selection;
+    let _ = daswin::HelperWindow::convert_selection;
+    let _ = daswin::HelperWindow::read_selection;
+    named::<daswin::Icon>();
+    let _ = daswin::Icon::from_rgba;
+    let _ = daswin::Icon::width;
+    let _ = daswin::Icon::height;
+    let _ = daswin::Icon::rgba;
+    named::<daswin::InputHandle>();
+    let _ = daswin::InputHandle::snapshot;
+    named::<daswin::InputSnapshot>();
+    let _ = daswin::InputSnapshot::is_key_down;
+    let _ = daswin::InputSnapshot::is_keysym_down;
+    let _ = daswin::InputSnapshot::keys_down;
+    let _ = daswin::InputSnapshot::is_button_down;
+    let _ = daswin::InputSnapshot::is_button_down_on;
+    let _ = daswin::InputSnapshot::cursor_of;
+    named::<daswin::MetricsHandle>();
+    let _ = daswin::MetricsHandle::snapshot;
+    named::<daswin::SavedWindowState>();
+    let _ = daswin::SavedWindowState::to_toml;
+    let _ = daswin::SavedWindowState::from_toml;
+    named::<daswin::ServerClock>();
+    let _ = daswin::ServerClock::instant;
+    let _ = daswin::ServerClock::ust_instant;
+    let _ = daswin::ServerClock::uncertainty;
+    named::<daswin::SessionGuard>();
+    let _ = daswin::SessionGuard::restore;
+    named::<daswin::Snapping>();
+    named::<daswin::TimestampedEvent>();
+    let _ = daswin::TimestampedEvent::text;
+    named::<daswin::Wakeups>();
+    named::<daswin::WindowProxy>();
+    let _ = daswin::WindowProxy::id;
+    let _ = daswin::WindowProxy::is_alive;
+    let _ = daswin::WindowProxy::set_title;
+    let _: fn(&daswin::WindowProxy, daswin::Size) = daswin::WindowProxy::set_inner_size;
+    let _ = daswin::WindowProxy::set_cursor_icon;
+    let _ = daswin::WindowProxy::request_redraw;
+    let _ = daswin::WindowProxy::toggle_fullscreen;
+    named::<daswin::XError>();
+    named::<daswin::ChannelError>();
+    named::<daswin::Error>();
+}
+
+#[cfg(feature = "dbus")]
+#[test]
+fn root_dbus() {
+    named::<daswin::MenuItem>();
+    let _ = daswin::MenuItem::new;
+    let _ = daswin::MenuItem::separator;
+    let _ = daswin::MenuItem::disabled;
+    let _ = daswin::MenuItem::id;
+    let _ = daswin::MenuItem::label;
+    named::<daswin::StatusIcon>();
+    let _ = daswin::StatusIcon::new;
+    let _ = daswin::StatusIcon::set_icon;
+    let _ = daswin::StatusIcon::set_title;
+    let _ = daswin::StatusIcon::set_tooltip;
+    let _ = daswin::StatusIcon::set_menu;
+}
+
+#[test]
+fn backend() {
+    named::<daswin::backend::BackendError>();
+    named::<daswin::backend::Backend>();
+    let _ = daswin::backend::Backend::from_name;
+    named::<daswin::backend::BackendFailure>();
+}
+
+#[test]
+fn device() {
+    named::<daswin::device::DeviceInfo>();
+    let _ = daswin::device::DeviceInfo::id;
+    let _ = daswin::device::DeviceInfo::name;
+    let _ = daswin::device::DeviceInfo::device_type;
+    let _ = daswin::device::DeviceInfo::attachment;
+    let _ = daswin::device::DeviceInfo::is_enabled;
+    let _ = daswin::device::DeviceInfo::buttons;
+    let _ = daswin::device::DeviceInfo::has_pressure;
+    let _ = daswin::device::DeviceInfo::has_touch;
+    let _ = daswin::device::DeviceInfo::max_touches;
+    let _ = daswin::device::DeviceInfo::scroll_axes;
+    let _ = daswin::device::DeviceInfo::relative_axes;
+    named::<daswin::device::DeviceType>();
+}
+
+#[cfg(feature = "dbus")]
+#[test]
+fn dialogs() {
+    named::<daswin::dialogs::FileDialog>();
+    let _ = daswin::dialogs::FileDialog::take_response;
+    named::<daswin::dialogs::FileDialogOptions>();
+    named::<daswin::dialogs::FileFilter>();
+    let _ = daswin::dialogs::FileFilter::new;
+    let _ = daswin::dialogs::open_file;
+    let _ = daswin::dialogs::save_file;
+}
+
+#[test]
+fn dpi() {
+    named::<daswin::dpi::LogicalPosition<f64>>();
+    let _ = daswin::dpi::LogicalPosition::<f64>::new;
+    let _ = daswin::dpi::LogicalPosition::<f64>::to_physical::<u32>;
+    let _ = daswin::dpi::LogicalPosition::<f64>::cast::<u32>;
+    named::<daswin::dpi::LogicalSize<f64>>();
+    let _ = daswin::dpi::LogicalSize::<f64>::new;
+    let _ = daswin::dpi::LogicalSize::<f64>::to_physical::<u32>;
+    let _ = daswin::dpi::LogicalSize::<f64>::cast::<u32>;
+    named::<daswin::dpi::PhysicalPosition<f64>>();
+    let _ = daswin::dpi::PhysicalPosition::<f64>::new;
+    let _ = daswin::dpi::PhysicalPosition::<f64>::to_logical::<f64>;
+    let _ = daswin::dpi::PhysicalPosition::<f64>::cast::<u32>;
+    named::<daswin::dpi::PhysicalSize<f64>>();
+    let _ = daswin::dpi::PhysicalSize::<f64>::new;
+    let _ = daswin::dpi::PhysicalSize::<f64>::to_logical::<f64>;
+    let _ = daswin::dpi::PhysicalSize::<f64>::cast::<u32>;
+    named::<daswin::dpi::Size>();
+    let _ = daswin::dpi::Size::to_physical;
+    let _ = daswin::dpi::Size::to_logical;
+    let _ = <f64 as daswin::dpi::Pixel>::from_f64;
+    let _ = <u32 as daswin::dpi::Pixel>::cast::<f64>;
+    let _ = daswin::dpi::DEFAULT_MIN_INNER_SIZE;
+    let _ = daswin::dpi::DEFAULT_SIZE_FRACTION;
+}
+
+#[test]
+fn event() {
+    named::<daswin::event::ChannelId>();
+    let _ = daswin::event::ChannelId::into_raw;
+    let _ = daswin::event::ChannelId::from_raw;
+    named::<daswin::event::DeviceId>();
+    let _ = daswin::event::DeviceId::into_raw;
+    let _ = daswin::event::DeviceId::from_raw;
+    named::<daswin::event::HotkeyId>();
+    let _ = daswin::event::HotkeyId::into_raw;
+    let _ = daswin::event::HotkeyId::from_raw;
+    named::<daswin::event::ReadbackId>();
+    let _ = daswin::event::ReadbackId::into_raw;
+    let _ = daswin::event::ReadbackId::from_raw;
+    named::<daswin::event::WindowId>();
+    let _ = daswin::event::WindowId::xid;
+    let _ = daswin::event::WindowId::into_raw;
+    let _ = daswin::event::WindowId::from_raw;
+    named::<daswin::event::DeviceEvent>();
+    named::<daswin::event::Edge>();
+    named::<daswin::event::Event>();
+    named::<daswin::event::ScrollDelta>();
+    let _ = daswin::event::ScrollDelta::PIXELS_PER_LINE;
+    let _ = daswin::event::ScrollDelta::to_pixels;
+    named::<daswin::event::StateChange>();
+    named::<daswin::event::TabletTool>();
+}
+
+#[test]
+fn event_loop() {
+    named::<daswin::event_loop::EventFilter>();
+    let _ = daswin::event_loop::EventFilter::KEYBOARD;
+    let _ = daswin::event_loop::EventFilter::POINTER_MOTION;
+    let _ = daswin::event_loop::EventFilter::POINTER_BUTTON;
+    let _ = daswin::event_loop::EventFilter::TOUCH;
+    let _ = daswin::event_loop::EventFilter::FOCUS;
+    let _ = daswin::event_loop::EventFilter::WINDOW_STATE;
+    let _ = daswin::event_loop::EventFilter::empty;
+    let _ = daswin::event_loop::EventFilter::all;
+    let _ = daswin::event_loop::EventFilter::bits;
+    let _ = daswin::event_loop::EventFilter::from_bits;
+    let _ = daswin::event_loop::EventFilter::from_bits_truncate;
+    let _ = daswin::event_loop::EventFilter::from_bits_unchecked;
+    let _ = daswin::event_loop::EventFilter::is_empty;
+    let _ = daswin::event_loop::EventFilter::is_all;
+    let _ = daswin::event_loop::EventFilter::intersects;
+    let _ = daswin::event_loop::EventFilter::contains;
+    let _ = daswin::event_loop::EventFilter::insert;
+    let _ = daswin::event_loop::EventFilter::remove;
+    let _ = daswin::event_loop::EventFilter::toggle;
+    let _ = daswin::event_loop::EventFilter::set;
+    let _ = daswin::event_loop::EventFilter::intersection;
+    let _ = daswin::event_loop::EventFilter::union;
+    let _ = daswin::event_loop::EventFilter::difference;
+    let _ = daswin::event_loop::EventFilter::symmetric_difference;
+    let _ = daswin::event_loop::EventFilter::complement;
+    named::<daswin::event_loop::EventLoop>();
+    let _ = daswin::event_loop::EventLoop::new;
+    let _ = daswin::event_loop::EventLoop::new_threaded;
+    let _ = daswin::event_loop::EventLoop::from_raw_display;
+    let _ = daswin::event_loop::EventLoop::backend;
+    let _ = daswin::event_loop::EventLoop::display;
+    let _ = daswin::event_loop::EventLoop::available_monitors;
+    let _ = daswin::event_loop::EventLoop::devices;
+    let _ = daswin::event_loop::EventLoop::device;
+    let _ = daswin::event_loop::EventLoop::core_pointer;
+    let _ = daswin::event_loop::EventLoop::master_pointers;
+    let _ = daswin::event_loop::EventLoop::last_input_time;
+    let _ = daswin::event_loop::EventLoop::calibrate_clock;
+    let _ = daswin::event_loop::EventLoop::monitor_from_raw;
+    let _ = daswin::event_loop::EventLoop::primary_monitor;
+    let _ = daswin::event_loop::EventLoop::window_manager;
+    let _ = daswin::event_loop::EventLoop::global_cursor_position;
+    let _ = daswin::event_loop::EventLoop::pointer_state;
+    let _ = daswin::event_loop::EventLoop::set_event_filter;
+    let _ = daswin::event_loop::EventLoop::event_filter;
+    let _ = daswin::event_loop::EventLoop::set_motion_coalescing;
+    let _ = daswin::event_loop::EventLoop::set_modal_input_blocking;
+    let _ = daswin::event_loop::EventLoop::set_late_latch;
+    let _ = daswin::event_loop::EventLoop::late_latch;
+    let _ = daswin::event_loop::EventLoop::set_snapping;
+    let _ = daswin::event_loop::EventLoop::snapping;
+    let _ = daswin::event_loop::EventLoop::set_queue_policy;
+    let _ = daswin::event_loop::EventLoop::set_key_repeat;
+    let _ = daswin::event_loop::EventLoop::key_repeat;
+    let _ = daswin::event_loop::EventLoop::listen_device_events;
+    let _ = daswin::event_loop::EventLoop::catch_exit_signals;
+    let _ = daswin::event_loop::EventLoop::set_frame_deadline;
+    let _ = daswin::event_loop::EventLoop::set_resize_throttle;
+    let _ = daswin::event_loop::EventLoop::set_connection_timeout;
+    let _ = daswin::event_loop::EventLoop::reconnect;
+    let _ = daswin::event_loop::EventLoop::register_global_hotkey;
+    let _ = daswin::event_loop::EventLoop::unregister_global_hotkey;
+    let _ = daswin::event_loop::EventLoop::create_channel::<()>;
+    let _ = daswin::event_loop::EventLoop::wait_events;
+    let _ = daswin::event_loop::EventLoop::drain_channel::<()>;
+    let _ = daswin::event_loop::EventLoop::session_guard;
+    let _ = daswin::event_loop::EventLoop::take_x_errors;
+    let _ = daswin::event_loop::EventLoop::gpu_error;
+    let _ = daswin::event_loop::EventLoop::text;
+    let _ = daswin::event_loop::EventLoop::key_label;
+    let _ = daswin::event_loop::EventLoop::keysym_to_keycode;
+    let _ = daswin::event_loop::EventLoop::keycode_to_keysym;
+    let _ = daswin::event_loop::EventLoop::window_at;
+    let _ = daswin::event_loop::EventLoop::input_snapshot;
+    let _ = daswin::event_loop::EventLoop::input_handle;
+    let _ = daswin::event_loop::EventLoop::metrics;
+    let _ = daswin::event_loop::EventLoop::metrics_handle;
+    let _ = daswin::event_loop::EventLoop::print_metrics_on_signal;
+    let _ = daswin::event_loop::EventLoop::recent_events;
+    let _ = daswin::event_loop::EventLoop::set_recent_events_include_text;
+    let _ = daswin::event_loop::EventLoop::set_session_client_id;
+    let _ = daswin::event_loop::EventLoop::poll_events;
+    let _ = daswin::event_loop::EventLoop::poll_late_input;
+    let _ = daswin::event_loop::EventLoop::pump_events;
+    let _ = daswin::event_loop::EventLoop::set_exit_on_last_window_closed;
+    let _ = daswin::event_loop::EventLoop::exit;
+    let _ = daswin::event_loop::EventLoop::run::<fn(&mut daswin::EventLoop, daswin::Event)>;
+    let _ = daswin::event_loop::EventLoop::set_panic_policy;
+    let _ = daswin::event_loop::EventLoop::panic_policy;
+    named::<daswin::event_loop::EventLoopBuilder>();
+    let _ = daswin::event_loop::EventLoopBuilder::new;
+    let _ = daswin::event_loop::EventLoopBuilder::with_backend;
+    let _ = daswin::event_loop::EventLoopBuilder::with_connection_thread;
+    let _ = daswin::event_loop::EventLoopBuilder::build;
+    named::<daswin::event_loop::KeyRepeat>();
+    named::<daswin::event_loop::PointerState>();
+    named::<daswin::event_loop::DeviceEvents>();
+    named::<daswin::event_loop::PanicPolicy>();
+    named::<daswin::event_loop::PumpStatus>();
+    named::<daswin::event_loop::QueuePolicy>();
+}
+
+#[cfg(feature = "dbus")]
+#[test]
+fn event_loop_dbus() {
+    let _ = daswin::event_loop::EventLoop::listen_sleep_events;
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn event_loop_async() {
+    let _ = daswin::event_loop::EventLoop::create_async_channel::<()>;
+    let _ = daswin::event_loop::EventLoop::event_stream;
+}
+
+#[cfg(feature = "scripting")]
+#[test]
+fn event_loop_scripting() {
+    let _ = daswin::event_loop::EventLoop::script_error;
+}
+
+#[cfg(feature = "capi")]
+#[test]
+fn ffi() {
+    named::<daswin::ffi::DaswinCanvasViewportChanged>();
+    named::<daswin::ffi::DaswinCloseRequested>();
+    named::<daswin::ffi::DaswinCursorMoved>();
+    named::<daswin::ffi::DaswinDestroyed>();
+    named::<daswin::ffi::DaswinDeviceEvent>();
+    named::<daswin::ffi::DaswinEmbeddedChildDestroyed>();
+    named::<daswin::ffi::DaswinEvent>();
+    named::<daswin::ffi::DaswinFocused>();
+    named::<daswin::ffi::DaswinFrameDeadlineMissed>();
+    named::<daswin::ffi::DaswinFramePresented>();
+    named::<daswin::ffi::DaswinKeyboardInput>();
+    named::<daswin::ffi::DaswinModalBlocked>();
+    named::<daswin::ffi::DaswinMonitorChanged>();
+    named::<daswin::ffi::DaswinMouseInput>();
+    named::<daswin::ffi::DaswinMouseWheel>();
+    named::<daswin::ffi::DaswinRedrawRequested>();
+    named::<daswin::ffi::DaswinResized>();
+    named::<daswin::ffi::DaswinSnapped>();
+    named::<daswin::ffi::DaswinStateChanged>();
+    named::<daswin::ffi::DaswinTablet>();
+    named::<daswin::ffi::DaswinText>();
+    named::<daswin::ffi::DaswinTrayIconClicked>();
+    named::<daswin::ffi::DaswinWindow>();
+    named::<daswin::ffi::DaswinWindowCreated>();
+    named::<daswin::ffi::DaswinEventTag>();
+    named::<daswin::ffi::DaswinEventData>();
+    let _ = daswin::ffi::daswin_window_new;
+    let _ = daswin::ffi::daswin_window_show;
+    let _ = daswin::ffi::daswin_window_raw_xlib;
+    let _ = daswin::ffi::daswin_window_destroy;
+    let _ = daswin::ffi::daswin_poll_event;
+    let _ = daswin::ffi::daswin_wait_events;
+    let _ = daswin::ffi::daswin_event_text;
+    let _ = daswin::ffi::DASWIN_OK;
+    let _ = daswin::ffi::DASWIN_NULL_ARGUMENT;
+    let _ = daswin::ffi::DASWIN_PANIC;
+    let _ = daswin::ffi::DASWIN_NO_STRING;
+}
+
+#[test]
+fn monitor() {
+    named::<daswin::monitor::MonitorHandle>();
+    let _ = daswin::monitor::MonitorHandle::into_raw;
+    let _ = daswin::monitor::MonitorHandle::name;
+    let _ = daswin::monitor::MonitorHandle::friendly_name;
+    let _ = daswin::monitor::MonitorHandle::physical_size_mm;
+    let _ = daswin::monitor::MonitorHandle::dpi;
+    let _ = daswin::monitor::MonitorHandle::scale_factor;
+    let _ = daswin::monitor::MonitorHandle::position;
+    let _ = daswin::monitor::MonitorHandle::size;
+    let _ = daswin::monitor::MonitorHandle::refresh_rate_millihertz;
+    let _ = daswin::monitor::MonitorHandle::is_primary;
+    let _ = daswin::monitor::MonitorHandle::work_area;
+    let _ = daswin::monitor::MonitorHandle::resolve_size;
+    let _ = daswin::monitor::MonitorHandle::video_modes;
+    named::<daswin::monitor::VideoMode>();
+    let _ = daswin::monitor::VideoMode::size;
+    let _ = daswin::monitor::VideoMode::refresh_rate_millihertz;
+    let _ = daswin::monitor::default_inner_size;
+}
+
+#[test]
+fn record() {
+    named::<daswin::record::EventPlayer<std::fs::File>>();
+    let _ = daswin::record::EventPlayer::<std::fs::File>::new;
+    let _ = daswin::record::EventPlayer::<std::fs::File>::recorded_sizes;
+    let _ = daswin::record::EventPlayer::<std::fs::File>::attach;
+    let _ = daswin::record::EventPlayer::<std::fs::File>::is_finished;
+    let _ = daswin::record::EventPlayer::<std::fs::File>::poll_events;
+    let _ = daswin::record::EventPlayer::<std::fs::File>::text;
+    let _ = daswin::record::EventPlayer::<std::fs::File>::gpu_error;
+    let _ = daswin::record::EventPlayer::<std::fs::File>::script_error;
+    named::<daswin::record::EventRecorder<std::fs::File>>();
+    let _ = daswin::record::EventRecorder::<std::fs::File>::new;
+    let _ = daswin::record::EventRecorder::<std::fs::File>::record;
+    let _ = daswin::record::EventRecorder::<std::fs::File>::finish;
+    named::<daswin::record::Playback>();
+}
+
+#[test]
+fn render() {
+    named::<daswin::render::CanvasViewport>();
+    let _ = daswin::render::CanvasViewport::new;
+    let _ = daswin::render::CanvasViewport::canvas_size;
+    let _ = daswin::render::CanvasViewport::policy;
+    let _ = daswin::render::CanvasViewport::set_policy;
+    let _ = daswin::render::CanvasViewport::target_size;
+    let _ = daswin::render::CanvasViewport::resize;
+    let _ = daswin::render::CanvasViewport::viewport;
+    let _ = daswin::render::CanvasViewport::scissor;
+    let _ = daswin::render::CanvasViewport::canvas_rect;
+    let _ = daswin::render::CanvasViewport::visible_canvas;
+    let _ = daswin::render::CanvasViewport::scale;
+    let _ = daswin::render::CanvasViewport::surface_to_canvas;
+    let _ = daswin::render::CanvasViewport::canvas_to_surface;
+    named::<daswin::render::CursorImage>();
+    let _ = daswin::render::CursorImage::new;
+    let _ = daswin::render::CursorImage::size;
+    let _ = daswin::render::CursorImage::hotspot;
+    let _ = daswin::render::CursorImage::rgba;
+    let _ = daswin::render::CursorImage::origin;
+    let _ = daswin::render::CursorImage::draw_onto;
+    named::<daswin::render::PresentParams>();
+    named::<daswin::render::RenderSurface<daswin::render::software::SoftwareSurface>>();
+    let _ = daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::new;
+    let _ = daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::window;
+    let _ = daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::window_mut;
+    let _ = daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::backend;
+    let _ = daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::backend_mut;
+    let _ = daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::size;
+    let _ = daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::params;
+    let _ = daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::set_params;
+    let _ = daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::set_canvas;
+    let _ = daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::set_aspect_policy;
+    let _ = daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::canvas;
+    let _ = daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::surface_to_canvas;
+    let _ = daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::stats;
+    let _ =
+        daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::handle_event;
+    let _ = daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::acquire;
+    let _ = daswin::render::RenderSurface::<daswin::render::software::SoftwareSurface>::present;
+    named::<daswin::render::SurfaceStats>();
+    named::<daswin::render::AcquireError>();
+    named::<daswin::render::AspectPolicy>();
+    render_backend::<daswin::render::software::SoftwareSurface>();
+}
+
+#[cfg(feature = "wgpu")]
+#[test]
+fn render_gpu() {
+    named::<daswin::render::GpuContext>();
+    let _ = daswin::render::GpuContext::with_window;
+    let _ = daswin::render::GpuContext::try_with_window;
+    let _ = daswin::render::GpuContext::with_options;
+    let _: fn(&_, _, std::ops::RangeFull, _, Readback) -> _ =
+        daswin::render::GpuContext::map_async_callback;
+    let _ = daswin::render::GpuContext::cancel_readback;
+    let _ = daswin::render::GpuContext::is_readback_pending;
+    named::<daswin::render::GpuInitError>();
+    named::<daswin::render::GpuOptions>();
+    named::<daswin::render::SurfaceCapabilities>();
+    let _ = daswin::render::SurfaceCapabilities::query;
+    named::<daswin::render::SurfaceConfigScheduler>();
+    named::<daswin::render::SurfaceConfigScheduler<()>>();
+    named::<daswin::render::ReconfigureCallback>();
+    named::<daswin::render::ReconfigureCallback<()>>();
+    let _ = daswin::render::SurfaceConfigScheduler::<()>::new;
+    let _ = daswin::render::SurfaceConfigScheduler::<()>::on_reconfigure::<
+        fn(&(), &wgpu::SurfaceConfiguration),
+    >;
+    let _ = daswin::render::SurfaceConfigScheduler::<()>::invalidate;
+    let _ = daswin::render::SurfaceConfigScheduler::<()>::is_pending;
+    let _ = daswin::render::SurfaceConfigScheduler::<()>::applied;
+    let _ = daswin::render::SurfaceConfigScheduler::<daswin::render::GpuContext>::apply::<
+        wgpu::Surface,
+    >;
+    named::<daswin::render::SurfaceOptions>();
+    let _ = daswin::render::SurfaceOptions::choose_format;
+    let _ = daswin::render::SurfaceOptions::negotiate;
+    named::<daswin::render::SurfaceState>();
+    let _ = daswin::render::SurfaceState::new;
+    let _ = daswin::render::SurfaceState::with_options;
+    let _ = daswin::render::SurfaceState::capabilities;
+    let _ = daswin::render::SurfaceState::options;
+    let _ = daswin::render::SurfaceState::set_options;
+    let _ = daswin::render::SurfaceState::on_reconfigure::<
+        fn(&daswin::render::GpuContext, &wgpu::SurfaceConfiguration),
+    >;
+    let _ = daswin::render::SurfaceState::flush;
+    let _ = daswin::render::SurfaceState::uses_intermediate;
+    let _ = daswin::render::SurfaceState::texture;
+    let _ = daswin::render::SurfaceState::resolve;
+    let _ = daswin::render::SurfaceState::format;
+    let _ = daswin::render::SurfaceState::present_mode;
+    let _ = daswin::render::SurfaceState::set_present_mode;
+    let _ = daswin::render::SurfaceState::current_size;
+    let _ = daswin::render::SurfaceState::resize;
+    let _ = daswin::render::SurfaceState::acquire;
+    let _ = daswin::render::SurfaceState::reconfigure;
+    let _ = daswin::render::SurfaceState::renegotiate_format;
+    named::<daswin::render::WgpuBackend>();
+    named::<daswin::render::WindowSurface>();
+    let _ = daswin::render::WindowSurface::new;
+    let _ = daswin::render::WindowSurface::set_options;
+    let _ = daswin::render::WindowSurface::window;
+    let _ = daswin::render::WindowSurface::window_mut;
+    let _ = daswin::render::WindowSurface::surface;
+    let _ = daswin::render::WindowSurface::surface_mut;
+    let _ = daswin::render::WindowSurface::acquire;
+    let _ = daswin::render::WindowSurface::present;
+    let _ = daswin::render::WindowSurface::recreate;
+    let _ = daswin::render::WindowSurface::handle_event;
+    let _ = daswin::render::WindowSurface::into_render_surface;
+    let _ = daswin::render::WindowSurface::destroy;
+    named::<daswin::render::AdapterFailure>();
+    named::<daswin::render::SurfaceConfigError>();
+    configure_surface::<wgpu::Surface>();
+}
+
+#[cfg(feature = "wgpu")]
+#[test]
+fn render_gallery() {
+    named::<daswin::render::gallery::Gallery>();
+    let _: fn(String, _) -> _ = daswin::render::gallery::Gallery::scan;
+    let _ = daswin::render::gallery::Gallery::len;
+    let _ = daswin::render::gallery::Gallery::is_empty;
+    let _ = daswin::render::gallery::Gallery::names;
+    let _ = daswin::render::gallery::Gallery::index;
+    let _ = daswin::render::gallery::Gallery::name;
+    let _ = daswin::render::gallery::Gallery::errors;
+    let _ = daswin::render::gallery::Gallery::compiled;
+    let _ = daswin::render::gallery::Gallery::set_format;
+    let _ = daswin::render::gallery::Gallery::next;
+    let _ = daswin::render::gallery::Gallery::previous;
+    let _ = daswin::render::gallery::Gallery::current;
+    let _ = daswin::render::gallery::CACHED_EFFECTS;
+}
+
+#[cfg(feature = "wgpu")]
+#[test]
+fn render_helpers() {
+    named::<daswin::render::helpers::Blit>();
+    let _ = daswin::render::helpers::Blit::new;
+    let _ = daswin::render::helpers::Blit::with_filter;
+    let _ = daswin::render::helpers::Blit::format;
+    let _ = daswin::render::helpers::Blit::rebuild;
+    let _ = daswin::render::helpers::Blit::bind_group;
+    let _ = daswin::render::helpers::Blit::draw;
+    let _ = daswin::render::helpers::Blit::blit;
+    named::<daswin::render::helpers::ComputeStage>();
+    let _ = daswin::render::helpers::ComputeStage::new;
+    let _ = daswin::render::helpers::ComputeStage::format;
+    let _ = daswin::render::helpers::ComputeStage::size;
+    let _ = daswin::render::helpers::ComputeStage::scene;
+    let _ = daswin::render::helpers::ComputeStage::bind_group_layout;
+    let _ = daswin::render::helpers::ComputeStage::declarations;
+    let _ = daswin::render::helpers::ComputeStage::resize;
+    let _ = daswin::render::helpers::ComputeStage::rebuild;
+    let _ = daswin::render::helpers::ComputeStage::dispatch;
+    let _ = daswin::render::helpers::ComputeStage::resolve;
+    named::<daswin::render::helpers::CursorPass>();
+    let _ = daswin::render::helpers::CursorPass::new;
+    let _ = daswin::render::helpers::CursorPass::format;
+    let _ = daswin::render::helpers::CursorPass::rebuild;
+    let _ = daswin::render::helpers::CursorPass::prepare;
+    let _ = daswin::render::helpers::CursorPass::draw;
+    named::<daswin::render::helpers::DamageTarget>();
+    let _ = daswin::render::helpers::DamageTarget::new;
+    let _ = daswin::render::helpers::DamageTarget::format;
+    let _ = daswin::render::helpers::DamageTarget::size;
+    let _ = daswin::render::helpers::DamageTarget::texture;
+    let _ = daswin::render::helpers::DamageTarget::supports_damage;
+    let _ = daswin::render::helpers::DamageTarget::set_damage;
+    let _ = daswin::render::helpers::DamageTarget::damage;
+    let _ = daswin::render::helpers::DamageTarget::pass;
+    let _ = daswin::render::helpers::DamageTarget::resize;
+    let _ = daswin::render::helpers::DamageTarget::rebuild;
+    let _ = daswin::render::helpers::DamageTarget::resolve;
+    named::<daswin::render::helpers::FullscreenShader>();
+    let _ = daswin::render::helpers::FullscreenShader::new;
+    let _ = daswin::render::helpers::FullscreenShader::try_new;
+    let _ = daswin::render::helpers::FullscreenShader::try_with_bind_group_layouts;
+    let _ = daswin::render::helpers::FullscreenShader::with_bind_group_layouts;
+    let _ = daswin::render::helpers::FullscreenShader::format;
+    let _ = daswin::render::helpers::FullscreenShader::rebuild;
+    let _ = daswin::render::helpers::FullscreenShader::set_globals;
+    let _ = daswin::render::helpers::FullscreenShader::set_cursor;
+    let _ = daswin::render::helpers::FullscreenShader::draw;
+    let _: fn(&_, fn()) -> _ = daswin::render::helpers::error_scope;
+    let _ = daswin::render::helpers::workgroups;
+}
+
+#[cfg(feature = "wgpu")]
+#[test]
+fn render_pacing() {
+    named::<daswin::render::pacing::FramePacer>();
+    let _ = daswin::render::pacing::FramePacer::new;
+    let _ = daswin::render::pacing::FramePacer::set_max_in_flight;
+    let _ = daswin::render::pacing::FramePacer::set_blocking;
+    let _ = daswin::render::pacing::FramePacer::frames_in_flight;
+    let _ = daswin::render::pacing::FramePacer::latency;
+    let _ = daswin::render::pacing::FramePacer::begin_frame;
+    let _ = daswin::render::pacing::FramePacer::end_frame;
+    named::<daswin::render::pacing::WouldBlock>();
+}
+
+#[cfg(feature = "wgpu")]
+#[test]
+fn render_pixel() {
+    named::<daswin::render::pixel::PixelPerfectPresenter>();
+    let _ = daswin::render::pixel::PixelPerfectPresenter::new;
+    let _ = daswin::render::pixel::PixelPerfectPresenter::canvas;
+    let _ = daswin::render::pixel::PixelPerfectPresenter::canvas_view;
+    let _ = daswin::render::pixel::PixelPerfectPresenter::canvas_size;
+    let _ = daswin::render::pixel::PixelPerfectPresenter::scale;
+    let _ = daswin::render::pixel::PixelPerfectPresenter::policy;
+    let _ = daswin::render::pixel::PixelPerfectPresenter::set_policy;
+    let _ = daswin::render::pixel::PixelPerfectPresenter::placement;
+    let _ = daswin::render::pixel::PixelPerfectPresenter::set_placement;
+    let _ = daswin::render::pixel::PixelPerfectPresenter::resize;
+    let _ = daswin::render::pixel::PixelPerfectPresenter::rebuild;
+    let _ = daswin::render::pixel::PixelPerfectPresenter::window_to_canvas_coords;
+    let _ = daswin::render::pixel::PixelPerfectPresenter::present;
+}
+
+#[test]
+fn render_software() {
+    named::<daswin::render::software::SoftwareFrame>();
+    let _ = daswin::render::software::SoftwareFrame::size;
+    let _ = daswin::render::software::SoftwareFrame::rgba_mut;
+    named::<daswin::render::software::SoftwareSurface>();
+    let _ = daswin::render::software::SoftwareSurface::new;
+    let _ = daswin::render::software::SoftwareSurface::resize;
+    let _ = daswin::render::software::SoftwareSurface::size;
+    let _ = daswin::render::software::SoftwareSurface::present;
+}
+
+#[cfg(feature = "wgpu")]
+#[test]
+fn render_text() {
+    named::<daswin::render::text::DebugText>();
+    let _ = daswin::render::text::DebugText::new;
+    let _ = daswin::render::text::DebugText::format;
+    let _ = daswin::render::text::DebugText::rebuild;
+    let _ = daswin::render::text::DebugText::queue_text;
+    let _ = daswin::render::text::DebugText::prepare;
+    let _ = daswin::render::text::DebugText::draw;
+    let _ = daswin::render::text::GLYPH_SIZE;
+}
+
+#[cfg(feature = "wgpu")]
+#[test]
+fn render_texture() {
+    named::<daswin::render::texture::Texture>();
+    let _ = daswin::render::texture::Texture::bind_group_layout;
+    let _ = daswin::render::texture::Texture::bind_group;
+    named::<daswin::render::texture::TextureBuilder>();
+    let _ = daswin::render::texture::TextureBuilder::from_rgba8;
+    let _ = daswin::render::texture::TextureBuilder::with_srgb;
+    let _ = daswin::render::texture::TextureBuilder::with_mipmaps;
+    let _ = daswin::render::texture::TextureBuilder::with_label;
+    let _ = daswin::render::texture::TextureBuilder::try_build;
+    let _ = daswin::render::texture::TextureBuilder::build;
+}
+
+#[cfg(feature = "scripting")]
+#[test]
+fn script() {
+    named::<daswin::script::ScriptError>();
+    named::<daswin::script::ScriptHost>();
+    let _ = daswin::script::ScriptHost::new;
+    let _: fn(&mut _, String) -> _ = daswin::script::ScriptHost::load_script;
+    let _ = daswin::script::ScriptHost::eval;
+    let _ = daswin::script::ScriptHost::set_instruction_budget;
+    let _ = daswin::script::ScriptHost::clear_color;
+    let _ = daswin::script::ScriptHost::uniform;
+    let _ = daswin::script::DEFAULT_INSTRUCTION_BUDGET;
+}
+
+#[test]
+fn window() {
+    named::<daswin::window::Rect>();
+    let _ = daswin::window::Rect::contains;
+    let _ = daswin::window::Rect::union;
+    named::<daswin::window::SoftwareCursor>();
+    named::<daswin::window::WindowBuilder>();
+    let _ = daswin::window::WindowBuilder::new;
+    let _ = daswin::window::WindowBuilder::with_title;
+    let _: fn(_, daswin::Size) -> _ = daswin::window::WindowBuilder::with_inner_size;
+    let _: fn(_, daswin::Size) -> _ = daswin::window::WindowBuilder::with_min_inner_size;
+    let _ = daswin::window::WindowBuilder::with_position;
+    let _ = daswin::window::WindowBuilder::with_transient_for;
+    let _ = daswin::window::WindowBuilder::with_owner;
+    let _ = daswin::window::WindowBuilder::with_focusable;
+    let _ = daswin::window::WindowBuilder::with_decorations;
+    let _ = daswin::window::WindowBuilder::with_restored_state;
+    let _ = daswin::window::WindowBuilder::with_embed_parent;
+    let _ = daswin::window::WindowBuilder::build;
+    named::<daswin::window::X11Window>();
+    let _: fn(&_, &_, daswin::Size) -> _ = daswin::window::X11Window::new;
+    let _ = daswin::window::X11Window::from_raw;
+    let _: fn(&mut _, fn(&mut daswin::X11Window)) = daswin::window::X11Window::on_first_present;
+    let _ = daswin::window::X11Window::notify_presented;
+    let _ = daswin::window::X11Window::enable_present_feedback;
+    let _ = daswin::window::X11Window::id;
+    let _ = daswin::window::X11Window::proxy;
+    let _ = daswin::window::X11Window::request_redraw;
+    let _ = daswin::window::X11Window::show;
+    let _ = daswin::window::X11Window::hide;
+    let _ = daswin::window::X11Window::cursor_position;
+    let _ = daswin::window::X11Window::cursor_position_of;
+    let _ = daswin::window::X11Window::current_monitor;
+    let _ = daswin::window::X11Window::center_on;
+    let _ = daswin::window::X11Window::inner_size_physical;
+    let _ = daswin::window::X11Window::inner_size;
+    let _ = daswin::window::X11Window::scale_factor;
+    let _: fn(&mut _, daswin::Size) = daswin::window::X11Window::set_inner_size;
+    let _ = daswin::window::X11Window::set_title;
+    let _ = daswin::window::X11Window::title;
+    let _ = daswin::window::X11Window::set_window_icon;
+    let _ = daswin::window::X11Window::set_cursor_icon;
+    let _ = daswin::window::X11Window::uses_software_cursor;
+    let _ = daswin::window::X11Window::software_cursor;
+    let _ = daswin::window::X11Window::set_min_inner_size;
+    let _ = daswin::window::X11Window::set_max_inner_size;
+    let _ = daswin::window::X11Window::set_aspect_ratio;
+    let _ = daswin::window::X11Window::set_size_increments;
+    let _ = daswin::window::X11Window::set_gravity;
+    let _ = daswin::window::X11Window::raise;
+    let _ = daswin::window::X11Window::lower;
+    let _ = daswin::window::X11Window::restack_above;
+    let _ = daswin::window::X11Window::restack_below;
+    let _ = daswin::window::X11Window::set_input_region;
+    let _ = daswin::window::X11Window::set_shape_region;
+    let _ = daswin::window::X11Window::focus;
+    let _ = daswin::window::X11Window::set_modal;
+    let _ = daswin::window::X11Window::is_modal;
+    let _ = daswin::window::X11Window::embed_foreign;
+    let _ = daswin::window::X11Window::set_foreign_rect;
+    let _ = daswin::window::X11Window::remove_foreign;
+    let _ = daswin::window::X11Window::foreign_windows;
+    let _ = daswin::window::X11Window::focus_foreign;
+    let _ = daswin::window::X11Window::fullscreen;
+    let _ = daswin::window::X11Window::is_maximized;
+    let _ = daswin::window::X11Window::save_state;
+    let _ = daswin::window::X11Window::is_fullscreen;
+    let _ = daswin::window::X11Window::set_exclusive_fullscreen_grab;
+    let _ = daswin::window::X11Window::set_keyboard_grab;
+    let _ = daswin::window::X11Window::set_pointer_grab;
+    let _ = daswin::window::X11Window::confine_cursor_with_barriers;
+    let _ = daswin::window::X11Window::has_keyboard_grab;
+    let _ = daswin::window::X11Window::has_pointer_grab;
+    let _ = daswin::window::X11Window::set_grab_escape;
+    let _ = daswin::window::X11Window::set_ime_allowed;
+    let _ = daswin::window::X11Window::set_ime_cursor_area;
+    let _ = daswin::window::X11Window::set_fullscreen;
+    let _ = daswin::window::X11Window::set_content_protected;
+    let _ = daswin::window::X11Window::is_content_protected;
+    let _ = daswin::window::X11Window::set_decorations;
+    let _ = daswin::window::X11Window::is_decorated;
+    let _ = daswin::window::X11Window::begin_manual_drag;
+    let _ = daswin::window::X11Window::drag_window;
+    let _ = daswin::window::X11Window::contains_global_point;
+    let _ = daswin::window::X11Window::window_to_root;
+    let _ = daswin::window::X11Window::root_to_window;
+    let _ = daswin::window::X11Window::window_to_monitor;
+    let _ = daswin::window::X11Window::refresh_geometry;
+    let _ = daswin::window::X11Window::is_compositor_bypassed;
+    named::<daswin::window::CursorIcon>();
+    named::<daswin::window::Fullscreen>();
+    named::<daswin::window::Gravity>();
+    named::<daswin::window::Position>();
+    named::<daswin::window::Protection>();
+}
+
+#[test]
+fn wm() {
+    named::<daswin::wm::WmInfo>();
+    let _ = daswin::wm::WmInfo::new;
+    let _ = daswin::wm::WmInfo::supports;
+    named::<daswin::wm::Feature>();
+}
+
+#[test]
+fn reexports() {
+    named::<daswin::Backend>();
+    named::<daswin::BackendError>();
+    named::<daswin::DeviceInfo>();
+    named::<daswin::DeviceType>();
+    named::<daswin::LogicalPosition<f64>>();
+    named::<daswin::LogicalSize<f64>>();
+    named::<daswin::PhysicalPosition<i32>>();
+    named::<daswin::PhysicalSize<u32>>();
+    named::<daswin::Size>();
+    let _ = daswin::DEFAULT_MIN_INNER_SIZE;
+    let _ = daswin::DEFAULT_SIZE_FRACTION;
+    named::<daswin::ChannelId>();
+    named::<daswin::DeviceEvent>();
+    named::<daswin::DeviceId>();
+    named::<daswin::Edge>();
+    named::<daswin::Event>();
+    named::<daswin::HotkeyId>();
+    named::<daswin::ReadbackId>();
+    named::<daswin::ScrollDelta>();
+    named::<daswin::StateChange>();
+    named::<daswin::TabletTool>();
+    named::<daswin::WindowId>();
+    named::<daswin::DeviceEvents>();
+    named::<daswin::EventFilter>();
+    named::<daswin::EventLoop>();
+    named::<daswin::EventLoopBuilder>();
+    named::<daswin::KeyRepeat>();
+    named::<daswin::PanicPolicy>();
+    named::<daswin::PointerState>();
+    named::<daswin::PumpStatus>();
+    named::<daswin::QueuePolicy>();
+    let _ = daswin::default_inner_size;
+    named::<daswin::MonitorHandle>();
+    named::<daswin::VideoMode>();
+    named::<daswin::CursorIcon>();
+    named::<daswin::Fullscreen>();
+    named::<daswin::Gravity>();
+    named::<daswin::Position>();
+    named::<daswin::Protection>();
+    named::<daswin::Rect>();
+    named::<daswin::SoftwareCursor>();
+    named::<daswin::WindowBuilder>();
+    named::<daswin::X11Window>();
+    named::<daswin::WmInfo>();
+}
+
+#[cfg(feature = "scripting")]
+#[test]
+fn reexports_scripting() {
+    named::<daswin::ScriptError>();
+    named::<daswin::ScriptHost>();
+}
+
+#[test]
+fn prelude() {
+    use daswin::prelude::*;
+
+    named::<LogicalPosition<f64>>();
+    named::<LogicalSize<f64>>();
+    named::<PhysicalPosition<i32>>();
+    named::<PhysicalSize<u32>>();
+    named::<Error>();
+    named::<Event>();
+    named::<WindowId>();
+    named::<EventLoop>();
+    named::<EventLoopBuilder>();
+    named::<PumpStatus>();
+    named::<Icon>();
+    named::<CursorIcon>();
+    named::<Fullscreen>();
+    named::<Position>();
+    named::<WindowBuilder>();
+    named::<X11Window>();
+}
+
+#[cfg(feature = "wgpu")]
+#[test]
+fn prelude_gpu() {
+    use daswin::prelude::*;
+
+    named::<GpuContext>();
+    named::<WindowSurface>();
+}